@@ -0,0 +1,115 @@
+use std::path::PathBuf;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use over_simple_game_1::core::engine::io::DirectFilesystemSimpleIO;
+use over_simple_game_1::core::map::coord::{Coord, CoordOrientation};
+use over_simple_game_1::core::map::generator::SimpleAlternationMapGenerator;
+use over_simple_game_1::core::map::path::find_path;
+use over_simple_game_1::prelude::*;
+
+fn coord_linear(c: &mut Criterion) {
+	c.bench_function("coord_to_linear", |b| {
+		let coord = Coord::new_axial(117, 43);
+		b.iter(|| black_box(coord).to_linear())
+	});
+	c.bench_function("coord_from_linear", |b| {
+		let (x, y) = Coord::new_axial(117, 43).to_linear();
+		b.iter(|| Coord::from_linear(black_box(x), black_box(y)))
+	});
+	c.bench_function("coord_linear_round_trip_full_map", |b| {
+		b.iter(|| {
+			for r in 0..=255u8 {
+				for q in 0..=255u8 {
+					let (x, y) = Coord::new_axial(q, r).to_linear();
+					black_box(Coord::from_linear(x, y));
+				}
+			}
+		})
+	});
+}
+
+fn coord_iterators(c: &mut Criterion) {
+	c.bench_function("coord_orientation_ring_16", |b| {
+		b.iter(|| CoordOrientation::iter_neighbors_ring(black_box(16)).count())
+	});
+	c.bench_function("coord_orientation_neighbors_16", |b| {
+		b.iter(|| CoordOrientation::iter_neighbors(black_box(16)).count())
+	});
+	c.bench_function("coord_neighbors_16", |b| {
+		let center = Coord::new_axial(128, 128);
+		b.iter(|| black_box(center).iter_neighbors(16).count())
+	});
+}
+
+fn coord_idx(c: &mut Criterion) {
+	c.bench_function("coord_idx_wrapping", |b| {
+		b.iter(|| {
+			for r in 0..=255u8 {
+				for q in 0..=255u8 {
+					black_box(Coord::new_axial(q, r).idx(199, 255, true));
+				}
+			}
+		})
+	});
+}
+
+fn bench_engine(width: u8, height: u8, wraps_x: bool) -> Engine<DirectFilesystemSimpleIO> {
+	let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+	path.push("resources");
+	let mut io = DirectFilesystemSimpleIO::with_path(path);
+	let mut engine = Engine::builder().build();
+	engine.setup(&mut io).expect("failed to setup engine");
+	let mut generator = SimpleAlternationMapGenerator::new(&mut engine, &["dirt", "grass", "sand"])
+		.expect("failed to create generator");
+	engine
+		.generate_map(&mut io, "bench", width, height, wraps_x, &mut generator)
+		.expect("failed to generate map");
+	engine
+}
+
+fn map_iteration(c: &mut Criterion) {
+	let engine = bench_engine(255, 255, true);
+	let map = engine.maps.get("bench").expect("bench map missing");
+	c.bench_function("tile_map_neighbors_around_center_16", |b| {
+		let center = Coord::new_axial(128, 128);
+		b.iter(|| map.iter_neighbors_around(black_box(center), 16).count())
+	});
+	c.bench_function("tile_map_neighbors_around_wrapping_edge_16", |b| {
+		let edge = Coord::new_axial(0, 128);
+		b.iter(|| map.iter_neighbors_around(black_box(edge), 16).count())
+	});
+}
+
+fn pathfinding(c: &mut Criterion) {
+	let engine = bench_engine(255, 255, true);
+	let map = engine.maps.get("bench").expect("bench map missing");
+	c.bench_function("find_path_across_wrapping_seam", |b| {
+		let (from, to) = (Coord::new_axial(240, 100), Coord::new_axial(20, 160));
+		b.iter(|| find_path(map, black_box(from), black_box(to), |_coord, _tile| Some(1)))
+	});
+	c.bench_function("find_path_through_walls", |b| {
+		let (from, to) = (Coord::new_axial(64, 10), Coord::new_axial(192, 10));
+		// Walls along the seam and down the middle, with one gap in each far from both ends
+		let wall = |coord: Coord| (coord.q() == 0 || coord.q() == 128) && coord.r() != 250;
+		b.iter(|| {
+			find_path(map, black_box(from), black_box(to), |coord, _tile| {
+				if wall(coord) {
+					None
+				} else {
+					Some(1)
+				}
+			})
+		})
+	});
+}
+
+criterion_group!(
+	benches,
+	coord_linear,
+	coord_iterators,
+	coord_idx,
+	map_iteration,
+	pathfinding
+);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use over_simple_game_1::core::structures::small_set::SmallSet;
+
+// Mirrors the tile entity access patterns: most tiles are empty or hold a single entity that
+// moves in and back out again, while drawing iterates every visible tile.
+const TILES: usize = 40 * 40;
+
+fn move_in_out(c: &mut Criterion) {
+	c.bench_function("hash_set_move_in_out", |b| {
+		let mut set = HashSet::new();
+		b.iter(|| {
+			set.insert(black_box(7u64));
+			set.remove(&black_box(7u64));
+		})
+	});
+	c.bench_function("small_set_move_in_out", |b| {
+		let mut set = SmallSet::<[u64; 2]>::new();
+		b.iter(|| {
+			set.insert(black_box(7u64));
+			set.remove(&black_box(7u64));
+		})
+	});
+}
+
+fn iterate_visible(c: &mut Criterion) {
+	c.bench_function("hash_set_iterate_visible", |b| {
+		let tiles: Vec<HashSet<u64>> = (0..TILES)
+			.map(|i| (0..(i % 8 == 0) as u64).collect())
+			.collect();
+		b.iter(|| tiles.iter().flat_map(|t| t.iter()).sum::<u64>())
+	});
+	c.bench_function("small_set_iterate_visible", |b| {
+		let tiles: Vec<SmallSet<[u64; 2]>> = (0..TILES)
+			.map(|i| {
+				let mut set = SmallSet::new();
+				if i % 8 == 0 {
+					set.insert(0);
+				}
+				set
+			})
+			.collect();
+		b.iter(|| tiles.iter().flat_map(|t| t.iter()).sum::<u64>())
+	});
+}
+
+criterion_group!(benches, move_in_out, iterate_visible);
+criterion_main!(benches);
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+
+/// How far a piece of sliced work has got.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkProgress {
+	Working {
+		done: usize,
+		total: usize,
+	},
+	Finished {
+		done: usize,
+	},
+	/// It ran out of frames and the rest was dropped.
+	Capped {
+		done: usize,
+		skipped: usize,
+	},
+}
+
+/// Work split into items and done a slice per frame, so something big like an AI turn doesn't
+/// freeze the game while it runs.
+///
+/// Each step works until the caller says the frame's budget is spent, and past `max_frames`
+/// steps the rest is dropped so one stuck turn can't hold the game up forever.
+#[derive(Clone, Debug)]
+pub struct SlicedWork<T> {
+	pending: VecDeque<T>,
+	total: usize,
+	done: usize,
+	frames: u32,
+	max_frames: u32,
+}
+
+impl<T> SlicedWork<T> {
+	pub fn new(items: impl IntoIterator<Item = T>, max_frames: u32) -> SlicedWork<T> {
+		let pending: VecDeque<T> = items.into_iter().collect();
+		SlicedWork {
+			total: pending.len(),
+			pending,
+			done: 0,
+			frames: 0,
+			max_frames: max_frames.max(1),
+		}
+	}
+
+	pub fn progress(&self) -> WorkProgress {
+		match (self.pending.len(), self.frames >= self.max_frames) {
+			(0, _) => WorkProgress::Finished { done: self.done },
+			(skipped, true) => WorkProgress::Capped {
+				done: self.done,
+				skipped,
+			},
+			_ => WorkProgress::Working {
+				done: self.done,
+				total: self.total,
+			},
+		}
+	}
+
+	/// Does items until `out_of_time` says to stop, checked after each so a step always gets at
+	/// least one done. Once this was the last frame allowed whatever is left is dropped.
+	///
+	/// An item whose work fails is not tried again.
+	pub fn step<E>(
+		&mut self,
+		mut out_of_time: impl FnMut() -> bool,
+		mut work: impl FnMut(T) -> Result<(), E>,
+	) -> Result<WorkProgress, E> {
+		if let WorkProgress::Working { .. } = self.progress() {
+			self.frames += 1;
+			while let Some(item) = self.pending.pop_front() {
+				self.done += 1;
+				work(item)?;
+				if out_of_time() {
+					break;
+				}
+			}
+		}
+		let progress = self.progress();
+		if let WorkProgress::Capped { .. } = progress {
+			self.pending.clear();
+		}
+		Ok(progress)
+	}
+}
+
+#[cfg(test)]
+mod budget_tests {
+	use super::*;
+
+	/// Out of time after every `per_frame` items.
+	fn every(per_frame: usize) -> impl FnMut() -> bool {
+		let mut count = 0;
+		move || {
+			count += 1;
+			count % per_frame == 0
+		}
+	}
+
+	#[test]
+	fn slices_across_frames() {
+		let mut work = SlicedWork::new(0..5, 10);
+		let mut seen = vec![];
+		let mut out_of_time = every(2);
+		let mut step = |work: &mut SlicedWork<i32>| {
+			work.step(&mut out_of_time, |item| -> Result<(), ()> {
+				seen.push(item);
+				Ok(())
+			})
+		};
+		assert_eq!(
+			step(&mut work),
+			Ok(WorkProgress::Working { done: 2, total: 5 })
+		);
+		assert_eq!(
+			step(&mut work),
+			Ok(WorkProgress::Working { done: 4, total: 5 })
+		);
+		assert_eq!(step(&mut work), Ok(WorkProgress::Finished { done: 5 }));
+		assert_eq!(step(&mut work), Ok(WorkProgress::Finished { done: 5 }));
+		assert_eq!(seen, [0, 1, 2, 3, 4]);
+	}
+
+	#[test]
+	fn drops_the_rest_past_the_cap() {
+		let mut work = SlicedWork::new(0..10, 2);
+		let ok = |_item| -> Result<(), ()> { Ok(()) };
+		let mut out_of_time = every(3);
+		assert_eq!(
+			work.step(&mut out_of_time, ok),
+			Ok(WorkProgress::Working { done: 3, total: 10 })
+		);
+		assert_eq!(
+			work.step(&mut out_of_time, ok),
+			Ok(WorkProgress::Capped {
+				done: 6,
+				skipped: 4
+			})
+		);
+		assert_eq!(work.progress(), WorkProgress::Finished { done: 6 });
+	}
+
+	#[test]
+	fn always_gets_something_done() {
+		let mut work = SlicedWork::new(vec!["a", "b"], 5);
+		let ok = |_item| -> Result<(), ()> { Ok(()) };
+		assert_eq!(
+			work.step(|| true, ok),
+			Ok(WorkProgress::Working { done: 1, total: 2 })
+		);
+		assert_eq!(work.step(|| true, |_item| Err("failed")), Err("failed"));
+		assert_eq!(work.progress(), WorkProgress::Finished { done: 2 });
+		let empty: SlicedWork<u8> = SlicedWork::new(vec![], 5);
+		assert_eq!(empty.progress(), WorkProgress::Finished { done: 0 });
+	}
+}
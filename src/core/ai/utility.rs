@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+use crate::core::fixed::Fixed;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum UtilityError {
+	#[error("action `{action}` considers unknown input `{input}`")]
+	UnknownInput { action: String, input: String },
+}
+
+fn serde_weight() -> Fixed {
+	Fixed::ONE
+}
+
+/// How a consideration turns its input into a score, both from 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Curve {
+	/// `slope * input + offset`.
+	Linear { slope: Fixed, offset: Fixed },
+	/// `1 - input`, for the less of something the better.
+	Inverse,
+	/// 1 once the input reaches `threshold`, 0 below it.
+	Step { threshold: Fixed },
+}
+
+impl Curve {
+	pub fn apply(self, input: Fixed) -> Fixed {
+		let score = match self {
+			Curve::Linear { slope, offset } => slope * input + offset,
+			Curve::Inverse => Fixed::ONE - input,
+			Curve::Step { threshold } if input >= threshold => Fixed::ONE,
+			Curve::Step { .. } => Fixed::ZERO,
+		};
+		score.max(Fixed::ZERO).min(Fixed::ONE)
+	}
+}
+
+/// One thing an action's score depends on.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consideration {
+	/// The name of the input, what they are depends on the game.
+	pub input: String,
+	pub curve: Curve,
+}
+
+/// Something to decide to do and what makes it worth doing.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtilityAction {
+	pub name: String,
+	/// Scales the whole score, to favor some actions over others.
+	#[serde(default = "serde_weight")]
+	pub weight: Fixed,
+	/// Their scores are multiplied, so any one of them at 0 rules the action out.
+	pub considerations: Vec<Consideration>,
+}
+
+/// What a decision is made from, the game's named inputs each from 0 to 1.
+pub trait UtilityInputs {
+	fn input(&self, name: &str) -> Option<Fixed>;
+}
+
+impl UtilityInputs for HashMap<String, Fixed> {
+	fn input(&self, name: &str) -> Option<Fixed> {
+		self.get(name).copied()
+	}
+}
+
+/// Picks what to do by scoring every action on its considerations, the utility AI way.
+///
+/// The actions are usually loaded from data so behaviors can be tuned and mixed without code
+/// changes, and scoring is fixed point so every machine decides the same.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UtilityAi {
+	actions: Vec<UtilityAction>,
+}
+
+impl UtilityAi {
+	pub fn new(actions: Vec<UtilityAction>) -> UtilityAi {
+		UtilityAi { actions }
+	}
+
+	pub fn actions(&self) -> &[UtilityAction] {
+		&self.actions
+	}
+
+	/// A copy with the weight of each action multiplied by what `scale` gives for it, such as to
+	/// give an AI a personality.
+	pub fn reweighted(&self, mut scale: impl FnMut(&UtilityAction) -> Fixed) -> UtilityAi {
+		let actions = self
+			.actions
+			.iter()
+			.map(|action| UtilityAction {
+				weight: action.weight * scale(action),
+				..action.clone()
+			})
+			.collect();
+		UtilityAi { actions }
+	}
+
+	pub fn score(
+		action: &UtilityAction,
+		inputs: &impl UtilityInputs,
+	) -> Result<Fixed, UtilityError> {
+		let mut score = action.weight.max(Fixed::ZERO);
+		for consideration in &action.considerations {
+			let input =
+				inputs
+					.input(&consideration.input)
+					.ok_or_else(|| UtilityError::UnknownInput {
+						action: action.name.clone(),
+						input: consideration.input.clone(),
+					})?;
+			score = score * consideration.curve.apply(input);
+		}
+		Ok(score)
+	}
+
+	/// Every action with its score, in the order they were given.
+	pub fn scores(
+		&self,
+		inputs: &impl UtilityInputs,
+	) -> Result<Vec<(&UtilityAction, Fixed)>, UtilityError> {
+		self.actions
+			.iter()
+			.map(|action| Ok((action, UtilityAi::score(action, inputs)?)))
+			.collect()
+	}
+
+	/// The best scoring action, the first of them on a tie, `None` if every action scored 0.
+	pub fn choose(
+		&self,
+		inputs: &impl UtilityInputs,
+	) -> Result<Option<&UtilityAction>, UtilityError> {
+		let mut best: Option<(&UtilityAction, Fixed)> = None;
+		for (action, score) in self.scores(inputs)? {
+			if score > best.map_or(Fixed::ZERO, |(_action, best)| best) {
+				best = Some((action, score));
+			}
+		}
+		Ok(best.map(|(action, _score)| action))
+	}
+}
+
+#[cfg(test)]
+mod utility_tests {
+	use super::*;
+
+	fn inputs(values: &[(&str, i32)]) -> HashMap<String, Fixed> {
+		values
+			.iter()
+			.map(|&(name, percent)| (name.to_owned(), Fixed::from_percent(percent)))
+			.collect()
+	}
+
+	fn ai() -> UtilityAi {
+		let actions: Vec<UtilityAction> = ron::de::from_str(
+			r#"[
+				(
+					name: "flee",
+					considerations: [
+						(input: "danger", curve: Step(threshold: 0.5)),
+						(input: "health", curve: Inverse),
+					],
+				),
+				(
+					name: "wander",
+					weight: 0.25,
+					considerations: [],
+				),
+				(
+					name: "eat",
+					weight: 2.0,
+					considerations: [
+						(input: "hunger", curve: Linear(slope: 0.5, offset: 0.0)),
+					],
+				),
+			]"#,
+		)
+		.unwrap();
+		UtilityAi::new(actions)
+	}
+
+	#[test]
+	fn curves_stay_in_range() {
+		let linear = Curve::Linear {
+			slope: Fixed::from_int(2),
+			offset: Fixed::from_percent(-50),
+		};
+		assert_eq!(linear.apply(Fixed::ZERO), Fixed::ZERO);
+		assert_eq!(
+			linear.apply(Fixed::from_percent(50)),
+			Fixed::from_percent(50)
+		);
+		assert_eq!(linear.apply(Fixed::ONE), Fixed::ONE);
+		assert_eq!(
+			Curve::Inverse.apply(Fixed::from_percent(25)),
+			Fixed::from_percent(75)
+		);
+		let step = Curve::Step {
+			threshold: Fixed::from_percent(50),
+		};
+		assert_eq!(step.apply(Fixed::from_percent(49)), Fixed::ZERO);
+		assert_eq!(step.apply(Fixed::from_percent(50)), Fixed::ONE);
+	}
+
+	#[test]
+	fn picks_the_best_scoring_action() {
+		let ai = ai();
+		let calm = inputs(&[("danger", 10), ("health", 20), ("hunger", 10)]);
+		assert_eq!(ai.choose(&calm).unwrap().unwrap().name, "wander");
+		let hungry = inputs(&[("danger", 10), ("health", 20), ("hunger", 80)]);
+		assert_eq!(ai.choose(&hungry).unwrap().unwrap().name, "eat");
+		// Hurt and in danger, fleeing beats eating
+		let hunted = inputs(&[("danger", 90), ("health", 0), ("hunger", 50)]);
+		assert_eq!(ai.choose(&hunted).unwrap().unwrap().name, "flee");
+		let scores: Vec<Fixed> = ai
+			.scores(&hunted)
+			.unwrap()
+			.into_iter()
+			.map(|(_action, score)| score)
+			.collect();
+		assert_eq!(
+			scores,
+			[Fixed::ONE, Fixed::from_percent(25), Fixed::from_percent(50)]
+		);
+	}
+
+	#[test]
+	fn reweighting_changes_the_choice() {
+		let ai = ai();
+		let hungry = inputs(&[("danger", 10), ("health", 20), ("hunger", 80)]);
+		let ascetic = ai.reweighted(|action| match action.name.as_str() {
+			"eat" => Fixed::from_ratio(1, 8),
+			_ => Fixed::ONE,
+		});
+		assert_eq!(ascetic.choose(&hungry).unwrap().unwrap().name, "wander");
+		assert_eq!(ai.actions()[2].weight, Fixed::from_int(2));
+		assert_eq!(ascetic.actions()[2].weight, Fixed::from_percent(25));
+	}
+
+	#[test]
+	fn nothing_worth_doing() {
+		let ai = UtilityAi::new(vec![]);
+		assert_eq!(ai.choose(&inputs(&[])), Ok(None));
+	}
+
+	#[test]
+	fn missing_inputs_are_errors() {
+		let ai = ai();
+		assert_eq!(
+			ai.choose(&inputs(&[("danger", 10)])),
+			Err(UtilityError::UnknownInput {
+				action: "flee".to_owned(),
+				input: "health".to_owned(),
+			})
+		);
+	}
+}
@@ -1,6 +1,8 @@
 use shipyard::*;
 use thiserror::*;
 
+use crate::core::reflect::{FieldType, ReflectError, TypeIssue};
+
 #[derive(Debug, Error)]
 pub enum ComponentAutoLoadError {
 	#[error("error while acquiring storage: {storage_name}")]
@@ -16,6 +18,14 @@ pub enum ComponentAutoLoadError {
 		entity: EntityId,
 		storage_name: String,
 	},
+	#[error("error while converting the values of a component: {storage_name}")]
+	ValueError {
+		source: ron::error::Error,
+		//backtrace: Backtrace, // Still needs nightly...
+		storage_name: String,
+	},
+	#[error("no component named {0} is registered")]
+	UnknownComponent(String),
 }
 
 #[typetag::serde()] // tag = "component"
@@ -27,6 +37,177 @@ pub trait ComponentAutoLoadable {
 	) -> Result<(), ComponentAutoLoadError>;
 }
 
+/// Copies one type of component off an entity, `None` if it doesn't have one.
+pub type ComponentSaveFn =
+	fn(
+		EntityId,
+		&AllStoragesViewMut,
+	) -> Result<Option<Box<dyn ComponentAutoLoadable>>, ComponentAutoLoadError>;
+
+/// Roughly the bytes one type of component takes over every entity that has it.
+pub type ComponentMemoryFn = fn(&AllStoragesViewMut) -> Result<usize, ComponentAutoLoadError>;
+
+/// Submitted for every type `component_auto_loadable!` is used on, so entities can be saved in
+/// the same form they are loaded from without listing their components again.
+pub struct ComponentRegistration {
+	pub name: &'static str,
+	pub save: ComponentSaveFn,
+	pub memory: ComponentMemoryFn,
+	/// Traces the component's fields, for editors and for checking templates.
+	pub describe: fn() -> Result<FieldType, ReflectError>,
+}
+
+inventory::collect!(ComponentRegistration);
+
+// For `component_auto_loadable!`, so crates using it don't need to depend on inventory themselves
+#[doc(hidden)]
+pub use inventory;
+
+/// Every registered component, sorted by name so saves come out the same each time.
+pub fn registered_components() -> Vec<&'static ComponentRegistration> {
+	let mut registrations: Vec<_> = inventory::iter::<ComponentRegistration>
+		.into_iter()
+		.collect();
+	registrations.sort_by_key(|registration| registration.name);
+	registrations
+}
+
+pub fn component_registration(name: &str) -> Option<&'static ComponentRegistration> {
+	inventory::iter::<ComponentRegistration>
+		.into_iter()
+		.find(|registration| registration.name == name)
+}
+
+/// Type checks entity template data, a list of `{"Component": value}` entries, against the
+/// registered components, finding every issue rather than stopping at the first like loading
+/// does. Components whose types can't be traced aren't checked.
+pub fn check_components(data: &ron::Value) -> Vec<TypeIssue> {
+	let issue = |path: &str, message: String| TypeIssue {
+		path: path.to_owned(),
+		message,
+	};
+	let entries = match data {
+		ron::Value::Seq(entries) => entries,
+		_ => return vec![issue("", "expected a list of components".to_owned())],
+	};
+	let mut issues = vec![];
+	for (idx, entry) in entries.iter().enumerate() {
+		let (name, value) = match entry {
+			ron::Value::Map(map) if map.len() == 1 => match map.iter().next() {
+				Some((ron::Value::String(name), value)) => (name, value),
+				_ => {
+					issues.push(issue(
+						&idx.to_string(),
+						"expected a component name".to_owned(),
+					));
+					continue;
+				}
+			},
+			_ => {
+				issues.push(issue(
+					&idx.to_string(),
+					"expected one `{\"Component\": value}` entry".to_owned(),
+				));
+				continue;
+			}
+		};
+		match component_registration(name).map(|registration| (registration.describe)()) {
+			Some(Ok(field_type)) => field_type.check(value, name, &mut issues),
+			Some(Err(_untraceable)) => {}
+			None => issues.push(issue(name, "no component has this name".to_owned())),
+		}
+	}
+	issues
+}
+
+/// The auto loadable components `entity` has, ready to be serialized as an entity template.
+pub fn save_entity_components(
+	entity: EntityId,
+	all_storages: &AllStoragesViewMut,
+) -> Result<Vec<Box<dyn ComponentAutoLoadable>>, ComponentAutoLoadError> {
+	let mut components = vec![];
+	for registration in registered_components() {
+		components.extend((registration.save)(entity, all_storages)?);
+	}
+	Ok(components)
+}
+
+/// Roughly the bytes every registered component takes over all the entities, by name. Components
+/// that aren't registered aren't counted.
+pub fn registered_components_memory(
+	all_storages: &AllStoragesViewMut,
+) -> Result<Vec<(&'static str, usize)>, ComponentAutoLoadError> {
+	registered_components()
+		.into_iter()
+		.map(|registration| Ok((registration.name, (registration.memory)(all_storages)?)))
+		.collect()
+}
+
+/// The values of each registered component `entity` has, by name, to show or edit them.
+pub fn component_values(
+	entity: EntityId,
+	all_storages: &AllStoragesViewMut,
+) -> Result<Vec<(&'static str, ron::Value)>, ComponentAutoLoadError> {
+	let mut values = vec![];
+	for registration in registered_components() {
+		let component = match (registration.save)(entity, all_storages)? {
+			Some(component) => component,
+			None => continue,
+		};
+		let value_error = |source| ComponentAutoLoadError::ValueError {
+			source,
+			storage_name: registration.name.to_owned(),
+		};
+		// Serialized as `{"Name": value}` since it goes through typetag
+		let text = ron::ser::to_string(&component).map_err(value_error)?;
+		let tagged: ron::Value = ron::de::from_str(&text).map_err(value_error)?;
+		if let ron::Value::Map(tagged) = tagged {
+			values.extend(
+				tagged
+					.values()
+					.next()
+					.cloned()
+					.map(|v| (registration.name, v)),
+			);
+		}
+	}
+	Ok(values)
+}
+
+/// Replaces the `name` component of `entity` with one read from `value`, so only values its type
+/// accepts get in.
+pub fn set_component_value(
+	entity: EntityId,
+	name: &str,
+	value: ron::Value,
+	all_storages: &mut AllStoragesViewMut,
+) -> Result<(), ComponentAutoLoadError> {
+	let registration = component_registration(name)
+		.ok_or_else(|| ComponentAutoLoadError::UnknownComponent(name.to_owned()))?;
+	let mut tagged = ron::Map::new();
+	tagged.insert(ron::Value::String(registration.name.to_owned()), value);
+	let component: Box<dyn ComponentAutoLoadable> =
+		ron::Value::Map(tagged).into_rust().map_err(|source| {
+			ComponentAutoLoadError::ValueError {
+				source,
+				storage_name: registration.name.to_owned(),
+			}
+		})?;
+	component.add_to_entity(entity, all_storages)
+}
+
+/// Makes each listed type auto loadable, so a game lists all of its components in one place:
+///
+/// ```ignore
+/// register_components!(DrawSprite, Health, Moves);
+/// ```
+#[macro_export]
+macro_rules! register_components {
+	($($typ:ty),* $(,)?) => {
+		$(over_simple_game_1::component_auto_loadable!($typ);)*
+	};
+}
+
 #[macro_export]
 macro_rules! component_auto_loadable {
 	($typ:ty) => {
@@ -60,5 +241,41 @@ macro_rules! component_auto_loadable {
 					})
 			}
 		}
+
+		over_simple_game_1::core::component::inventory::submit! {
+			#![crate = over_simple_game_1::core::component::inventory]
+			over_simple_game_1::core::component::ComponentRegistration {
+				name: stringify!($typ),
+				save: |entity, all_storages| {
+					use over_simple_game_1::core::component::{
+						ComponentAutoLoadError, ComponentAutoLoadable,
+					};
+					use shipyard::*;
+					let storage = all_storages
+						.try_borrow::<View<$typ>>()
+						.map_err(|source| ComponentAutoLoadError::GetStorageError {
+							source,
+							storage_name: stringify!($typ).to_owned(),
+						})?;
+					Ok(storage.get(entity).ok().map(|component| {
+						Box::new(component.clone()) as Box<dyn ComponentAutoLoadable>
+					}))
+				},
+				memory: |all_storages| {
+					use over_simple_game_1::core::component::ComponentAutoLoadError;
+					use shipyard::*;
+					let storage = all_storages
+						.try_borrow::<View<$typ>>()
+						.map_err(|source| ComponentAutoLoadError::GetStorageError {
+							source,
+							storage_name: stringify!($typ).to_owned(),
+						})?;
+					// Each is kept with its id, and the sparse array points back at it
+					let each = std::mem::size_of::<$typ>() + 2 * std::mem::size_of::<EntityId>();
+					Ok((&storage).iter().count() * each)
+				},
+				describe: over_simple_game_1::core::reflect::trace::<$typ>,
+			}
+		}
 	};
 }
@@ -0,0 +1,160 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+use crate::core::engine::{IndexMaps, MapCoord};
+use crate::core::map::coord::Coord;
+use crate::core::map::tile_map::TileMap;
+use crate::core::structures::typed_index_map::TypedIndexMap;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AddressError {
+	#[error("`{0}` is not an address like `world0:12,7`")]
+	Malformed(String),
+	#[error("no map named `{0}`")]
+	NoMap(String),
+	#[error("{0} is outside of its map")]
+	OutOfRange(MapAddress),
+}
+
+/// A tile anywhere in the world by its map's name and its axial coord, written `world0:12,7`.
+///
+/// This is what players, scenario files and saves use, map indexes depend on the order the
+/// maps were made in so they stay internal.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MapAddress {
+	pub map: String,
+	pub coord: Coord,
+}
+
+impl MapAddress {
+	pub fn new(map: impl Into<String>, coord: Coord) -> MapAddress {
+		MapAddress {
+			map: map.into(),
+			coord,
+		}
+	}
+
+	/// The address of `at`, `None` if its map doesn't exist.
+	pub fn of(
+		maps: &TypedIndexMap<IndexMaps, String, TileMap, u32>,
+		at: MapCoord,
+	) -> Option<MapAddress> {
+		let (name, _tile_map) = maps.get_index(at.map)?;
+		Some(MapAddress::new(name.clone(), at.coord))
+	}
+
+	/// Where the address is among `maps`, it has to be on a tile of an existing map.
+	pub fn resolve(
+		&self,
+		maps: &TypedIndexMap<IndexMaps, String, TileMap, u32>,
+	) -> Result<MapCoord, AddressError> {
+		let (map, _name, tile_map) = maps
+			.get_full(&self.map)
+			.ok_or_else(|| AddressError::NoMap(self.map.clone()))?;
+		if tile_map.get_tile(self.coord).is_none() {
+			return Err(AddressError::OutOfRange(self.clone()));
+		}
+		Ok(MapCoord {
+			map,
+			coord: self.coord,
+		})
+	}
+}
+
+impl fmt::Display for MapAddress {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{},{}", self.map, self.coord.q(), self.coord.r())
+	}
+}
+
+impl FromStr for MapAddress {
+	type Err = AddressError;
+
+	/// The map name is everything before the last `:`, so it may have colons of its own.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let malformed = || AddressError::Malformed(s.to_owned());
+		let split = s.rfind(':').ok_or_else(malformed)?;
+		let (map, coord) = (s[..split].trim(), &s[split + 1..]);
+		let mut parts = coord.split(',').map(|part| part.trim().parse::<u8>());
+		match (map, parts.next(), parts.next(), parts.next()) {
+			(map, Some(Ok(q)), Some(Ok(r)), None) if !map.is_empty() => {
+				Ok(MapAddress::new(map, Coord::new_axial(q, r)))
+			}
+			_ => Err(malformed()),
+		}
+	}
+}
+
+impl TryFrom<String> for MapAddress {
+	type Error = AddressError;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		s.parse()
+	}
+}
+
+impl From<MapAddress> for String {
+	fn from(address: MapAddress) -> Self {
+		address.to_string()
+	}
+}
+
+#[cfg(test)]
+mod address_tests {
+	use super::*;
+	use crate::core::map::generator::test_maps::plain_map;
+
+	#[test]
+	fn parses_and_formats() {
+		let address: MapAddress = "world0:12,7".parse().unwrap();
+		assert_eq!(address, MapAddress::new("world0", Coord::new_axial(12, 7)));
+		assert_eq!(address.to_string(), "world0:12,7");
+		assert_eq!(
+			"moon:base: 3, 4".parse(),
+			Ok(MapAddress::new("moon:base", Coord::new_axial(3, 4)))
+		);
+		for bad in &[
+			"world0",
+			":1,2",
+			"world0:1",
+			"world0:1,2,3",
+			"world0:1,300",
+			"w:a,b",
+		] {
+			assert_eq!(
+				bad.parse::<MapAddress>(),
+				Err(AddressError::Malformed((*bad).to_owned()))
+			);
+		}
+		let read: Vec<MapAddress> = ron::de::from_str(r#"["world0:1,2"]"#).unwrap();
+		assert_eq!(read, [MapAddress::new("world0", Coord::new_axial(1, 2))]);
+		assert_eq!(ron::ser::to_string(&read[0]).unwrap(), r#""world0:1,2""#);
+	}
+
+	#[test]
+	fn resolves_against_the_maps() {
+		let mut maps = TypedIndexMap::<IndexMaps, String, TileMap, u32>::new();
+		for name in &["world0", "world1"] {
+			let tile_map = plain_map(3, 3, false);
+			maps.insert_full((*name).to_owned(), tile_map).unwrap();
+		}
+		let address = MapAddress::new("world1", Coord::new_axial(2, 1));
+		let at = address.resolve(&maps).unwrap();
+		assert_eq!(maps.get_index(at.map).unwrap().0, "world1");
+		assert_eq!(MapAddress::of(&maps, at), Some(address));
+		assert_eq!(
+			MapAddress::new("mars", Coord::new_axial(0, 0)).resolve(&maps),
+			Err(AddressError::NoMap("mars".to_owned()))
+		);
+		let outside = MapAddress::new("world0", Coord::new_axial(9, 0));
+		assert_eq!(
+			outside.resolve(&maps),
+			Err(AddressError::OutOfRange(outside.clone()))
+		);
+	}
+}
@@ -0,0 +1,31 @@
+use shipyard::EntityId;
+
+/// Units grouped under a banner entity, this is the banner's component.
+///
+/// The banner stands on the members' tile and takes the move orders for all of them, the
+/// members are moved along with it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Army {
+	members: Vec<EntityId>,
+}
+
+impl Army {
+	pub fn new(members: Vec<EntityId>) -> Army {
+		Army { members }
+	}
+
+	pub fn members(&self) -> &[EntityId] {
+		&self.members
+	}
+
+	pub fn contains(&self, entity: EntityId) -> bool {
+		self.members.contains(&entity)
+	}
+
+	/// Takes `entity` out of the army, returning whether it was in it.
+	pub fn remove(&mut self, entity: EntityId) -> bool {
+		let count = self.members.len();
+		self.members.retain(|&member| member != entity);
+		self.members.len() != count
+	}
+}
@@ -0,0 +1,234 @@
+use std::time::Duration;
+
+/// How fast the simulation runs relative to real time.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum SimulationSpeed {
+	Paused,
+	Normal,
+	Double,
+	Quadruple,
+}
+
+impl SimulationSpeed {
+	pub fn multiplier(self) -> u32 {
+		match self {
+			SimulationSpeed::Paused => 0,
+			SimulationSpeed::Normal => 1,
+			SimulationSpeed::Double => 2,
+			SimulationSpeed::Quadruple => 4,
+		}
+	}
+
+	pub fn from_multiplier(multiplier: u32) -> Option<SimulationSpeed> {
+		match multiplier {
+			0 => Some(SimulationSpeed::Paused),
+			1 => Some(SimulationSpeed::Normal),
+			2 => Some(SimulationSpeed::Double),
+			4 => Some(SimulationSpeed::Quadruple),
+			_ => None,
+		}
+	}
+
+	pub fn faster(self) -> SimulationSpeed {
+		match self {
+			SimulationSpeed::Paused => SimulationSpeed::Normal,
+			SimulationSpeed::Normal => SimulationSpeed::Double,
+			SimulationSpeed::Double | SimulationSpeed::Quadruple => SimulationSpeed::Quadruple,
+		}
+	}
+
+	pub fn slower(self) -> SimulationSpeed {
+		match self {
+			SimulationSpeed::Quadruple => SimulationSpeed::Double,
+			SimulationSpeed::Double => SimulationSpeed::Normal,
+			SimulationSpeed::Normal | SimulationSpeed::Paused => SimulationSpeed::Paused,
+		}
+	}
+}
+
+/// Fixed timestep scheduler for the simulation, independent of the render rate.
+///
+/// Feed it the real frame delta and it returns how many simulation ticks should run.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use over_simple_game_1::core::engine::clock::*;
+/// let mut clock = SimulationClock::new(Duration::from_millis(100));
+/// assert_eq!(clock.advance(Duration::from_millis(250)), 2);
+/// clock.set_speed(SimulationSpeed::Paused);
+/// assert_eq!(clock.advance(Duration::from_millis(250)), 0);
+/// clock.step();
+/// assert_eq!(clock.advance(Duration::from_millis(0)), 1);
+/// assert_eq!(clock.current_tick(), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SimulationClock {
+	tick_duration: Duration,
+	speed: SimulationSpeed,
+	resume_speed: SimulationSpeed,
+	accumulated: Duration,
+	pending_steps: u32,
+	/// Caps the ticks returned per `advance` so a long stall doesn't spiral into a catch-up storm.
+	pub max_ticks_per_advance: u32,
+	tick: u64,
+}
+
+impl SimulationClock {
+	pub fn new(tick_duration: Duration) -> SimulationClock {
+		assert!(tick_duration > Duration::from_secs(0));
+		SimulationClock {
+			tick_duration,
+			speed: SimulationSpeed::Normal,
+			resume_speed: SimulationSpeed::Normal,
+			accumulated: Duration::from_secs(0),
+			pending_steps: 0,
+			max_ticks_per_advance: 16,
+			tick: 0,
+		}
+	}
+
+	pub fn speed(&self) -> SimulationSpeed {
+		self.speed
+	}
+
+	pub fn set_speed(&mut self, speed: SimulationSpeed) {
+		if speed != SimulationSpeed::Paused {
+			self.resume_speed = speed;
+		}
+		self.speed = speed;
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.speed == SimulationSpeed::Paused
+	}
+
+	/// Pauses, or resumes at the speed that was active before pausing.
+	pub fn toggle_pause(&mut self) {
+		if self.is_paused() {
+			self.speed = self.resume_speed;
+		} else {
+			self.speed = SimulationSpeed::Paused;
+		}
+	}
+
+	/// Queues a single tick to run on the next `advance`, only meaningful while paused.
+	pub fn step(&mut self) {
+		if self.is_paused() {
+			self.pending_steps = self.pending_steps.saturating_add(1);
+		}
+	}
+
+	pub fn current_tick(&self) -> u64 {
+		self.tick
+	}
+
+	/// How far the clock is into the next tick, `0..1`, to draw smoothly between ticks. It stays
+	/// at 0 while paused. Only for drawing, the ticks themselves are counted in whole nanoseconds.
+	#[allow(clippy::float_arithmetic)]
+	pub fn tick_fraction(&self) -> f32 {
+		self.accumulated.as_secs_f32() / self.tick_duration.as_secs_f32()
+	}
+
+	pub fn advance(&mut self, real_delta: Duration) -> u32 {
+		let ticks = if self.is_paused() {
+			self.accumulated = Duration::from_secs(0);
+			std::mem::replace(&mut self.pending_steps, 0)
+		} else {
+			self.pending_steps = 0;
+			self.accumulated += real_delta * self.speed.multiplier();
+			let tick_nanos = self.tick_duration.as_nanos();
+			let ticks = self.accumulated.as_nanos() / tick_nanos;
+			if ticks > self.max_ticks_per_advance as u128 {
+				self.accumulated = Duration::from_secs(0);
+				self.max_ticks_per_advance
+			} else {
+				self.accumulated -= self.tick_duration * ticks as u32;
+				ticks as u32
+			}
+		};
+		self.tick += ticks as u64;
+		ticks
+	}
+}
+
+#[cfg(test)]
+mod clock_tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn speed_steps_round_trip() {
+		assert_eq!(SimulationSpeed::Paused.faster(), SimulationSpeed::Normal);
+		assert_eq!(
+			SimulationSpeed::Quadruple.faster(),
+			SimulationSpeed::Quadruple
+		);
+		assert_eq!(SimulationSpeed::Quadruple.slower(), SimulationSpeed::Double);
+		assert_eq!(SimulationSpeed::Paused.slower(), SimulationSpeed::Paused);
+		for speed in &[
+			SimulationSpeed::Paused,
+			SimulationSpeed::Normal,
+			SimulationSpeed::Double,
+			SimulationSpeed::Quadruple,
+		] {
+			assert_eq!(
+				SimulationSpeed::from_multiplier(speed.multiplier()),
+				Some(*speed)
+			);
+		}
+	}
+
+	#[test]
+	fn toggle_pause_resumes_prior_speed() {
+		let mut clock = SimulationClock::new(Duration::from_millis(10));
+		clock.set_speed(SimulationSpeed::Quadruple);
+		clock.toggle_pause();
+		assert!(clock.is_paused());
+		clock.toggle_pause();
+		assert_eq!(clock.speed(), SimulationSpeed::Quadruple);
+	}
+
+	#[test]
+	fn steps_only_queue_while_paused() {
+		let mut clock = SimulationClock::new(Duration::from_millis(10));
+		clock.step();
+		assert_eq!(clock.advance(Duration::from_millis(0)), 0);
+		clock.set_speed(SimulationSpeed::Paused);
+		clock.step();
+		clock.step();
+		assert_eq!(clock.advance(Duration::from_secs(5)), 2);
+		assert_eq!(clock.advance(Duration::from_secs(5)), 0);
+	}
+
+	#[test]
+	fn fraction_is_the_leftover_time() {
+		let mut clock = SimulationClock::new(Duration::from_millis(100));
+		assert_eq!(clock.advance(Duration::from_millis(150)), 1);
+		assert!((clock.tick_fraction() - 0.5).abs() < 1e-4);
+		clock.toggle_pause();
+		clock.advance(Duration::from_millis(10));
+		assert_eq!(clock.tick_fraction(), 0.0);
+	}
+
+	#[test]
+	fn long_stalls_are_capped() {
+		let mut clock = SimulationClock::new(Duration::from_millis(10));
+		assert_eq!(clock.advance(Duration::from_secs(60)), 16);
+		assert_eq!(clock.advance(Duration::from_millis(10)), 1);
+	}
+
+	proptest!(
+		#[test]
+		fn speed_scales_tick_count(frames in 1..64u32, multiplier in prop::sample::select(vec![1u32, 2, 4])) {
+			let speed = SimulationSpeed::from_multiplier(multiplier).unwrap();
+			let mut normal = SimulationClock::new(Duration::from_millis(16));
+			let mut fast = SimulationClock::new(Duration::from_millis(16));
+			fast.set_speed(speed);
+			for _ in 0..frames {
+				normal.advance(Duration::from_millis(16));
+				fast.advance(Duration::from_millis(16));
+			}
+			prop_assert_eq!(normal.current_tick() * multiplier as u64, fast.current_tick());
+		}
+	);
+}
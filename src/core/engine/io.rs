@@ -7,6 +7,14 @@ use serde::Serialize;
 use crate::core::map::tile::{TileIdx, TileType};
 use std::convert::Infallible;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteMode {
+	/// Replaces anything already in the file.
+	Truncate,
+	/// Writes after anything already in the file.
+	Append,
+}
+
 /// A simple Input/Output interface to get readers/writers from file names.
 ///
 /// Implement this to allow the engine to be able to talk to storage.
@@ -15,10 +23,24 @@ pub trait EngineIO: Debug + Sized {
 	type Read: std::io::Read;
 	fn read(&mut self, file_path: &Path) -> Result<Self::Read, Self::ReadError>;
 
+	/// Whether `file_path` is there to read, so optional files can be told apart from ones that
+	/// fail to open. Anything `read` can open counts unless the IO has a better way to tell.
+	fn exists(&mut self, file_path: &Path) -> bool {
+		self.read(file_path).is_ok()
+	}
+
+	type WriteError: std::error::Error + Send + Sync;
+	type Write: std::io::Write;
+	/// Opens `file_path` for writing, creating it and any missing parent directories.
+	fn write(&mut self, file_path: &Path, mode: WriteMode)
+		-> Result<Self::Write, Self::WriteError>;
+
 	type TileInterface: Debug + Serialize + DeserializeOwned;
 	fn blank_tile_interface() -> Self::TileInterface;
 
 	type TileAddedError: std::error::Error + Send + Sync + 'static;
+	/// Called for each tile type added, and again for the same index when a later tile types file
+	/// replaces its definition.
 	fn tile_added(
 		&mut self,
 		index: TileIdx,
@@ -63,6 +85,30 @@ impl EngineIO for DirectFilesystemSimpleIO {
 		std::fs::File::open(path)
 	}
 
+	fn exists(&mut self, file_path: &Path) -> bool {
+		self.0.join(file_path).exists()
+	}
+
+	type WriteError = std::io::Error;
+	type Write = std::fs::File;
+
+	fn write(
+		&mut self,
+		file_path: &Path,
+		mode: WriteMode,
+	) -> Result<Self::Write, Self::WriteError> {
+		let path = self.0.join(file_path);
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		std::fs::OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(mode == WriteMode::Truncate)
+			.append(mode == WriteMode::Append)
+			.open(path)
+	}
+
 	type TileInterface = ();
 
 	fn blank_tile_interface() -> Self::TileInterface {}
@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use thiserror::*;
+
+#[derive(Error, Debug)]
+pub enum JobError {
+	#[error("failed to start a job thread")]
+	SpawnError {
+		#[from]
+		source: std::io::Error,
+	},
+
+	#[error("job panicked: {0}")]
+	Panicked(String),
+
+	#[error("job {0:?} is not running")]
+	UnknownJob(JobId),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(u64);
+
+/// A job that is done, with what it made.
+#[derive(Debug)]
+pub struct Finished<T> {
+	pub id: JobId,
+	pub result: Result<T, JobError>,
+}
+
+type Job<T> = (JobId, Box<dyn FnOnce() -> T + Send>);
+
+/// Runs jobs on a few background threads so heavy work doesn't hold up the frame, handing what
+/// they made back to whoever drains it, normally once a frame on the main thread.
+///
+/// A job that panics only fails itself, the thread goes on to the next. Dropping the pool waits
+/// for the jobs already started.
+pub struct JobPool<T: Send + 'static> {
+	jobs: Option<mpsc::Sender<Job<T>>>,
+	finished: mpsc::Receiver<Finished<T>>,
+	workers: Vec<thread::JoinHandle<()>>,
+	next_id: u64,
+	running: Vec<JobId>,
+	/// Finished while waiting for another job, kept for the next `drain`.
+	held: VecDeque<Finished<T>>,
+}
+
+impl<T: Send + 'static> JobPool<T> {
+	/// Starts `threads` threads named after `name`, at least one.
+	pub fn new(name: &str, threads: usize) -> Result<JobPool<T>, JobError> {
+		let (jobs, queued) = mpsc::channel::<Job<T>>();
+		let queued = Arc::new(Mutex::new(queued));
+		let (done, finished) = mpsc::channel();
+		let workers = (0..threads.max(1))
+			.map(|index| {
+				let queued = Arc::clone(&queued);
+				let done = done.clone();
+				thread::Builder::new()
+					.name(format!("{} {}", name, index))
+					.spawn(move || work(&queued, &done))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(JobPool {
+			jobs: Some(jobs),
+			finished,
+			workers,
+			next_id: 0,
+			running: vec![],
+			held: VecDeque::new(),
+		})
+	}
+
+	pub fn spawn(&mut self, job: impl FnOnce() -> T + Send + 'static) -> JobId {
+		let id = JobId(self.next_id);
+		self.next_id += 1;
+		self.running.push(id);
+		if let Some(jobs) = &self.jobs {
+			// The workers only go away with the pool
+			let _ = jobs.send((id, Box::new(job)));
+		}
+		id
+	}
+
+	/// How many jobs are queued or running.
+	pub fn running(&self) -> usize {
+		self.running.len()
+	}
+
+	fn received(&mut self, finished: &Finished<T>) {
+		self.running.retain(|&id| id != finished.id);
+	}
+
+	/// Every job that finished since the last drain, without waiting on the rest.
+	pub fn drain(&mut self) -> Vec<Finished<T>> {
+		let mut drained: Vec<Finished<T>> = self.held.drain(..).collect();
+		while let Ok(finished) = self.finished.try_recv() {
+			self.received(&finished);
+			drained.push(finished);
+		}
+		drained
+	}
+
+	/// Blocks until job `id` is done, keeping any others that finish meanwhile for `drain`.
+	pub fn wait_for(&mut self, id: JobId) -> Result<T, JobError> {
+		if let Some(index) = self.held.iter().position(|finished| finished.id == id) {
+			if let Some(finished) = self.held.remove(index) {
+				return finished.result;
+			}
+		}
+		if !self.running.contains(&id) {
+			return Err(JobError::UnknownJob(id));
+		}
+		while let Ok(finished) = self.finished.recv() {
+			self.received(&finished);
+			if finished.id == id {
+				return finished.result;
+			}
+			self.held.push_back(finished);
+		}
+		Err(JobError::UnknownJob(id))
+	}
+}
+
+impl<T: Send + 'static> Drop for JobPool<T> {
+	fn drop(&mut self) {
+		// Closing the queue lets the workers finish what they have and stop
+		self.jobs = None;
+		for worker in self.workers.drain(..) {
+			let _ = worker.join();
+		}
+	}
+}
+
+fn work<T>(queued: &Mutex<mpsc::Receiver<Job<T>>>, done: &mpsc::Sender<Finished<T>>) {
+	loop {
+		let job = match queued.lock() {
+			Ok(queued) => queued.recv(),
+			Err(_) => return,
+		};
+		let (id, job) = match job {
+			Ok(job) => job,
+			Err(_) => return,
+		};
+		let result = panic::catch_unwind(AssertUnwindSafe(job)).map_err(|payload| {
+			let message = payload
+				.downcast_ref::<&str>()
+				.map(|s| s.to_string())
+				.or_else(|| payload.downcast_ref::<String>().cloned())
+				.unwrap_or_else(|| "unknown panic".to_owned());
+			JobError::Panicked(message)
+		});
+		if done.send(Finished { id, result }).is_err() {
+			return;
+		}
+	}
+}
+
+#[cfg(test)]
+mod jobs_tests {
+	use super::*;
+
+	#[test]
+	fn runs_jobs_in_the_background() {
+		let mut pool = JobPool::new("test", 3).unwrap();
+		let ids: Vec<JobId> = (0..10u64).map(|n| pool.spawn(move || n * n)).collect();
+		assert_eq!(pool.running(), 10);
+		// Waiting out of order keeps the others
+		assert_eq!(pool.wait_for(ids[9]).unwrap(), 81);
+		let mut squares: Vec<u64> = ids[..9]
+			.iter()
+			.map(|&id| pool.wait_for(id).unwrap())
+			.collect();
+		squares.sort();
+		assert_eq!(squares, [0, 1, 4, 9, 16, 25, 36, 49, 64]);
+		assert_eq!(pool.running(), 0);
+		assert!(pool.drain().is_empty());
+		assert!(matches!(
+			pool.wait_for(ids[0]),
+			Err(JobError::UnknownJob(_))
+		));
+	}
+
+	#[test]
+	fn a_panic_only_fails_its_job() {
+		let mut pool = JobPool::new("test", 1).unwrap();
+		let panics = pool.spawn(|| -> u8 { panic!("broken") });
+		let fine = pool.spawn(|| 7u8);
+		assert_eq!(pool.wait_for(fine).unwrap(), 7);
+		let drained = pool.drain();
+		assert_eq!(drained.len(), 1);
+		assert_eq!(drained[0].id, panics);
+		match &drained[0].result {
+			Err(JobError::Panicked(message)) => assert_eq!(message, "broken"),
+			other => panic!("expected a panic, got {:?}", other),
+		}
+	}
+}
@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::core::engine::io::{EngineIO, WriteMode};
+
+/// Something that happened in the game worth keeping a record of.
+///
+/// Each is written as one line of JSON that reads back as the event, the line's `seq` and
+/// `elapsed_ms` are ignored.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+	MapGenerated {
+		map: String,
+		/// Wider than `Coord` for the wide maps.
+		width: u16,
+		height: u16,
+		wraps_x: bool,
+		seed: u64,
+	},
+	EntityMoved {
+		entity: String,
+		map: String,
+		q: u8,
+		r: u8,
+	},
+	TileChanged {
+		map: String,
+		q: u8,
+		r: u8,
+		tile: String,
+	},
+	/// Events from a game built on the engine, such as a turn starting or a city being founded.
+	Game {
+		kind: String,
+		data: serde_json::Value,
+	},
+}
+
+#[derive(Serialize)]
+struct JournalLine<'a> {
+	seq: u64,
+	elapsed_ms: u128,
+	#[serde(flatten)]
+	event: &'a JournalEvent,
+}
+
+/// Appends events as JSON lines to a file, for balancing analysis, external tools and post-mortem
+/// debugging.
+///
+/// Keeps the most recent lines in memory whether writing or not, so they can be dumped after a
+/// crash. Writing failures are logged and stop the writing instead of the game.
+pub struct Journal<W: Write> {
+	writer: Option<W>,
+	seq: u64,
+	started: Instant,
+	recent: VecDeque<String>,
+	recent_capacity: usize,
+}
+
+impl<W: Write> Journal<W> {
+	pub fn new(recent_capacity: usize) -> Journal<W> {
+		Journal {
+			writer: None,
+			seq: 0,
+			started: Instant::now(),
+			recent: VecDeque::with_capacity(recent_capacity),
+			recent_capacity,
+		}
+	}
+
+	/// Starts appending to `path` through the engine IO.
+	pub fn open<IO: EngineIO<Write = W>>(
+		&mut self,
+		io: &mut IO,
+		path: &Path,
+	) -> Result<(), IO::WriteError> {
+		self.writer = Some(io.write(path, WriteMode::Append)?);
+		Ok(())
+	}
+
+	pub fn close(&mut self) {
+		if let Some(mut writer) = self.writer.take() {
+			if let Err(error) = writer.flush() {
+				warn!("Failed flushing the journal: {}", error);
+			}
+		}
+	}
+
+	pub fn is_writing(&self) -> bool {
+		self.writer.is_some()
+	}
+
+	pub fn record(&mut self, event: JournalEvent) {
+		self.seq += 1;
+		let line = JournalLine {
+			seq: self.seq,
+			elapsed_ms: self.started.elapsed().as_millis(),
+			event: &event,
+		};
+		let line = match serde_json::to_string(&line) {
+			Ok(line) => line,
+			Err(error) => {
+				warn!("Failed serializing journal event {:?}: {}", event, error);
+				return;
+			}
+		};
+		if let Some(writer) = &mut self.writer {
+			if let Err(error) = writeln!(writer, "{}", line) {
+				warn!(
+					"Failed writing to the journal, no longer writing it: {}",
+					error
+				);
+				self.writer = None;
+			}
+		}
+		if self.recent_capacity > 0 {
+			if self.recent.len() == self.recent_capacity {
+				self.recent.pop_front();
+			}
+			self.recent.push_back(line);
+		}
+	}
+
+	/// The most recently recorded lines, oldest first.
+	pub fn recent(&self) -> impl Iterator<Item = &str> {
+		self.recent.iter().map(|line| line.as_str())
+	}
+}
+
+#[cfg(test)]
+mod journal_tests {
+	use super::*;
+
+	#[test]
+	fn keeps_only_the_most_recent_lines() {
+		let mut journal = Journal::<Vec<u8>>::new(2);
+		for kind in &["a", "b", "c"] {
+			journal.record(JournalEvent::Game {
+				kind: (*kind).to_owned(),
+				data: serde_json::Value::Null,
+			});
+		}
+		let recent: Vec<&str> = journal.recent().collect();
+		assert_eq!(recent.len(), 2);
+		assert!(recent[0].contains("\"seq\":2"));
+		assert!(recent[1].contains("\"kind\":\"c\""));
+	}
+
+	#[test]
+	fn writes_one_tagged_json_object_per_line() {
+		let mut journal = Journal::new(0);
+		journal.writer = Some(Vec::new());
+		journal.record(JournalEvent::MapGenerated {
+			map: "world0".to_owned(),
+			width: 6,
+			height: 6,
+			wraps_x: true,
+			seed: 1,
+		});
+		journal.record(JournalEvent::EntityMoved {
+			entity: "e".to_owned(),
+			map: "world0".to_owned(),
+			q: 1,
+			r: 2,
+		});
+		let written = String::from_utf8(journal.writer.take().unwrap()).unwrap();
+		let lines: Vec<serde_json::Value> = written
+			.lines()
+			.map(|line| serde_json::from_str(line).unwrap())
+			.collect();
+		assert_eq!(lines.len(), 2);
+		assert_eq!(lines[0]["event"], "map_generated");
+		assert_eq!(lines[0]["seq"], 1);
+		assert_eq!(lines[1]["event"], "entity_moved");
+		assert_eq!(lines[1]["r"], 2);
+		assert_eq!(journal.recent().count(), 0);
+	}
+
+	#[test]
+	fn lines_read_back_as_events() {
+		let mut journal = Journal::<Vec<u8>>::new(1);
+		journal.record(JournalEvent::TileChanged {
+			map: "world0".to_owned(),
+			q: 3,
+			r: 4,
+			tile: "sand".to_owned(),
+		});
+		let line = journal.recent().next().unwrap();
+		match serde_json::from_str::<JournalEvent>(line).unwrap() {
+			JournalEvent::TileChanged { map, q, r, tile } => {
+				assert_eq!(
+					(map.as_str(), q, r, tile.as_str()),
+					("world0", 3, 4, "sand")
+				);
+			}
+			event => panic!("read back as {:?}", event),
+		}
+	}
+}
@@ -1,20 +1,40 @@
+pub mod address;
+pub mod army;
+pub mod clock;
 pub mod io;
+pub mod jobs;
+pub mod journal;
+pub mod orders;
+pub mod persistent;
+pub mod schema;
+pub mod statistics;
+pub mod tags;
 
 use thiserror::*;
 
+use crate::core::map::ambient::{MapAmbient, MapAmbients, MapAmbientsError};
+use crate::core::map::decoration::{DecorationIdx, DecorationTypes, DecorationTypesError};
 use crate::core::map::generator::MapGenerator;
-use crate::core::map::tile::{TileTypes, TileTypesError};
+use crate::core::map::metadata::MapMetadata;
+use crate::core::map::tile::{TileIdx, TileType, TileTypes, TileTypesError};
 use crate::core::map::tile_map::{TileMap, TileMapError};
 
 //use std::backtrace::Backtrace;
 use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::path::PathBuf;
 
 use crate::core::engine::io::EngineIO;
-use crate::core::map::coord::Coord;
+use crate::core::engine::journal::{Journal, JournalEvent};
+use crate::core::engine::persistent::{PersistentId, PersistentIds};
+use crate::core::engine::schema::Schemas;
+use crate::core::map::coord::{Coord, Coord32, HexOrientation};
 use crate::core::structures::typed_index_map::{
 	TypedIndexMap, TypedIndexMapError, TypedIndexMapIndex,
 };
+use log::warn;
 use shipyard::{EntitiesView, EntityId, ViewMut};
+use tracing::trace_span;
 
 #[derive(Error, Debug)]
 pub enum EngineError<IO: EngineIO + 'static> {
@@ -25,12 +45,32 @@ pub enum EngineError<IO: EngineIO + 'static> {
 		//backtrace: Backtrace, // Still needs nightly...
 	},
 
+	#[error("failed to load decoration data")]
+	DecorationDataError {
+		#[from]
+		source: DecorationTypesError<IO>,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to load map ambient data")]
+	MapAmbientDataError {
+		#[from]
+		source: MapAmbientsError<IO>,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("requested map ambient does not exist: {0}")]
+	MapAmbientDoesNotExist(String),
+
 	#[error("cannot generate map as it already exists: {0}")]
 	MapAlreadyExists(String),
 
 	#[error("requested map does not exist: {0}")]
 	MapDoesNotExists(String),
 
+	#[error("the engine is limited to {0} maps, unable to create new map")]
+	TooManyMaps(usize),
+
 	#[error("map storage full, unable to create new map")]
 	UnableToInsertMap {
 		#[from]
@@ -38,6 +78,13 @@ pub enum EngineError<IO: EngineIO + 'static> {
 		//backtrace: Backtrace, // Still needs nightly...
 	},
 
+	#[error("map storage full, unable to create new wide map")]
+	UnableToInsertWideMap {
+		#[from]
+		source: TypedIndexMapError<String, TileMap<Coord32>, u32>,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
 	#[error("requested map does not exist at ID: {0:?}")]
 	MapDoesNotExistsIdx(MapIdx),
 
@@ -48,6 +95,13 @@ pub enum EngineError<IO: EngineIO + 'static> {
 		//backtrace: Backtrace, // Still needs nightly...
 	},
 
+	#[error("failed to open the journal at: {path}")]
+	JournalOpenFailed {
+		source: IO::WriteError,
+		//backtrace: Backtrace, // Still needs nightly...
+		path: String,
+	},
+
 	#[error("invalid coordinate requested on map `{map_name}` for: {coord:?}")]
 	CoordIsOutOfRange { map_name: String, coord: MapCoord },
 }
@@ -57,36 +111,209 @@ pub enum IndexMaps {}
 
 pub type MapIdx = TypedIndexMapIndex<IndexMaps, u32>;
 
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum IndexWideMaps {}
+
+pub type WideMapIdx = TypedIndexMapIndex<IndexWideMaps, u32>;
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct MapCoord {
 	pub map: MapIdx,
 	pub coord: Coord,
 }
 
+/// A tile's type or decoration changed after its map was generated, such as by the editor.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TileChanged {
+	pub at: MapCoord,
+}
+
+/// Configures an `Engine` before it is set up, see `Engine::builder`.
+pub struct EngineBuilder<IO: EngineIO> {
+	tile_types_paths: Vec<PathBuf>,
+	max_maps: Option<usize>,
+	journal_capacity: usize,
+	seed: u64,
+	hex_orientation: HexOrientation,
+	_io: PhantomData<fn() -> IO>,
+}
+
+impl<IO: EngineIO> Default for EngineBuilder<IO> {
+	fn default() -> Self {
+		EngineBuilder {
+			tile_types_paths: vec![PathBuf::from("tiles/tile_types.ron")],
+			max_maps: None,
+			journal_capacity: 64,
+			seed: 0,
+			hex_orientation: HexOrientation::PointyTop,
+			_io: PhantomData,
+		}
+	}
+}
+
+impl<IO: EngineIO> EngineBuilder<IO> {
+	/// Where `setup` reads the base tile types from through the IO, replacing any paths set so far,
+	/// `tiles/tile_types.ron` by default.
+	pub fn tile_types_path(mut self, path: impl Into<PathBuf>) -> EngineBuilder<IO> {
+		self.tile_types_paths = vec![path.into()];
+		self
+	}
+
+	/// Another tile types file read after the ones before it, such as a mod's. Tile types it
+	/// defines again replace the earlier ones and are logged as conflicts.
+	pub fn add_tile_types_path(mut self, path: impl Into<PathBuf>) -> EngineBuilder<IO> {
+		self.tile_types_paths.push(path.into());
+		self
+	}
+
+	/// Generating more maps than this fails, only the map index limits it by default.
+	pub fn max_maps(mut self, max_maps: usize) -> EngineBuilder<IO> {
+		self.max_maps = Some(max_maps);
+		self
+	}
+
+	/// How many of the latest journal events are kept in memory, for crash reports and for
+	/// anything reading them back, 64 by default.
+	pub fn journal_capacity(mut self, capacity: usize) -> EngineBuilder<IO> {
+		self.journal_capacity = capacity;
+		self
+	}
+
+	/// Seeds the maps whose generators don't pick a seed, so the same seed gives the same maps.
+	pub fn seed(mut self, seed: u64) -> EngineBuilder<IO> {
+		self.seed = seed;
+		self
+	}
+
+	/// Which way up the game draws its hexes, pointy-top by default.
+	pub fn hex_orientation(mut self, hex_orientation: HexOrientation) -> EngineBuilder<IO> {
+		self.hex_orientation = hex_orientation;
+		self
+	}
+
+	pub fn build(self) -> Engine<IO> {
+		Engine {
+			tile_types: TileTypes::new(),
+			decoration_types: DecorationTypes::new(),
+			map_ambients: MapAmbients::new(),
+			maps: TypedIndexMap::new(),
+			wide_maps: TypedIndexMap::new(),
+			journal: Journal::new(self.journal_capacity),
+			schemas: Schemas::new(),
+			persistent_ids: PersistentIds::new(),
+			tile_changes: vec![],
+			new_tile_types: vec![],
+			tile_types_paths: self.tile_types_paths,
+			max_maps: self.max_maps,
+			maps_made: 0,
+			seed: self.seed,
+			hex_orientation: self.hex_orientation,
+		}
+	}
+}
+
 pub struct Engine<IO: EngineIO> {
 	pub tile_types: TileTypes<IO>,
+	pub decoration_types: DecorationTypes,
+	pub map_ambients: MapAmbients,
 	pub maps: TypedIndexMap<IndexMaps, String, TileMap, u32>,
+	/// Maps past 256 tiles a side, addressed by `Coord32`. Nothing but the map generation and
+	/// tile access handles them yet, entities and tile changes are on `maps` only.
+	pub wide_maps: TypedIndexMap<IndexWideMaps, String, TileMap<Coord32>, u32>,
+	pub journal: Journal<IO::Write>,
+	pub schemas: Schemas,
+	/// Forget an entity here when deleting it.
+	pub persistent_ids: PersistentIds,
+	tile_changes: Vec<TileChanged>,
+	/// Tile types registered after setup, for renderers to make drawable.
+	new_tile_types: Vec<TileIdx>,
+	tile_types_paths: Vec<PathBuf>,
+	max_maps: Option<usize>,
+	/// Maps generated so far of either size, so every map gets its own seed.
+	maps_made: u64,
+	seed: u64,
+	hex_orientation: HexOrientation,
 }
 
 impl<IO: EngineIO> Engine<IO> {
-	/// Creates a new game Engine.
+	/// Configures a new game Engine.
 	///
 	/// ```
-	/// let engine = over_simple_game_1::core::engine::Engine::<over_simple_game_1::core::engine::io::DirectFilesystemSimpleIO>::new();
+	/// use over_simple_game_1::core::engine::{io::DirectFilesystemSimpleIO, Engine};
+	/// let engine: Engine<DirectFilesystemSimpleIO> = Engine::builder()
+	/// 	.tile_types_path("tiles/tile_types.ron")
+	/// 	.seed(42)
+	/// 	.build();
+	/// assert_eq!(engine.seed(), 42);
 	/// ```
-	pub fn new() -> Engine<IO> {
-		Engine {
-			tile_types: TileTypes::new(),
-			maps: TypedIndexMap::new(),
-		}
+	pub fn builder() -> EngineBuilder<IO> {
+		EngineBuilder::default()
+	}
+
+	/// The seed the engine was built with.
+	pub fn seed(&self) -> u64 {
+		self.seed
+	}
+
+	/// Which way up the hexes are drawn, for turning coords to and from linear space.
+	pub fn hex_orientation(&self) -> HexOrientation {
+		self.hex_orientation
 	}
 
 	pub fn setup(&mut self, io: &mut IO) -> Result<(), EngineError<IO>> {
-		self.tile_types.load_tiles(io)?;
+		for path in &self.tile_types_paths {
+			let conflicts = self.tile_types.load_tiles(io, &self.schemas, path)?;
+			for conflict in conflicts {
+				let previous = match &conflict.previous {
+					Some(previous) => previous.display().to_string(),
+					None => "the built in tile types".to_owned(),
+				};
+				warn!(
+					"Tile type `{}` from {} was replaced by {}",
+					conflict.name,
+					previous,
+					conflict.replaced_by.display()
+				);
+			}
+		}
+		self.decoration_types.load_decorations(io, &self.schemas)?;
+		self.map_ambients.load_ambients(io, &self.schemas)?;
 
 		Ok(())
 	}
 
+	/// Adds a tile type after setup, such as one a generator or script makes up on the fly. It is
+	/// kept for `take_new_tile_types` so renderers know to make it drawable before it is used.
+	pub fn register_tile_type(
+		&mut self,
+		io: &mut IO,
+		tile_type: TileType<IO>,
+	) -> Result<TileIdx, EngineError<IO>> {
+		let index = self.tile_types.register_tile(io, tile_type)?;
+		self.new_tile_types.push(index);
+		Ok(index)
+	}
+
+	/// The tile types registered since the last call, oldest first.
+	pub fn take_new_tile_types(&mut self) -> Vec<TileIdx> {
+		std::mem::take(&mut self.new_tile_types)
+	}
+
+	/// Starts appending journal events to `path`, they are only kept in memory until then.
+	pub fn enable_journal(
+		&mut self,
+		io: &mut IO,
+		path: impl AsRef<std::path::Path>,
+	) -> Result<(), EngineError<IO>> {
+		let path = path.as_ref();
+		self.journal
+			.open(io, path)
+			.map_err(|source| EngineError::JournalOpenFailed {
+				source,
+				path: path.display().to_string(),
+			})
+	}
+
 	pub fn generate_map(
 		&mut self,
 		_io: &mut IO,
@@ -97,18 +324,232 @@ impl<IO: EngineIO> Engine<IO> {
 		generator: &mut impl MapGenerator,
 	) -> Result<(), EngineError<IO>> {
 		let name = name.to_string();
-		if self.maps.contains_key(&name) {
-			return Err(EngineError::MapAlreadyExists(name));
-		}
+		let _span = trace_span!("generate_map", map = %name, max_x, max_y, wraps_x).entered();
+		self.check_new_map(&name)?;
 
-		let tile_map = TileMap::new(max_x, max_y, wraps_x, generator)?;
+		let mut tile_map = TileMap::new(max_x, max_y, wraps_x, generator)?;
+		if tile_map.seed == 0 {
+			tile_map.seed = self.new_map_seed();
+		}
+		tile_map.metadata = MapMetadata::new(name.clone(), generator.info());
+		self.journal.record(JournalEvent::MapGenerated {
+			map: name.clone(),
+			width: max_x.into(),
+			height: max_y.into(),
+			wraps_x,
+			seed: tile_map.seed,
+		});
 		self.maps
 			.insert(name, tile_map)
 			.map_err(|source| EngineError::UnableToInsertMap { source })?;
+		self.maps_made += 1;
+
+		Ok(())
+	}
+
+	/// `generate_map` for a map up to 65536 tiles a side, kept in `wide_maps`. Map names are
+	/// shared with `maps` and count towards the same limit.
+	pub fn generate_wide_map(
+		&mut self,
+		_io: &mut IO,
+		name: impl ToString,
+		max_x: u16,
+		max_y: u16,
+		wraps_x: bool,
+		generator: &mut impl MapGenerator<Coord32>,
+	) -> Result<(), EngineError<IO>> {
+		let name = name.to_string();
+		let _span = trace_span!("generate_wide_map", map = %name, max_x, max_y, wraps_x).entered();
+		self.check_new_map(&name)?;
+
+		let mut tile_map = TileMap::new(max_x, max_y, wraps_x, generator)?;
+		if tile_map.seed == 0 {
+			tile_map.seed = self.new_map_seed();
+		}
+		tile_map.metadata = MapMetadata::new(name.clone(), generator.info());
+		self.journal.record(JournalEvent::MapGenerated {
+			map: name.clone(),
+			width: max_x,
+			height: max_y,
+			wraps_x,
+			seed: tile_map.seed,
+		});
+		self.wide_maps
+			.insert(name, tile_map)
+			.map_err(|source| EngineError::UnableToInsertWideMap { source })?;
+		self.maps_made += 1;
+
+		Ok(())
+	}
 
+	/// The seed for the next map made when its generator doesn't pick one. Different for each map
+	/// wide or not, and still 0 for the first map of an unseeded engine.
+	fn new_map_seed(&self) -> u64 {
+		(self.seed ^ self.maps_made).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+	}
+
+	/// Fails if a new map can't be called `name` or there is no room for another.
+	fn check_new_map(&self, name: &str) -> Result<(), EngineError<IO>> {
+		if self.maps.contains_key(name) || self.wide_maps.contains_key(name) {
+			return Err(EngineError::MapAlreadyExists(name.to_owned()));
+		}
+		if let Some(max_maps) = self.max_maps {
+			if self.maps.len() + self.wide_maps.len() >= max_maps {
+				return Err(EngineError::TooManyMaps(max_maps));
+			}
+		}
 		Ok(())
 	}
 
+	/// Generates the map anew in place with `generator`, keeping its size, ambient, display name
+	/// and the entities on its tiles. When the generator doesn't pick a seed the map keeps the one
+	/// it had, so giving it the generator from its metadata makes the same map again.
+	///
+	/// Every tile may have changed, so anything caching the map should redraw all of it.
+	pub fn regenerate_map(
+		&mut self,
+		_io: &mut IO,
+		name: &str,
+		generator: &mut impl MapGenerator,
+	) -> Result<(), EngineError<IO>> {
+		let _span = trace_span!("regenerate_map", map = %name).entered();
+		let old = self
+			.maps
+			.get_mut(name)
+			.ok_or_else(|| EngineError::MapDoesNotExists(name.to_owned()))?;
+		let mut tile_map = TileMap::new(old.width, old.height, old.wraps_x, generator)?;
+		if tile_map.seed == 0 {
+			tile_map.seed = old.seed;
+		}
+		tile_map.ambient = old.ambient;
+		tile_map.metadata = MapMetadata::new(old.metadata.display_name.clone(), generator.info());
+		for (tile, old_tile) in tile_map.tiles.iter_mut().zip(old.tiles.iter_mut()) {
+			std::mem::swap(&mut tile.entities, &mut old_tile.entities);
+		}
+		self.journal.record(JournalEvent::MapGenerated {
+			map: name.to_owned(),
+			width: tile_map.width.into(),
+			height: tile_map.height.into(),
+			wraps_x: tile_map.wraps_x,
+			seed: tile_map.seed,
+		});
+		*old = tile_map;
+		Ok(())
+	}
+
+	/// The name players see for the map, its name in the engine stays the same.
+	pub fn set_map_display_name(
+		&mut self,
+		map: &str,
+		display_name: impl Into<String>,
+	) -> Result<(), EngineError<IO>> {
+		let tile_map = self
+			.maps
+			.get_mut(map)
+			.ok_or_else(|| EngineError::MapDoesNotExists(map.to_owned()))?;
+		tile_map.metadata.display_name = display_name.into();
+		Ok(())
+	}
+
+	/// Gives the map the named ambient from `maps/ambients.ron`.
+	pub fn set_map_ambient(&mut self, map: &str, ambient: &str) -> Result<(), EngineError<IO>> {
+		let ambient = self
+			.map_ambients
+			.ambients
+			.get_index_of(ambient)
+			.ok_or_else(|| EngineError::MapAmbientDoesNotExist(ambient.to_owned()))?;
+		let tile_map = self
+			.maps
+			.get_mut(map)
+			.ok_or_else(|| EngineError::MapDoesNotExists(map.to_owned()))?;
+		tile_map.ambient = Some(ambient);
+		Ok(())
+	}
+
+	/// The ambient of the map, `None` if the map doesn't exist or was never given one.
+	pub fn map_ambient(&self, map: MapIdx) -> Option<&MapAmbient> {
+		let (_name, tile_map) = self.maps.get_index(map)?;
+		let (_name, ambient) = self.map_ambients.ambients.get_index(tile_map.ambient?)?;
+		Some(ambient)
+	}
+
+	/// Gives the tile a new type and clears its decoration, returning whether it changed.
+	pub fn paint_tile(&mut self, at: MapCoord, id: TileIdx) -> Result<bool, EngineError<IO>> {
+		let (map_name, map) = self
+			.maps
+			.get_index_mut(at.map)
+			.ok_or_else(|| EngineError::MapDoesNotExistsIdx(at.map))?;
+		let tile = map
+			.get_tile_mut(at.coord)
+			.ok_or_else(|| EngineError::CoordIsOutOfRange {
+				map_name: (*map_name).clone(),
+				coord: at,
+			})?;
+		if tile.id == id && tile.decoration.is_none() {
+			return Ok(false);
+		}
+		tile.id = id;
+		tile.take_decoration();
+		let tile_name = self
+			.tile_types
+			.tile_types
+			.get_index(id)
+			.map(|(name, _tile_type)| name.clone())
+			.unwrap_or_default();
+		self.journal.record(JournalEvent::TileChanged {
+			map: map_name.clone(),
+			q: at.coord.q(),
+			r: at.coord.r(),
+			tile: tile_name,
+		});
+		self.tile_changes.push(TileChanged { at });
+		Ok(true)
+	}
+
+	/// Puts `decoration` on the tile, or clears it with `None`, returning whether it changed.
+	pub fn set_decoration(
+		&mut self,
+		at: MapCoord,
+		decoration: Option<DecorationIdx>,
+	) -> Result<bool, EngineError<IO>> {
+		let (map_name, map) = self
+			.maps
+			.get_index_mut(at.map)
+			.ok_or_else(|| EngineError::MapDoesNotExistsIdx(at.map))?;
+		let tile = map
+			.get_tile_mut(at.coord)
+			.ok_or_else(|| EngineError::CoordIsOutOfRange {
+				map_name: (*map_name).clone(),
+				coord: at,
+			})?;
+		if tile.decoration == decoration {
+			return Ok(false);
+		}
+		tile.decoration = decoration;
+		self.tile_changes.push(TileChanged { at });
+		Ok(true)
+	}
+
+	/// The tiles changed since the last call, oldest first, for anything caching what tiles look
+	/// like. They pile up until taken.
+	pub fn take_tile_changes(&mut self) -> Vec<TileChanged> {
+		std::mem::take(&mut self.tile_changes)
+	}
+
+	/// Gives `entity` a `PersistentId` component if it has none yet, returning its id.
+	pub fn assign_persistent_id(
+		&mut self,
+		entity: EntityId,
+		entities: EntitiesView,
+		mut storage: ViewMut<PersistentId>,
+	) -> PersistentId {
+		let id = self.persistent_ids.allocate(entity);
+		if !storage.contains(entity) {
+			entities.add_component(&mut storage, id, entity);
+		}
+		id
+	}
+
 	pub fn move_entity_to_coord(
 		&mut self,
 		entity: EntityId,
@@ -116,7 +557,9 @@ impl<IO: EngineIO> Engine<IO> {
 		entities: EntitiesView,
 		mut storage: ViewMut<MapCoord>,
 	) -> Result<(), EngineError<IO>> {
-		if !storage.contains(entity) {
+		let _span = trace_span!("move_entity_to_coord", ?entity, ?c).entered();
+		// Placing an entity where it already is isn't a move, so it isn't journaled
+		let moved = if !storage.contains(entity) {
 			entities.add_component(&mut storage, c, entity);
 			let (map_name, map) = self
 				.maps
@@ -129,6 +572,7 @@ impl<IO: EngineIO> Engine<IO> {
 					coord: c,
 				})?;
 			tile.entities.insert(entity);
+			true
 		} else {
 			let coord = &mut storage[entity];
 			if coord.map == c.map && coord.coord != c.coord {
@@ -153,6 +597,7 @@ impl<IO: EngineIO> Engine<IO> {
 					.entities
 					.insert(entity);
 				coord.coord = c.coord;
+				true
 			} else if coord.coord != c.coord {
 				{
 					// Old tile
@@ -185,7 +630,25 @@ impl<IO: EngineIO> Engine<IO> {
 						.insert(entity);
 				}
 				*coord = c;
+				true
+			} else {
+				false
 			}
+		};
+		if !moved {
+			return Ok(());
+		}
+		if let Some((map_name, _map)) = self.maps.get_index(c.map) {
+			let entity = match self.persistent_ids.id(entity) {
+				Some(id) => id.to_string(),
+				None => format!("{:?}", entity),
+			};
+			self.journal.record(JournalEvent::EntityMoved {
+				entity,
+				map: map_name.clone(),
+				q: c.coord.q(),
+				r: c.coord.r(),
+			});
 		}
 
 		Ok(())
@@ -0,0 +1,224 @@
+use std::collections::VecDeque;
+
+use crate::core::engine::{MapCoord, MapIdx};
+use crate::core::map::coord::Coord;
+use crate::core::map::path::find_path;
+use crate::core::map::tile_map::TileMap;
+
+/// Where an entity has been told to move, one leg per waypoint, walked a tile at a time.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MoveOrder {
+	waypoints: VecDeque<MapCoord>,
+	/// Reached waypoints go back on the end instead of being dropped, walking the legs forever.
+	patrol: bool,
+	/// The rest of the way to the first waypoint, the next tile last.
+	path: Vec<Coord>,
+}
+
+impl MoveOrder {
+	pub fn new(to: MapCoord) -> MoveOrder {
+		let mut order = MoveOrder::default();
+		order.push(to);
+		order
+	}
+
+	/// Moves along `path` on `map`, a way found from where the entity stands such as one around
+	/// danger, instead of finding its own. A way is still found again if the entity leaves it.
+	pub fn along(map: MapIdx, path: &[Coord]) -> MoveOrder {
+		let mut order = MoveOrder::default();
+		if let Some(&to) = path.last() {
+			order.push(MapCoord { map, coord: to });
+			// Kept the way `next_step` keeps it, the next tile last and without the start
+			order.path = path.iter().skip(1).rev().copied().collect();
+		}
+		order
+	}
+
+	/// Adds a leg after the others, a waypoint repeating the last one is ignored.
+	pub fn push(&mut self, to: MapCoord) {
+		if self.waypoints.back() != Some(&to) {
+			self.waypoints.push_back(to);
+		}
+	}
+
+	pub fn waypoints(&self) -> impl ExactSizeIterator<Item = &MapCoord> {
+		self.waypoints.iter()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.waypoints.is_empty()
+	}
+
+	pub fn is_patrolling(&self) -> bool {
+		self.patrol
+	}
+
+	/// Patrols between `from`, normally where the entity stands now, and the waypoints.
+	pub fn patrol_from(&mut self, from: MapCoord) {
+		self.push(from);
+		self.patrol = true;
+	}
+
+	/// Stops patrolling after the current leg.
+	pub fn stop_patrol(&mut self) {
+		self.patrol = false;
+		self.waypoints.truncate(1);
+	}
+
+	/// The tile to move to next from `from`, dropping the waypoints already reached.
+	///
+	/// Each leg is found with `find_path` once and then followed, it is found again if the
+	/// entity has left it. Waypoints on another map than `from` can't be walked to and are
+	/// dropped too. `None` once every waypoint is reached or when the way is blocked.
+	pub fn next_step(&mut self, from: MapCoord, map: &TileMap) -> Option<MapCoord> {
+		while let Some(to) = self.waypoints.pop_front() {
+			if to.map != from.map {
+				continue;
+			}
+			if to.coord != from.coord {
+				self.waypoints.push_front(to);
+				let on_path = self.path.first() == Some(&to.coord)
+					&& self
+						.path
+						.last()
+						.map_or(false, |&next| map.distance(from.coord, next) == 1);
+				if !on_path {
+					// Every tile can be walked on alike until tiles have movement costs
+					self.path = find_path(map, from.coord, to.coord, |_coord, _tile| Some(1))
+						.unwrap_or_default();
+					self.path.reverse();
+					self.path.pop();
+				}
+				return self.path.pop().map(|coord| MapCoord {
+					map: from.map,
+					coord,
+				});
+			}
+			// A patrol needs somewhere else to go, or reaching this waypoint would loop forever
+			if self.patrol && !self.waypoints.is_empty() {
+				self.push(to);
+			}
+		}
+		None
+	}
+}
+
+#[cfg(test)]
+mod orders_tests {
+	use super::*;
+	use crate::core::engine::IndexMaps;
+	use crate::core::map::coord::Coord;
+	use crate::core::map::generator::test_maps::plain_map;
+	use crate::core::structures::typed_index_map::TypedIndexMap;
+
+	fn map(width: u8, height: u8, wraps_x: bool) -> (TileMap, crate::core::engine::MapIdx) {
+		let tile_map = plain_map(width, height, wraps_x);
+		let mut maps = TypedIndexMap::<IndexMaps, String, (), u32>::new();
+		let (map, _old) = maps.insert_full("test".to_owned(), ()).unwrap();
+		(tile_map, map)
+	}
+
+	fn at(map: crate::core::engine::MapIdx, q: u8, r: u8) -> MapCoord {
+		MapCoord {
+			map,
+			coord: Coord::new_axial(q, r),
+		}
+	}
+
+	#[test]
+	fn walks_each_leg_in_turn() {
+		let (tile_map, map) = map(7, 7, false);
+		let mut order = MoveOrder::new(at(map, 3, 0));
+		order.push(at(map, 3, 2));
+		let mut position = at(map, 0, 0);
+		let mut path = vec![];
+		while let Some(next) = order.next_step(position, &tile_map) {
+			assert_eq!(tile_map.distance(position.coord, next.coord), 1);
+			position = next;
+			path.push(position);
+		}
+		assert_eq!(path.len(), 5);
+		assert_eq!(path[2], at(map, 3, 0));
+		assert_eq!(position, at(map, 3, 2));
+		assert!(order.is_empty());
+	}
+
+	#[test]
+	fn repeated_waypoints_are_ignored() {
+		let (_tile_map, map) = map(3, 3, false);
+		let mut order = MoveOrder::new(at(map, 1, 1));
+		order.push(at(map, 1, 1));
+		order.push(at(map, 2, 2));
+		assert_eq!(order.waypoints().len(), 2);
+	}
+
+	#[test]
+	fn patrols_back_and_forth() {
+		let (tile_map, map) = map(7, 7, false);
+		let start = at(map, 0, 0);
+		let mut order = MoveOrder::new(at(map, 2, 0));
+		order.patrol_from(start);
+		let mut position = start;
+		let mut visited = vec![];
+		for _ in 0..7 {
+			position = order.next_step(position, &tile_map).unwrap();
+			visited.push(position.coord.q());
+		}
+		assert_eq!(visited, vec![1, 2, 1, 0, 1, 2, 1]);
+		assert!(order.is_patrolling());
+
+		// Finishes the leg back to the start and stops there
+		order.stop_patrol();
+		while let Some(next) = order.next_step(position, &tile_map) {
+			position = next;
+		}
+		assert_eq!(position, start);
+		assert!(order.is_empty());
+	}
+
+	#[test]
+	fn finds_the_way_again_when_moved_off_it() {
+		let (tile_map, map) = map(7, 7, false);
+		let mut order = MoveOrder::new(at(map, 6, 0));
+		assert_eq!(
+			order.next_step(at(map, 0, 0), &tile_map),
+			Some(at(map, 1, 0))
+		);
+		// Pushed somewhere else instead of taking the step
+		let pushed = at(map, 3, 5);
+		let next = order.next_step(pushed, &tile_map).unwrap();
+		assert_eq!(tile_map.distance(pushed.coord, next.coord), 1);
+		assert!(tile_map.distance(next.coord, Coord::new_axial(6, 0)) < 5);
+	}
+
+	#[test]
+	fn follows_the_path_it_is_given() {
+		let (tile_map, map) = map(7, 7, false);
+		// Longer than it has to be, as a way around something would be
+		let path = [(0, 0), (0, 1), (1, 1), (2, 0)];
+		let path: Vec<Coord> = path.iter().map(|&(q, r)| Coord::new_axial(q, r)).collect();
+		let mut order = MoveOrder::along(map, &path);
+		let mut position = at(map, 0, 0);
+		let mut walked = vec![position.coord];
+		while let Some(next) = order.next_step(position, &tile_map) {
+			position = next;
+			walked.push(position.coord);
+		}
+		assert_eq!(walked, path);
+		assert!(MoveOrder::along(map, &[]).is_empty());
+	}
+
+	#[test]
+	fn goes_around_the_seam_of_wrapping_maps() {
+		let (tile_map, map) = map(9, 3, true);
+		assert_eq!(
+			tile_map.distance(Coord::new_axial(0, 1), Coord::new_axial(9, 1)),
+			1
+		);
+		let mut order = MoveOrder::new(at(map, 9, 1));
+		assert_eq!(
+			order.next_step(at(map, 0, 1), &tile_map),
+			Some(at(map, 9, 1))
+		);
+	}
+}
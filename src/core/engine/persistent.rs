@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use shipyard::EntityId;
+use thiserror::*;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PersistentIdError {
+	#[error("persistent id {id} is already used by entity {entity:?}")]
+	Taken { id: PersistentId, entity: EntityId },
+}
+
+/// An entity's id that stays the same across sessions, unlike its `EntityId`, for saves,
+/// replays, network messages and scenarios to refer to it by. The engine hands them out and
+/// never gives the same one twice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct PersistentId(pub u64);
+
+impl fmt::Display for PersistentId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "#{}", self.0)
+	}
+}
+
+/// Which entity has which persistent id, both ways around.
+#[derive(Clone, Debug, Default)]
+pub struct PersistentIds {
+	next: u64,
+	entities: HashMap<PersistentId, EntityId>,
+	ids: HashMap<EntityId, PersistentId>,
+}
+
+impl PersistentIds {
+	pub fn new() -> PersistentIds {
+		PersistentIds::default()
+	}
+
+	/// The id of `entity`, giving it a new one if it has none yet.
+	pub fn allocate(&mut self, entity: EntityId) -> PersistentId {
+		if let Some(&id) = self.ids.get(&entity) {
+			return id;
+		}
+		let id = PersistentId(self.next);
+		self.next += 1;
+		self.entities.insert(id, entity);
+		self.ids.insert(entity, id);
+		id
+	}
+
+	/// Gives `entity` the id it had before, such as when loading a save. Ids handed out from
+	/// then on come after it.
+	pub fn restore(&mut self, id: PersistentId, entity: EntityId) -> Result<(), PersistentIdError> {
+		match self.entities.get(&id) {
+			Some(&existing) if existing == entity => return Ok(()),
+			Some(&existing) => {
+				return Err(PersistentIdError::Taken {
+					id,
+					entity: existing,
+				})
+			}
+			None => (),
+		}
+		if let Some(old) = self.ids.insert(entity, id) {
+			self.entities.remove(&old);
+		}
+		self.entities.insert(id, entity);
+		self.next = self.next.max(id.0 + 1);
+		Ok(())
+	}
+
+	/// Drops `entity` once it is deleted, its id is not used again.
+	pub fn forget(&mut self, entity: EntityId) -> Option<PersistentId> {
+		let id = self.ids.remove(&entity)?;
+		self.entities.remove(&id);
+		Some(id)
+	}
+
+	pub fn entity(&self, id: PersistentId) -> Option<EntityId> {
+		self.entities.get(&id).copied()
+	}
+
+	pub fn id(&self, entity: EntityId) -> Option<PersistentId> {
+		self.ids.get(&entity).copied()
+	}
+
+	pub fn len(&self) -> usize {
+		self.ids.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ids.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod persistent_tests {
+	use super::*;
+	use shipyard::{EntitiesViewMut, World};
+
+	fn entities(count: usize) -> Vec<EntityId> {
+		World::new().run(|mut entities: EntitiesViewMut| {
+			(0..count).map(|_| entities.add_entity((), ())).collect()
+		})
+	}
+
+	#[test]
+	fn looks_up_both_ways() {
+		let e = entities(3);
+		let mut ids = PersistentIds::new();
+		let a = ids.allocate(e[0]);
+		let b = ids.allocate(e[1]);
+		assert_ne!(a, b);
+		assert_eq!(ids.allocate(e[0]), a);
+		assert_eq!(ids.entity(b), Some(e[1]));
+		assert_eq!(ids.id(e[0]), Some(a));
+		assert_eq!(ids.id(e[2]), None);
+		assert_eq!(ids.forget(e[0]), Some(a));
+		assert_eq!(ids.entity(a), None);
+		// Never handed out again
+		let c = ids.allocate(e[2]);
+		assert!(c != a && c != b);
+		assert_eq!(ids.len(), 2);
+	}
+
+	#[test]
+	fn restores_saved_ids() {
+		let e = entities(3);
+		let mut ids = PersistentIds::new();
+		ids.restore(PersistentId(41), e[0]).unwrap();
+		assert_eq!(ids.entity(PersistentId(41)), Some(e[0]));
+		assert_eq!(ids.allocate(e[1]), PersistentId(42));
+		assert_eq!(
+			ids.restore(PersistentId(41), e[2]),
+			Err(PersistentIdError::Taken {
+				id: PersistentId(41),
+				entity: e[0],
+			})
+		);
+		ids.restore(PersistentId(7), e[0]).unwrap();
+		assert_eq!(ids.entity(PersistentId(41)), None);
+		assert_eq!(ids.id(e[0]), Some(PersistentId(7)));
+		assert_eq!(ids.allocate(e[2]), PersistentId(43));
+	}
+}
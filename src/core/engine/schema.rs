@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+pub const TILE_TYPES: &str = "tile_types";
+pub const DECORATION_TYPES: &str = "decoration_types";
+pub const MAP_AMBIENTS: &str = "map_ambients";
+pub const PLAYER_STATISTICS: &str = "player_statistics";
+
+/// Rewrites data from one version of a schema into the next, loosely typed so the old layout
+/// doesn't need a type of its own.
+pub type Migration = fn(ron::Value) -> anyhow::Result<ron::Value>;
+
+/// Just the stamp of a versioned data file, its data is skipped over.
+#[derive(Deserialize)]
+#[serde(rename = "Versioned")]
+struct Stamp {
+	schema: String,
+	version: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Versioned")]
+struct Versioned<T> {
+	data: T,
+}
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+	#[error("failed to read {schema} data")]
+	ReadError {
+		schema: &'static str,
+		source: std::io::Error,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to parse {schema} data")]
+	ParseError {
+		schema: &'static str,
+		source: ron::error::Error,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to serialize {schema} data")]
+	SerializeError {
+		schema: &'static str,
+		source: ron::error::Error,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to write {schema} data")]
+	WriteError {
+		schema: &'static str,
+		source: std::io::Error,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("no data schema named {0} is known")]
+	UnknownSchema(&'static str),
+
+	#[error("expected {expected} data but found {found} data")]
+	WrongSchema {
+		expected: &'static str,
+		found: String,
+	},
+
+	#[error("{schema} data is version {found} but this build reads up to version {current}, it was made by a newer version of the game")]
+	TooNew {
+		schema: &'static str,
+		found: u32,
+		current: u32,
+	},
+
+	#[error("{schema} data is version {found} and needs migrating to version {current}, but there is no migration from version {missing}")]
+	MissingMigration {
+		schema: &'static str,
+		found: u32,
+		current: u32,
+		missing: u32,
+	},
+
+	#[error("migrating {schema} data from version {from} failed")]
+	MigrationFailed {
+		schema: &'static str,
+		from: u32,
+		source: anyhow::Error,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+}
+
+/// The current version of each kind of data file and the migrations bringing older ones up to it.
+///
+/// Data files are stamped by wrapping them as `Versioned(schema: "...", version: N, data: ...)`,
+/// files without a stamp are read as version 1.
+#[derive(Debug, Default)]
+pub struct Schemas {
+	current: HashMap<&'static str, u32>,
+	migrations: HashMap<(&'static str, u32), Migration>,
+}
+
+impl Schemas {
+	/// The schemas of the engine's own data files, all at their current versions.
+	pub fn new() -> Schemas {
+		let mut schemas = Schemas::default();
+		schemas.declare(TILE_TYPES, 1);
+		schemas.declare(DECORATION_TYPES, 1);
+		schemas.declare(MAP_AMBIENTS, 1);
+		schemas.declare(PLAYER_STATISTICS, 1);
+		schemas
+	}
+
+	/// Sets the version of `schema` this build reads and writes.
+	pub fn declare(&mut self, schema: &'static str, current: u32) {
+		self.current.insert(schema, current);
+	}
+
+	/// Registers how to bring `schema` data from version `from` to `from + 1`.
+	pub fn register_migration(&mut self, schema: &'static str, from: u32, migration: Migration) {
+		self.migrations.insert((schema, from), migration);
+	}
+
+	pub fn current(&self, schema: &'static str) -> Option<u32> {
+		self.current.get(schema).copied()
+	}
+
+	/// Reads `schema` data, migrating it first if it is older than the current version.
+	pub fn read<T: DeserializeOwned>(
+		&self,
+		schema: &'static str,
+		mut reader: impl Read,
+	) -> Result<T, SchemaError> {
+		let current = self
+			.current(schema)
+			.ok_or(SchemaError::UnknownSchema(schema))?;
+		let mut text = String::new();
+		reader
+			.read_to_string(&mut text)
+			.map_err(|source| SchemaError::ReadError { schema, source })?;
+		let parse_error = |source| SchemaError::ParseError { schema, source };
+
+		let (found, stamped) = match ron::de::from_str::<Stamp>(&text) {
+			Ok(stamp) if stamp.schema != schema => {
+				return Err(SchemaError::WrongSchema {
+					expected: schema,
+					found: stamp.schema,
+				})
+			}
+			Ok(stamp) => (stamp.version, true),
+			Err(_) => (1, false),
+		};
+		if found > current {
+			return Err(SchemaError::TooNew {
+				schema,
+				found,
+				current,
+			});
+		}
+		if found == current {
+			return if stamped {
+				ron::de::from_str::<Versioned<T>>(&text).map(|versioned| versioned.data)
+			} else {
+				ron::de::from_str(&text)
+			}
+			.map_err(parse_error);
+		}
+
+		let mut value = if stamped {
+			ron::de::from_str::<Versioned<ron::Value>>(&text).map(|versioned| versioned.data)
+		} else {
+			ron::de::from_str(&text)
+		}
+		.map_err(parse_error)?;
+		for from in found..current {
+			let migration =
+				self.migrations
+					.get(&(schema, from))
+					.ok_or(SchemaError::MissingMigration {
+						schema,
+						found,
+						current,
+						missing: from,
+					})?;
+			value = migration(value).map_err(|source| SchemaError::MigrationFailed {
+				schema,
+				from,
+				source,
+			})?;
+		}
+		value.into_rust().map_err(parse_error)
+	}
+
+	/// Writes `data` stamped with the current version of `schema`, so reading it back later knows
+	/// which migrations it needs.
+	pub fn write<T: Serialize>(
+		&self,
+		schema: &'static str,
+		mut writer: impl Write,
+		data: &T,
+	) -> Result<(), SchemaError> {
+		let current = self
+			.current(schema)
+			.ok_or(SchemaError::UnknownSchema(schema))?;
+		let data = ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default())
+			.map_err(|source| SchemaError::SerializeError { schema, source })?;
+		writeln!(
+			writer,
+			"Versioned(schema: \"{}\", version: {}, data: {})",
+			schema, current, data
+		)
+		.map_err(|source| SchemaError::WriteError { schema, source })
+	}
+}
+
+#[cfg(test)]
+mod schema_tests {
+	use super::*;
+	use serde::{Deserialize, Serialize};
+
+	const UNITS: &str = "units";
+
+	#[derive(Debug, Deserialize, Serialize, PartialEq)]
+	struct Unit {
+		name: String,
+		moves: u8,
+	}
+
+	/// Version 1 called moves `speed`.
+	fn rename_speed(value: ron::Value) -> anyhow::Result<ron::Value> {
+		let mut units = match value {
+			ron::Value::Seq(units) => units,
+			_ => anyhow::bail!("expected a list of units"),
+		};
+		for unit in &mut units {
+			if let ron::Value::Map(fields) = unit {
+				let speed = ron::Value::String("speed".to_owned());
+				let speed = fields.remove(&speed).unwrap_or(ron::Value::Unit);
+				fields.insert(ron::Value::String("moves".to_owned()), speed);
+			}
+		}
+		Ok(ron::Value::Seq(units))
+	}
+
+	/// Version 2 had no changes worth a migration of their own.
+	fn unchanged(value: ron::Value) -> anyhow::Result<ron::Value> {
+		Ok(value)
+	}
+
+	fn schemas() -> Schemas {
+		let mut schemas = Schemas::default();
+		schemas.declare(UNITS, 3);
+		schemas.register_migration(UNITS, 1, rename_speed);
+		schemas.register_migration(UNITS, 2, unchanged);
+		schemas
+	}
+
+	#[test]
+	fn current_data_reads_directly() {
+		let units: Vec<Unit> = schemas()
+			.read(
+				UNITS,
+				br#"Versioned(schema: "units", version: 3, data: [Unit(name: "scout", moves: 3)])"#
+					as &[u8],
+			)
+			.unwrap();
+		assert_eq!(
+			units,
+			vec![Unit {
+				name: "scout".to_owned(),
+				moves: 3
+			}]
+		);
+	}
+
+	#[test]
+	fn older_and_unstamped_data_is_migrated() {
+		let stamped = br#"// Comments before the stamp are fine
+			Versioned(schema: "units", version: 1, data: [Unit(name: "scout", speed: 3)])"#;
+		let unstamped = br#"[Unit(name: "scout", speed: 3)]"#;
+		for text in &[&stamped[..], &unstamped[..]] {
+			let units: Vec<Unit> = schemas().read(UNITS, *text).unwrap();
+			assert_eq!(units[0].moves, 3);
+		}
+	}
+
+	#[test]
+	fn written_data_reads_back() {
+		let units = vec![Unit {
+			name: "scout".to_owned(),
+			moves: 3,
+		}];
+		let mut text = vec![];
+		schemas().write(UNITS, &mut text, &units).unwrap();
+		assert!(text.starts_with(br#"Versioned(schema: "units", version: 3"#));
+		let read: Vec<Unit> = schemas().read(UNITS, &text[..]).unwrap();
+		assert_eq!(read, units);
+	}
+
+	#[test]
+	fn incompatible_data_is_explained() {
+		let read = |text: &'static [u8]| schemas().read::<Vec<Unit>>(UNITS, text).map(|_| ());
+		match read(br#"Versioned(schema: "units", version: 4, data: [])"#) {
+			Err(SchemaError::TooNew {
+				found: 4,
+				current: 3,
+				..
+			}) => (),
+			other => panic!("newer data not rejected: {:?}", other),
+		}
+		match read(br#"Versioned(schema: "maps", version: 3, data: [])"#) {
+			Err(SchemaError::WrongSchema { found, .. }) => assert_eq!(found, "maps"),
+			other => panic!("wrong schema not rejected: {:?}", other),
+		}
+		let mut schemas = schemas();
+		schemas.migrations.remove(&(UNITS, 2));
+		match schemas.read::<Vec<Unit>>(UNITS, &b"[]"[..]) {
+			Err(SchemaError::MissingMigration {
+				found: 1,
+				missing: 2,
+				..
+			}) => (),
+			other => panic!("missing migration not rejected: {:?}", other.map(|_| ())),
+		}
+	}
+}
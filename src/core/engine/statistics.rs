@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+use crate::core::engine::io::{EngineIO, WriteMode};
+use crate::core::engine::schema::{self, SchemaError, Schemas};
+
+#[derive(Error, Debug)]
+pub enum StatisticsError<IO: EngineIO>
+where
+	IO::ReadError: 'static,
+	IO::WriteError: 'static,
+{
+	#[error("failed to open the player statistics")]
+	FileReadError {
+		source: IO::ReadError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to create the player statistics file")]
+	FileWriteError {
+		source: IO::WriteError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to read or write the player statistics")]
+	SchemaError {
+		source: SchemaError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+}
+
+/// How a finished game went, from the point of view of the local player.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameOutcome {
+	/// The kind of victory, such as `"domination"`, or `None` when the game was lost.
+	pub victory: Option<String>,
+	pub score: u32,
+	pub turns: u32,
+	/// Who the player played as.
+	pub played_as: String,
+}
+
+/// Statistics kept across every game played on this machine, for the hall of fame.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStatistics {
+	#[serde(default)]
+	pub games_played: u32,
+	/// How many games were won by each kind of victory.
+	#[serde(default)]
+	pub victories: BTreeMap<String, u32>,
+	/// The best games so far, best first.
+	#[serde(default)]
+	pub best_games: Vec<GameOutcome>,
+}
+
+impl PlayerStatistics {
+	/// How many of the best games are kept.
+	pub const BEST_GAMES: usize = 10;
+
+	/// Counts a finished game, returning its place in the best games if it made it in.
+	pub fn record_game(&mut self, outcome: GameOutcome) -> Option<usize> {
+		self.games_played += 1;
+		if let Some(victory) = &outcome.victory {
+			*self.victories.entry(victory.clone()).or_insert(0) += 1;
+		}
+		// Earlier games with the same score keep their place ahead of it
+		let place = self
+			.best_games
+			.iter()
+			.position(|best| best.score < outcome.score)
+			.unwrap_or(self.best_games.len());
+		if place >= Self::BEST_GAMES {
+			return None;
+		}
+		self.best_games.insert(place, outcome);
+		self.best_games.truncate(Self::BEST_GAMES);
+		Some(place)
+	}
+
+	pub fn games_won(&self) -> u32 {
+		self.victories.values().sum()
+	}
+
+	/// Reads the statistics from `path` in the user data directory, the caller decides what a
+	/// missing file means as the IO can't tell that apart from other failures.
+	pub fn load<IO: EngineIO>(
+		io: &mut IO,
+		schemas: &Schemas,
+		path: &Path,
+	) -> Result<PlayerStatistics, StatisticsError<IO>> {
+		let reader = io
+			.read(path)
+			.map_err(|source| StatisticsError::FileReadError { source })?;
+		schemas
+			.read(schema::PLAYER_STATISTICS, reader)
+			.map_err(|source| StatisticsError::SchemaError { source })
+	}
+
+	pub fn save<IO: EngineIO>(
+		&self,
+		io: &mut IO,
+		schemas: &Schemas,
+		path: &Path,
+	) -> Result<(), StatisticsError<IO>> {
+		let writer = io
+			.write(path, WriteMode::Truncate)
+			.map_err(|source| StatisticsError::FileWriteError { source })?;
+		schemas
+			.write(schema::PLAYER_STATISTICS, writer, self)
+			.map_err(|source| StatisticsError::SchemaError { source })
+	}
+}
+
+#[cfg(test)]
+mod statistics_tests {
+	use super::*;
+
+	fn outcome(victory: Option<&str>, score: u32) -> GameOutcome {
+		GameOutcome {
+			victory: victory.map(|v| v.to_owned()),
+			score,
+			turns: 100,
+			played_as: "test".to_owned(),
+		}
+	}
+
+	#[test]
+	fn games_are_counted_and_ranked() {
+		let mut statistics = PlayerStatistics::default();
+		assert_eq!(
+			statistics.record_game(outcome(Some("domination"), 50)),
+			Some(0)
+		);
+		assert_eq!(statistics.record_game(outcome(None, 80)), Some(0));
+		assert_eq!(
+			statistics.record_game(outcome(Some("science"), 50)),
+			Some(2)
+		);
+		assert_eq!(
+			statistics.record_game(outcome(Some("domination"), 60)),
+			Some(1)
+		);
+		assert_eq!(statistics.games_played, 4);
+		assert_eq!(statistics.games_won(), 3);
+		assert_eq!(statistics.victories["domination"], 2);
+		let scores: Vec<u32> = statistics.best_games.iter().map(|g| g.score).collect();
+		assert_eq!(scores, vec![80, 60, 50, 50]);
+		assert_eq!(
+			statistics.best_games[2].victory.as_deref(),
+			Some("domination")
+		);
+
+		for _ in 0..PlayerStatistics::BEST_GAMES {
+			statistics.record_game(outcome(None, 70));
+		}
+		assert_eq!(statistics.best_games.len(), PlayerStatistics::BEST_GAMES);
+		assert_eq!(statistics.record_game(outcome(None, 10)), None);
+		assert_eq!(statistics.games_played, 15);
+	}
+
+	#[test]
+	fn statistics_read_back() {
+		let mut statistics = PlayerStatistics::default();
+		statistics.record_game(outcome(Some("culture"), 42));
+		let schemas = Schemas::new();
+		let mut text = vec![];
+		schemas
+			.write(schema::PLAYER_STATISTICS, &mut text, &statistics)
+			.unwrap();
+		let read: PlayerStatistics = schemas.read(schema::PLAYER_STATISTICS, &text[..]).unwrap();
+		assert_eq!(read, statistics);
+	}
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use shipyard::*;
+use smallvec::SmallVec;
+
+/// Names for logical groups an entity belongs to, like `settler` or `predator`, so scenarios and
+/// commands can refer to them without knowing what components make them up.
+///
+/// Kept sorted and without duplicates, most entities only have a tag or two.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct Tags {
+	tags: SmallVec<[String; 2]>,
+}
+
+component_auto_loadable!(Tags);
+
+impl From<Vec<String>> for Tags {
+	fn from(tags: Vec<String>) -> Self {
+		Tags::new(tags)
+	}
+}
+
+impl From<Tags> for Vec<String> {
+	fn from(tags: Tags) -> Self {
+		tags.tags.into_vec()
+	}
+}
+
+impl Tags {
+	pub fn new(tags: impl IntoIterator<Item = impl Into<String>>) -> Tags {
+		let mut tags: SmallVec<[String; 2]> = tags.into_iter().map(Into::into).collect();
+		tags.sort();
+		tags.dedup();
+		Tags { tags }
+	}
+
+	pub fn has(&self, tag: &str) -> bool {
+		self.position(tag).is_ok()
+	}
+
+	/// Returns whether it was newly added.
+	pub fn insert(&mut self, tag: &str) -> bool {
+		match self.position(tag) {
+			Ok(_index) => false,
+			Err(index) => {
+				self.tags.insert(index, tag.to_owned());
+				true
+			}
+		}
+	}
+
+	/// Returns whether it had the tag.
+	pub fn remove(&mut self, tag: &str) -> bool {
+		match self.position(tag) {
+			Ok(index) => {
+				self.tags.remove(index);
+				true
+			}
+			Err(_index) => false,
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.tags.is_empty()
+	}
+
+	/// In sorted order.
+	pub fn iter(&self) -> impl Iterator<Item = &str> {
+		self.tags.iter().map(String::as_str)
+	}
+
+	fn position(&self, tag: &str) -> Result<usize, usize> {
+		self.tags.binary_search_by(|t| t.as_str().cmp(tag))
+	}
+}
+
+/// Every entity tagged `tag`.
+pub fn query_entities_with_tag(tags: &View<Tags>, tag: &str) -> Vec<EntityId> {
+	tags.iter()
+		.with_id()
+		.filter(|(_entity, tags)| tags.has(tag))
+		.map(|(entity, _tags)| entity)
+		.collect()
+}
+
+/// Every entity tagged with all of `all`, such as `["animal", "predator"]`.
+pub fn query_entities_with_tags(tags: &View<Tags>, all: &[&str]) -> Vec<EntityId> {
+	tags.iter()
+		.with_id()
+		.filter(|(_entity, tags)| all.iter().all(|tag| tags.has(tag)))
+		.map(|(entity, _tags)| entity)
+		.collect()
+}
+
+/// Tags `entity` with `tag`, giving it the component if it has none yet. Returns whether it was
+/// newly tagged.
+pub fn add_tag(
+	entities: &EntitiesView,
+	tags: &mut ViewMut<Tags>,
+	entity: EntityId,
+	tag: &str,
+) -> bool {
+	if let Ok(existing) = (&mut *tags).get(entity) {
+		return existing.insert(tag);
+	}
+	entities.add_component(tags, Tags::new(vec![tag]), entity);
+	true
+}
+
+/// Takes `tag` off `entity`, and the component too once it has no tags left. Returns whether it
+/// was tagged.
+pub fn remove_tag(tags: &mut ViewMut<Tags>, entity: EntityId, tag: &str) -> bool {
+	let (removed, empty) = match (&mut *tags).get(entity) {
+		Ok(existing) => (existing.remove(tag), existing.is_empty()),
+		Err(_) => return false,
+	};
+	if empty {
+		tags.remove(entity);
+	}
+	removed
+}
+
+#[cfg(test)]
+mod tags_tests {
+	use super::*;
+
+	#[test]
+	fn sorted_without_duplicates() {
+		let mut tags = Tags::new(vec!["settler", "land", "settler"]);
+		assert_eq!(tags.iter().collect::<Vec<_>>(), ["land", "settler"]);
+		assert!(tags.has("settler"));
+		assert!(!tags.has("sea"));
+		assert!(tags.insert("civilian"));
+		assert!(!tags.insert("land"));
+		assert_eq!(
+			tags.iter().collect::<Vec<_>>(),
+			["civilian", "land", "settler"]
+		);
+		assert!(tags.remove("land"));
+		assert!(!tags.remove("land"));
+		assert_eq!(tags.iter().collect::<Vec<_>>(), ["civilian", "settler"]);
+	}
+
+	#[test]
+	fn reads_as_a_list() {
+		let tags: Tags = ron::de::from_str(r#"["predator", "animal"]"#).unwrap();
+		assert_eq!(tags, Tags::new(vec!["animal", "predator"]));
+		assert_eq!(
+			ron::ser::to_string(&tags).unwrap(),
+			r#"["animal","predator"]"#
+		);
+	}
+}
@@ -0,0 +1,283 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A 16.16 fixed point number, for simulation math that has to come out the same on every
+/// machine, such as for lockstep games and replays. Floats stay in the renderer.
+///
+/// It covers about -32768 to 32768 in steps of 1/65536. Going outside that panics in debug
+/// builds like the integer math it is.
+///
+/// ```
+/// # use over_simple_game_1::core::fixed::Fixed;
+/// let quarter = Fixed::from_percent(25);
+/// assert_eq!(quarter * Fixed::from_int(8), Fixed::from_int(2));
+/// assert_eq!((Fixed::ONE / Fixed::from_int(4)).to_f32(), 0.25);
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i32);
+
+impl Fixed {
+	pub const FRACTION_BITS: u32 = 16;
+	pub const ZERO: Fixed = Fixed(0);
+	pub const ONE: Fixed = Fixed(1 << Fixed::FRACTION_BITS);
+	pub const MIN: Fixed = Fixed(i32::MIN);
+	pub const MAX: Fixed = Fixed(i32::MAX);
+
+	pub const fn from_bits(bits: i32) -> Fixed {
+		Fixed(bits)
+	}
+
+	pub const fn to_bits(self) -> i32 {
+		self.0
+	}
+
+	pub const fn from_int(value: i16) -> Fixed {
+		Fixed((value as i32) << Fixed::FRACTION_BITS)
+	}
+
+	/// `numerator / denominator` rounded to the nearest step.
+	pub fn from_ratio(numerator: i32, denominator: i32) -> Fixed {
+		assert!(denominator != 0, "fixed point ratio over zero");
+		let scaled = i64::from(numerator) << Fixed::FRACTION_BITS;
+		let denominator = i64::from(denominator);
+		// Rounds halves away from zero the same on every platform
+		let half = denominator.abs() / 2;
+		let rounded = if (scaled < 0) == (denominator < 0) {
+			(scaled + half * denominator.signum()) / denominator
+		} else {
+			(scaled - half * denominator.signum()) / denominator
+		};
+		debug_assert!(
+			rounded >= i64::from(i32::MIN) && rounded <= i64::from(i32::MAX),
+			"fixed point ratio overflow"
+		);
+		Fixed(rounded as i32)
+	}
+
+	pub fn from_percent(percent: i32) -> Fixed {
+		Fixed::from_ratio(percent, 100)
+	}
+
+	/// The nearest step to `value`, for loading numbers written as floats. Scaling by a power of
+	/// two is exact so this is the same everywhere, out of range values saturate.
+	#[allow(clippy::float_arithmetic)]
+	pub fn from_f64(value: f64) -> Fixed {
+		Fixed((value * f64::from(Fixed::ONE.0)).round() as i32)
+	}
+
+	/// For drawing only, never feed this back into the simulation.
+	#[allow(clippy::float_arithmetic)]
+	pub fn to_f32(self) -> f32 {
+		self.0 as f32 / Fixed::ONE.0 as f32
+	}
+
+	/// Exact, every step fits in a `f64`.
+	#[allow(clippy::float_arithmetic)]
+	pub fn to_f64(self) -> f64 {
+		f64::from(self.0) / f64::from(Fixed::ONE.0)
+	}
+
+	/// The largest whole number not above this one.
+	pub fn floor(self) -> i32 {
+		self.0 >> Fixed::FRACTION_BITS
+	}
+
+	/// The nearest whole number, halves rounding up.
+	pub fn round(self) -> i32 {
+		(self.0 + (Fixed::ONE.0 >> 1)) >> Fixed::FRACTION_BITS
+	}
+
+	/// `count` times this, rounded down, for scaling counts too large to be a `Fixed`.
+	pub fn mul_floor(self, count: i64) -> i64 {
+		(i64::from(self.0) * count) >> Fixed::FRACTION_BITS
+	}
+
+	pub fn fraction(self) -> Fixed {
+		Fixed(self.0 & (Fixed::ONE.0 - 1))
+	}
+
+	pub fn abs(self) -> Fixed {
+		Fixed(self.0.abs())
+	}
+
+	pub fn saturating_add(self, other: Fixed) -> Fixed {
+		Fixed(self.0.saturating_add(other.0))
+	}
+
+	pub fn saturating_sub(self, other: Fixed) -> Fixed {
+		Fixed(self.0.saturating_sub(other.0))
+	}
+}
+
+impl Add for Fixed {
+	type Output = Fixed;
+
+	fn add(self, other: Fixed) -> Fixed {
+		Fixed(self.0 + other.0)
+	}
+}
+
+impl AddAssign for Fixed {
+	fn add_assign(&mut self, other: Fixed) {
+		self.0 += other.0;
+	}
+}
+
+impl Sub for Fixed {
+	type Output = Fixed;
+
+	fn sub(self, other: Fixed) -> Fixed {
+		Fixed(self.0 - other.0)
+	}
+}
+
+impl SubAssign for Fixed {
+	fn sub_assign(&mut self, other: Fixed) {
+		self.0 -= other.0;
+	}
+}
+
+impl Neg for Fixed {
+	type Output = Fixed;
+
+	fn neg(self) -> Fixed {
+		Fixed(-self.0)
+	}
+}
+
+impl Mul for Fixed {
+	type Output = Fixed;
+
+	/// Rounded to the nearest step, halves rounding up.
+	fn mul(self, other: Fixed) -> Fixed {
+		let product = i64::from(self.0) * i64::from(other.0);
+		let rounded = (product + (1 << (Fixed::FRACTION_BITS - 1))) >> Fixed::FRACTION_BITS;
+		debug_assert!(
+			rounded >= i64::from(i32::MIN) && rounded <= i64::from(i32::MAX),
+			"fixed point multiply overflow"
+		);
+		Fixed(rounded as i32)
+	}
+}
+
+impl Div for Fixed {
+	type Output = Fixed;
+
+	/// Rounded toward zero, panics on dividing by zero.
+	fn div(self, other: Fixed) -> Fixed {
+		let quotient = (i64::from(self.0) << Fixed::FRACTION_BITS) / i64::from(other.0);
+		debug_assert!(
+			quotient >= i64::from(i32::MIN) && quotient <= i64::from(i32::MAX),
+			"fixed point divide overflow"
+		);
+		Fixed(quotient as i32)
+	}
+}
+
+impl fmt::Debug for Fixed {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Fixed({})", self.to_f64())
+	}
+}
+
+impl fmt::Display for Fixed {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Display::fmt(&self.to_f64(), f)
+	}
+}
+
+/// Written as a plain number so data files stay readable.
+impl Serialize for Fixed {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_f64(self.to_f64())
+	}
+}
+
+impl<'de> Deserialize<'de> for Fixed {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let value = f64::deserialize(deserializer)?;
+		if value.is_nan() || value < Fixed::MIN.to_f64() || value > Fixed::MAX.to_f64() {
+			return Err(serde::de::Error::custom(format!(
+				"{} is out of the fixed point range",
+				value
+			)));
+		}
+		Ok(Fixed::from_f64(value))
+	}
+}
+
+#[cfg(test)]
+mod fixed_tests {
+	use super::*;
+	use proptest::prelude::*;
+
+	#[test]
+	fn ratios_round_to_nearest() {
+		assert_eq!(Fixed::from_ratio(1, 2), Fixed::from_bits(1 << 15));
+		assert_eq!(Fixed::from_ratio(-1, 2), Fixed::from_bits(-(1 << 15)));
+		assert_eq!(Fixed::from_ratio(1, -2), Fixed::from_bits(-(1 << 15)));
+		// 65536 / 3 = 21845.33
+		assert_eq!(Fixed::from_ratio(1, 3).to_bits(), 21845);
+		// 2 * 65536 / 3 = 43690.67
+		assert_eq!(Fixed::from_ratio(2, 3).to_bits(), 43691);
+		assert_eq!(Fixed::from_percent(100), Fixed::ONE);
+	}
+
+	#[test]
+	fn ratios_reach_both_ends_of_the_range() {
+		assert_eq!(Fixed::from_ratio(-32768, 1), Fixed::MIN);
+		assert_eq!(Fixed::from_ratio(i32::MAX, 65536), Fixed::MAX);
+		assert_eq!(Fixed::from_ratio(40000, 2), Fixed::from_int(20000));
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "fixed point ratio overflow")]
+	fn out_of_range_ratios_panic() {
+		Fixed::from_ratio(40000, 1);
+	}
+
+	#[test]
+	fn whole_parts() {
+		let value = Fixed::from_ratio(7, 4);
+		assert_eq!(value.floor(), 1);
+		assert_eq!(value.round(), 2);
+		assert_eq!(value.fraction(), Fixed::from_ratio(3, 4));
+		assert_eq!((-value).floor(), -2);
+		assert_eq!((-value).round(), -2);
+		assert_eq!(Fixed::from_ratio(-1, 2).round(), 0);
+		assert_eq!(Fixed::from_percent(5).mul_floor(100_000), 5_000);
+		assert_eq!(Fixed::from_ratio(1, 4).mul_floor(65_536 * 3), 49_152);
+	}
+
+	#[test]
+	fn serializes_as_a_number() {
+		let value = Fixed::from_percent(25);
+		assert_eq!(ron::ser::to_string(&value).unwrap(), "0.25");
+		assert_eq!(ron::de::from_str::<Fixed>("0.25").unwrap(), value);
+		assert_eq!(ron::de::from_str::<Fixed>("3").unwrap(), Fixed::from_int(3));
+		assert!(ron::de::from_str::<Fixed>("40000.0").is_err());
+	}
+
+	proptest!(
+		#[test]
+		fn ints_multiply_exactly(a in -181i16..181, b in -181i16..181) {
+			prop_assert_eq!(Fixed::from_int(a) * Fixed::from_int(b), Fixed::from_int(a * b));
+		}
+
+		#[test]
+		fn dividing_undoes_multiplying(a in -300i16..300, b in 1i16..100) {
+			let a = Fixed::from_int(a);
+			let b = Fixed::from_int(b);
+			prop_assert_eq!(a * b / b, a);
+		}
+
+		#[test]
+		fn bits_round_trip_through_f64(bits in any::<i32>()) {
+			let value = Fixed::from_bits(bits);
+			prop_assert_eq!(Fixed::from_f64(value.to_f64()), value);
+		}
+	);
+}
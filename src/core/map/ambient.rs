@@ -0,0 +1,234 @@
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+use crate::core::engine::io::EngineIO;
+use crate::core::engine::schema::{self, SchemaError, Schemas};
+use crate::core::structures::typed_index_map::{
+	TypedIndexMap, TypedIndexMapError, TypedIndexMapIndex,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum MapAmbientsMap {}
+
+pub type MapAmbientIdx = TypedIndexMapIndex<MapAmbientsMap, u8>;
+
+fn serde_background() -> [f32; 4] {
+	[0.0, 0.0, 0.0, 1.0]
+}
+
+fn serde_white() -> [f32; 4] {
+	[1.0, 1.0, 1.0, 1.0]
+}
+
+fn serde_scale() -> f32 {
+	1.0
+}
+
+/// Scattered points of light behind everything else, such as for space.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Starfield {
+	/// Stars per screen, the same stars repeat past its edges.
+	pub count: u16,
+	#[serde(default = "serde_white")]
+	pub color: [f32; 4],
+	/// How far the stars move as the view moves, `0.0` stays still and `1.0` moves with the map.
+	#[serde(default)]
+	pub parallax: f32,
+	#[serde(default)]
+	pub seed: u64,
+}
+
+/// An image repeated across the screen behind the tiles, drawn in the order listed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ParallaxLayer {
+	/// Image name in the `backgrounds` resources, without the extension.
+	pub image: String,
+	/// How far the layer moves as the view moves, `0.0` stays still and `1.0` moves with the map.
+	pub parallax: f32,
+	/// Screen pixels per image pixel, before UI scaling.
+	#[serde(default = "serde_scale")]
+	pub scale: f32,
+	#[serde(default = "serde_white")]
+	pub tint: [f32; 4],
+}
+
+/// The look of a map outside of its tiles, so a planet, a dungeon and space feel different.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MapAmbient {
+	pub name: String,
+	/// Color behind everything, RGBA.
+	#[serde(default = "serde_background")]
+	pub background: [f32; 4],
+	#[serde(default)]
+	pub starfield: Option<Starfield>,
+	#[serde(default)]
+	pub layers: Vec<ParallaxLayer>,
+	/// Multiplied into the color of the tiles and what is on them, white leaves them unchanged.
+	#[serde(default = "serde_white")]
+	pub light_tint: [f32; 4],
+}
+
+#[derive(Debug)]
+pub struct MapAmbients {
+	pub ambients: TypedIndexMap<MapAmbientsMap, String, MapAmbient, u8>,
+}
+
+#[derive(Error, Debug)]
+pub enum MapAmbientsError<IO: EngineIO>
+where
+	IO::ReadError: 'static,
+{
+	#[error("failed to load map ambient information file")]
+	FileReadError {
+		source: IO::ReadError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to parse map ambient information")]
+	FileParseError {
+		source: SchemaError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error(
+		"map ambients already filled to the max of {} when inserting {}",
+		MapAmbientIdx::MAX, .0.name
+	)]
+	MapAmbientsFilled(MapAmbient),
+
+	#[error("map ambients have already been loaded")]
+	MapAmbientsAlreadyFilled(),
+
+	#[error("map ambient name is empty")]
+	EmptyMapAmbientName(MapAmbient),
+
+	#[error("attempted to insert a duplicate map ambient name: {}", .0.name)]
+	DuplicateMapAmbientName(MapAmbient),
+}
+
+impl MapAmbients {
+	pub(crate) fn new() -> MapAmbients {
+		MapAmbients {
+			ambients: TypedIndexMap::new(),
+		}
+	}
+
+	fn add_ambient<IO: EngineIO>(
+		&mut self,
+		ambient: MapAmbient,
+	) -> Result<MapAmbientIdx, MapAmbientsError<IO>> {
+		if ambient.name.is_empty() {
+			return Err(MapAmbientsError::EmptyMapAmbientName(ambient));
+		}
+		if self.ambients.contains_key(&ambient.name) {
+			return Err(MapAmbientsError::DuplicateMapAmbientName(ambient));
+		}
+
+		let (index, old_value) = self
+			.ambients
+			.insert_full(ambient.name.clone(), ambient)
+			.map_err(|e| match e {
+				TypedIndexMapError::TypedIndexMapFull(_max, _key, ambient) => {
+					MapAmbientsError::MapAmbientsFilled(ambient)
+				}
+			})?;
+		assert!(old_value.is_none());
+
+		Ok(index)
+	}
+
+	pub(crate) fn load_ambients<IO: EngineIO>(
+		&mut self,
+		io: &mut IO,
+		schemas: &Schemas,
+	) -> Result<(), MapAmbientsError<IO>> {
+		if !self.ambients.is_empty() {
+			return Err(MapAmbientsError::MapAmbientsAlreadyFilled());
+		}
+
+		// Ambients are optional, without them every map draws the same as one with no ambient
+		let path = std::path::Path::new("maps/ambients.ron");
+		if !io.exists(path) {
+			return Ok(());
+		}
+		let reader = io
+			.read(path)
+			.map_err(|source| MapAmbientsError::FileReadError { source })?;
+
+		let ambients: Vec<MapAmbient> = schemas
+			.read(schema::MAP_AMBIENTS, reader)
+			.map_err(|source| MapAmbientsError::FileParseError { source })?;
+
+		for ambient in ambients {
+			self.add_ambient(ambient)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod ambient_tests {
+	use super::*;
+	use crate::core::map::generator::test_io::DummyIO;
+
+	#[test]
+	fn left_out_fields_take_their_defaults() {
+		let mut io = DummyIO {
+			data: b"[
+				MapAmbient(name: \"planet\"),
+				MapAmbient(
+					name: \"space\",
+					background: (0.0, 0.0, 0.05, 1.0),
+					starfield: Some(Starfield(count: 200)),
+					layers: [ParallaxLayer(image: \"nebula\", parallax: 0.1)],
+				),
+			]",
+		};
+		let mut ambients = MapAmbients::new();
+		ambients.load_ambients(&mut io, &Schemas::new()).unwrap();
+		let planet = ambients.ambients.get("planet").unwrap();
+		assert_eq!(planet.background, [0.0, 0.0, 0.0, 1.0]);
+		assert_eq!(planet.light_tint, [1.0, 1.0, 1.0, 1.0]);
+		assert!(planet.starfield.is_none());
+		assert!(planet.layers.is_empty());
+		let space = ambients.ambients.get("space").unwrap();
+		let starfield = space.starfield.as_ref().unwrap();
+		assert_eq!(starfield.count, 200);
+		assert_eq!(starfield.parallax, 0.0);
+		assert_eq!(space.layers[0].scale, 1.0);
+		assert_eq!(space.layers[0].tint, [1.0, 1.0, 1.0, 1.0]);
+	}
+
+	#[test]
+	fn a_missing_file_is_no_ambients() {
+		let mut ambients = MapAmbients::new();
+		ambients
+			.load_ambients(&mut DummyIO::default(), &Schemas::new())
+			.unwrap();
+		assert!(ambients.ambients.is_empty());
+	}
+
+	#[test]
+	fn duplicate_and_empty_names_are_rejected() {
+		let mut io = DummyIO {
+			data: b"[MapAmbient(name: \"space\"), MapAmbient(name: \"space\")]",
+		};
+		let mut ambients = MapAmbients::new();
+		match ambients.load_ambients(&mut io, &Schemas::new()) {
+			Err(MapAmbientsError::DuplicateMapAmbientName(a)) => assert_eq!(a.name, "space"),
+			other => panic!("duplicate name not rejected: {:?}", other.map(|_| ())),
+		}
+		let mut ambients = MapAmbients::new();
+		match ambients.add_ambient::<DummyIO>(MapAmbient {
+			name: String::new(),
+			background: serde_background(),
+			starfield: None,
+			layers: vec![],
+			light_tint: serde_white(),
+		}) {
+			Err(MapAmbientsError::EmptyMapAmbientName(_)) => (),
+			other => panic!("empty name not rejected: {:?}", other.map(|_| ())),
+		}
+	}
+}
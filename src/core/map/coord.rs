@@ -1,3 +1,5 @@
+use std::fmt::Debug;
+use std::hash::Hash;
 use std::ops::{Add, Neg, Sub};
 
 /// Hex Coordinates, cubic notation but axial stored.
@@ -32,13 +34,38 @@ impl Coord {
 	/// assert_eq!(coord.q(), 0);
 	/// assert_eq!(coord.r(), 1);
 	/// ```
-	pub fn new_axial(q: u8, r: u8) -> Coord {
+	#[inline]
+	pub const fn new_axial(q: u8, r: u8) -> Coord {
 		Coord(q, r)
 	}
 
 	pub const CENTER_TO_POINT: f32 = 0.57735026; // 0.5773502691896258; //0.5/(TAU/12.0).cos(); // `cos` is not const capable for some reason...;
 	const SQRT3: f32 = 1.7320508; // 1.732050807568877; //3.0f32.sqrt(); // `sqrt` is not const capable either, why?!
 
+	// `to_linear` as a matrix over axial q/r, `CENTER_TO_POINT * SQRT3` is 1.0 so the x row folds
+	// down to `q + r/2` and only the y row needs a real constant.
+	const LINEAR_X_PER_Q: f32 = 1.0;
+	const LINEAR_X_PER_R: f32 = 0.5;
+	const LINEAR_Y_PER_R: f32 = Coord::CENTER_TO_POINT * 1.5;
+
+	/// Corners of a hex in linear space relative to its center, clockwise from the top point.
+	pub const LINEAR_CORNERS: [(f32, f32); 6] = [
+		(0.0, -Coord::CENTER_TO_POINT),
+		(0.5, -Coord::CENTER_TO_POINT * 0.5),
+		(0.5, Coord::CENTER_TO_POINT * 0.5),
+		(0.0, Coord::CENTER_TO_POINT),
+		(-0.5, Coord::CENTER_TO_POINT * 0.5),
+		(-0.5, -Coord::CENTER_TO_POINT * 0.5),
+	];
+
+	#[inline]
+	fn axial_to_linear(q: f32, r: f32) -> (f32, f32) {
+		(
+			Self::LINEAR_X_PER_Q * q + Self::LINEAR_X_PER_R * r,
+			Self::LINEAR_Y_PER_R * r,
+		)
+	}
+
 	/// Uses linear (pixel) coordinate to create a new `Coord`
 	///
 	/// Currently this just treats the hex tiles like they are offset rectangles,
@@ -56,11 +83,12 @@ impl Coord {
 	/// let (x, y) = Coord::to_linear(Coord::new_axial(7, 28));
 	/// assert_eq!(Coord::from_linear(x, y), Coord::new_axial(7, 28));
 	/// ```
+	#[inline]
 	pub fn from_linear(x: f32, y: f32) -> Coord {
-		let s3 = 3.0f32.sqrt();
-		let segment = (x + s3 * y + 1.0).floor();
+		let s3y = Self::SQRT3 * y;
+		let segment = (x + s3y + 1.0).floor();
 		let q = (((2.0 * x + 1.0).floor() + segment) / 3.0).floor();
-		let r = ((segment + (-x + s3 * y + 1.0).floor()) / 3.0).floor();
+		let r = ((segment + (-x + s3y + 1.0).floor()) / 3.0).floor();
 		Coord::new_axial((q - r) as i16 as u8, r as i16 as u8)
 	}
 
@@ -82,19 +110,18 @@ impl Coord {
 	/// assert_approx_eq!(c02.0, 1.0);
 	/// assert_approx_eq!(c02.1, 1.7320508);
 	/// ```
+	#[inline]
 	pub fn to_linear(self) -> (f32, f32) {
-		let q = self.0 as f32;
-		let r = self.1 as f32;
-		let x = Self::CENTER_TO_POINT * (Self::SQRT3 * q + Self::SQRT3 / 2.0 * r);
-		let y = Self::CENTER_TO_POINT * (3.0 / 2.0 * r);
-		(x, y)
+		Self::axial_to_linear(self.0 as f32, self.1 as f32)
 	}
 
-	pub fn q(&self) -> u8 {
+	#[inline]
+	pub const fn q(&self) -> u8 {
 		self.0
 	}
 
-	pub fn r(&self) -> u8 {
+	#[inline]
+	pub const fn r(&self) -> u8 {
 		self.1
 	}
 
@@ -102,6 +129,51 @@ impl Coord {
 		(self.q(), self.r())
 	}
 
+	/// As `(column, row)` in the "odd-r" offset layout that editors like Tiled use for pointy
+	/// topped maps, where each odd row is pushed half a tile right of the even row above it.
+	///
+	/// ```
+	/// # use over_simple_game_1::core::map::coord::Coord;
+	/// assert_eq!(Coord::new_axial(3, 0).to_offset_odd_r(), (3, 0));
+	/// assert_eq!(Coord::new_axial(3, 3).to_offset_odd_r(), (4, 3));
+	/// assert_eq!(Coord::from_offset_odd_r(4, 3), Some(Coord::new_axial(3, 3)));
+	/// // Left of the axial origin's column
+	/// assert_eq!(Coord::from_offset_odd_r(0, 2), None);
+	/// ```
+	pub fn to_offset_odd_r(self) -> (u16, u8) {
+		(self.0 as u16 + self.1 as u16 / 2, self.1)
+	}
+
+	/// From the "odd-r" offset `column` and `row`, `None` when that is outside of axial 0..=255.
+	pub fn from_offset_odd_r(column: u16, row: u8) -> Option<Coord> {
+		Self::from_offset(column, row, row as u16 / 2)
+	}
+
+	/// As `(column, row)` in the "even-r" offset layout, where each even row is pushed half a
+	/// tile right of the odd row above it.
+	///
+	/// ```
+	/// # use over_simple_game_1::core::map::coord::Coord;
+	/// assert_eq!(Coord::new_axial(3, 3).to_offset_even_r(), (5, 3));
+	/// assert_eq!(Coord::from_offset_even_r(5, 3), Some(Coord::new_axial(3, 3)));
+	/// ```
+	pub fn to_offset_even_r(self) -> (u16, u8) {
+		(self.0 as u16 + (self.1 as u16 + 1) / 2, self.1)
+	}
+
+	/// From the "even-r" offset `column` and `row`, `None` when that is outside of axial 0..=255.
+	pub fn from_offset_even_r(column: u16, row: u8) -> Option<Coord> {
+		Self::from_offset(column, row, (row as u16 + 1) / 2)
+	}
+
+	fn from_offset(column: u16, row: u8, shift: u16) -> Option<Coord> {
+		let q = column.checked_sub(shift)?;
+		if q > 255 {
+			return None;
+		}
+		Some(Coord::new_axial(q as u8, row))
+	}
+
 	pub fn x(&self) -> i16 {
 		self.0 as i16
 	}
@@ -159,10 +231,77 @@ impl Coord {
 		)
 	}
 
+	/// Deterministic pseudo-random value for this coordinate, for cosmetic choices that must
+	/// come out the same every time the map is drawn without storing anything per tile.
+	///
+	/// ```
+	/// # use over_simple_game_1::core::map::coord::Coord;
+	/// let coord = Coord::new_axial(3, 7);
+	/// assert_eq!(coord.hash_with_seed(42), coord.hash_with_seed(42));
+	/// assert_ne!(coord.hash_with_seed(42), coord.hash_with_seed(43));
+	/// ```
+	pub fn hash_with_seed(self, seed: u64) -> u64 {
+		// splitmix64 finalizer over the packed coordinate and seed
+		let mut x =
+			seed ^ (((self.0 as u64) << 8) | self.1 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+		x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		x ^ (x >> 31)
+	}
+
 	// pub fn as_coord_orientation(self) -> CoordOrientation {
 	// 	CoordOrientation(self.0, self.1)
 	// }
 
+	/// Turned `steps` sixths of a turn around `center`, see `CoordOrientation::rotate_around`.
+	///
+	/// `None` when it lands outside of axial 0..=255, it knows nothing of maps so check the result
+	/// against the map too.
+	///
+	/// ```
+	/// # use over_simple_game_1::core::map::coord::Coord;
+	/// let center = Coord::new_axial(4, 4);
+	/// assert_eq!(Coord::new_axial(6, 4).rotate_about(center, 1), Some(Coord::new_axial(4, 6)));
+	/// assert_eq!(Coord::new_axial(6, 4).rotate_about(center, -2), Some(Coord::new_axial(4, 2)));
+	/// ```
+	pub fn rotate_about(self, center: Coord, steps: i8) -> Option<Coord> {
+		let mut offset = self.cubic_offset_from(center);
+		for _ in 0..steps.rem_euclid(6) {
+			let (x, y, z) = offset;
+			offset = (-z, -x, -y);
+		}
+		center.at_cubic_offset(offset)
+	}
+
+	/// Mirrored across `axis` running through `center`, see `CoordOrientation::reflect`. `None`
+	/// when it lands outside of axial 0..=255, like `rotate_about`.
+	pub fn reflect_about(self, center: Coord, axis: CoordAxis) -> Option<Coord> {
+		let (x, y, z) = self.cubic_offset_from(center);
+		let reflected = match axis {
+			CoordAxis::X => (x, z, y),
+			CoordAxis::Y => (z, y, x),
+			CoordAxis::Z => (y, x, z),
+		};
+		center.at_cubic_offset(reflected)
+	}
+
+	/// Cubic offset from `center` without wrapping, unlike subtracting.
+	fn cubic_offset_from(self, center: Coord) -> (i16, i16, i16) {
+		let x = self.0 as i16 - center.0 as i16;
+		let z = self.1 as i16 - center.1 as i16;
+		(x, -x - z, z)
+	}
+
+	fn at_cubic_offset(self, (x, _y, z): (i16, i16, i16)) -> Option<Coord> {
+		let q = self.0 as i16 + x;
+		let r = self.1 as i16 + z;
+		if (0..=255).contains(&q) && (0..=255).contains(&r) {
+			Some(Coord::new_axial(q as u8, r as u8))
+		} else {
+			None
+		}
+	}
+
 	pub fn iter_neighbors_ring(self, distance: u8) -> CoordRingIterator {
 		CoordRingIterator::new(self, distance)
 	}
@@ -170,6 +309,45 @@ impl Coord {
 	pub fn iter_neighbors(self, distance: u8) -> CoordNeighborIterator {
 		CoordNeighborIterator::new(self, distance)
 	}
+
+	/// This coord and then every ring around it out to `max_distance` in the same order as
+	/// `iter_neighbors_ring`, so the first that fits in a search is always the same nearest one.
+	///
+	/// Unlike `iter_neighbors` it doesn't wrap past axial 0 or 255 but skips those coords, it knows
+	/// nothing of maps so check each against the map too. Rings past 127 are not walked.
+	///
+	/// ```
+	/// # use over_simple_game_1::core::map::coord::Coord;
+	/// let taken = [Coord::new_axial(4, 4), Coord::new_axial(5, 4)];
+	/// let free = Coord::new_axial(4, 4)
+	/// 	.iter_spiral(3)
+	/// 	.find(|coord| !taken.contains(coord));
+	/// assert_eq!(free, Some(Coord::new_axial(4, 5)));
+	/// assert_eq!(Coord::new_axial(0, 0).iter_spiral(1).count(), 3);
+	/// ```
+	pub fn iter_spiral(self, max_distance: u8) -> CoordSpiralIterator {
+		CoordSpiralIterator::new(self, max_distance)
+	}
+
+	/// Every hex on the straight line from here to `other`, both ends included, each a neighbor
+	/// of the one before. Lines running exactly between two hexes always take the same side.
+	///
+	/// ```
+	/// # use over_simple_game_1::core::map::coord::Coord;
+	/// let line: Vec<Coord> = Coord::new_axial(0, 0).iter_line_to(Coord::new_axial(3, 0)).collect();
+	/// assert_eq!(line.len(), 4);
+	/// assert_eq!(line[2], Coord::new_axial(2, 0));
+	/// ```
+	pub fn iter_line_to(self, other: Coord) -> CoordLineIterator {
+		CoordLineIterator::new(self, other)
+	}
+
+	/// Every hex overlapping the linear rect from `min` to `max`, row by row, as axial `(q, r)`
+	/// with its linear center. These are not bounded to any map so may be negative or past 255,
+	/// see `TileMap::iter_linear_rect` for the tiles.
+	pub fn iter_linear_rect(min: (f32, f32), max: (f32, f32)) -> CoordLinearRectIterator {
+		CoordLinearRectIterator::new(min, max)
+	}
 }
 
 impl Add<CoordOrientation> for Coord {
@@ -205,6 +383,192 @@ impl Sub<Coord> for Coord {
 	}
 }
 
+/// `Coord` with 16 bits for each of q and r, for maps past 256 tiles a side. Laid out the same
+/// way so every `Coord` widens into it, `Coord` stays the compact form for the maps that fit.
+///
+/// ```
+/// # use over_simple_game_1::core::map::coord::{Coord, Coord32};
+/// let coord = Coord::new_axial(200, 7);
+/// assert_eq!(Coord32::from(coord), Coord32::new_axial(200, 7));
+/// assert_eq!(Coord32::from(coord).to_coord(), Some(coord));
+/// assert_eq!(Coord32::new_axial(300, 7).to_coord(), None);
+/// ```
+#[derive(Clone, Copy, Default, Debug, Hash, PartialOrd, PartialEq, Ord, Eq)]
+pub struct Coord32(u16, u16);
+
+impl Coord32 {
+	#[inline]
+	pub const fn new_axial(q: u16, r: u16) -> Coord32 {
+		Coord32(q, r)
+	}
+
+	#[inline]
+	pub const fn q(&self) -> u16 {
+		self.0
+	}
+
+	#[inline]
+	pub const fn r(&self) -> u16 {
+		self.1
+	}
+
+	pub fn to_axial_tuple(&self) -> (u16, u16) {
+		(self.q(), self.r())
+	}
+
+	pub fn x(&self) -> i32 {
+		self.0 as i32
+	}
+
+	pub fn y(&self) -> i32 {
+		-self.x() - self.z()
+	}
+
+	pub fn z(&self) -> i32 {
+		self.1 as i32
+	}
+
+	pub fn to_cubic_tuple(&self) -> (i32, i32, i32) {
+		(self.x(), self.y(), self.z())
+	}
+
+	/// The same tile as a `Coord`, if it is within the first 256 on both axes.
+	pub fn to_coord(self) -> Option<Coord> {
+		if self.0 > 255 || self.1 > 255 {
+			return None;
+		}
+		Some(Coord::new_axial(self.0 as u8, self.1 as u8))
+	}
+
+	/// See `Coord::idx`.
+	pub fn idx(self, max_x: u16, max_z: u16, wraps_x: bool) -> Option<usize> {
+		if self.1 > max_z || (!wraps_x && self.0 > max_x) {
+			return None;
+		}
+		let x = self.0 as usize % (max_x as usize + 1);
+		let z = self.1 as usize;
+		Some((z * (max_x as usize + 1)) + x)
+	}
+
+	/// See `Coord::offset_by`.
+	pub fn offset_by(
+		self,
+		offset: CoordOrientation,
+		width: u16,
+		height: u16,
+		wraps_x: bool,
+	) -> Option<Coord32> {
+		let columns = width as i32 + 1;
+		let mut q = self.0 as i32 + offset.0 as i32;
+		let r = self.1 as i32 + offset.1 as i32;
+		if r < 0 || r > height as i32 {
+			return None;
+		}
+		if wraps_x {
+			q = q.rem_euclid(columns);
+		} else if q < 0 || q >= columns {
+			return None;
+		}
+		Some(Coord32::new_axial(q as u16, r as u16))
+	}
+
+	/// Steps between the two, without going around any seam.
+	pub fn distance_to(self, other: Coord32) -> u32 {
+		let (dx, dy, dz) = (
+			self.x() - other.x(),
+			self.y() - other.y(),
+			self.z() - other.z(),
+		);
+		dx.abs().max(dy.abs()).max(dz.abs()) as u32
+	}
+
+	/// See `Coord::to_linear`, `HexOrientation::axial_to_linear` for the other orientations.
+	#[inline]
+	pub fn to_linear(self) -> (f32, f32) {
+		Coord::axial_to_linear(self.0 as f32, self.1 as f32)
+	}
+}
+
+impl From<Coord> for Coord32 {
+	fn from(coord: Coord) -> Coord32 {
+		Coord32::new_axial(coord.q() as u16, coord.r() as u16)
+	}
+}
+
+/// A hex coordinate a `TileMap` is addressed by, `Coord` for maps up to 256 tiles a side and
+/// `Coord32` past that. The map keeps its largest q and r as the coordinate's `Extent`.
+pub trait TileCoord: Copy + Eq + Hash + Debug {
+	type Extent: Copy + Debug + Into<u32>;
+
+	/// Axial `(q, r)`, widened for arithmetic.
+	fn axial_u32(self) -> (u32, u32);
+
+	/// From axial coordinates known to fit, such as ones wrapped into a map.
+	fn from_axial_u32(q: u32, r: u32) -> Self;
+
+	fn idx(self, max_x: Self::Extent, max_z: Self::Extent, wraps_x: bool) -> Option<usize>;
+
+	fn offset_by(
+		self,
+		offset: CoordOrientation,
+		width: Self::Extent,
+		height: Self::Extent,
+		wraps_x: bool,
+	) -> Option<Self>;
+}
+
+impl TileCoord for Coord {
+	type Extent = u8;
+
+	fn axial_u32(self) -> (u32, u32) {
+		(self.0 as u32, self.1 as u32)
+	}
+
+	fn from_axial_u32(q: u32, r: u32) -> Coord {
+		Coord::new_axial(q as u8, r as u8)
+	}
+
+	fn idx(self, max_x: u8, max_z: u8, wraps_x: bool) -> Option<usize> {
+		Coord::idx(self, max_x, max_z, wraps_x)
+	}
+
+	fn offset_by(
+		self,
+		offset: CoordOrientation,
+		width: u8,
+		height: u8,
+		wraps_x: bool,
+	) -> Option<Coord> {
+		Coord::offset_by(self, offset, width, height, wraps_x)
+	}
+}
+
+impl TileCoord for Coord32 {
+	type Extent = u16;
+
+	fn axial_u32(self) -> (u32, u32) {
+		(self.0 as u32, self.1 as u32)
+	}
+
+	fn from_axial_u32(q: u32, r: u32) -> Coord32 {
+		Coord32::new_axial(q as u16, r as u16)
+	}
+
+	fn idx(self, max_x: u16, max_z: u16, wraps_x: bool) -> Option<usize> {
+		Coord32::idx(self, max_x, max_z, wraps_x)
+	}
+
+	fn offset_by(
+		self,
+		offset: CoordOrientation,
+		width: u16,
+		height: u16,
+		wraps_x: bool,
+	) -> Option<Coord32> {
+		Coord32::offset_by(self, offset, width, height, wraps_x)
+	}
+}
+
 pub struct CoordRingIterator {
 	center: Coord,
 	offset: CoordOrientationRingIterator,
@@ -251,6 +615,303 @@ impl Iterator for CoordNeighborIterator {
 	}
 }
 
+pub struct CoordSpiralIterator {
+	center: Coord,
+	offset: CoordOrientationNeighborIterator,
+}
+
+impl CoordSpiralIterator {
+	fn new(center: Coord, max_distance: u8) -> CoordSpiralIterator {
+		CoordSpiralIterator {
+			center,
+			offset: CoordOrientationNeighborIterator::new(max_distance.min(127)),
+		}
+	}
+}
+
+impl Iterator for CoordSpiralIterator {
+	type Item = Coord;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let offset = self.offset.next()?;
+			let q = self.center.0 as i16 + offset.0 as i16;
+			let r = self.center.1 as i16 + offset.1 as i16;
+			if (0..=255).contains(&q) && (0..=255).contains(&r) {
+				return Some(Coord::new_axial(q as u8, r as u8));
+			}
+		}
+	}
+}
+
+/// Lerps in cube coordinates and rounds to the nearest hex, all in integers scaled by the step
+/// count so every machine draws the same line.
+pub struct CoordLineIterator {
+	from: (i32, i32, i32),
+	delta: (i32, i32, i32),
+	steps: i32,
+	step: i32,
+}
+
+impl CoordLineIterator {
+	fn new(from: Coord, to: Coord) -> CoordLineIterator {
+		let cubic = |c: Coord| {
+			let (x, y, z) = c.to_cubic_tuple();
+			(x as i32, y as i32, z as i32)
+		};
+		let (from, to) = (cubic(from), cubic(to));
+		let delta = (to.0 - from.0, to.1 - from.1, to.2 - from.2);
+		CoordLineIterator {
+			from,
+			delta,
+			steps: delta.0.abs().max(delta.1.abs()).max(delta.2.abs()),
+			step: 0,
+		}
+	}
+}
+
+impl Iterator for CoordLineIterator {
+	type Item = Coord;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.step > self.steps {
+			return None;
+		}
+		let step = self.step;
+		self.step += 1;
+		let (fx, fy, fz) = self.from;
+		if self.steps == 0 {
+			return Some(Coord::new_axial(fx as u8, fz as u8));
+		}
+		// Six times the step count apart per hex, the nudges keep a point exactly between two
+		// hexes off the fence and still add up to 0
+		let scale = 6 * self.steps;
+		let at = |from: i32, delta: i32, nudge: i32| 6 * (from * self.steps + delta * step) + nudge;
+		let (x, y, z) = (
+			at(fx, self.delta.0, 1),
+			at(fy, self.delta.1, 2),
+			at(fz, self.delta.2, -3),
+		);
+		let round = |v: i32| (2 * v + scale).div_euclid(2 * scale);
+		let (mut rx, ry, mut rz) = (round(x), round(y), round(z));
+		let (ex, ey, ez) = (
+			(rx * scale - x).abs(),
+			(ry * scale - y).abs(),
+			(rz * scale - z).abs(),
+		);
+		// Whichever rounded the furthest is put back on the plane from the other two
+		if ex > ey && ex > ez {
+			rx = -ry - rz;
+		} else if ey <= ez {
+			rz = -rx - ry;
+		}
+		Some(Coord::new_axial(rx as u8, rz as u8))
+	}
+}
+
+pub struct CoordLinearRectIterator {
+	/// Walks the pointy-top rect with x and y swapped, for flat-top hexes.
+	transposed: bool,
+	min_x: f32,
+	max_x: f32,
+	q: i16,
+	q_end: i16,
+	r: i16,
+	r_end: i16,
+}
+
+impl CoordLinearRectIterator {
+	fn new((min_x, min_y): (f32, f32), (max_x, max_y): (f32, f32)) -> CoordLinearRectIterator {
+		CoordLinearRectIterator::with_transposed(false, (min_x, min_y), (max_x, max_y))
+	}
+
+	fn with_transposed(
+		transposed: bool,
+		(min_x, min_y): (f32, f32),
+		(max_x, max_y): (f32, f32),
+	) -> CoordLinearRectIterator {
+		// A row's hexes reach a point's length above and below its center, rows only touching the
+		// rect are left out
+		let r = ((min_y - Coord::CENTER_TO_POINT) / Coord::LINEAR_Y_PER_R).floor() as i16 + 1;
+		let r_end = ((max_y + Coord::CENTER_TO_POINT) / Coord::LINEAR_Y_PER_R).ceil() as i16 - 1;
+		let mut iter = CoordLinearRectIterator {
+			transposed,
+			min_x,
+			max_x,
+			q: 0,
+			q_end: -1,
+			r,
+			r_end,
+		};
+		iter.start_row();
+		iter
+	}
+
+	fn start_row(&mut self) {
+		// Hexes are a linear unit wide and each row down is shifted half a hex right
+		let shift = Coord::LINEAR_X_PER_R * self.r as f32;
+		self.q = ((self.min_x - 0.5 - shift) / Coord::LINEAR_X_PER_Q).floor() as i16 + 1;
+		self.q_end = ((self.max_x + 0.5 - shift) / Coord::LINEAR_X_PER_Q).ceil() as i16 - 1;
+	}
+}
+
+impl Iterator for CoordLinearRectIterator {
+	type Item = (i16, i16, (f32, f32));
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.r <= self.r_end {
+			if self.q <= self.q_end {
+				let q = self.q;
+				self.q += 1;
+				let (x, y) = Coord::axial_to_linear(q as f32, self.r as f32);
+				return Some(if self.transposed {
+					(self.r, q, (y, x))
+				} else {
+					(q, self.r, (x, y))
+				});
+			}
+			self.r += 1;
+			self.start_row();
+		}
+		None
+	}
+}
+
+/// Which way up the hexes are drawn, the axial coordinates are the same either way with `q`
+/// running left to right.
+///
+/// Flat-top is pointy-top mirrored across the diagonal, so its linear space is `Coord::to_linear`
+/// with q/r and x/y swapped: columns are a linear unit apart along the flat sides and each column
+/// right is shifted half a hex down.
+///
+/// ```
+/// # use assert_approx_eq::assert_approx_eq;
+/// # use over_simple_game_1::core::map::coord::{Coord, HexOrientation};
+/// let (x, y) = HexOrientation::FlatTop.to_linear(Coord::new_axial(1, 0));
+/// assert_approx_eq!(x, 0.8660254);
+/// assert_approx_eq!(y, 0.5);
+/// assert_eq!(HexOrientation::FlatTop.from_linear(x, y), Coord::new_axial(1, 0));
+/// assert_eq!(
+/// 	HexOrientation::PointyTop.to_linear(Coord::new_axial(1, 0)),
+/// 	Coord::new_axial(1, 0).to_linear()
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum HexOrientation {
+	PointyTop,
+	FlatTop,
+}
+
+impl Default for HexOrientation {
+	fn default() -> Self {
+		HexOrientation::PointyTop
+	}
+}
+
+impl HexOrientation {
+	#[inline]
+	pub fn axial_to_linear(self, q: f32, r: f32) -> (f32, f32) {
+		match self {
+			HexOrientation::PointyTop => Coord::axial_to_linear(q, r),
+			HexOrientation::FlatTop => {
+				let (y, x) = Coord::axial_to_linear(r, q);
+				(x, y)
+			}
+		}
+	}
+
+	/// `Coord::to_linear` for this orientation.
+	#[inline]
+	pub fn to_linear(self, coord: Coord) -> (f32, f32) {
+		self.axial_to_linear(coord.q() as f32, coord.r() as f32)
+	}
+
+	/// Batch form of `to_linear` with every position moved by `origin`. `out` is cleared first
+	/// so callers can reuse the allocation.
+	///
+	/// ```
+	/// # use over_simple_game_1::core::map::coord::{Coord, HexOrientation};
+	/// let hex = HexOrientation::FlatTop;
+	/// let coords = [Coord::new_axial(0, 0), Coord::new_axial(3, 9)];
+	/// let mut linear = vec![(1.0, 1.0)];
+	/// hex.to_linear_many((2.0, 0.0), &coords, &mut linear);
+	/// let (x, y) = hex.to_linear(coords[1]);
+	/// assert_eq!(linear, vec![(2.0, 0.0), (x + 2.0, y)]);
+	/// ```
+	pub fn to_linear_many(
+		self,
+		(origin_x, origin_y): (f32, f32),
+		coords: &[Coord],
+		out: &mut Vec<(f32, f32)>,
+	) {
+		out.clear();
+		out.extend(coords.iter().map(|&coord| {
+			let (x, y) = self.to_linear(coord);
+			(origin_x + x, origin_y + y)
+		}));
+	}
+
+	/// `CoordOrientation::to_linear` for this orientation.
+	#[inline]
+	pub fn offset_to_linear(self, offset: CoordOrientation) -> (f32, f32) {
+		self.axial_to_linear(offset.q() as f32, offset.r() as f32)
+	}
+
+	/// `Coord::from_linear` for this orientation.
+	#[inline]
+	pub fn from_linear(self, x: f32, y: f32) -> Coord {
+		match self {
+			HexOrientation::PointyTop => Coord::from_linear(x, y),
+			HexOrientation::FlatTop => {
+				let swapped = Coord::from_linear(y, x);
+				Coord::new_axial(swapped.r(), swapped.q())
+			}
+		}
+	}
+
+	/// Corners of a hex in linear space relative to its center, clockwise, from the top point for
+	/// pointy-top and from the left point for flat-top.
+	pub fn corners(self) -> [(f32, f32); 6] {
+		match self {
+			HexOrientation::PointyTop => Coord::LINEAR_CORNERS,
+			HexOrientation::FlatTop => {
+				let mut corners = Coord::LINEAR_CORNERS;
+				for corner in &mut corners {
+					*corner = (corner.1, corner.0);
+				}
+				// Swapping mirrors them, so reverse to keep them clockwise and start on the left
+				corners.reverse();
+				corners.rotate_right(1);
+				corners
+			}
+		}
+	}
+
+	/// Half of a hex's linear width and height, its extent from the center along each axis.
+	pub fn half_size(self) -> (f32, f32) {
+		match self {
+			HexOrientation::PointyTop => (0.5, Coord::CENTER_TO_POINT),
+			HexOrientation::FlatTop => (Coord::CENTER_TO_POINT, 0.5),
+		}
+	}
+
+	/// `Coord::iter_linear_rect` for this orientation, flat-top walks column by column.
+	pub fn iter_linear_rect(
+		self,
+		(min_x, min_y): (f32, f32),
+		(max_x, max_y): (f32, f32),
+	) -> CoordLinearRectIterator {
+		match self {
+			HexOrientation::PointyTop => {
+				CoordLinearRectIterator::with_transposed(false, (min_x, min_y), (max_x, max_y))
+			}
+			HexOrientation::FlatTop => {
+				CoordLinearRectIterator::with_transposed(true, (min_y, min_x), (max_y, max_x))
+			}
+		}
+	}
+}
+
 #[derive(Clone, Copy, Default, Debug, Hash, PartialOrd, PartialEq, Ord, Eq)]
 pub struct CoordOrientation(i8, i8);
 
@@ -264,15 +925,18 @@ impl CoordOrientation {
 	/// assert_eq!(coord.q(), 0);
 	/// assert_eq!(coord.r(), 1);
 	/// ```
-	pub fn new_axial(q: i8, r: i8) -> CoordOrientation {
+	#[inline]
+	pub const fn new_axial(q: i8, r: i8) -> CoordOrientation {
 		CoordOrientation(q, r)
 	}
 
-	pub fn q(&self) -> i8 {
+	#[inline]
+	pub const fn q(&self) -> i8 {
 		self.0
 	}
 
-	pub fn r(&self) -> i8 {
+	#[inline]
+	pub const fn r(&self) -> i8 {
 		self.1
 	}
 
@@ -296,12 +960,9 @@ impl CoordOrientation {
 		(self.x(), self.y(), self.z())
 	}
 
+	#[inline]
 	pub fn to_linear(self) -> (f32, f32) {
-		let q = self.0 as f32;
-		let r = self.1 as f32;
-		let x = Coord::CENTER_TO_POINT * (Coord::SQRT3 * q + Coord::SQRT3 / 2.0 * r);
-		let y = Coord::CENTER_TO_POINT * (3.0 / 2.0 * r);
-		(x, y)
+		Coord::axial_to_linear(self.0 as f32, self.1 as f32)
 	}
 
 	pub fn distance_to(self, other: CoordOrientation) -> u8 {
@@ -328,6 +989,27 @@ impl CoordOrientation {
 		CoordOrientation::new_axial(y, x)
 	}
 
+	/// Turned `steps` sixths of a turn around the origin, clockwise like `cw` or counter clockwise
+	/// like `ccw` when negative.
+	pub fn rotate_around(self, steps: i8) -> CoordOrientation {
+		let mut rotated = self;
+		for _ in 0..steps.rem_euclid(6) {
+			rotated = rotated.cw();
+		}
+		rotated
+	}
+
+	/// Mirrored across `axis` running through the origin.
+	pub fn reflect(self, axis: CoordAxis) -> CoordOrientation {
+		let (x, y, z) = self.to_cubic_tuple();
+		// As axial, cubic x and z
+		match axis {
+			CoordAxis::X => CoordOrientation::new_axial(x, y),
+			CoordAxis::Y => CoordOrientation::new_axial(z, x),
+			CoordAxis::Z => CoordOrientation::new_axial(y, z),
+		}
+	}
+
 	// pub fn as_coord(self) -> Coord {
 	// 	Coord(self.0, self.1)
 	// }
@@ -387,6 +1069,15 @@ impl Neg for CoordOrientation {
 	}
 }
 
+/// The three axes hexes line up along, named for the cubic coordinate that stays the same when
+/// mirroring across them while the other two swap.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum CoordAxis {
+	X,
+	Y,
+	Z,
+}
+
 pub struct CoordOrientationRingIterator {
 	side: CoordOrientation,
 	side_count: u8,
@@ -481,6 +1172,161 @@ mod coord_tests {
 			.boxed()
 	}
 
+	proptest!(
+		#[test]
+		fn hash_with_seed_spreads_neighbors(c in rand_coord_strategy(), seed in any::<u64>()) {
+			let hashes = c
+				.iter_neighbors(1)
+				.map(|n| n.hash_with_seed(seed) % 64)
+				.collect::<HashSet<u64>>();
+			// Seven tiles in 64 buckets, a decent mix essentially never collapses to a couple
+			prop_assert!(hashes.len() >= 4, "neighbors of {:?} only hit {:?}", c, hashes);
+		}
+	);
+
+	proptest!(
+		#[test]
+		fn lines_step_from_neighbor_to_neighbor(a in rand_coord_strategy(), b in rand_coord_strategy()) {
+			let line: Vec<Coord> = a.iter_line_to(b).collect();
+			let (dx, dy, dz) = (b.x() - a.x(), b.y() - a.y(), b.z() - a.z());
+			let distance = dx.abs().max(dy.abs()).max(dz.abs()) as usize;
+			prop_assert_eq!(line.len(), distance + 1);
+			prop_assert_eq!(line.first(), Some(&a));
+			prop_assert_eq!(line.last(), Some(&b));
+			for pair in line.windows(2) {
+				let (dx, dy, dz) = (
+					pair[1].x() - pair[0].x(),
+					pair[1].y() - pair[0].y(),
+					pair[1].z() - pair[0].z(),
+				);
+				prop_assert_eq!(dx.abs().max(dy.abs()).max(dz.abs()), 1, "{:?}", line);
+			}
+		}
+	);
+
+	#[test]
+	fn lines_between_hexes_keep_to_one_side() {
+		let line: Vec<(u8, u8)> = Coord::new_axial(0, 0)
+			.iter_line_to(Coord::new_axial(1, 1))
+			.map(|c| c.to_axial_tuple())
+			.collect();
+		// Straight down the edge between 1,0 and 0,1
+		assert_eq!(line, [(0, 0), (1, 0), (1, 1)]);
+		let same: Vec<Coord> = Coord::new_axial(4, 4)
+			.iter_line_to(Coord::new_axial(4, 4))
+			.collect();
+		assert_eq!(same, [Coord::new_axial(4, 4)]);
+	}
+
+	#[test]
+	fn linear_rect_iterator_covers_the_rect() {
+		let hexes: Vec<(i16, i16)> = Coord::iter_linear_rect((0.0, 0.0), (2.0, 1.0))
+			.map(|(q, r, _center)| (q, r))
+			.collect();
+		// Hexes only touching the rect's edges are left out, so the shifted row below has fewer
+		assert_eq!(hexes, vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1)]);
+		for (q, r, (x, y)) in Coord::iter_linear_rect((-3.2, -2.0), (4.1, 3.3)) {
+			assert!(
+				x + 0.5 > -3.2 && x - 0.5 < 4.1,
+				"{},{} is off to the side",
+				q,
+				r
+			);
+			let point = Coord::CENTER_TO_POINT;
+			assert!(
+				y + point > -2.0 && y - point < 3.3,
+				"{},{} is off the top",
+				q,
+				r
+			);
+		}
+	}
+
+	proptest!(
+		#[test]
+		fn flat_top_round_trips_and_keeps_neighbors_apart(c in rand_coord_strategy()) {
+			let hex = HexOrientation::FlatTop;
+			let (x, y) = hex.to_linear(c);
+			prop_assert_eq!(hex.from_linear(x, y), c);
+			for offset in CoordOrientationRingIterator::new(1) {
+				let (dx, dy) = hex.offset_to_linear(offset);
+				// Neighbors are a linear unit apart across the flat sides like pointy-top's
+				prop_assert!(((dx * dx + dy * dy).sqrt() - 1.0).abs() < 0.001, "{:?}", offset);
+			}
+		}
+	);
+
+	#[test]
+	fn flat_top_linear_rect_iterator_covers_the_rect() {
+		let (half_x, half_y) = HexOrientation::FlatTop.half_size();
+		let hexes: Vec<(i16, i16)> = HexOrientation::FlatTop
+			.iter_linear_rect((-3.2, -2.0), (4.1, 3.3))
+			.map(|(q, r, (x, y))| {
+				assert!(
+					x + half_x > -3.2 && x - half_x < 4.1,
+					"{},{} is off to the side",
+					q,
+					r
+				);
+				assert!(
+					y + half_y > -2.0 && y - half_y < 3.3,
+					"{},{} is off the top",
+					q,
+					r
+				);
+				assert_eq!(
+					HexOrientation::FlatTop.axial_to_linear(q as f32, r as f32),
+					(x, y)
+				);
+				(q, r)
+			})
+			.collect();
+		assert!(hexes.contains(&(0, 0)) && hexes.contains(&(1, 0)) && hexes.contains(&(0, 1)));
+		let corners = HexOrientation::FlatTop.corners();
+		assert_eq!(corners[0], (-Coord::CENTER_TO_POINT, 0.0));
+		assert_eq!(corners[3], (Coord::CENTER_TO_POINT, 0.0));
+		// Clockwise on screen, the second corner is up along the top flat side
+		assert!(corners[1].1 < 0.0 && corners[2].1 < 0.0);
+	}
+
+	proptest!(
+		#[test]
+		fn coord32_agrees_with_coord_where_both_fit(
+			c in rand_coord_strategy(),
+			offset in rand_coord_orientation_strategy(),
+			width in 0..255u8,
+			height in 0..255u8,
+			wraps_x in any::<bool>(),
+		) {
+			let wide = Coord32::from(c);
+			prop_assert_eq!(wide.idx(width as u16, height as u16, wraps_x), c.idx(width, height, wraps_x));
+			prop_assert_eq!(wide.to_linear(), c.to_linear());
+			// The narrow form also lets one past the edge through, `idx` then rejects it
+			let narrow = c
+				.offset_by(offset, width, height, wraps_x)
+				.filter(|n| n.idx(width, height, wraps_x).is_some());
+			prop_assert_eq!(
+				wide.offset_by(offset, width as u16, height as u16, wraps_x),
+				narrow.map(Coord32::from)
+			);
+		}
+	);
+
+	#[test]
+	fn coord32_reaches_past_256() {
+		let far = Coord32::new_axial(999, 300);
+		assert_eq!(far.idx(999, 300, false), Some(300 * 1000 + 999));
+		assert_eq!(far.idx(998, 300, false), None);
+		// Wraps around the seam of a 1000 column map
+		let east = CoordOrientation::new_axial(1, 0);
+		assert_eq!(
+			far.offset_by(east, 999, 300, true),
+			Some(Coord32::new_axial(0, 300))
+		);
+		assert_eq!(far.offset_by(east, 999, 300, false), None);
+		assert_eq!(Coord32::new_axial(0, 0).distance_to(far), 1299);
+	}
+
 	#[test]
 	fn coord_orientation_ring_iterator_small_count() {
 		{
@@ -635,4 +1481,113 @@ mod coord_tests {
 			prop_assert_eq!(coord.cw().cw().cw(), -coord);
 		}
 	);
+
+	/// `distance_to` without wrapping, for coords over 127 apart.
+	fn far_distance(a: Coord, b: Coord) -> i16 {
+		let (x, y, z) = a.cubic_offset_from(b);
+		x.abs().max(y.abs()).max(z.abs())
+	}
+
+	proptest!(
+		#[test]
+		fn offset_coords_round_trip(coord in rand_coord_strategy()) {
+			let (column, row) = coord.to_offset_odd_r();
+			prop_assert_eq!(Coord::from_offset_odd_r(column, row), Some(coord));
+			let (column, row) = coord.to_offset_even_r();
+			prop_assert_eq!(Coord::from_offset_even_r(column, row), Some(coord));
+		}
+	);
+
+	#[test]
+	fn offset_rows_line_up_on_screen() {
+		// A column in either layout stays about straight down the screen
+		for row in 0..8u8 {
+			let odd = Coord::from_offset_odd_r(4, row).unwrap().to_linear().0;
+			let even = Coord::from_offset_even_r(4, row).unwrap().to_linear().0;
+			let shifted = row % 2 == 1;
+			assert_eq!(odd, if shifted { 4.5 } else { 4.0 });
+			assert_eq!(even, if shifted { 3.5 } else { 4.0 });
+		}
+	}
+
+	proptest!(
+		#[test]
+		fn spirals_walk_outward(coord in rand_coord_strategy(), max_distance in 0..12u8) {
+			let spiral: Vec<Coord> = coord.iter_spiral(max_distance).collect();
+			prop_assert_eq!(spiral.first(), Some(&coord));
+			prop_assert!(spiral
+				.windows(2)
+				.all(|pair| far_distance(coord, pair[0]) <= far_distance(coord, pair[1])));
+			// The same coords as the rings around it, less any that would wrap
+			let mut rings = vec![];
+			for distance in 0..=max_distance {
+				rings.extend(
+					coord
+						.iter_neighbors_ring(distance)
+						.filter(|&ring| far_distance(coord, ring) == distance as i16),
+				);
+			}
+			prop_assert_eq!(spiral, rings);
+		}
+	);
+
+	proptest!(
+		#[test]
+		fn rotations_are_turns(coord in rand_coord_orientation_strategy(), steps in -12i8..12) {
+			prop_assert_eq!(coord.rotate_around(1), coord.cw());
+			prop_assert_eq!(coord.rotate_around(-1), coord.ccw());
+			prop_assert_eq!(coord.rotate_around(steps + 6), coord.rotate_around(steps));
+			prop_assert_eq!(coord.rotate_around(steps).rotate_around(-steps), coord);
+		}
+
+		#[test]
+		fn rotating_about_keeps_the_distance(
+			coord in rand_coord_strategy(),
+			center in rand_coord_strategy(),
+			steps in -6i8..6
+		) {
+			if let Some(rotated) = coord.rotate_about(center, steps) {
+				prop_assert_eq!(far_distance(rotated, center), far_distance(coord, center));
+				prop_assert_eq!(rotated.rotate_about(center, -steps), Some(coord));
+			}
+			prop_assert_eq!(coord.rotate_about(center, 6), Some(coord));
+		}
+
+		#[test]
+		fn reflecting_twice_makes_itself(
+			coord in rand_coord_strategy(),
+			center in rand_coord_strategy()
+		) {
+			for &axis in &[CoordAxis::X, CoordAxis::Y, CoordAxis::Z] {
+				if let Some(reflected) = coord.reflect_about(center, axis) {
+					prop_assert_eq!(far_distance(reflected, center), far_distance(coord, center));
+					prop_assert_eq!(reflected.reflect_about(center, axis), Some(coord));
+				}
+			}
+		}
+	);
+
+	#[test]
+	fn reflections_keep_their_axis() {
+		let offset = CoordOrientation::new_axial(2, -3);
+		let (x, y, z) = offset.to_cubic_tuple();
+		assert_eq!(offset.reflect(CoordAxis::X).to_cubic_tuple(), (x, z, y));
+		assert_eq!(offset.reflect(CoordAxis::Y).to_cubic_tuple(), (z, y, x));
+		assert_eq!(offset.reflect(CoordAxis::Z).to_cubic_tuple(), (y, x, z));
+		// Two reflections make a rotation
+		assert_eq!(
+			offset.reflect(CoordAxis::X).reflect(CoordAxis::Y),
+			offset.rotate_around(2)
+		);
+		let center = Coord::new_axial(5, 5);
+		assert_eq!(
+			Coord::new_axial(7, 4).reflect_about(center, CoordAxis::Z),
+			Some(Coord::new_axial(4, 4))
+		);
+		let center = Coord::new_axial(200, 5);
+		assert_eq!(
+			Coord::new_axial(0, 5).reflect_about(center, CoordAxis::Z),
+			None
+		);
+	}
 }
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+use crate::core::engine::io::EngineIO;
+use crate::core::engine::schema::{self, SchemaError, Schemas};
+use crate::core::structures::typed_index_map::{
+	TypedIndexMap, TypedIndexMapError, TypedIndexMapIndex,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum DecorationTypesMap {}
+
+pub type DecorationIdx = TypedIndexMapIndex<DecorationTypesMap, u16>;
+
+/// Purely visual dressing on a tile (trees, rocks, flowers), independent of its tile type.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DecorationType {
+	pub name: String,
+}
+
+#[derive(Debug)]
+pub struct DecorationTypes {
+	pub decoration_types: TypedIndexMap<DecorationTypesMap, String, DecorationType, u16>,
+}
+
+#[derive(Error, Debug)]
+pub enum DecorationTypesError<IO: EngineIO>
+where
+	IO::ReadError: 'static,
+{
+	#[error("failed to load decoration information file")]
+	FileReadError {
+		source: IO::ReadError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to parse decoration information")]
+	FileParseError {
+		source: SchemaError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error(
+		"decoration types already filled to the max of {} when inserting {}",
+		DecorationIdx::MAX, .0.name
+	)]
+	DecorationTypesFilled(DecorationType),
+
+	#[error("decoration types have already been loaded")]
+	DecorationTypesAlreadyFilled(),
+
+	#[error("decoration type name is empty")]
+	EmptyDecorationTypeName(DecorationType),
+
+	#[error("attempted to insert a duplicate decoration type name: {}", .0.name)]
+	DuplicateDecorationTypeName(DecorationType),
+}
+
+impl DecorationTypes {
+	pub(crate) fn new() -> DecorationTypes {
+		DecorationTypes {
+			decoration_types: TypedIndexMap::new(),
+		}
+	}
+
+	fn add_decoration<IO: EngineIO>(
+		&mut self,
+		decoration_type: DecorationType,
+	) -> Result<DecorationIdx, DecorationTypesError<IO>> {
+		if decoration_type.name.is_empty() {
+			return Err(DecorationTypesError::EmptyDecorationTypeName(
+				decoration_type,
+			));
+		}
+		if self.decoration_types.contains_key(&decoration_type.name) {
+			return Err(DecorationTypesError::DuplicateDecorationTypeName(
+				decoration_type,
+			));
+		}
+
+		let (index, old_value) = self
+			.decoration_types
+			.insert_full(decoration_type.name.clone(), decoration_type)
+			.map_err(|e| match e {
+				TypedIndexMapError::TypedIndexMapFull(_max, _key, decoration_type) => {
+					DecorationTypesError::DecorationTypesFilled(decoration_type)
+				}
+			})?;
+		assert!(old_value.is_none());
+
+		Ok(index)
+	}
+
+	pub(crate) fn load_decorations<IO: EngineIO>(
+		&mut self,
+		io: &mut IO,
+		schemas: &Schemas,
+	) -> Result<(), DecorationTypesError<IO>> {
+		if !self.decoration_types.is_empty() {
+			return Err(DecorationTypesError::DecorationTypesAlreadyFilled());
+		}
+
+		let reader = io
+			.read(std::path::Path::new("tiles/decorations.ron"))
+			.map_err(|source| DecorationTypesError::FileReadError { source })?;
+
+		let decoration_types: Vec<DecorationType> = schemas
+			.read(schema::DECORATION_TYPES, reader)
+			.map_err(|source| DecorationTypesError::FileParseError { source })?;
+
+		for decoration_type in decoration_types {
+			self.add_decoration(decoration_type)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod decoration_tests {
+	use super::*;
+	use crate::core::map::generator::test_io::DummyIO;
+
+	#[test]
+	fn loads_decorations_in_order() {
+		let mut io = DummyIO {
+			data: b"[DecorationType(name: \"tree\"), DecorationType(name: \"rock\")]",
+		};
+		let mut decorations = DecorationTypes::new();
+		decorations
+			.load_decorations(&mut io, &Schemas::new())
+			.unwrap();
+		let names: Vec<&str> = decorations
+			.decoration_types
+			.keys()
+			.map(|s| s.as_str())
+			.collect();
+		assert_eq!(names, vec!["tree", "rock"]);
+	}
+
+	#[test]
+	fn duplicate_and_empty_names_are_rejected() {
+		let mut io = DummyIO {
+			data: b"[DecorationType(name: \"tree\"), DecorationType(name: \"tree\")]",
+		};
+		let mut decorations = DecorationTypes::new();
+		match decorations.load_decorations(&mut io, &Schemas::new()) {
+			Err(DecorationTypesError::DuplicateDecorationTypeName(d)) => assert_eq!(d.name, "tree"),
+			other => panic!("duplicate name not rejected: {:?}", other.map(|_| ())),
+		}
+		let mut decorations = DecorationTypes::new();
+		match decorations.add_decoration::<DummyIO>(DecorationType {
+			name: String::new(),
+		}) {
+			Err(DecorationTypesError::EmptyDecorationTypeName(_)) => (),
+			other => panic!("empty name not rejected: {:?}", other.map(|_| ())),
+		}
+	}
+}
@@ -1,14 +1,50 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
 use super::tile::{Tile, TileIdx};
 use crate::core::engine::io::EngineIO;
 use crate::core::engine::Engine;
+use crate::core::fixed::Fixed;
+use crate::core::map::coord::{Coord, TileCoord};
+use crate::core::map::decoration::DecorationIdx;
 use crate::core::map::tile_map::TileMap;
 use anyhow::Context as AnyContext;
 
-pub trait MapGenerator {
-	fn generate(&mut self, tile_map: &mut TileMap) -> anyhow::Result<()>;
+/// What a generator is called and how it was set up, kept in the metadata of the maps it makes
+/// so they can be told apart and made again.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratorInfo {
+	pub name: String,
+	pub params: BTreeMap<String, String>,
+}
+
+impl GeneratorInfo {
+	pub fn new(name: impl Into<String>) -> GeneratorInfo {
+		GeneratorInfo {
+			name: name.into(),
+			params: BTreeMap::new(),
+		}
+	}
+
+	pub fn with_param(mut self, name: impl Into<String>, value: impl ToString) -> GeneratorInfo {
+		self.params.insert(name.into(), value.to_string());
+		self
+	}
+}
+
+/// Fills in the tiles of a new map, `C` is what the map is addressed by.
+pub trait MapGenerator<C: TileCoord = Coord> {
+	fn generate(&mut self, tile_map: &mut TileMap<C>) -> anyhow::Result<()>;
+
+	/// `custom` without parameters unless the generator says otherwise.
+	fn info(&self) -> GeneratorInfo {
+		GeneratorInfo::new("custom")
+	}
 }
 
-pub struct SimpleAlternationMapGenerator(Vec<TileIdx>);
+/// The tile types in turn, named for the map's metadata.
+pub struct SimpleAlternationMapGenerator(Vec<TileIdx>, Vec<String>);
 impl MapGenerator for SimpleAlternationMapGenerator {
 	fn generate(&mut self, tile_map: &mut TileMap) -> Result<(), anyhow::Error> {
 		tile_map.tiles.clear();
@@ -22,6 +58,10 @@ impl MapGenerator for SimpleAlternationMapGenerator {
 
 		Ok(())
 	}
+
+	fn info(&self) -> GeneratorInfo {
+		GeneratorInfo::new("alternating").with_param("tiles", self.1.join(","))
+	}
 }
 impl SimpleAlternationMapGenerator {
 	pub fn new<NameIter: IntoIterator, IO: EngineIO>(
@@ -32,7 +72,98 @@ impl SimpleAlternationMapGenerator {
 		NameIter::Item: AsRef<str>,
 	{
 		let mut tiles = Vec::new();
+		let mut tile_names = Vec::new();
 		for name in names {
+			let name: &str = name.as_ref();
+			let idx = engine
+				.tile_types
+				.tile_types
+				.get_index_of(name)
+				.with_context(|| format!("missing tile type: {}", name))?;
+			tiles.push(idx);
+			tile_names.push(name.to_owned());
+		}
+		Ok(SimpleAlternationMapGenerator(tiles, tile_names))
+	}
+}
+
+/// Scatters `decoration` over tiles whose type is in `on`, `density` being the chance per tile
+/// from 0 to 1.
+#[derive(Clone, Debug)]
+pub struct DecorationRule {
+	pub decoration: DecorationIdx,
+	pub on: Vec<TileIdx>,
+	pub density: Fixed,
+}
+
+/// Runs another generator then places decorations according to its rules, the first rule that
+/// matches a tile wins.
+///
+/// Placement is hashed from the seed and tile coordinate so the same seed always decorates the
+/// same way regardless of generation order, and rolled in fixed point so on every machine too.
+pub struct DecorationScatter<G: MapGenerator> {
+	pub inner: G,
+	pub seed: u64,
+	pub rules: Vec<DecorationRule>,
+}
+
+impl<G: MapGenerator> MapGenerator for DecorationScatter<G> {
+	fn generate(&mut self, tile_map: &mut TileMap) -> anyhow::Result<()> {
+		self.inner.generate(tile_map)?;
+		tile_map.seed = self.seed;
+		let row_length = tile_map.width as usize + 1;
+		for (idx, tile) in tile_map.tiles.iter_mut().enumerate() {
+			let coord = Coord::new_axial((idx % row_length) as u8, (idx / row_length) as u8);
+			// The top bits of the hash as a fraction from 0 up to 1
+			let roll = Fixed::from_bits(
+				(coord.hash_with_seed(self.seed) >> (64 - Fixed::FRACTION_BITS)) as i32,
+			);
+			tile.decoration = self
+				.rules
+				.iter()
+				.find(|rule| rule.on.contains(&tile.id))
+				.filter(|rule| roll < rule.density)
+				.map(|rule| rule.decoration);
+		}
+
+		Ok(())
+	}
+
+	fn info(&self) -> GeneratorInfo {
+		// The seed is the map's own
+		self.inner
+			.info()
+			.with_param("decoration_rules", self.rules.len())
+	}
+}
+
+impl<G: MapGenerator> DecorationScatter<G> {
+	pub fn new(inner: G, seed: u64) -> DecorationScatter<G> {
+		DecorationScatter {
+			inner,
+			seed,
+			rules: Vec::new(),
+		}
+	}
+
+	/// Adds a rule by decoration and tile type names.
+	pub fn with_rule<NameIter: IntoIterator, IO: EngineIO>(
+		mut self,
+		engine: &Engine<IO>,
+		decoration: &str,
+		on: NameIter,
+		density: Fixed,
+	) -> anyhow::Result<DecorationScatter<G>>
+	where
+		NameIter::Item: AsRef<str>,
+	{
+		let decoration = engine
+			.decoration_types
+			.decoration_types
+			.get_index_of(decoration)
+			.with_context(|| format!("missing decoration type: {}", decoration))?;
+		let mut tiles = Vec::new();
+		for name in on {
 			let name: &str = name.as_ref();
 			let idx = engine
 				.tile_types
@@ -41,6 +172,125 @@ impl SimpleAlternationMapGenerator {
 				.with_context(|| format!("missing tile type: {}", name))?;
 			tiles.push(idx)
 		}
-		Ok(SimpleAlternationMapGenerator(tiles))
+		self.rules.push(DecorationRule {
+			decoration,
+			on: tiles,
+			density,
+		});
+		Ok(self)
+	}
+}
+
+/// Maps for tests that need tiles to stand on more than particular terrain.
+#[cfg(test)]
+pub(crate) mod test_maps {
+	use super::*;
+	use crate::core::map::tile::TileTypesMap;
+	use crate::core::structures::typed_index_map::TypedIndexMap;
+
+	/// Makes every tile of a map from its index.
+	pub(crate) struct FillGenerator<F>(pub F);
+
+	impl<C: TileCoord, F: FnMut(usize) -> Tile> MapGenerator<C> for FillGenerator<F> {
+		fn generate(&mut self, tile_map: &mut TileMap<C>) -> anyhow::Result<()> {
+			let columns = tile_map.width.into() as usize + 1;
+			let count = columns * (tile_map.height.into() as usize + 1);
+			tile_map.tiles = (0..count).map(&mut self.0).collect();
+			Ok(())
+		}
+	}
+
+	/// Ids of `count` tile types, as the engine numbers them in the order they are loaded.
+	pub(crate) fn tile_ids(count: usize) -> Vec<TileIdx> {
+		let mut tile_types = TypedIndexMap::<TileTypesMap, String, (), u16>::new();
+		(0..count)
+			.map(|n| tile_types.insert_full(n.to_string(), ()).unwrap().0)
+			.collect()
+	}
+
+	/// The tile type `plain_map` is made of.
+	pub(crate) fn plain() -> TileIdx {
+		tile_ids(1)[0]
+	}
+
+	/// A map made by `FillGenerator` with `tile`.
+	pub(crate) fn filled_map<C: TileCoord>(
+		width: C::Extent,
+		height: C::Extent,
+		wraps_x: bool,
+		tile: impl FnMut(usize) -> Tile,
+	) -> TileMap<C> {
+		TileMap::new(width, height, wraps_x, &mut FillGenerator(tile)).unwrap()
+	}
+
+	/// A map of `plain` tiles and nothing else.
+	pub(crate) fn plain_map(width: u8, height: u8, wraps_x: bool) -> TileMap {
+		let plain = plain();
+		filled_map(width, height, wraps_x, |_idx| Tile::new(plain))
+	}
+}
+
+/// An `EngineIO` for tests along with tile types to read maps against.
+#[cfg(test)]
+pub(crate) mod test_io {
+	use crate::core::engine::io::{EngineIO, WriteMode};
+	use crate::core::map::tile::{TileIdx, TileType, TileTypes};
+	use std::{convert::Infallible, path::Path};
+
+	/// Reads `data` for every path and throws away anything written, without any `data` there
+	/// are no files at all.
+	#[derive(Debug, Default, Eq, PartialEq)]
+	pub(crate) struct DummyIO {
+		pub(crate) data: &'static [u8],
+	}
+
+	impl EngineIO for DummyIO {
+		type ReadError = Infallible;
+		type Read = &'static [u8];
+
+		fn read(&mut self, _: &Path) -> Result<Self::Read, Self::ReadError> {
+			Ok(self.data)
+		}
+
+		fn exists(&mut self, _: &Path) -> bool {
+			!self.data.is_empty()
+		}
+
+		type WriteError = Infallible;
+		type Write = std::io::Sink;
+
+		fn write(&mut self, _: &Path, _: WriteMode) -> Result<Self::Write, Self::WriteError> {
+			Ok(std::io::sink())
+		}
+
+		type TileInterface = ();
+
+		fn blank_tile_interface() -> Self::TileInterface {}
+
+		type TileAddedError = Infallible;
+
+		fn tile_added(
+			&mut self,
+			_: TileIdx,
+			_: &mut TileType<Self>,
+		) -> Result<(), Self::TileAddedError> {
+			Ok(())
+		}
+	}
+
+	/// Tile types with `names`, numbered in that order.
+	pub(crate) fn tile_types(names: &[&str]) -> TileTypes<DummyIO> {
+		let mut tile_types = TileTypes::new();
+		for name in names {
+			let tile_type = TileType {
+				name: name.to_string(),
+				interface: (),
+			};
+			tile_types
+				.tile_types
+				.insert(name.to_string(), tile_type)
+				.unwrap();
+		}
+		tile_types
 	}
 }
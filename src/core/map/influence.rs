@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+use crate::core::fixed::Fixed;
+use crate::core::map::coord::{Coord, CoordOrientation};
+use crate::core::map::tile_map::TileMap;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum InfluenceError {
+	#[error("influence field has {expected} tiles but the map has {tiles}")]
+	TileCountMismatch { expected: usize, tiles: usize },
+	#[error("no influence field named `{0}`")]
+	NoField(String),
+}
+
+/// How an influence field moves each time it diffuses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InfluenceRule {
+	/// How far each tile moves towards the average of its neighbors, from 0 to 1.
+	pub spread: Fixed,
+	/// How much of each tile fades away, from 0 to 1.
+	pub decay: Fixed,
+}
+
+/// A scalar value on every tile of one map, such as how strongly a religion is held there, that
+/// spreads outward from its sources and fades where they are gone.
+///
+/// It is all fixed point math so every machine diffuses it the same.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InfluenceField {
+	pub rule: InfluenceRule,
+	/// In the linear order of the map's tiles.
+	values: Vec<Fixed>,
+}
+
+impl InfluenceField {
+	pub fn new(rule: InfluenceRule, tile_map: &TileMap) -> InfluenceField {
+		InfluenceField {
+			rule,
+			values: vec![Fixed::ZERO; tile_map.tiles.len()],
+		}
+	}
+
+	pub fn values(&self) -> &[Fixed] {
+		&self.values
+	}
+
+	pub fn value_at(&self, tile_map: &TileMap, coord: Coord) -> Fixed {
+		coord
+			.idx(tile_map.width, tile_map.height, tile_map.wraps_x)
+			.and_then(|idx| self.values.get(idx).copied())
+			.unwrap_or(Fixed::ZERO)
+	}
+
+	/// The highest value on the map, for scaling it to draw.
+	pub fn max_value(&self) -> Fixed {
+		self.values.iter().copied().max().unwrap_or(Fixed::ZERO)
+	}
+
+	/// One step of spreading, each tile moves towards its neighbors by `spread` then fades by
+	/// `decay`, then each of `sources` adds its strength to its tile.
+	pub fn diffuse(
+		&mut self,
+		tile_map: &TileMap,
+		sources: &[(Coord, Fixed)],
+	) -> Result<(), InfluenceError> {
+		if self.values.len() != tile_map.tiles.len() {
+			return Err(InfluenceError::TileCountMismatch {
+				expected: self.values.len(),
+				tiles: tile_map.tiles.len(),
+			});
+		}
+		let spread = self.rule.spread.max(Fixed::ZERO).min(Fixed::ONE);
+		let keep = Fixed::ONE - self.rule.decay.max(Fixed::ZERO).min(Fixed::ONE);
+		let row_length = tile_map.width as usize + 1;
+		let mut next = Vec::with_capacity(self.values.len());
+		for (idx, &value) in self.values.iter().enumerate() {
+			let coord = Coord::new_axial((idx % row_length) as u8, (idx / row_length) as u8);
+			let (mut sum, mut count) = (Fixed::ZERO, 0i16);
+			for neighbor in CoordOrientation::iter_neighbors_ring(1).filter_map(|co| {
+				coord.offset_by(co, tile_map.width, tile_map.height, tile_map.wraps_x)
+			}) {
+				if let Some(neighbor) =
+					neighbor.idx(tile_map.width, tile_map.height, tile_map.wraps_x)
+				{
+					if let Some(&neighbor) = self.values.get(neighbor) {
+						sum = sum.saturating_add(neighbor);
+						count += 1;
+					}
+				}
+			}
+			let value = if count == 0 {
+				value
+			} else {
+				let average = sum / Fixed::from_int(count);
+				value + spread * (average - value)
+			};
+			next.push(value * keep);
+		}
+		for &(coord, strength) in sources {
+			if let Some(idx) = coord.idx(tile_map.width, tile_map.height, tile_map.wraps_x) {
+				if let Some(value) = next.get_mut(idx) {
+					*value = value.saturating_add(strength);
+				}
+			}
+		}
+		self.values = next;
+		Ok(())
+	}
+}
+
+/// The named influence fields of one map.
+#[derive(Clone, Debug, Default)]
+pub struct InfluenceFields {
+	fields: Vec<(String, InfluenceField)>,
+}
+
+impl InfluenceFields {
+	/// Adds the field `name` if the map doesn't have it yet, returning it either way.
+	pub fn get_or_insert(
+		&mut self,
+		name: &str,
+		rule: InfluenceRule,
+		tile_map: &TileMap,
+	) -> &mut InfluenceField {
+		let idx = match self.fields.iter().position(|(n, _field)| n == name) {
+			Some(idx) => idx,
+			None => {
+				self.fields
+					.push((name.to_owned(), InfluenceField::new(rule, tile_map)));
+				self.fields.len() - 1
+			}
+		};
+		&mut self.fields[idx].1
+	}
+
+	pub fn get(&self, name: &str) -> Result<&InfluenceField, InfluenceError> {
+		self.fields
+			.iter()
+			.find(|(n, _field)| n == name)
+			.map(|(_name, field)| field)
+			.ok_or_else(|| InfluenceError::NoField(name.to_owned()))
+	}
+
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.fields.iter().map(|(name, _field)| name.as_str())
+	}
+}
+
+#[cfg(test)]
+mod influence_tests {
+	use super::*;
+	use crate::core::map::generator::test_maps::plain_map;
+
+	fn tile_map(wraps_x: bool) -> TileMap {
+		plain_map(8, 8, wraps_x)
+	}
+
+	fn rule(spread: i32, decay: i32) -> InfluenceRule {
+		InfluenceRule {
+			spread: Fixed::from_percent(spread),
+			decay: Fixed::from_percent(decay),
+		}
+	}
+
+	#[test]
+	fn spreads_outward_from_sources() {
+		let tile_map = tile_map(false);
+		let mut field = InfluenceField::new(rule(50, 0), &tile_map);
+		let source = Coord::new_axial(4, 4);
+		field
+			.diffuse(&tile_map, &[(source, Fixed::from_int(10))])
+			.unwrap();
+		assert_eq!(field.value_at(&tile_map, source), Fixed::from_int(10));
+		assert_eq!(field.max_value(), Fixed::from_int(10));
+		assert_eq!(
+			field.value_at(&tile_map, Coord::new_axial(5, 4)),
+			Fixed::ZERO
+		);
+		for _ in 0..4 {
+			field
+				.diffuse(&tile_map, &[(source, Fixed::from_int(10))])
+				.unwrap();
+		}
+		let near = field.value_at(&tile_map, Coord::new_axial(5, 4));
+		let far = field.value_at(&tile_map, Coord::new_axial(7, 4));
+		assert!(near > far && far > Fixed::ZERO, "{} {}", near, far);
+		assert!(field.value_at(&tile_map, source) > near);
+	}
+
+	#[test]
+	fn fades_without_sources() {
+		let tile_map = tile_map(true);
+		let mut field = InfluenceField::new(rule(25, 50), &tile_map);
+		field
+			.diffuse(&tile_map, &[(Coord::new_axial(0, 0), Fixed::from_int(8))])
+			.unwrap();
+		let mut last = field.max_value();
+		for _ in 0..10 {
+			field.diffuse(&tile_map, &[]).unwrap();
+			assert!(field.max_value() < last);
+			last = field.max_value();
+		}
+		// Wrapped around the seam
+		assert!(field.value_at(&tile_map, Coord::new_axial(8, 0)) > Fixed::ZERO);
+	}
+
+	#[test]
+	fn diffusing_is_repeatable() {
+		let tile_map = tile_map(true);
+		let sources = [
+			(Coord::new_axial(1, 1), Fixed::from_int(3)),
+			(Coord::new_axial(6, 7), Fixed::from_ratio(5, 2)),
+		];
+		let mut a = InfluenceField::new(rule(30, 10), &tile_map);
+		let mut b = a.clone();
+		for _ in 0..20 {
+			a.diffuse(&tile_map, &sources).unwrap();
+			b.diffuse(&tile_map, &sources).unwrap();
+		}
+		assert_eq!(a, b);
+	}
+
+	#[test]
+	fn named_fields() {
+		let tile_map = tile_map(false);
+		let mut fields = InfluenceFields::default();
+		fields
+			.get_or_insert("religion", rule(10, 0), &tile_map)
+			.diffuse(&tile_map, &[(Coord::new_axial(2, 2), Fixed::ONE)])
+			.unwrap();
+		// Already there, so the rule given is not used
+		fields.get_or_insert("religion", rule(90, 90), &tile_map);
+		fields.get_or_insert("ideology", rule(10, 0), &tile_map);
+		assert_eq!(fields.names().collect::<Vec<_>>(), ["religion", "ideology"]);
+		let religion = fields.get("religion").unwrap();
+		assert_eq!(religion.rule, rule(10, 0));
+		assert_eq!(religion.max_value(), Fixed::ONE);
+		assert_eq!(
+			fields.get("culture"),
+			Err(InfluenceError::NoField("culture".to_owned()))
+		);
+	}
+}
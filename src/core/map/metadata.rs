@@ -0,0 +1,125 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::map::generator::GeneratorInfo;
+
+/// What is known about how a map came to be, kept with it by the engine for map lists, exports
+/// and making the same map again. The seed it was made with is the map's own `TileMap::seed`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MapMetadata {
+	/// Shown to players, the map's name in the engine is still what commands and addresses use.
+	pub display_name: String,
+	pub generator: GeneratorInfo,
+	/// Seconds since the unix epoch, 0 when not known.
+	pub created: u64,
+}
+
+impl MapMetadata {
+	/// Metadata for a map generated just now.
+	pub fn new(display_name: impl Into<String>, generator: GeneratorInfo) -> MapMetadata {
+		let created = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |since| since.as_secs());
+		MapMetadata {
+			display_name: display_name.into(),
+			generator,
+			created,
+		}
+	}
+
+	/// As `# name: value` lines along with the map's seed, for formats that allow comments.
+	pub fn to_comments(&self, seed: u64) -> String {
+		let mut text = format!(
+			"# name: {}\n# generator: {}\n",
+			self.display_name, self.generator.name
+		);
+		for (name, value) in &self.generator.params {
+			text.push_str(&format!("# {}: {}\n", name, value));
+		}
+		text.push_str(&format!(
+			"# seed: {}\n# created: {}\n",
+			seed,
+			format_timestamp(self.created)
+		));
+		text
+	}
+}
+
+impl fmt::Display for MapMetadata {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}, {}", self.display_name, self.generator.name)?;
+		if !self.generator.params.is_empty() {
+			let params: Vec<String> = self
+				.generator
+				.params
+				.iter()
+				.map(|(name, value)| format!("{}={}", name, value))
+				.collect();
+			write!(f, " ({})", params.join(", "))?;
+		}
+		write!(f, ", created {}", format_timestamp(self.created))
+	}
+}
+
+/// Seconds since the unix epoch as a UTC date and time to the minute, `unknown` for 0.
+pub fn format_timestamp(seconds: u64) -> String {
+	if seconds == 0 {
+		return "unknown".to_owned();
+	}
+	// Days to a civil date, after Howard Hinnant's `civil_from_days`
+	let days = (seconds / 86400) as i64 + 719_468;
+	let era = days.div_euclid(146_097);
+	let day_of_era = days - era * 146_097;
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = if month_index < 10 {
+		month_index + 3
+	} else {
+		month_index - 9
+	};
+	let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+	let minutes = seconds % 86400 / 60;
+	format!(
+		"{:04}-{:02}-{:02} {:02}:{:02} UTC",
+		year,
+		month,
+		day,
+		minutes / 60,
+		minutes % 60
+	)
+}
+
+#[cfg(test)]
+mod metadata_tests {
+	use super::*;
+
+	#[test]
+	fn formats_timestamps() {
+		assert_eq!(format_timestamp(0), "unknown");
+		assert_eq!(format_timestamp(1), "1970-01-01 00:00 UTC");
+		assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00 UTC");
+		assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13 UTC");
+	}
+
+	#[test]
+	fn describes_the_map() {
+		let metadata = MapMetadata {
+			display_name: "Home".to_owned(),
+			generator: GeneratorInfo::new("alternating").with_param("tiles", "dirt,grass"),
+			created: 1_700_000_000,
+		};
+		assert_eq!(
+			metadata.to_string(),
+			"Home, alternating (tiles=dirt,grass), created 2023-11-14 22:13 UTC"
+		);
+		assert_eq!(
+			metadata.to_comments(42),
+			"# name: Home\n# generator: alternating\n# tiles: dirt,grass\n# seed: 42\n# created: 2023-11-14 22:13 UTC\n"
+		);
+	}
+}
@@ -1,4 +1,17 @@
+pub(crate) mod ambient;
 pub mod coord;
+pub(crate) mod decoration;
+#[cfg_attr(feature = "deterministic", deny(clippy::float_arithmetic))]
 pub mod generator;
+#[cfg_attr(feature = "deterministic", deny(clippy::float_arithmetic))]
+pub mod influence;
+pub mod metadata;
+#[cfg_attr(feature = "deterministic", deny(clippy::float_arithmetic))]
+pub mod path;
+pub mod territory;
+pub mod text_map;
 pub(crate) mod tile;
 pub(crate) mod tile_map;
+pub mod tiled;
+#[cfg_attr(feature = "deterministic", deny(clippy::float_arithmetic))]
+pub mod visibility;
@@ -0,0 +1,147 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use tracing::{field, trace_span};
+
+use crate::core::map::coord::{Coord, CoordOrientation};
+use crate::core::map::tile::Tile;
+use crate::core::map::tile_map::TileMap;
+
+/// The cheapest way from `from` to `to` by A*, both ends included, `None` when there is none.
+///
+/// `cost` is what entering a tile costs, `None` when it can't be entered at all, and anything
+/// below 1 counts as 1 so the distance stays a true lower bound. The start tile is never asked
+/// about. Paths go around the seam of a wrapping map, and ties are broken by tile order so the
+/// same map always gives the same path.
+pub fn find_path(
+	tile_map: &TileMap,
+	from: Coord,
+	to: Coord,
+	mut cost: impl FnMut(Coord, &Tile) -> Option<u32>,
+) -> Option<Vec<Coord>> {
+	let span = trace_span!("find_path", ?from, ?to, expanded = field::Empty);
+	let _enter = span.enter();
+	let mut expanded = 0u32;
+	let (width, height, wraps_x) = (tile_map.width, tile_map.height, tile_map.wraps_x);
+	let start = from.idx(width, height, wraps_x)?;
+	let goal = to.idx(width, height, wraps_x)?;
+	let row_length = width as usize + 1;
+	let coord_of =
+		|idx: usize| Coord::new_axial((idx % row_length) as u8, (idx / row_length) as u8);
+
+	let mut costs = vec![u32::MAX; tile_map.tiles.len()];
+	let mut came_from = vec![usize::MAX; tile_map.tiles.len()];
+	let mut open = BinaryHeap::new();
+	costs[start] = 0;
+	open.push(Reverse((tile_map.distance(from, to) as u32, start)));
+	while let Some(Reverse((_estimate, idx))) = open.pop() {
+		expanded += 1;
+		if idx == goal {
+			span.record("expanded", &expanded);
+			let mut path = vec![coord_of(goal)];
+			let mut at = goal;
+			while at != start {
+				at = came_from[at];
+				path.push(coord_of(at));
+			}
+			path.reverse();
+			return Some(path);
+		}
+		let coord = coord_of(idx);
+		let so_far = costs[idx];
+		for neighbor in CoordOrientation::iter_neighbors_ring(1)
+			.filter_map(|co| coord.offset_by(co, width, height, wraps_x))
+		{
+			let next = match neighbor.idx(width, height, wraps_x) {
+				Some(next) => next,
+				None => continue,
+			};
+			let step = match tile_map
+				.tiles
+				.get(next)
+				.and_then(|tile| cost(neighbor, tile))
+			{
+				Some(step) => step.max(1),
+				None => continue,
+			};
+			let total = so_far.saturating_add(step);
+			if total >= costs[next] {
+				continue;
+			}
+			costs[next] = total;
+			came_from[next] = idx;
+			let estimate = total.saturating_add(tile_map.distance(neighbor, to) as u32);
+			open.push(Reverse((estimate, next)));
+		}
+	}
+	span.record("expanded", &expanded);
+	None
+}
+
+#[cfg(test)]
+mod path_tests {
+	use super::*;
+	use crate::core::map::generator::test_maps::plain_map;
+
+	fn open(_coord: Coord, _tile: &Tile) -> Option<u32> {
+		Some(1)
+	}
+
+	fn is_connected(tile_map: &TileMap, path: &[Coord]) -> bool {
+		path.windows(2)
+			.all(|step| tile_map.distance(step[0], step[1]) == 1)
+	}
+
+	#[test]
+	fn shortest_on_open_ground() {
+		let tile_map = plain_map(7, 7, false);
+		let from = Coord::new_axial(0, 0);
+		let to = Coord::new_axial(5, 3);
+		let path = find_path(&tile_map, from, to, open).unwrap();
+		assert_eq!(path.first(), Some(&from));
+		assert_eq!(path.last(), Some(&to));
+		assert_eq!(path.len(), tile_map.distance(from, to) as usize + 1);
+		assert!(is_connected(&tile_map, &path));
+		assert_eq!(find_path(&tile_map, from, from, open), Some(vec![from]));
+	}
+
+	#[test]
+	fn goes_around_walls_and_costly_ground() {
+		let tile_map = plain_map(7, 7, false);
+		let from = Coord::new_axial(0, 3);
+		let to = Coord::new_axial(4, 3);
+		// A wall down column 2 with a gap at the bottom
+		let walled = |coord: Coord, _tile: &Tile| match coord.to_axial_tuple() {
+			(2, r) if r < 7 => None,
+			_ => Some(1),
+		};
+		let path = find_path(&tile_map, from, to, walled).unwrap();
+		assert!(path.contains(&Coord::new_axial(2, 7)));
+		assert!(is_connected(&tile_map, &path));
+		// Not a wall but dear enough that going around is cheaper
+		let swamp = |coord: Coord, _tile: &Tile| match coord.to_axial_tuple() {
+			(2, r) if r < 7 => Some(20),
+			_ => Some(1),
+		};
+		let waded = find_path(&tile_map, from, to, swamp).unwrap();
+		assert!(waded.contains(&Coord::new_axial(2, 7)));
+		assert_eq!(waded.len(), path.len());
+		let sealed = |coord: Coord, _tile: &Tile| match coord.to_axial_tuple() {
+			(2, _) => None,
+			_ => Some(1),
+		};
+		assert_eq!(find_path(&tile_map, from, to, sealed), None);
+	}
+
+	#[test]
+	fn crosses_the_seam() {
+		let tile_map = plain_map(9, 3, true);
+		let from = Coord::new_axial(1, 1);
+		let to = Coord::new_axial(8, 1);
+		let path = find_path(&tile_map, from, to, open).unwrap();
+		assert_eq!(path.len(), 4);
+		assert!(path.contains(&Coord::new_axial(0, 1)));
+		let flat = plain_map(9, 3, false);
+		assert_eq!(find_path(&flat, from, to, open).unwrap().len(), 8);
+	}
+}
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use thiserror::*;
+
+/// Who owns a tile, `0` is nobody and players are numbered from 1.
+pub type OwnerId = u8;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TerritoryError {
+	#[error("territory frames have {expected} tiles but this one has {tiles}")]
+	TileCountMismatch { expected: usize, tiles: usize },
+}
+
+/// The tile owners at the end of one turn, run length encoded as borders only move a little each
+/// turn so most of the map is long runs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerritoryFrame {
+	pub turn: u32,
+	/// Lengths and owners of each run of tiles in the linear order of the map.
+	runs: Vec<(u32, OwnerId)>,
+	/// Each player's score that turn, indexed by `OwnerId - 1`.
+	pub scores: Vec<u32>,
+}
+
+impl TerritoryFrame {
+	pub fn compress(turn: u32, owners: &[OwnerId], scores: Vec<u32>) -> TerritoryFrame {
+		let mut runs: Vec<(u32, OwnerId)> = vec![];
+		for &owner in owners {
+			match runs.last_mut() {
+				Some((length, last)) if *last == owner => *length += 1,
+				_ => runs.push((1, owner)),
+			}
+		}
+		TerritoryFrame { turn, runs, scores }
+	}
+
+	pub fn decompress(&self) -> Vec<OwnerId> {
+		let mut owners = Vec::with_capacity(self.tile_count());
+		for &(length, owner) in &self.runs {
+			owners.resize(owners.len() + length as usize, owner);
+		}
+		owners
+	}
+
+	pub fn tile_count(&self) -> usize {
+		self.runs
+			.iter()
+			.map(|&(length, _owner)| length as usize)
+			.sum()
+	}
+
+	/// How many tiles `owner` held that turn.
+	pub fn tiles_owned_by(&self, owner: OwnerId) -> usize {
+		self.runs
+			.iter()
+			.filter(|&&(_length, o)| o == owner)
+			.map(|&(length, _owner)| length as usize)
+			.sum()
+	}
+
+	pub fn score(&self, owner: OwnerId) -> Option<u32> {
+		let index = (owner as usize).checked_sub(1)?;
+		self.scores.get(index).copied()
+	}
+}
+
+/// A frame of every turn of a game, for the end of game timeline to scrub through.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerritoryHistory {
+	/// In turn order.
+	frames: Vec<TerritoryFrame>,
+}
+
+impl TerritoryHistory {
+	pub fn new() -> TerritoryHistory {
+		TerritoryHistory::default()
+	}
+
+	/// Records the owners of every tile in linear order at the end of `turn`. Frames already
+	/// recorded for `turn` or later are dropped, as that's a turn being played again after a load.
+	pub fn record(
+		&mut self,
+		turn: u32,
+		owners: &[OwnerId],
+		scores: Vec<u32>,
+	) -> Result<(), TerritoryError> {
+		if let Some(first) = self.frames.first() {
+			let expected = first.tile_count();
+			if expected != owners.len() {
+				return Err(TerritoryError::TileCountMismatch {
+					expected,
+					tiles: owners.len(),
+				});
+			}
+		}
+		self.frames.retain(|frame| frame.turn < turn);
+		self.frames
+			.push(TerritoryFrame::compress(turn, owners, scores));
+		Ok(())
+	}
+
+	pub fn frames(&self) -> &[TerritoryFrame] {
+		&self.frames
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.frames.is_empty()
+	}
+
+	/// The frame showing the borders as they were during `turn`, the last one recorded at or
+	/// before it as turns can be skipped.
+	pub fn frame_at(&self, turn: u32) -> Option<&TerritoryFrame> {
+		match self.frames.binary_search_by_key(&turn, |frame| frame.turn) {
+			Ok(index) => Some(&self.frames[index]),
+			Err(after) => after.checked_sub(1).map(|index| &self.frames[index]),
+		}
+	}
+
+	/// The first and last turns recorded.
+	pub fn turns(&self) -> Option<(u32, u32)> {
+		Some((self.frames.first()?.turn, self.frames.last()?.turn))
+	}
+
+	/// `owner`'s score each recorded turn, for the score graph.
+	pub fn score_history(&self, owner: OwnerId) -> impl Iterator<Item = (u32, u32)> + '_ {
+		self.frames
+			.iter()
+			.filter_map(move |frame| Some((frame.turn, frame.score(owner)?)))
+	}
+}
+
+#[cfg(test)]
+mod territory_tests {
+	use super::*;
+
+	#[test]
+	fn frames_compress_and_decompress() {
+		let owners = [0, 0, 0, 1, 1, 2, 0, 0];
+		let frame = TerritoryFrame::compress(3, &owners, vec![10, 20]);
+		assert_eq!(frame.runs.len(), 4);
+		assert_eq!(frame.decompress(), owners);
+		assert_eq!(frame.tiles_owned_by(0), 5);
+		assert_eq!(frame.tiles_owned_by(1), 2);
+		assert_eq!(frame.score(2), Some(20));
+		assert_eq!(frame.score(0), None);
+	}
+
+	#[test]
+	fn history_finds_frames_and_replays_turns() {
+		let mut history = TerritoryHistory::new();
+		history.record(1, &[0, 0, 1], vec![1]).unwrap();
+		history.record(2, &[0, 1, 1], vec![2]).unwrap();
+		history.record(4, &[1, 1, 1], vec![4]).unwrap();
+		assert_eq!(history.frame_at(0), None);
+		assert_eq!(history.frame_at(3).unwrap().turn, 2);
+		assert_eq!(history.frame_at(9).unwrap().turn, 4);
+		assert_eq!(history.turns(), Some((1, 4)));
+		assert_eq!(
+			history.score_history(1).collect::<Vec<_>>(),
+			vec![(1, 1), (2, 2), (4, 4)]
+		);
+
+		// Loaded back to turn 2 and played it again
+		history.record(2, &[0, 0, 0], vec![0]).unwrap();
+		assert_eq!(history.frames().len(), 2);
+		assert_eq!(history.frame_at(9).unwrap().decompress(), vec![0, 0, 0]);
+
+		assert_eq!(
+			history.record(3, &[0, 0], vec![]),
+			Err(TerritoryError::TileCountMismatch {
+				expected: 3,
+				tiles: 2
+			})
+		);
+		assert_eq!(history.frames().len(), 2);
+	}
+}
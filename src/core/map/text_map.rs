@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use thiserror::*;
+
+use crate::core::engine::io::{EngineIO, WriteMode};
+use crate::core::map::coord::Coord;
+use crate::core::map::generator::{GeneratorInfo, MapGenerator};
+use crate::core::map::tile::{Tile, TileIdx, TileTypes};
+use crate::core::map::tile_map::TileMap;
+
+const SEPARATOR: &str = "---";
+
+/// Symbols handed out to tile types whose first letter is already taken.
+const SPARE_SYMBOLS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// A map written as plain text, for test fixtures and sketching maps in a text editor.
+///
+/// A header of `<symbol> = <tile type>` legend lines, and optionally `wraps = true`, is ended by
+/// a `---` line. Each line after it is a row of the map from `r` 0 down, holding a symbol per tile
+/// or, when the line has a comma, tile type names separated by commas which need no legend.
+/// Blank lines and lines starting with `#` are skipped everywhere.
+///
+/// ```text
+/// # A small island
+/// ~ = water
+/// g = grass
+/// ---
+/// ~~~~
+/// ~gg~
+/// ~~~~
+/// ```
+#[derive(Debug)]
+pub struct TextMapGenerator {
+	/// Largest axial coordinates of the map, as given to `Engine::generate_map`.
+	pub max_x: u8,
+	pub max_y: u8,
+	pub wraps_x: bool,
+	/// The file it was loaded from, kept in the metadata of its map.
+	pub path: Option<String>,
+	tiles: Vec<TileIdx>,
+}
+
+#[derive(Error, Debug)]
+pub enum TextMapError<IO: EngineIO>
+where
+	IO::ReadError: 'static,
+	IO::WriteError: 'static,
+{
+	#[error("failed to open text map file")]
+	FileReadError {
+		source: IO::ReadError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to open text map file for writing")]
+	FileWriteError {
+		source: IO::WriteError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed reading or writing text map")]
+	IOError {
+		#[from]
+		source: std::io::Error,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("line {0} of the header is neither `<symbol> = <tile type>` nor `wraps = <bool>`")]
+	BadHeaderLine(usize),
+
+	#[error("symbol `{0}` is in the legend twice")]
+	DuplicateSymbol(char),
+
+	#[error("no `---` line ending the header")]
+	MissingSeparator,
+
+	#[error("symbol `{0}` on line {1} is not in the legend")]
+	UnknownSymbol(char, usize),
+
+	#[error("no tile type named `{0}`")]
+	UnknownTileType(String),
+
+	#[error("line {line} has {found} tiles where the first row has {expected}")]
+	RaggedRow {
+		line: usize,
+		found: usize,
+		expected: usize,
+	},
+
+	#[error("the map has no rows")]
+	Empty,
+
+	#[error("a {0}x{1} map is over 256 tiles a side")]
+	MapTooLarge(usize, usize),
+}
+
+impl TextMapGenerator {
+	pub fn load<IO: EngineIO>(
+		io: &mut IO,
+		path: &Path,
+		tile_types: &TileTypes<IO>,
+	) -> Result<TextMapGenerator, TextMapError<IO>> {
+		let mut text = String::new();
+		io.read(path)
+			.map_err(|source| TextMapError::FileReadError { source })?
+			.read_to_string(&mut text)?;
+		let mut generator = Self::parse(&text, tile_types)?;
+		generator.path = Some(path.display().to_string());
+		Ok(generator)
+	}
+
+	pub fn parse<IO: EngineIO>(
+		text: &str,
+		tile_types: &TileTypes<IO>,
+	) -> Result<TextMapGenerator, TextMapError<IO>> {
+		let tile_type = |name: &str| -> Result<TileIdx, TextMapError<IO>> {
+			tile_types
+				.tile_types
+				.get_index_of(name)
+				.ok_or_else(|| TextMapError::UnknownTileType(name.to_owned()))
+		};
+		let mut lines = text
+			.lines()
+			.enumerate()
+			.map(|(index, line)| (index + 1, line.trim_end()))
+			.filter(|(_number, line)| !line.is_empty() && !line.starts_with('#'));
+
+		let mut legend = HashMap::new();
+		let mut wraps_x = false;
+		loop {
+			let (number, line) = lines.next().ok_or(TextMapError::MissingSeparator)?;
+			if line == SEPARATOR {
+				break;
+			}
+			let (key, value) = match line.find('=') {
+				Some(split) => (line[..split].trim(), line[split + 1..].trim()),
+				None => return Err(TextMapError::BadHeaderLine(number)),
+			};
+			let mut symbol = key.chars();
+			match (key, symbol.next(), symbol.next()) {
+				("wraps", _, _) => {
+					wraps_x = value
+						.parse()
+						.map_err(|_| TextMapError::BadHeaderLine(number))?
+				}
+				(_, Some(symbol), None) if !value.is_empty() => {
+					if legend.insert(symbol, tile_type(value)?).is_some() {
+						return Err(TextMapError::DuplicateSymbol(symbol));
+					}
+				}
+				_ => return Err(TextMapError::BadHeaderLine(number)),
+			}
+		}
+
+		let mut tiles = vec![];
+		let mut width = None;
+		let mut height = 0;
+		for (number, line) in lines {
+			let row_start = tiles.len();
+			if line.contains(',') {
+				for name in line.split(',') {
+					tiles.push(tile_type(name.trim())?);
+				}
+			} else {
+				for symbol in line.chars() {
+					match legend.get(&symbol) {
+						Some(&tile) => tiles.push(tile),
+						None => return Err(TextMapError::UnknownSymbol(symbol, number)),
+					}
+				}
+			}
+			let found = tiles.len() - row_start;
+			match width {
+				None => width = Some(found),
+				Some(expected) if expected != found => {
+					return Err(TextMapError::RaggedRow {
+						line: number,
+						found,
+						expected,
+					})
+				}
+				Some(_) => (),
+			}
+			height += 1;
+		}
+
+		let width = width.ok_or(TextMapError::Empty)?;
+		if width > 256 || height > 256 {
+			return Err(TextMapError::MapTooLarge(width, height));
+		}
+		Ok(TextMapGenerator {
+			max_x: (width - 1) as u8,
+			max_y: (height - 1) as u8,
+			wraps_x,
+			path: None,
+			tiles,
+		})
+	}
+}
+
+impl MapGenerator for TextMapGenerator {
+	fn generate(&mut self, tile_map: &mut TileMap) -> anyhow::Result<()> {
+		if (tile_map.width, tile_map.height) != (self.max_x, self.max_y) {
+			anyhow::bail!(
+				"text map is {}x{} but was generated as {}x{}",
+				self.max_x,
+				self.max_y,
+				tile_map.width,
+				tile_map.height
+			);
+		}
+		tile_map.tiles.clear();
+		tile_map
+			.tiles
+			.extend(self.tiles.iter().map(|&tile| Tile::new(tile)));
+		Ok(())
+	}
+
+	fn info(&self) -> GeneratorInfo {
+		let info = GeneratorInfo::new("text");
+		match &self.path {
+			Some(path) => info.with_param("path", path),
+			None => info,
+		}
+	}
+}
+
+/// Writes `tile_map` in the format `TextMapGenerator` reads, with a symbol per tile type used.
+///
+/// A symbol is the first letter of the tile type's name where no other type took it first,
+/// otherwise a spare letter or digit. Maps using more tile types than there are symbols are written
+/// as comma separated names instead.
+pub fn to_text<IO: EngineIO>(tile_map: &TileMap, tile_types: &TileTypes<IO>) -> String {
+	let mut used: Vec<TileIdx> = vec![];
+	for tile in &tile_map.tiles {
+		if !used.contains(&tile.id) {
+			used.push(tile.id);
+		}
+	}
+	let name = |tile: TileIdx| {
+		tile_types
+			.tile_types
+			.get_index(tile)
+			.map_or("", |(name, _tile_type)| name.as_str())
+	};
+
+	let mut symbols: HashMap<TileIdx, char> = HashMap::new();
+	for &tile in &used {
+		let first = name(tile).chars().next().filter(|c| c.is_alphanumeric());
+		let taken = |c: &char| symbols.values().any(|s| s == c);
+		let symbol = first
+			.filter(|c| !taken(c))
+			.or_else(|| SPARE_SYMBOLS.chars().find(|c| !taken(c)));
+		match symbol {
+			Some(symbol) => symbols.insert(tile, symbol),
+			None => {
+				symbols.clear();
+				break;
+			}
+		};
+	}
+
+	let mut text = String::new();
+	for &tile in &used {
+		if let Some(symbol) = symbols.get(&tile) {
+			text.push_str(&format!("{} = {}\n", symbol, name(tile)));
+		}
+	}
+	if tile_map.wraps_x {
+		text.push_str("wraps = true\n");
+	}
+	text.push_str(SEPARATOR);
+	text.push('\n');
+	for r in 0..=tile_map.height {
+		let row = (0..=tile_map.width).filter_map(|q| tile_map.get_tile(Coord::new_axial(q, r)));
+		if symbols.is_empty() {
+			let names: Vec<&str> = row.map(|tile| name(tile.id)).collect();
+			text.push_str(&names.join(","));
+		} else {
+			text.extend(row.map(|tile| symbols[&tile.id]));
+		}
+		text.push('\n');
+	}
+	text
+}
+
+/// Writes `tile_map` as text to `path` in the user data directory, see `to_text`, after its
+/// metadata as comments.
+pub fn save_text_map<IO: EngineIO>(
+	io: &mut IO,
+	path: &Path,
+	tile_map: &TileMap,
+	tile_types: &TileTypes<IO>,
+) -> Result<(), TextMapError<IO>> {
+	let text = tile_map.metadata.to_comments(tile_map.seed) + &to_text(tile_map, tile_types);
+	io.write(path, WriteMode::Truncate)
+		.map_err(|source| TextMapError::FileWriteError { source })?
+		.write_all(text.as_bytes())?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod text_map_tests {
+	use super::*;
+	use crate::core::map::generator::test_io::{tile_types, DummyIO};
+
+	#[test]
+	fn symbols_and_names_read_the_same() {
+		let tile_types = tile_types(&["grass", "water"]);
+		let index = |name: &str| tile_types.tile_types.get_index_of(name).unwrap();
+		let mut generator = TextMapGenerator::parse(
+			"# Comments and blank lines are skipped\n\n~ = water\ng = grass\nwraps = true\n---\n~g~\nwater, grass, grass\n",
+			&tile_types,
+		)
+		.unwrap();
+		assert_eq!((generator.max_x, generator.max_y), (2, 1));
+		assert!(generator.wraps_x);
+		let tile_map = TileMap::new(2, 1, true, &mut generator).unwrap();
+		let at = |q, r| tile_map.get_tile(Coord::new_axial(q, r)).unwrap().id;
+		assert_eq!(at(0, 0), index("water"));
+		assert_eq!(at(1, 0), index("grass"));
+		assert_eq!(at(2, 0), index("water"));
+		assert_eq!(at(0, 1), index("water"));
+		assert_eq!(at(2, 1), index("grass"));
+	}
+
+	#[test]
+	fn written_maps_read_back() {
+		let tile_types = tile_types(&["grass", "gravel", "water"]);
+		let mut generator = TextMapGenerator::parse(
+			"g = grass\nr = gravel\nw = water\n---\ngrw\nwwg\n",
+			&tile_types,
+		)
+		.unwrap();
+		let tile_map = TileMap::new(2, 1, false, &mut generator).unwrap();
+		let text = to_text(&tile_map, &tile_types);
+		// Gravel can't have `g` so gets the first spare symbol
+		assert_eq!(text, "g = grass\na = gravel\nw = water\n---\ngaw\nwwg\n");
+		// As saved, with the metadata as comments in front
+		let text = tile_map.metadata.to_comments(tile_map.seed) + &text;
+		let mut generator = TextMapGenerator::parse(&text, &tile_types).unwrap();
+		let read_back = TileMap::new(2, 1, false, &mut generator).unwrap();
+		let ids = |tile_map: &TileMap| tile_map.tiles.iter().map(|t| t.id).collect::<Vec<_>>();
+		assert_eq!(ids(&read_back), ids(&tile_map));
+	}
+
+	#[test]
+	fn mistakes_are_reported_with_their_line() {
+		let tile_types = tile_types(&["grass"]);
+		let parse = |text| TextMapGenerator::parse::<DummyIO>(text, &tile_types).map(|_| ());
+		match parse("g = grass\n---\ngg\nggg\n") {
+			Err(TextMapError::RaggedRow {
+				line: 4,
+				found: 3,
+				expected: 2,
+			}) => (),
+			other => panic!("ragged row not rejected: {:?}", other),
+		}
+		match parse("g = grass\n---\ngx\n") {
+			Err(TextMapError::UnknownSymbol('x', 3)) => (),
+			other => panic!("unknown symbol not rejected: {:?}", other),
+		}
+		match parse("g = sand\n---\ng\n") {
+			Err(TextMapError::UnknownTileType(name)) => assert_eq!(name, "sand"),
+			other => panic!("unknown tile type not rejected: {:?}", other),
+		}
+		match parse("g = grass\n") {
+			Err(TextMapError::MissingSeparator) => (),
+			other => panic!("missing separator not rejected: {:?}", other),
+		}
+	}
+}
@@ -1,32 +1,47 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use shipyard::EntityId;
 use thiserror::*;
 
 use crate::core::engine::io::EngineIO;
+use crate::core::engine::schema::{self, SchemaError, Schemas};
+use crate::core::map::decoration::DecorationIdx;
+use crate::core::structures::small_set::SmallSet;
 use crate::core::structures::typed_index_map::{
 	TypedIndexMap, TypedIndexMapError, TypedIndexMapIndex,
 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum TileTypesMap {}
 
 pub type TileIdx = TypedIndexMapIndex<TileTypesMap, u16>;
 
+/// Nearly every tile holds zero or one entity, so keep a couple inline before spilling.
+pub type TileEntities = SmallSet<[EntityId; 2]>;
+
 #[derive(Debug)]
 pub struct Tile {
 	pub id: TileIdx,
-	pub entities: HashSet<EntityId>,
+	pub entities: TileEntities,
+	/// Visual dressing kept apart from the tile type so it can be cleared without changing it.
+	pub decoration: Option<DecorationIdx>,
 }
 
 impl Tile {
 	pub(crate) fn new(id: TileIdx) -> Tile {
 		Tile {
 			id,
-			entities: HashSet::new(),
+			entities: TileEntities::new(),
+			decoration: None,
 		}
 	}
+
+	/// Removes the decoration, such as when an improvement is built or the tile is pillaged.
+	pub fn take_decoration(&mut self) -> Option<DecorationIdx> {
+		self.decoration.take()
+	}
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -38,6 +53,17 @@ pub struct TileType<IO: EngineIO> {
 #[derive(Debug)]
 pub struct TileTypes<IO: EngineIO> {
 	pub tile_types: TypedIndexMap<TileTypesMap, String, TileType<IO>, u16>,
+	/// The file each tile type was last defined in, the built in `unknown` isn't in here.
+	origins: HashMap<String, PathBuf>,
+}
+
+/// A tile type defined again by a later tile types file, which replaced the earlier definition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TileTypeConflict {
+	pub name: String,
+	/// `None` when it was the built in `unknown` tile type.
+	pub previous: Option<PathBuf>,
+	pub replaced_by: PathBuf,
 }
 
 #[derive(Error, Debug)]
@@ -53,7 +79,7 @@ where
 
 	#[error("failed to parse tiledata information")]
 	FileParseError {
-		source: ron::error::Error,
+		source: SchemaError,
 		//backtrace: Backtrace, // Still needs nightly...
 	},
 
@@ -63,9 +89,6 @@ where
 	)]
 	TileTypesFilled(TileType<IO>),
 
-	#[error("tile types have already been loaded")]
-	TileTypesAlreadyFilled(),
-
 	#[error("generic invalid tile type data with message: {0}")]
 	InvalidTileTypeData(String, TileType<IO>),
 
@@ -84,6 +107,7 @@ impl<IO: EngineIO> TileTypes<IO> {
 	pub(crate) fn new() -> TileTypes<IO> {
 		TileTypes {
 			tile_types: TypedIndexMap::new(),
+			origins: HashMap::new(),
 		}
 	}
 
@@ -91,7 +115,7 @@ impl<IO: EngineIO> TileTypes<IO> {
 		&mut self,
 		io: &mut IO,
 		mut tile_type: TileType<IO>,
-	) -> Result<(), TileTypesError<IO>> {
+	) -> Result<TileIdx, TileTypesError<IO>> {
 		if tile_type.name.is_empty() {
 			return Err(TileTypesError::InvalidTileTypeData(
 				"name is empty".into(),
@@ -120,7 +144,7 @@ impl<IO: EngineIO> TileTypes<IO> {
 				.expect("unable to lookup just inserted value")
 				.1,
 		) {
-			Ok(()) => Ok(()),
+			Ok(()) => Ok(index),
 			Err(source) => {
 				let (_name, tile_type) = self
 					.tile_types
@@ -131,31 +155,99 @@ impl<IO: EngineIO> TileTypes<IO> {
 		}
 	}
 
-	pub(crate) fn load_tiles(&mut self, io: &mut IO) -> Result<(), TileTypesError<IO>> {
-		if !self.tile_types.is_empty() {
-			return Err(TileTypesError::TileTypesAlreadyFilled());
+	/// The built in `unknown` tile type is always index 0.
+	fn add_unknown_tile(&mut self, io: &mut IO) -> Result<(), TileTypesError<IO>> {
+		if self.tile_types.is_empty() {
+			self.add_tile(
+				io,
+				TileType {
+					name: "unknown".into(),
+					interface: IO::blank_tile_interface(),
+				},
+			)?;
 		}
+		Ok(())
+	}
 
-		self.add_tile(
-			io,
-			TileType {
-				name: "unknown".into(),
-				interface: IO::blank_tile_interface(),
-			},
-		)?;
+	/// Adds a tile type that isn't from a file, one already named like it is an error.
+	pub(crate) fn register_tile(
+		&mut self,
+		io: &mut IO,
+		tile_type: TileType<IO>,
+	) -> Result<TileIdx, TileTypesError<IO>> {
+		self.add_unknown_tile(io)?;
+		self.add_tile(io, tile_type)
+	}
+
+	/// Gives the tile type at `index` a new definition, telling the IO about it as if it was added.
+	fn replace_tile(
+		&mut self,
+		io: &mut IO,
+		index: TileIdx,
+		mut tile_type: TileType<IO>,
+	) -> Result<(), TileTypesError<IO>> {
+		if let Err(source) = io.tile_added(index, &mut tile_type) {
+			return Err(TileTypesError::EngineIORegisterTileError { source, tile_type });
+		}
+		*self
+			.tile_types
+			.get_index_mut(index)
+			.expect("unable to lookup replaced value")
+			.1 = tile_type;
+		Ok(())
+	}
+
+	/// Adds the tile types in the file at `path`, the first file loaded also adds the built in
+	/// `unknown` tile type at index 0. Files are merged by name, a tile type already defined by an
+	/// earlier file is replaced in place, keeping its index, and returned as a conflict.
+	pub(crate) fn load_tiles(
+		&mut self,
+		io: &mut IO,
+		schemas: &Schemas,
+		path: &Path,
+	) -> Result<Vec<TileTypeConflict>, TileTypesError<IO>> {
+		self.add_unknown_tile(io)?;
 
 		let reader = io
-			.read(std::path::Path::new("tiles/tile_types.ron"))
+			.read(path)
 			.map_err(|source| TileTypesError::FileReadError { source })?;
 
-		let tile_types: Vec<TileType<IO>> = ron::de::from_reader(reader)
+		let mut tile_types: Vec<TileType<IO>> = schemas
+			.read(schema::TILE_TYPES, reader)
 			.map_err(|source| TileTypesError::FileParseError { source })?;
 
+		// Only files replace each other, a name twice in one file is a mistake. It is looked for
+		// before anything is merged so a bad file leaves the tile types as they were.
+		let mut names = HashSet::with_capacity(tile_types.len());
+		if let Some(duplicate) = tile_types
+			.iter()
+			.position(|tile_type| !names.insert(tile_type.name.as_str()))
+		{
+			return Err(TileTypesError::DuplicateTileTypeName(
+				tile_types.swap_remove(duplicate),
+			));
+		}
+
+		let mut conflicts = vec![];
 		for tile_type in tile_types {
-			self.add_tile(io, tile_type)?;
+			let name = tile_type.name.clone();
+			match self.tile_types.get_index_of(&name) {
+				Some(index) => {
+					self.replace_tile(io, index, tile_type)?;
+					conflicts.push(TileTypeConflict {
+						previous: self.origins.get(&name).cloned(),
+						name: name.clone(),
+						replaced_by: path.to_owned(),
+					});
+				}
+				None => {
+					self.add_tile(io, tile_type)?;
+				}
+			}
+			self.origins.insert(name, path.to_owned());
 		}
 
-		Ok(())
+		Ok(conflicts)
 	}
 
 	// pub fn get_index<IO: EngineIO>(
@@ -195,18 +287,31 @@ impl<IO: EngineIO> TileTypes<IO> {
 #[cfg(test)]
 mod tile_tests {
 	use super::*;
+	use crate::core::engine::io::WriteMode;
+	use crate::core::map::generator::test_io::DummyIO;
 	use proptest::prelude::*;
 	use std::{convert::Infallible, hash::Hasher, path::Path};
 
-	#[derive(Debug, Default, Eq, PartialEq)]
-	struct DummyIO {}
+	/// Serves each path's data and records the tile types the IO was told about.
+	#[derive(Debug, Default)]
+	struct FilesIO {
+		files: HashMap<&'static str, &'static [u8]>,
+		added: Vec<(usize, String)>,
+	}
 
-	impl EngineIO for DummyIO {
+	impl EngineIO for FilesIO {
 		type ReadError = Infallible;
 		type Read = &'static [u8];
 
-		fn read(&mut self, _: &Path) -> Result<Self::Read, Self::ReadError> {
-			Ok(b"")
+		fn read(&mut self, path: &Path) -> Result<Self::Read, Self::ReadError> {
+			Ok(self.files[path.to_str().unwrap()])
+		}
+
+		type WriteError = Infallible;
+		type Write = std::io::Sink;
+
+		fn write(&mut self, _: &Path, _: WriteMode) -> Result<Self::Write, Self::WriteError> {
+			Ok(std::io::sink())
 		}
 
 		type TileInterface = ();
@@ -217,9 +322,10 @@ mod tile_tests {
 
 		fn tile_added(
 			&mut self,
-			_: TileIdx,
-			_: &mut TileType<Self>,
+			index: TileIdx,
+			tile_type: &mut TileType<Self>,
 		) -> Result<(), Self::TileAddedError> {
+			self.added.push((index.into(), tile_type.name.clone()));
 			Ok(())
 		}
 	}
@@ -317,4 +423,82 @@ mod tile_tests {
 			panic!("empty string not marked as error");
 		}
 	}
+
+	#[test]
+	fn tile_type_files_merge_by_name() {
+		let mut io = FilesIO::default();
+		io.files.insert(
+			"base.ron",
+			b"[(name: \"dirt\", interface: ()), (name: \"grass\", interface: ())]",
+		);
+		io.files.insert(
+			"mod.ron",
+			b"[(name: \"sand\", interface: ()), (name: \"grass\", interface: ())]",
+		);
+		io.files.insert(
+			"twice.ron",
+			b"[(name: \"dirt\", interface: ()), (name: \"rock\", interface: ()), (name: \"rock\", interface: ())]",
+		);
+		let schemas = Schemas::new();
+		let mut tts = TileTypes::new();
+		let conflicts = tts
+			.load_tiles(&mut io, &schemas, Path::new("base.ron"))
+			.unwrap();
+		assert!(conflicts.is_empty());
+		let conflicts = tts
+			.load_tiles(&mut io, &schemas, Path::new("mod.ron"))
+			.unwrap();
+		assert_eq!(
+			conflicts,
+			vec![TileTypeConflict {
+				name: "grass".to_owned(),
+				previous: Some(PathBuf::from("base.ron")),
+				replaced_by: PathBuf::from("mod.ron"),
+			}]
+		);
+		let names: Vec<&str> = tts.tile_types.keys().map(|s| s.as_str()).collect();
+		assert_eq!(names, vec!["unknown", "dirt", "grass", "sand"]);
+		// The replaced grass keeps its index
+		assert_eq!(io.added.last(), Some(&(2, "grass".to_owned())));
+
+		let added = io.added.len();
+		match tts.load_tiles(&mut io, &schemas, Path::new("twice.ron")) {
+			Err(TileTypesError::DuplicateTileTypeName(tile_type)) => {
+				assert_eq!(tile_type.name, "rock")
+			}
+			other => panic!(
+				"duplicate in one file not rejected: {:?}",
+				other.map(|_| ())
+			),
+		}
+		// Nothing from the rejected file was merged, not even what came before the duplicate
+		let names: Vec<&str> = tts.tile_types.keys().map(|s| s.as_str()).collect();
+		assert_eq!(names, vec!["unknown", "dirt", "grass", "sand"]);
+		assert_eq!(io.added.len(), added);
+		assert_eq!(tts.origins["dirt"], PathBuf::from("base.ron"));
+	}
+
+	#[test]
+	fn registered_tile_types_are_added_after_unknown() {
+		let mut io = DummyIO::default();
+		let mut tts = TileTypes::new();
+		let water = TileType {
+			name: "water".to_owned(),
+			interface: (),
+		};
+		let index: usize = tts.register_tile(&mut io, water).unwrap().into();
+		assert_eq!(index, 1);
+		let water = TileType {
+			name: "water".to_owned(),
+			interface: (),
+		};
+		match tts.register_tile(&mut io, water) {
+			Err(TileTypesError::DuplicateTileTypeName(tile_type)) => {
+				assert_eq!(tile_type.name, "water")
+			}
+			other => panic!("duplicate not rejected: {:?}", other.map(|_| ())),
+		}
+		let names: Vec<&str> = tts.tile_types.keys().map(|s| s.as_str()).collect();
+		assert_eq!(names, vec!["unknown", "water"]);
+	}
 }
@@ -1,7 +1,11 @@
 use thiserror::*;
 
-use crate::core::map::coord::{Coord, CoordOrientation, CoordOrientationNeighborIterator};
+use crate::core::map::ambient::MapAmbientIdx;
+use crate::core::map::coord::{
+	Coord, CoordOrientation, CoordOrientationNeighborIterator, HexOrientation, TileCoord,
+};
 use crate::core::map::generator::MapGenerator;
+use crate::core::map::metadata::MapMetadata;
 use crate::core::map::tile::Tile;
 
 #[derive(Error, Debug)]
@@ -17,15 +21,26 @@ pub enum TileMapError
 	},
 }
 
+/// The tiles of a map, addressed by `Coord` unless it is made with `Coord32` for maps past 256
+/// tiles a side. Only the addressing works on the wide maps so far, the rest of the engine and the
+/// games built on it take the `Coord` ones.
 #[derive(Debug)]
-pub struct TileMap {
-	pub width: u8,
-	pub height: u8,
+pub struct TileMap<C: TileCoord = Coord> {
+	/// The largest q, one less than the columns.
+	pub width: C::Extent,
+	/// The largest r, one less than the rows.
+	pub height: C::Extent,
 	pub wraps_x: bool, // I.E. a planet
+	/// Set by the generator if it has one, also seeds purely cosmetic per-tile choices.
+	pub seed: u64,
+	/// How the map looks outside of its tiles, see `Engine::set_map_ambient`.
+	pub ambient: Option<MapAmbientIdx>,
+	/// Filled in by the engine for the maps it generates.
+	pub metadata: MapMetadata,
 	pub tiles: Vec<Tile>,
 }
 
-impl TileMap {
+impl<C: TileCoord> TileMap<C> {
 	/// Creates a new TileMap
 	///
 	/// ```
@@ -35,16 +50,21 @@ impl TileMap {
 	/// //let max_tile_map = over_simple_game_1::TileMap::new(255, 255, true, false);
 	/// ```
 	pub fn new(
-		width: u8,
-		height: u8,
+		width: C::Extent,
+		height: C::Extent,
 		wraps_x: bool,
-		generator: &mut impl MapGenerator,
-	) -> Result<TileMap, TileMapError> {
+		generator: &mut impl MapGenerator<C>,
+	) -> Result<TileMap<C>, TileMapError> {
+		let columns = width.into() as usize + 1;
+		let rows = height.into() as usize + 1;
 		let mut tile_map = TileMap {
 			width,
 			height,
 			wraps_x,
-			tiles: Vec::with_capacity((width as usize + 1) * (height as usize + 1)),
+			seed: 0,
+			ambient: None,
+			metadata: MapMetadata::default(),
+			tiles: Vec::with_capacity(columns * rows),
 		};
 
 		generator
@@ -54,27 +74,26 @@ impl TileMap {
 		Ok(tile_map)
 	}
 
-	pub fn get_tile(&self, c: Coord) -> Option<&Tile> {
+	pub fn get_tile(&self, c: C) -> Option<&Tile> {
 		let idx = c.idx(self.width, self.height, self.wraps_x)?;
 		Some(&self.tiles[idx])
 	}
 
-	pub fn get_tile_mut(&mut self, c: Coord) -> Option<&mut Tile> {
+	pub fn get_tile_mut(&mut self, c: C) -> Option<&mut Tile> {
 		let idx = c.idx(self.width, self.height, self.wraps_x)?;
 		Some(&mut self.tiles[idx])
 	}
 
-	pub fn coord_to_in_map_bounds(&self, coord: Coord) -> Coord {
-		let q = coord.q().rem_euclid(self.width + 1);
-		let r = coord.r().rem_euclid(self.height + 1);
-		Coord::new_axial(q, r)
+	pub fn coord_to_in_map_bounds(&self, coord: C) -> C {
+		let (q, r) = coord.axial_u32();
+		C::from_axial_u32(q % (self.width.into() + 1), r % (self.height.into() + 1))
 	}
 
 	pub fn iter_neighbors_around(
 		&self,
-		center: Coord,
+		center: C,
 		distance: u8,
-	) -> TileMapNeighborsAroundIterator {
+	) -> TileMapNeighborsAroundIterator<C> {
 		TileMapNeighborsAroundIterator {
 			map: self,
 			center,
@@ -83,13 +102,57 @@ impl TileMap {
 	}
 }
 
-pub struct TileMapNeighborsAroundIterator<'a> {
-	map: &'a TileMap,
-	center: Coord,
+impl TileMap {
+	/// Steps between two tiles, going around the seam when the map wraps and that is shorter.
+	pub fn distance(&self, from: Coord, to: Coord) -> u16 {
+		let columns = self.width as i16 + 1;
+		let dq = to.q() as i16 - from.q() as i16;
+		let dr = to.r() as i16 - from.r() as i16;
+		let hex_distance = |dq: i16| dq.abs().max(dr.abs()).max((dq + dr).abs()) as u16;
+		if self.wraps_x {
+			hex_distance(dq)
+				.min(hex_distance(dq - columns))
+				.min(hex_distance(dq + columns))
+		} else {
+			hex_distance(dq)
+		}
+	}
+
+	/// The tiles overlapping the linear rect from `min` to `max` with where each is drawn as
+	/// `hex` hexes. A wrapping map repeats past its seam, so a wide enough rect gives the same
+	/// tile more than once at different places.
+	pub fn iter_linear_rect(
+		&self,
+		hex: HexOrientation,
+		min: (f32, f32),
+		max: (f32, f32),
+	) -> impl Iterator<Item = (Coord, &Tile, (f32, f32))> + '_ {
+		let columns = self.width as i16 + 1;
+		hex.iter_linear_rect(min, max)
+			.filter_map(move |(q, r, position)| {
+				if r < 0 || r > self.height as i16 {
+					return None;
+				}
+				let q = if self.wraps_x {
+					q.rem_euclid(columns)
+				} else if q < 0 || q >= columns {
+					return None;
+				} else {
+					q
+				};
+				let coord = Coord::new_axial(q as u8, r as u8);
+				self.get_tile(coord).map(|tile| (coord, tile, position))
+			})
+	}
+}
+
+pub struct TileMapNeighborsAroundIterator<'a, C: TileCoord = Coord> {
+	map: &'a TileMap<C>,
+	center: C,
 	iter: CoordOrientationNeighborIterator,
 }
 
-impl<'a> Iterator for TileMapNeighborsAroundIterator<'a> {
+impl<'a, C: TileCoord> Iterator for TileMapNeighborsAroundIterator<'a, C> {
 	type Item = (CoordOrientation, &'a Tile);
 
 	fn next(&mut self) -> Option<Self::Item> {
@@ -107,3 +170,34 @@ impl<'a> Iterator for TileMapNeighborsAroundIterator<'a> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tile_map_tests {
+	use super::*;
+	use crate::core::map::coord::Coord32;
+	use crate::core::map::generator::test_maps::{filled_map, tile_ids};
+	use crate::core::map::tile::TileIdx;
+
+	#[test]
+	fn wide_maps_go_past_256_columns() {
+		// Tiles numbered by their index so it can tell which one it got
+		let types = tile_ids(7);
+		let tile_map: TileMap<Coord32> =
+			filled_map(599, 1, true, |idx| Tile::new(types[idx % types.len()]));
+		assert_eq!(tile_map.tiles.len(), 1200);
+		let far = Coord32::new_axial(599, 1);
+		assert_eq!(tile_map.get_tile(far).unwrap().id, types[1199 % 7]);
+		assert!(tile_map.get_tile(Coord32::new_axial(0, 2)).is_none());
+		assert_eq!(
+			tile_map.coord_to_in_map_bounds(Coord32::new_axial(600, 1)),
+			Coord32::new_axial(0, 1)
+		);
+		// Around the seam from the last column back to the first
+		let around: Vec<TileIdx> = tile_map
+			.iter_neighbors_around(far, 1)
+			.map(|(_offset, tile)| tile.id)
+			.collect();
+		assert_eq!(around.len(), 5);
+		assert!(around.contains(&types[600 % 7]));
+	}
+}
@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+use thiserror::*;
+
+use crate::core::engine::io::EngineIO;
+use crate::core::map::coord::Coord;
+use crate::core::map::generator::{GeneratorInfo, MapGenerator};
+use crate::core::map::tile::{Tile, TileIdx, TileTypes};
+use crate::core::map::tile_map::TileMap;
+
+/// Tiled keeps flip and rotation flags in the top bits of every tile gid.
+const GID_FLAGS: u32 = 0xF000_0000;
+
+#[derive(Deserialize)]
+struct TiledMap {
+	width: u32,
+	height: u32,
+	#[serde(default)]
+	infinite: bool,
+	orientation: String,
+	tilewidth: f32,
+	tileheight: f32,
+	#[serde(default)]
+	hexsidelength: f32,
+	#[serde(default)]
+	staggeraxis: String,
+	#[serde(default)]
+	staggerindex: String,
+	#[serde(default)]
+	layers: Vec<TiledLayer>,
+	#[serde(default)]
+	tilesets: Vec<TiledTileset>,
+}
+
+#[derive(Deserialize)]
+struct TiledLayer {
+	#[serde(default)]
+	name: String,
+	#[serde(rename = "type")]
+	kind: String,
+	#[serde(default)]
+	data: Option<TiledLayerData>,
+	#[serde(default)]
+	objects: Vec<TiledMapObject>,
+	/// Children of a group layer.
+	#[serde(default)]
+	layers: Vec<TiledLayer>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TiledLayerData {
+	Gids(Vec<u32>),
+	Encoded(String),
+}
+
+#[derive(Deserialize)]
+struct TiledMapObject {
+	#[serde(default)]
+	name: String,
+	/// Called `class` by Tiled 1.9 and `type` before and after.
+	#[serde(default, rename = "type", alias = "class")]
+	class: String,
+	x: f32,
+	y: f32,
+	#[serde(default)]
+	width: f32,
+	#[serde(default)]
+	height: f32,
+	/// Tile objects are anchored at their bottom left instead of their top left.
+	#[serde(default)]
+	gid: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct TiledTileset {
+	#[serde(default)]
+	firstgid: u32,
+	#[serde(default)]
+	name: String,
+	#[serde(default)]
+	source: Option<String>,
+	#[serde(default)]
+	tiles: Vec<TiledTile>,
+}
+
+#[derive(Deserialize)]
+struct TiledTile {
+	id: u32,
+	#[serde(default, rename = "type", alias = "class")]
+	class: String,
+}
+
+/// An entity an object layer asks for, to be made from the template named by its class.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledObject {
+	pub name: String,
+	pub template: String,
+	pub coord: Coord,
+}
+
+/// A hex map saved by the Tiled editor as JSON, ready to generate its `TileMap` from.
+///
+/// Tile layers are laid over each other in order and their tiles are mapped to tile types by the
+/// class set on them in the tileset. Tiled maps are rectangles while these maps are
+/// parallelograms, so the spare corners are filled with the `fill` tile type.
+#[derive(Debug)]
+pub struct TiledMapGenerator {
+	/// Largest axial coordinates of the map, as given to `Engine::generate_map`.
+	pub max_x: u8,
+	pub max_y: u8,
+	/// The file it was loaded from, kept in the metadata of its map.
+	pub path: String,
+	tiles: Vec<TileIdx>,
+	/// Spawning these is left to the caller since templates live with the game.
+	pub objects: Vec<TiledObject>,
+}
+
+#[derive(Error, Debug)]
+pub enum TiledImportError<IO: EngineIO>
+where
+	IO::ReadError: 'static,
+{
+	#[error("failed to load Tiled map file")]
+	FileReadError {
+		source: IO::ReadError,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("failed to parse Tiled map")]
+	FileParseError {
+		source: serde_json::Error,
+		//backtrace: Backtrace, // Still needs nightly...
+	},
+
+	#[error("only hexagonal maps can be imported, not {0}")]
+	NotHexagonal(String),
+
+	#[error("only pointy topped hex maps, staggered along the y axis, can be imported")]
+	NotPointyTopped,
+
+	#[error("infinite maps can't be imported")]
+	Infinite,
+
+	#[error("a {0}x{1} map is over 256 tiles a side once made a parallelogram")]
+	MapTooLarge(u32, u32),
+
+	#[error("tileset `{0}` is external, embed it in the map to import it")]
+	ExternalTileset(String),
+
+	#[error("tile layer `{0}` is encoded, save it as CSV to import it")]
+	EncodedLayer(String),
+
+	#[error("tile layer `{name}` has {found} tiles where the map has {expected}")]
+	LayerSizeMismatch {
+		name: String,
+		found: usize,
+		expected: usize,
+	},
+
+	#[error("tile gid {0} is not in any tileset")]
+	UnknownGid(u32),
+
+	#[error("tile {0} of tileset `{1}` has no class naming its tile type")]
+	UnnamedTile(u32, String),
+
+	#[error("no tile type named `{0}`")]
+	UnknownTileType(String),
+
+	#[error("object `{0}` has no class naming its entity template")]
+	UnnamedObject(String),
+}
+
+impl TiledMapGenerator {
+	pub fn load<IO: EngineIO>(
+		io: &mut IO,
+		path: &Path,
+		tile_types: &TileTypes<IO>,
+		fill: TileIdx,
+	) -> Result<TiledMapGenerator, TiledImportError<IO>> {
+		let reader = io
+			.read(path)
+			.map_err(|source| TiledImportError::FileReadError { source })?;
+		let map: TiledMap = serde_json::from_reader(reader)
+			.map_err(|source| TiledImportError::FileParseError { source })?;
+
+		if map.orientation != "hexagonal" {
+			return Err(TiledImportError::NotHexagonal(map.orientation));
+		}
+		if map.staggeraxis != "y" {
+			return Err(TiledImportError::NotPointyTopped);
+		}
+		if map.infinite {
+			return Err(TiledImportError::Infinite);
+		}
+		let layout = Layout::new(&map);
+		let (max_x, max_y) = match (layout.max_q(), map.height) {
+			(max_q, height) if max_q <= 255 && map.width > 0 && (1..=256).contains(&height) => {
+				(max_q as u8, (height - 1) as u8)
+			}
+			_ => return Err(TiledImportError::MapTooLarge(map.width, map.height)),
+		};
+
+		let mut gids = vec![0u32; (map.width * map.height) as usize];
+		let mut objects = vec![];
+		let mut layers: Vec<&TiledLayer> = map.layers.iter().rev().collect();
+		while let Some(layer) = layers.pop() {
+			match layer.kind.as_str() {
+				"tilelayer" => match &layer.data {
+					Some(TiledLayerData::Gids(data)) if data.len() == gids.len() => {
+						for (gid, &layer_gid) in gids.iter_mut().zip(data) {
+							if layer_gid & !GID_FLAGS != 0 {
+								*gid = layer_gid & !GID_FLAGS;
+							}
+						}
+					}
+					Some(TiledLayerData::Gids(data)) => {
+						return Err(TiledImportError::LayerSizeMismatch {
+							name: layer.name.clone(),
+							found: data.len(),
+							expected: gids.len(),
+						})
+					}
+					Some(TiledLayerData::Encoded(_)) => {
+						return Err(TiledImportError::EncodedLayer(layer.name.clone()))
+					}
+					None => (),
+				},
+				"objectgroup" => {
+					for object in &layer.objects {
+						if object.class.is_empty() {
+							return Err(TiledImportError::UnnamedObject(object.name.clone()));
+						}
+						objects.push(TiledObject {
+							name: object.name.clone(),
+							template: object.class.clone(),
+							coord: layout.object_coord(object),
+						});
+					}
+				}
+				"group" => layers.extend(layer.layers.iter().rev()),
+				_ => (),
+			}
+		}
+
+		let mut tile_of_gid: HashMap<u32, TileIdx> = HashMap::new();
+		let mut tiles = vec![fill; (max_x as usize + 1) * (max_y as usize + 1)];
+		for (cell, &gid) in gids.iter().enumerate() {
+			if gid == 0 {
+				continue;
+			}
+			let tile = match tile_of_gid.get(&gid) {
+				Some(&tile) => tile,
+				None => {
+					let tile = Self::tile_type_of_gid(&map.tilesets, gid, tile_types)?;
+					tile_of_gid.insert(gid, tile);
+					tile
+				}
+			};
+			let column = cell as u32 % map.width;
+			let row = cell as u32 / map.width;
+			let q = layout.q_of(column, row) as usize;
+			tiles[row as usize * (max_x as usize + 1) + q] = tile;
+		}
+
+		Ok(TiledMapGenerator {
+			max_x,
+			max_y,
+			path: path.display().to_string(),
+			tiles,
+			objects,
+		})
+	}
+
+	fn tile_type_of_gid<IO: EngineIO>(
+		tilesets: &[TiledTileset],
+		gid: u32,
+		tile_types: &TileTypes<IO>,
+	) -> Result<TileIdx, TiledImportError<IO>> {
+		let tileset = tilesets
+			.iter()
+			.filter(|tileset| tileset.firstgid <= gid)
+			.max_by_key(|tileset| tileset.firstgid)
+			.ok_or(TiledImportError::UnknownGid(gid))?;
+		if let Some(source) = &tileset.source {
+			return Err(TiledImportError::ExternalTileset(source.clone()));
+		}
+		let id = gid - tileset.firstgid;
+		let name = tileset
+			.tiles
+			.iter()
+			.find(|tile| tile.id == id)
+			.map(|tile| tile.class.as_str())
+			.filter(|class| !class.is_empty())
+			.ok_or_else(|| TiledImportError::UnnamedTile(id, tileset.name.clone()))?;
+		tile_types
+			.tile_types
+			.get_index_of(name)
+			.ok_or_else(|| TiledImportError::UnknownTileType(name.to_owned()))
+	}
+}
+
+impl MapGenerator for TiledMapGenerator {
+	fn generate(&mut self, tile_map: &mut TileMap) -> anyhow::Result<()> {
+		if (tile_map.width, tile_map.height) != (self.max_x, self.max_y) {
+			anyhow::bail!(
+				"Tiled map is {}x{} but was generated as {}x{}",
+				self.max_x,
+				self.max_y,
+				tile_map.width,
+				tile_map.height
+			);
+		}
+		tile_map.tiles.clear();
+		tile_map
+			.tiles
+			.extend(self.tiles.iter().map(|&tile| Tile::new(tile)));
+		Ok(())
+	}
+
+	fn info(&self) -> GeneratorInfo {
+		GeneratorInfo::new("tiled").with_param("path", &self.path)
+	}
+}
+
+/// Where Tiled's staggered rows land in axial coordinates.
+struct Layout {
+	width: u32,
+	height: u32,
+	tile_width: f32,
+	/// Distance between the tops of two rows, they overlap by the slanted edges.
+	row_height: f32,
+	/// With `odd` the odd rows are pushed half a tile right, with `even` the even ones are.
+	odd_rows_shifted: bool,
+}
+
+impl Layout {
+	fn new(map: &TiledMap) -> Layout {
+		Layout {
+			width: map.width,
+			height: map.height,
+			tile_width: map.tilewidth,
+			row_height: ((map.tileheight + map.hexsidelength) * 0.5).max(1.0),
+			odd_rows_shifted: map.staggerindex != "even",
+		}
+	}
+
+	fn is_shifted(&self, row: u32) -> bool {
+		(row % 2 == 1) == self.odd_rows_shifted
+	}
+
+	/// How far left the last row starts, the other rows are moved right by it so none go past 0.
+	fn shear(&self) -> u32 {
+		let last = self.height.saturating_sub(1);
+		if self.odd_rows_shifted {
+			last / 2
+		} else {
+			(last + 1) / 2
+		}
+	}
+
+	fn q_of(&self, column: u32, row: u32) -> u32 {
+		let shift = if self.odd_rows_shifted {
+			row / 2
+		} else {
+			(row + 1) / 2
+		};
+		column + self.shear() - shift
+	}
+
+	fn max_q(&self) -> u32 {
+		self.width.saturating_sub(1) + self.shear()
+	}
+
+	/// The tile whose bounding box holds the object's center, clamped to the map.
+	fn object_coord(&self, object: &TiledMapObject) -> Coord {
+		let x = object.x + object.width * 0.5;
+		let y = match object.gid {
+			Some(_) => object.y - object.height * 0.5,
+			None => object.y + object.height * 0.5,
+		};
+		let last_row = self.height.saturating_sub(1);
+		let row = ((y / self.row_height).floor().max(0.0) as u32).min(last_row);
+		let x = if self.is_shifted(row) {
+			x - self.tile_width * 0.5
+		} else {
+			x
+		};
+		let last_column = self.width.saturating_sub(1);
+		let column = ((x / self.tile_width).floor().max(0.0) as u32).min(last_column);
+		Coord::new_axial(self.q_of(column, row) as u8, row as u8)
+	}
+}
+
+#[cfg(test)]
+mod tiled_tests {
+	use super::*;
+	use crate::core::map::generator::test_io::{tile_types, DummyIO};
+
+	const MAP: &[u8] = br#"{
+		"width": 3, "height": 3, "infinite": false,
+		"orientation": "hexagonal", "staggeraxis": "y", "staggerindex": "odd",
+		"tilewidth": 32, "tileheight": 32, "hexsidelength": 16,
+		"layers": [
+			{"name": "ground", "type": "tilelayer", "data": [1, 1, 1, 2, 2, 2, 1, 0, 1]},
+			{"name": "over", "type": "tilelayer", "data": [0, 0, 0, 0, 0, 0, 0, 0, 2147483650]},
+			{"name": "things", "type": "objectgroup", "objects": [
+				{"name": "scout", "type": "test_unit", "x": 40, "y": 30, "width": 0, "height": 0}
+			]}
+		],
+		"tilesets": [{"firstgid": 1, "name": "terrain", "tiles": [
+			{"id": 0, "type": "grass"},
+			{"id": 1, "class": "dirt"}
+		]}]
+	}"#;
+
+	#[test]
+	fn staggered_rows_become_a_parallelogram() {
+		let tile_types = tile_types(&["fill", "grass", "dirt"]);
+		let index = |name: &str| tile_types.tile_types.get_index_of(name).unwrap();
+		let mut io = DummyIO { data: MAP };
+		let mut generator =
+			TiledMapGenerator::load(&mut io, Path::new("map.json"), &tile_types, index("fill"))
+				.unwrap();
+		// The last row starts one tile left of the first in axial terms
+		assert_eq!((generator.max_x, generator.max_y), (3, 2));
+		let tile_map = TileMap::new(3, 2, false, &mut generator).unwrap();
+		let at = |q, r| tile_map.get_tile(Coord::new_axial(q, r)).unwrap().id;
+		assert_eq!(at(0, 0), index("fill"));
+		assert_eq!(at(1, 0), index("grass"));
+		assert_eq!(at(3, 0), index("grass"));
+		assert_eq!(at(1, 1), index("dirt"));
+		assert_eq!(at(3, 1), index("dirt"));
+		assert_eq!(at(0, 2), index("grass"));
+		// Empty in every layer
+		assert_eq!(at(1, 2), index("fill"));
+		// Later layers win, and the flip flag is ignored
+		assert_eq!(at(2, 2), index("dirt"));
+		assert_eq!(at(3, 2), index("fill"));
+		assert_eq!(
+			generator.objects,
+			vec![TiledObject {
+				name: "scout".to_owned(),
+				template: "test_unit".to_owned(),
+				coord: Coord::new_axial(1, 1),
+			}]
+		);
+	}
+
+	#[test]
+	fn flat_topped_and_unnamed_tiles_are_rejected() {
+		let tile_types = tile_types(&["fill"]);
+		let fill = tile_types.tile_types.get_index_of("fill").unwrap();
+		let mut io = DummyIO {
+			data: br#"{"width": 1, "height": 1, "orientation": "hexagonal", "staggeraxis": "x",
+				"tilewidth": 32, "tileheight": 32}"#,
+		};
+		match TiledMapGenerator::load(&mut io, Path::new("map.json"), &tile_types, fill) {
+			Err(TiledImportError::NotPointyTopped) => (),
+			other => panic!("flat topped map not rejected: {:?}", other.map(|_| ())),
+		}
+		let mut io = DummyIO {
+			data: br#"{"width": 1, "height": 1, "orientation": "hexagonal", "staggeraxis": "y",
+				"tilewidth": 32, "tileheight": 32,
+				"layers": [{"name": "ground", "type": "tilelayer", "data": [1]}],
+				"tilesets": [{"firstgid": 1, "name": "terrain", "tiles": []}]}"#,
+		};
+		match TiledMapGenerator::load(&mut io, Path::new("map.json"), &tile_types, fill) {
+			Err(TiledImportError::UnnamedTile(0, name)) => assert_eq!(name, "terrain"),
+			other => panic!("unnamed tile not rejected: {:?}", other.map(|_| ())),
+		}
+	}
+}
@@ -0,0 +1,222 @@
+use std::cmp::Ordering;
+
+use crate::core::map::coord::{Coord, CoordOrientation};
+use crate::core::map::tile::Tile;
+use crate::core::map::tile_map::TileMap;
+
+/// Every coord visible from `center` out to `radius` tiles, nearest first, by shadowcasting.
+///
+/// `blocks` says which tiles can't be seen past, they can still be seen themselves. Each ring
+/// around the center is split evenly by angle between its tiles, a blocking tile shadows its share
+/// of every ring past it and a tile is hidden when its center is in shadow. The center is always
+/// visible and never blocks. All in whole numbers, so the same on every machine.
+pub fn visible_coords(
+	tile_map: &TileMap,
+	center: Coord,
+	radius: u8,
+	mut blocks: impl FnMut(Coord, &Tile) -> bool,
+) -> Vec<Coord> {
+	let (width, height, wraps_x) = (tile_map.width, tile_map.height, tile_map.wraps_x);
+	let mut seen = vec![false; tile_map.tiles.len()];
+	let mut visible = Vec::new();
+	match center.idx(width, height, wraps_x) {
+		Some(idx) if idx < seen.len() => {
+			seen[idx] = true;
+			visible.push(center);
+		}
+		_ => return visible,
+	}
+
+	let mut shadows = Shadows::default();
+	// The ring iterator only goes so far
+	for distance in 1..=radius.min(127) {
+		let tiles = 6 * distance as u32;
+		let mut blockers = vec![];
+		for (index, offset) in CoordOrientation::iter_neighbors_ring(distance).enumerate() {
+			let coord = match center.offset_by(offset, width, height, wraps_x) {
+				Some(coord) => coord,
+				None => continue,
+			};
+			let idx = match coord.idx(width, height, wraps_x) {
+				Some(idx) if idx < tile_map.tiles.len() => idx,
+				_ => continue,
+			};
+			let index = index as u32;
+			if !shadows.covers(Turn::new(2 * index, 2 * tiles)) && !seen[idx] {
+				seen[idx] = true;
+				visible.push(coord);
+			}
+			if blocks(coord, &tile_map.tiles[idx]) {
+				blockers.push(index);
+			}
+		}
+		// Only shadowing the rings past this one
+		for index in blockers {
+			if index == 0 {
+				// Straddles where the turn starts
+				shadows.add(Turn::new(0, 2 * tiles), Turn::new(1, 2 * tiles));
+				shadows.add(Turn::new(2 * tiles - 1, 2 * tiles), Turn::new(1, 1));
+			} else {
+				shadows.add(
+					Turn::new(2 * index - 1, 2 * tiles),
+					Turn::new(2 * index + 1, 2 * tiles),
+				);
+			}
+		}
+		if shadows.is_full() {
+			break;
+		}
+	}
+	visible
+}
+
+/// Part of the way around a ring, 0 where it starts and 1 once all the way around.
+#[derive(Clone, Copy, Debug)]
+struct Turn {
+	num: u32,
+	den: u32,
+}
+
+impl Turn {
+	fn new(num: u32, den: u32) -> Turn {
+		Turn { num, den }
+	}
+}
+
+impl PartialEq for Turn {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Turn {}
+
+impl PartialOrd for Turn {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Turn {
+	fn cmp(&self, other: &Self) -> Ordering {
+		(self.num as u64 * other.den as u64).cmp(&(other.num as u64 * self.den as u64))
+	}
+}
+
+/// The parts of the turn in shadow, sorted and merged wherever they touch.
+#[derive(Default)]
+struct Shadows {
+	spans: Vec<(Turn, Turn)>,
+}
+
+impl Shadows {
+	fn add(&mut self, start: Turn, end: Turn) {
+		self.spans.push((start, end));
+		self.spans.sort_by(|a, b| a.0.cmp(&b.0));
+		let mut merged: Vec<(Turn, Turn)> = Vec::with_capacity(self.spans.len());
+		for &(start, end) in &self.spans {
+			match merged.last_mut() {
+				Some(last) if start <= last.1 => last.1 = last.1.max(end),
+				_ => merged.push((start, end)),
+			}
+		}
+		self.spans = merged;
+	}
+
+	/// Whether `at` is inside a shadow rather than on its edge, where the turn starts is inside
+	/// when shadows reach it from both sides.
+	fn covers(&self, at: Turn) -> bool {
+		let (zero, one) = (Turn::new(0, 1), Turn::new(1, 1));
+		if at == zero {
+			return matches!(
+				(self.spans.first(), self.spans.last()),
+				(Some(first), Some(last)) if first.0 == zero && last.1 == one
+			);
+		}
+		self.spans
+			.iter()
+			.any(|&(start, end)| start < at && at < end)
+	}
+
+	fn is_full(&self) -> bool {
+		match self.spans.as_slice() {
+			[(start, end)] => *start == Turn::new(0, 1) && *end == Turn::new(1, 1),
+			_ => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod visibility_tests {
+	use super::*;
+	use crate::core::map::generator::test_maps::plain_map;
+
+	fn walls(walls: &[(u8, u8)]) -> impl Fn(Coord, &Tile) -> bool + '_ {
+		move |coord: Coord, _tile: &Tile| walls.contains(&coord.to_axial_tuple())
+	}
+
+	#[test]
+	fn sees_everything_on_open_ground() {
+		let tile_map = plain_map(20, 20, false);
+		let center = Coord::new_axial(10, 10);
+		let visible = visible_coords(&tile_map, center, 3, walls(&[]));
+		assert_eq!(visible.len(), 1 + 3 * 3 * 4);
+		assert_eq!(visible[0], center);
+		assert!(visible.iter().all(|&coord| center.distance_to(coord) <= 3));
+		// Nearest first
+		assert!(visible
+			.windows(2)
+			.all(|pair| center.distance_to(pair[0]) <= center.distance_to(pair[1])));
+		// Cut off by the map's edge
+		let corner = visible_coords(&tile_map, Coord::new_axial(0, 0), 1, walls(&[]));
+		assert_eq!(corner.len(), 3);
+	}
+
+	#[test]
+	fn walls_cast_shadows() {
+		let tile_map = plain_map(20, 20, false);
+		let center = Coord::new_axial(10, 10);
+		let wall = [(11, 10)];
+		let visible = visible_coords(&tile_map, center, 4, walls(&wall));
+		// The wall itself is seen but not what is straight behind it
+		assert!(visible.contains(&Coord::new_axial(11, 10)));
+		assert!(!visible.contains(&Coord::new_axial(12, 10)));
+		assert!(!visible.contains(&Coord::new_axial(14, 10)));
+		// Those only partly behind it are seen
+		assert!(visible.contains(&Coord::new_axial(12, 9)));
+		assert!(visible.contains(&Coord::new_axial(11, 11)));
+		// The shadow widens with distance, hiding 1, 3 then 3 tiles of the rings past the wall
+		assert_eq!(visible.len(), 1 + 3 * 4 * 5 - 7);
+		// That wall was where the turn starts, any other direction is shadowed alike
+		let wall = [(10, 11)];
+		let visible = visible_coords(&tile_map, center, 4, walls(&wall));
+		assert!(!visible.contains(&Coord::new_axial(10, 12)));
+		assert_eq!(visible.len(), 1 + 3 * 4 * 5 - 7);
+	}
+
+	#[test]
+	fn enclosed_sees_only_its_walls() {
+		let tile_map = plain_map(20, 20, false);
+		let center = Coord::new_axial(10, 10);
+		let ring: Vec<(u8, u8)> = center
+			.iter_neighbors_ring(1)
+			.map(|coord| coord.to_axial_tuple())
+			.collect();
+		let visible = visible_coords(&tile_map, center, 5, walls(&ring));
+		assert_eq!(visible.len(), 7);
+		// A gap lets a wedge through
+		let gapped = &ring[1..];
+		let visible = visible_coords(&tile_map, center, 5, walls(gapped));
+		assert!(visible.len() > 7);
+		assert!(visible.contains(&center.iter_neighbors_ring(3).next().unwrap()));
+	}
+
+	#[test]
+	fn sees_across_the_seam() {
+		let tile_map = plain_map(9, 5, true);
+		let visible = visible_coords(&tile_map, Coord::new_axial(0, 2), 2, walls(&[]));
+		assert!(visible.contains(&Coord::new_axial(8, 2)));
+		assert!(visible.contains(&Coord::new_axial(9, 2)));
+		assert_eq!(visible.len(), 19);
+	}
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::mem::size_of;
+
+use shipyard::EntityId;
+
+use crate::core::map::tile::Tile;
+use crate::core::map::tile_map::TileMap;
+
+/// The budget name that covers every subsystem together.
+pub const TOTAL: &str = "total";
+
+/// Roughly how many bytes a value takes, itself and what it owns on the heap, for memory reports.
+pub trait MemoryUsage {
+	fn memory_usage(&self) -> usize;
+}
+
+impl MemoryUsage for Tile {
+	fn memory_usage(&self) -> usize {
+		let spilled = if self.entities.spilled() {
+			self.entities.len() * size_of::<EntityId>()
+		} else {
+			0
+		};
+		size_of::<Tile>() + spilled
+	}
+}
+
+impl MemoryUsage for TileMap {
+	fn memory_usage(&self) -> usize {
+		let unused = (self.tiles.capacity() - self.tiles.len()) * size_of::<Tile>();
+		size_of::<TileMap>() + unused + self.tiles.iter().map(Tile::memory_usage).sum::<usize>()
+	}
+}
+
+/// Bytes as the largest unit that keeps them above 1, to a tenth.
+pub fn format_bytes(bytes: usize) -> String {
+	const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+	let mut unit = 0;
+	let mut scale = 1;
+	while unit + 1 < UNITS.len() && bytes >= scale * 1024 {
+		unit += 1;
+		scale *= 1024;
+	}
+	if unit == 0 {
+		return format!("{} B", bytes);
+	}
+	format!(
+		"{}.{} {}",
+		bytes / scale,
+		bytes % scale * 10 / scale,
+		UNITS[unit]
+	)
+}
+
+/// A subsystem using more memory than its budget allows.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OverBudget {
+	pub name: String,
+	pub bytes: usize,
+	pub budget: usize,
+}
+
+/// About how much memory each subsystem uses, such as the maps, the ECS storages and the atlases.
+///
+/// The numbers are estimates from sizes and counts, not measured from the allocator, good for
+/// seeing what is growing and roughly by how much.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+	subsystems: Vec<(String, usize)>,
+}
+
+impl MemoryReport {
+	pub fn new() -> MemoryReport {
+		MemoryReport::default()
+	}
+
+	/// Counts `bytes` towards subsystem `name`, adding to what it has so far.
+	pub fn add(&mut self, name: &str, bytes: usize) {
+		match self.subsystems.iter_mut().find(|(n, _bytes)| n == name) {
+			Some((_name, total)) => *total += bytes,
+			None => self.subsystems.push((name.to_owned(), bytes)),
+		}
+	}
+
+	pub fn bytes(&self, name: &str) -> Option<usize> {
+		if name == TOTAL {
+			return Some(self.total());
+		}
+		self.subsystems
+			.iter()
+			.find(|(n, _bytes)| n == name)
+			.map(|(_name, bytes)| *bytes)
+	}
+
+	pub fn total(&self) -> usize {
+		self.subsystems.iter().map(|(_name, bytes)| *bytes).sum()
+	}
+
+	/// The subsystems in the order they were first added.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+		self.subsystems
+			.iter()
+			.map(|(name, bytes)| (name.as_str(), *bytes))
+	}
+
+	/// The subsystems over their budget in bytes, by name, with `TOTAL` for all of them together.
+	/// Subsystems without a budget are never over.
+	pub fn over_budget(&self, budgets: &BTreeMap<String, usize>) -> Vec<OverBudget> {
+		budgets
+			.iter()
+			.filter_map(|(name, &budget)| {
+				let bytes = self.bytes(name)?;
+				if bytes <= budget {
+					return None;
+				}
+				Some(OverBudget {
+					name: name.clone(),
+					bytes,
+					budget,
+				})
+			})
+			.collect()
+	}
+}
+
+impl fmt::Display for MemoryReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (name, bytes) in self.iter() {
+			writeln!(f, "{}: {}", name, format_bytes(bytes))?;
+		}
+		write!(f, "{}: {}", TOTAL, format_bytes(self.total()))
+	}
+}
+
+#[cfg(test)]
+mod memory_tests {
+	use super::*;
+	use crate::core::map::generator::test_maps::plain_map;
+
+	#[test]
+	fn maps_grow_with_their_tiles() {
+		let small = plain_map(3, 3, false);
+		let big = plain_map(7, 7, false);
+		assert!(small.memory_usage() >= 16 * size_of::<Tile>());
+		assert_eq!(
+			big.memory_usage() - small.memory_usage(),
+			48 * size_of::<Tile>()
+		);
+	}
+
+	#[test]
+	fn reports_and_budgets() {
+		let mut report = MemoryReport::new();
+		report.add("maps", 3 * 1024 * 1024);
+		report.add("atlases", 1536);
+		report.add("maps", 1024 * 1024);
+		assert_eq!(report.bytes("maps"), Some(4 * 1024 * 1024));
+		assert_eq!(report.bytes(TOTAL), Some(4 * 1024 * 1024 + 1536));
+		assert_eq!(report.bytes("caches"), None);
+		assert_eq!(
+			report.to_string(),
+			"maps: 4.0 MiB\natlases: 1.5 KiB\ntotal: 4.0 MiB"
+		);
+
+		let budgets: BTreeMap<String, usize> = vec![
+			("maps".to_owned(), 2 * 1024 * 1024),
+			("atlases".to_owned(), 4096),
+			("caches".to_owned(), 0),
+			(TOTAL.to_owned(), 4 * 1024 * 1024),
+		]
+		.into_iter()
+		.collect();
+		let over = report.over_budget(&budgets);
+		let names: Vec<&str> = over.iter().map(|over| over.name.as_str()).collect();
+		assert_eq!(names, ["maps", TOTAL]);
+		assert_eq!(over[0].bytes, 4 * 1024 * 1024);
+	}
+
+	#[test]
+	fn formats_bytes() {
+		assert_eq!(format_bytes(0), "0 B");
+		assert_eq!(format_bytes(1023), "1023 B");
+		assert_eq!(format_bytes(1024), "1.0 KiB");
+		assert_eq!(format_bytes(5 * 1024 * 1024 + 512 * 1024), "5.5 MiB");
+		assert_eq!(format_bytes(3 * 1024 * 1024 * 1024), "3.0 GiB");
+	}
+}
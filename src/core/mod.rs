@@ -1,5 +1,11 @@
+#[cfg_attr(feature = "deterministic", deny(clippy::float_arithmetic))]
+pub mod ai;
 #[macro_use]
 pub mod component;
+#[cfg_attr(feature = "deterministic", deny(clippy::float_arithmetic))]
 pub mod engine;
+pub mod fixed;
 pub mod map;
+pub mod memory;
+pub mod reflect;
 pub mod structures;
@@ -0,0 +1,615 @@
+use std::fmt;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, Deserializer, IntoDeserializer, Visitor};
+use thiserror::*;
+
+/// How far into options, sequences and maps a trace goes, so types holding themselves end.
+const MAX_TRACE_DEPTH: usize = 8;
+
+/// The shape of a type as it deserializes, enough to show its values in an editor and to check
+/// data against it.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldType {
+	Bool,
+	Integer,
+	Float,
+	Char,
+	String,
+	Bytes,
+	Unit,
+	Option(Box<FieldType>),
+	Seq(Box<FieldType>),
+	Tuple(Vec<FieldType>),
+	Map(Box<FieldType>, Box<FieldType>),
+	Struct {
+		name: &'static str,
+		fields: Vec<(&'static str, FieldType)>,
+	},
+	/// Only the variant names are known, each variant can hold something different.
+	Enum {
+		name: &'static str,
+		variants: &'static [&'static str],
+	},
+	/// Deserializes from whatever the data holds, so there is nothing to check it against.
+	Any,
+}
+
+#[derive(Debug, Error)]
+pub enum ReflectError {
+	#[error("tracing the type failed: {0}")]
+	TraceFailed(String),
+}
+
+impl de::Error for ReflectError {
+	fn custom<T: fmt::Display>(msg: T) -> Self {
+		ReflectError::TraceFailed(msg.to_string())
+	}
+}
+
+/// Something in data that doesn't fit the type it is read as.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeIssue {
+	/// The field names and indices leading to the value, dot separated.
+	pub path: String,
+	pub message: String,
+}
+
+impl fmt::Display for TypeIssue {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {}", self.path, self.message)
+	}
+}
+
+/// Finds the shape of `T` by deserializing one from nothing and noting what it asks for.
+///
+/// Types that need a self describing format, like untagged enums, or that reject the blank values
+/// handed to them fail to trace.
+pub fn trace<T: DeserializeOwned>() -> Result<FieldType, ReflectError> {
+	let mut traced = None;
+	T::deserialize(Tracer {
+		out: &mut traced,
+		depth: 0,
+	})?;
+	Ok(traced.unwrap_or(FieldType::Any))
+}
+
+fn join_path(path: &str, part: impl fmt::Display) -> String {
+	if path.is_empty() {
+		part.to_string()
+	} else {
+		format!("{}.{}", path, part)
+	}
+}
+
+impl FieldType {
+	/// Adds everywhere `value` doesn't fit this type to `issues`. Fields that are left out aren't
+	/// issues since they can have defaults, and enums accept anything.
+	pub fn check(&self, value: &ron::Value, path: &str, issues: &mut Vec<TypeIssue>) {
+		use ron::Value;
+		let fits = match (self, value) {
+			(FieldType::Any, _) | (FieldType::Enum { .. }, _) => true,
+			(FieldType::Bool, Value::Bool(_)) => true,
+			(FieldType::Integer, Value::Number(ron::Number::Integer(_))) => true,
+			(FieldType::Float, Value::Number(_)) => true,
+			(FieldType::Char, Value::Char(_)) => true,
+			(FieldType::String, Value::String(_)) => true,
+			(FieldType::Bytes, Value::String(_)) | (FieldType::Bytes, Value::Seq(_)) => true,
+			(FieldType::Unit, Value::Unit) => true,
+			(FieldType::Option(_), Value::Option(None)) => true,
+			(FieldType::Option(inner), Value::Option(Some(value))) => {
+				inner.check(value, path, issues);
+				true
+			}
+			(FieldType::Seq(element), Value::Seq(values)) => {
+				for (idx, value) in values.iter().enumerate() {
+					element.check(value, &join_path(path, idx), issues);
+				}
+				true
+			}
+			(FieldType::Tuple(elements), Value::Seq(values)) => {
+				if elements.len() == values.len() {
+					for (idx, (element, value)) in elements.iter().zip(values).enumerate() {
+						element.check(value, &join_path(path, idx), issues);
+					}
+				} else {
+					issues.push(TypeIssue {
+						path: path.to_owned(),
+						message: format!(
+							"expected {} values but found {}",
+							elements.len(),
+							values.len()
+						),
+					});
+				}
+				true
+			}
+			(FieldType::Map(key_type, value_type), Value::Map(entries)) => {
+				for (key, value) in entries.iter() {
+					key_type.check(key, path, issues);
+					value_type.check(value, path, issues);
+				}
+				true
+			}
+			(FieldType::Struct { fields, .. }, Value::Unit) => fields.is_empty(),
+			(FieldType::Struct { name, fields }, Value::Map(entries)) => {
+				for (key, value) in entries.iter() {
+					let key = match key {
+						Value::String(key) => key,
+						_ => {
+							issues.push(TypeIssue {
+								path: path.to_owned(),
+								message: format!("`{}` field names have to be names", name),
+							});
+							continue;
+						}
+					};
+					match fields.iter().find(|(field, _type)| field == key) {
+						Some((_field, field_type)) => {
+							field_type.check(value, &join_path(path, key), issues)
+						}
+						None => issues.push(TypeIssue {
+							path: join_path(path, key),
+							message: format!("`{}` has no such field", name),
+						}),
+					}
+				}
+				true
+			}
+			_ => false,
+		};
+		if !fits {
+			issues.push(TypeIssue {
+				path: path.to_owned(),
+				message: format!("expected {}", self),
+			});
+		}
+	}
+}
+
+impl fmt::Display for FieldType {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FieldType::Bool => write!(f, "a bool"),
+			FieldType::Integer => write!(f, "an integer"),
+			FieldType::Float => write!(f, "a number"),
+			FieldType::Char => write!(f, "a character"),
+			FieldType::String => write!(f, "a string"),
+			FieldType::Bytes => write!(f, "bytes"),
+			FieldType::Unit => write!(f, "()"),
+			FieldType::Option(inner) => write!(f, "an optional {}", inner),
+			FieldType::Seq(element) => write!(f, "a list of {}", element),
+			FieldType::Tuple(elements) => write!(f, "a tuple of {}", elements.len()),
+			FieldType::Map(key, value) => write!(f, "a map of {} to {}", key, value),
+			FieldType::Struct { name, .. } => write!(f, "a `{}`", name),
+			FieldType::Enum { name, .. } => write!(f, "a `{}`", name),
+			FieldType::Any => write!(f, "anything"),
+		}
+	}
+}
+
+/// Hands out blank values, writing what was asked for into `out`.
+struct Tracer<'a> {
+	out: &'a mut Option<FieldType>,
+	depth: usize,
+}
+
+impl<'a> Tracer<'a> {
+	/// `len` traced slots, none once the trace is too deep.
+	fn slots(&self, len: usize) -> Vec<Option<FieldType>> {
+		if self.depth < MAX_TRACE_DEPTH {
+			vec![None; len]
+		} else {
+			vec![]
+		}
+	}
+}
+
+fn traced(slot: Option<Option<FieldType>>) -> FieldType {
+	slot.flatten().unwrap_or(FieldType::Any)
+}
+
+macro_rules! trace_primitive {
+	($($method:ident => $field_type:ident, $visit:ident($($value:expr)?);)*) => {
+		$(
+			fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ReflectError> {
+				*self.out = Some(FieldType::$field_type);
+				visitor.$visit($($value)?)
+			}
+		)*
+	};
+}
+
+impl<'de, 'a> Deserializer<'de> for Tracer<'a> {
+	type Error = ReflectError;
+
+	trace_primitive! {
+		deserialize_bool => Bool, visit_bool(false);
+		deserialize_i8 => Integer, visit_i8(0);
+		deserialize_i16 => Integer, visit_i16(0);
+		deserialize_i32 => Integer, visit_i32(0);
+		deserialize_i64 => Integer, visit_i64(0);
+		deserialize_u8 => Integer, visit_u8(0);
+		deserialize_u16 => Integer, visit_u16(0);
+		deserialize_u32 => Integer, visit_u32(0);
+		deserialize_u64 => Integer, visit_u64(0);
+		deserialize_f32 => Float, visit_f32(0.0);
+		deserialize_f64 => Float, visit_f64(0.0);
+		deserialize_char => Char, visit_char(' ');
+		deserialize_str => String, visit_str("");
+		deserialize_string => String, visit_str("");
+		deserialize_identifier => String, visit_str("");
+		deserialize_bytes => Bytes, visit_bytes(&[]);
+		deserialize_byte_buf => Bytes, visit_bytes(&[]);
+		deserialize_unit => Unit, visit_unit();
+		deserialize_ignored_any => Any, visit_unit();
+		deserialize_any => Any, visit_unit();
+	}
+
+	fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ReflectError> {
+		if self.depth >= MAX_TRACE_DEPTH {
+			*self.out = Some(FieldType::Option(Box::new(FieldType::Any)));
+			return visitor.visit_none();
+		}
+		let mut inner = None;
+		let value = visitor.visit_some(Tracer {
+			out: &mut inner,
+			depth: self.depth + 1,
+		})?;
+		*self.out = Some(FieldType::Option(Box::new(traced(Some(inner)))));
+		Ok(value)
+	}
+
+	fn deserialize_unit_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, ReflectError> {
+		self.deserialize_unit(visitor)
+	}
+
+	fn deserialize_newtype_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, ReflectError> {
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ReflectError> {
+		let mut slots = self.slots(1);
+		let value = visitor.visit_seq(TraceSeq {
+			slots: slots.iter_mut(),
+			depth: self.depth + 1,
+		})?;
+		*self.out = Some(FieldType::Seq(Box::new(traced(slots.pop()))));
+		Ok(value)
+	}
+
+	fn deserialize_tuple<V: Visitor<'de>>(
+		self,
+		len: usize,
+		visitor: V,
+	) -> Result<V::Value, ReflectError> {
+		let mut slots = vec![None; len];
+		let value = visitor.visit_seq(TraceSeq {
+			slots: slots.iter_mut(),
+			depth: self.depth + 1,
+		})?;
+		*self.out = Some(FieldType::Tuple(
+			slots.into_iter().map(|slot| traced(Some(slot))).collect(),
+		));
+		Ok(value)
+	}
+
+	fn deserialize_tuple_struct<V: Visitor<'de>>(
+		self,
+		_name: &'static str,
+		len: usize,
+		visitor: V,
+	) -> Result<V::Value, ReflectError> {
+		self.deserialize_tuple(len, visitor)
+	}
+
+	fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ReflectError> {
+		let mut slots = self.slots(2);
+		let value = visitor.visit_map(TraceMap {
+			slots: slots.iter_mut(),
+			value: None,
+			depth: self.depth + 1,
+		})?;
+		let value_type = traced(slots.pop());
+		*self.out = Some(FieldType::Map(
+			Box::new(traced(slots.pop())),
+			Box::new(value_type),
+		));
+		Ok(value)
+	}
+
+	fn deserialize_struct<V: Visitor<'de>>(
+		self,
+		name: &'static str,
+		fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, ReflectError> {
+		let mut slots = vec![None; fields.len()];
+		let value = visitor.visit_map(TraceStruct {
+			fields: fields.iter().zip(slots.iter_mut()),
+			value: None,
+			depth: self.depth,
+		})?;
+		*self.out = Some(FieldType::Struct {
+			name,
+			fields: fields
+				.iter()
+				.copied()
+				.zip(slots.into_iter().map(|slot| traced(Some(slot))))
+				.collect(),
+		});
+		Ok(value)
+	}
+
+	fn deserialize_enum<V: Visitor<'de>>(
+		self,
+		name: &'static str,
+		variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, ReflectError> {
+		*self.out = Some(FieldType::Enum { name, variants });
+		visitor.visit_enum(TraceEnum { depth: self.depth })
+	}
+}
+
+/// A sequence with an element for each slot, a map's key and value when it has two.
+struct TraceSeq<'a> {
+	slots: std::slice::IterMut<'a, Option<FieldType>>,
+	depth: usize,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for TraceSeq<'a> {
+	type Error = ReflectError;
+
+	fn next_element_seed<S: DeserializeSeed<'de>>(
+		&mut self,
+		seed: S,
+	) -> Result<Option<S::Value>, ReflectError> {
+		match self.slots.next() {
+			Some(out) => seed
+				.deserialize(Tracer {
+					out,
+					depth: self.depth,
+				})
+				.map(Some),
+			None => Ok(None),
+		}
+	}
+}
+
+/// A map with one entry, or none when there are no slots.
+struct TraceMap<'a> {
+	slots: std::slice::IterMut<'a, Option<FieldType>>,
+	value: Option<&'a mut Option<FieldType>>,
+	depth: usize,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for TraceMap<'a> {
+	type Error = ReflectError;
+
+	fn next_key_seed<K: DeserializeSeed<'de>>(
+		&mut self,
+		seed: K,
+	) -> Result<Option<K::Value>, ReflectError> {
+		let (key, value) = match (self.slots.next(), self.slots.next()) {
+			(Some(key), Some(value)) => (key, value),
+			_ => return Ok(None),
+		};
+		self.value = Some(value);
+		seed.deserialize(Tracer {
+			out: key,
+			depth: self.depth,
+		})
+		.map(Some)
+	}
+
+	fn next_value_seed<V: DeserializeSeed<'de>>(
+		&mut self,
+		seed: V,
+	) -> Result<V::Value, ReflectError> {
+		let out = self.value.take().ok_or_else(|| {
+			ReflectError::TraceFailed("map value asked for before its key".to_owned())
+		})?;
+		seed.deserialize(Tracer {
+			out,
+			depth: self.depth,
+		})
+	}
+}
+
+/// Every field of a struct in order, each traced into its slot.
+struct TraceStruct<'a> {
+	fields: std::iter::Zip<
+		std::slice::Iter<'static, &'static str>,
+		std::slice::IterMut<'a, Option<FieldType>>,
+	>,
+	value: Option<&'a mut Option<FieldType>>,
+	depth: usize,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for TraceStruct<'a> {
+	type Error = ReflectError;
+
+	fn next_key_seed<K: DeserializeSeed<'de>>(
+		&mut self,
+		seed: K,
+	) -> Result<Option<K::Value>, ReflectError> {
+		let (&field, out) = match self.fields.next() {
+			Some(next) => next,
+			None => return Ok(None),
+		};
+		self.value = Some(out);
+		let key: de::value::StrDeserializer<ReflectError> = field.into_deserializer();
+		seed.deserialize(key).map(Some)
+	}
+
+	fn next_value_seed<V: DeserializeSeed<'de>>(
+		&mut self,
+		seed: V,
+	) -> Result<V::Value, ReflectError> {
+		let out = self.value.take().ok_or_else(|| {
+			ReflectError::TraceFailed("field value asked for before its name".to_owned())
+		})?;
+		seed.deserialize(Tracer {
+			out,
+			depth: self.depth,
+		})
+	}
+}
+
+/// Always the first variant, what it holds is traced and thrown away.
+struct TraceEnum {
+	depth: usize,
+}
+
+impl<'de> de::EnumAccess<'de> for TraceEnum {
+	type Error = ReflectError;
+	type Variant = Self;
+
+	fn variant_seed<V: DeserializeSeed<'de>>(
+		self,
+		seed: V,
+	) -> Result<(V::Value, Self), ReflectError> {
+		let variant: de::value::U32Deserializer<ReflectError> = 0u32.into_deserializer();
+		Ok((seed.deserialize(variant)?, self))
+	}
+}
+
+impl<'de> de::VariantAccess<'de> for TraceEnum {
+	type Error = ReflectError;
+
+	fn unit_variant(self) -> Result<(), ReflectError> {
+		Ok(())
+	}
+
+	fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+		self,
+		seed: T,
+	) -> Result<T::Value, ReflectError> {
+		seed.deserialize(Tracer {
+			out: &mut None,
+			depth: self.depth + 1,
+		})
+	}
+
+	fn tuple_variant<V: Visitor<'de>>(
+		self,
+		len: usize,
+		visitor: V,
+	) -> Result<V::Value, ReflectError> {
+		let tracer = Tracer {
+			out: &mut None,
+			depth: self.depth + 1,
+		};
+		tracer.deserialize_tuple(len, visitor)
+	}
+
+	fn struct_variant<V: Visitor<'de>>(
+		self,
+		fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, ReflectError> {
+		let tracer = Tracer {
+			out: &mut None,
+			depth: self.depth + 1,
+		};
+		tracer.deserialize_struct("", fields, visitor)
+	}
+}
+
+#[cfg(test)]
+mod reflect_tests {
+	use super::*;
+	use serde::Deserialize;
+	use std::collections::HashMap;
+
+	#[derive(Deserialize)]
+	#[allow(dead_code)]
+	enum Shape {
+		Circle(f32),
+		Square { side: f32 },
+	}
+
+	#[derive(Deserialize)]
+	#[allow(dead_code)]
+	struct Unit {
+		name: String,
+		moves: u8,
+		position: (i32, i32),
+		shape: Shape,
+		tags: Vec<String>,
+		costs: HashMap<String, f32>,
+		leader: Option<Box<Unit>>,
+	}
+
+	#[test]
+	fn structs_trace_their_fields() {
+		let unit = trace::<Unit>().unwrap();
+		let fields = match &unit {
+			FieldType::Struct {
+				name: "Unit",
+				fields,
+			} => fields,
+			other => panic!("not traced as a struct: {:?}", other),
+		};
+		let names: Vec<&str> = fields.iter().map(|(name, _type)| *name).collect();
+		assert_eq!(
+			names,
+			["name", "moves", "position", "shape", "tags", "costs", "leader"]
+		);
+		assert_eq!(fields[0].1, FieldType::String);
+		assert_eq!(fields[1].1, FieldType::Integer);
+		assert_eq!(
+			fields[2].1,
+			FieldType::Tuple(vec![FieldType::Integer, FieldType::Integer])
+		);
+		assert_eq!(
+			fields[3].1,
+			FieldType::Enum {
+				name: "Shape",
+				variants: &["Circle", "Square"]
+			}
+		);
+		assert_eq!(fields[4].1, FieldType::Seq(Box::new(FieldType::String)));
+		assert_eq!(
+			fields[5].1,
+			FieldType::Map(Box::new(FieldType::String), Box::new(FieldType::Float))
+		);
+		match &fields[6].1 {
+			FieldType::Option(leader) => assert_eq!(leader.to_string(), "a `Unit`"),
+			other => panic!("leader not traced as an option: {:?}", other),
+		}
+	}
+
+	#[test]
+	fn data_is_checked_against_the_trace() {
+		let unit = trace::<Unit>().unwrap();
+		let check = |text: &str| {
+			let value: ron::Value = ron::de::from_str(text).unwrap();
+			let mut issues = vec![];
+			unit.check(&value, "", &mut issues);
+			issues
+				.iter()
+				.map(|issue| issue.to_string())
+				.collect::<Vec<String>>()
+		};
+		assert!(check(r#"(name: "scout", moves: 3, tags: ["fast"])"#).is_empty());
+		assert_eq!(
+			check(r#"(name: 3, moves: 1.5, position: (1, 2, 3), speed: 2, tags: [1])"#),
+			[
+				"moves: expected an integer",
+				"name: expected a string",
+				"position: expected 2 values but found 3",
+				"speed: `Unit` has no such field",
+				"tags.0: expected a string",
+			]
+		);
+	}
+}
@@ -1 +1,2 @@
+pub mod small_set;
 pub mod typed_index_map;
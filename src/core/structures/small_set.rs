@@ -0,0 +1,150 @@
+use std::fmt;
+
+use smallvec::{Array, SmallVec};
+
+/// A set stored inline as a small unsorted vector, for sets that are nearly always tiny.
+///
+/// Lookups are linear so this is only a win while the set stays within (or near) the inline
+/// capacity, where it avoids the hashing and allocation that a `HashSet` pays for.
+///
+/// ```
+/// # use over_simple_game_1::core::structures::small_set::SmallSet;
+/// let mut set = SmallSet::<[u32; 2]>::new();
+/// assert!(set.insert(4));
+/// assert!(!set.insert(4));
+/// assert!(set.contains(&4));
+/// assert!(set.remove(&4));
+/// assert!(set.is_empty());
+/// ```
+pub struct SmallSet<A: Array> {
+	items: SmallVec<A>,
+}
+
+impl<A: Array> SmallSet<A>
+where
+	A::Item: PartialEq,
+{
+	pub fn new() -> Self {
+		SmallSet {
+			items: SmallVec::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	pub fn contains(&self, value: &A::Item) -> bool {
+		self.items.iter().any(|v| v == value)
+	}
+
+	/// Returns `true` if the value was not already present, same as `HashSet::insert`.
+	pub fn insert(&mut self, value: A::Item) -> bool {
+		if self.contains(&value) {
+			return false;
+		}
+		self.items.push(value);
+		true
+	}
+
+	/// Returns `true` if the value was present, same as `HashSet::remove`.
+	pub fn remove(&mut self, value: &A::Item) -> bool {
+		match self.items.iter().position(|v| v == value) {
+			None => false,
+			Some(idx) => {
+				self.items.swap_remove(idx);
+				true
+			}
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.items.clear();
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<A::Item> {
+		self.items.iter()
+	}
+
+	/// Whether the set has grown past its inline capacity onto the heap.
+	pub fn spilled(&self) -> bool {
+		self.items.spilled()
+	}
+}
+
+impl<A: Array> Default for SmallSet<A>
+where
+	A::Item: PartialEq,
+{
+	fn default() -> Self {
+		SmallSet::new()
+	}
+}
+
+impl<A: Array> Clone for SmallSet<A>
+where
+	A::Item: Clone,
+{
+	fn clone(&self) -> Self {
+		SmallSet {
+			items: self.items.clone(),
+		}
+	}
+}
+
+impl<A: Array> fmt::Debug for SmallSet<A>
+where
+	A::Item: fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_set().entries(self.items.iter()).finish()
+	}
+}
+
+impl<'a, A: Array> IntoIterator for &'a SmallSet<A> {
+	type Item = &'a A::Item;
+	type IntoIter = std::slice::Iter<'a, A::Item>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.items.iter()
+	}
+}
+
+#[cfg(test)]
+mod small_set_tests {
+	use super::*;
+	use proptest::prelude::*;
+	use std::collections::HashSet;
+
+	proptest!(
+		#[test]
+		fn behaves_like_hash_set(ops in prop::collection::vec((any::<bool>(), 0..8u8), 0..64)) {
+			let mut small = SmallSet::<[u8; 2]>::new();
+			let mut hashed = HashSet::new();
+			for (insert, value) in ops {
+				if insert {
+					prop_assert_eq!(small.insert(value), hashed.insert(value));
+				} else {
+					prop_assert_eq!(small.remove(&value), hashed.remove(&value));
+				}
+				prop_assert_eq!(small.len(), hashed.len());
+			}
+			let small_values: HashSet<u8> = small.iter().cloned().collect();
+			prop_assert_eq!(small_values, hashed);
+		}
+	);
+
+	#[test]
+	fn stays_inline_within_capacity() {
+		let mut set = SmallSet::<[u32; 2]>::new();
+		set.insert(1);
+		set.insert(2);
+		assert!(!set.spilled());
+		set.insert(3);
+		assert!(set.spilled());
+	}
+}
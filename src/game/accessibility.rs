@@ -0,0 +1,27 @@
+use ggez::graphics::Color;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AccessibilityConfig {
+	/// Thicker, opaque outlines and shadowed text for overlays drawn on top of the map.
+	#[serde(default)]
+	pub high_contrast: bool,
+}
+
+impl AccessibilityConfig {
+	pub fn line_width(&self, width: f32) -> f32 {
+		if self.high_contrast {
+			width * 2.5
+		} else {
+			width
+		}
+	}
+
+	pub fn overlay_color(&self, color: Color) -> Color {
+		if self.high_contrast {
+			Color::new(color.r, color.g, color.b, 1.0)
+		} else {
+			color
+		}
+	}
+}
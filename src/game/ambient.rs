@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect, WrapMode};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+use log::*;
+
+use over_simple_game_1::prelude::{MapAmbient, Starfield};
+
+/// Small deterministic mixer so the same starfield seed always places the same stars.
+fn splitmix64(state: &mut u64) -> u64 {
+	*state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+	let mut z = *state;
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	z ^ (z >> 31)
+}
+
+fn unit(state: &mut u64) -> f32 {
+	(splitmix64(state) >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Draws the background of a map's ambient: the starfield and then the parallax layers.
+pub struct AmbientBackground {
+	layers: HashMap<String, graphics::Image>,
+	/// Ambient name, screen size and UI scale the cached starfield was built for.
+	starfield: Option<((String, u32, u32, u32), graphics::Mesh)>,
+}
+
+impl AmbientBackground {
+	pub fn new() -> AmbientBackground {
+		AmbientBackground {
+			layers: HashMap::new(),
+			starfield: None,
+		}
+	}
+
+	/// Loads `/backgrounds/<image>.png` for every layer of every ambient, missing ones are left
+	/// out with a warning so a map without its art still plays.
+	pub fn load<'a>(
+		&mut self,
+		ctx: &mut Context,
+		ambients: impl Iterator<Item = &'a MapAmbient>,
+	) -> GameResult {
+		self.layers.clear();
+		self.starfield = None;
+		for layer in ambients.flat_map(|ambient| &ambient.layers) {
+			if self.layers.contains_key(&layer.image) {
+				continue;
+			}
+			let path = format!("/backgrounds/{}.png", layer.image);
+			match graphics::Image::new(ctx, &path) {
+				Ok(mut image) => {
+					image.set_wrap(WrapMode::Tile, WrapMode::Tile);
+					self.layers.insert(layer.image.clone(), image);
+				}
+				Err(error) => warn!("Failed loading parallax layer `{}`: {}", path, error),
+			}
+		}
+		Ok(())
+	}
+
+	/// Draws in screen pixels, `scroll` is how far the map has scrolled in pixels.
+	pub fn draw(
+		&mut self,
+		ctx: &mut Context,
+		ambient: &MapAmbient,
+		scroll: (f32, f32),
+		screen_size: (f32, f32),
+		ui_scale: f32,
+	) -> GameResult {
+		if let Some(starfield) = &ambient.starfield {
+			self.draw_starfield(ctx, &ambient.name, starfield, scroll, screen_size, ui_scale)?;
+		}
+		for layer in &ambient.layers {
+			let image = match self.layers.get(&layer.image) {
+				Some(image) => image,
+				None => continue,
+			};
+			let scale = layer.scale * ui_scale;
+			let tile_width = image.width() as f32 * scale;
+			let tile_height = image.height() as f32 * scale;
+			if tile_width <= 0.0 || tile_height <= 0.0 {
+				continue;
+			}
+			// The source rect spills past the image and the tiling wrap repeats it
+			let src = Rect::new(
+				scroll.0 * layer.parallax / tile_width,
+				scroll.1 * layer.parallax / tile_height,
+				screen_size.0 / tile_width,
+				screen_size.1 / tile_height,
+			);
+			image.draw(
+				ctx,
+				DrawParam::new()
+					.src(src)
+					.scale(na::Vector2::new(scale, scale))
+					.color(Color::from(layer.tint)),
+			)?;
+		}
+		Ok(())
+	}
+
+	fn draw_starfield(
+		&mut self,
+		ctx: &mut Context,
+		name: &str,
+		starfield: &Starfield,
+		scroll: (f32, f32),
+		(width, height): (f32, f32),
+		ui_scale: f32,
+	) -> GameResult {
+		let key = (
+			name.to_owned(),
+			width.to_bits(),
+			height.to_bits(),
+			ui_scale.to_bits(),
+		);
+		if self.starfield.as_ref().map(|(built_for, _mesh)| built_for) != Some(&key) {
+			self.starfield = None;
+			if starfield.count == 0 || width <= 0.0 || height <= 0.0 {
+				return Ok(());
+			}
+			let mut state = starfield.seed;
+			let mut builder = graphics::MeshBuilder::new();
+			for _ in 0..starfield.count {
+				let x = unit(&mut state) * width;
+				let y = unit(&mut state) * height;
+				let size = (0.75 + unit(&mut state)) * ui_scale;
+				let brightness = 0.4 + unit(&mut state) * 0.6;
+				let [r, g, b, a] = starfield.color;
+				builder.rectangle(
+					DrawMode::fill(),
+					Rect::new(x, y, size, size),
+					Color::new(r, g, b, a * brightness),
+				);
+			}
+			self.starfield = Some((key, builder.build(ctx)?));
+		}
+		if let Some((_built_for, mesh)) = &self.starfield {
+			// One screen of stars drawn four times covers the screen wherever it is scrolled to
+			let x = (-scroll.0 * starfield.parallax).rem_euclid(width);
+			let y = (-scroll.1 * starfield.parallax).rem_euclid(height);
+			for &(dx, dy) in &[(0.0, 0.0), (-width, 0.0), (0.0, -height), (-width, -height)] {
+				mesh.draw(ctx, DrawParam::new().dest(na::Point2::new(x + dx, y + dy)))?;
+			}
+		}
+		Ok(())
+	}
+}
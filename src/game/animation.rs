@@ -0,0 +1,242 @@
+use std::f32::consts::PI;
+
+use over_simple_game_1::core::engine::MapIdx;
+use shipyard::EntityId;
+
+use crate::game::components::DrawSprite;
+
+/// Something that happened in a fight, for the timeline to show.
+#[derive(Clone, Debug)]
+pub enum CombatEvent {
+	/// `toward` is from the attacker to the defender in linear map units.
+	Attack {
+		attacker: EntityId,
+		defender: EntityId,
+		toward: (f32, f32),
+	},
+	/// The entity can already be gone from the world, `sprite` is how it looked and `at` is
+	/// where it stood.
+	Death {
+		entity: EntityId,
+		sprite: DrawSprite,
+		map: MapIdx,
+		at: (f32, f32),
+	},
+}
+
+enum Track {
+	Lunge {
+		entity: EntityId,
+		toward: (f32, f32),
+	},
+	HitFlash {
+		entity: EntityId,
+	},
+	/// Stands in for a dead entity until its death shows, then fades out.
+	Ghost {
+		sprite: DrawSprite,
+		map: MapIdx,
+		at: (f32, f32),
+	},
+	Corpse {
+		map: MapIdx,
+		at: (f32, f32),
+	},
+	/// Rings spreading out from something found on a tile.
+	Discovery {
+		map: MapIdx,
+		at: (f32, f32),
+	},
+}
+
+/// A track playing from `start` for `duration` seconds of timeline time.
+struct Clip {
+	track: Track,
+	start: f32,
+	duration: f32,
+}
+
+impl Clip {
+	/// `0..1` through the clip, `None` before it starts.
+	fn progress(&self, time: f32) -> Option<f32> {
+		if time < self.start {
+			None
+		} else {
+			Some(((time - self.start) / self.duration).min(1.0))
+		}
+	}
+}
+
+/// Short visual sequences played over the game state, which has already changed by the time they
+/// start, so a hit lands and a unit dies on screen a moment after it happened.
+///
+/// Time runs with the simulation speed and stops while it is paused.
+pub struct AnimationTimeline {
+	time: f32,
+	clips: Vec<Clip>,
+}
+
+impl AnimationTimeline {
+	const LUNGE_SECONDS: f32 = 0.3;
+	/// How far towards the defender the attacker lunges, in tiles.
+	const LUNGE_DISTANCE: f32 = 0.35;
+	const FLASH_SECONDS: f32 = 0.2;
+	const FADE_SECONDS: f32 = 0.6;
+	const CORPSE_SECONDS: f32 = 20.0;
+	/// The last part of a corpse's time it spends fading away.
+	const CORPSE_FADE_SECONDS: f32 = 2.0;
+	const DISCOVERY_SECONDS: f32 = 1.2;
+
+	pub fn new() -> AnimationTimeline {
+		AnimationTimeline {
+			time: 0.0,
+			clips: vec![],
+		}
+	}
+
+	/// Queues the sequence for `event`. A defender is hit when the lunge at it peaks, and a
+	/// death shows once the last hit on the entity has landed.
+	pub fn play(&mut self, event: CombatEvent) {
+		match event {
+			CombatEvent::Attack {
+				attacker,
+				defender,
+				toward,
+			} => {
+				let length = (toward.0 * toward.0 + toward.1 * toward.1).sqrt();
+				let toward = if length > f32::EPSILON {
+					(toward.0 / length, toward.1 / length)
+				} else {
+					(0.0, 0.0)
+				};
+				self.clips.push(Clip {
+					track: Track::Lunge {
+						entity: attacker,
+						toward,
+					},
+					start: self.time,
+					duration: Self::LUNGE_SECONDS,
+				});
+				self.clips.push(Clip {
+					track: Track::HitFlash { entity: defender },
+					start: self.time + Self::LUNGE_SECONDS / 2.0,
+					duration: Self::FLASH_SECONDS,
+				});
+			}
+			CombatEvent::Death {
+				entity,
+				sprite,
+				map,
+				at,
+			} => {
+				let start = self
+					.clips
+					.iter()
+					.filter(|clip| match clip.track {
+						Track::HitFlash { entity: hit } => hit == entity,
+						_ => false,
+					})
+					.map(|clip| clip.start)
+					.fold(self.time, f32::max);
+				self.clips.push(Clip {
+					track: Track::Ghost { sprite, map, at },
+					start,
+					duration: Self::FADE_SECONDS,
+				});
+				self.clips.push(Clip {
+					track: Track::Corpse { map, at },
+					start: start + Self::FADE_SECONDS,
+					duration: Self::CORPSE_SECONDS,
+				});
+			}
+		}
+	}
+
+	/// Shows that something was found at `at` on `map`, such as a goody hut being opened.
+	pub fn play_discovery(&mut self, map: MapIdx, at: (f32, f32)) {
+		self.clips.push(Clip {
+			track: Track::Discovery { map, at },
+			start: self.time,
+			duration: Self::DISCOVERY_SECONDS,
+		});
+	}
+
+	/// Advances by `delta` seconds of simulation time, dropping the clips that have finished.
+	pub fn advance(&mut self, delta: f32) {
+		self.time += delta;
+		let time = self.time;
+		self.clips
+			.retain(|clip| clip.progress(time).map_or(true, |progress| progress < 1.0));
+	}
+
+	/// How far from its tile to draw `entity` for the lunges it is in.
+	pub fn sprite_offset(&self, entity: EntityId) -> (f32, f32) {
+		let mut offset = (0.0, 0.0);
+		for clip in &self.clips {
+			let toward = match clip.track {
+				Track::Lunge {
+					entity: lunging,
+					toward,
+				} if lunging == entity => toward,
+				_ => continue,
+			};
+			if let Some(progress) = clip.progress(self.time) {
+				// Out and back, furthest at the middle
+				let reach = (progress * PI).sin() * Self::LUNGE_DISTANCE;
+				offset.0 += toward.0 * reach;
+				offset.1 += toward.1 * reach;
+			}
+		}
+		offset
+	}
+
+	/// How strongly `entity` flashes from being hit, `0..1`.
+	pub fn hit_flash(&self, entity: EntityId) -> f32 {
+		self.clips
+			.iter()
+			.filter_map(|clip| match clip.track {
+				Track::HitFlash { entity: hit } if hit == entity => clip.progress(self.time),
+				_ => None,
+			})
+			.map(|progress| 1.0 - progress)
+			.fold(0.0, f32::max)
+	}
+
+	/// Dead entities still shown on `map`, each with its sprite, where it stood and its alpha.
+	pub fn ghosts(&self, map: MapIdx) -> impl Iterator<Item = (&DrawSprite, (f32, f32), f32)> + '_ {
+		let time = self.time;
+		self.clips.iter().filter_map(move |clip| match &clip.track {
+			Track::Ghost {
+				sprite,
+				map: on,
+				at,
+			} if *on == map => {
+				let alpha = clip.progress(time).map_or(1.0, |progress| 1.0 - progress);
+				Some((sprite, *at, alpha))
+			}
+			_ => None,
+		})
+	}
+
+	/// Where the dead lie on `map`, each with its alpha.
+	pub fn corpses(&self, map: MapIdx) -> impl Iterator<Item = ((f32, f32), f32)> + '_ {
+		let time = self.time;
+		self.clips.iter().filter_map(move |clip| match clip.track {
+			Track::Corpse { map: on, at } if on == map => {
+				let progress = clip.progress(time)?;
+				let remaining = (1.0 - progress) * clip.duration;
+				Some((at, (remaining / Self::CORPSE_FADE_SECONDS).min(1.0)))
+			}
+			_ => None,
+		})
+	}
+
+	/// Discoveries still showing on `map`, each with where it was and how far along it is, `0..1`.
+	pub fn discoveries(&self, map: MapIdx) -> impl Iterator<Item = ((f32, f32), f32)> + '_ {
+		let time = self.time;
+		self.clips.iter().filter_map(move |clip| match clip.track {
+			Track::Discovery { map: on, at } if on == map => Some((at, clip.progress(time)?)),
+			_ => None,
+		})
+	}
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use structopt::StructOpt;
+
+/// Size of a generated map, passed to the generator as its largest coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapSize {
+	pub width: u8,
+	pub height: u8,
+}
+
+impl FromStr for MapSize {
+	type Err = String;
+
+	/// `WIDTHxHEIGHT`, or one number for a square map.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let parse = |part: &str| match part.trim().parse::<u8>() {
+			Ok(size) if size > 0 => Ok(size),
+			_ => Err(format!("`{}` is not a map size from 1 to 255", part)),
+		};
+		let mut parts = s.splitn(2, |c| c == 'x' || c == 'X');
+		let width = parse(parts.next().unwrap_or(""))?;
+		let height = match parts.next() {
+			Some(height) => parse(height)?,
+			None => width,
+		};
+		Ok(MapSize { width, height })
+	}
+}
+
+/// How the starting map is generated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeneratorChoice {
+	/// Rows of dirt, grass and sand scattered with trees, rocks and flowers.
+	Alternating,
+	/// The same rows without the decorations.
+	Bare,
+}
+
+impl FromStr for GeneratorChoice {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"alternating" => Ok(GeneratorChoice::Alternating),
+			"bare" => Ok(GeneratorChoice::Bare),
+			_ => Err(format!(
+				"unknown generator `{}`, expected `alternating` or `bare`",
+				s
+			)),
+		}
+	}
+}
+
+/// Where a hosted game is, as `host:port`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerAddress {
+	pub host: String,
+	pub port: u16,
+}
+
+impl FromStr for ServerAddress {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		// From the right so IPv6 hosts keep their colons
+		let mut parts = s.rsplitn(2, ':');
+		let port = parts.next().unwrap_or("");
+		let host = parts.next().unwrap_or("");
+		if host.is_empty() {
+			return Err(format!("`{}` is not a `host:port` address", s));
+		}
+		let port = port
+			.parse()
+			.map_err(|_e| format!("`{}` is not a port number", port))?;
+		Ok(ServerAddress {
+			host: host.to_owned(),
+			port,
+		})
+	}
+}
+
+impl fmt::Display for ServerAddress {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}:{}", self.host, self.port)
+	}
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(about = "A little hex map game")]
+pub struct Args {
+	/// Where saves, settings, logs and crash reports go, instead of the platform's data directory
+	#[structopt(long, value_name = "path")]
+	pub data_dir: Option<String>,
+
+	/// Size of the starting map as `WIDTHxHEIGHT`, or one number for a square map
+	#[structopt(long, default_value = "6x6", value_name = "size")]
+	pub map_size: MapSize,
+
+	/// Seed for generating maps, the same seed generates the same maps
+	#[structopt(long)]
+	pub seed: Option<u64>,
+
+	/// Draws the hexes with flat tops instead of points, the tile art has to be drawn for it
+	#[structopt(long)]
+	pub flat_top: bool,
+
+	/// How the starting map is generated, `alternating` or `bare`
+	#[structopt(long, default_value = "alternating", value_name = "name")]
+	pub generator: GeneratorChoice,
+
+	/// A Tiled `.json` or text `.txt` map, in the resources or user data, to start on instead of
+	/// the generated maps
+	#[structopt(long, value_name = "path")]
+	pub scenario: Option<String>,
+
+	/// Sets the maps up without opening a window, prints them and exits
+	#[structopt(long)]
+	pub headless: bool,
+
+	/// Paints the tile changes recorded in a game journal, in the resources or user data, over the
+	/// starting maps
+	#[structopt(long, value_name = "journal")]
+	pub replay: Option<String>,
+
+	/// Joins the game hosted at `host:port`
+	#[structopt(long, value_name = "host:port")]
+	pub connect: Option<ServerAddress>,
+
+	/// A folder of config files used before the saved settings and the resources' `config/`
+	#[structopt(long, value_name = "path", parse(from_os_str))]
+	pub config: Option<PathBuf>,
+
+	/// Writes the game journal to this file in the user data directory, overriding
+	/// `config/journal.ron`
+	#[structopt(long, value_name = "file")]
+	pub journal: Option<String>,
+
+	/// Also imports this Tiled JSON map, in the resources or user data
+	#[structopt(long, value_name = "path")]
+	pub import_tiled: Option<String>,
+
+	/// Writes a chrome trace, viewable at `chrome://tracing`
+	#[structopt(long, value_name = "file")]
+	pub trace_chrome: Option<String>,
+
+	/// Checks the resources and packs them with pre-built atlases into this zip, then exits
+	#[structopt(long, value_name = "file")]
+	pub pack_assets: Option<String>,
+}
@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 
+use ggez::graphics::{DrawParam, Rect};
 use guillotiere::{AllocId, Allocation, AllocatorOptions, AtlasAllocator, Size};
 use indexmap::map::IndexMap;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use thiserror::*;
@@ -28,6 +30,18 @@ pub enum AtlasError {
 #[derive(Clone, Copy, Debug)]
 pub struct AtlasId<Unique: Copy>(usize, PhantomData<Unique>);
 
+/// Borders in source pixels that keep their size when the image is stretched over a larger area,
+/// only the edges and middle between them stretch, or repeat if `tiled`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NineSlice {
+	pub left: u16,
+	pub top: u16,
+	pub right: u16,
+	pub bottom: u16,
+	#[serde(default)]
+	pub tiled: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct AtlasEntry<Unique: Copy> {
 	id: AtlasId<Unique>,
@@ -35,6 +49,13 @@ pub struct AtlasEntry<Unique: Copy> {
 	pub name: String,
 	pub min: [f32; 2],
 	pub max: [f32; 2],
+	/// Size of the source image in pixels.
+	pub size: [u16; 2],
+	pub nine_slice: Option<NineSlice>,
+	/// `None` for entries of a pre-built atlas, which has no allocator to give space back to.
+	alloc_id: Option<AllocId>,
+	/// Its space was given back, the id stays reserved for the name if it is created again.
+	released: bool,
 }
 
 impl<Unique: Copy> AtlasEntry<Unique> {
@@ -54,14 +75,62 @@ impl<Unique: Copy> AtlasEntry<Unique> {
 		self.max[1]
 	}
 
+	/// Width in the page's texture coordinates, see `width_px` for pixels.
 	pub fn width(&self) -> f32 {
 		self.max[0] - self.min[0]
 	}
 
+	/// Height in the page's texture coordinates, see `height_px` for pixels.
 	pub fn height(&self) -> f32 {
 		self.max[1] - self.min[1]
 	}
 
+	pub fn width_px(&self) -> u16 {
+		self.size[0]
+	}
+
+	pub fn height_px(&self) -> u16 {
+		self.size[1]
+	}
+
+	pub fn size_px(&self) -> (u16, u16) {
+		(self.size[0], self.size[1])
+	}
+
+	/// Where this entry is on its page, for `DrawParam::src`.
+	pub fn src(&self) -> Rect {
+		Rect::new(self.left(), self.top(), self.width(), self.height())
+	}
+
+	/// Draws this entry from its page stretched over `area`, which need not keep its aspect.
+	///
+	/// ggez sizes a `src` sub-rect by how many of the page's pixels it covers, so the scale is
+	/// relative to this entry's pixel size rather than to one.
+	pub fn draw_param(&self, area: Rect) -> DrawParam {
+		self.draw_param_at_level(area, 0)
+	}
+
+	/// `draw_param` for drawing from mip `level` of its page, which has `2^level` times fewer
+	/// pixels across to scale up.
+	pub fn draw_param_at_level(&self, area: Rect, level: usize) -> DrawParam {
+		let level_scale = (1 << level) as f32;
+		DrawParam::new()
+			.src(self.src())
+			.dest([area.x, area.y])
+			.scale([
+				area.w / self.width_px() as f32 * level_scale,
+				area.h / self.height_px() as f32 * level_scale,
+			])
+	}
+
+	/// Texture coordinate of a point given as a `0..1` fraction across and down this entry.
+	pub fn uv_at(&self, x: f32, y: f32) -> [f32; 2] {
+		[
+			self.min[0] + x * self.width(),
+			self.min[1] + y * self.height(),
+		]
+	}
+
 	pub fn get_id(&self) -> AtlasId<Unique> {
 		self.id
 	}
@@ -69,6 +138,10 @@ impl<Unique: Copy> AtlasEntry<Unique> {
 	pub fn get_atlas_idx(&self) -> usize {
 		self.atlas_id
 	}
+
+	pub fn is_released(&self) -> bool {
+		self.released
+	}
 }
 
 pub struct AtlasBuilder<ImageType, Unique: Copy> {
@@ -83,6 +156,8 @@ pub struct AtlasBuilder<ImageType, Unique: Copy> {
 pub struct Atlas<ImageType, Unique: Copy> {
 	atlas_id: usize,
 	pub image: ImageType,
+	/// Each half the size of the one before, starting at half of `image`.
+	pub mips: Vec<ImageType>,
 	entries: IndexMap<String, AtlasEntry<Unique>>,
 }
 
@@ -113,6 +188,10 @@ impl<ImageType, Unique: Copy> AtlasBuilder<ImageType, Unique> {
 		}
 	}
 
+	fn has_live_entries(&self) -> bool {
+		self.entries.values().any(|entry| !entry.released)
+	}
+
 	pub fn get_or_create_with<I, FI>(
 		&mut self,
 		name: &str,
@@ -122,72 +201,209 @@ impl<ImageType, Unique: Copy> AtlasBuilder<ImageType, Unique> {
 		I: IntoIterator<Item = u8>,
 		FI: FnOnce() -> Result<(u16, u16, I), anyhow::Error>,
 	{
-		if let Some(id) = self.entries.get_index_of(name) {
-			return Ok(AtlasId(id, Default::default()));
+		let existing = self.entries.get_full(name);
+		if let Some((id, _name, entry)) = existing {
+			if !entry.released {
+				return Ok(AtlasId(id, Default::default()));
+			}
 		}
+		let nine_slice = existing.and_then(|(_id, _name, entry)| entry.nine_slice);
+		let existing = existing.map(|(id, _name, _entry)| id);
 
 		let (width, height, image_data) = image_fn()?;
-		match self
+		let alloc = self.allocate_image(name, width, height, image_data)?;
+		let size = self.allocator.size();
+		let id = AtlasId(
+			existing.unwrap_or_else(|| self.entries.len()),
+			Default::default(),
+		);
+		let entry = AtlasEntry {
+			id,
+			atlas_id: self.atlas_id,
+			name: name.into(),
+			min: [
+				alloc.rectangle.min.x as f32 / size.width as f32,
+				alloc.rectangle.min.y as f32 / size.height as f32,
+			],
+			max: [
+				alloc.rectangle.max.x as f32 / size.width as f32,
+				alloc.rectangle.max.y as f32 / size.height as f32,
+			],
+			size: [width, height],
+			nine_slice,
+			alloc_id: Some(alloc.id),
+			released: false,
+		};
+		self.entries.insert(name.into(), entry);
+		Ok(id)
+	}
+
+	/// Finds space for the image and copies it in, giving the space back if the image data
+	/// doesn't match its size.
+	fn allocate_image(
+		&mut self,
+		name: &str,
+		width: u16,
+		height: u16,
+		image_data: impl IntoIterator<Item = u8>,
+	) -> Result<Allocation, AtlasError> {
+		let alloc = self
 			.allocator
 			.allocate((width as i32, height as i32).into())
-		{
-			None => Err(AtlasError::AllocationFailed),
-			Some(alloc) => {
-				let atlas_size = self.allocator.size();
-				let rgba = &mut self.image_data;
-				let stride = atlas_size.width * 4;
-				let mut iter = image_data.into_iter();
-				for y in alloc.rectangle.min.y..alloc.rectangle.max.y {
-					for x in (alloc.rectangle.min.x * 4)..(alloc.rectangle.max.x * 4) {
-						let idx = ((y * stride) + x) as usize;
-						match iter.next() {
-							None => {
-								return Err(AtlasError::SourceImageTooSmallError(name.into()));
-							}
-							Some(v) => {
-								rgba[idx] = v;
-							}
-						}
+			.ok_or(AtlasError::AllocationFailed)?;
+		let atlas_size = self.allocator.size();
+		let rgba = &mut self.image_data;
+		let stride = atlas_size.width * 4;
+		let mut iter = image_data.into_iter();
+		let mut result = Ok(alloc);
+		'rows: for y in alloc.rectangle.min.y..alloc.rectangle.max.y {
+			for x in (alloc.rectangle.min.x * 4)..(alloc.rectangle.max.x * 4) {
+				let idx = ((y * stride) + x) as usize;
+				match iter.next() {
+					None => {
+						result = Err(AtlasError::SourceImageTooSmallError(name.into()));
+						break 'rows;
+					}
+					Some(v) => {
+						rgba[idx] = v;
 					}
 				}
-				if iter.next().is_some() {
-					return Err(AtlasError::SourceImageTooLargeError(name.into()));
-				}
-				let id = AtlasId(self.entries.len(), Default::default());
+			}
+		}
+		if result.is_ok() && iter.next().is_some() {
+			result = Err(AtlasError::SourceImageTooLargeError(name.into()));
+		}
+		match result {
+			Ok(alloc) => {
 				self.allocations.insert(alloc.id, alloc);
-				let size = self.allocator.size();
-				let entry = AtlasEntry {
-					id,
-					atlas_id: self.atlas_id,
-					name: name.into(),
-					min: [
-						alloc.rectangle.min.x as f32 / size.width as f32,
-						alloc.rectangle.min.y as f32 / size.height as f32,
-					],
-					max: [
-						alloc.rectangle.max.x as f32 / size.height as f32,
-						alloc.rectangle.max.y as f32 / size.height as f32,
-					],
-				};
-				self.entries.insert(name.into(), entry);
-				Ok(id)
 			}
+			Err(_) => self.allocator.deallocate(alloc.id),
+		}
+		result
+	}
+
+	/// Gives the entry's space back to be reused, returns false if there is no such live entry.
+	///
+	/// Its id stays reserved for the name, creating it again fills the same id.
+	pub fn release(&mut self, name: &str) -> bool {
+		let entry = match self.entries.get_mut(name) {
+			Some(entry) if !entry.released => entry,
+			_ => return false,
+		};
+		entry.released = true;
+		if let Some(alloc_id) = entry.alloc_id {
+			self.allocator.deallocate(alloc_id);
+			self.allocations.remove(&alloc_id);
 		}
+		true
+	}
+
+	/// Copies an entry's pixels back out of the page.
+	fn entry_pixels(&self, entry: &AtlasEntry<Unique>) -> Vec<u8> {
+		let alloc_id = entry
+			.alloc_id
+			.expect("entries of an atlas being built always have an allocation");
+		let rectangle = self.allocations[&alloc_id].rectangle;
+		let stride = self.allocator.size().width as usize * 4;
+		let mut pixels = Vec::with_capacity(entry.size[0] as usize * entry.size[1] as usize * 4);
+		for y in rectangle.min.y as usize..rectangle.max.y as usize {
+			let row = y * stride;
+			pixels.extend_from_slice(
+				&self.image_data
+					[row + rectangle.min.x as usize * 4..row + rectangle.max.x as usize * 4],
+			);
+		}
+		pixels
 	}
 
 	pub fn generate<F>(&self, generate_image: &mut F) -> anyhow::Result<Atlas<ImageType, Unique>>
+	where
+		F: FnMut(u16, u16, &[u8]) -> anyhow::Result<ImageType>,
+	{
+		self.generate_with_mips(0, generate_image)
+	}
+
+	/// Also generates up to `mip_levels` halved copies of the page, stopping early if it gets
+	/// down to a single pixel.
+	///
+	/// Entries are packed edge to edge, so their edges blend a little with their neighbours in
+	/// the smaller copies.
+	pub fn generate_with_mips<F>(
+		&self,
+		mip_levels: usize,
+		generate_image: &mut F,
+	) -> anyhow::Result<Atlas<ImageType, Unique>>
 	where
 		F: FnMut(u16, u16, &[u8]) -> anyhow::Result<ImageType>,
 	{
 		let size = self.allocator.size();
+		let (image, mips) = generate_page_with_mips(
+			size.width as u16,
+			size.height as u16,
+			&self.image_data,
+			mip_levels,
+			generate_image,
+		)?;
 		Ok(Atlas {
 			atlas_id: self.atlas_id,
-			image: generate_image(size.width as u16, size.height as u16, &self.image_data)?,
+			image,
+			mips,
 			entries: self.entries.clone(),
 		})
 	}
 }
 
+fn generate_page_with_mips<ImageType, F>(
+	mut width: u16,
+	mut height: u16,
+	page: &[u8],
+	mip_levels: usize,
+	generate_image: &mut F,
+) -> anyhow::Result<(ImageType, Vec<ImageType>)>
+where
+	F: FnMut(u16, u16, &[u8]) -> anyhow::Result<ImageType>,
+{
+	let image = generate_image(width, height, page)?;
+	let mut mips = Vec::with_capacity(mip_levels);
+	let mut rgba: Option<Vec<u8>> = None;
+	while mips.len() < mip_levels && (width > 1 || height > 1) {
+		let (w, h, half) = downsample(width, height, rgba.as_deref().unwrap_or(page));
+		mips.push(generate_image(w, h, &half)?);
+		width = w;
+		height = h;
+		rgba = Some(half);
+	}
+	Ok((image, mips))
+}
+
+/// Halves an rgba8 image by averaging each 2x2 block, weighting the colors by alpha so clear
+/// pixels don't darken the edges of what's next to them. An odd last row or column is dropped.
+fn downsample(width: u16, height: u16, rgba: &[u8]) -> (u16, u16, Vec<u8>) {
+	let (half_width, half_height) = ((width / 2).max(1), (height / 2).max(1));
+	let stride = width as usize * 4;
+	let mut half = Vec::with_capacity(half_width as usize * half_height as usize * 4);
+	for y in 0..half_height as usize {
+		for x in 0..half_width as usize {
+			let (mut color, mut alpha) = ([0u32; 3], 0u32);
+			for (dx, dy) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+				let sx = (x * 2 + dx).min(width as usize - 1);
+				let sy = (y * 2 + dy).min(height as usize - 1);
+				let pixel = &rgba[sy * stride + sx * 4..][..4];
+				let a = pixel[3] as u32;
+				for (sum, &c) in color.iter_mut().zip(pixel) {
+					*sum += c as u32 * a;
+				}
+				alpha += a;
+			}
+			for &sum in &color {
+				half.push(if alpha == 0 { 0 } else { (sum / alpha) as u8 });
+			}
+			half.push((alpha / 4) as u8);
+		}
+	}
+	(half_width, half_height, half)
+}
+
 impl<ImageType, Unique: Copy> Atlas<ImageType, Unique> {
 	pub fn get_entry(&self, id: AtlasId<Unique>) -> &AtlasEntry<Unique> {
 		match &self.entries.get_index(id.0) {
@@ -197,6 +413,48 @@ impl<ImageType, Unique: Copy> Atlas<ImageType, Unique> {
 	}
 }
 
+/// Where an entry is on the pages of a pre-built atlas.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PackedAtlasEntry {
+	pub name: String,
+	pub page: usize,
+	pub min: [f32; 2],
+	pub max: [f32; 2],
+	pub size: [u16; 2],
+	#[serde(default)]
+	pub nine_slice: Option<NineSlice>,
+}
+
+impl PackedAtlasEntry {
+	/// Copies the entry's pixels out of its page.
+	pub fn pixels(&self, page_width: u16, page_height: u16, page: &[u8]) -> Vec<u8> {
+		let left = (self.min[0] * page_width as f32).round() as usize;
+		let top = (self.min[1] * page_height as f32).round() as usize;
+		let stride = page_width as usize * 4;
+		let row_bytes = self.size[0] as usize * 4;
+		let mut pixels = Vec::with_capacity(row_bytes * self.size[1] as usize);
+		for y in top..top + self.size[1] as usize {
+			let row = y * stride + left * 4;
+			pixels.extend_from_slice(&page[row..row + row_bytes]);
+		}
+		pixels
+	}
+}
+
+/// The entries of a `MultiAtlas` saved alongside its page images, so it can be loaded again
+/// without decoding and packing every image.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PackedAtlas {
+	pub pages: usize,
+	pub entries: Vec<PackedAtlasEntry>,
+}
+
+impl PackedAtlas {
+	pub fn contains(&self, name: &str) -> bool {
+		self.entries.iter().any(|entry| entry.name == name)
+	}
+}
+
 pub struct MultiAtlasBuilder<ImageType, Unique: Copy> {
 	atlases: Vec<AtlasBuilder<ImageType, Unique>>,
 	entries: IndexMap<String, AtlasEntry<Unique>>,
@@ -222,6 +480,13 @@ impl<ImageType, Unique: Copy> MultiAtlasBuilder<ImageType, Unique> {
 		}
 	}
 
+	pub fn set_nine_slice(&mut self, id: AtlasId<Unique>, nine_slice: Option<NineSlice>) {
+		match self.entries.get_index_mut(id.0) {
+			None => panic!("looked up atlas entry with invalid atlas id, should never happen unless an atlas is recreated, id: {}", id.0),
+			Some((_key, entry)) => entry.nine_slice = nine_slice,
+		}
+	}
+
 	pub fn get_or_create_with<I, FI>(
 		&mut self,
 		name: &str,
@@ -231,35 +496,106 @@ impl<ImageType, Unique: Copy> MultiAtlasBuilder<ImageType, Unique> {
 		I: IntoIterator<Item = u8>,
 		FI: FnOnce() -> Result<(u16, u16, I), anyhow::Error>,
 	{
-		if let Some(id) = self.entries.get_index_of(name) {
-			return Ok(AtlasId(id, Default::default()));
+		let existing = self.entries.get_full(name);
+		if let Some((id, _name, entry)) = existing {
+			if !entry.released {
+				return Ok(AtlasId(id, Default::default()));
+			}
 		}
+		let nine_slice = existing.and_then(|(_id, _name, entry)| entry.nine_slice);
+		let existing = existing.map(|(id, _name, _entry)| id);
 
 		let (width, height, image_data) = image_fn()?;
+		let image_data: Vec<u8> = image_data.into_iter().collect();
+		let id = AtlasId(
+			existing.unwrap_or_else(|| self.entries.len()),
+			Default::default(),
+		);
+		let entry = self.place(name, width, height, &image_data)?;
+		let entry = AtlasEntry {
+			id,
+			nine_slice,
+			..entry
+		};
+		self.entries.insert(name.into(), entry);
+		Ok(id)
+	}
+
+	/// Puts the image on the first page with room for it, starting a new page if none has.
+	fn place(
+		&mut self,
+		name: &str,
+		width: u16,
+		height: u16,
+		image_data: &[u8],
+	) -> Result<AtlasEntry<Unique>, AtlasError> {
 		for atlas in self.atlases.iter_mut() {
-			match atlas.get_or_create_with(name, || Ok((width, height, image_data.into_iter()))) {
-				Ok(result) => {
-					let id = AtlasId(self.entries.len(), Default::default());
-					let mut entry = (*atlas.get_entry(result)).clone();
-					entry.id = id;
-					self.entries.insert(name.into(), entry);
-					return Ok(id);
-				}
-				Err(AtlasError::AllocationFailed) => {
-					todo!();
-				}
-				err => {
-					return err;
-				}
+			match atlas.get_or_create_with(name, || Ok((width, height, image_data.iter().copied())))
+			{
+				Ok(result) => return Ok(atlas.get_entry(result).clone()),
+				Err(AtlasError::AllocationFailed) => continue,
+				Err(error) => return Err(error),
 			}
 		}
 
 		let (w, h) = self.atlases[0].allocator.size().to_tuple();
-		let last = self.atlases.len() - 1;
-		self.atlases
-			.push(AtlasBuilder::new_multi(last, w as u16, h as u16));
-		// If it can't fit on a new one then something is just wrong...
-		self.atlases[last].get_or_create_with(name, || Ok((width, height, image_data.into_iter())))
+		let mut atlas = AtlasBuilder::new_multi(self.atlases.len(), w as u16, h as u16);
+		// If it can't fit on a new one then it's larger than a page
+		let result =
+			atlas.get_or_create_with(name, || Ok((width, height, image_data.iter().copied())))?;
+		let entry = atlas.get_entry(result).clone();
+		self.atlases.push(atlas);
+		Ok(entry)
+	}
+
+	/// Gives the entry's space back to be reused, returns false if there is no such live entry.
+	///
+	/// Its id stays reserved for the name, creating it again fills the same id, possibly on
+	/// another page.
+	pub fn release(&mut self, name: &str) -> bool {
+		let entry = match self.entries.get_mut(name) {
+			Some(entry) if !entry.released => entry,
+			_ => return false,
+		};
+		entry.released = true;
+		self.atlases[entry.atlas_id].release(name)
+	}
+
+	/// Repacks the live entries onto as few fresh pages as they fit, largest first, after enough
+	/// have been released to leave the pages patchy. Ids and names stay the same.
+	pub fn defragment(&mut self) -> Result<(), AtlasError> {
+		let (w, h) = self.atlases[0].allocator.size().to_tuple();
+		let old = std::mem::replace(
+			&mut self.atlases,
+			vec![AtlasBuilder::new_multi(0, w as u16, h as u16)],
+		);
+		let mut live: Vec<(usize, u32)> = self
+			.entries
+			.values()
+			.enumerate()
+			.filter(|(_index, entry)| !entry.released)
+			.map(|(index, entry)| (index, entry.size[0] as u32 * entry.size[1] as u32))
+			.collect();
+		live.sort_by_key(|&(_index, area)| std::cmp::Reverse(area));
+		for (index, _area) in live {
+			let (name, entry) = self
+				.entries
+				.get_index(index)
+				.expect("index was just taken from the entries");
+			let name = name.clone();
+			let pixels = old[entry.atlas_id].entry_pixels(entry);
+			let [width, height] = entry.size;
+			let placed = self.place(&name, width, height, &pixels)?;
+			let (_name, entry) = self
+				.entries
+				.get_index_mut(index)
+				.expect("index was just taken from the entries");
+			entry.atlas_id = placed.atlas_id;
+			entry.min = placed.min;
+			entry.max = placed.max;
+			entry.alloc_id = placed.alloc_id;
+		}
+		Ok(())
 	}
 
 	pub fn generate<F>(
@@ -269,11 +605,30 @@ impl<ImageType, Unique: Copy> MultiAtlasBuilder<ImageType, Unique> {
 	where
 		F: FnMut(u16, u16, &[u8]) -> anyhow::Result<ImageType>,
 	{
-		let mut atlases = Vec::with_capacity(self.atlases.len());
-		for atlas in self.atlases.iter().filter(|a| !a.entries.is_empty()) {
-			atlases.push(atlas.generate(generate_image)?);
+		self.generate_with_mips(0, generate_image)
+	}
+
+	/// Generates every page with up to `mip_levels` halved copies, see
+	/// `AtlasBuilder::generate_with_mips`.
+	pub fn generate_with_mips<F>(
+		&self,
+		mip_levels: usize,
+		generate_image: &mut F,
+	) -> anyhow::Result<MultiAtlas<ImageType, Unique>>
+	where
+		F: FnMut(u16, u16, &[u8]) -> anyhow::Result<ImageType>,
+	{
+		// Empty pages at the end are left out, ones in the middle have to stay so the later pages
+		// keep their indices
+		let pages = self
+			.atlases
+			.iter()
+			.rposition(|atlas| atlas.has_live_entries())
+			.map_or(0, |last| last + 1);
+		let mut atlases = Vec::with_capacity(pages);
+		for atlas in &self.atlases[..pages] {
+			atlases.push(atlas.generate_with_mips(mip_levels, generate_image)?);
 		}
-		atlases.shrink_to_fit();
 		Ok(MultiAtlas {
 			atlases,
 			entries: self.entries.clone(),
@@ -282,6 +637,83 @@ impl<ImageType, Unique: Copy> MultiAtlasBuilder<ImageType, Unique> {
 }
 
 impl<ImageType, Unique: Copy> MultiAtlas<ImageType, Unique> {
+	/// Rebuilds the atlas from `packed` and the rgba8 pixels of its pages, as width, height and
+	/// data, with up to `mip_levels` halved copies of each page. Entries can't be released.
+	pub fn from_packed<F>(
+		packed: &PackedAtlas,
+		pages: &[(u16, u16, Vec<u8>)],
+		mip_levels: usize,
+		generate_image: &mut F,
+	) -> anyhow::Result<MultiAtlas<ImageType, Unique>>
+	where
+		F: FnMut(u16, u16, &[u8]) -> anyhow::Result<ImageType>,
+	{
+		anyhow::ensure!(
+			pages.len() == packed.pages,
+			"packed atlas has {} pages but {} were given",
+			packed.pages,
+			pages.len()
+		);
+		let mut entries = IndexMap::with_capacity(packed.entries.len());
+		for (index, entry) in packed.entries.iter().enumerate() {
+			anyhow::ensure!(
+				entry.page < pages.len(),
+				"packed atlas entry `{}` is on page {} of {}",
+				entry.name,
+				entry.page,
+				pages.len()
+			);
+			entries.insert(
+				entry.name.clone(),
+				AtlasEntry {
+					id: AtlasId(index, Default::default()),
+					atlas_id: entry.page,
+					name: entry.name.clone(),
+					min: entry.min,
+					max: entry.max,
+					size: entry.size,
+					nine_slice: entry.nine_slice,
+					alloc_id: None,
+					released: false,
+				},
+			);
+		}
+		let mut atlases = Vec::with_capacity(pages.len());
+		for (atlas_id, (width, height, rgba)) in pages.iter().enumerate() {
+			let (image, mips) =
+				generate_page_with_mips(*width, *height, rgba, mip_levels, generate_image)?;
+			atlases.push(Atlas {
+				atlas_id,
+				image,
+				mips,
+				entries: entries
+					.iter()
+					.filter(|(_name, entry)| entry.atlas_id == atlas_id)
+					.map(|(name, entry)| (name.clone(), entry.clone()))
+					.collect(),
+			});
+		}
+		Ok(MultiAtlas { atlases, entries })
+	}
+
+	/// The live entries, to save with the page images and load again with `from_packed`.
+	pub fn to_packed(&self) -> PackedAtlas {
+		PackedAtlas {
+			pages: self.atlases.len(),
+			entries: self
+				.entries()
+				.map(|entry| PackedAtlasEntry {
+					name: entry.name.clone(),
+					page: entry.atlas_id,
+					min: entry.min,
+					max: entry.max,
+					size: entry.size,
+					nine_slice: entry.nine_slice,
+				})
+				.collect(),
+		}
+	}
+
 	pub fn get_entry(&self, id: AtlasId<Unique>) -> &AtlasEntry<Unique> {
 		match &self.entries.get_index(id.0) {
 			None => panic!("looked up atlas entry with invalid atlas id, should never happen unless an atlas is recreated, id: {}", id.0),
@@ -305,7 +737,158 @@ impl<ImageType, Unique: Copy> MultiAtlas<ImageType, Unique> {
 		Some(&self.atlases[id].image)
 	}
 
+	/// The page's image at mip `level`, the full size one at 0.
+	pub fn get_mip_image_by_index(&self, id: usize, level: usize) -> Option<&ImageType> {
+		let atlas = self.atlases.get(id)?;
+		match level {
+			0 => Some(&atlas.image),
+			level => atlas.mips.get(level - 1),
+		}
+	}
+
+	/// How many mip levels every page has past the full size one.
+	pub fn mip_levels(&self) -> usize {
+		self.atlases
+			.iter()
+			.map(|atlas| atlas.mips.len())
+			.min()
+			.unwrap_or(0)
+	}
+
+	/// The mip level to draw from when each pixel on screen covers `texels_per_pixel` pixels of
+	/// the full size pages, the smallest that still has at least one pixel per screen pixel.
+	pub fn mip_level(&self, texels_per_pixel: f32) -> usize {
+		if texels_per_pixel.is_nan() || texels_per_pixel <= 1.0 {
+			return 0;
+		}
+		(texels_per_pixel.log2().floor() as usize).min(self.mip_levels())
+	}
+
 	pub fn get_entry_by_name(&self, name: &str) -> Option<&AtlasEntry<Unique>> {
-		self.entries.get(name)
+		self.entries.get(name).filter(|entry| !entry.released)
+	}
+
+	/// Every entry that wasn't released.
+	pub fn entries(&self) -> impl Iterator<Item = &AtlasEntry<Unique>> + '_ {
+		self.entries.values().filter(|entry| !entry.released)
+	}
+}
+
+#[cfg(test)]
+mod atlas_tests {
+	use super::*;
+
+	#[derive(Clone, Copy, Debug)]
+	enum TestAtlas {}
+
+	type TestBuilder = MultiAtlasBuilder<Vec<u8>, TestAtlas>;
+
+	/// A `width` by `height` image where every byte says which image and where in it it is.
+	fn image(seed: u8, width: u16, height: u16) -> (u16, u16, Vec<u8>) {
+		let len = width as usize * height as usize * 4;
+		let rgba = (0..len).map(|i| seed.wrapping_mul(31) ^ i as u8).collect();
+		(width, height, rgba)
+	}
+
+	fn add(builder: &mut TestBuilder, name: &str, seed: u8, width: u16, height: u16) -> usize {
+		builder
+			.get_or_create_with(name, || Ok(image(seed, width, height)))
+			.unwrap()
+			.0
+	}
+
+	fn pages(builder: &TestBuilder) -> (MultiAtlas<Vec<u8>, TestAtlas>, Vec<Vec<u8>>) {
+		let atlas = builder
+			.generate(&mut |_width, _height, rgba| Ok(rgba.to_vec()))
+			.unwrap();
+		let pages = (0..atlas.len_atlases())
+			.map(|page| atlas.get_image_by_index(page).unwrap().clone())
+			.collect();
+		(atlas, pages)
+	}
+
+	#[test]
+	fn released_names_come_back_with_their_id() {
+		let mut builder = TestBuilder::new(32, 32);
+		let a = add(&mut builder, "a", 1, 8, 8);
+		let b = add(&mut builder, "b", 2, 8, 8);
+		assert!(builder.release("a"));
+		assert!(!builder.release("a"));
+		assert!(builder.get_entry(AtlasId(a, PhantomData)).is_released());
+		assert_eq!(add(&mut builder, "c", 3, 8, 8), 2);
+		assert_eq!(add(&mut builder, "a", 1, 16, 8), a);
+		let entry = builder.get_entry(AtlasId(a, PhantomData));
+		assert!(!entry.is_released());
+		assert_eq!(entry.size_px(), (16, 8));
+		assert_eq!(add(&mut builder, "b", 2, 8, 8), b);
+	}
+
+	#[test]
+	fn full_pages_spill_onto_new_ones() {
+		let mut builder = TestBuilder::new(16, 16);
+		add(&mut builder, "full", 1, 16, 16);
+		let spilled = add(&mut builder, "spilled", 2, 8, 8);
+		assert_eq!(
+			builder
+				.get_entry(AtlasId(spilled, PhantomData))
+				.get_atlas_idx(),
+			1
+		);
+		match builder.get_or_create_with("huge", || Ok(image(3, 17, 1))) {
+			Err(AtlasError::AllocationFailed) => (),
+			other => panic!(
+				"expected an allocation failure, got {:?}",
+				other.map(|id| id.0)
+			),
+		}
+		assert_eq!(pages(&builder).0.len_atlases(), 2);
+	}
+
+	#[test]
+	fn defragment_keeps_ids_names_and_pixels() {
+		let mut builder = TestBuilder::new(16, 16);
+		let sizes = [
+			("big", 16, 16),
+			("small", 8, 4),
+			("gone", 16, 8),
+			("square", 8, 8),
+		];
+		let ids: Vec<usize> = sizes
+			.iter()
+			.enumerate()
+			.map(|(seed, &(name, width, height))| {
+				add(&mut builder, name, seed as u8, width, height)
+			})
+			.collect();
+		assert!(builder.release("big"));
+		assert!(builder.release("gone"));
+		let (atlas, before_pages) = pages(&builder);
+		assert_eq!(before_pages.len(), 3);
+		let before = atlas.to_packed();
+
+		builder.defragment().unwrap();
+		let (atlas, after_pages) = pages(&builder);
+		assert_eq!(after_pages.len(), 1);
+		for (&(name, _width, _height), &id) in sizes.iter().zip(&ids) {
+			let entry = builder.get_entry(AtlasId(id, PhantomData));
+			assert_eq!(entry.name, name);
+			assert_eq!(entry.get_id().0, id);
+		}
+		let after = atlas.to_packed();
+		assert_eq!(after.entries.len(), 2);
+		for old in &before.entries {
+			let new = after
+				.entries
+				.iter()
+				.find(|entry| entry.name == old.name)
+				.unwrap();
+			assert_eq!(new.page, 0);
+			assert_eq!(
+				new.pixels(16, 16, &after_pages[new.page]),
+				old.pixels(16, 16, &before_pages[old.page]),
+				"pixels of `{}` changed",
+				old.name
+			);
+		}
 	}
 }
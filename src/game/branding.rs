@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use anyhow::Context as AnyContext;
+use ggez::{graphics, Context};
+use log::*;
+
+pub const GAME_TITLE: &str = "OverSimpleGame1";
+/// Identifies the game to ggez and the platform's directories.
+pub const GAME_ID: &str = "over-simple-game-1";
+pub const AUTHOR: &str = "OvermindDL1";
+
+const ICON_PATH: &str = "/icon.png";
+const FALLBACK_ICON_SIZE: u32 = 64;
+
+/// Sets the window icon from `/icon.png` in the resources, or a generated hexagon if it is missing.
+pub fn set_window_icon(ctx: &mut Context) -> anyhow::Result<()> {
+	if ggez::filesystem::exists(ctx, ICON_PATH) {
+		graphics::set_window_icon(ctx, Some(Path::new(ICON_PATH)))
+			.context("failed setting the window icon")?;
+	} else {
+		debug!(
+			"No `{}` in the resources, using the generated icon",
+			ICON_PATH
+		);
+		let rgba = hex_icon_rgba(FALLBACK_ICON_SIZE);
+		let icon = winit::Icon::from_rgba(rgba, FALLBACK_ICON_SIZE, FALLBACK_ICON_SIZE)
+			.context("generated window icon was malformed")?;
+		graphics::window(ctx).set_window_icon(Some(icon));
+	}
+	Ok(())
+}
+
+/// A pointy-top hexagon filling a `size` square image, grass colored with a darker rim.
+pub fn hex_icon_rgba(size: u32) -> Vec<u8> {
+	let sqrt3 = 3.0f32.sqrt();
+	let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+	for y in 0..size {
+		for x in 0..size {
+			// Pixel center in -1..1 with the hexagon's points touching the top and bottom
+			let px = ((x as f32 + 0.5) / size as f32) * 2.0 - 1.0;
+			let py = ((y as f32 + 0.5) / size as f32) * 2.0 - 1.0;
+			let edge = (px.abs() * 2.0 / sqrt3).max(py.abs() + px.abs() / sqrt3);
+			let pixel = if edge > 1.0 {
+				[0, 0, 0, 0]
+			} else if edge > 0.85 {
+				[32, 72, 24, 255]
+			} else {
+				[86, 156, 64, 255]
+			};
+			rgba.extend_from_slice(&pixel);
+		}
+	}
+	rgba
+}
+
+pub fn window_title(map: &str, tick: u64) -> String {
+	format!("{} - {} - tick {}", GAME_TITLE, map, tick)
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+/// How the camera center is kept near the map on axes that don't wrap.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CameraPolicy {
+	/// The center can never leave the map plus margin.
+	HardClamp,
+	/// The center can be dragged past the margin but is pulled back once let go.
+	Elastic,
+	/// No limits at all.
+	Free,
+}
+
+fn serde_camera_policy() -> CameraPolicy {
+	CameraPolicy::HardClamp
+}
+
+fn serde_camera_elastic_strength() -> f32 {
+	8.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraConfig {
+	#[serde(default = "serde_camera_policy")]
+	pub policy: CameraPolicy,
+	/// How many tiles past the map edge the center may go, so the edge isn't stuck mid screen.
+	#[serde(default)]
+	pub margin: f32,
+	/// Rate the elastic policy pulls back at, higher is snappier.
+	#[serde(default = "serde_camera_elastic_strength")]
+	pub elastic_strength: f32,
+}
+
+impl Default for CameraConfig {
+	fn default() -> Self {
+		CameraConfig {
+			policy: serde_camera_policy(),
+			margin: 0.0,
+			elastic_strength: serde_camera_elastic_strength(),
+		}
+	}
+}
+
+impl CameraConfig {
+	/// Applies the policy to one axis of the camera center, `held` is whether the user is
+	/// currently dragging the camera so the elastic policy should let it stretch.
+	pub fn constrain(&self, value: f32, min: f32, max: f32, held: bool, delta: f32) -> f32 {
+		let min = min - self.margin;
+		let max = max + self.margin;
+		let target = value.max(min).min(max);
+		match self.policy {
+			CameraPolicy::HardClamp => target,
+			CameraPolicy::Free => value,
+			CameraPolicy::Elastic if held => value,
+			CameraPolicy::Elastic => {
+				let pull = 1.0 - (-self.elastic_strength * delta).exp();
+				let pulled = value + (target - value) * pull;
+				// Snap the last bit so it settles instead of creeping forever
+				if (target - pulled).abs() < 0.001 {
+					target
+				} else {
+					pulled
+				}
+			}
+		}
+	}
+}
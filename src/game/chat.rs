@@ -0,0 +1,107 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+pub struct ChatMessage {
+	pub sender: String,
+	pub text: String,
+	pub received: Instant,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ChatInput {
+	Message(String),
+	Command(String, Vec<String>),
+}
+
+pub struct ChatBox {
+	focused: bool,
+	input: String,
+	history: VecDeque<ChatMessage>,
+	max_history: usize,
+	pub fade_after: Duration,
+}
+
+impl ChatBox {
+	pub fn new(max_history: usize) -> ChatBox {
+		ChatBox {
+			focused: false,
+			input: String::new(),
+			history: VecDeque::with_capacity(max_history),
+			max_history,
+			fade_after: Duration::from_secs(10),
+		}
+	}
+
+	pub fn is_focused(&self) -> bool {
+		self.focused
+	}
+
+	pub fn focus(&mut self) {
+		self.focused = true;
+	}
+
+	pub fn unfocus(&mut self) {
+		self.focused = false;
+		self.input.clear();
+	}
+
+	pub fn input(&self) -> &str {
+		&self.input
+	}
+
+	/// Feeds a received character into the entry box, returns the parsed input once submitted.
+	pub fn push_char(&mut self, ch: char) -> Option<ChatInput> {
+		match ch {
+			'\r' | '\n' => {
+				let line = std::mem::replace(&mut self.input, String::new());
+				self.focused = false;
+				Self::parse_line(line.trim())
+			}
+			// Backspace on most platforms, delete on mac
+			'\u{8}' | '\u{7f}' => {
+				self.input.pop();
+				None
+			}
+			ch if ch.is_control() => None,
+			ch => {
+				self.input.push(ch);
+				None
+			}
+		}
+	}
+
+	fn parse_line(line: &str) -> Option<ChatInput> {
+		if line.is_empty() {
+			return None;
+		}
+		if line.starts_with('/') {
+			let mut args = line[1..].split_whitespace().map(|s| s.to_owned());
+			let name = args.next()?;
+			return Some(ChatInput::Command(name, args.collect()));
+		}
+		Some(ChatInput::Message(line.to_owned()))
+	}
+
+	pub fn post(&mut self, sender: impl ToString, text: impl ToString) {
+		if self.history.len() >= self.max_history {
+			self.history.pop_front();
+		}
+		self.history.push_back(ChatMessage {
+			sender: sender.to_string(),
+			text: text.to_string(),
+			received: Instant::now(),
+		});
+	}
+
+	/// Messages that should currently be visible, oldest first.
+	pub fn visible_history(&self, max_lines: usize) -> impl Iterator<Item = &ChatMessage> {
+		let now = Instant::now();
+		let focused = self.focused;
+		let fade_after = self.fade_after;
+		let skip = self.history.len().saturating_sub(max_lines);
+		self.history
+			.iter()
+			.skip(skip)
+			.filter(move |m| focused || now.duration_since(m.received) < fade_after)
+	}
+}
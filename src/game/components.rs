@@ -1,16 +1,17 @@
 use ggez::graphics::Rect;
-use over_simple_game_1::component_auto_loadable;
+use over_simple_game_1::register_components;
 use serde::{Deserialize, Serialize};
 
 pub struct IsSelected();
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DrawSprite {
 	pub sprite_name: String,
+	/// Area the sprite is stretched over relative to its tile's center, in tiles.
 	pub rect: Rect,
 }
-component_auto_loadable!(DrawSprite);
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Blorp {}
-component_auto_loadable!(Blorp);
+
+register_components!(DrawSprite, Blorp);
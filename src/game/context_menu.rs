@@ -0,0 +1,114 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+
+use over_simple_game_1::core::engine::MapCoord;
+
+use crate::game::atlas::{AtlasId, MultiAtlas};
+use crate::game::panel;
+use crate::game::text::{TextRole, TextStyles};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContextAction {
+	Select,
+	CenterView,
+	DropPin,
+}
+
+impl ContextAction {
+	pub fn label(self) -> &'static str {
+		match self {
+			ContextAction::Select => "Select",
+			ContextAction::CenterView => "Center view here",
+			ContextAction::DropPin => "Drop pin",
+		}
+	}
+}
+
+/// A small popup list of actions for a tile, positioned and drawn in screen pixels.
+pub struct ContextMenu {
+	pub anchor: na::Point2<f32>,
+	pub coord: MapCoord,
+	pub title: String,
+	pub items: Vec<ContextAction>,
+}
+
+impl ContextMenu {
+	const WIDTH: f32 = 180.0;
+	const LINE_HEIGHT: f32 = 20.0;
+
+	pub fn new(
+		anchor: na::Point2<f32>,
+		coord: MapCoord,
+		title: String,
+		items: Vec<ContextAction>,
+	) -> ContextMenu {
+		ContextMenu {
+			anchor,
+			coord,
+			title,
+			items,
+		}
+	}
+
+	/// The title takes the first line, the items follow one per line.
+	pub fn bounds(&self, ui_scale: f32) -> Rect {
+		Rect::new(
+			self.anchor.x,
+			self.anchor.y,
+			Self::WIDTH * ui_scale,
+			Self::LINE_HEIGHT * ui_scale * (self.items.len() + 1) as f32,
+		)
+	}
+
+	pub fn item_at(&self, position: na::Point2<f32>, ui_scale: f32) -> Option<ContextAction> {
+		if !self.bounds(ui_scale).contains([position.x, position.y]) {
+			return None;
+		}
+		let line = ((position.y - self.anchor.y) / (Self::LINE_HEIGHT * ui_scale)) as usize;
+		if line == 0 {
+			None
+		} else {
+			self.items.get(line - 1).copied()
+		}
+	}
+
+	/// Draws over a nine slice `skin` from the atlas if there is one, else a plain box.
+	pub fn draw<Unique: Copy>(
+		&self,
+		ctx: &mut Context,
+		text_styles: &mut TextStyles,
+		ui_scale: f32,
+		skin: Option<(&MultiAtlas<graphics::Image, Unique>, AtlasId<Unique>)>,
+	) -> GameResult {
+		let bounds = self.bounds(ui_scale);
+		match skin {
+			Some((atlas, id)) => {
+				panel::draw_panel(ctx, atlas, id, bounds, ui_scale, graphics::WHITE)?;
+			}
+			None => {
+				let mesh = graphics::MeshBuilder::new()
+					.rectangle(DrawMode::fill(), bounds, Color::new(0.1, 0.1, 0.1, 0.9))
+					.rectangle(DrawMode::stroke(1.0), bounds, graphics::WHITE)
+					.build(ctx)?;
+				mesh.draw(ctx, DrawParam::new())?;
+			}
+		}
+		let line_height = Self::LINE_HEIGHT * ui_scale;
+		let margin = 4.0 * ui_scale;
+		let lines = std::iter::once((self.title.as_str(), TextRole::Heading))
+			.chain(self.items.iter().map(|item| (item.label(), TextRole::Body)));
+		for (line, (text, role)) in lines.enumerate() {
+			let text = text_styles.layout(role, text, ui_scale);
+			graphics::draw(
+				ctx,
+				text,
+				DrawParam::new().dest(na::Point2::new(
+					bounds.x + margin,
+					bounds.y + line as f32 * line_height + margin * 0.5,
+				)),
+			)?;
+		}
+		Ok(())
+	}
+}
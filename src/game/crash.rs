@@ -0,0 +1,69 @@
+use std::backtrace::Backtrace;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Context as AnyContext;
+
+/// The report of the last panic, filled in by the hook and taken once the unwind is caught.
+static PANIC_REPORT: Mutex<Option<String>> = Mutex::new(None);
+
+/// Records the message, location and backtrace of any panic before running the default hook.
+pub fn install_panic_hook() {
+	let default_hook = std::panic::take_hook();
+	std::panic::set_hook(Box::new(move |info| {
+		let payload = info.payload();
+		let message = if let Some(message) = payload.downcast_ref::<&str>() {
+			(*message).to_owned()
+		} else if let Some(message) = payload.downcast_ref::<String>() {
+			message.clone()
+		} else {
+			"<non-string panic payload>".to_owned()
+		};
+		let location = info
+			.location()
+			.map(|location| location.to_string())
+			.unwrap_or_else(|| "<unknown location>".to_owned());
+		let report = panic_report(&message, &location, &Backtrace::force_capture());
+		if let Ok(mut last) = PANIC_REPORT.lock() {
+			*last = Some(report);
+		}
+		default_hook(info);
+	}));
+}
+
+/// A new folder for a crash report, relative to the user data directory.
+pub fn report_folder() -> PathBuf {
+	let stamp = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.map(|since| since.as_secs())
+		.unwrap_or(0);
+	PathBuf::from(format!("crash/{}", stamp))
+}
+
+/// Writes a crash report of only the panic into `user_data`, for when there is no game to add
+/// its state.
+pub fn write_panic_report(user_data: &Path, panic_report: &str) -> anyhow::Result<PathBuf> {
+	let path = user_data.join(report_folder());
+	std::fs::create_dir_all(&path)
+		.with_context(|| format!("failed creating `{}`", path.display()))?;
+	std::fs::write(path.join("panic.txt"), panic_report)
+		.with_context(|| format!("failed writing the panic report to `{}`", path.display()))?;
+	Ok(path)
+}
+
+/// Takes the report of the last panic, if the hook saw one.
+pub fn take_panic_report() -> Option<String> {
+	PANIC_REPORT.lock().ok().and_then(|mut last| last.take())
+}
+
+fn panic_report(message: &str, location: &str, backtrace: &Backtrace) -> String {
+	format!(
+		"{} v{}\nthread `{}` panicked at {}:\n{}\n\nbacktrace:\n{}\n",
+		env!("CARGO_PKG_NAME"),
+		env!("CARGO_PKG_VERSION"),
+		std::thread::current().name().unwrap_or("<unnamed>"),
+		location,
+		message,
+		backtrace,
+	)
+}
@@ -0,0 +1,135 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect};
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+
+fn serde_enabled() -> bool {
+	true
+}
+
+fn serde_intensity() -> f32 {
+	1.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EffectsConfig {
+	/// Turns off both the shake and the flashes.
+	#[serde(default = "serde_enabled")]
+	pub enabled: bool,
+	/// Multiplies how far the view shakes, `0.0` turns off just the shake.
+	#[serde(default = "serde_intensity")]
+	pub shake_intensity: f32,
+	/// Multiplies how strong the flashes are, `0.0` turns off just the flashes.
+	#[serde(default = "serde_intensity")]
+	pub flash_intensity: f32,
+}
+
+impl Default for EffectsConfig {
+	fn default() -> Self {
+		EffectsConfig {
+			enabled: serde_enabled(),
+			shake_intensity: serde_intensity(),
+			flash_intensity: serde_intensity(),
+		}
+	}
+}
+
+/// Something that happened that the screen should react to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreenEvent {
+	/// A hit or explosion at `at` in map units, `strength` from `0.0` to `1.0`. Shakes the view
+	/// less the further it is from the view center, not at all once off screen.
+	Impact { at: (f32, f32), strength: f32 },
+	/// The player tried to select or target something that can't be.
+	SelectionError,
+}
+
+/// Camera shake and full screen flashes, both fading out over a fraction of a second.
+pub struct ScreenEffects {
+	pub config: EffectsConfig,
+	/// From `0.0` to `1.0`, the shake offset grows with its square so small hits stay subtle.
+	trauma: f32,
+	/// Drives the shake pattern, only advances while shaking.
+	time: f32,
+	flash: Option<(Color, f32)>,
+}
+
+impl ScreenEffects {
+	/// Largest shake offset, as a fraction of the view height.
+	const MAX_SHAKE: f32 = 0.03;
+	/// Trauma lost per second.
+	const TRAUMA_DECAY: f32 = 1.5;
+	const FLASH_SECONDS: f32 = 0.25;
+
+	pub fn new(config: EffectsConfig) -> ScreenEffects {
+		ScreenEffects {
+			config,
+			trauma: 0.0,
+			time: 0.0,
+			flash: None,
+		}
+	}
+
+	/// `view_center` and `view_height` are in map units, for how near an impact is.
+	pub fn event(&mut self, event: ScreenEvent, view_center: (f32, f32), view_height: f32) {
+		if !self.config.enabled {
+			return;
+		}
+		match event {
+			ScreenEvent::Impact { at, strength } => {
+				let distance =
+					((at.0 - view_center.0).powi(2) + (at.1 - view_center.1).powi(2)).sqrt();
+				let nearness = (1.0 - distance / view_height.max(f32::EPSILON)).max(0.0);
+				self.trauma = (self.trauma + strength * nearness).min(1.0);
+			}
+			ScreenEvent::SelectionError => {
+				self.flash = Some((Color::new(1.0, 0.15, 0.1, 0.2), Self::FLASH_SECONDS));
+			}
+		}
+	}
+
+	pub fn update(&mut self, delta: f32) {
+		if self.trauma > 0.0 {
+			self.time += delta;
+			self.trauma = (self.trauma - delta * Self::TRAUMA_DECAY).max(0.0);
+		}
+		if let Some((_color, remaining)) = &mut self.flash {
+			*remaining -= delta;
+			if *remaining <= 0.0 {
+				self.flash = None;
+			}
+		}
+	}
+
+	/// How far to move the view this frame, in the same units as `view_height`.
+	pub fn shake_offset(&self, view_height: f32) -> (f32, f32) {
+		if !self.config.enabled || self.trauma <= 0.0 {
+			return (0.0, 0.0);
+		}
+		let amount = self.trauma * self.trauma * Self::MAX_SHAKE * self.config.shake_intensity;
+		// Sines at unrelated frequencies wander without an obvious repeating pattern
+		let t = self.time;
+		let x = (t * 47.0).sin() * 0.6 + (t * 83.0).sin() * 0.4;
+		let y = (t * 53.0).sin() * 0.6 + (t * 71.0).cos() * 0.4;
+		(x * amount * view_height, y * amount * view_height)
+	}
+
+	/// Draws the fading flash over the whole screen, in screen pixels.
+	pub fn draw_flash(&self, ctx: &mut Context, screen_size: (f32, f32)) -> GameResult {
+		let (color, remaining) = match self.flash {
+			Some(flash) if self.config.enabled => flash,
+			_ => return Ok(()),
+		};
+		let fade = remaining / Self::FLASH_SECONDS;
+		let alpha = (color.a * fade * self.config.flash_intensity).min(1.0);
+		if alpha <= 0.0 {
+			return Ok(());
+		}
+		let mesh = graphics::Mesh::new_rectangle(
+			ctx,
+			DrawMode::fill(),
+			Rect::new(0.0, 0.0, screen_size.0, screen_size.1),
+			Color::new(color.r, color.g, color.b, alpha),
+		)?;
+		mesh.draw(ctx, DrawParam::new())
+	}
+}
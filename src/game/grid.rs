@@ -0,0 +1,105 @@
+use ggez::graphics::{self, Color, DrawParam, Drawable};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+
+use over_simple_game_1::prelude::{Coord, HexOrientation};
+
+use crate::game::accessibility::AccessibilityConfig;
+
+fn serde_grid_color() -> Color {
+	Color::new(0.0, 0.0, 0.0, 0.35)
+}
+
+fn serde_grid_line_width() -> f32 {
+	0.03
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridStyle {
+	#[serde(default = "serde_grid_color")]
+	pub color: Color,
+	/// Width of the lines in tiles, not pixels, so they scale with the zoom.
+	#[serde(default = "serde_grid_line_width")]
+	pub line_width: f32,
+	#[serde(default)]
+	pub enabled: bool,
+}
+
+impl Default for GridStyle {
+	fn default() -> Self {
+		GridStyle {
+			color: serde_grid_color(),
+			line_width: serde_grid_line_width(),
+			enabled: false,
+		}
+	}
+}
+
+pub struct GridOverlay {
+	pub style: GridStyle,
+	mesh: Option<graphics::Mesh>,
+	/// Center and radius the cached mesh was built for.
+	built_for: Option<(Coord, u8)>,
+}
+
+impl GridOverlay {
+	pub fn new(style: GridStyle) -> GridOverlay {
+		GridOverlay {
+			style,
+			mesh: None,
+			built_for: None,
+		}
+	}
+
+	pub fn toggle(&mut self) {
+		self.style.enabled = !self.style.enabled;
+	}
+
+	pub fn invalidate(&mut self) {
+		self.mesh = None;
+		self.built_for = None;
+	}
+
+	/// Draws the outline of every tile in `positions`, rebuilding the mesh only when the view
+	/// has moved to a different center tile or radius.
+	pub fn draw(
+		&mut self,
+		ctx: &mut Context,
+		accessibility: &AccessibilityConfig,
+		hex: HexOrientation,
+		center: Coord,
+		radius: u8,
+		positions: impl FnOnce() -> Vec<(f32, f32)>,
+	) -> GameResult {
+		if !self.style.enabled {
+			return Ok(());
+		}
+		if self.built_for != Some((center, radius)) {
+			self.built_for = Some((center, radius));
+			self.mesh = None;
+			let mut builder = graphics::MeshBuilder::new();
+			let width = accessibility.line_width(self.style.line_width);
+			let color = accessibility.overlay_color(self.style.color);
+			let mut empty = true;
+			for (x, y) in positions() {
+				// Only the three right hand edges, or top edges for flat-top, the others belong to
+				// the neighbors so each edge is drawn once and the alpha doesn't double up where
+				// they overlap.
+				let edge: Vec<na::Point2<f32>> = hex.corners()[0..4]
+					.iter()
+					.map(|&(cx, cy)| na::Point2::new(x + cx, y + cy))
+					.collect();
+				builder.line(&edge, width, color)?;
+				empty = false;
+			}
+			if !empty {
+				self.mesh = Some(builder.build(ctx)?);
+			}
+		}
+		if let Some(mesh) = &self.mesh {
+			mesh.draw(ctx, DrawParam::new())?;
+		}
+		Ok(())
+	}
+}
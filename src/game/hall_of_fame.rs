@@ -0,0 +1,110 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+
+use over_simple_game_1::core::engine::statistics::{GameOutcome, PlayerStatistics};
+
+use crate::game::atlas::{AtlasId, MultiAtlas};
+use crate::game::panel;
+use crate::game::text::{TextRole, TextStyles};
+
+/// Where the statistics are kept in the user data directory.
+pub const STATISTICS_PATH: &str = "statistics.ron";
+
+const WIDTH: f32 = 480.0;
+const LINE_HEIGHT: f32 = 20.0;
+
+fn outcome_line(place: usize, outcome: &GameOutcome) -> String {
+	let result = match &outcome.victory {
+		Some(victory) => format!("{} victory", victory),
+		None => "defeat".to_owned(),
+	};
+	format!(
+		"{}. {} - {} as {} in {} turns",
+		place + 1,
+		outcome.score,
+		result,
+		outcome.played_as,
+		outcome.turns
+	)
+}
+
+/// The games played and won and the best scores, centered on the screen.
+pub fn draw_hall_of_fame<Unique: Copy>(
+	ctx: &mut Context,
+	text_styles: &mut TextStyles,
+	statistics: &PlayerStatistics,
+	screen_size: (f32, f32),
+	ui_scale: f32,
+	skin: Option<(&MultiAtlas<graphics::Image, Unique>, AtlasId<Unique>)>,
+) -> GameResult {
+	let mut lines: Vec<(TextRole, String)> = vec![
+		(TextRole::Heading, "Hall of Fame".to_owned()),
+		(
+			TextRole::Body,
+			format!(
+				"{} games played, {} won",
+				statistics.games_played,
+				statistics.games_won()
+			),
+		),
+	];
+	for (victory, count) in &statistics.victories {
+		lines.push((
+			TextRole::Body,
+			format!("  {} victories: {}", victory, count),
+		));
+	}
+	lines.push((TextRole::Heading, "Best games".to_owned()));
+	if statistics.best_games.is_empty() {
+		lines.push((TextRole::Body, "no games finished yet".to_owned()));
+	}
+	for (place, outcome) in statistics.best_games.iter().enumerate() {
+		lines.push((TextRole::Body, outcome_line(place, outcome)));
+	}
+
+	let line_height = LINE_HEIGHT * ui_scale;
+	let margin = 10.0 * ui_scale;
+	let width = (WIDTH * ui_scale).min(screen_size.0);
+	// The lines, the headings' extra space and the hint
+	let height = line_height * (lines.len() as f32 + 2.0) + margin * 2.0;
+	let bounds = Rect::new(
+		(screen_size.0 - width) * 0.5,
+		((screen_size.1 - height) * 0.5).max(0.0),
+		width,
+		height,
+	);
+	match skin {
+		Some((atlas, id)) => {
+			panel::draw_panel(ctx, atlas, id, bounds, ui_scale, graphics::WHITE)?;
+		}
+		None => {
+			let mesh = graphics::MeshBuilder::new()
+				.rectangle(DrawMode::fill(), bounds, Color::new(0.1, 0.1, 0.1, 0.95))
+				.rectangle(DrawMode::stroke(1.0), bounds, graphics::WHITE)
+				.build(ctx)?;
+			mesh.draw(ctx, DrawParam::new())?;
+		}
+	}
+
+	let dim = Color::new(0.6, 0.6, 0.6, 1.0);
+	let x = bounds.x + margin;
+	let mut y = bounds.y + margin;
+	for (role, line) in &lines {
+		if *role == TextRole::Heading && y > bounds.y + margin {
+			y += line_height * 0.5;
+		}
+		let text = text_styles.layout(*role, line, ui_scale);
+		graphics::draw(ctx, text, DrawParam::new().dest(na::Point2::new(x, y)))?;
+		y += line_height;
+	}
+
+	y += line_height * 0.5;
+	let text = text_styles.layout(TextRole::Tooltip, "Esc: close", ui_scale * 0.7);
+	graphics::draw(
+		ctx,
+		text,
+		DrawParam::new().dest(na::Point2::new(x, y)).color(dim),
+	)?;
+	Ok(())
+}
@@ -0,0 +1,98 @@
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+
+use over_simple_game_1::core::engine::io::{EngineIO, WriteMode};
+use over_simple_game_1::core::map::tile::{TileIdx, TileType};
+
+use crate::game::args::Args;
+use crate::game::world;
+
+/// Reads from the resources and then the user data directory and writes to the user data
+/// directory, the same as the game does through ggez.
+#[derive(Debug)]
+struct HeadlessIO {
+	resources: PathBuf,
+	user_data: PathBuf,
+}
+
+impl EngineIO for HeadlessIO {
+	type ReadError = std::io::Error;
+	type Read = std::fs::File;
+
+	fn read(&mut self, file_path: &Path) -> Result<Self::Read, Self::ReadError> {
+		let file_path = file_path.strip_prefix("/").unwrap_or(file_path);
+		std::fs::File::open(self.resources.join(file_path))
+			.or_else(|_e| std::fs::File::open(self.user_data.join(file_path)))
+	}
+
+	fn exists(&mut self, file_path: &Path) -> bool {
+		let file_path = file_path.strip_prefix("/").unwrap_or(file_path);
+		self.resources.join(file_path).exists() || self.user_data.join(file_path).exists()
+	}
+
+	type WriteError = std::io::Error;
+	type Write = std::fs::File;
+
+	fn write(
+		&mut self,
+		file_path: &Path,
+		mode: WriteMode,
+	) -> Result<Self::Write, Self::WriteError> {
+		let path = self
+			.user_data
+			.join(file_path.strip_prefix("/").unwrap_or(file_path));
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		let mut options = std::fs::OpenOptions::new();
+		match mode {
+			WriteMode::Truncate => options.write(true).truncate(true),
+			WriteMode::Append => options.append(true),
+		};
+		options.create(true).open(path)
+	}
+
+	type TileInterface = ();
+
+	fn blank_tile_interface() -> Self::TileInterface {}
+
+	type TileAddedError = Infallible;
+
+	fn tile_added(
+		&mut self,
+		_index: TileIdx,
+		_tile_type: &mut TileType<Self>,
+	) -> Result<(), Self::TileAddedError> {
+		Ok(())
+	}
+}
+
+/// Sets up the engine and maps as the game would without opening a window, then prints the maps,
+/// for checking map generation, scenarios and replays from scripts.
+///
+/// There is no ECS, so the objects of Tiled maps aren't spawned.
+pub fn run_headless(args: &Args, user_data: PathBuf) -> anyhow::Result<()> {
+	let mut io = HeadlessIO {
+		resources: PathBuf::from("./resources"),
+		user_data,
+	};
+	let mut engine = world::engine_builder(args).build();
+	if let Some(path) = &args.journal {
+		engine.enable_journal(&mut io, path)?;
+	}
+	engine.setup(&mut io)?;
+	match &args.scenario {
+		Some(path) => {
+			world::generate_scenario_map(&mut engine, &mut io, Path::new(path))?;
+		}
+		None => world::generate_start_maps(&mut engine, &mut io, args)?,
+	}
+	if let Some(path) = &args.import_tiled {
+		world::generate_tiled_map(&mut engine, &mut io, Path::new(path))?;
+	}
+	if let Some(path) = &args.replay {
+		world::replay_journal(&mut engine, &mut io, Path::new(path))?;
+	}
+	print!("{}", world::maps_text(&engine));
+	Ok(())
+}
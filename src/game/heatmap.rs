@@ -0,0 +1,249 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect, Vertex};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+
+use over_simple_game_1::core::engine::MapIdx;
+use over_simple_game_1::prelude::{Coord, HexOrientation};
+
+use crate::game::text::{TextRole, TextStyles};
+
+/// Lowest to highest, blue through green and yellow to red.
+const RAMP: [[f32; 3]; 4] = [
+	[0.1, 0.2, 0.9],
+	[0.1, 0.8, 0.3],
+	[0.95, 0.9, 0.1],
+	[0.9, 0.15, 0.1],
+];
+
+/// Enough to see the tiles through.
+const HEATMAP_ALPHA: f32 = 0.55;
+
+const LEGEND_WIDTH: f32 = 160.0;
+const LEGEND_BAR_HEIGHT: f32 = 12.0;
+
+/// The triangles of a hex fanned from its first corner, for `hex_vertices`.
+pub const HEX_INDICES: [u32; 12] = [0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 5];
+
+/// The corners of the `hex` centered on `(px, py)` in `color`, drawn untextured with
+/// `HEX_INDICES`.
+pub fn hex_vertices(hex: HexOrientation, (px, py): (f32, f32), color: [f32; 4]) -> [Vertex; 6] {
+	let mut vertices = [Vertex {
+		pos: [px, py],
+		uv: [0.0, 0.0],
+		color,
+	}; 6];
+	for (vertex, (cx, cy)) in vertices.iter_mut().zip(&hex.corners()) {
+		vertex.pos = [px + cx, py + cy];
+	}
+	vertices
+}
+
+/// The ramp color for `t` from 0 to 1.
+fn heat_color(t: f32, alpha: f32) -> [f32; 4] {
+	let scaled = t.max(0.0).min(1.0) * (RAMP.len() - 1) as f32;
+	let idx = (scaled.floor() as usize).min(RAMP.len() - 2);
+	let blend = scaled - idx as f32;
+	let (from, to) = (RAMP[idx], RAMP[idx + 1]);
+	[
+		from[0] + (to[0] - from[0]) * blend,
+		from[1] + (to[1] - from[1]) * blend,
+		from[2] + (to[2] - from[2]) * blend,
+		alpha,
+	]
+}
+
+/// A scalar field's value on every tile of one map, in the linear order of its tiles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeatmapValues {
+	pub map: MapIdx,
+	pub values: Vec<f32>,
+}
+
+/// Colors the tiles by the values of a scalar field, such as an influence, for seeing what the
+/// simulation is doing.
+pub struct HeatmapOverlay {
+	/// The field shown, `None` while the overlay is off.
+	field: Option<String>,
+	/// The fields the visible map has to pick from, kept up to date by the game.
+	pub available: Vec<String>,
+	values: Option<HeatmapValues>,
+	/// The lowest and highest values, the ends of the ramp.
+	range: (f32, f32),
+	mesh: Option<graphics::Mesh>,
+	/// Center and radius the cached mesh was built for.
+	built_for: Option<(Coord, u8)>,
+}
+
+impl HeatmapOverlay {
+	pub fn new() -> HeatmapOverlay {
+		HeatmapOverlay {
+			field: None,
+			available: vec![],
+			values: None,
+			range: (0.0, 0.0),
+			mesh: None,
+			built_for: None,
+		}
+	}
+
+	pub fn field(&self) -> Option<&str> {
+		self.field.as_deref()
+	}
+
+	/// Shows the field `name`, or turns the overlay off with `None`.
+	pub fn select(&mut self, field: Option<String>) {
+		self.field = field;
+		self.values = None;
+		self.invalidate();
+	}
+
+	/// Replaces the values shown, the mesh is only rebuilt when they changed.
+	pub fn set_values(&mut self, values: HeatmapValues) {
+		if self.values.as_ref() == Some(&values) {
+			return;
+		}
+		self.range = values
+			.values
+			.iter()
+			.fold(None, |range: Option<(f32, f32)>, &value| match range {
+				Some((low, high)) => Some((low.min(value), high.max(value))),
+				None => Some((value, value)),
+			})
+			.unwrap_or((0.0, 0.0));
+		self.values = Some(values);
+		self.invalidate();
+	}
+
+	pub fn invalidate(&mut self) {
+		self.mesh = None;
+		self.built_for = None;
+	}
+
+	fn ramp_position(&self, value: f32) -> f32 {
+		let (low, high) = self.range;
+		if high > low {
+			(value - low) / (high - low)
+		} else {
+			0.0
+		}
+	}
+
+	/// Colors every tile in `tiles`, given as the index of the tile on `map` and where it is
+	/// drawn, rebuilding the mesh only when the view has moved to a different center tile or
+	/// radius.
+	pub fn draw(
+		&mut self,
+		ctx: &mut Context,
+		hex: HexOrientation,
+		map: MapIdx,
+		center: Coord,
+		radius: u8,
+		tiles: impl FnOnce() -> Vec<(usize, (f32, f32))>,
+	) -> GameResult {
+		let values = match &self.values {
+			Some(values) if self.field.is_some() && values.map == map => values,
+			_ => return Ok(()),
+		};
+		if self.built_for != Some((center, radius)) {
+			self.built_for = Some((center, radius));
+			self.mesh = None;
+			let mut builder = graphics::MeshBuilder::new();
+			let mut empty = true;
+			for (idx, position) in tiles() {
+				if let Some(&value) = values.values.get(idx) {
+					let color = heat_color(self.ramp_position(value), HEATMAP_ALPHA);
+					builder.raw(&hex_vertices(hex, position, color), &HEX_INDICES, None);
+					empty = false;
+				}
+			}
+			if !empty {
+				self.mesh = Some(builder.build(ctx)?);
+			}
+		}
+		if let Some(mesh) = &self.mesh {
+			mesh.draw(ctx, DrawParam::new())?;
+		}
+		Ok(())
+	}
+
+	/// The field's name over the ramp with the lowest and highest values at its ends, in the
+	/// bottom left corner of the screen.
+	pub fn draw_legend(
+		&self,
+		ctx: &mut Context,
+		text_styles: &mut TextStyles,
+		screen_size: (f32, f32),
+		ui_scale: f32,
+	) -> GameResult {
+		let field = match (&self.field, &self.values) {
+			(Some(field), Some(_values)) => field,
+			_ => return Ok(()),
+		};
+		let margin = 8.0 * ui_scale;
+		let width = LEGEND_WIDTH * ui_scale;
+		let bar_height = LEGEND_BAR_HEIGHT * ui_scale;
+		let line_height = 18.0 * ui_scale;
+		let bar = Rect::new(
+			margin * 2.0,
+			screen_size.1 - margin * 2.0 - line_height - bar_height,
+			width,
+			bar_height,
+		);
+		let backing = Rect::new(
+			margin,
+			bar.y - line_height - margin,
+			width + margin * 2.0,
+			bar_height + line_height * 2.0 + margin * 2.0,
+		);
+		let mut builder = graphics::MeshBuilder::new();
+		builder.rectangle(DrawMode::fill(), backing, Color::new(0.1, 0.1, 0.1, 0.8));
+		// A quad per step of the ramp, the colors blend between its corners
+		let steps = RAMP.len() - 1;
+		for step in 0..steps {
+			let left = bar.x + bar.w * step as f32 / steps as f32;
+			let right = bar.x + bar.w * (step + 1) as f32 / steps as f32;
+			let from = heat_color(step as f32 / steps as f32, 1.0);
+			let to = heat_color((step + 1) as f32 / steps as f32, 1.0);
+			let corner = |x: f32, y: f32, color: [f32; 4]| Vertex {
+				pos: [x, y],
+				uv: [0.0, 0.0],
+				color,
+			};
+			builder.raw(
+				&[
+					corner(left, bar.top(), from),
+					corner(right, bar.top(), to),
+					corner(right, bar.bottom(), to),
+					corner(left, bar.bottom(), from),
+				],
+				&[0, 1, 2, 0, 2, 3],
+				None,
+			);
+		}
+		builder.build(ctx)?.draw(ctx, DrawParam::new())?;
+
+		let text = text_styles.layout(TextRole::Tooltip, field, ui_scale);
+		graphics::draw(
+			ctx,
+			text,
+			DrawParam::new().dest(na::Point2::new(bar.x, backing.y + margin)),
+		)?;
+		let (low, high) = self.range;
+		let below = bar.bottom() + margin * 0.5;
+		let text = text_styles.layout(TextRole::Tooltip, &format!("{:.2}", low), ui_scale);
+		graphics::draw(
+			ctx,
+			text,
+			DrawParam::new().dest(na::Point2::new(bar.x, below)),
+		)?;
+		let high = format!("{:.2}", high);
+		let text = text_styles.layout(TextRole::Tooltip, &high, ui_scale);
+		let text_width = text.width(ctx) as f32;
+		graphics::draw(
+			ctx,
+			text,
+			DrawParam::new().dest(na::Point2::new(bar.right() - text_width, below)),
+		)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,378 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+use shipyard::EntityId;
+use winit::VirtualKeyCode;
+
+use over_simple_game_1::core::reflect::FieldType;
+
+use crate::game::atlas::{AtlasId, MultiAtlas};
+use crate::game::panel;
+use crate::game::text::{TextRole, TextStyles};
+
+#[derive(Clone, Debug, PartialEq)]
+enum PathStep {
+	Field(String),
+	Index(usize),
+	Some,
+}
+
+/// One value in a component, nested values get a row each.
+struct InspectorRow {
+	component: &'static str,
+	path: Vec<PathStep>,
+	label: String,
+	value: ron::Value,
+	field_type: FieldType,
+}
+
+impl InspectorRow {
+	fn is_editable(&self) -> bool {
+		match (&self.field_type, &self.value) {
+			(FieldType::Integer, _) | (FieldType::Float, _) => true,
+			(FieldType::String, ron::Value::String(_)) => true,
+			(FieldType::Bool, ron::Value::Bool(_)) => true,
+			_ => false,
+		}
+	}
+
+	/// The value as typed in, strings without their quotes.
+	fn edit_text(&self) -> String {
+		match &self.value {
+			ron::Value::String(text) => text.clone(),
+			value => value_text(value),
+		}
+	}
+}
+
+fn value_text(value: &ron::Value) -> String {
+	match value {
+		ron::Value::Number(ron::Number::Integer(value)) => value.to_string(),
+		ron::Value::Number(ron::Number::Float(value)) => format!("{}", value.get()),
+		ron::Value::String(text) => format!("{:?}", text),
+		ron::Value::Bool(value) => value.to_string(),
+		value => ron::ser::to_string(value).unwrap_or_else(|_| "?".to_owned()),
+	}
+}
+
+/// Replaces what `path` leads to in `value` with `new`, false if there is nothing there.
+fn set_at(value: &mut ron::Value, path: &[PathStep], new: ron::Value) -> bool {
+	let (step, rest) = match path.split_first() {
+		Some(split) => split,
+		None => {
+			*value = new;
+			return true;
+		}
+	};
+	let inner = match (step, value) {
+		(PathStep::Field(name), ron::Value::Map(entries)) => entries
+			.iter_mut()
+			.find(|(key, _value)| **key == ron::Value::String(name.clone()))
+			.map(|(_key, value)| value),
+		(PathStep::Index(idx), ron::Value::Seq(values)) => values.get_mut(*idx),
+		(PathStep::Some, ron::Value::Option(Some(inner))) => Some(&mut **inner),
+		_ => None,
+	};
+	inner.map_or(false, |inner| set_at(inner, rest, new))
+}
+
+/// What the game should do after a key press in the inspector.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InspectorInput {
+	/// Replace the entity's `component` with one read from `value`.
+	Set {
+		component: &'static str,
+		value: ron::Value,
+	},
+	/// Typed text that doesn't fit the value being edited.
+	Rejected(String),
+	Close,
+}
+
+/// Keyboard driven debug window listing one entity's components and their values, numbers,
+/// strings and bools can be edited live.
+pub struct Inspector {
+	pub entity: EntityId,
+	row: usize,
+	/// The text typed for the selected row, `None` unless it is being edited.
+	editing: Option<String>,
+	/// Each component's whole value, edits are made to a copy of it.
+	components: Vec<(&'static str, ron::Value)>,
+	rows: Vec<InspectorRow>,
+}
+
+impl Inspector {
+	const WIDTH: f32 = 420.0;
+	const LINE_HEIGHT: f32 = 20.0;
+	const VISIBLE_ROWS: usize = 24;
+
+	pub fn new(entity: EntityId) -> Inspector {
+		Inspector {
+			entity,
+			row: 0,
+			editing: None,
+			components: vec![],
+			rows: vec![],
+		}
+	}
+
+	/// Takes the entity's current component values, each with its traced type if it traced.
+	pub fn refresh(&mut self, components: Vec<(&'static str, ron::Value, Option<FieldType>)>) {
+		self.rows.clear();
+		self.components.clear();
+		for (component, value, field_type) in components {
+			let field_type = field_type.unwrap_or(FieldType::Any);
+			Self::add_rows(
+				&mut self.rows,
+				component,
+				&mut vec![],
+				component.to_owned(),
+				&value,
+				&field_type,
+			);
+			self.components.push((component, value));
+		}
+		self.row = self.row.min(self.rows.len().saturating_sub(1));
+	}
+
+	fn add_rows(
+		rows: &mut Vec<InspectorRow>,
+		component: &'static str,
+		path: &mut Vec<PathStep>,
+		label: String,
+		value: &ron::Value,
+		field_type: &FieldType,
+	) {
+		let mut nested = |step: PathStep, label: String, value, field_type| {
+			path.push(step);
+			Self::add_rows(rows, component, path, label, value, field_type);
+			path.pop();
+		};
+		match (field_type, value) {
+			(FieldType::Struct { fields, .. }, ron::Value::Map(entries)) if !fields.is_empty() => {
+				for (field, field_type) in fields {
+					let key = ron::Value::String((*field).to_owned());
+					if let Some((_key, value)) = entries.iter().find(|(k, _value)| **k == key) {
+						let step = PathStep::Field((*field).to_owned());
+						nested(step, format!("{}.{}", label, field), value, field_type);
+					}
+				}
+			}
+			(FieldType::Seq(element), ron::Value::Seq(values)) if !values.is_empty() => {
+				for (idx, value) in values.iter().enumerate() {
+					let label = format!("{}.{}", label, idx);
+					nested(PathStep::Index(idx), label, value, element);
+				}
+			}
+			(FieldType::Tuple(elements), ron::Value::Seq(values))
+				if elements.len() == values.len() =>
+			{
+				for (idx, (element, value)) in elements.iter().zip(values).enumerate() {
+					let label = format!("{}.{}", label, idx);
+					nested(PathStep::Index(idx), label, value, element);
+				}
+			}
+			(FieldType::Option(inner), ron::Value::Option(Some(value))) => {
+				nested(PathStep::Some, label, value, inner)
+			}
+			_ => rows.push(InspectorRow {
+				component,
+				path: path.clone(),
+				label,
+				value: value.clone(),
+				field_type: field_type.clone(),
+			}),
+		}
+	}
+
+	/// Typed text goes to the value being edited, Enter and Escape come in as keys instead.
+	pub fn push_char(&mut self, ch: char) {
+		if let Some(text) = &mut self.editing {
+			match ch {
+				// Backspace on most platforms, delete on mac
+				'\u{8}' | '\u{7f}' => {
+					text.pop();
+				}
+				ch if ch.is_control() => (),
+				ch => text.push(ch),
+			}
+		}
+	}
+
+	pub fn key(&mut self, key: VirtualKeyCode, shift: bool) -> Option<InspectorInput> {
+		if let Some(text) = self.editing.take() {
+			return match key {
+				VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => self.commit(&text),
+				VirtualKeyCode::Escape => None,
+				_ => {
+					self.editing = Some(text);
+					None
+				}
+			};
+		}
+		let rows = self.rows.len().max(1);
+		match key {
+			VirtualKeyCode::Escape => return Some(InspectorInput::Close),
+			VirtualKeyCode::Up => self.row = (self.row + rows - 1) % rows,
+			VirtualKeyCode::Down => self.row = (self.row + 1) % rows,
+			VirtualKeyCode::Left => return self.adjust(if shift { -10 } else { -1 }),
+			VirtualKeyCode::Right => return self.adjust(if shift { 10 } else { 1 }),
+			VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+				let row = self.rows.get(self.row)?;
+				match row.field_type {
+					FieldType::Bool => return self.adjust(1),
+					_ if row.is_editable() => self.editing = Some(row.edit_text()),
+					_ => (),
+				}
+			}
+			_ => (),
+		}
+		None
+	}
+
+	/// Steps the selected number by `step`, tenths of it for floats, or flips a bool.
+	fn adjust(&self, step: i64) -> Option<InspectorInput> {
+		let row = self.rows.get(self.row)?;
+		let value = match (&row.field_type, &row.value) {
+			(FieldType::Bool, ron::Value::Bool(value)) => ron::Value::Bool(!value),
+			(FieldType::Integer, ron::Value::Number(number)) => {
+				let value = number.as_i64().unwrap_or(0).saturating_add(step);
+				ron::Value::Number(ron::Number::Integer(value))
+			}
+			(FieldType::Float, ron::Value::Number(number)) => {
+				// Rounded to the step so repeated steps don't drift
+				let value = ((number.into_f64() * 10.0).round() + step as f64) / 10.0;
+				ron::Value::Number(ron::Number::new(value))
+			}
+			_ => return None,
+		};
+		self.set(row, value)
+	}
+
+	fn commit(&self, text: &str) -> Option<InspectorInput> {
+		let row = self.rows.get(self.row)?;
+		let value = match row.field_type {
+			FieldType::Integer => match text.trim().parse::<i64>() {
+				Ok(value) => ron::Value::Number(ron::Number::Integer(value)),
+				Err(_) => {
+					return Some(InspectorInput::Rejected(format!(
+						"`{}` isn't a whole number",
+						text
+					)))
+				}
+			},
+			FieldType::Float => match text.trim().parse::<f64>() {
+				Ok(value) if value.is_finite() => ron::Value::Number(ron::Number::new(value)),
+				_ => {
+					return Some(InspectorInput::Rejected(format!(
+						"`{}` isn't a number",
+						text
+					)))
+				}
+			},
+			_ => ron::Value::String(text.to_owned()),
+		};
+		self.set(row, value)
+	}
+
+	fn set(&self, row: &InspectorRow, new: ron::Value) -> Option<InspectorInput> {
+		let (component, value) = self
+			.components
+			.iter()
+			.find(|(component, _value)| *component == row.component)?;
+		let mut value = value.clone();
+		if !set_at(&mut value, &row.path, new) {
+			return None;
+		}
+		Some(InspectorInput::Set { component, value })
+	}
+
+	/// Draws along the right edge of the screen, scrolled to keep the selected row in view.
+	pub fn draw<Unique: Copy>(
+		&self,
+		ctx: &mut Context,
+		text_styles: &mut TextStyles,
+		screen_size: (f32, f32),
+		ui_scale: f32,
+		skin: Option<(&MultiAtlas<graphics::Image, Unique>, AtlasId<Unique>)>,
+	) -> GameResult {
+		let line_height = Self::LINE_HEIGHT * ui_scale;
+		let margin = 10.0 * ui_scale;
+		let width = (Self::WIDTH * ui_scale).min(screen_size.0);
+		let shown = self.rows.len().min(Self::VISIBLE_ROWS).max(1);
+		// The title, a gap, the rows, a gap and the hint
+		let height = line_height * (shown + 3) as f32 + margin * 2.0;
+		let bounds = Rect::new(screen_size.0 - width - margin, margin, width, height);
+		match skin {
+			Some((atlas, id)) => {
+				panel::draw_panel(ctx, atlas, id, bounds, ui_scale, graphics::WHITE)?;
+			}
+			None => {
+				let mesh = graphics::MeshBuilder::new()
+					.rectangle(DrawMode::fill(), bounds, Color::new(0.1, 0.1, 0.1, 0.95))
+					.rectangle(DrawMode::stroke(1.0), bounds, graphics::WHITE)
+					.build(ctx)?;
+				mesh.draw(ctx, DrawParam::new())?;
+			}
+		}
+
+		let dim = Color::new(0.6, 0.6, 0.6, 1.0);
+		let x = bounds.x + margin;
+		let mut y = bounds.y + margin;
+		let title = format!("Entity {:?}", self.entity);
+		let text = text_styles.layout(TextRole::Heading, &title, ui_scale);
+		graphics::draw(ctx, text, DrawParam::new().dest(na::Point2::new(x, y)))?;
+		y += line_height * 1.5;
+
+		if self.rows.is_empty() {
+			let text = text_styles.layout(TextRole::Body, "no components", ui_scale);
+			graphics::draw(
+				ctx,
+				text,
+				DrawParam::new().dest(na::Point2::new(x, y)).color(dim),
+			)?;
+			y += line_height;
+		}
+		let first = (self.row + 1).saturating_sub(shown);
+		for (row, inspected) in self.rows.iter().enumerate().skip(first).take(shown) {
+			let selected = row == self.row;
+			let color = if selected { graphics::WHITE } else { dim };
+			let label = format!("{} {}", if selected { ">" } else { " " }, inspected.label);
+			let text = text_styles.layout(TextRole::Body, &label, ui_scale);
+			graphics::draw(
+				ctx,
+				text,
+				DrawParam::new().dest(na::Point2::new(x, y)).color(color),
+			)?;
+			let value = match &self.editing {
+				Some(text) if selected => format!("{}_", text),
+				_ => value_text(&inspected.value),
+			};
+			let color = if inspected.is_editable() {
+				color
+			} else {
+				Color { a: 0.6, ..color }
+			};
+			let text = text_styles.layout(TextRole::Body, &value, ui_scale);
+			let value_width = text.width(ctx) as f32;
+			graphics::draw(
+				ctx,
+				text,
+				DrawParam::new()
+					.dest(na::Point2::new(bounds.right() - margin - value_width, y))
+					.color(color),
+			)?;
+			y += line_height;
+		}
+
+		y += line_height * 0.5;
+		let hint = "arrows: select and step (shift for more), Enter: edit, Esc: close";
+		let text = text_styles.layout(TextRole::Tooltip, hint, ui_scale * 0.7);
+		graphics::draw(
+			ctx,
+			text,
+			DrawParam::new().dest(na::Point2::new(x, y)).color(dim),
+		)?;
+		Ok(())
+	}
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ggez::nalgebra as na;
+use serde::{Deserialize, Serialize};
+use winit::{MouseButton, MouseCursor};
+
+use crate::game::keybindings::KeyBindings;
+
+fn serde_click_leeway() -> f32 {
+	4.0
+}
+
+fn serde_double_click_ms() -> u64 {
+	400
+}
+
+fn serde_long_press_ms() -> u64 {
+	600
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InputConfig {
+	/// Logical pixels a press may move and still count as a click, before UI scaling.
+	#[serde(default = "serde_click_leeway")]
+	pub click_leeway: f32,
+	#[serde(default = "serde_double_click_ms")]
+	pub double_click_ms: u64,
+	#[serde(default = "serde_long_press_ms")]
+	pub long_press_ms: u64,
+	#[serde(default)]
+	pub keys: KeyBindings,
+}
+
+impl Default for InputConfig {
+	fn default() -> Self {
+		InputConfig {
+			click_leeway: serde_click_leeway(),
+			double_click_ms: serde_double_click_ms(),
+			long_press_ms: serde_long_press_ms(),
+			keys: KeyBindings::default(),
+		}
+	}
+}
+
+/// What the mouse is currently doing, positions are all screen ratios (`0..1` across the window).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InteractionMode {
+	Idle,
+	DragPan,
+	BoxSelect {
+		start: na::Point2<f32>,
+	},
+	/// Waiting for a tile to be picked as a target, any other button cancels.
+	TargetSelect,
+	/// Holding the left button paints the tile under the cursor.
+	EditorPaint,
+}
+
+/// Something the game should act on, produced by feeding input through `Interaction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InteractionAction {
+	Click(MouseButton, na::Point2<f32>),
+	/// A second click of the same button close enough in time and space to the first.
+	DoubleClick(MouseButton, na::Point2<f32>),
+	/// The left button was held still past the long press time, the release won't click.
+	LongPress(na::Point2<f32>),
+	Pan {
+		from: na::Point2<f32>,
+		to: na::Point2<f32>,
+	},
+	BoxSelect {
+		from: na::Point2<f32>,
+		to: na::Point2<f32>,
+	},
+	Target(na::Point2<f32>),
+	Paint(na::Point2<f32>),
+	Cancelled,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ButtonPress {
+	pub start: na::Point2<f32>,
+	pub time: Instant,
+	pub long_pressed: bool,
+}
+
+/// Owns the mouse interaction mode and all transitions between them.
+///
+/// Input events go in, the mode is updated, and at most one action comes out for the game to
+/// apply, so adding a new interaction is a new mode and its transitions instead of more branches
+/// in every mouse callback.
+pub struct Interaction {
+	mode: InteractionMode,
+	pressed: HashMap<MouseButton, ButtonPress>,
+	position: na::Point2<f32>,
+	last_click: Option<(MouseButton, na::Point2<f32>, Instant)>,
+	pub double_click: Duration,
+	pub long_press: Duration,
+}
+
+impl Interaction {
+	pub fn new(config: &InputConfig) -> Interaction {
+		Interaction {
+			mode: InteractionMode::Idle,
+			pressed: HashMap::new(),
+			position: na::Point2::new(0.0, 0.0),
+			last_click: None,
+			double_click: Duration::from_millis(config.double_click_ms),
+			long_press: Duration::from_millis(config.long_press_ms),
+		}
+	}
+
+	pub fn mode(&self) -> InteractionMode {
+		self.mode
+	}
+
+	/// Last known cursor position.
+	pub fn position(&self) -> na::Point2<f32> {
+		self.position
+	}
+
+	/// Switches to `mode` from idle, or back to idle if it is already active.
+	pub fn toggle(&mut self, mode: InteractionMode) {
+		self.mode = if self.mode == mode {
+			InteractionMode::Idle
+		} else {
+			mode
+		};
+	}
+
+	/// Drops back to idle, returns `false` if it already was.
+	pub fn cancel(&mut self) -> bool {
+		let was_idle = self.mode == InteractionMode::Idle;
+		self.mode = InteractionMode::Idle;
+		!was_idle
+	}
+
+	pub fn cursor(&self) -> MouseCursor {
+		match self.mode {
+			InteractionMode::Idle => MouseCursor::Default,
+			InteractionMode::DragPan => MouseCursor::Grabbing,
+			InteractionMode::BoxSelect { .. } => MouseCursor::Crosshair,
+			InteractionMode::TargetSelect => MouseCursor::Crosshair,
+			InteractionMode::EditorPaint => MouseCursor::Cell,
+		}
+	}
+
+	pub fn button_down(
+		&mut self,
+		button: MouseButton,
+		position: na::Point2<f32>,
+		box_modifier: bool,
+	) -> Option<InteractionAction> {
+		self.position = position;
+		self.pressed.insert(
+			button,
+			ButtonPress {
+				start: position,
+				time: Instant::now(),
+				long_pressed: false,
+			},
+		);
+		match (self.mode, button) {
+			(InteractionMode::Idle, MouseButton::Left) if box_modifier => {
+				self.mode = InteractionMode::BoxSelect { start: position };
+				None
+			}
+			(InteractionMode::TargetSelect, MouseButton::Left) => None,
+			(InteractionMode::TargetSelect, _) => {
+				self.mode = InteractionMode::Idle;
+				Some(InteractionAction::Cancelled)
+			}
+			(InteractionMode::EditorPaint, MouseButton::Left) => {
+				Some(InteractionAction::Paint(position))
+			}
+			_ => None,
+		}
+	}
+
+	/// `leeway` is how far, as a screen ratio on each axis, a press may move and still be a click.
+	pub fn button_up(
+		&mut self,
+		button: MouseButton,
+		position: na::Point2<f32>,
+		leeway: (f32, f32),
+	) -> Option<InteractionAction> {
+		self.position = position;
+		let press = self.pressed.remove(&button);
+		let is_click = match &press {
+			Some(press) => !press.long_pressed && Self::within(press.start, position, leeway),
+			None => false,
+		};
+		match (self.mode, button) {
+			(InteractionMode::DragPan, MouseButton::Left) => {
+				self.mode = InteractionMode::Idle;
+				None
+			}
+			(InteractionMode::BoxSelect { start }, MouseButton::Left) => {
+				self.mode = InteractionMode::Idle;
+				if is_click {
+					Some(InteractionAction::Click(button, position))
+				} else {
+					Some(InteractionAction::BoxSelect {
+						from: start,
+						to: position,
+					})
+				}
+			}
+			(InteractionMode::TargetSelect, MouseButton::Left) if is_click => {
+				self.mode = InteractionMode::Idle;
+				Some(InteractionAction::Target(position))
+			}
+			(InteractionMode::Idle, _) if is_click => {
+				let now = Instant::now();
+				match self.last_click.take() {
+					Some((last_button, last_position, time))
+						if last_button == button
+							&& now.duration_since(time) <= self.double_click
+							&& Self::within(last_position, position, leeway) =>
+					{
+						Some(InteractionAction::DoubleClick(button, position))
+					}
+					_ => {
+						self.last_click = Some((button, position, now));
+						Some(InteractionAction::Click(button, position))
+					}
+				}
+			}
+			_ => None,
+		}
+	}
+
+	pub fn motion(
+		&mut self,
+		position: na::Point2<f32>,
+		leeway: (f32, f32),
+	) -> Option<InteractionAction> {
+		let last = std::mem::replace(&mut self.position, position);
+		let left = self.pressed.get(&MouseButton::Left).copied();
+		match (self.mode, left) {
+			(InteractionMode::Idle, Some(press))
+				if !Self::within(press.start, position, leeway) =>
+			{
+				self.mode = InteractionMode::DragPan;
+				// Pan from where the press started so the leeway doesn't show up as a jump
+				Some(InteractionAction::Pan {
+					from: press.start,
+					to: position,
+				})
+			}
+			(InteractionMode::DragPan, Some(_)) => Some(InteractionAction::Pan {
+				from: last,
+				to: position,
+			}),
+			(InteractionMode::EditorPaint, Some(_)) => Some(InteractionAction::Paint(position)),
+			_ => None,
+		}
+	}
+
+	/// Checks for gestures that fire from time passing rather than from an input event.
+	pub fn poll(&mut self, now: Instant) -> Option<InteractionAction> {
+		if self.mode != InteractionMode::Idle {
+			return None;
+		}
+		let long_press = self.long_press;
+		if let Some(press) = self.pressed.get_mut(&MouseButton::Left) {
+			if !press.long_pressed && now.duration_since(press.time) >= long_press {
+				press.long_pressed = true;
+				return Some(InteractionAction::LongPress(press.start));
+			}
+		}
+		None
+	}
+
+	/// The corners of the box being dragged out, if box selecting.
+	pub fn box_selection(&self) -> Option<(na::Point2<f32>, na::Point2<f32>)> {
+		match self.mode {
+			InteractionMode::BoxSelect { start } => Some((start, self.position)),
+			_ => None,
+		}
+	}
+
+	fn within(a: na::Point2<f32>, b: na::Point2<f32>, leeway: (f32, f32)) -> bool {
+		(a.x - b.x).abs() < leeway.0 && (a.y - b.y).abs() < leeway.1
+	}
+}
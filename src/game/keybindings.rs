@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+use winit::VirtualKeyCode;
+
+/// Actions that can be rebound, escape and the chat keys are fixed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeyAction {
+	Target,
+	Paint,
+	ToggleCoords,
+	ToggleGrid,
+	DropPin,
+	Patrol,
+	Pause,
+	Step,
+	Faster,
+	Slower,
+	Settings,
+	EndTurn,
+}
+
+impl KeyAction {
+	pub const ALL: [KeyAction; 12] = [
+		KeyAction::Target,
+		KeyAction::Paint,
+		KeyAction::ToggleCoords,
+		KeyAction::ToggleGrid,
+		KeyAction::DropPin,
+		KeyAction::Patrol,
+		KeyAction::Pause,
+		KeyAction::Step,
+		KeyAction::Faster,
+		KeyAction::Slower,
+		KeyAction::Settings,
+		KeyAction::EndTurn,
+	];
+
+	pub fn label(self) -> &'static str {
+		match self {
+			KeyAction::Target => "Pick a target",
+			KeyAction::Paint => "Paint tiles",
+			KeyAction::ToggleCoords => "Show coordinates",
+			KeyAction::ToggleGrid => "Show grid",
+			KeyAction::DropPin => "Drop pin",
+			KeyAction::Patrol => "Patrol the selected units' orders",
+			KeyAction::Pause => "Pause",
+			KeyAction::Step => "Step one tick",
+			KeyAction::Faster => "Speed up",
+			KeyAction::Slower => "Slow down",
+			KeyAction::Settings => "Settings",
+			KeyAction::EndTurn => "End the turn",
+		}
+	}
+
+	fn default_key(self) -> VirtualKeyCode {
+		match self {
+			KeyAction::Target => VirtualKeyCode::T,
+			KeyAction::Paint => VirtualKeyCode::E,
+			KeyAction::ToggleCoords => VirtualKeyCode::F3,
+			KeyAction::ToggleGrid => VirtualKeyCode::G,
+			KeyAction::DropPin => VirtualKeyCode::M,
+			KeyAction::Patrol => VirtualKeyCode::L,
+			KeyAction::Pause => VirtualKeyCode::P,
+			KeyAction::Step => VirtualKeyCode::Period,
+			KeyAction::Faster => VirtualKeyCode::Equals,
+			KeyAction::Slower => VirtualKeyCode::Minus,
+			KeyAction::Settings => VirtualKeyCode::F10,
+			KeyAction::EndTurn => VirtualKeyCode::N,
+		}
+	}
+}
+
+/// Keys that can be bound, also the table for reading key names back from the config.
+const BINDABLE_KEYS: &[VirtualKeyCode] = {
+	use VirtualKeyCode::*;
+	&[
+		A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Key1, Key2,
+		Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10,
+		F11, F12, Space, Tab, Back, Insert, Delete, Home, End, PageUp, PageDown, Minus, Equals,
+		Period, Comma, Slash, Backslash, Semicolon, Apostrophe, LBracket, RBracket, Grave, Add,
+		Subtract, Multiply, Divide, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6,
+		Numpad7, Numpad8, Numpad9,
+	]
+};
+
+pub fn key_name(key: VirtualKeyCode) -> String {
+	format!("{:?}", key)
+}
+
+pub fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+	BINDABLE_KEYS
+		.iter()
+		.copied()
+		.find(|&key| key_name(key) == name)
+}
+
+pub fn is_bindable(key: VirtualKeyCode) -> bool {
+	BINDABLE_KEYS.contains(&key)
+}
+
+/// One key per action, stored by key name so the config stays readable.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(
+	try_from = "HashMap<KeyAction, String>",
+	into = "HashMap<KeyAction, String>"
+)]
+pub struct KeyBindings {
+	keys: HashMap<KeyAction, VirtualKeyCode>,
+}
+
+impl Default for KeyBindings {
+	fn default() -> Self {
+		KeyBindings {
+			keys: KeyAction::ALL
+				.iter()
+				.map(|&action| (action, action.default_key()))
+				.collect(),
+		}
+	}
+}
+
+impl TryFrom<HashMap<KeyAction, String>> for KeyBindings {
+	type Error = String;
+
+	/// Actions left out keep their default key.
+	fn try_from(names: HashMap<KeyAction, String>) -> Result<Self, Self::Error> {
+		let mut bindings = KeyBindings::default();
+		for (action, name) in names {
+			let key = key_from_name(&name)
+				.ok_or_else(|| format!("unknown key `{}` bound to {:?}", name, action))?;
+			bindings.bind(action, key);
+		}
+		Ok(bindings)
+	}
+}
+
+impl From<KeyBindings> for HashMap<KeyAction, String> {
+	fn from(bindings: KeyBindings) -> Self {
+		bindings
+			.keys
+			.into_iter()
+			.map(|(action, key)| (action, key_name(key)))
+			.collect()
+	}
+}
+
+impl KeyBindings {
+	pub fn key_for(&self, action: KeyAction) -> VirtualKeyCode {
+		self.keys
+			.get(&action)
+			.copied()
+			.unwrap_or_else(|| action.default_key())
+	}
+
+	pub fn action_for(&self, key: VirtualKeyCode) -> Option<KeyAction> {
+		KeyAction::ALL
+			.iter()
+			.copied()
+			.find(|&action| self.key_for(action) == key)
+	}
+
+	/// Binds `key` to `action`, an action that already had `key` swaps to `action`'s old key so
+	/// no key ever triggers two actions.
+	pub fn bind(&mut self, action: KeyAction, key: VirtualKeyCode) {
+		let old_key = self.key_for(action);
+		if let Some(other) = self.action_for(key) {
+			self.keys.insert(other, old_key);
+		}
+		self.keys.insert(action, key);
+	}
+}
@@ -0,0 +1,100 @@
+use ggez::graphics::{self, Color, DrawParam, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+
+use crate::game::text::{TextRole, TextStyles};
+
+pub struct WorldLabel {
+	/// Linear (map space) position the label is centered on.
+	pub position: (f32, f32),
+	pub text: String,
+	pub color: Color,
+}
+
+pub struct LabelLayer {
+	pub labels: Vec<WorldLabel>,
+	pub show_coords: bool,
+	/// Range of `screen_tiles` the labels are fully visible in, they fade out past either end.
+	pub visible_zoom: (f32, f32),
+	pub fade_margin: f32,
+	/// Draws a dark drop shadow under the text so it stays readable over bright tiles.
+	pub shadow: bool,
+}
+
+impl LabelLayer {
+	pub fn new() -> LabelLayer {
+		LabelLayer {
+			labels: vec![],
+			show_coords: false,
+			visible_zoom: (1.5, 12.0),
+			fade_margin: 1.0,
+			shadow: false,
+		}
+	}
+
+	pub fn alpha_for_zoom(&self, zoom: f32) -> f32 {
+		let (min, max) = self.visible_zoom;
+		let fade_in = (zoom - (min - self.fade_margin)) / self.fade_margin;
+		let fade_out = ((max + self.fade_margin) - zoom) / self.fade_margin;
+		fade_in.min(fade_out).max(0.0).min(1.0)
+	}
+
+	/// `base_size` is the pixel size of the text at a zoom of 4 tiles, it shrinks as you zoom out.
+	pub fn size_for_zoom(&self, zoom: f32, base_size: f32) -> f32 {
+		(base_size * (4.0 / zoom).sqrt())
+			.max(10.0)
+			.min(base_size * 1.5)
+	}
+
+	/// Draws the persistent labels and any `extra` ones in the tooltip text style, the screen
+	/// coordinates must be in pixels.
+	#[allow(clippy::too_many_arguments)]
+	pub fn draw(
+		&self,
+		ctx: &mut Context,
+		text_styles: &mut TextStyles,
+		view: Rect,
+		screen_size: (f32, f32),
+		zoom: f32,
+		ui_scale: f32,
+		extra: impl IntoIterator<Item = WorldLabel>,
+	) -> GameResult {
+		let alpha = self.alpha_for_zoom(zoom);
+		if alpha <= 0.0 {
+			return Ok(());
+		}
+		let base_size = text_styles.style(TextRole::Tooltip).size;
+		let size = self.size_for_zoom(zoom, base_size) * ui_scale;
+		let (screen_width, screen_height) = screen_size;
+		let mut draw_label = |ctx: &mut Context, label: &WorldLabel| -> GameResult {
+			let (x, y) = label.position;
+			if !view.contains([x, y]) {
+				return Ok(());
+			}
+			let px = (x - view.x) / view.w * screen_width;
+			let py = (y - view.y) / view.h * screen_height;
+			let text = text_styles.layout_sized(TextRole::Tooltip, &label.text, size);
+			let (width, height) = text.dimensions(ctx);
+			let mut color = label.color;
+			color.a *= alpha;
+			let dest = na::Point2::new(px - width as f32 * 0.5, py - height as f32 * 0.5);
+			if self.shadow {
+				graphics::draw(
+					ctx,
+					text,
+					DrawParam::new()
+						.dest(na::Point2::new(dest.x + 1.0, dest.y + 1.0))
+						.color(Color::new(0.0, 0.0, 0.0, alpha)),
+				)?;
+			}
+			graphics::draw(ctx, text, DrawParam::new().dest(dest).color(color))
+		};
+		for label in &self.labels {
+			draw_label(ctx, label)?;
+		}
+		for label in extra {
+			draw_label(ctx, &label)?;
+		}
+		Ok(())
+	}
+}
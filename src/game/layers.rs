@@ -0,0 +1,116 @@
+/// Which coordinates a layer draws in, they are set before its callbacks run.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LayerSpace {
+	/// Linear map units under the camera.
+	World,
+	/// Logical pixels of the window.
+	Screen,
+}
+
+/// The layers the game is drawn in, bottom to top.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RenderLayer {
+	/// The map ambient's background behind the tiles.
+	Background,
+	Terrain,
+	/// Grid, borders, fog and the like laid over the tiles.
+	Overlays,
+	/// Improvements and other marks on the tiles that units stand over.
+	Decals,
+	Entities,
+	/// Selection, orders and other feedback over the units.
+	Effects,
+	Ui,
+}
+
+impl RenderLayer {
+	pub const ALL: [RenderLayer; 7] = [
+		RenderLayer::Background,
+		RenderLayer::Terrain,
+		RenderLayer::Overlays,
+		RenderLayer::Decals,
+		RenderLayer::Entities,
+		RenderLayer::Effects,
+		RenderLayer::Ui,
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			RenderLayer::Background => "background",
+			RenderLayer::Terrain => "terrain",
+			RenderLayer::Overlays => "overlays",
+			RenderLayer::Decals => "decals",
+			RenderLayer::Entities => "entities",
+			RenderLayer::Effects => "effects",
+			RenderLayer::Ui => "ui",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<RenderLayer> {
+		RenderLayer::ALL
+			.iter()
+			.copied()
+			.find(|layer| layer.name() == name)
+	}
+
+	pub fn space(self) -> LayerSpace {
+		match self {
+			RenderLayer::Background | RenderLayer::Ui => LayerSpace::Screen,
+			_ => LayerSpace::World,
+		}
+	}
+}
+
+struct LayerEntry<F> {
+	enabled: bool,
+	callbacks: Vec<F>,
+}
+
+/// The draw callbacks of each layer in the order they were registered, layers can be turned off
+/// to see what is under them.
+pub struct RenderLayers<F> {
+	/// Indexed in `RenderLayer::ALL` order.
+	layers: Vec<LayerEntry<F>>,
+}
+
+impl<F: Copy> RenderLayers<F> {
+	pub fn new() -> RenderLayers<F> {
+		RenderLayers {
+			layers: RenderLayer::ALL
+				.iter()
+				.map(|_| LayerEntry {
+					enabled: true,
+					callbacks: vec![],
+				})
+				.collect(),
+		}
+	}
+
+	fn entry(&mut self, layer: RenderLayer) -> &mut LayerEntry<F> {
+		&mut self.layers[layer as usize]
+	}
+
+	/// Adds `callback` to `layer`, over the callbacks already there.
+	pub fn register(&mut self, layer: RenderLayer, callback: F) {
+		self.entry(layer).callbacks.push(callback);
+	}
+
+	pub fn set_enabled(&mut self, layer: RenderLayer, enabled: bool) {
+		self.entry(layer).enabled = enabled;
+	}
+
+	pub fn is_enabled(&self, layer: RenderLayer) -> bool {
+		self.layers[layer as usize].enabled
+	}
+
+	/// The enabled layers' callbacks bottom to top, copied out so the callbacks can borrow
+	/// whatever holds the layers.
+	pub fn callbacks(&self) -> Vec<(RenderLayer, F)> {
+		RenderLayer::ALL
+			.iter()
+			.zip(&self.layers)
+			.filter(|(_layer, entry)| entry.enabled)
+			.flat_map(|(&layer, entry)| entry.callbacks.iter().map(move |&f| (layer, f)))
+			.collect()
+	}
+}
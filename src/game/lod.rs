@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+fn serde_lod_strategic_zoom() -> f32 {
+	10.0
+}
+
+fn serde_lod_min_sprite_pixels() -> f32 {
+	64.0
+}
+
+/// How entities are drawn at the current zoom, from least to most detail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EntityDetail {
+	/// The strategic view, flat colored hexes with one icon on each occupied tile.
+	Strategic,
+	/// Sprites would be too small to make out, so each occupied tile gets a count badge.
+	Counts,
+	/// Every sprite, with a count badge on tiles holding more than one.
+	Sprites,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LodConfig {
+	/// Past this many tiles on screen the map is drawn as the strategic view.
+	#[serde(default = "serde_lod_strategic_zoom")]
+	pub strategic_zoom: f32,
+	/// Sprites drawn smaller than this many pixels across are left out for count badges, so how
+	/// far out that happens depends on the window size too.
+	#[serde(default = "serde_lod_min_sprite_pixels")]
+	pub min_sprite_pixels: f32,
+}
+
+impl Default for LodConfig {
+	fn default() -> Self {
+		LodConfig {
+			strategic_zoom: serde_lod_strategic_zoom(),
+			min_sprite_pixels: serde_lod_min_sprite_pixels(),
+		}
+	}
+}
+
+impl LodConfig {
+	pub fn is_strategic(&self, screen_tiles: f32) -> bool {
+		screen_tiles > self.strategic_zoom
+	}
+
+	/// Sprites are drawn a tile across, so `pixels_per_tile` is how big they come out.
+	pub fn entity_detail(&self, screen_tiles: f32, pixels_per_tile: f32) -> EntityDetail {
+		if self.is_strategic(screen_tiles) {
+			EntityDetail::Strategic
+		} else if pixels_per_tile < self.min_sprite_pixels {
+			EntityDetail::Counts
+		} else {
+			EntityDetail::Sprites
+		}
+	}
+}
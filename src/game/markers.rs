@@ -0,0 +1,59 @@
+use over_simple_game_1::core::engine::{MapCoord, MapIdx};
+
+pub struct MapPin {
+	pub coord: MapCoord,
+	pub name: String,
+}
+
+pub struct MapPins {
+	pins: Vec<MapPin>,
+}
+
+impl MapPins {
+	pub fn new() -> MapPins {
+		MapPins { pins: vec![] }
+	}
+
+	pub fn add(&mut self, coord: MapCoord, name: impl ToString) -> usize {
+		self.pins.push(MapPin {
+			coord,
+			name: name.to_string(),
+		});
+		self.pins.len() - 1
+	}
+
+	pub fn remove(&mut self, index: usize) -> Option<MapPin> {
+		if index < self.pins.len() {
+			Some(self.pins.remove(index))
+		} else {
+			None
+		}
+	}
+
+	pub fn get(&self, index: usize) -> Option<&MapPin> {
+		self.pins.get(index)
+	}
+
+	pub fn len(&self) -> usize {
+		self.pins.len()
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (usize, &MapPin)> {
+		self.pins.iter().enumerate()
+	}
+
+	pub fn iter_on_map(&self, map: MapIdx) -> impl Iterator<Item = &MapPin> {
+		self.pins.iter().filter(move |pin| pin.coord.map == map)
+	}
+}
+
+/// Joins the remaining command arguments back into a name, dropping any surrounding quotes.
+pub fn pin_name_from_args(args: &[String]) -> Option<String> {
+	let name = args.join(" ");
+	let name = name.trim().trim_matches('"').trim();
+	if name.is_empty() {
+		None
+	} else {
+		Some(name.to_owned())
+	}
+}
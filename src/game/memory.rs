@@ -0,0 +1,110 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ggez::graphics;
+use serde::{Deserialize, Serialize};
+
+use over_simple_game_1::core::memory::{MemoryReport, OverBudget, TOTAL};
+
+use crate::game::atlas::MultiAtlas;
+
+fn serde_memory_budgets_mib() -> BTreeMap<String, usize> {
+	vec![
+		("maps", 256),
+		("ecs", 256),
+		("atlases", 512),
+		("caches", 128),
+		(TOTAL, 1024),
+	]
+	.into_iter()
+	.map(|(name, mib)| (name.to_owned(), mib))
+	.collect()
+}
+
+fn serde_memory_check_seconds() -> f32 {
+	30.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MemoryConfig {
+	/// MiB each subsystem may use before it is warned about, `total` for all of them together.
+	#[serde(default = "serde_memory_budgets_mib")]
+	pub budgets_mib: BTreeMap<String, usize>,
+	/// How often the budgets are checked, 0 to never check them.
+	#[serde(default = "serde_memory_check_seconds")]
+	pub check_seconds: f32,
+}
+
+impl Default for MemoryConfig {
+	fn default() -> Self {
+		MemoryConfig {
+			budgets_mib: serde_memory_budgets_mib(),
+			check_seconds: serde_memory_check_seconds(),
+		}
+	}
+}
+
+impl MemoryConfig {
+	pub fn budgets(&self) -> BTreeMap<String, usize> {
+		self.budgets_mib
+			.iter()
+			.map(|(name, &mib)| (name.clone(), mib * 1024 * 1024))
+			.collect()
+	}
+}
+
+/// Checks the memory report against the budgets now and then, telling about each subsystem once
+/// when it goes over and again only after it has been back under.
+#[derive(Debug)]
+pub struct MemoryWatch {
+	config: MemoryConfig,
+	remaining: f32,
+	over: BTreeSet<String>,
+}
+
+impl MemoryWatch {
+	pub fn new(config: MemoryConfig) -> MemoryWatch {
+		MemoryWatch {
+			remaining: config.check_seconds,
+			config,
+			over: BTreeSet::new(),
+		}
+	}
+
+	/// Whether a check is due after `delta` more seconds.
+	pub fn tick(&mut self, delta: f32) -> bool {
+		if self.config.check_seconds <= 0.0 {
+			return false;
+		}
+		self.remaining -= delta;
+		if self.remaining > 0.0 {
+			return false;
+		}
+		self.remaining = self.config.check_seconds;
+		true
+	}
+
+	/// The subsystems that went over their budget since the last check.
+	pub fn check(&mut self, report: &MemoryReport) -> Vec<OverBudget> {
+		let over = report.over_budget(&self.config.budgets());
+		let newly = over
+			.iter()
+			.filter(|over| !self.over.contains(&over.name))
+			.cloned()
+			.collect();
+		self.over = over.into_iter().map(|over| over.name).collect();
+		newly
+	}
+}
+
+/// The pixels of every page of `atlas` at every mip level, as they are held on the gpu.
+pub fn atlas_bytes<Unique: Copy>(atlas: &MultiAtlas<graphics::Image, Unique>) -> usize {
+	(0..atlas.len_atlases())
+		.flat_map(|page| {
+			(0..)
+				.map(move |level| atlas.get_mip_image_by_index(page, level))
+				.take_while(Option::is_some)
+				.flatten()
+		})
+		.map(|image| image.width() as usize * image.height() as usize * 4)
+		.sum()
+}
@@ -1,42 +1,178 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::Infallible;
 use std::fmt;
+use std::num::NonZeroU64;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::Context as AnyContext;
 use ggez::conf::{FullscreenType, NumSamples, WindowMode, WindowSetup};
 use ggez::graphics::spritebatch::SpriteBatch;
-use ggez::graphics::{Color, DrawMode, DrawParam, Drawable, FilterMode, Rect, Vertex};
+use ggez::graphics::{Color, DrawMode, DrawParam, Drawable, Font, Rect, Vertex};
 use ggez::input::{keyboard, mouse};
 use ggez::nalgebra as na;
 use ggez::{graphics, Context, ContextBuilder, GameError};
 use log::*;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use shipyard::*;
+use tracing::{field, trace_span};
 use winit::{
-	dpi, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
-	VirtualKeyCode, WindowEvent,
+	dpi, ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseCursor,
+	MouseScrollDelta, VirtualKeyCode, WindowEvent,
 };
 
-use over_simple_game_1::core::engine::MapCoord;
-use over_simple_game_1::core::map::generator::SimpleAlternationMapGenerator;
-use over_simple_game_1::games::civ::CivGame;
+use over_simple_game_1::core::ai::budget::{SlicedWork, WorkProgress};
+use over_simple_game_1::core::component::{
+	component_registration, component_values, registered_components_memory, set_component_value,
+};
+use over_simple_game_1::core::engine::address::MapAddress;
+use over_simple_game_1::core::engine::army::Army;
+use over_simple_game_1::core::engine::clock::{SimulationClock, SimulationSpeed};
+use over_simple_game_1::core::engine::io::WriteMode;
+use over_simple_game_1::core::engine::jobs::JobPool;
+use over_simple_game_1::core::engine::orders::MoveOrder;
+use over_simple_game_1::core::engine::schema::Schemas;
+use over_simple_game_1::core::engine::statistics::{GameOutcome, PlayerStatistics};
+use over_simple_game_1::core::engine::tags::{add_tag, query_entities_with_tag, remove_tag, Tags};
+use over_simple_game_1::core::engine::{EngineError, MapCoord};
+use over_simple_game_1::core::map::metadata::MapMetadata;
+use over_simple_game_1::core::map::territory::TerritoryHistory;
+use over_simple_game_1::core::map::text_map::save_text_map;
+use over_simple_game_1::core::map::tiled::TiledObject;
+use over_simple_game_1::core::memory::{format_bytes, MemoryReport, MemoryUsage};
+use over_simple_game_1::games::civ::goodies::GoodyReward;
+use over_simple_game_1::games::civ::{CivGame, GoodyFound, UnitPlan};
 use over_simple_game_1::prelude::*;
 
-use crate::game::atlas::{AtlasId, MultiAtlas, MultiAtlasBuilder};
+use crate::game::accessibility::AccessibilityConfig;
+use crate::game::ambient::AmbientBackground;
+use crate::game::animation::{AnimationTimeline, CombatEvent};
+use crate::game::args::Args;
+use crate::game::atlas::{AtlasId, MultiAtlas, MultiAtlasBuilder, PackedAtlas};
+use crate::game::camera::{CameraConfig, CameraPolicy};
+use crate::game::chat::{ChatBox, ChatInput};
 use crate::game::components::DrawSprite;
+use crate::game::context_menu::{ContextAction, ContextMenu};
+use crate::game::effects::{EffectsConfig, ScreenEffects, ScreenEvent};
+use crate::game::grid::{GridOverlay, GridStyle};
+use crate::game::hall_of_fame::STATISTICS_PATH;
+use crate::game::heatmap::{hex_vertices, HeatmapOverlay, HeatmapValues, HEX_INDICES};
+use crate::game::inspector::{Inspector, InspectorInput};
+use crate::game::interaction::{InputConfig, Interaction, InteractionAction, InteractionMode};
+use crate::game::keybindings::KeyAction;
+use crate::game::labels::{LabelLayer, WorldLabel};
+use crate::game::layers::{LayerSpace, RenderLayer, RenderLayers};
+use crate::game::lod::{EntityDetail, LodConfig};
+use crate::game::markers::{pin_name_from_args, MapPins};
+use crate::game::memory::{atlas_bytes, MemoryConfig, MemoryWatch};
+use crate::game::modal::{Modal, ModalAction, ModalQueue};
+use crate::game::movement::{linear_delta, MoveHop, MovementConfig};
+use crate::game::music::{MusicManager, MusicPlaylists};
+use crate::game::offscreen::RenderTarget;
+use crate::game::panel::UiImageInfo;
+use crate::game::screen::ScreenMetrics;
+use crate::game::sequence::{Sequence, SequencePlayer, SequenceStep};
+use crate::game::session::{format_duration, SessionStats};
+use crate::game::settings::{
+	AtlasFilter, AudioConfig, Setting, SettingsInput, SettingsScreen, VideoConfig,
+};
+use crate::game::soundscape::{AmbientZone, Soundscape};
+use crate::game::text::{TextConfig, TextRole, TextStyles};
+use crate::game::tutorial::{Tutorial, TutorialEvent, TutorialHighlight, TutorialRunner};
+
+mod accessibility;
+
+mod ambient;
+
+mod animation;
+
+pub mod args;
 
 mod atlas;
 
+mod branding;
+
+mod camera;
+
+mod chat;
+
+mod context_menu;
+
+mod effects;
+
+pub mod crash;
+
+mod grid;
+
+mod hall_of_fame;
+
+mod heatmap;
+
+pub mod headless;
+
+mod inspector;
+
+mod interaction;
+
+mod keybindings;
+
+mod layers;
+
+mod lod;
+
+mod labels;
+
+mod markers;
+
+mod memory;
+
+mod modal;
+
+mod movement;
+
+mod music;
+
+mod offscreen;
+
+pub mod pack;
+
+mod panel;
+
+mod placeholder;
+
+mod screen;
+
+mod sequence;
+
+mod session;
+
+mod settings;
+
+mod soundscape;
+
+mod text;
+
+mod tutorial;
+
+pub mod user_data;
+
+mod world;
+
 mod components;
 
 #[derive(Clone, Copy, Debug)]
 enum MapAtlas {}
 
+/// Width and height of each page of the tiles atlas.
+const TILES_ATLAS_SIZE: u16 = 2048;
+
 #[derive(Clone, Copy, Debug)]
 enum EntityAtlas {}
 
+#[derive(Clone, Copy, Debug)]
+enum UiAtlas {}
+
 fn serde_hex_bound() -> Rect {
 	Rect {
 		x: -0.5,
@@ -56,45 +192,533 @@ struct TileDrawableInfo {
 	bounds: Rect,
 	#[serde(default = "serde_hex_color")]
 	color: Color,
+	/// Flat color used when zoomed out to the strategic view, defaults to the average of the image.
+	#[serde(default)]
+	strategic_color: Option<Color>,
+	/// Weights of the `<name>_0.png`, `<name>_1.png`, ... variants, empty uses just `<name>.png`.
+	#[serde(default)]
+	variant_weights: Vec<u32>,
+	/// Orientations the image may be drawn in, one is picked per tile. Empty draws it as is.
+	#[serde(default)]
+	orientations: Vec<TileOrientation>,
+}
+
+impl Default for TileDrawableInfo {
+	fn default() -> Self {
+		TileDrawableInfo {
+			bounds: serde_hex_bound(),
+			color: serde_hex_color(),
+			strategic_color: None,
+			variant_weights: vec![],
+			orientations: vec![],
+		}
+	}
+}
+
+/// The tiles atlas images of tile type `name` with their weights.
+fn tile_images(name: &str, info: &TileDrawableInfo) -> Vec<(String, u32)> {
+	if info.variant_weights.is_empty() {
+		vec![(format!("tiles/{}", name), 1)]
+	} else {
+		info.variant_weights
+			.iter()
+			.enumerate()
+			.filter(|&(_i, &weight)| weight > 0)
+			.map(|(i, &weight)| (format!("tiles/{}_{}", name, i), weight))
+			.collect()
+	}
+}
+
+/// The tiles atlas image of decoration type `name`.
+fn decoration_image(name: &str) -> String {
+	format!("decorations/{}", name)
+}
+
+/// Tiles atlas images are named after where they are in the resources.
+fn atlas_image_path(image: &str) -> PathBuf {
+	PathBuf::from(format!("/{}.png", image))
+}
+
+/// Mirroring then rotation applied to a tile image, so one image can supply every hex direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct TileOrientation {
+	/// Clockwise sixths of a turn.
+	#[serde(default)]
+	rotation: u8,
+	/// Flipped left to right.
+	#[serde(default)]
+	mirrored: bool,
+}
+
+impl TileOrientation {
+	fn is_identity(self) -> bool {
+		self.rotation % 6 == 0 && !self.mirrored
+	}
+
+	/// Which of `HexOrientation::corners` of the source image ends up drawn at `corner`.
+	fn source_corner(self, corner: usize) -> usize {
+		let unrotated = (corner + 6 - (self.rotation % 6) as usize) % 6;
+		if self.mirrored {
+			(6 - unrotated) % 6
+		} else {
+			unrotated
+		}
+	}
 }
 
 struct TilesDrawable {
-	atlas_id: AtlasId<MapAtlas>,
+	variants: Vec<(AtlasId<MapAtlas>, u32)>,
+	total_weight: u32,
 	info: TileDrawableInfo,
+	strategic_color: Color,
+}
+
+impl TilesDrawable {
+	/// Weighted pick of a variant from a per-tile hash, so the same tile always looks the same.
+	fn pick_variant(&self, hash: u64) -> AtlasId<MapAtlas> {
+		if self.variants.len() == 1 {
+			return self.variants[0].0;
+		}
+		let mut roll = (hash % self.total_weight as u64) as u32;
+		for &(id, weight) in &self.variants {
+			if roll < weight {
+				return id;
+			}
+			roll -= weight;
+		}
+		self.variants[0].0
+	}
+}
+
+fn serde_journal_path() -> String {
+	"journal.jsonl".to_owned()
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct JournalConfig {
+	#[serde(default)]
+	enabled: bool,
+	/// Relative to the user data directory.
+	#[serde(default = "serde_journal_path")]
+	path: String,
+}
+
+impl Default for JournalConfig {
+	fn default() -> Self {
+		JournalConfig {
+			enabled: false,
+			path: serde_journal_path(),
+		}
+	}
+}
+
+fn serde_game_speed() -> String {
+	"standard".to_owned()
+}
+
+fn serde_difficulty() -> String {
+	"normal".to_owned()
+}
+
+/// Which of the game speeds and difficulties in `civ/rules.ron` a new game is set up with.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct GameSetupConfig {
+	#[serde(default = "serde_game_speed")]
+	speed: String,
+	#[serde(default = "serde_difficulty")]
+	difficulty: String,
+	/// Seconds each turn gets before it ends by itself, turns aren't timed when left out. Never
+	/// 0, which would end every turn as soon as it starts.
+	#[serde(default)]
+	turn_timer: Option<NonZeroU64>,
+}
+
+impl Default for GameSetupConfig {
+	fn default() -> Self {
+		GameSetupConfig {
+			speed: serde_game_speed(),
+			difficulty: serde_difficulty(),
+			turn_timer: None,
+		}
+	}
+}
+
+/// How the entity templates are to be reloaded, asked for by `/reload`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TemplateReloadRequest {
+	Reload,
+	/// Also re-applies the changed components to the entities already made from the templates.
+	ReloadAndPatch,
+	/// Checks for changed templates while watching, patching and only saying anything if some did.
+	Watch,
+}
+
+/// A unit to show the AI's plan for, asked for by `/ai`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct UnitAiRequest {
+	entity: EntityId,
+	/// Orders the unit where the plan would send it.
+	follow: bool,
+	/// Plans the way this AI persona would instead of with the plain weights.
+	persona: Option<String>,
+}
+
+/// An AI turn to run through every unit, asked for by `/ai turn`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AiTurnRequest {
+	persona: Option<String>,
+}
+
+/// An AI turn running a slice of its units a frame.
+struct AiTurn {
+	units: SlicedWork<EntityId>,
+	persona: Option<String>,
+	/// The units the turn gave somewhere to go so far.
+	ordered: usize,
+}
+
+/// The time an AI turn gets each frame before the rest waits for the next one.
+const AI_FRAME_BUDGET: Duration = Duration::from_millis(4);
+
+/// Frames an AI turn gets before its remaining units are skipped, about ten seconds.
+const AI_TURN_MAX_FRAMES: u32 = 600;
+
+/// Background threads for the job pool, heavy work that would hold up the frame goes to them.
+const JOB_THREADS: usize = 2;
+
+/// Finishes a background job on the main thread with what it made.
+type JobCompletion = Box<dyn FnOnce(&mut GameState) + Send>;
+
+/// Seconds between checks for changed entity templates while watching them.
+const TEMPLATE_WATCH_SECONDS: f32 = 1.0;
+
+/// Where a decoration sits within its tile, a bit above center so it reads as standing on it.
+const DECORATION_BOUNDS: Rect = Rect {
+	x: -0.3,
+	y: -0.4,
+	w: 0.6,
+	h: 0.6,
+};
+
+/// Largest width or height of a map export, past this GPUs may refuse the offscreen target.
+const MAX_EXPORT_PIXELS: f32 = 8192.0;
+
+const DEFAULT_EXPORT_PIXELS_PER_HEX: f32 = 64.0;
+
+/// Visible tiles are meshed in squares of this many linear units, so a changed tile only rebuilds
+/// the square holding it.
+const TILE_CHUNK_SIZE: f32 = 8.0;
+
+/// The meshes of the visible tiles within one chunk along with the tiles, so the chunk can be
+/// rebuilt on its own. A chunk's tiles are all on the same side of a wrapping map's seam, `shift`
+/// is how far that copy of the map is from where `HexOrientation::to_linear` puts it.
+#[derive(Debug)]
+struct TileMeshChunk {
+	tiles: Vec<Coord>,
+	shift: (f32, f32),
+	meshes: Vec<Option<graphics::Mesh>>,
+}
+
+/// The chunk's tiles as they currently are on `tile_map` with the `positions` they are drawn at.
+fn chunk_tiles<'a>(
+	tile_map: &'a TileMap,
+	tiles: &'a [Coord],
+	positions: &'a [(f32, f32)],
+) -> impl Iterator<Item = (Coord, &'a Tile, (f32, f32))> + 'a {
+	tiles
+		.iter()
+		.zip(positions)
+		.filter_map(move |(&coord, &pos)| tile_map.get_tile(coord).map(|tile| (coord, tile, pos)))
+}
+
+/// A count of the units on a tile, gold when an army is among them.
+struct StackBadge {
+	center: (f32, f32),
+	/// In tiles, badges standing in for sprites are bigger than the ones in a sprite's corner.
+	radius: f32,
+	units: usize,
+	army: bool,
+}
+
+/// Width of the minimap in logical pixels before the ui scale, its height follows the map.
+const MINIMAP_WIDTH: f32 = 192.0;
+
+/// The linear area all of `tile_map` is drawn over, axial coordinates lay it out as a
+/// parallelogram leaning right as the rows go down, or leaning down as the columns go right for
+/// flat-top hexes.
+fn whole_map_area(hex: HexOrientation, tile_map: &TileMap) -> Rect {
+	let (far_x, far_y) = hex.to_linear(Coord::new_axial(tile_map.width, tile_map.height));
+	let (half_x, half_y) = hex.half_size();
+	Rect::new(-half_x, -half_y, far_x + half_x * 2.0, far_y + half_y * 2.0)
+}
+
+/// Every tile of `tile_map` once, where it is drawn without any wrapping.
+fn whole_map_tiles(
+	hex: HexOrientation,
+	tile_map: &TileMap,
+) -> impl Iterator<Item = (Coord, &Tile, (f32, f32))> {
+	(0..=tile_map.height)
+		.flat_map(move |r| (0..=tile_map.width).map(move |q| Coord::new_axial(q, r)))
+		.filter_map(move |coord| {
+			let tile = tile_map.get_tile(coord)?;
+			Some((coord, tile, hex.to_linear(coord)))
+		})
+}
+
+/// Where an export named `file` goes in the user data directory, `extension` is added if it has
+/// none.
+fn export_path(file: &str, extension: &str) -> PathBuf {
+	let mut path = PathBuf::from("exports").join(file);
+	if path.extension().is_none() {
+		path.set_extension(extension);
+	}
+	path
+}
+
+/// Written next to an exported map image as `<file>.ron`, since PNGs can't hold it here.
+#[derive(Serialize)]
+struct MapExportInfo<'a> {
+	map: &'a str,
+	metadata: &'a MapMetadata,
+	seed: u64,
+	pixels_per_hex: f32,
+}
+
+fn read_bytes(ctx: &mut Context, path: &Path) -> anyhow::Result<Vec<u8>> {
+	use std::io::Read;
+	let mut buf = Vec::new();
+	let mut reader = ggez::filesystem::open(ctx, path)?;
+	let _ = reader.read_to_end(&mut buf)?;
+	Ok(buf)
+}
+
+fn decode_rgba(bytes: &[u8]) -> anyhow::Result<(u16, u16, Vec<u8>)> {
+	let image = image::load_from_memory(bytes)?.to_rgba();
+	Ok((
+		image.width() as u16,
+		image.height() as u16,
+		image.into_raw(),
+	))
 }
 
-struct MouseButtonPressedData {
-	screen: na::Point2<f32>,
-	time: Instant,
+fn load_rgba(ctx: &mut Context, path: &Path) -> anyhow::Result<(u16, u16, Vec<u8>)> {
+	decode_rgba(&read_bytes(ctx, path)?)
 }
 
-impl MouseButtonPressedData {
-	fn new(x: f32, y: f32) -> MouseButtonPressedData {
-		MouseButtonPressedData {
-			screen: [x, y].into(),
-			time: Instant::now(),
+/// The image loaded from `path`, or a placeholder with `name` on it when it is missing or broken
+/// so the content error shows in game instead of stopping it.
+fn rgba_or_placeholder(
+	loaded: anyhow::Result<(u16, u16, Vec<u8>)>,
+	path: &Path,
+	name: &str,
+) -> (u16, u16, Vec<u8>) {
+	match loaded {
+		Ok(image) => image,
+		Err(error) => {
+			warn!(
+				"Failed loading `{}`, using a placeholder: {:#}",
+				path.display(),
+				error
+			);
+			placeholder::placeholder_rgba(name)
 		}
 	}
 }
 
+/// The tiles atlas pre-built by `--pack-assets` with its pages, if the resources have one with
+/// all of `images`.
+fn load_packed_tiles_atlas(
+	ctx: &mut Context,
+	images: &[&str],
+) -> Option<(PackedAtlas, Vec<(u16, u16, Vec<u8>)>)> {
+	let path = Path::new("/").join(pack::PACKED_TILES_ATLAS);
+	let file = ggez::filesystem::open(ctx, &path).ok()?;
+	let packed: PackedAtlas = match ron::de::from_reader(file) {
+		Ok(packed) => packed,
+		Err(error) => {
+			warn!(
+				"Failed reading `{}`, building the tiles atlas instead: {}",
+				path.display(),
+				error
+			);
+			return None;
+		}
+	};
+	if let Some(missing) = images.iter().find(|image| !packed.contains(image)) {
+		info!(
+			"The packed tiles atlas has no `{}`, building the tiles atlas instead",
+			missing
+		);
+		return None;
+	}
+	let mut pages = Vec::with_capacity(packed.pages);
+	for page in 0..packed.pages {
+		let page_path = Path::new("/").join(pack::packed_page_path(page));
+		match load_rgba(ctx, &page_path) {
+			Ok(rgba) => pages.push(rgba),
+			Err(error) => {
+				warn!(
+					"Failed loading `{}`, building the tiles atlas instead: {:#}",
+					page_path.display(),
+					error
+				);
+				return None;
+			}
+		}
+	}
+	Some((packed, pages))
+}
+
+fn tiles_atlas_image(
+	ctx: &mut Context,
+	tiles_filter: AtlasFilter,
+	width: u16,
+	height: u16,
+	rgba: &[u8],
+) -> anyhow::Result<graphics::Image> {
+	let mut image = graphics::Image::from_rgba8(ctx, width, height, rgba)
+		.context("failed converting tiles atlas texture")?;
+	image.set_filter(tiles_filter.filter.into());
+	Ok(image)
+}
+
+/// Alpha weighted average of an rgba8 image, fully transparent images come out white.
+fn average_color(rgba: &[u8]) -> Color {
+	let mut sums = [0.0f64; 3];
+	let mut total_alpha = 0.0f64;
+	for pixel in rgba.chunks_exact(4) {
+		let alpha = pixel[3] as f64 / 255.0;
+		for (sum, &channel) in sums.iter_mut().zip(pixel) {
+			*sum += channel as f64 / 255.0 * alpha;
+		}
+		total_alpha += alpha;
+	}
+	if total_alpha <= 0.0 {
+		return graphics::WHITE;
+	}
+	Color::new(
+		(sums[0] / total_alpha) as f32,
+		(sums[1] / total_alpha) as f32,
+		(sums[2] / total_alpha) as f32,
+		1.0,
+	)
+}
+
+/// Draws one part of the game within its render layer.
+type DrawCallback =
+	fn(&mut GameState, &mut shipyard::World, &mut Engine<GameState>) -> anyhow::Result<()>;
+
 struct GameState {
 	ctx: Context,
 	visible_map: String,
 	screen_tiles: f32,
 	zoom: f32,
 	view_center: na::Point2<f32>,
-	screen_size: dpi::LogicalSize,
+	/// The engine's, how map coords turn into linear space for drawing and picking.
+	hex: HexOrientation,
+	screen: ScreenMetrics,
 	aspect_ratio: f32,
 	tiles_atlas: MultiAtlas<graphics::Image, MapAtlas>,
-	tiles_meshes: Vec<Option<graphics::Mesh>>,
+	tiles_meshes: Vec<TileMeshChunk>,
+	/// Reused for where a chunk's tiles are drawn while its meshes are built.
+	tiles_positions: Vec<(f32, f32)>,
+	tiles_meshes_strategic: bool,
+	/// The tiles atlas mip level the meshes were built with.
+	tiles_mip_level: usize,
+	/// The most pixels of a tile image across one map unit, picks the mip level for a zoom.
+	tiles_texels_per_unit: f32,
+	ambient_background: AmbientBackground,
+	soundscape: Soundscape,
+	music: MusicManager,
+	/// The visible map's ambient light, multiplied into the tiles and entities.
+	light_tint: Color,
+	/// When the map goes strategic and sprites give way to count badges.
+	lod: LodConfig,
+	strategic_icon_mesh: Option<graphics::Mesh>,
 	tiles_drawable: Vec<TilesDrawable>,
+	/// Indexed by `DecorationIdx`, the images live in the tiles atlas under `decorations/<name>`.
+	decorations_drawable: Vec<AtlasId<MapAtlas>>,
 	entity_atlas: MultiAtlas<graphics::Image, EntityAtlas>,
 	entity_spritebatches: Vec<SpriteBatch>,
+	/// The entity atlas mip level the sprite batches draw from.
+	entity_mip_level: usize,
+	/// The most pixels across of any sprite, they are drawn a tile across.
+	entity_texels_per_tile: f32,
+	/// Unit counts drawn over tiles this frame, the numbers are drawn with the labels.
+	stack_badges: Vec<StackBadge>,
+	ui_atlas: MultiAtlas<graphics::Image, UiAtlas>,
+	/// Nine slice skin for popup panels, plain boxes are drawn without it.
+	panel_skin: Option<AtlasId<UiAtlas>>,
 	selected: Option<EntityId>,
 	selected_mesh: Option<graphics::Mesh>,
-	click_leeway: f32,
-	mouse_buttons_clicked: HashMap<MouseButton, MouseButtonPressedData>,
-	mouse_last_position: na::Point2<f32>,
+	input: InputConfig,
+	video: VideoConfig,
+	audio: AudioConfig,
+	settings: Option<SettingsScreen>,
+	interaction: Interaction,
+	box_selected: Vec<EntityId>,
+	/// Entities that finished a hop since the game rules last looked at what they arrived on.
+	arrived: Vec<(EntityId, MapCoord)>,
+	paint_brush: Option<TileIdx>,
+	context_menu: Option<ContextMenu>,
+	inspector: Option<Inspector>,
+	mouse_logical_position: dpi::LogicalPosition,
+	chat: ChatBox,
+	clock: SimulationClock,
+	view_rect: Rect,
+	/// How fast units following move orders go.
+	movement: MovementConfig,
+	effects: ScreenEffects,
+	/// Attacks and deaths still playing out on screen.
+	animations: AnimationTimeline,
+	labels: LabelLayer,
+	text: TextStyles,
+	pins: MapPins,
+	pin_mesh: Option<graphics::Mesh>,
+	grid: GridOverlay,
+	heatmap: HeatmapOverlay,
+	window_title: String,
+	cursor: MouseCursor,
+	accessibility: AccessibilityConfig,
+	camera: CameraConfig,
+	layers: RenderLayers<DrawCallback>,
+	/// The visible map's strategic view, rendered once until the map or its tiles change.
+	minimap: RenderTarget,
+	show_minimap: bool,
+	template_reload: Option<TemplateReloadRequest>,
+	/// An entity to write out as an entity template and where, asked for by `/export template`.
+	template_export: Option<(EntityId, PathBuf)>,
+	unit_ai_request: Option<UnitAiRequest>,
+	ai_turn_request: Option<AiTurnRequest>,
+	/// Completed a frame at a time by `finish_jobs`.
+	jobs: JobPool<JobCompletion>,
+	/// How many units the running AI turn is through out of how many, while waiting for it.
+	waiting: Option<(usize, usize)>,
+	session: SessionStats,
+	/// The borders at the end of each turn by map name, for the end of game timeline. There are
+	/// no players to own tiles yet, so every tile is recorded as nobody's.
+	territory: HashMap<String, TerritoryHistory>,
+	/// Seconds until the next check for changed templates, `None` when not watching them.
+	template_watch: Option<f32>,
+	/// Asked for by `/mem`.
+	memory_request: bool,
+	memory: MemoryWatch,
+	/// Kept across games in the user data directory.
+	statistics: PlayerStatistics,
+	show_hall_of_fame: bool,
+	/// Dialogs over everything else, the one showing takes all input.
+	modals: ModalQueue,
+	/// Maps changed by commands since they were last saved, by name.
+	unsaved_maps: BTreeSet<String>,
+	/// A scenario intro or tutorial beat playing, the game's input waits until it is done.
+	sequence: Option<SequencePlayer>,
+	/// Where the camera glides from and to over the sequence step playing, in map units.
+	camera_glide: Option<((f32, f32), (f32, f32))>,
+	tutorial: Option<TutorialRunner>,
+	/// Where everything the game writes goes.
+	user_data: PathBuf,
+	/// Config files here are used before the saved settings and the resources.
+	config_dir: Option<PathBuf>,
 }
 
 pub struct Game {
@@ -103,6 +727,8 @@ pub struct Game {
 	engine: Engine<GameState>,
 	civ: CivGame,
 	events_loop: ggez::event::EventsLoop,
+	/// The AI turn being run, a slice each frame.
+	ai_turn: Option<AiTurn>,
 	// gamepad_enabled: bool,
 }
 
@@ -121,12 +747,42 @@ impl EngineIO for GameState {
 	type ReadError = GameError;
 	type Read = ggez::filesystem::File;
 
+	/// Reads from the resources and then the user data directory.
 	fn read(&mut self, file_path: &Path) -> Result<Self::Read, Self::ReadError> {
 		let mut path = PathBuf::from("/");
 		path.push(file_path);
 		ggez::filesystem::open(&mut self.ctx, path)
 	}
 
+	fn exists(&mut self, file_path: &Path) -> bool {
+		let mut path = PathBuf::from("/");
+		path.push(file_path);
+		ggez::filesystem::exists(&self.ctx, path)
+	}
+
+	type WriteError = std::io::Error;
+	type Write = std::fs::File;
+
+	/// Writes go to the user data directory, never the resources.
+	fn write(
+		&mut self,
+		file_path: &Path,
+		mode: WriteMode,
+	) -> Result<Self::Write, Self::WriteError> {
+		let path = self
+			.user_data
+			.join(file_path.strip_prefix("/").unwrap_or(file_path));
+		if let Some(parent) = path.parent() {
+			std::fs::create_dir_all(parent)?;
+		}
+		let mut options = std::fs::OpenOptions::new();
+		match mode {
+			WriteMode::Truncate => options.write(true).truncate(true),
+			WriteMode::Append => options.append(true),
+		};
+		options.create(true).open(path)
+	}
+
 	type TileInterface = ();
 
 	fn blank_tile_interface() -> Self::TileInterface {}
@@ -143,12 +799,13 @@ impl EngineIO for GameState {
 }
 
 impl Game {
-	pub fn new() -> anyhow::Result<Game> {
+	/// Everything the game writes goes in `user_data`, which is also searched after the resources.
+	pub fn new(user_data: PathBuf, args: &Args) -> anyhow::Result<Game> {
 		let window_setup = WindowSetup {
-			title: "OverSimpleGame1".to_string(),
+			title: branding::GAME_TITLE.to_string(),
 			samples: NumSamples::Zero,
 			vsync: false,
-			icon: "".to_string(), // TODO: Create an icon
+			icon: "".to_string(), // Set after creation so a generated fallback can be used
 			srgb: false,
 		};
 
@@ -165,7 +822,7 @@ impl Game {
 			resizable: true,
 		};
 
-		let (ctx, events_loop) = ContextBuilder::new("over-simple-game-1", "OvermindDL1")
+		let (mut ctx, events_loop) = ContextBuilder::new(branding::GAME_ID, branding::AUTHOR)
 			.window_setup(window_setup)
 			.window_mode(window_mode)
 			.add_resource_path(
@@ -176,16 +833,20 @@ impl Game {
 				// } else {
 				std::path::PathBuf::from("./resources"), // },
 			)
+			.add_resource_path(user_data.clone())
 			.build()
 			.context("Failed to create GGEZ Context")?;
+		branding::set_window_icon(&mut ctx)?;
 
 		// // This is... not right, why does ggez not let us test this ourselves?
 		// let conf = ggez::conf::Conf::new();
 		// let gamepad_enabled = conf.modules.gamepad;
 
-		let state = GameState::new(ctx);
+		let mut state = GameState::new(ctx, user_data);
+		state.config_dir = args.config.clone();
 		let ecs = shipyard::World::new();
-		let engine = Engine::new();
+		let engine = world::engine_builder(args).build();
+		state.hex = engine.hex_orientation();
 		let civ = CivGame::new("/civ");
 
 		Ok(Game {
@@ -194,20 +855,96 @@ impl Game {
 			engine,
 			civ,
 			events_loop,
+			ai_turn: None,
 			// gamepad_enabled,
 		})
 	}
 
-	pub fn setup(&mut self) -> anyhow::Result<()> {
+	/// Sets the game up as the program `args` ask.
+	pub fn setup(&mut self, args: &Args) -> anyhow::Result<()> {
+		let journal: JournalConfig = self.state.load_config("journal")?;
+		let configured = if journal.enabled {
+			Some(journal.path)
+		} else {
+			None
+		};
+		match args.journal.clone().or(configured) {
+			Some(path) => {
+				self.engine.enable_journal(&mut self.state, &path)?;
+				info!(
+					"Writing the game journal to `{}` in the user data directory",
+					path
+				);
+			}
+			None => debug!("Game journal is disabled"),
+		}
 		self.engine.setup(&mut self.state)?;
 		self.state.setup(&mut self.engine)?;
-		let mut generator =
-			SimpleAlternationMapGenerator::new(&mut self.engine, &["dirt", "grass", "sand"])?;
-		// let mut generator = civ::maps::NoiseMap::new(&self.engine.tile_types);
-		let name = self.state.visible_map.clone();
-		self.engine
-			.generate_map(&mut self.state, name, 6, 6, true, &mut generator)?;
+		let game_setup: GameSetupConfig = self.state.load_config("game")?;
+		self.state.session.set_turn_limit(
+			game_setup
+				.turn_timer
+				.map(|seconds| Duration::from_secs(seconds.get())),
+		);
+		self.civ
+			.load_rules(&mut self.state, &game_setup.speed, &game_setup.difficulty)?;
+		info!(
+			"Playing at {} speed on {} difficulty",
+			self.civ.rules.speed.name, self.civ.rules.difficulty.name
+		);
+		self.civ.load_wildlife(&mut self.state, &self.engine)?;
+		self.civ.load_goodies(&mut self.state, &self.engine)?;
+		self.civ.load_influence(&mut self.state, &self.engine)?;
+		self.civ.load_unit_ai(&mut self.state)?;
+		self.civ.load_personas(&mut self.state)?;
+
+		match &args.scenario {
+			Some(path) => {
+				let (name, objects) = world::generate_scenario_map(
+					&mut self.engine,
+					&mut self.state,
+					Path::new(path),
+				)
+				.with_context(|| format!("failed loading scenario `{}`", path))?;
+				self.spawn_objects(&name, &objects)?;
+				info!("Starting on scenario `{}`", name);
+				self.state.visible_map = name;
+				let intro = Path::new(path).with_extension("intro.ron");
+				match self.state.load_sequence(&intro) {
+					Ok(sequence) => self.state.sequence = Some(SequencePlayer::new(sequence)),
+					Err(error) => debug!("No scenario intro played: {:#}", error),
+				}
+				let tutorial = Path::new(path).with_extension("tutorial.ron");
+				match self.state.load_tutorial(&tutorial) {
+					Ok(tutorial) => self.state.tutorial = Some(TutorialRunner::new(tutorial)),
+					Err(error) => debug!("No scenario tutorial started: {:#}", error),
+				}
+			}
+			None => {
+				world::generate_start_maps(&mut self.engine, &mut self.state, args)?;
+				let huts = self
+					.civ
+					.place_goody_huts(&mut self.engine, world::START_MAP)?;
+				debug!("Placed {} goody huts", huts);
+				self.spawn_test_stack()?;
+			}
+		}
 
+		if let Some(path) = &args.import_tiled {
+			self.import_tiled(Path::new(path))
+				.with_context(|| format!("failed importing Tiled map `{}`", path))?;
+		}
+
+		if let Some(path) = &args.replay {
+			world::replay_journal(&mut self.engine, &mut self.state, Path::new(path))
+				.with_context(|| format!("failed replaying journal `{}`", path))?;
+		}
+
+		Ok(())
+	}
+
+	/// A small stack to try grouping into an army with.
+	fn spawn_test_stack(&mut self) -> anyhow::Result<()> {
 		let coord = MapCoord {
 			map: self
 				.engine
@@ -222,25 +959,137 @@ impl Game {
 		let civ = &mut self.civ;
 		ecs.run(
 			|mut all_storages: AllStoragesViewMut| -> anyhow::Result<()> {
-				let entity =
-					civ.create_entity_from_template(state, "test_unit", &mut all_storages)?;
-				engine.move_entity_to_coord(
-					entity,
-					coord,
-					all_storages.try_borrow()?,
-					all_storages.try_borrow()?,
-				)?;
+				for _ in 0..3 {
+					let entity = civ.create_entity_from_template(
+						state,
+						engine,
+						"test_unit",
+						&mut all_storages,
+					)?;
+					engine.move_entity_to_coord(
+						entity,
+						coord,
+						all_storages.try_borrow()?,
+						all_storages.try_borrow()?,
+					)?;
+				}
 				Ok(())
 			},
-		)?;
+		)
+	}
 
+	/// Adds the Tiled JSON map at `path`, in the resources or user data, as a map named after the
+	/// file and spawns its objects from the entity templates named by their classes.
+	///
+	/// The corners a Tiled map doesn't cover are filled with the first tile type.
+	pub fn import_tiled(&mut self, path: &Path) -> anyhow::Result<()> {
+		let (name, objects) = world::generate_tiled_map(&mut self.engine, &mut self.state, path)?;
+		self.spawn_objects(&name, &objects)?;
+		info!(
+			"Imported Tiled map `{}` with {} objects",
+			name,
+			objects.len()
+		);
 		Ok(())
 	}
 
+	/// Spawns Tiled map objects on the map `name` from the entity templates named by their classes.
+	fn spawn_objects(&mut self, name: &str, objects: &[TiledObject]) -> anyhow::Result<()> {
+		let map = self
+			.engine
+			.maps
+			.get_index_of(name)
+			.context("imported map is missing")?;
+
+		let state = &mut self.state;
+		let engine = &mut self.engine;
+		let civ = &mut self.civ;
+		self.ecs.run(
+			|mut all_storages: AllStoragesViewMut| -> anyhow::Result<()> {
+				for object in objects {
+					let entity = civ
+						.create_entity_from_template(
+							state,
+							engine,
+							&object.template,
+							&mut all_storages,
+						)
+						.with_context(|| format!("failed spawning object `{}`", object.name))?;
+					let coord = MapCoord {
+						map,
+						coord: object.coord,
+					};
+					engine.move_entity_to_coord(
+						entity,
+						coord,
+						all_storages.try_borrow()?,
+						all_storages.try_borrow()?,
+					)?;
+				}
+				Ok(())
+			},
+		)
+	}
+
+	/// Writes `panic_report`, the recent journal and a dump of the game state to a new folder under
+	/// `crash/` in the user data directory, returning the folder's full path.
+	///
+	/// Called after a panic so the state may be half updated, each part is attempted even if an
+	/// earlier one fails.
+	pub fn write_crash_report(&mut self, panic_report: &str) -> anyhow::Result<PathBuf> {
+		use std::io::Write;
+		let folder = crash::report_folder();
+		let journal = self.engine.journal.recent().collect::<Vec<_>>().join("\n");
+		let files = [
+			("panic.txt", panic_report.to_owned()),
+			("journal.jsonl", journal),
+			("state.txt", self.emergency_state_dump()),
+		];
+		let mut failures = Vec::new();
+		for (name, contents) in files.iter() {
+			let written = self
+				.state
+				.write(&folder.join(name), WriteMode::Truncate)
+				.map_err(anyhow::Error::from)
+				.and_then(|mut file| Ok(file.write_all(contents.as_bytes())?));
+			if let Err(e) = written {
+				failures.push(format!("{}: {:?}", name, e));
+			}
+		}
+		let path = self.state.user_data.join(&folder);
+		anyhow::ensure!(
+			failures.len() < files.len(),
+			"failed writing every part of the crash report to `{}`: {}",
+			path.display(),
+			failures.join(", ")
+		);
+		for failure in failures {
+			error!("Failed writing part of the crash report, {}", failure);
+		}
+		Ok(path)
+	}
+
+	/// There is no save format yet, so this records what is needed to recreate the session by hand.
+	fn emergency_state_dump(&self) -> String {
+		let mut dump = format!(
+			"visible map: {}\ntick: {}\nview center: {}, {}\nzoom: {} tiles\n",
+			self.state.visible_map,
+			self.state.clock.current_tick(),
+			self.state.view_center.x,
+			self.state.view_center.y,
+			self.state.screen_tiles,
+		);
+		dump.push_str(&world::maps_text(&self.engine));
+		dump
+	}
+
 	pub fn run(&mut self) -> anyhow::Result<()> {
 		while self.state.ctx.continuing {
 			self.run_once()?;
 		}
+		if let Err(error) = self.state.record_game(&self.engine.schemas) {
+			warn!("Failed recording the game in the statistics: {:#}", error);
+		}
 
 		Ok(())
 	}
@@ -253,7 +1102,9 @@ impl Game {
 		state.ctx.timer_context.tick();
 		events_loop.poll_events(|event| {
 			state.ctx.process_event(&event);
-			state.dispatch_event(ecs, engine, event).unwrap();
+			if let Err(error) = state.dispatch_event(ecs, engine, event) {
+				state.show_error(&error);
+			}
 		});
 		// Handle gamepad events if necessary.
 		// Yeah okay, ggez has this entirely borked behind private...
@@ -275,97 +1126,780 @@ impl Game {
 		// 		}
 		// 	}
 		// }
+		let last_tick = self.state.clock.current_tick();
 		self.state.update(&mut self.ecs, &mut self.engine)?;
+		for tick in last_tick + 1..=self.state.clock.current_tick() {
+			self.step_wildlife(tick)?;
+			self.step_influence(tick)?;
+			self.refresh_danger(tick)?;
+		}
+		self.discover_goodies()?;
+		self.refresh_heatmap();
+		if let Some(request) = self.state.take_template_reload() {
+			self.reload_templates(request)?;
+		}
+		if let Some((entity, path)) = self.state.template_export.take() {
+			self.export_template(entity, &path);
+		}
+		if let Some(request) = self.state.unit_ai_request.take() {
+			self.plan_unit(request);
+		}
+		if let Some(request) = self.state.ai_turn_request.take() {
+			self.start_ai_turn(request);
+		}
+		self.step_ai_turn();
+		self.check_memory()?;
+		self.state.finish_jobs();
 		self.state.draw(&mut self.ecs, &mut self.engine)?;
 
 		Ok(())
 	}
-}
 
-impl GameState {
-	fn new(mut ctx: Context) -> GameState {
-		let tiles_atlas = MultiAtlasBuilder::new(1, 1)
-			.generate(&mut |_width, _height, _data| {
-				Ok(graphics::Image::solid(&mut ctx, 1, graphics::WHITE)?)
-			})
-			.unwrap();
-		let entity_atlas = MultiAtlasBuilder::new(1, 1)
-			.generate(&mut |_width, _height, _data| {
-				Ok(graphics::Image::solid(&mut ctx, 1, graphics::WHITE)?)
-			})
-			.unwrap();
-		GameState {
-			ctx,
-			visible_map: "world0".to_owned(),
-			screen_tiles: 2.0,
-			zoom: 2.0,
-			view_center: na::Point2::from([0.0, 0.0]),
-			screen_size: dpi::LogicalSize {
-				width: 1.0,
-				height: 1.0,
-			},
-			aspect_ratio: 1.0,
-			tiles_atlas,
-			tiles_meshes: vec![],
-			tiles_drawable: vec![],
-			entity_spritebatches: vec![],
-			entity_atlas,
-			selected: None,
-			selected_mesh: None,
-			click_leeway: 4.0,
-			mouse_buttons_clicked: HashMap::new(),
-			mouse_last_position: [0.0, 0.0].into(),
-		}
+	/// About how much memory the maps, the ECS storages, the atlases and the caches use. Only
+	/// registered components are counted for the ECS.
+	fn memory_report(&mut self) -> anyhow::Result<MemoryReport> {
+		let mut report = MemoryReport::new();
+		let maps: usize = self
+			.engine
+			.maps
+			.values()
+			.map(|tile_map| tile_map.memory_usage())
+			.sum();
+		report.add("maps", maps);
+		let components = self
+			.ecs
+			.run(|all_storages: AllStoragesViewMut| registered_components_memory(&all_storages))?;
+		report.add("ecs", components.iter().map(|(_name, bytes)| *bytes).sum());
+		self.state.memory_usage(&mut report);
+		Ok(report)
 	}
 
-	pub fn setup(&mut self, engine: &mut Engine<GameState>) -> anyhow::Result<()> {
-		self.tiles_drawable.clear();
-		self.tiles_drawable
-			.reserve(engine.tile_types.tile_types.len());
-		let mut tile_atlas_builder = MultiAtlasBuilder::new(2048, 2048);
-		for name in engine.tile_types.tile_types.values().map(|t| &t.name) {
-			let ctx = &mut self.ctx;
-			let id = tile_atlas_builder.get_or_create_with(name, || {
-				use std::io::Read;
-				let mut path = PathBuf::from("/tiles");
-				path.push(format!("{}.png", name));
+	/// Posts the memory report when `/mem` asked for it, and warns about subsystems over their
+	/// budget when a check is due.
+	fn check_memory(&mut self) -> anyhow::Result<()> {
+		let requested = std::mem::replace(&mut self.state.memory_request, false);
+		let delta = ggez::timer::delta(&self.state.ctx).as_secs_f32();
+		let due = self.state.memory.tick(delta);
+		if !requested && !due {
+			return Ok(());
+		}
+		let report = self.memory_report()?;
+		if requested {
+			for line in report.to_string().lines() {
+				info!("Memory {}", line);
+				self.state.chat.post("system", line);
+			}
+		}
+		for over in self.state.memory.check(&report) {
+			let message = format!(
+				"{} is using {} of memory, over its budget of {}",
+				over.name,
+				format_bytes(over.bytes),
+				format_bytes(over.budget)
+			);
+			warn!("{}", message);
+			self.state.chat.post("system", message);
+		}
+		Ok(())
+	}
 
-				let mut buf = Vec::new();
-				let mut reader = ggez::filesystem::open(ctx, path)?;
-				let _ = reader.read_to_end(&mut buf)?;
-				let image = image::load_from_memory(&buf)?.to_rgba();
-				let width = image.width() as u16;
-				let height = image.height() as u16;
-				let rgba = image.into_raw();
+	/// Spawns and despawns the wildlife due on simulation `tick`, forgetting the selection of any
+	/// that left.
+	fn step_wildlife(&mut self, tick: u64) -> anyhow::Result<()> {
+		let state = &mut self.state;
+		let engine = &mut self.engine;
+		let civ = &mut self.civ;
+		let step = self.ecs.run(|mut all_storages: AllStoragesViewMut| {
+			civ.step_wildlife(state, engine, tick, &mut all_storages)
+		})?;
+		if step.spawned.is_empty() && step.despawned.is_empty() {
+			return Ok(());
+		}
+		debug!(
+			"Wildlife on tick {}: {} spawned, {} left",
+			tick,
+			step.spawned.len(),
+			step.despawned.len()
+		);
+		if let Some(selected) = self.state.selected {
+			if step.despawned.contains(&selected) {
+				self.state.selected = None;
+			}
+		}
+		self.state
+			.box_selected
+			.retain(|entity| !step.despawned.contains(entity));
+		Ok(())
+	}
 
-				Ok((width, height, rgba))
+	/// Spreads the religions and ideologies due on simulation `tick`.
+	fn step_influence(&mut self, tick: u64) -> anyhow::Result<()> {
+		let engine = &self.engine;
+		let civ = &mut self.civ;
+		self.ecs.run(|mut all_storages: AllStoragesViewMut| {
+			civ.step_influence(engine, tick, &mut all_storages)
+		})
+	}
+
+	/// Copies the fields the visible map has for the heatmap overlay to pick from, and the values
+	/// of the one it shows.
+	fn refresh_heatmap(&mut self) {
+		let heatmap = &mut self.state.heatmap;
+		let map = match self.engine.maps.get_index_of(&self.state.visible_map) {
+			Some(map) => map,
+			None => return,
+		};
+		let available = self.civ.field_names(map);
+		if heatmap.available != available {
+			heatmap.available = available;
+		}
+		let values = match heatmap.field() {
+			Some(name) => self.civ.field_values(map, name),
+			None => return,
+		};
+		if let Some(values) = values {
+			heatmap.set_values(HeatmapValues {
+				map,
+				values: values.iter().map(|value| value.to_f32()).collect(),
+			});
+		}
+	}
+
+	fn refresh_danger(&mut self, tick: u64) -> anyhow::Result<()> {
+		let engine = &self.engine;
+		let civ = &mut self.civ;
+		self.ecs.run(|mut all_storages: AllStoragesViewMut| {
+			civ.refresh_danger(engine, tick, &mut all_storages)
+		})
+	}
+
+	/// Opens the goody huts that units arrived on, announcing what was in them.
+	fn discover_goodies(&mut self) -> anyhow::Result<()> {
+		for (entity, at) in std::mem::take(&mut self.state.arrived) {
+			let state = &mut self.state;
+			let engine = &mut self.engine;
+			let civ = &mut self.civ;
+			let found = self.ecs.run(|mut all_storages: AllStoragesViewMut| {
+				civ.discover_goody(state, engine, entity, at, &mut all_storages)
 			})?;
+			if let Some(found) = found {
+				self.announce_goody(&found);
+			}
+		}
+		Ok(())
+	}
+
+	fn announce_goody(&mut self, found: &GoodyFound) {
+		let message = match &found.reward {
+			GoodyReward::Gold(gold) => format!(
+				"the ruins held {} gold, there is {} now",
+				gold, self.civ.stockpile.gold
+			),
+			GoodyReward::Research(research) => {
+				format!("the ruins held old writings worth {} research", research)
+			}
+			GoodyReward::Unit(template) => format!("a {} from the ruins joins up", template),
+		};
+		match MapAddress::of(&self.engine.maps, found.at) {
+			Some(address) => info!("Goody hut at {}: {}", address, message),
+			None => info!("Goody hut at {:?}: {}", found.at.coord, message),
+		}
+		self.state.chat.post("system", message);
+		self.state
+			.animations
+			.play_discovery(found.at.map, self.state.hex.to_linear(found.at.coord));
+		self.state.tutorial_event(TutorialEvent::GoodyFound);
+	}
+
+	fn export_template(&mut self, entity: EntityId, path: &Path) {
+		let state = &mut self.state;
+		let civ = &self.civ;
+		let written = self.ecs.run(|all_storages: AllStoragesViewMut| {
+			civ.write_entity_template(state, path, entity, &all_storages)
+		});
+		let message = match written {
+			Ok(()) => format!("exported to {}", path.display()),
+			Err(error) => format!("export failed: {:#}", error),
+		};
+		self.state.chat.post("system", message);
+	}
+
+	/// Shows in the chat what the AI would have the unit do and why, ordering it there if asked
+	/// to follow the plan.
+	fn plan_unit(&mut self, request: UnitAiRequest) {
+		if !self.has_persona(request.persona.as_deref()) {
+			return;
+		}
+		let engine = &self.engine;
+		let civ = &self.civ;
+		let entity = request.entity;
+		let persona = request.persona.as_deref();
+		let plan = self.ecs.run(|all_storages: AllStoragesViewMut| {
+			civ.plan_unit(engine, entity, persona, &all_storages)
+		});
+		let plan = match plan {
+			Ok(Some(plan)) => plan,
+			Ok(None) => {
+				self.state
+					.chat
+					.post("system", "the AI has nothing for it to do");
+				return;
+			}
+			Err(error) => {
+				self.state
+					.chat
+					.post("system", format!("the AI failed: {:#}", error));
+				return;
+			}
+		};
+		let scores: Vec<String> = plan
+			.scores
+			.iter()
+			.map(|(behavior, score)| format!("{} {:.2}", behavior.name(), score.to_f32()))
+			.collect();
+		let destination = match plan.to {
+			Some(to) => format!("to {},{}", to.coord.q(), to.coord.r()),
+			None => "where it is".to_owned(),
+		};
+		self.state.chat.post(
+			"system",
+			format!(
+				"{} {} ({})",
+				plan.behavior.name(),
+				destination,
+				scores.join(", ")
+			),
+		);
+		if let (true, Some(to)) = (request.follow, plan.to) {
+			let order = MoveOrder::along(to.map, &plan.path);
+			self.ecs
+				.run(|entities: EntitiesView, mut orders: ViewMut<MoveOrder>| {
+					entities.add_component(&mut orders, order, entity);
+				});
+		}
+	}
+
+	/// Whether there is an AI persona `name`, saying which there are in the chat if not. No
+	/// persona is always fine.
+	fn has_persona(&mut self, name: Option<&str>) -> bool {
+		let name = match name {
+			Some(name) => name,
+			None => return true,
+		};
+		let personas: Vec<&str> = self
+			.civ
+			.personas()
+			.iter()
+			.map(|persona| persona.name.as_str())
+			.collect();
+		if personas.contains(&name) {
+			return true;
+		}
+		self.state.chat.post(
+			"system",
+			format!(
+				"no AI persona named `{}`, there is: {}",
+				name,
+				personas.join(", ")
+			),
+		);
+		false
+	}
+
+	/// Starts an AI turn planning and ordering every unit, run a slice a frame by
+	/// `step_ai_turn`. There are no AI players yet, so it runs the player's units as if an AI had
+	/// them.
+	fn start_ai_turn(&mut self, request: AiTurnRequest) {
+		if self.ai_turn.is_some() {
+			self.state
+				.chat
+				.post("system", "an AI turn is already running");
+			return;
+		}
+		if !self.has_persona(request.persona.as_deref()) {
+			return;
+		}
+		let civ = &self.civ;
+		let units = self
+			.ecs
+			.run(|all_storages: AllStoragesViewMut| civ.ai_units(&all_storages));
+		match units {
+			Ok(units) => {
+				self.ai_turn = Some(AiTurn {
+					units: SlicedWork::new(units, AI_TURN_MAX_FRAMES),
+					persona: request.persona,
+					ordered: 0,
+				})
+			}
+			Err(error) => self
+				.state
+				.chat
+				.post("system", format!("the AI failed: {:#}", error)),
+		}
+	}
+
+	/// Runs the AI turn for this frame's budget, saying in the chat how it went once it's over.
+	fn step_ai_turn(&mut self) {
+		let AiTurn {
+			units,
+			persona,
+			ordered,
+		} = match &mut self.ai_turn {
+			Some(turn) => turn,
+			None => return,
+		};
+		let engine = &self.engine;
+		let civ = &self.civ;
+		let persona = persona.as_deref();
+		let started = Instant::now();
+		let progress = self.ecs.run(|all_storages: AllStoragesViewMut| {
+			units.step(
+				|| started.elapsed() >= AI_FRAME_BUDGET,
+				|entity| -> anyhow::Result<()> {
+					let plan = civ.plan_unit(engine, entity, persona, &all_storages)?;
+					if let Some(UnitPlan {
+						to: Some(to), path, ..
+					}) = plan
+					{
+						let entities = all_storages.try_borrow::<EntitiesView>()?;
+						let mut orders = all_storages.try_borrow::<ViewMut<MoveOrder>>()?;
+						let order = MoveOrder::along(to.map, &path);
+						entities.add_component(&mut orders, order, entity);
+						*ordered += 1;
+					}
+					Ok(())
+				},
+			)
+		});
+		let message = match progress {
+			Ok(WorkProgress::Working { done, total }) => {
+				self.state.waiting = Some((done, total));
+				return;
+			}
+			Ok(WorkProgress::Finished { done }) => {
+				format!("AI turn over, {} units of {} given orders", ordered, done)
+			}
+			Ok(WorkProgress::Capped { done, skipped }) => {
+				warn!(
+					"The AI turn ran out of time after {} units, skipping {}",
+					done, skipped
+				);
+				format!(
+					"AI turn ran out of time, {} units of {} given orders and {} skipped",
+					ordered, done, skipped
+				)
+			}
+			Err(error) => format!("the AI failed: {:#}", error),
+		};
+		self.ai_turn = None;
+		self.state.waiting = None;
+		self.state.chat.post("system", message);
+	}
+
+	/// Reloads the changed entity templates so they can be tuned without restarting, reporting
+	/// how it went in the chat.
+	fn reload_templates(&mut self, request: TemplateReloadRequest) -> anyhow::Result<()> {
+		let reload = self.civ.reload_templates(&mut self.state);
+		let chat = &mut self.state.chat;
+		for (name, error) in &reload.failed {
+			warn!("Failed reloading entity template `{}`: {:#}", name, error);
+			chat.post(
+				"system",
+				format!("template `{}` failed to reload: {:#}", name, error),
+			);
+		}
+		if reload.changed.is_empty() {
+			if request != TemplateReloadRequest::Watch && reload.failed.is_empty() {
+				chat.post("system", "no templates changed");
+			}
+			return Ok(());
+		}
+		if request == TemplateReloadRequest::Reload {
+			let names: Vec<&str> = reload
+				.changed
+				.iter()
+				.map(|patch| patch.template.as_str())
+				.collect();
+			chat.post(
+				"system",
+				format!("reloaded templates: {}", names.join(", ")),
+			);
+			return Ok(());
+		}
+		self.ecs.run(
+			|mut all_storages: AllStoragesViewMut| -> anyhow::Result<()> {
+				for patch in &reload.changed {
+					let patched = if patch.is_empty() {
+						0
+					} else {
+						patch.apply(&mut all_storages)?
+					};
+					chat.post(
+						"system",
+						format!(
+							"reloaded template `{}`, patched {} entities",
+							patch.template, patched
+						),
+					);
+				}
+				Ok(())
+			},
+		)
+	}
+}
+
+impl GameState {
+	fn new(mut ctx: Context, user_data: PathBuf) -> GameState {
+		let tiles_atlas = MultiAtlasBuilder::new(1, 1)
+			.generate(&mut |_width, _height, _data| {
+				Ok(graphics::Image::solid(&mut ctx, 1, graphics::WHITE)?)
+			})
+			.unwrap();
+		let entity_atlas = MultiAtlasBuilder::new(1, 1)
+			.generate(&mut |_width, _height, _data| {
+				Ok(graphics::Image::solid(&mut ctx, 1, graphics::WHITE)?)
+			})
+			.unwrap();
+		let ui_atlas = MultiAtlasBuilder::new(1, 1)
+			.generate(&mut |_width, _height, _data| {
+				Ok(graphics::Image::solid(&mut ctx, 1, graphics::WHITE)?)
+			})
+			.unwrap();
+		let window = graphics::window(&ctx);
+		let screen = ScreenMetrics::new(
+			window
+				.get_inner_size()
+				.unwrap_or_else(|| dpi::LogicalSize::new(1.0, 1.0)),
+			window.get_hidpi_factor(),
+		);
+		let mut state = GameState {
+			ctx,
+			visible_map: world::START_MAP.to_owned(),
+			screen_tiles: 2.0,
+			zoom: 2.0,
+			view_center: na::Point2::from([0.0, 0.0]),
+			hex: HexOrientation::default(),
+			screen,
+			aspect_ratio: screen.aspect_ratio(),
+			tiles_atlas,
+			tiles_meshes: vec![],
+			tiles_positions: vec![],
+			tiles_meshes_strategic: false,
+			tiles_mip_level: 0,
+			tiles_texels_per_unit: 1.0,
+			ambient_background: AmbientBackground::new(),
+			soundscape: Soundscape::new(),
+			music: MusicManager::new(),
+			light_tint: graphics::WHITE,
+			lod: LodConfig::default(),
+			strategic_icon_mesh: None,
+			tiles_drawable: vec![],
+			decorations_drawable: vec![],
+			entity_spritebatches: vec![],
+			entity_mip_level: 0,
+			entity_texels_per_tile: 1.0,
+			stack_badges: vec![],
+			entity_atlas,
+			ui_atlas,
+			panel_skin: None,
+			selected: None,
+			selected_mesh: None,
+			input: InputConfig::default(),
+			video: VideoConfig::default(),
+			audio: AudioConfig::default(),
+			settings: None,
+			interaction: Interaction::new(&InputConfig::default()),
+			box_selected: vec![],
+			arrived: vec![],
+			paint_brush: None,
+			context_menu: None,
+			inspector: None,
+			mouse_logical_position: dpi::LogicalPosition::new(0.0, 0.0),
+			chat: ChatBox::new(64),
+			clock: SimulationClock::new(Duration::from_millis(100)),
+			view_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+			movement: MovementConfig::default(),
+			effects: ScreenEffects::new(EffectsConfig::default()),
+			animations: AnimationTimeline::new(),
+			labels: LabelLayer::new(),
+			text: TextStyles::new(),
+			pins: MapPins::new(),
+			pin_mesh: None,
+			grid: GridOverlay::new(GridStyle::default()),
+			heatmap: HeatmapOverlay::new(),
+			window_title: branding::GAME_TITLE.to_owned(),
+			cursor: MouseCursor::Default,
+			accessibility: AccessibilityConfig::default(),
+			camera: CameraConfig::default(),
+			layers: RenderLayers::new(),
+			minimap: RenderTarget::new(),
+			show_minimap: false,
+			template_reload: None,
+			template_export: None,
+			unit_ai_request: None,
+			ai_turn_request: None,
+			jobs: JobPool::new("job", JOB_THREADS).unwrap(),
+			waiting: None,
+			session: SessionStats::new(None),
+			territory: HashMap::new(),
+			template_watch: None,
+			memory_request: false,
+			memory: MemoryWatch::new(MemoryConfig::default()),
+			statistics: PlayerStatistics::default(),
+			show_hall_of_fame: false,
+			modals: ModalQueue::default(),
+			unsaved_maps: BTreeSet::new(),
+			sequence: None,
+			camera_glide: None,
+			tutorial: None,
+			user_data,
+			config_dir: None,
+		};
+		state.register_layers();
+		state
+	}
+
+	/// Registers the built in drawing, each part in the layer it belongs to.
+	fn register_layers(&mut self) {
+		let layers = &mut self.layers;
+		layers.register(RenderLayer::Background, GameState::draw_ambient);
+		layers.register(RenderLayer::Terrain, GameState::draw_map);
+		layers.register(RenderLayer::Overlays, GameState::draw_heatmap);
+		layers.register(RenderLayer::Overlays, GameState::draw_grid);
+		layers.register(RenderLayer::Decals, GameState::draw_corpses);
+		layers.register(RenderLayer::Entities, GameState::draw_entities);
+		layers.register(RenderLayer::Effects, GameState::draw_selection);
+		layers.register(RenderLayer::Effects, GameState::draw_discoveries);
+		layers.register(RenderLayer::Effects, GameState::draw_pins);
+		layers.register(RenderLayer::Effects, GameState::draw_orders);
+		layers.register(RenderLayer::Effects, GameState::draw_tutorial_tile);
+		layers.register(RenderLayer::Ui, GameState::draw_labels);
+		layers.register(RenderLayer::Ui, GameState::draw_minimap);
+		layers.register(RenderLayer::Ui, GameState::draw_heatmap_legend);
+		layers.register(RenderLayer::Ui, GameState::draw_waiting);
+		layers.register(RenderLayer::Ui, GameState::draw_session);
+		layers.register(RenderLayer::Ui, GameState::draw_interaction);
+		layers.register(RenderLayer::Ui, GameState::draw_flash);
+		layers.register(RenderLayer::Ui, GameState::draw_context_menu);
+		layers.register(RenderLayer::Ui, GameState::draw_inspector);
+		layers.register(RenderLayer::Ui, GameState::draw_hall_of_fame);
+		layers.register(RenderLayer::Ui, GameState::draw_tutorial);
+		layers.register(RenderLayer::Ui, GameState::draw_sequence);
+		layers.register(RenderLayer::Ui, GameState::draw_chat);
+		layers.register(RenderLayer::Ui, GameState::draw_settings);
+		layers.register(RenderLayer::Ui, GameState::draw_modal);
+	}
 
+	/// Builds the drawables and atlas of every tile type and decoration, again whenever tile types
+	/// are registered after setup. The atlas pre-built by `--pack-assets` is used if it has all of
+	/// their images.
+	fn setup_tiles_atlas(&mut self, engine: &Engine<GameState>) -> anyhow::Result<()> {
+		let mut tiles = Vec::with_capacity(engine.tile_types.tile_types.len());
+		for name in engine.tile_types.tile_types.values().map(|t| &t.name) {
 			let mut path = PathBuf::from("/tiles");
 			path.push(format!("{}.png.ron", name));
-			let info = match ggez::filesystem::open(ctx, path) {
+			let info = match ggez::filesystem::open(&mut self.ctx, path) {
 				Err(_e) => {
 					debug!(
 						"Unable to load ron data for tile of `{}.png.ron`, using defaults",
 						name
 					);
-					TileDrawableInfo {
-						bounds: Rect::new(-0.5, -0.5833333, 1.0, 1.1666666),
-						color: Color::new(1.0, 1.0, 1.0, 1.0),
-					}
+					TileDrawableInfo::default()
 				}
 				Ok(file) => ron::de::from_reader::<_, TileDrawableInfo>(file)?,
 			};
+			let images = tile_images(name, &info);
+			anyhow::ensure!(
+				!images.is_empty(),
+				"tile `{}` has variant weights but they are all zero",
+				name
+			);
+			tiles.push((info, images));
+		}
+		let decorations: Vec<String> = engine
+			.decoration_types
+			.decoration_types
+			.values()
+			.map(|d| decoration_image(&d.name))
+			.collect();
+		let images: Vec<&str> = tiles
+			.iter()
+			.flat_map(|(_info, images)| images.iter().map(|(image, _weight)| image.as_str()))
+			.chain(decorations.iter().map(String::as_str))
+			.collect();
+
+		// For the strategic view colors
+		let mut image_colors: HashMap<String, Color> = HashMap::new();
+		let tiles_filter = self.video.tiles_filter;
+		let tiles_atlas = match load_packed_tiles_atlas(&mut self.ctx, &images) {
+			Some((packed, pages)) => {
+				let ctx = &mut self.ctx;
+				let tiles_atlas = MultiAtlas::from_packed(
+					&packed,
+					&pages,
+					tiles_filter.mip_levels as usize,
+					&mut |width, height, rgba| {
+						tiles_atlas_image(ctx, tiles_filter, width, height, rgba)
+					},
+				)?;
+				for entry in &packed.entries {
+					let (width, height, page) = &pages[entry.page];
+					image_colors.insert(
+						entry.name.clone(),
+						average_color(&entry.pixels(*width, *height, page)),
+					);
+				}
+				tiles_atlas
+			}
+			None => {
+				// Decoding is the slow part so it is spread over threads, the files are read here
+				// as ggez's filesystem needs the context
+				let mut decoder = JobPool::new("atlas decoding", JOB_THREADS)?;
+				let mut decoding = HashMap::with_capacity(images.len());
+				for &image in &images {
+					if !decoding.contains_key(image) {
+						let path = atlas_image_path(image);
+						let job = read_bytes(&mut self.ctx, &path)
+							.map(|bytes| decoder.spawn(move || decode_rgba(&bytes)));
+						decoding.insert(image, (path, job));
+					}
+				}
+				let mut tile_atlas_builder =
+					MultiAtlasBuilder::new(TILES_ATLAS_SIZE, TILES_ATLAS_SIZE);
+				for &image in &images {
+					let image_colors = &mut image_colors;
+					let decoding = &mut decoding;
+					let decoder = &mut decoder;
+					tile_atlas_builder.get_or_create_with(image, || {
+						let (path, job) = decoding
+							.remove(image)
+							.with_context(|| format!("`{}` was never decoded", image))?;
+						let name = path
+							.file_stem()
+							.map_or_else(|| image.into(), |stem| stem.to_string_lossy());
+						let loaded = job.and_then(|job| Ok(decoder.wait_for(job)??));
+						let (width, height, rgba) = rgba_or_placeholder(loaded, &path, &name);
+						image_colors.insert(image.to_owned(), average_color(&rgba));
+						Ok((width, height, rgba))
+					})?;
+				}
+				let ctx = &mut self.ctx;
+				tile_atlas_builder.generate_with_mips(
+					tiles_filter.mip_levels as usize,
+					&mut |width, height, rgba| {
+						tiles_atlas_image(ctx, tiles_filter, width, height, rgba)
+					},
+				)?
+			}
+		};
 
-			self.tiles_drawable
-				.push(TilesDrawable { atlas_id: id, info })
+		let atlas_id = |image: &str| -> anyhow::Result<AtlasId<MapAtlas>> {
+			Ok(tiles_atlas
+				.get_entry_by_name(image)
+				.with_context(|| format!("tiles atlas is missing `{}`", image))?
+				.get_id())
+		};
+		self.tiles_drawable.clear();
+		self.tiles_drawable.reserve(tiles.len());
+		for (info, images) in tiles {
+			let mut variants = Vec::with_capacity(images.len());
+			for (image, weight) in &images {
+				variants.push((atlas_id(image)?, *weight));
+			}
+			// The strategic view color comes from the first variant, they should be close anyway
+			let image_color = image_colors
+				.get(&images[0].0)
+				.copied()
+				.unwrap_or(graphics::WHITE);
+			let strategic_color = info.strategic_color.unwrap_or_else(|| {
+				Color::new(
+					image_color.r * info.color.r,
+					image_color.g * info.color.g,
+					image_color.b * info.color.b,
+					1.0,
+				)
+			});
+			self.tiles_drawable.push(TilesDrawable {
+				total_weight: variants.iter().map(|&(_id, weight)| weight).sum(),
+				variants,
+				info,
+				strategic_color,
+			})
 		}
-		self.tiles_atlas = tile_atlas_builder.generate(&mut |width, height, rgba| {
-			let mut image = graphics::Image::from_rgba8(&mut self.ctx, width, height, rgba)
-				.context("failed converting tiles atlas texture")?;
-			image.set_filter(FilterMode::Nearest);
-			Ok(image)
-		})?;
+		self.decorations_drawable.clear();
+		for image in &decorations {
+			self.decorations_drawable.push(atlas_id(image)?);
+		}
+		self.tiles_atlas = tiles_atlas;
+		let tiles_atlas = &self.tiles_atlas;
+		self.tiles_texels_per_unit = self
+			.tiles_drawable
+			.iter()
+			.flat_map(|drawable| {
+				let bounds = drawable.info.bounds;
+				drawable.variants.iter().map(move |&(id, _weight)| {
+					tiles_atlas.get_entry(id).height_px() as f32 / bounds.h
+				})
+			})
+			.fold(1.0, f32::max);
+		self.tiles_mip_level = 0;
+		self.tiles_meshes.clear();
+		Ok(())
+	}
+
+	pub fn setup(&mut self, engine: &mut Engine<GameState>) -> anyhow::Result<()> {
+		// Loaded first as it says how the atlases are sampled
+		self.video = self.load_config("video")?;
+		self.setup_tiles_atlas(engine)?;
+		self.setup_ui_atlas()?;
+		self.setup_text()?;
+		self.ambient_background
+			.load(&mut self.ctx, engine.map_ambients.ambients.values())?;
+		let zones: Vec<AmbientZone> =
+			match ggez::filesystem::open(&mut self.ctx, "/sounds/ambient.ron") {
+				Err(_e) => {
+					debug!(
+						"No `/sounds/ambient.ron` in the resources, playing without ambient sounds"
+					);
+					vec![]
+				}
+				Ok(file) => {
+					ron::de::from_reader(file).context("failed parsing `/sounds/ambient.ron`")?
+				}
+			};
+		self.soundscape.load(
+			&mut self.ctx,
+			zones,
+			engine.tile_types.tile_types.values().map(|t| &t.name),
+		);
+		let playlists: MusicPlaylists =
+			match ggez::filesystem::open(&mut self.ctx, "/music/playlists.ron") {
+				Err(_e) => {
+					debug!("No `/music/playlists.ron` in the resources, playing without music");
+					MusicPlaylists::default()
+				}
+				Ok(file) => {
+					ron::de::from_reader(file).context("failed parsing `/music/playlists.ron`")?
+				}
+			};
+		self.music.set_playlists(playlists);
+		let grid_style: GridStyle = self.load_config("grid")?;
+		self.grid = GridOverlay::new(grid_style);
+		self.input = self.load_config("input")?;
+		self.interaction = Interaction::new(&self.input);
+		self.apply_video()?;
+		self.audio = self.load_config("audio")?;
+		let effects: EffectsConfig = self.load_config("effects")?;
+		self.effects = ScreenEffects::new(effects);
+		let accessibility: AccessibilityConfig = self.load_config("accessibility")?;
+		self.set_high_contrast(accessibility.high_contrast);
+		self.camera = self.load_config("camera")?;
+		self.lod = self.load_config("lod")?;
+		self.movement = self.load_config("movement")?;
+		let memory: MemoryConfig = self.load_config("memory")?;
+		self.memory = MemoryWatch::new(memory);
+		self.statistics = self.load_statistics(&engine.schemas);
+
 		// let image = self.tiles_atlas.get_image_by_index(0).unwrap();
 		// image.encode(&mut self.ctx, graphics::ImageFormat::Png, "/tilemap0.png")?;
 
@@ -392,25 +1926,391 @@ impl GameState {
 				Ok((width, height, rgba))
 			})?;
 		}
-		self.entity_atlas = entity_atlas_builder.generate(&mut |width, height, rgba| {
+		let entities_filter = self.video.entities_filter;
+		self.entity_atlas = entity_atlas_builder.generate_with_mips(
+			entities_filter.mip_levels as usize,
+			&mut |width, height, rgba| {
+				let mut image = graphics::Image::from_rgba8(&mut self.ctx, width, height, rgba)
+					.context("failed converting entities atlas texture")?;
+				image.set_filter(entities_filter.filter.into());
+				Ok(image)
+			},
+		)?;
+		self.entity_texels_per_tile = self
+			.entity_atlas
+			.entries()
+			.map(|entry| entry.width_px().max(entry.height_px()) as f32)
+			.fold(1.0, f32::max);
+		self.entity_spritebatches.clear();
+		Ok(())
+	}
+
+	/// Loads `/config/<name>.ron` from the resources, falling back to the defaults if it's missing.
+	fn setup_ui_atlas(&mut self) -> anyhow::Result<()> {
+		let mut ui_atlas_builder = MultiAtlasBuilder::new(512, 512);
+		self.panel_skin = None;
+		if ggez::filesystem::exists(&mut self.ctx, "/ui/panel.png") {
+			let ctx = &mut self.ctx;
+			let id = ui_atlas_builder.get_or_create_with("panel", || {
+				use std::io::Read;
+				let mut buf = Vec::new();
+				let mut reader = ggez::filesystem::open(ctx, "/ui/panel.png")?;
+				let _ = reader.read_to_end(&mut buf)?;
+				let image = image::load_from_memory(&buf)?.to_rgba();
+				Ok((
+					image.width() as u16,
+					image.height() as u16,
+					image.into_raw(),
+				))
+			})?;
+			let info = match ggez::filesystem::open(ctx, "/ui/panel.png.ron") {
+				Err(_e) => UiImageInfo::default(),
+				Ok(file) => ron::de::from_reader::<_, UiImageInfo>(file)
+					.context("failed parsing `/ui/panel.png.ron`")?,
+			};
+			ui_atlas_builder.set_nine_slice(id, info.nine_slice);
+			self.panel_skin = Some(id);
+		} else {
+			debug!("No `/ui/panel.png` in the resources, drawing plain panels");
+		}
+		let ui_filter = self.video.ui_filter;
+		self.ui_atlas = ui_atlas_builder.generate(&mut |width, height, rgba| {
 			let mut image = graphics::Image::from_rgba8(&mut self.ctx, width, height, rgba)
-				.context("failed converting entities atlas texture")?;
-			image.set_filter(FilterMode::Nearest);
+				.context("failed converting ui atlas texture")?;
+			image.set_filter(ui_filter.into());
 			Ok(image)
 		})?;
 		Ok(())
 	}
 
-	fn dispatch_event(
+	/// Loads the fonts named in `config/text.ron` through the engine IO and applies its styles.
+	fn setup_text(&mut self) -> anyhow::Result<()> {
+		use std::io::Read;
+		let config: TextConfig = self.load_config("text")?;
+		let mut fonts = HashMap::with_capacity(config.fonts.len());
+		for (name, path) in config.fonts {
+			let mut bytes = Vec::new();
+			self.read(Path::new(&path))
+				.with_context(|| format!("failed opening font `{}` at `{}`", name, path))?
+				.read_to_end(&mut bytes)
+				.with_context(|| format!("failed reading font `{}` at `{}`", name, path))?;
+			let font = Font::new_glyph_font_bytes(&mut self.ctx, &bytes)
+				.with_context(|| format!("failed loading font `{}` at `{}`", name, path))?;
+			fonts.insert(name, font);
+		}
+		self.text.set(fonts, config.styles)
+	}
+
+	/// The statistics of the games played so far, ones that fail to load are logged and started
+	/// over rather than stopping the game.
+	fn load_statistics(&mut self, schemas: &Schemas) -> PlayerStatistics {
+		let path = PathBuf::from(format!("/{}", STATISTICS_PATH));
+		if !ggez::filesystem::exists(&self.ctx, &path) {
+			return PlayerStatistics::default();
+		}
+		match PlayerStatistics::load(self, schemas, Path::new(STATISTICS_PATH)) {
+			Ok(statistics) => statistics,
+			Err(error) => {
+				let error = anyhow::Error::from(error);
+				warn!(
+					"Failed loading `{}`, starting the statistics over: {:#}",
+					STATISTICS_PATH, error
+				);
+				self.modals.push(Modal::notice(
+					"Statistics lost",
+					format!(
+						"The statistics of past games couldn't be read so they start over: {:#}",
+						error
+					),
+				));
+				PlayerStatistics::default()
+			}
+		}
+	}
+
+	/// Counts the game being quit in the statistics kept across games and saves them. Nothing
+	/// decides who won yet, so every game counts as a defeat without a score, and a game quit
+	/// before its first turn ended isn't counted at all.
+	fn record_game(&mut self, schemas: &Schemas) -> anyhow::Result<()> {
+		let turns = self.session.turn_durations().len() as u32;
+		if turns == 0 {
+			return Ok(());
+		}
+		let place = self.statistics.record_game(GameOutcome {
+			victory: None,
+			score: 0,
+			turns,
+			played_as: "local".to_owned(),
+		});
+		if let Some(place) = place {
+			info!(
+				"The game made it into the best games at place {}",
+				place + 1
+			);
+		}
+		let statistics = self.statistics.clone();
+		statistics
+			.save(self, schemas, Path::new(STATISTICS_PATH))
+			.with_context(|| format!("failed writing `{}`", STATISTICS_PATH))
+	}
+
+	/// Loads `name` from the `--config` folder, or else from the settings saved in the user data
+	/// directory, or else from the configs in the resources, or else uses the defaults.
+	fn load_config<T: DeserializeOwned + Default>(&mut self, name: &str) -> anyhow::Result<T> {
+		if let Some(config_dir) = &self.config_dir {
+			let path = config_dir.join(format!("{}.ron", name));
+			if path.exists() {
+				let text = std::fs::read_to_string(&path)
+					.with_context(|| format!("failed reading config `{}`", path.display()))?;
+				return ron::de::from_str(&text)
+					.with_context(|| format!("failed parsing config `{}`", path.display()));
+			}
+		}
+		let saved = PathBuf::from(format!("/settings/{}.ron", name));
+		let path = if ggez::filesystem::exists(&self.ctx, &saved) {
+			saved
+		} else {
+			PathBuf::from(format!("/config/{}.ron", name))
+		};
+		match ggez::filesystem::open(&mut self.ctx, &path) {
+			Err(_e) => {
+				debug!("Unable to load config `{}`, using defaults", path.display());
+				Ok(T::default())
+			}
+			Ok(file) => ron::de::from_reader(file)
+				.with_context(|| format!("failed parsing config `{}`", path.display())),
+		}
+	}
+
+	/// Saves `value` to the user data directory where `load_config` prefers it over the resources.
+	fn save_config<T: Serialize>(&mut self, name: &str, value: &T) -> anyhow::Result<()> {
+		use std::io::Write;
+		let path = PathBuf::from(format!("settings/{}.ron", name));
+		let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+			.with_context(|| format!("failed serializing config `{}`", name))?;
+		self.write(&path, WriteMode::Truncate)
+			.with_context(|| format!("failed opening `{}` for writing", path.display()))?
+			.write_all(text.as_bytes())
+			.with_context(|| format!("failed writing `{}`", path.display()))?;
+		Ok(())
+	}
+
+	fn save_settings(&mut self) -> anyhow::Result<()> {
+		self.video.ui_scale = Some(self.screen.ui_scale());
+		let video = self.video.clone();
+		self.save_config("video", &video)?;
+		let audio = self.audio.clone();
+		self.save_config("audio", &audio)?;
+		let input = self.input.clone();
+		self.save_config("input", &input)?;
+		let accessibility = self.accessibility.clone();
+		self.save_config("accessibility", &accessibility)?;
+		let effects = self.effects.config.clone();
+		self.save_config("effects", &effects)?;
+		let camera = self.camera.clone();
+		self.save_config("camera", &camera)?;
+		let grid = self.grid.style.clone();
+		self.save_config("grid", &grid)?;
+		let lod = self.lod.clone();
+		self.save_config("lod", &lod)?;
+		Ok(())
+	}
+
+	fn apply_video(&mut self) -> anyhow::Result<()> {
+		let fullscreen_type = if self.video.fullscreen {
+			FullscreenType::Desktop
+		} else {
+			FullscreenType::Windowed
+		};
+		graphics::set_fullscreen(&mut self.ctx, fullscreen_type)
+			.context("failed changing the fullscreen mode")?;
+		if let Some(ui_scale) = self.video.ui_scale {
+			self.screen.set_ui_scale(ui_scale);
+		}
+		Ok(())
+	}
+
+	fn toggle_settings(&mut self) -> anyhow::Result<()> {
+		if self.settings.take().is_some() {
+			self.save_settings()?;
+			self.chat.post("system", "settings saved");
+		} else {
+			self.settings = Some(SettingsScreen::new());
+		}
+		Ok(())
+	}
+
+	fn setting_value(&self, setting: Setting) -> String {
+		let on_off = |on: bool| if on { "On" } else { "Off" }.to_owned();
+		let percent = |volume: f32| format!("{:.0}%", volume * 100.0);
+		match setting {
+			Setting::Fullscreen => on_off(self.video.fullscreen),
+			Setting::UiScale => format!("{:.2}", self.screen.ui_scale()),
+			Setting::HighContrast => on_off(self.accessibility.high_contrast),
+			Setting::ScreenEffects => on_off(self.effects.config.enabled),
+			Setting::MasterVolume => percent(self.audio.master_volume),
+			Setting::MusicVolume => percent(self.audio.music_volume),
+			Setting::EffectsVolume => percent(self.audio.effects_volume),
+			Setting::ClickLeeway => format!("{:.0}", self.input.click_leeway),
+			Setting::DoubleClickMs => self.input.double_click_ms.to_string(),
+			Setting::LongPressMs => self.input.long_press_ms.to_string(),
+			Setting::Key(action) => keybindings::key_name(self.input.keys.key_for(action)),
+			Setting::CameraPolicy => format!("{:?}", self.camera.policy),
+			Setting::CameraMargin => format!("{:.1}", self.camera.margin),
+			Setting::Grid => on_off(self.grid.style.enabled),
+		}
+	}
+
+	/// Changes `setting` by `step` increments and applies it right away.
+	fn adjust_setting(&mut self, setting: Setting, step: i32) -> anyhow::Result<()> {
+		let volume = |volume: f32| (volume + step as f32 * 0.1).max(0.0).min(1.0);
+		let millis = |ms: u64| (ms as i64 + step as i64 * 50).max(50).min(2000) as u64;
+		match setting {
+			Setting::Fullscreen => {
+				self.video.fullscreen = !self.video.fullscreen;
+				self.apply_video()?;
+			}
+			Setting::UiScale => self
+				.screen
+				.set_ui_scale(self.screen.ui_scale() + step as f32 * 0.1),
+			Setting::HighContrast => self.set_high_contrast(!self.accessibility.high_contrast),
+			Setting::ScreenEffects => self.effects.config.enabled = !self.effects.config.enabled,
+			Setting::MasterVolume => self.audio.master_volume = volume(self.audio.master_volume),
+			Setting::MusicVolume => self.audio.music_volume = volume(self.audio.music_volume),
+			Setting::EffectsVolume => self.audio.effects_volume = volume(self.audio.effects_volume),
+			Setting::ClickLeeway => {
+				self.input.click_leeway = (self.input.click_leeway + step as f32).max(0.0).min(32.0)
+			}
+			Setting::DoubleClickMs => {
+				self.input.double_click_ms = millis(self.input.double_click_ms);
+				self.interaction.double_click = Duration::from_millis(self.input.double_click_ms);
+			}
+			Setting::LongPressMs => {
+				self.input.long_press_ms = millis(self.input.long_press_ms);
+				self.interaction.long_press = Duration::from_millis(self.input.long_press_ms);
+			}
+			// Rebinding goes through press-to-bind instead
+			Setting::Key(_action) => (),
+			Setting::CameraPolicy => {
+				let policies = [
+					CameraPolicy::HardClamp,
+					CameraPolicy::Elastic,
+					CameraPolicy::Free,
+				];
+				let index = policies
+					.iter()
+					.position(|&policy| policy == self.camera.policy)
+					.unwrap_or(0) as i32;
+				let len = policies.len() as i32;
+				self.camera.policy = policies[(index + step).rem_euclid(len) as usize];
+			}
+			Setting::CameraMargin => {
+				self.camera.margin = (self.camera.margin + step as f32 * 0.5).max(0.0).min(10.0)
+			}
+			Setting::Grid => self.grid.toggle(),
+		}
+		Ok(())
+	}
+
+	fn settings_key_event(
+		&mut self,
+		keycode: VirtualKeyCode,
+		modifiers: ModifiersState,
+	) -> anyhow::Result<()> {
+		let input = match &mut self.settings {
+			Some(settings) => settings.key(keycode, modifiers.shift),
+			None => return Ok(()),
+		};
+		match input {
+			None => (),
+			Some(SettingsInput::Adjust(setting, step)) => self.adjust_setting(setting, step)?,
+			Some(SettingsInput::Bind(action, key)) => self.input.keys.bind(action, key),
+			Some(SettingsInput::Close) => self.toggle_settings()?,
+		}
+		Ok(())
+	}
+
+	fn toggle_inspector(&mut self) {
+		if self.inspector.take().is_some() {
+			return;
+		}
+		match self.box_selected.first() {
+			Some(&entity) => self.inspector = Some(Inspector::new(entity)),
+			None => self
+				.chat
+				.post("system", "box select the unit to inspect first"),
+		}
+	}
+
+	/// Reads the inspected entity's components again so the inspector shows their values now.
+	fn refresh_inspector(&mut self, ecs: &mut shipyard::World) -> anyhow::Result<()> {
+		let inspector = match &mut self.inspector {
+			Some(inspector) => inspector,
+			None => return Ok(()),
+		};
+		let entity = inspector.entity;
+		let values =
+			ecs.run(|all_storages: AllStoragesViewMut| component_values(entity, &all_storages))?;
+		inspector.refresh(
+			values
+				.into_iter()
+				.map(|(name, value)| {
+					let field_type = component_registration(name).and_then(|r| (r.describe)().ok());
+					(name, value, field_type)
+				})
+				.collect(),
+		);
+		Ok(())
+	}
+
+	fn inspector_key_event(
 		&mut self,
 		ecs: &mut shipyard::World,
-		engine: &mut Engine<GameState>,
-		event: Event,
+		keycode: VirtualKeyCode,
+		modifiers: ModifiersState,
 	) -> anyhow::Result<()> {
-		match event {
-			Event::WindowEvent { event, .. } => match event {
-				WindowEvent::Resized(logical_size) => {
-					self.resize_event(ecs, engine, logical_size)?;
+		let (entity, input) = match &mut self.inspector {
+			Some(inspector) => (inspector.entity, inspector.key(keycode, modifiers.shift)),
+			None => return Ok(()),
+		};
+		match input {
+			None => (),
+			Some(InspectorInput::Set { component, value }) => {
+				let set = ecs.run(|mut all_storages: AllStoragesViewMut| {
+					set_component_value(entity, component, value, &mut all_storages)
+				});
+				match set {
+					Ok(()) => self.refresh_inspector(ecs)?,
+					Err(error) => self.chat.post(
+						"system",
+						format!("can't set {}: {:#}", component, anyhow::Error::from(error)),
+					),
+				}
+			}
+			Some(InspectorInput::Rejected(message)) => self.chat.post("system", message),
+			Some(InspectorInput::Close) => self.inspector = None,
+		}
+		Ok(())
+	}
+
+	fn dispatch_event(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		event: Event,
+	) -> anyhow::Result<()> {
+		match event {
+			Event::WindowEvent { event, .. } => match event {
+				WindowEvent::Resized(logical_size) => {
+					self.resize_event(ecs, engine, logical_size)?;
+				}
+				WindowEvent::HiDpiFactorChanged(hidpi_factor) => {
+					self.screen.hidpi_factor = hidpi_factor;
+					debug!(
+						"HiDPI factor changed to {}, physical size is now {:?}",
+						hidpi_factor,
+						self.screen.physical_size()
+					);
 				}
 				WindowEvent::CloseRequested => {
 					if self.quit_event(ecs, engine)? {
@@ -460,26 +2360,24 @@ impl GameState {
 				WindowEvent::MouseInput {
 					state: element_state,
 					button,
+					modifiers,
 					..
 				} => {
-					let position = mouse::position(&self.ctx);
+					// Use the position winit reported rather than ggez's copy so it stays in the
+					// same logical pixel space as the window size it gets divided by.
+					let position = self.mouse_logical_position;
 					match element_state {
 						ElementState::Pressed => {
-							self.mouse_button_down_event(
-								ecs, engine, button, position.x, position.y,
-							)?;
+							self.mouse_button_down_event(ecs, engine, button, position, modifiers)?;
 						}
 						ElementState::Released => {
-							self.mouse_button_up_event(
-								ecs, engine, button, position.x, position.y,
-							)?;
+							self.mouse_button_up_event(ecs, engine, button, position)?;
 						}
 					}
 				}
-				WindowEvent::CursorMoved { .. } => {
-					let position = mouse::position(&self.ctx);
-					let delta = mouse::delta(&self.ctx);
-					self.mouse_motion_event(ecs, engine, position.x, position.y, delta.x, delta.y)?;
+				WindowEvent::CursorMoved { position, .. } => {
+					self.mouse_logical_position = position;
+					self.mouse_motion_event(ecs, engine, position)?;
 				}
 				_x => {
 					// trace!("ignoring window event {:?}", x);
@@ -504,8 +2402,8 @@ impl GameState {
 		_engine: &mut Engine<GameState>,
 		logical_size: dpi::LogicalSize,
 	) -> anyhow::Result<()> {
-		self.screen_size = logical_size;
-		self.aspect_ratio = (logical_size.width / logical_size.height) as f32;
+		self.screen.logical_size = logical_size;
+		self.aspect_ratio = self.screen.aspect_ratio();
 		self.tiles_meshes.clear();
 		Ok(())
 	}
@@ -515,7 +2413,22 @@ impl GameState {
 		_ecs: &mut shipyard::World,
 		_engine: &mut Engine<GameState>,
 	) -> anyhow::Result<bool> {
-		Ok(true)
+		if self.unsaved_maps.is_empty() {
+			return Ok(true);
+		}
+		if !self.modals.has_action(ModalAction::SaveAndQuit) {
+			let maps: Vec<&str> = self.unsaved_maps.iter().map(|name| name.as_str()).collect();
+			self.modals.push(
+				Modal::new(
+					"Quit?",
+					format!("These maps have unsaved changes: {}", maps.join(", ")),
+				)
+				.button("Save and Quit", ModalAction::SaveAndQuit)
+				.button("Quit", ModalAction::Quit)
+				.button("Cancel", ModalAction::Close),
+			);
+		}
+		Ok(false)
 	}
 
 	fn focus_event(
@@ -528,14 +2441,599 @@ impl GameState {
 	}
 
 	fn text_input_event(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		ch: char,
+	) -> anyhow::Result<()> {
+		if !self.modals.is_empty() {
+			return Ok(());
+		}
+		// Enter edits in the inspector rather than opening the chat
+		if let Some(inspector) = &mut self.inspector {
+			inspector.push_char(ch);
+			return Ok(());
+		}
+		if !self.chat.is_focused() {
+			if self.sequence.is_some() {
+				return Ok(());
+			}
+			match ch {
+				'\r' => self.chat.focus(),
+				'/' => {
+					self.chat.focus();
+					self.chat.push_char(ch);
+				}
+				_ => (),
+			}
+			return Ok(());
+		}
+		match self.chat.push_char(ch) {
+			None => (),
+			// TODO: Route through the server once there is a network layer, local echo for now
+			Some(ChatInput::Message(text)) => self.chat.post("local", text),
+			Some(ChatInput::Command(name, args)) => {
+				// The tutorial can always be stopped
+				let event = TutorialEvent::Command(name.clone());
+				if name == "tutorial" || self.tutorial_allows(Some(&event)) {
+					self.chat_command_event(ecs, engine, &name, &args)?;
+					self.tutorial_event(event);
+				} else {
+					self.chat
+						.post("system", "the tutorial is waiting for something else");
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn chat_command_event(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		name: &str,
+		args: &[String],
+	) -> anyhow::Result<()> {
+		match name {
+			"help" => self.chat.post(
+				"system",
+				"commands: /help, /maps, /map <name>, /regenerate [map], /rename <map> <display name>, /goto <map:q,r>, /pause, /step, /speed <0|1|2|4>, /endturn, /turntimer <seconds>|off, /session, /coords, /grid, /overlay [field <name>|off], /minimap, /layer [name on|off], /contrast, /pin, /tag [name|add|remove <name>], /paint [tile], /army [form|split], /export map|text <name> <file>, /export template <file>, /save, /import text <file>, /ai [go|turn] [persona], /impact [strength], /animate attack|death, /reload templates [patch]|watch, /inspect, /halloffame, /sequence <name>, /tutorial <name>|stop, /mem, /settings, /uiscale <scale>",
+			),
+			"maps" => {
+				for (name, tile_map) in engine.maps.iter() {
+					self.chat.post(
+						"system",
+						format!(
+							"`{}` {}x{}: {}, seed {}",
+							name,
+							tile_map.width as u16 + 1,
+							tile_map.height as u16 + 1,
+							tile_map.metadata,
+							tile_map.seed
+						),
+					);
+				}
+			}
+			"regenerate" => {
+				let name = args.get(0).unwrap_or(&self.visible_map).clone();
+				match world::regenerate_map(engine, self, &name) {
+					Ok(()) => {
+						if name == self.visible_map {
+							self.tiles_meshes.clear();
+							self.minimap.invalidate();
+						}
+						self.unsaved_maps.insert(name.clone());
+						self.chat
+							.post("system", format!("regenerated map `{}`", name));
+					}
+					Err(error) => self
+						.chat
+						.post("system", format!("regenerating failed: {:#}", error)),
+				}
+			}
+			"rename" => match args {
+				[map, display_name @ ..] if !display_name.is_empty() => {
+					match engine.set_map_display_name(map, display_name.join(" ")) {
+						Ok(()) => {
+							self.unsaved_maps.insert(map.clone());
+							self.chat.post("system", format!("renamed map `{}`", map));
+						}
+						Err(error) => self.chat.post("system", format!("{}", error)),
+					}
+				}
+				_ => self
+					.chat
+					.post("system", "usage: /rename <map> <display name>"),
+			},
+			"map" => match args.get(0) {
+				Some(name) => match engine.maps.get_index_of(name) {
+					Some(map) => {
+						let coord = MapCoord {
+							map,
+							coord: Coord::new_axial(0, 0),
+						};
+						self.jump_to_coord(engine, coord)?;
+					}
+					None => self.chat.post("system", format!("no map named `{}`", name)),
+				},
+				None => self.chat.post("system", "usage: /map <name>"),
+			},
+			"export" => match args {
+				[kind, name, file, rest @ ..] if kind == "map" && rest.len() <= 1 => {
+					let pixels_per_hex = match rest.get(0).map(|s| s.parse::<f32>()) {
+						None => DEFAULT_EXPORT_PIXELS_PER_HEX,
+						Some(Ok(pixels_per_hex)) => pixels_per_hex,
+						Some(Err(_)) => {
+							self.chat
+								.post("system", "pixels per hex must be a number");
+							return Ok(());
+						}
+					};
+					match self.export_map_png(engine, name, file, pixels_per_hex) {
+						Ok(path) => self
+							.chat
+							.post("system", format!("exporting to {}", path.display())),
+						Err(error) => self
+							.chat
+							.post("system", format!("export failed: {:#}", error)),
+					}
+				}
+				[kind, file] if kind == "template" => match self.box_selected.first() {
+					Some(&entity) => {
+						self.template_export = Some((entity, export_path(file, "ron")))
+					}
+					None => self
+						.chat
+						.post("system", "box select the unit to export first"),
+				},
+				[kind, name, file] if kind == "text" => {
+					match self.export_map_text(engine, name, file) {
+						Ok(path) => {
+							self.unsaved_maps.remove(name);
+							self.chat
+								.post("system", format!("exported to {}", path.display()));
+						}
+						Err(error) => self
+							.chat
+							.post("system", format!("export failed: {:#}", error)),
+					}
+				}
+				_ => self.chat.post(
+					"system",
+					"usage: /export map <name> <file> [pixels per hex], /export text <name> <file>, /export template <file>",
+				),
+			},
+			"save" if self.unsaved_maps.is_empty() => self.chat.post("system", "nothing to save"),
+			"save" => match self.save_maps(engine) {
+				Ok(()) => self.chat.post("system", "saved the changed maps to exports"),
+				Err(error) => self
+					.chat
+					.post("system", format!("saving failed: {:#}", error)),
+			},
+			"import" => match args {
+				[kind, file] if kind == "text" => match self.import_map_text(engine, file) {
+					Ok(name) => self
+						.chat
+						.post("system", format!("imported map `{}`", name)),
+					Err(error) => self
+						.chat
+						.post("system", format!("import failed: {:#}", error)),
+				},
+				_ => self.chat.post("system", "usage: /import text <file>"),
+			},
+			"army" => match args.get(0).map(|s| s.as_str()) {
+				None | Some("form") => self.form_army(ecs, engine)?,
+				Some("split") => self.split_army(ecs, engine)?,
+				Some(_) => self.chat.post("system", "usage: /army [form|split]"),
+			},
+			"animate" => match args.get(0).map(|s| s.as_str()) {
+				Some("attack") => self.preview_attack(ecs, engine)?,
+				Some("death") => self.preview_death(ecs, engine)?,
+				_ => self.chat.post("system", "usage: /animate attack|death"),
+			},
+			"impact" => {
+				let strength = args
+					.get(0)
+					.and_then(|s| s.parse::<f32>().ok())
+					.unwrap_or(0.5);
+				let at = (self.view_center.x, self.view_center.y);
+				self.screen_event(ScreenEvent::Impact { at, strength });
+			}
+			"reload" => match (
+				args.get(0).map(|s| s.as_str()),
+				args.get(1).map(|s| s.as_str()),
+			) {
+				(Some("templates"), None) => {
+					self.template_reload = Some(TemplateReloadRequest::Reload)
+				}
+				(Some("templates"), Some("patch")) => {
+					self.template_reload = Some(TemplateReloadRequest::ReloadAndPatch)
+				}
+				(Some("watch"), None) => {
+					if self.template_watch.take().is_some() {
+						self.chat.post("system", "stopped watching templates");
+					} else {
+						self.template_watch = Some(TEMPLATE_WATCH_SECONDS);
+						self.chat.post(
+							"system",
+							"watching templates, changes patch the entities made from them",
+						);
+					}
+				}
+				_ => self
+					.chat
+					.post("system", "usage: /reload templates [patch] or /reload watch"),
+			},
+			"coords" => self.labels.show_coords = !self.labels.show_coords,
+			"grid" => self.grid.toggle(),
+			"overlay" => match args {
+				[kind, name] if kind == "field" => {
+					if self.heatmap.available.contains(name) {
+						self.heatmap.select(Some(name.clone()));
+					} else {
+						self.chat.post(
+							"system",
+							format!(
+								"no field `{}` on this map, there is: {}",
+								name,
+								self.heatmap.available.join(", ")
+							),
+						);
+					}
+				}
+				[off] if off == "off" => self.heatmap.select(None),
+				_ => {
+					let showing = self.heatmap.field().unwrap_or("nothing").to_owned();
+					self.chat.post(
+						"system",
+						format!(
+							"showing {}, fields: {}, usage: /overlay field <name> or /overlay off",
+							showing,
+							self.heatmap.available.join(", ")
+						),
+					);
+				}
+			},
+			"minimap" => self.show_minimap = !self.show_minimap,
+			"mem" => self.memory_request = true,
+			"halloffame" => self.show_hall_of_fame = !self.show_hall_of_fame,
+			"layer" => match (
+				args.get(0).and_then(|name| RenderLayer::from_name(name)),
+				args.get(1).map(|s| s.as_str()),
+			) {
+				(Some(layer), Some("on")) => self.layers.set_enabled(layer, true),
+				(Some(layer), Some("off")) => self.layers.set_enabled(layer, false),
+				_ => {
+					let layers: Vec<String> = RenderLayer::ALL
+						.iter()
+						.map(|&layer| {
+							let state = if self.layers.is_enabled(layer) {
+								"on"
+							} else {
+								"off"
+							};
+							format!("{} {}", layer.name(), state)
+						})
+						.collect();
+					self.chat.post(
+						"system",
+						format!("layers: {}, usage: /layer <name> <on|off>", layers.join(", ")),
+					);
+				}
+			},
+			"contrast" => self.set_high_contrast(!self.accessibility.high_contrast),
+			"paint" => self.toggle_editor_paint(engine, args.get(0).map(|s| s.as_str())),
+			"settings" => self.toggle_settings()?,
+			"inspect" => self.toggle_inspector(),
+			"ai" if args.get(0).map(|s| s.as_str()) == Some("turn") => match &args[1..] {
+				[] | [_] => {
+					self.ai_turn_request = Some(AiTurnRequest {
+						persona: args.get(1).cloned(),
+					})
+				}
+				_ => self.chat.post("system", "usage: /ai turn [persona]"),
+			},
+			"ai" => {
+				let (follow, rest) = match args {
+					[go, rest @ ..] if go == "go" => (true, rest),
+					rest => (false, rest),
+				};
+				match (self.box_selected.first(), rest) {
+					(Some(&entity), []) | (Some(&entity), [_]) => {
+						self.unit_ai_request = Some(UnitAiRequest {
+							entity,
+							follow,
+							persona: rest.get(0).cloned(),
+						})
+					}
+					(None, []) | (None, [_]) => self
+						.chat
+						.post("system", "box select the unit to plan for first"),
+					_ => self.chat.post("system", "usage: /ai [go] [persona]"),
+				}
+			}
+			"uiscale" => match args.get(0).and_then(|s| s.parse::<f32>().ok()) {
+				Some(scale) => self.screen.set_ui_scale(scale),
+				None => self.chat.post(
+					"system",
+					format!("ui scale is {}, usage: /uiscale <scale>", self.screen.ui_scale()),
+				),
+			},
+			"pin" => self.pin_command_event(ecs, engine, args)?,
+			"goto" => match args.get(0).map(|s| s.parse::<MapAddress>()) {
+				Some(Ok(address)) => match address.resolve(&engine.maps) {
+					Ok(coord) => self.jump_to_coord(engine, coord)?,
+					Err(e) => self.chat.post("system", e.to_string()),
+				},
+				Some(Err(e)) => self.chat.post("system", e.to_string()),
+				None => self.chat.post("system", "usage: /goto <map:q,r>"),
+			},
+			"tag" => self.tag_command_event(ecs, args),
+			"sequence" => match args.get(0) {
+				Some(name) => {
+					let path = PathBuf::from("sequences").join(format!("{}.ron", name));
+					match self.load_sequence(&path) {
+						Ok(sequence) => self.sequence = Some(SequencePlayer::new(sequence)),
+						Err(error) => self.chat.post("system", format!("{:#}", error)),
+					}
+				}
+				None => self.chat.post("system", "usage: /sequence <name>"),
+			},
+			"tutorial" => match args.get(0).map(|arg| arg.as_str()) {
+				Some("stop") => {
+					if self.tutorial.take().is_some() {
+						self.chat.post("system", "tutorial ended");
+					}
+				}
+				Some(name) => {
+					let path = PathBuf::from("tutorials").join(format!("{}.ron", name));
+					match self.load_tutorial(&path) {
+						Ok(tutorial) => self.tutorial = Some(TutorialRunner::new(tutorial)),
+						Err(error) => self.chat.post("system", format!("{:#}", error)),
+					}
+				}
+				None => self.chat.post("system", "usage: /tutorial <name>|stop"),
+			},
+			"pause" => self.clock.toggle_pause(),
+			"endturn" => self.end_turn(engine, false),
+			"turntimer" => match args.get(0).map(|s| s.as_str()) {
+				Some("off") => {
+					self.session.set_turn_limit(None);
+					self.chat.post("system", "turns are no longer timed");
+				}
+				Some(seconds) => match seconds.parse() {
+					Ok(seconds) if seconds > 0 => {
+						self.session
+							.set_turn_limit(Some(Duration::from_secs(seconds)));
+						self.chat
+							.post("system", format!("turns now last {} seconds", seconds));
+					}
+					_ => self.chat.post("system", "usage: /turntimer <seconds>|off"),
+				},
+				None => self.chat.post("system", "usage: /turntimer <seconds>|off"),
+			},
+			"session" => {
+				let summary = self.session.summary();
+				self.chat.post("system", summary);
+			}
+			"step" => self.clock.step(),
+			"speed" => match args
+				.get(0)
+				.and_then(|s| s.parse().ok())
+				.and_then(SimulationSpeed::from_multiplier)
+			{
+				Some(speed) => self.clock.set_speed(speed),
+				None => self.chat.post("system", "usage: /speed <0|1|2|4>"),
+			},
+			_ => self
+				.chat
+				.post("system", format!("unknown command: /{}", name)),
+		}
+		Ok(())
+	}
+
+	/// Lists the tags of the box selection, tags or untags it, or box selects everything tagged.
+	fn tag_command_event(&mut self, ecs: &mut shipyard::World, args: &[String]) {
+		match args {
+			[] => {
+				let lines: Vec<String> = ecs.run(|tags: View<Tags>| {
+					self.box_selected
+						.iter()
+						.map(|&entity| {
+							let names: Vec<&str> = tags
+								.get(entity)
+								.map(|t| t.iter().collect())
+								.unwrap_or_default();
+							format!("{:?}: {}", entity, names.join(", "))
+						})
+						.collect()
+				});
+				if lines.is_empty() {
+					self.chat
+						.post("system", "box select something to see its tags");
+				}
+				for line in lines {
+					self.chat.post("system", line);
+				}
+			}
+			[add, tag] if add == "add" => {
+				let box_selected = &self.box_selected;
+				let added = ecs.run(|entities: EntitiesView, mut tags: ViewMut<Tags>| {
+					box_selected
+						.iter()
+						.filter(|&&entity| add_tag(&entities, &mut tags, entity, tag))
+						.count()
+				});
+				self.chat
+					.post("system", format!("tagged {} with {}", added, tag));
+			}
+			[remove, tag] if remove == "remove" => {
+				let box_selected = &self.box_selected;
+				let removed = ecs.run(|mut tags: ViewMut<Tags>| {
+					box_selected
+						.iter()
+						.filter(|&&entity| remove_tag(&mut tags, entity, tag))
+						.count()
+				});
+				self.chat
+					.post("system", format!("untagged {} from {}", removed, tag));
+			}
+			[tag] => {
+				self.box_selected = ecs.run(|tags: View<Tags>| query_entities_with_tag(&tags, tag));
+				self.chat.post(
+					"system",
+					format!("{} tagged {} selected", self.box_selected.len(), tag),
+				);
+			}
+			_ => self
+				.chat
+				.post("system", "usage: /tag [name|add|remove <name>]"),
+		}
+	}
+
+	fn pin_command_event(
 		&mut self,
 		_ecs: &mut shipyard::World,
-		_engine: &mut Engine<GameState>,
-		_ch: char,
+		engine: &mut Engine<GameState>,
+		args: &[String],
+	) -> anyhow::Result<()> {
+		let parse_index = |arg: Option<&String>| arg.and_then(|s| s.parse::<usize>().ok());
+		match args.get(0).map(|s| s.as_str()) {
+			Some("add") => {
+				// Either a whole address or a coord on the visible map
+				let (address, name) = match args.get(1).map(|s| s.parse::<MapAddress>()) {
+					Some(Ok(address)) => (Some(address), &args[2..]),
+					_ => {
+						let q = args.get(1).and_then(|s| s.parse::<u8>().ok());
+						let r = args.get(2).and_then(|s| s.parse::<u8>().ok());
+						let address = match (q, r) {
+							(Some(q), Some(r)) => Some(MapAddress::new(
+								self.visible_map.clone(),
+								Coord::new_axial(q, r),
+							)),
+							_ => None,
+						};
+						(address, &args[3.min(args.len())..])
+					}
+				};
+				match (address, pin_name_from_args(name)) {
+					(Some(address), Some(name)) => match address.resolve(&engine.maps) {
+						Ok(coord) => {
+							let index = self.pins.add(coord, &name);
+							self.chat.post(
+								"system",
+								format!("pin {} added at {}: {}", index, address, name),
+							);
+						}
+						Err(e) => self.chat.post("system", e.to_string()),
+					},
+					_ => self
+						.chat
+						.post("system", "usage: /pin add <map:q,r>|<q> <r> <name>"),
+				}
+			}
+			Some("list") => {
+				let lines: Vec<String> = self
+					.pins
+					.iter()
+					.map(
+						|(index, pin)| match MapAddress::of(&engine.maps, pin.coord) {
+							Some(address) => format!("{}: {} at {}", index, pin.name, address),
+							None => format!("{}: {} on a removed map", index, pin.name),
+						},
+					)
+					.collect();
+				if lines.is_empty() {
+					self.chat.post("system", "no pins placed");
+				}
+				for line in lines {
+					self.chat.post("system", line);
+				}
+			}
+			Some("go") => match parse_index(args.get(1)).and_then(|i| self.pins.get(i)) {
+				Some(pin) => {
+					let coord = pin.coord;
+					self.jump_to_coord(engine, coord)?;
+				}
+				None => self.chat.post("system", "usage: /pin go <index>"),
+			},
+			Some("remove") => match parse_index(args.get(1)).and_then(|i| self.pins.remove(i)) {
+				Some(pin) => self
+					.chat
+					.post("system", format!("pin removed: {}", pin.name)),
+				None => self.chat.post("system", "usage: /pin remove <index>"),
+			},
+			_ => self
+				.chat
+				.post("system", "usage: /pin <add|list|go|remove> ..."),
+		}
+		Ok(())
+	}
+
+	fn jump_to_coord(
+		&mut self,
+		engine: &mut Engine<GameState>,
+		coord: MapCoord,
 	) -> anyhow::Result<()> {
+		let (map_name, _map) = engine
+			.maps
+			.get_index(coord.map)
+			.context("jump target map does not exist")?;
+		self.visible_map = map_name.clone();
+		let (x, y) = self.hex.to_linear(coord.coord);
+		self.view_center = na::Point2::new(x, y);
+		self.tiles_meshes.clear();
+		self.minimap.invalidate();
 		Ok(())
 	}
 
+	/// Enters paint mode with the named tile type, or the last brush, or leaves it if already in it.
+	fn toggle_editor_paint(&mut self, engine: &Engine<GameState>, tile_type: Option<&str>) {
+		if let Some(name) = tile_type {
+			match engine.tile_types.tile_types.get_index_of(name) {
+				Some(idx) => self.paint_brush = Some(idx),
+				None => {
+					self.chat
+						.post("system", format!("unknown tile type: {}", name));
+					return;
+				}
+			}
+			if self.interaction.mode() == InteractionMode::EditorPaint {
+				return;
+			}
+		}
+		if self.paint_brush.is_none() {
+			self.chat.post("system", "usage: /paint <tile type>");
+			return;
+		}
+		self.interaction.toggle(InteractionMode::EditorPaint);
+	}
+
+	fn set_high_contrast(&mut self, high_contrast: bool) {
+		self.accessibility.high_contrast = high_contrast;
+		self.labels.shadow = high_contrast;
+		// Cached overlay meshes bake in their line widths and colors
+		self.selected_mesh = None;
+		self.strategic_icon_mesh = None;
+		self.grid.invalidate();
+	}
+
+	fn is_strategic(&self) -> bool {
+		self.lod.is_strategic(self.screen_tiles)
+	}
+
+	fn hovered_coord(&self, engine: &Engine<GameState>) -> anyhow::Result<MapCoord> {
+		let position = self.interaction.position();
+		let (map_x, map_y) = self.screen_ratio_to_map(position.x, position.y);
+		Ok(MapCoord {
+			map: engine
+				.maps
+				.get_index_of(&self.visible_map)
+				.context("visible map doesn't exist")?,
+			coord: self.hex.from_linear(map_x, map_y),
+		})
+	}
+
 	fn key_down_event(
 		&mut self,
 		_ecs: &mut shipyard::World,
@@ -549,19 +3047,88 @@ impl GameState {
 
 	fn key_up_event(
 		&mut self,
-		_ecs: &mut shipyard::World,
-		_engine: &mut Engine<GameState>,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
 		keycode: VirtualKeyCode,
 		modifiers: ModifiersState,
 	) -> anyhow::Result<()> {
 		use VirtualKeyCode::*;
-		match (keycode, modifiers) {
-			(Escape, _) => ggez::event::quit(&mut self.ctx),
-			(W, _) => (),
-			(A, _) => (),
-			(S, _) => (),
-			(D, _) => (),
-			_ => (),
+		if !self.modals.is_empty() {
+			if let Some(action) = self.modals.key(keycode, modifiers.shift) {
+				self.modal_action(engine, action);
+			}
+			return Ok(());
+		}
+		if self.chat.is_focused() {
+			if keycode == Escape {
+				self.chat.unfocus();
+			}
+			return Ok(());
+		}
+		if let Some(sequence) = &mut self.sequence {
+			match keycode {
+				Escape => sequence.skip(),
+				Space | Return => sequence.next_step(),
+				_ => (),
+			}
+			return Ok(());
+		}
+		if self.settings.is_some() {
+			return self.settings_key_event(keycode, modifiers);
+		}
+		if self.inspector.is_some() {
+			return self.inspector_key_event(ecs, keycode, modifiers);
+		}
+		if self.show_hall_of_fame {
+			if keycode == Escape {
+				self.show_hall_of_fame = false;
+			}
+			return Ok(());
+		}
+		if keycode == Escape {
+			if self.context_menu.take().is_none() && !self.interaction.cancel() {
+				if self.tutorial.take().is_some() {
+					self.chat.post("system", "tutorial ended");
+				} else if self.quit_event(ecs, engine)? {
+					ggez::event::quit(&mut self.ctx);
+				}
+			}
+			return Ok(());
+		}
+		if keycode == Space {
+			self.tutorial_event(TutorialEvent::Continue);
+		}
+		// The numpad plus and minus always change the speed as well as whatever is bound
+		let action = match keycode {
+			Add => Some(KeyAction::Faster),
+			Subtract => Some(KeyAction::Slower),
+			_ => self.input.keys.action_for(keycode),
+		};
+		let event = action.map(TutorialEvent::Key);
+		if !self.tutorial_allows(event.as_ref()) {
+			return Ok(());
+		}
+		match action {
+			Some(KeyAction::Target) => self.interaction.toggle(InteractionMode::TargetSelect),
+			Some(KeyAction::Paint) => self.toggle_editor_paint(engine, None),
+			Some(KeyAction::ToggleCoords) => self.labels.show_coords = !self.labels.show_coords,
+			Some(KeyAction::ToggleGrid) => self.grid.toggle(),
+			Some(KeyAction::DropPin) => {
+				let coord = self.hovered_coord(engine)?;
+				let index = self.pins.add(coord, format!("Pin {}", self.pins.len()));
+				self.chat.post("system", format!("pin {} dropped", index));
+			}
+			Some(KeyAction::Patrol) => self.toggle_patrol(ecs)?,
+			Some(KeyAction::Pause) => self.clock.toggle_pause(),
+			Some(KeyAction::Step) => self.clock.step(),
+			Some(KeyAction::Faster) => self.clock.set_speed(self.clock.speed().faster()),
+			Some(KeyAction::Slower) => self.clock.set_speed(self.clock.speed().slower()),
+			Some(KeyAction::Settings) => self.toggle_settings()?,
+			Some(KeyAction::EndTurn) => self.end_turn(engine, false),
+			None => (),
+		}
+		if let Some(event) = event {
+			self.tutorial_event(event);
 		}
 		Ok(())
 	}
@@ -573,6 +3140,12 @@ impl GameState {
 		_x: f32,
 		y: f32,
 	) -> anyhow::Result<()> {
+		if !self.modals.is_empty()
+			|| self.sequence.is_some()
+			|| !self.tutorial_allows(Some(&TutorialEvent::Zoom))
+		{
+			return Ok(());
+		}
 		self.screen_tiles += (-y * 0.5) * (1.0 + self.screen_tiles * 0.5);
 		if self.screen_tiles < 1.0 {
 			self.screen_tiles = 1.0;
@@ -580,22 +3153,47 @@ impl GameState {
 			self.screen_tiles = 16.0;
 		}
 		self.tiles_meshes.clear();
+		self.tutorial_event(TutorialEvent::Zoom);
 		Ok(())
 	}
 
 	fn mouse_button_down_event(
 		&mut self,
-		_ecs: &mut shipyard::World,
-		_engine: &mut Engine<GameState>,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
 		button: MouseButton,
-		x: f32,
-		y: f32,
+		position: dpi::LogicalPosition,
+		modifiers: ModifiersState,
 	) -> anyhow::Result<()> {
-		let screen_x = x / self.screen_size.width as f32;
-		let screen_y = y / self.screen_size.height as f32;
-		self.mouse_buttons_clicked
-			.insert(button, MouseButtonPressedData::new(screen_x, screen_y));
-		self.mouse_last_position = [screen_x, screen_y].into();
+		if !self.modals.is_empty() {
+			let pixel = na::Point2::new(position.x as f32, position.y as f32);
+			let screen_size = (self.screen.width(), self.screen.height());
+			if let Some(action) = self
+				.modals
+				.click(pixel, screen_size, self.screen.ui_scale())
+			{
+				self.modal_action(engine, action);
+			}
+			return Ok(());
+		}
+		if self.sequence.is_some() {
+			return Ok(());
+		}
+		if let Some(menu) = self.context_menu.take() {
+			// An open menu swallows the press, either picking an item or just closing it
+			let pixel = na::Point2::new(position.x as f32, position.y as f32);
+			if let Some(action) = menu.item_at(pixel, self.screen.ui_scale()) {
+				self.context_action_event(ecs, engine, menu.coord, action)?;
+			}
+			return Ok(());
+		}
+		let screen = self.screen.logical_to_ratio(position);
+		if let Some(action) = self
+			.interaction
+			.button_down(button, screen, modifiers.shift)
+		{
+			self.interaction_event(ecs, engine, action)?;
+		}
 		Ok(())
 	}
 
@@ -612,7 +3210,7 @@ impl GameState {
 			.context("unable to lookup the visible map")?
 			.1;
 		match tile_map.get_tile_mut(coord.coord) {
-			None => (),
+			None => self.screen_event(ScreenEvent::SelectionError),
 			Some(tile) => {
 				let entity = ecs
 					.try_entity_builder()?
@@ -640,374 +3238,2598 @@ impl GameState {
 		)
 	}
 
-	fn remove_selected(
+	fn remove_selected(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		match self.selected {
+			None => (),
+			Some(entity) => {
+				self.selected = None;
+				Self::delete_from_map(ecs, engine, entity)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Deletes an entity that stands on a tile, taking it off the tile first. It is taken out of
+	/// any army it was in, and an army left with fewer than two members is disbanded.
+	fn delete_from_map(
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		entity: EntityId,
+	) -> anyhow::Result<()> {
+		let disbanded = ecs.run(|mut armies: ViewMut<Army>| {
+			let mut disbanded = vec![];
+			for (banner, army) in (&mut armies).iter().with_id() {
+				if army.remove(entity) && army.members().len() < 2 {
+					disbanded.push(banner);
+				}
+			}
+			disbanded
+		});
+		engine.persistent_ids.forget(entity);
+		let maps = &mut engine.maps;
+		ecs.run(
+			|mut all_storages: AllStoragesViewMut| -> anyhow::Result<()> {
+				{
+					let coords = all_storages.try_borrow::<View<MapCoord>>()?;
+					let coord = coords[entity];
+					let tile_map = maps
+						.get_index_mut(coord.map)
+						.context("unable to lookup the visible map")?
+						.1;
+					let tile = tile_map
+						.get_tile_mut(coord.coord)
+						.context("tile cannot be found that should exist")?;
+					tile.entities.remove(&entity);
+				}
+				all_storages.delete(entity);
+				Ok(())
+			},
+		)?;
+		for banner in disbanded {
+			Self::delete_from_map(ecs, engine, banner)?;
+		}
+		Ok(())
+	}
+
+	/// The linear area tiles and entities are drawn over, the view with half a tile around it for
+	/// screen shake and the zoom easing towards `screen_tiles` from either side.
+	fn draw_bounds(&self) -> ((f32, f32), (f32, f32)) {
+		let tiles = self.zoom.max(self.screen_tiles);
+		let half_width = tiles * 0.5 * self.aspect_ratio + 0.5;
+		let half_height = tiles * 0.5 + 0.5;
+		let (x, y) = (self.view_center.x, self.view_center.y);
+		(
+			(x - half_width, y - half_height),
+			(x + half_width, y + half_height),
+		)
+	}
+
+	fn screen_ratio_to_map(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
+		let visible_width = self.screen_tiles * self.aspect_ratio;
+		let visible_height = self.screen_tiles;
+		let offset_x = (visible_width * 0.5) - self.view_center.x;
+		let offset_y = (visible_height * 0.5) - self.view_center.y;
+		let map_x = (screen_x * visible_width) - offset_x;
+		let map_y = (screen_y * visible_height) - offset_y;
+		(map_x, map_y)
+	}
+
+	fn mouse_button_up_event(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		button: MouseButton,
+		position: dpi::LogicalPosition,
+	) -> anyhow::Result<()> {
+		let screen = self.screen.logical_to_ratio(position);
+		let leeway = self.click_leeway_ratio();
+		if let Some(action) = self.interaction.button_up(button, screen, leeway) {
+			self.interaction_event(ecs, engine, action)?;
+		}
+		Ok(())
+	}
+
+	/// How far a press may move, as a screen ratio on each axis, and still count as a click.
+	fn click_leeway_ratio(&self) -> (f32, f32) {
+		let leeway = self.screen.ui(self.input.click_leeway);
+		(leeway / self.screen.width(), leeway / self.screen.height())
+	}
+
+	fn mouse_motion_event(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		position: dpi::LogicalPosition,
+	) -> anyhow::Result<()> {
+		if !self.modals.is_empty() || self.sequence.is_some() {
+			return Ok(());
+		}
+		let screen = self.screen.logical_to_ratio(position);
+		let leeway = self.click_leeway_ratio();
+		if let Some(action) = self.interaction.motion(screen, leeway) {
+			self.interaction_event(ecs, engine, action)?;
+		}
+		Ok(())
+	}
+
+	fn interaction_event(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		action: InteractionAction,
+	) -> anyhow::Result<()> {
+		let event = match action {
+			InteractionAction::Click(MouseButton::Right, _at) if !self.box_selected.is_empty() => {
+				Some(TutorialEvent::MoveOrder)
+			}
+			InteractionAction::Click(_button, _at) => {
+				let coord = self.hovered_coord(engine)?;
+				MapAddress::of(&engine.maps, coord).map(TutorialEvent::Select)
+			}
+			InteractionAction::Pan { .. } => Some(TutorialEvent::Pan),
+			// Letting a gesture go is never blocked
+			InteractionAction::Cancelled => return Ok(()),
+			_ => None,
+		};
+		if !self.tutorial_allows(event.as_ref()) {
+			if let InteractionAction::Click(..) | InteractionAction::DoubleClick(..) = action {
+				self.screen_event(ScreenEvent::SelectionError);
+			}
+			return Ok(());
+		}
+		match action {
+			InteractionAction::Click(MouseButton::Right, _at) if !self.box_selected.is_empty() => {
+				let map_coord = self.hovered_coord(engine)?;
+				let append = keyboard::is_mod_active(&self.ctx, keyboard::KeyMods::SHIFT);
+				self.order_move(ecs, engine, map_coord, append)?;
+			}
+			InteractionAction::Click(_button, _at) => {
+				let map_coord = self.hovered_coord(engine)?;
+				self.set_selected_coord(ecs, engine, map_coord)?;
+			}
+			InteractionAction::Pan { from, to } => {
+				let (old_map_x, old_map_y) = self.screen_ratio_to_map(to.x, to.y);
+				let (new_map_x, new_map_y) = self.screen_ratio_to_map(from.x, from.y);
+				self.view_center.x += new_map_x - old_map_x;
+				self.view_center.y += new_map_y - old_map_y;
+				self.tiles_meshes.clear();
+			}
+			InteractionAction::BoxSelect { from, to } => {
+				self.box_select(ecs, engine, from, to)?;
+				self.chat
+					.post("system", format!("{} selected", self.box_selected.len()));
+			}
+			InteractionAction::Target(_at) => {
+				// TODO: Hand the target to whatever requested it once there are orders to target with
+				let coord = self.hovered_coord(engine)?;
+				let on_map = engine
+					.maps
+					.get_index(coord.map)
+					.and_then(|(_name, tile_map)| tile_map.get_tile(coord.coord))
+					.is_some();
+				if on_map {
+					let coord = coord.coord;
+					self.chat
+						.post("system", format!("targeted {},{}", coord.q(), coord.r()));
+				} else {
+					self.screen_event(ScreenEvent::SelectionError);
+				}
+			}
+			InteractionAction::Paint(_at) => {
+				if let Some(brush) = self.paint_brush {
+					let coord = self.hovered_coord(engine)?;
+					match engine.paint_tile(coord, brush) {
+						Ok(_) => {
+							if let Some((name, _tile_map)) = engine.maps.get_index(coord.map) {
+								self.unsaved_maps.insert(name.clone());
+							}
+						}
+						// Painting past the map edge does nothing
+						Err(EngineError::CoordIsOutOfRange { .. }) => (),
+						Err(error) => return Err(error.into()),
+					}
+				}
+			}
+			InteractionAction::DoubleClick(_button, _at) => {
+				self.select_same_type_on_screen(ecs, engine)?;
+				self.chat
+					.post("system", format!("{} selected", self.box_selected.len()));
+			}
+			InteractionAction::LongPress(at) => {
+				let coord = self.hovered_coord(engine)?;
+				let tile_map = engine
+					.maps
+					.get_index(coord.map)
+					.context("unable to lookup the visible map")?
+					.1;
+				let title = match tile_map.get_tile(coord.coord) {
+					None => format!("{},{}", coord.coord.q(), coord.coord.r()),
+					Some(tile) => format!(
+						"{},{} {}",
+						coord.coord.q(),
+						coord.coord.r(),
+						engine
+							.tile_types
+							.tile_types
+							.get_index(tile.id)
+							.map(|(name, _)| name.as_str())
+							.unwrap_or("?")
+					),
+				};
+				let anchor =
+					na::Point2::new(at.x * self.screen.width(), at.y * self.screen.height());
+				self.context_menu = Some(ContextMenu::new(
+					anchor,
+					coord,
+					title,
+					vec![
+						ContextAction::Select,
+						ContextAction::CenterView,
+						ContextAction::DropPin,
+					],
+				));
+			}
+			InteractionAction::Cancelled => (),
+		}
+		if let Some(event) = event {
+			self.tutorial_event(event);
+		}
+		Ok(())
+	}
+
+	fn context_action_event(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		coord: MapCoord,
+		action: ContextAction,
+	) -> anyhow::Result<()> {
+		match action {
+			ContextAction::Select => self.set_selected_coord(ecs, engine, coord)?,
+			ContextAction::CenterView => self.jump_to_coord(engine, coord)?,
+			ContextAction::DropPin => {
+				let index = self.pins.add(coord, format!("Pin {}", self.pins.len()));
+				self.chat.post("system", format!("pin {} dropped", index));
+			}
+		}
+		Ok(())
+	}
+
+	/// Selects every visible entity drawn with the same sprite as one on the hovered tile.
+	fn select_same_type_on_screen(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let hovered = self.hovered_coord(engine)?;
+		let tile_map = engine
+			.maps
+			.get(&self.visible_map)
+			.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
+		let draw_sprites = ecs.try_borrow::<View<DrawSprite>>()?;
+		self.box_selected.clear();
+		let sprite_name = match tile_map.get_tile(hovered.coord).and_then(|tile| {
+			tile.entities
+				.iter()
+				.find_map(|&entity| draw_sprites.get(entity).ok())
+		}) {
+			None => {
+				self.screen_event(ScreenEvent::SelectionError);
+				return Ok(());
+			}
+			Some(draw) => draw.sprite_name.clone(),
+		};
+		let radius = (self.screen_tiles * self.aspect_ratio + 1.0)
+			.abs()
+			.min(20.0) as u8;
+		let center = self.hex.from_linear(self.view_center.x, self.view_center.y);
+		let (center_x, center_y) = self.hex.to_linear(center);
+		for (co, tile) in tile_map.iter_neighbors_around(center, radius) {
+			let (opx, opy) = self.hex.offset_to_linear(co);
+			if !self.view_rect.contains([center_x + opx, center_y + opy]) {
+				continue;
+			}
+			for &entity in &tile.entities {
+				if let Ok(draw) = draw_sprites.get(entity) {
+					if draw.sprite_name == sprite_name {
+						self.box_selected.push(entity);
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Selects every drawn entity on the tiles whose centers fall within the screen box.
+	fn box_select(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		from: na::Point2<f32>,
+		to: na::Point2<f32>,
+	) -> anyhow::Result<()> {
+		let (from_x, from_y) = self.screen_ratio_to_map(from.x, from.y);
+		let (to_x, to_y) = self.screen_ratio_to_map(to.x, to.y);
+		let area = Rect::new(
+			from_x.min(to_x),
+			from_y.min(to_y),
+			(from_x - to_x).abs(),
+			(from_y - to_y).abs(),
+		);
+		let tile_map = engine
+			.maps
+			.get(&self.visible_map)
+			.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
+		let draw_sprites = ecs.try_borrow::<View<DrawSprite>>()?;
+		let radius = (self.screen_tiles * self.aspect_ratio + 1.0)
+			.abs()
+			.min(20.0) as u8;
+		let center = self.hex.from_linear(self.view_center.x, self.view_center.y);
+		let (center_x, center_y) = self.hex.to_linear(center);
+		self.box_selected.clear();
+		for (co, tile) in tile_map.iter_neighbors_around(center, radius) {
+			let (opx, opy) = self.hex.offset_to_linear(co);
+			if !area.contains([center_x + opx, center_y + opy]) {
+				continue;
+			}
+			for &entity in &tile.entities {
+				if draw_sprites.get(entity).is_ok() {
+					self.box_selected.push(entity);
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn update(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		// Only the simulation is scaled, camera and rendering keep running at real time
+		let ticks = self.clock.advance(ggez::timer::delta(&self.ctx));
+		for _ in 0..ticks {
+			self.tick(ecs, engine)?;
+		}
+		if self
+			.session
+			.advance(ggez::timer::delta(&self.ctx), self.clock.is_paused())
+		{
+			self.end_turn(engine, true);
+		}
+		if !engine.take_new_tile_types().is_empty() {
+			// Rebuilt whole, tile types are rarely registered after setup
+			self.setup_tiles_atlas(engine)?;
+			self.minimap.invalidate();
+		}
+		let title = branding::window_title(&self.visible_map, self.clock.current_tick());
+		if title != self.window_title {
+			graphics::set_window_title(&self.ctx, &title);
+			self.window_title = title;
+		}
+		if let Some(action) = self.interaction.poll(Instant::now()) {
+			self.interaction_event(ecs, engine, action)?;
+		}
+		self.effects
+			.update(ggez::timer::delta(&self.ctx).as_secs_f32());
+		self.update_sequence(ecs, engine);
+		if self.tutorial.is_some() {
+			self.tutorial_event(TutorialEvent::ShowMap(self.visible_map.clone()));
+		}
+		if let Some(tile_map) = engine.maps.get(&self.visible_map) {
+			// Listens over about half the screen, so zooming out hears more of the map
+			let radius = (self.zoom * 0.5).max(2.0).min(12.0);
+			self.soundscape.update(
+				self.hex,
+				tile_map,
+				(self.view_center.x, self.view_center.y),
+				radius,
+				ggez::timer::delta(&self.ctx).as_secs_f32(),
+				self.audio.master_volume * self.audio.effects_volume,
+			)?;
+		}
+		// The settings screen is the only menu so far
+		self.music.update(
+			&mut self.ctx,
+			self.settings.is_some(),
+			ggez::timer::delta(&self.ctx).as_secs_f32(),
+			self.audio.master_volume * self.audio.music_volume,
+		)?;
+		self.animations.advance(
+			ggez::timer::delta(&self.ctx).as_secs_f32() * self.clock.speed().multiplier() as f32,
+		);
+		self.refresh_inspector(ecs)?;
+		self.update_cursor();
+		Ok(())
+	}
+
+	/// Finishes the player's turn, by the player or by the turn timer running out. There are no
+	/// turns in the simulation yet, so this only keeps the turn's time and borders.
+	fn end_turn(&mut self, engine: &Engine<GameState>, timed_out: bool) {
+		let turn = self.session.turn();
+		let took = format_duration(self.session.end_turn());
+		self.record_territory(engine, turn);
+		let message = if timed_out {
+			format!("turn {} ran out of time after {}", turn, took)
+		} else {
+			format!("turn {} ended after {}", turn, took)
+		};
+		info!("{}", message);
+		self.chat.post("system", message);
+	}
+
+	/// Records the borders of every map at the end of `turn`, a map made again at another size
+	/// starts its history over.
+	fn record_territory(&mut self, engine: &Engine<GameState>, turn: u32) {
+		for (name, tile_map) in engine.maps.iter() {
+			let owners = vec![0; tile_map.tiles.len()];
+			let history = self
+				.territory
+				.entry(name.clone())
+				.or_insert_with(TerritoryHistory::new);
+			if let Err(error) = history.record(turn, &owners, vec![]) {
+				debug!("Restarting the territory history of `{}`: {}", name, error);
+				*history = TerritoryHistory::new();
+				history
+					.record(turn, &owners, vec![])
+					.expect("an empty history refused a frame?");
+			}
+		}
+	}
+
+	/// Adds the atlases and the caches to a memory report. Meshes are counted as a textured quad
+	/// per tile for each of its chunk's meshes.
+	fn memory_usage(&self, report: &mut MemoryReport) {
+		report.add(
+			"atlases",
+			atlas_bytes(&self.tiles_atlas)
+				+ atlas_bytes(&self.entity_atlas)
+				+ atlas_bytes(&self.ui_atlas),
+		);
+		let quad = 4 * std::mem::size_of::<Vertex>() + 6 * std::mem::size_of::<u32>();
+		let meshes: usize = self
+			.tiles_meshes
+			.iter()
+			.map(|chunk| {
+				let meshes = chunk.meshes.iter().filter(|mesh| mesh.is_some()).count();
+				chunk.tiles.len() * meshes * quad
+			})
+			.sum();
+		report.add(
+			"caches",
+			meshes + self.minimap.memory() + self.text.cache_memory(),
+		);
+	}
+
+	/// A reload asked for by `/reload`, or due while watching the templates.
+	fn take_template_reload(&mut self) -> Option<TemplateReloadRequest> {
+		if let Some(request) = self.template_reload.take() {
+			return Some(request);
+		}
+		let remaining = self.template_watch.as_mut()?;
+		*remaining -= ggez::timer::delta(&self.ctx).as_secs_f32();
+		if *remaining > 0.0 {
+			return None;
+		}
+		*remaining = TEMPLATE_WATCH_SECONDS;
+		Some(TemplateReloadRequest::Watch)
+	}
+
+	fn screen_event(&mut self, event: ScreenEvent) {
+		if let ScreenEvent::SelectionError = event {
+			let volume = self.audio.master_volume * self.audio.effects_volume;
+			self.music
+				.notify(&mut self.ctx, "/sounds/notify/error.ogg", volume);
+		}
+		let view_center = (self.view_center.x, self.view_center.y);
+		self.effects.event(event, view_center, self.zoom);
+	}
+
+	fn load_sequence(&mut self, path: &Path) -> anyhow::Result<Sequence> {
+		let reader = self
+			.read(path)
+			.with_context(|| format!("no sequence at `{}`", path.display()))?;
+		ron::de::from_reader(reader)
+			.with_context(|| format!("failed parsing sequence `{}`", path.display()))
+	}
+
+	/// Logs `error` and shows it in a dialog, for failures the player should know about that
+	/// the game can carry on past.
+	fn show_error(&mut self, error: &anyhow::Error) {
+		error!("{:#}", error);
+		self.modals.push(
+			Modal::new("Something went wrong", format!("{:#}", error))
+				.button("Continue", ModalAction::Close)
+				.button("Quit", ModalAction::Quit),
+		);
+	}
+
+	fn modal_action(&mut self, engine: &Engine<GameState>, action: ModalAction) {
+		match action {
+			ModalAction::Close => (),
+			ModalAction::Quit => ggez::event::quit(&mut self.ctx),
+			ModalAction::SaveAndQuit => match self.save_maps(engine) {
+				Ok(()) => ggez::event::quit(&mut self.ctx),
+				Err(error) => self.modals.push(Modal::notice(
+					"Saving failed",
+					format!("Nothing was lost, the game keeps running: {:#}", error),
+				)),
+			},
+		}
+	}
+
+	/// Exports each map with unsaved changes as a text map named after it, which `/import text`
+	/// loads back.
+	fn save_maps(&mut self, engine: &Engine<GameState>) -> anyhow::Result<()> {
+		for name in self.unsaved_maps.clone() {
+			// Maps can't be removed yet, but one that is gone has nothing to save
+			if engine.maps.contains_key(&name) {
+				let path = self.export_map_text(engine, &name, &name)?;
+				info!("Saved map `{}` to `{}`", name, path.display());
+			}
+			self.unsaved_maps.remove(&name);
+		}
+		Ok(())
+	}
+
+	fn load_tutorial(&mut self, path: &Path) -> anyhow::Result<Tutorial> {
+		let reader = self
+			.read(path)
+			.with_context(|| format!("no tutorial at `{}`", path.display()))?;
+		ron::de::from_reader(reader)
+			.with_context(|| format!("failed parsing tutorial `{}`", path.display()))
+	}
+
+	/// Whether the tutorial lets input causing `event` through, `None` for input it has no
+	/// event for.
+	fn tutorial_allows(&self, event: Option<&TutorialEvent>) -> bool {
+		self.tutorial
+			.as_ref()
+			.map_or(true, |tutorial| tutorial.allows(event))
+	}
+
+	/// Tells the tutorial what happened, ending it after its last step.
+	fn tutorial_event(&mut self, event: TutorialEvent) {
+		let tutorial = match &mut self.tutorial {
+			Some(tutorial) => tutorial,
+			None => return,
+		};
+		if tutorial.event(&event) && tutorial.is_finished() {
+			let message = format!("tutorial `{}` done", tutorial.title());
+			self.chat.post("system", message);
+			self.tutorial = None;
+		}
+	}
+
+	/// Starts the sequence's steps that are due and glides the camera, dropping the sequence once
+	/// it is done.
+	fn update_sequence(&mut self, ecs: &mut shipyard::World, engine: &mut Engine<GameState>) {
+		let delta = ggez::timer::delta(&self.ctx).as_secs_f32();
+		let started = match &mut self.sequence {
+			Some(sequence) => sequence.advance(delta),
+			None => return,
+		};
+		for step in started {
+			self.camera_glide = None;
+			if let Err(error) = self.start_sequence_step(ecs, engine, &step) {
+				// A mistake in the scenario's data shouldn't stop the rest of it
+				self.chat
+					.post("system", format!("sequence step failed: {:#}", error));
+			}
+		}
+		let progress = match &self.sequence {
+			Some(sequence) if sequence.is_finished() => {
+				self.sequence = None;
+				self.camera_glide = None;
+				return;
+			}
+			Some(sequence) => sequence.current().map_or(1.0, |(_step, progress)| progress),
+			None => return,
+		};
+		if let Some((from, to)) = self.camera_glide {
+			// Eases in and out
+			let t = progress * progress * (3.0 - 2.0 * progress);
+			self.view_center =
+				na::Point2::new(from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+			self.tiles_meshes.clear();
+		}
+	}
+
+	fn start_sequence_step(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		step: &SequenceStep,
+	) -> anyhow::Result<()> {
+		match step {
+			SequenceStep::MoveCamera { to, .. } => {
+				let coord = to.resolve(&engine.maps)?;
+				self.glide_camera_to(engine, coord)?;
+			}
+			SequenceStep::FocusEntity { tag, .. } => {
+				let coord = ecs
+					.run(|tags: View<Tags>, coords: View<MapCoord>| {
+						query_entities_with_tag(&tags, tag)
+							.into_iter()
+							.find_map(|entity| coords.get(entity).ok().copied())
+					})
+					.with_context(|| format!("no entity on a map is tagged {}", tag))?;
+				self.glide_camera_to(engine, coord)?;
+			}
+			SequenceStep::Impact { at, strength } => {
+				let at = at.resolve(&engine.maps)?;
+				self.screen_event(ScreenEvent::Impact {
+					at: self.hex.to_linear(at.coord),
+					strength: *strength,
+				});
+			}
+			SequenceStep::Discovery { at } => {
+				let at = at.resolve(&engine.maps)?;
+				self.animations
+					.play_discovery(at.map, self.hex.to_linear(at.coord));
+			}
+			SequenceStep::Dialog { .. } | SequenceStep::Wait { .. } => (),
+		}
+		Ok(())
+	}
+
+	/// Glides to `coord` over the current step when it is on the visible map, otherwise cuts
+	/// straight to it.
+	fn glide_camera_to(
+		&mut self,
+		engine: &mut Engine<GameState>,
+		coord: MapCoord,
+	) -> anyhow::Result<()> {
+		if engine.maps.get_index_of(&self.visible_map) != Some(coord.map) {
+			return self.jump_to_coord(engine, coord);
+		}
+		let from = (self.view_center.x, self.view_center.y);
+		self.camera_glide = Some((from, self.hex.to_linear(coord.coord)));
+		Ok(())
+	}
+
+	fn update_cursor(&mut self) {
+		let cursor = self.interaction.cursor();
+		if cursor != self.cursor {
+			mouse::set_cursor_type(&mut self.ctx, cursor);
+			self.cursor = cursor;
+		}
+	}
+
+	fn tick(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		self.step_move_orders(ecs, engine)?;
+		Ok(())
+	}
+
+	/// Orders the box selected entities to `to`, after their current orders if `append`.
+	fn order_move(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+		to: MapCoord,
+		append: bool,
+	) -> anyhow::Result<()> {
+		let on_map = engine
+			.maps
+			.get_index(to.map)
+			.and_then(|(_name, tile_map)| tile_map.get_tile(to.coord))
+			.is_some();
+		if !on_map {
+			self.screen_event(ScreenEvent::SelectionError);
+			return Ok(());
+		}
+		let commanded = self.commanded(ecs);
+		ecs.run(|entities: EntitiesView, mut orders: ViewMut<MoveOrder>| {
+			for &entity in &commanded {
+				let appended = append
+					&& match (&mut orders).get(entity) {
+						Ok(order) => {
+							order.push(to);
+							true
+						}
+						Err(_) => false,
+					};
+				if !appended {
+					entities.add_component(&mut orders, MoveOrder::new(to), entity);
+				}
+			}
+		});
+		Ok(())
+	}
+
+	/// Turns patrolling on for the box selected entities with orders, between where they are and
+	/// their waypoints, or off if they all already patrol.
+	fn toggle_patrol(&mut self, ecs: &mut shipyard::World) -> anyhow::Result<()> {
+		let commanded = self.commanded(ecs);
+		let (ordered, patrolling) = ecs.run(
+			|mut orders: ViewMut<MoveOrder>, coords: View<MapCoord>| -> (usize, bool) {
+				let ordered: Vec<EntityId> = commanded
+					.iter()
+					.copied()
+					.filter(|&entity| orders.get(entity).map_or(false, |order| !order.is_empty()))
+					.collect();
+				let stop = ordered.iter().all(|&entity| {
+					orders
+						.get(entity)
+						.map_or(false, |order| order.is_patrolling())
+				});
+				for &entity in &ordered {
+					let from = coords.get(entity).ok().copied();
+					if let (Ok(order), Some(from)) = ((&mut orders).get(entity), from) {
+						if stop {
+							order.stop_patrol();
+						} else if !order.is_patrolling() {
+							order.patrol_from(from);
+						}
+					}
+				}
+				(ordered.len(), !stop)
+			},
+		);
+		match (ordered, patrolling) {
+			(0, _) => self
+				.chat
+				.post("system", "no selected units have orders to patrol"),
+			(count, true) => self.chat.post("system", format!("{} patrolling", count)),
+			(count, false) => self
+				.chat
+				.post("system", format!("{} stopped patrolling", count)),
+		}
+		Ok(())
+	}
+
+	/// The box selected entities that take orders, units in an army are ordered through its
+	/// banner instead.
+	fn commanded(&self, ecs: &shipyard::World) -> Vec<EntityId> {
+		let box_selected = &self.box_selected;
+		ecs.run(|armies: View<Army>| {
+			let mut commanded = vec![];
+			for &entity in box_selected {
+				let entity = (&armies)
+					.iter()
+					.with_id()
+					.find(|(_banner, army)| army.contains(entity))
+					.map_or(entity, |(banner, _army)| banner);
+				if !commanded.contains(&entity) {
+					commanded.push(entity);
+				}
+			}
+			commanded
+		})
+	}
+
+	/// Plays the first box selected unit attacking the second, there is no combat yet to do it.
+	fn preview_attack(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let (attacker, defender) = match self.box_selected[..] {
+			[attacker, defender, ..] => (attacker, defender),
+			_ => {
+				self.chat.post(
+					"system",
+					"box select two units, the first attacks the second",
+				);
+				return Ok(());
+			}
+		};
+		let (from, to) = ecs.run(|coords: View<MapCoord>| {
+			(
+				coords.get(attacker).ok().copied(),
+				coords.get(defender).ok().copied(),
+			)
+		});
+		let toward = match (from, to) {
+			(Some(from), Some(to)) if from.map == to.map => {
+				let (name, tile_map) = engine
+					.maps
+					.get_index(from.map)
+					.context("unit stands on a map that doesn't exist")?;
+				// Fighting within about a screen of the camera brings on the war music
+				let (x, y) = self.hex.to_linear(to.coord);
+				let distance =
+					((x - self.view_center.x).powi(2) + (y - self.view_center.y).powi(2)).sqrt();
+				if *name == self.visible_map && distance < self.zoom {
+					self.music.combat_nearby();
+				}
+				linear_delta(self.hex, tile_map, from.coord, to.coord)
+			}
+			_ => {
+				self.chat
+					.post("system", "both units have to stand on the same map");
+				return Ok(());
+			}
+		};
+		self.animations.play(CombatEvent::Attack {
+			attacker,
+			defender,
+			toward,
+		});
+		Ok(())
+	}
+
+	/// Kills the box selected units, taking them off the map right away while their deaths play.
+	fn preview_death(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let dying = std::mem::take(&mut self.box_selected);
+		let hex = self.hex;
+		for entity in dying {
+			let death = ecs.run(|coords: View<MapCoord>, draw_sprites: View<DrawSprite>| {
+				let coord = coords.get(entity).ok()?;
+				let sprite = draw_sprites.get(entity).ok()?;
+				Some(CombatEvent::Death {
+					entity,
+					sprite: sprite.clone(),
+					map: coord.map,
+					at: hex.to_linear(coord.coord),
+				})
+			});
+			if let Some(death) = death {
+				self.animations.play(death);
+				if self.selected == Some(entity) {
+					self.selected = None;
+				}
+				Self::delete_from_map(ecs, engine, entity)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Groups the box selected units under a new banner, they must all stand on the same tile.
+	fn form_army(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let members = self.box_selected.clone();
+		if members.len() < 2 {
+			self.chat
+				.post("system", "select at least two units to form an army");
+			return Ok(());
+		}
+		if self.commanded(ecs).len() != members.len() {
+			self.chat.post(
+				"system",
+				"some of the selected units are already in an army, /army split it first",
+			);
+			return Ok(());
+		}
+		let coords: Vec<MapCoord> = ecs.run(|coords: View<MapCoord>| {
+			members
+				.iter()
+				.filter_map(|&entity| coords.get(entity).ok().copied())
+				.collect()
+		});
+		let at = match coords.first() {
+			Some(&at) if coords.len() == members.len() && coords.iter().all(|&c| c == at) => at,
+			_ => {
+				self.chat
+					.post("system", "units must share a tile to form an army");
+				return Ok(());
+			}
+		};
+		// The banner takes the orders from now on, the members' own would fight it
+		ecs.run(|mut orders: ViewMut<MoveOrder>| {
+			for &member in &members {
+				if let Ok(order) = (&mut orders).get(member) {
+					*order = MoveOrder::default();
+				}
+			}
+		});
+		let count = members.len();
+		let banner = ecs
+			.try_entity_builder()?
+			.try_with(Army::new(members))?
+			.try_build()?;
+		ecs.run(|entities: EntitiesView, coords: ViewMut<MapCoord>| {
+			engine.move_entity_to_coord(banner, at, entities, coords)
+		})?;
+		self.chat
+			.post("system", format!("army of {} formed", count));
+		Ok(())
+	}
+
+	/// Disbands the armies of the box selected units, their members stay where they are.
+	fn split_army(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let box_selected = &self.box_selected;
+		let banners: Vec<EntityId> = ecs.run(|armies: View<Army>| {
+			(&armies)
+				.iter()
+				.with_id()
+				.filter(|(_banner, army)| box_selected.iter().any(|&entity| army.contains(entity)))
+				.map(|(banner, _army)| banner)
+				.collect()
+		});
+		if banners.is_empty() {
+			self.chat.post("system", "no selected units are in an army");
+			return Ok(());
+		}
+		for &banner in &banners {
+			Self::delete_from_map(ecs, engine, banner)?;
+		}
+		self.chat
+			.post("system", format!("{} armies split", banners.len()));
+		Ok(())
+	}
+
+	/// Advances the hops under way, putting each entity on its new tile as its hop completes,
+	/// then starts every entity with a move order and no hop on its next hop, army members along
+	/// with their banner.
+	fn step_move_orders(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let arrived = ecs.run(|mut hops: ViewMut<MoveHop>| -> Vec<(EntityId, MoveHop)> {
+			let arrived: Vec<_> = (&mut hops)
+				.iter()
+				.with_id()
+				.filter_map(|(entity, hop)| {
+					if hop.advance() {
+						Some((entity, *hop))
+					} else {
+						None
+					}
+				})
+				.collect();
+			for &(entity, _hop) in &arrived {
+				hops.remove(entity);
+			}
+			arrived
+		});
+		for (entity, hop) in arrived {
+			// Anything taken off the map or moved some other way meanwhile stays where it is now
+			let still_on_from =
+				ecs.run(|coords: View<MapCoord>| coords.get(entity).ok() == Some(&hop.from));
+			if still_on_from {
+				ecs.run(|entities: EntitiesView, coords: ViewMut<MapCoord>| {
+					engine.move_entity_to_coord(entity, hop.to, entities, coords)
+				})?;
+				self.arrived.push((entity, hop.to));
+			}
+		}
+
+		let maps = &engine.maps;
+		let steps = ecs.run(
+			|mut orders: ViewMut<MoveOrder>,
+			 coords: View<MapCoord>,
+			 hops: View<MoveHop>|
+			 -> Vec<(EntityId, MapCoord)> {
+				let mut steps = vec![];
+				for (entity, (order, &coord)) in (&mut orders, &coords).iter().with_id() {
+					if order.is_empty() || hops.contains(entity) {
+						continue;
+					}
+					if let Some((_name, tile_map)) = maps.get_index(coord.map) {
+						if let Some(next) = order.next_step(coord, tile_map) {
+							steps.push((entity, next));
+						}
+					}
+				}
+				steps
+			},
+		);
+		let hop_ticks = self.movement.hop_ticks;
+		for (entity, to) in steps {
+			ecs.run(
+				|entities: EntitiesView,
+				 armies: View<Army>,
+				 coords: View<MapCoord>,
+				 mut hops: ViewMut<MoveHop>| {
+					let members = armies
+						.get(entity)
+						.map_or_else(|_| vec![], |army| army.members().to_vec());
+					for entity in std::iter::once(entity).chain(members) {
+						if let Ok(&from) = coords.get(entity) {
+							entities.add_component(
+								&mut hops,
+								MoveHop::new(from, to, hop_ticks),
+								entity,
+							);
+						}
+					}
+				},
+			);
+		}
+		Ok(())
+	}
+
+	/// The move order paths of the box selected entities or their armies, each starting at the
+	/// entity.
+	fn order_paths(
+		&self,
+		ecs: &shipyard::World,
+		engine: &Engine<GameState>,
+	) -> Vec<Vec<(f32, f32)>> {
+		let visible_map = match engine.maps.get_index_of(&self.visible_map) {
+			Some(map) => map,
+			None => return vec![],
+		};
+		let commanded = self.commanded(ecs);
+		let hex = self.hex;
+		ecs.run(|orders: View<MoveOrder>, coords: View<MapCoord>| {
+			commanded
+				.iter()
+				.filter_map(|&entity| {
+					let order = orders.get(entity).ok()?;
+					let start = coords.get(entity).ok()?;
+					if order.is_empty() || start.map != visible_map {
+						return None;
+					}
+					let waypoints = order
+						.waypoints()
+						.take_while(|waypoint| waypoint.map == visible_map)
+						.map(|waypoint| hex.to_linear(waypoint.coord));
+					Some(
+						std::iter::once(hex.to_linear(start.coord))
+							.chain(waypoints)
+							.collect(),
+					)
+				})
+				.collect()
+		})
+	}
+
+	fn restrict_view_center(
+		&mut self,
+		engine: &Engine<GameState>,
+		delta: Duration,
+	) -> anyhow::Result<()> {
+		let map = engine
+			.maps
+			.get(&self.visible_map)
+			.context("visible map does not exist")?;
+		let held = self.interaction.mode() == InteractionMode::DragPan;
+		let delta = delta.as_secs_f32();
+		let before = self.view_center;
+
+		match self.hex {
+			HexOrientation::PointyTop => {
+				let (_full_max_x, max_y) =
+					self.hex.to_linear(Coord::new_axial(map.width, map.height));
+				self.view_center.y =
+					self.camera
+						.constrain(self.view_center.y, 0.0, max_y, held, delta);
+
+				let view_coord = self.hex.from_linear(self.view_center.x, self.view_center.y);
+				let (min_x, _y) = self.hex.to_linear(Coord::new_axial(0, view_coord.r()));
+				let (max_x, _y) = self
+					.hex
+					.to_linear(Coord::new_axial(map.width, view_coord.r()));
+				if map.wraps_x {
+					if self.view_center.x < min_x - 0.5 {
+						trace!("Wrapping map on X min");
+						self.view_center.x += max_x - min_x + 1.0;
+					} else if self.view_center.x > max_x + 0.5 {
+						trace!("Wrapping map on X max");
+						self.view_center.x -= max_x - min_x + 1.0;
+					}
+				} else {
+					self.view_center.x =
+						self.camera
+							.constrain(self.view_center.x, min_x, max_x, held, delta);
+				}
+			}
+			HexOrientation::FlatTop => {
+				// Mirrored, the columns are kept within the map's width and each column's
+				// tiles within its height
+				let (max_x, _y) = self.hex.to_linear(Coord::new_axial(map.width, 0));
+				let (half_x, _half_y) = self.hex.half_size();
+				if map.wraps_x {
+					// Each column right is also half a hex down, so the seam is on a slant
+					let (seam_x, seam_y) = self.hex.axial_to_linear(map.width as f32 + 1.0, 0.0);
+					if self.view_center.x < -half_x {
+						trace!("Wrapping map on X min");
+						self.view_center.x += seam_x;
+						self.view_center.y += seam_y;
+					} else if self.view_center.x > max_x + half_x {
+						trace!("Wrapping map on X max");
+						self.view_center.x -= seam_x;
+						self.view_center.y -= seam_y;
+					}
+				} else {
+					self.view_center.x =
+						self.camera
+							.constrain(self.view_center.x, 0.0, max_x, held, delta);
+				}
+
+				let view_coord = self.hex.from_linear(self.view_center.x, self.view_center.y);
+				let (_x, min_y) = self.hex.to_linear(Coord::new_axial(view_coord.q(), 0));
+				let (_x, max_y) = self
+					.hex
+					.to_linear(Coord::new_axial(view_coord.q(), map.height));
+				self.view_center.y =
+					self.camera
+						.constrain(self.view_center.y, min_y, max_y, held, delta);
+			}
+		}
+		if self.camera.policy == CameraPolicy::Elastic && self.view_center != before {
+			// The tile mesh is built around the center, so follow the pull back
+			self.tiles_meshes.clear();
+		}
+
+		Ok(())
+	}
+
+	fn draw(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let delta = ggez::timer::delta(&self.ctx);
+		self.zoom -= (self.zoom - self.screen_tiles) * (delta.as_secs_f32() * 5.0);
+		self.restrict_view_center(engine, delta)?;
+		let (shake_x, shake_y) = self.effects.shake_offset(self.zoom);
+		let screen_coords = Rect::new(
+			self.view_center.x - self.zoom * 0.5 * self.aspect_ratio + shake_x,
+			self.view_center.y - self.zoom * 0.5 + shake_y,
+			self.zoom * self.aspect_ratio,
+			self.zoom,
+		);
+		self.view_rect = screen_coords;
+		let screen_pixels = Rect::new(0.0, 0.0, self.screen.width(), self.screen.height());
+		// Whatever the background layer is doing, the last frame shouldn't show through
+		graphics::clear(&mut self.ctx, graphics::BLACK);
+		// Filled in by the entities, cleared here so turning them off hides the badges too
+		self.stack_badges.clear();
+		let mut space = None;
+		for (layer, callback) in self.layers.callbacks() {
+			if space != Some(layer.space()) {
+				space = Some(layer.space());
+				let coordinates = match layer.space() {
+					LayerSpace::World => screen_coords,
+					LayerSpace::Screen => screen_pixels,
+				};
+				graphics::set_screen_coordinates(&mut self.ctx, coordinates)?;
+			}
+			callback(self, ecs, engine)?;
+		}
+		graphics::present(&mut self.ctx)?;
+		self.text.end_frame();
+		Ok(())
+	}
+
+	/// The whole visible map in the bottom right corner with the view outlined on it.
+	fn draw_minimap(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		if !self.show_minimap {
+			return Ok(());
+		}
+		let tile_map = engine
+			.maps
+			.get(&self.visible_map)
+			.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
+		let area = whole_map_area(self.hex, tile_map);
+		let Rect {
+			x: left,
+			y: top,
+			w: width,
+			h: height,
+		} = self.minimap_bounds(tile_map);
+		let size = (width as u16, height as u16);
+		if self.minimap.is_stale(size) {
+			let meshes =
+				self.build_tiles_meshes(tile_map, whole_map_tiles(self.hex, tile_map), true, 0)?;
+			self.minimap
+				.render(&mut self.ctx, size, area, graphics::BLACK, |ctx| {
+					meshes
+						.iter()
+						.flatten()
+						.try_for_each(|mesh| mesh.draw(ctx, DrawParam::new()))
+				})?;
+		}
+		self.minimap
+			.draw(&mut self.ctx, DrawParam::new().dest([left, top]))?;
+
+		// The view clipped to the map, past a wrapping seam it just stops at the edge
+		let to_minimap_x = |x: f32| left + ((x - area.x) / area.w).max(0.0).min(1.0) * width;
+		let to_minimap_y = |y: f32| top + ((y - area.y) / area.h).max(0.0).min(1.0) * height;
+		let view = self.view_rect;
+		let (view_left, view_top) = (to_minimap_x(view.left()), to_minimap_y(view.top()));
+		let outline = Rect::new(
+			view_left,
+			view_top,
+			to_minimap_x(view.right()) - view_left,
+			to_minimap_y(view.bottom()) - view_top,
+		);
+		if outline.w > 0.0 && outline.h > 0.0 {
+			let line_width = self.accessibility.line_width(1.5 * self.screen.ui_scale());
+			let mesh = graphics::Mesh::new_rectangle(
+				&mut self.ctx,
+				DrawMode::stroke(line_width),
+				outline,
+				graphics::WHITE,
+			)?;
+			mesh.draw(&mut self.ctx, DrawParam::new())?;
+		}
+		Ok(())
+	}
+
+	/// Where the minimap of `tile_map` goes on the screen, in the bottom right corner.
+	fn minimap_bounds(&self, tile_map: &TileMap) -> Rect {
+		let area = whole_map_area(self.hex, tile_map);
+		let width = (MINIMAP_WIDTH * self.screen.ui_scale()).ceil();
+		let height = (width * area.h / area.w).ceil().max(1.0);
+		let margin = 8.0 * self.screen.ui_scale();
+		Rect::new(
+			self.screen.width() - width - margin,
+			self.screen.height() - height - margin,
+			width,
+			height,
+		)
+	}
+
+	fn draw_flash(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let size = (self.screen.width(), self.screen.height());
+		self.effects.draw_flash(&mut self.ctx, size)?;
+		Ok(())
+	}
+
+	fn draw_context_menu(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		if let Some(menu) = &self.context_menu {
+			let skin = self.panel_skin.map(|id| (&self.ui_atlas, id));
+			menu.draw(&mut self.ctx, &mut self.text, self.screen.ui_scale(), skin)?;
+		}
+		Ok(())
+	}
+
+	fn draw_inspector(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		if let Some(inspector) = &self.inspector {
+			let skin = self.panel_skin.map(|id| (&self.ui_atlas, id));
+			inspector.draw(
+				&mut self.ctx,
+				&mut self.text,
+				(self.screen.width(), self.screen.height()),
+				self.screen.ui_scale(),
+				skin,
+			)?;
+		}
+		Ok(())
+	}
+
+	fn draw_hall_of_fame(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		if self.show_hall_of_fame {
+			let skin = self.panel_skin.map(|id| (&self.ui_atlas, id));
+			hall_of_fame::draw_hall_of_fame(
+				&mut self.ctx,
+				&mut self.text,
+				&self.statistics,
+				(self.screen.width(), self.screen.height()),
+				self.screen.ui_scale(),
+				skin,
+			)?;
+		}
+		Ok(())
+	}
+
+	fn draw_sequence(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let sequence = match &self.sequence {
+			Some(sequence) => sequence,
+			None => return Ok(()),
+		};
+		if let Some((SequenceStep::Dialog { speaker, text, .. }, _progress)) = sequence.current() {
+			let hint = if sequence.skippable() {
+				"Space: next, Esc: skip"
+			} else {
+				"Space: next"
+			};
+			let skin = self.panel_skin.map(|id| (&self.ui_atlas, id));
+			sequence::draw_dialog(
+				&mut self.ctx,
+				&mut self.text,
+				speaker.as_deref(),
+				text,
+				hint,
+				(self.screen.width(), self.screen.height()),
+				self.screen.ui_scale(),
+				skin,
+			)?;
+		}
+		Ok(())
+	}
+
+	fn draw_modal(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		if let Some(modal) = self.modals.current() {
+			let skin = self.panel_skin.map(|id| (&self.ui_atlas, id));
+			modal.draw(
+				&mut self.ctx,
+				&mut self.text,
+				(self.screen.width(), self.screen.height()),
+				self.screen.ui_scale(),
+				skin,
+			)?;
+		}
+		Ok(())
+	}
+
+	fn draw_settings(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		if let Some(settings) = &self.settings {
+			let values: Vec<(Setting, String)> = settings
+				.tab
+				.settings()
+				.into_iter()
+				.map(|setting| (setting, self.setting_value(setting)))
+				.collect();
+			let skin = self.panel_skin.map(|id| (&self.ui_atlas, id));
+			settings.draw(
+				&mut self.ctx,
+				&mut self.text,
+				(self.screen.width(), self.screen.height()),
+				self.screen.ui_scale(),
+				skin,
+				|setting| {
+					values
+						.iter()
+						.find(|(s, _value)| *s == setting)
+						.map(|(_s, value)| value.clone())
+						.unwrap_or_default()
+				},
+			)?;
+		}
+		Ok(())
+	}
+
+	fn draw_entities(
+		&mut self,
+		ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let span = trace_span!("draw_entities", sprites = field::Empty);
+		let _enter = span.enter();
+		let mut sprite_count = 0u32;
+		// TODO: SpriteBatch doesn't seem terribly efficient, examine if it would be better to either cache and reuse it like the map mesh, or to build a mesh for it instead...
+		let pixels_per_tile = self.screen.height() / self.zoom;
+		let mip_level = self
+			.entity_atlas
+			.mip_level(self.entity_texels_per_tile / pixels_per_tile);
+		if self.entity_atlas.len_atlases() != self.entity_spritebatches.len()
+			|| self.entity_mip_level != mip_level
+		{
+			self.entity_spritebatches.clear();
+			self.entity_spritebatches
+				.reserve(self.entity_atlas.len_atlases());
+			for i in 0..self.entity_atlas.len_atlases() {
+				self.entity_spritebatches.push(SpriteBatch::new(
+					self.entity_atlas
+						.get_mip_image_by_index(i, mip_level)
+						.context("Atlas is missing an image")?
+						.clone(),
+				));
+			}
+			self.entity_mip_level = mip_level;
+		}
+
+		let tile_map = engine
+			.maps
+			.get(&self.visible_map)
+			.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
+		let draw_sprites = ecs.try_borrow::<View<DrawSprite>>()?;
+		let armies = ecs.try_borrow::<View<Army>>()?;
+		let hops = ecs.try_borrow::<View<MoveHop>>()?;
+		let fraction = self.clock.tick_fraction();
+		let (min, max) = self.draw_bounds();
+		let detail = self.lod.entity_detail(self.screen_tiles, pixels_per_tile);
+		let mut icons = vec![];
+		self.stack_badges.clear();
+		for (_coord, tile, (px, py)) in tile_map.iter_linear_rect(self.hex, min, max) {
+			if detail == EntityDetail::Strategic {
+				// Individual sprites are unreadable this far out, so a single icon marks the tile.
+				if tile.entities.iter().any(|&e| draw_sprites.get(e).is_ok()) {
+					icons.push(na::Point2::new(px, py));
+				}
+				continue;
+			}
+			let units = tile
+				.entities
+				.iter()
+				.filter(|&&e| draw_sprites.get(e).is_ok())
+				.count();
+			let army = || tile.entities.iter().any(|&e| armies.get(e).is_ok());
+			if detail == EntityDetail::Counts {
+				if units > 0 {
+					self.stack_badges.push(StackBadge {
+						center: (px, py),
+						radius: 0.3,
+						units,
+						army: army(),
+					});
+				}
+				continue;
+			}
+			if units > 1 {
+				self.stack_badges.push(StackBadge {
+					center: (px + 0.3, py - 0.3),
+					radius: 0.15,
+					units,
+					army: army(),
+				});
+			}
+			for &entity in &tile.entities {
+				if let Ok(draw) = draw_sprites.get(entity) {
+					if let Some(sprite) = self.entity_atlas.get_entry_by_name(&draw.sprite_name) {
+						// Units part way through a hop are drawn along the way to the next tile
+						let (ox, oy) = hops
+							.get(entity)
+							.map_or((0.0, 0.0), |hop| hop.offset(self.hex, tile_map, fraction));
+						let (lx, ly) = self.animations.sprite_offset(entity);
+						let area = Rect::new(
+							px + ox + lx + draw.rect.x,
+							py + oy + ly + draw.rect.y,
+							draw.rect.w,
+							draw.rect.h,
+						);
+						let flash = self.animations.hit_flash(entity);
+						let tint = self.light_tint;
+						let color = Color::new(
+							tint.r + (1.0 - tint.r) * flash,
+							tint.g * (1.0 - flash * 0.75),
+							tint.b * (1.0 - flash * 0.75),
+							tint.a,
+						);
+						let params = sprite.draw_param_at_level(area, mip_level).color(color);
+						let batch = &mut self.entity_spritebatches[sprite.get_atlas_idx()];
+						batch.add(params);
+						sprite_count += 1;
+					}
+				}
+			}
+		}
+		if detail == EntityDetail::Sprites {
+			let visible_map = engine.maps.get_index_of(&self.visible_map);
+			let animations = &self.animations;
+			for (draw, (px, py), alpha) in visible_map
+				.into_iter()
+				.flat_map(|map| animations.ghosts(map))
+			{
+				if let Some(sprite) = self.entity_atlas.get_entry_by_name(&draw.sprite_name) {
+					let area =
+						Rect::new(px + draw.rect.x, py + draw.rect.y, draw.rect.w, draw.rect.h);
+					let mut color = self.light_tint;
+					color.a *= alpha;
+					let params = sprite.draw_param_at_level(area, mip_level).color(color);
+					self.entity_spritebatches[sprite.get_atlas_idx()].add(params);
+					sprite_count += 1;
+				}
+			}
+		}
+		span.record("sprites", &sprite_count);
+		let params = DrawParam::new();
+		for batch in &mut self.entity_spritebatches {
+			batch.draw(&mut self.ctx, params)?;
+			batch.clear();
+		}
+
+		if !self.stack_badges.is_empty() {
+			let line_width = self.accessibility.line_width(0.03);
+			let mut builder = graphics::MeshBuilder::new();
+			for badge in &self.stack_badges {
+				let point = na::Point2::new(badge.center.0, badge.center.1);
+				let fill = if badge.army {
+					Color::new(0.85, 0.65, 0.15, 1.0)
+				} else {
+					Color::new(0.3, 0.3, 0.35, 1.0)
+				};
+				builder.circle(DrawMode::fill(), point, badge.radius, 0.01, fill);
+				builder.circle(
+					DrawMode::stroke(line_width),
+					point,
+					badge.radius,
+					0.01,
+					graphics::BLACK,
+				);
+			}
+			let mesh = builder.build(&mut self.ctx)?;
+			mesh.draw(&mut self.ctx, DrawParam::new())?;
+		}
+
+		if !icons.is_empty() {
+			if self.strategic_icon_mesh.is_none() {
+				self.strategic_icon_mesh = Some(
+					graphics::MeshBuilder::new()
+						.circle(
+							DrawMode::fill(),
+							na::Point2::new(0.0, 0.0),
+							0.3,
+							0.02,
+							Color::new(0.95, 0.95, 0.85, 1.0),
+						)
+						.circle(
+							DrawMode::stroke(self.accessibility.line_width(0.06)),
+							na::Point2::new(0.0, 0.0),
+							0.3,
+							0.02,
+							graphics::BLACK,
+						)
+						.build(&mut self.ctx)?,
+				);
+			}
+			if let Some(mesh) = &self.strategic_icon_mesh {
+				for dest in icons {
+					mesh.draw(&mut self.ctx, DrawParam::new().dest(dest))?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Clears to the visible map's background and draws its starfield and parallax layers, in
+	/// screen pixels since the layers scroll at their own rates.
+	fn draw_ambient(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let (width, height) = (self.screen.width(), self.screen.height());
+		graphics::set_screen_coordinates(&mut self.ctx, Rect::new(0.0, 0.0, width, height))?;
+		let ambient = engine
+			.maps
+			.get_index_of(&self.visible_map)
+			.and_then(|map| engine.map_ambient(map));
+		let ambient = match ambient {
+			Some(ambient) => ambient,
+			None => {
+				self.light_tint = graphics::WHITE;
+				graphics::clear(&mut self.ctx, graphics::BLACK);
+				return Ok(());
+			}
+		};
+		self.light_tint = Color::from(ambient.light_tint);
+		graphics::clear(&mut self.ctx, Color::from(ambient.background));
+		let pixels_per_tile = height / self.zoom;
+		let scroll = (
+			self.view_center.x * pixels_per_tile,
+			self.view_center.y * pixels_per_tile,
+		);
+		self.ambient_background.draw(
+			&mut self.ctx,
+			ambient,
+			scroll,
+			(width, height),
+			self.screen.ui_scale(),
+		)?;
+		Ok(())
+	}
+
+	/// Builds the meshes for `tiles`, each with its coordinate and where to draw it. Strategic
+	/// meshes are flat colored hexes, otherwise one mesh per tiles atlas and then one per atlas for
+	/// the decorations, `None` where nothing uses that atlas. Textured meshes draw from
+	/// `mip_level` of the atlas.
+	fn build_tiles_meshes<'a>(
+		&mut self,
+		tile_map: &TileMap,
+		tiles: impl Iterator<Item = (Coord, &'a Tile, (f32, f32))>,
+		strategic: bool,
+		mip_level: usize,
+	) -> anyhow::Result<Vec<Option<graphics::Mesh>>> {
+		let span = trace_span!("build_tiles_mesh", tiles = field::Empty, strategic);
+		let _enter = span.enter();
+		let mut tile_count = 0u32;
+		let mut mesh_builders: Vec<_> = (0..self.tiles_atlas.len_atlases())
+			.map(|_| (false, graphics::MeshBuilder::new()))
+			.collect();
+		let mut strategic_builder = graphics::MeshBuilder::new();
+		// Decorations get their own builders so they are drawn over every tile, not just the
+		// tiles that happen to come earlier in the same atlas
+		let mut decoration_builders: Vec<_> = (0..self.tiles_atlas.len_atlases())
+			.map(|_| (false, graphics::MeshBuilder::new()))
+			.collect();
+		for (coord, tile, (px, py)) in tiles {
+			tile_count += 1;
+			let idx: usize = tile.id.into();
+			// Anything without a drawable draws as the reserved unknown tile
+			let tile_drawable = self
+				.tiles_drawable
+				.get(idx)
+				.unwrap_or(&self.tiles_drawable[0]);
+			if strategic {
+				let color = tile_drawable.strategic_color;
+				let color: [f32; 4] = [color.r, color.g, color.b, color.a];
+				strategic_builder.raw(&hex_vertices(self.hex, (px, py), color), &HEX_INDICES, None);
+				continue;
+			}
+			let hash =
+				if tile_drawable.variants.len() > 1 || tile_drawable.info.orientations.len() > 1 {
+					coord.hash_with_seed(tile_map.seed)
+				} else {
+					0
+				};
+			let atlas_id = tile_drawable.pick_variant(hash);
+			let uv = self.tiles_atlas.get_entry(atlas_id);
+			let bounds = tile_drawable.info.bounds;
+			let mut pos = bounds;
+			pos.translate([px, py]);
+			let color = tile_drawable.info.color;
+			let color: [f32; 4] = [color.r, color.g, color.b, color.a];
+			let (active, mesh_builder) = &mut mesh_builders[uv.get_atlas_idx()];
+			*active = true;
+			let tile_orientation = match tile_drawable.info.orientations.len() {
+				0 => TileOrientation::default(),
+				// Shifted so the pick isn't correlated with the variant pick
+				len => tile_drawable.info.orientations[(hash >> 32) as usize % len],
+			};
+			if !tile_orientation.is_identity() {
+				// A rotated quad would sample outside its image, so draw the hex itself as a
+				// fan with each corner sampling the source corner it was turned from. Anything
+				// drawn outside the hex in the image is cropped.
+				let uv_of = |(x, y): (f32, f32)| {
+					uv.uv_at((x - bounds.x) / bounds.w, (y - bounds.y) / bounds.h)
+				};
+				let mut vertices = [Vertex {
+					pos: [px, py],
+					uv: uv_of((0.0, 0.0)),
+					color,
+				}; 7];
+				let corners = self.hex.corners();
+				for (corner, vertex) in vertices[1..].iter_mut().enumerate() {
+					let (cx, cy) = corners[corner];
+					vertex.pos = [px + cx, py + cy];
+					vertex.uv = uv_of(corners[tile_orientation.source_corner(corner)]);
+				}
+				mesh_builder.raw(
+					&vertices,
+					&[0, 1, 2, 0, 2, 3, 0, 3, 4, 0, 4, 5, 0, 5, 6, 0, 6, 1],
+					None,
+				);
+			} else {
+				mesh_builder.raw(
+					&[
+						Vertex {
+							// left-top
+							pos: [pos.left(), pos.top()],
+							uv: [uv.left(), uv.top()],
+							color,
+						},
+						Vertex {
+							// left-bottom
+							pos: [pos.left(), pos.bottom()],
+							uv: [uv.left(), uv.bottom()],
+							color,
+						},
+						Vertex {
+							// right-bottom
+							pos: [pos.right(), pos.bottom()],
+							uv: [uv.right(), uv.bottom()],
+							color,
+						},
+						Vertex {
+							// right-top
+							pos: [pos.right(), pos.top()],
+							uv: [uv.right(), uv.top()],
+							color,
+						},
+					],
+					&[0, 1, 2, 0, 2, 3],
+					None,
+				);
+			}
+			// Decorations without a drawable have nothing to fall back on so they are skipped
+			let decoration_drawable = tile.decoration.and_then(|decoration| {
+				let idx: usize = decoration.into();
+				self.decorations_drawable.get(idx)
+			});
+			if let Some(&decoration_drawable) = decoration_drawable {
+				let uv = self.tiles_atlas.get_entry(decoration_drawable);
+				let mut pos = DECORATION_BOUNDS;
+				pos.translate([px, py]);
+				let color: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+				let (active, mesh_builder) = &mut decoration_builders[uv.get_atlas_idx()];
+				*active = true;
+				mesh_builder.raw(
+					&[
+						Vertex {
+							pos: [pos.left(), pos.top()],
+							uv: [uv.left(), uv.top()],
+							color,
+						},
+						Vertex {
+							pos: [pos.left(), pos.bottom()],
+							uv: [uv.left(), uv.bottom()],
+							color,
+						},
+						Vertex {
+							pos: [pos.right(), pos.bottom()],
+							uv: [uv.right(), uv.bottom()],
+							color,
+						},
+						Vertex {
+							pos: [pos.right(), pos.top()],
+							uv: [uv.right(), uv.top()],
+							color,
+						},
+					],
+					&[0, 1, 2, 0, 2, 3],
+					None,
+				);
+			}
+		}
+		span.record("tiles", &tile_count);
+		let mut meshes = vec![];
+		if strategic {
+			let mesh = if tile_count > 0 {
+				Some(strategic_builder.build(&mut self.ctx)?)
+			} else {
+				None
+			};
+			meshes.push(mesh);
+		} else {
+			let builders = mesh_builders
+				.into_iter()
+				.enumerate()
+				.chain(decoration_builders.into_iter().enumerate());
+			for (idx, (active, mut builder)) in builders {
+				if !active {
+					meshes.push(None);
+				} else {
+					let texture = self
+						.tiles_atlas
+						.get_mip_image_by_index(idx, mip_level)
+						.context("failed to get image that must exist")?;
+					meshes.push(Some(builder.texture(texture.clone()).build(&mut self.ctx)?));
+				}
+			}
+		}
+		Ok(meshes)
+	}
+
+	/// The tiles atlas mip level to draw from with map units `pixels_per_unit` pixels across.
+	fn tiles_mip_level_at(&self, pixels_per_unit: f32) -> usize {
+		self.tiles_atlas
+			.mip_level(self.tiles_texels_per_unit / pixels_per_unit)
+	}
+
+	/// Renders all of `map_name` offscreen at `pixels_per_hex` and writes it as a PNG to
+	/// `exports/<file>` in the user data directory, the camera and window size don't matter. The
+	/// PNG is encoded in the background and the chat says once it is written, the map's metadata
+	/// is written next to it straight away.
+	fn export_map_png(
+		&mut self,
+		engine: &Engine<GameState>,
+		map_name: &str,
+		file: &str,
+		pixels_per_hex: f32,
+	) -> anyhow::Result<PathBuf> {
+		let (map, _name, tile_map) = engine
+			.maps
+			.get_full(map_name)
+			.with_context(|| format!("no map named `{}`", map_name))?;
+		if !(pixels_per_hex > 0.0 && pixels_per_hex.is_finite()) {
+			anyhow::bail!("pixels per hex must be above 0, not {}", pixels_per_hex);
+		}
+		let area = whole_map_area(self.hex, tile_map);
+		let width = (area.w * pixels_per_hex).ceil();
+		let height = (area.h * pixels_per_hex).ceil();
+		if width > MAX_EXPORT_PIXELS || height > MAX_EXPORT_PIXELS {
+			anyhow::bail!(
+				"a {}x{} export is over the {} pixel limit, use fewer pixels per hex",
+				width,
+				height,
+				MAX_EXPORT_PIXELS
+			);
+		}
+		let (width, height) = (width as u16, height as u16);
+
+		let mip_level = self.tiles_mip_level_at(pixels_per_hex);
+		let meshes = self.build_tiles_meshes(
+			tile_map,
+			whole_map_tiles(self.hex, tile_map),
+			false,
+			mip_level,
+		)?;
+		let (background, tint) = match engine.map_ambient(map) {
+			Some(ambient) => (
+				Color::from(ambient.background),
+				Color::from(ambient.light_tint),
+			),
+			None => (graphics::BLACK, graphics::WHITE),
+		};
+
+		let mut target = RenderTarget::new();
+		target.render(&mut self.ctx, (width, height), area, background, |ctx| {
+			meshes
+				.iter()
+				.flatten()
+				.try_for_each(|mesh| mesh.draw(ctx, DrawParam::new().color(tint)))
+		})?;
+		let pixels = target.to_rgba8(&mut self.ctx)?;
+		let path = export_path(file, "png");
+		self.write_export_info(&path, map_name, tile_map, pixels_per_hex)?;
+		let writer = self
+			.write(&path, WriteMode::Truncate)
+			.with_context(|| format!("failed opening `{}` for writing", path.display()))?;
+		// Encoding a big map takes a while, so it is done in the background
+		let written = path.clone();
+		self.jobs.spawn(move || {
+			let encoded = image::png::PNGEncoder::new(writer)
+				.encode(
+					&pixels,
+					width as u32,
+					height as u32,
+					image::ColorType::RGBA(8),
+				)
+				.with_context(|| format!("failed writing `{}`", written.display()));
+			let complete: JobCompletion = Box::new(move |state: &mut GameState| {
+				let message = match encoded {
+					Ok(()) => format!("exported to {}", written.display()),
+					Err(error) => format!("export failed: {:#}", error),
+				};
+				state.chat.post("system", message);
+			});
+			complete
+		});
+		Ok(path)
+	}
+
+	fn write_export_info(
+		&mut self,
+		image_path: &Path,
+		map_name: &str,
+		tile_map: &TileMap,
+		pixels_per_hex: f32,
+	) -> anyhow::Result<()> {
+		use std::io::Write;
+		let path = PathBuf::from(format!("{}.ron", image_path.display()));
+		let info = MapExportInfo {
+			map: map_name,
+			metadata: &tile_map.metadata,
+			seed: tile_map.seed,
+			pixels_per_hex,
+		};
+		let text = ron::ser::to_string_pretty(&info, ron::ser::PrettyConfig::default())
+			.context("failed serializing the map's metadata")?;
+		self.write(&path, WriteMode::Truncate)
+			.with_context(|| format!("failed opening `{}` for writing", path.display()))?
+			.write_all(text.as_bytes())
+			.with_context(|| format!("failed writing `{}`", path.display()))?;
+		Ok(())
+	}
+
+	/// Finishes the background jobs that are done, on this thread.
+	fn finish_jobs(&mut self) {
+		for finished in self.jobs.drain() {
+			match finished.result {
+				Ok(complete) => complete(self),
+				Err(error) => {
+					warn!("A background job failed: {}", error);
+					self.modals
+						.push(Modal::notice("Background job failed", error.to_string()));
+				}
+			}
+		}
+	}
+
+	/// Writes `map_name` as a text map to `exports/<file>` in the user data directory.
+	fn export_map_text(
+		&mut self,
+		engine: &Engine<GameState>,
+		map_name: &str,
+		file: &str,
+	) -> anyhow::Result<PathBuf> {
+		let tile_map = engine
+			.maps
+			.get(map_name)
+			.with_context(|| format!("no map named `{}`", map_name))?;
+		let path = export_path(file, "txt");
+		save_text_map(self, &path, tile_map, &engine.tile_types)?;
+		Ok(path)
+	}
+
+	/// Adds the text map at `file`, in the resources or user data, as a map named after the file.
+	fn import_map_text(
+		&mut self,
+		engine: &mut Engine<GameState>,
+		file: &str,
+	) -> anyhow::Result<String> {
+		world::generate_text_map(engine, self, Path::new(file))
+	}
+
+	fn draw_map(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let strategic = self.is_strategic();
+		let mip_level = self.tiles_mip_level_at(self.screen.height() / self.zoom);
+		if mip_level != self.tiles_mip_level {
+			self.tiles_mip_level = mip_level;
+			self.tiles_meshes.clear();
+		}
+		let changes = engine.take_tile_changes();
+		let (visible_map, _name, tile_map) = engine
+			.maps
+			.get_full(&self.visible_map)
+			.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
+		let changed: Vec<Coord> = changes
+			.iter()
+			.filter(|change| change.at.map == visible_map)
+			.map(|change| change.at.coord)
+			.collect();
+		if !changed.is_empty() {
+			self.minimap.invalidate();
+		}
+		if self.tiles_meshes.is_empty() || self.tiles_meshes_strategic != strategic {
+			let (min, max) = self.draw_bounds();
+			let (seam_x, seam_y) = self.hex.axial_to_linear(tile_map.width as f32 + 1.0, 0.0);
+			let mut chunks = BTreeMap::new();
+			for (coord, _tile, (px, py)) in tile_map.iter_linear_rect(self.hex, min, max) {
+				// How many times around the seam this copy of the tile is
+				let (x, _y) = self.hex.to_linear(coord);
+				let wraps = ((px - x) / seam_x).round() as i32;
+				let key = (
+					wraps,
+					(px / TILE_CHUNK_SIZE).floor() as i32,
+					(py / TILE_CHUNK_SIZE).floor() as i32,
+				);
+				chunks.entry(key).or_insert_with(Vec::new).push(coord);
+			}
+			self.tiles_meshes.clear();
+			let mut positions = std::mem::take(&mut self.tiles_positions);
+			for ((wraps, _x, _y), tiles) in chunks {
+				let shift = (seam_x * wraps as f32, seam_y * wraps as f32);
+				self.hex.to_linear_many(shift, &tiles, &mut positions);
+				let meshes = self.build_tiles_meshes(
+					tile_map,
+					chunk_tiles(tile_map, &tiles, &positions),
+					strategic,
+					mip_level,
+				)?;
+				self.tiles_meshes.push(TileMeshChunk {
+					tiles,
+					shift,
+					meshes,
+				});
+			}
+			self.tiles_positions = positions;
+			self.tiles_meshes_strategic = strategic;
+		} else {
+			// Only the chunks holding a changed tile are rebuilt, a wrapping map can show the
+			// same tile in more than one. ggez can only replace a mesh's vertices whole, so
+			// the chunk's meshes are built anew.
+			let mut positions = std::mem::take(&mut self.tiles_positions);
+			for idx in 0..self.tiles_meshes.len() {
+				let chunk = &mut self.tiles_meshes[idx];
+				if !chunk.tiles.iter().any(|coord| changed.contains(coord)) {
+					continue;
+				}
+				let tiles = std::mem::take(&mut chunk.tiles);
+				let shift = chunk.shift;
+				self.hex.to_linear_many(shift, &tiles, &mut positions);
+				let meshes = self.build_tiles_meshes(
+					tile_map,
+					chunk_tiles(tile_map, &tiles, &positions),
+					strategic,
+					mip_level,
+				)?;
+				self.tiles_meshes[idx] = TileMeshChunk {
+					tiles,
+					shift,
+					meshes,
+				};
+			}
+			self.tiles_positions = positions;
+		}
+		let param = DrawParam::new().color(self.light_tint);
+		// Mesh by mesh across the chunks so decorations are drawn over the tiles of every chunk
+		let layers = self
+			.tiles_meshes
+			.iter()
+			.map(|chunk| chunk.meshes.len())
+			.max()
+			.unwrap_or(0);
+		for layer in 0..layers {
+			for chunk in &self.tiles_meshes {
+				if let Some(Some(mesh)) = chunk.meshes.get(layer) {
+					mesh.draw(&mut self.ctx, param)?;
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Dark marks where units died, fading away after a while.
+	fn draw_corpses(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let visible_map = match engine.maps.get_index_of(&self.visible_map) {
+			Some(map) => map,
+			None => return Ok(()),
+		};
+		let mut builder = graphics::MeshBuilder::new();
+		let mut any = false;
+		for ((x, y), alpha) in self.animations.corpses(visible_map) {
+			builder.ellipse(
+				DrawMode::fill(),
+				na::Point2::new(x, y + 0.2),
+				0.3,
+				0.12,
+				0.01,
+				Color::new(0.15, 0.05, 0.05, 0.6 * alpha),
+			);
+			any = true;
+		}
+		if any {
+			let mesh = builder.build(&mut self.ctx)?;
+			mesh.draw(&mut self.ctx, DrawParam::new().color(self.light_tint))?;
+		}
+		Ok(())
+	}
+
+	/// Gold rings spreading out from where something was found, fading as they go.
+	fn draw_discoveries(
 		&mut self,
-		ecs: &mut shipyard::World,
+		_ecs: &mut shipyard::World,
 		engine: &mut Engine<GameState>,
 	) -> anyhow::Result<()> {
-		match self.selected {
-			None => (),
-			Some(entity) => {
-				self.selected = None;
-				let maps = &mut engine.maps;
-				ecs.run(
-					|mut all_storages: AllStoragesViewMut| -> anyhow::Result<()> {
-						{
-							let coords = all_storages.try_borrow::<View<MapCoord>>()?;
-							let coord = coords[entity];
-							let tile_map = maps
-								.get_index_mut(coord.map)
-								.context("unable to lookup the visible map")?
-								.1;
-							let tile = tile_map
-								.get_tile_mut(coord.coord)
-								.context("tile cannot be found that should exist")?;
-							tile.entities.remove(&entity);
-						}
-						all_storages.delete(entity);
-						Ok(())
-					},
-				)?;
+		let visible_map = match engine.maps.get_index_of(&self.visible_map) {
+			Some(map) => map,
+			None => return Ok(()),
+		};
+		let mut builder = graphics::MeshBuilder::new();
+		let mut any = false;
+		for ((x, y), progress) in self.animations.discoveries(visible_map) {
+			// Two rings, the second trailing the first
+			for &lag in &[0.0, 0.3] {
+				let ring = ((progress - lag) / (1.0 - lag)).max(0.0);
+				if ring <= 0.0 || ring >= 1.0 {
+					continue;
+				}
+				builder.circle(
+					DrawMode::stroke(0.06 * (1.0 - ring) + 0.01),
+					na::Point2::new(x, y),
+					0.2 + ring * 1.3,
+					0.01,
+					Color::new(1.0, 0.85, 0.3, 1.0 - ring),
+				);
+				any = true;
 			}
 		}
-
+		if any {
+			let mesh = builder.build(&mut self.ctx)?;
+			mesh.draw(&mut self.ctx, DrawParam::new())?;
+		}
 		Ok(())
 	}
 
-	fn screen_ratio_to_map(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
-		let visible_width = self.screen_tiles * self.aspect_ratio;
-		let visible_height = self.screen_tiles;
-		let offset_x = (visible_width * 0.5) - self.view_center.x;
-		let offset_y = (visible_height * 0.5) - self.view_center.y;
-		let map_x = (screen_x * visible_width) - offset_x;
-		let map_y = (screen_y * visible_height) - offset_y;
-		(map_x, map_y)
+	fn draw_grid(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let tile_map = engine
+			.maps
+			.get(&self.visible_map)
+			.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
+		let radius = (self.screen_tiles * self.aspect_ratio + 1.0)
+			.abs()
+			.min(20.0) as u8;
+		let center = self.hex.from_linear(self.view_center.x, self.view_center.y);
+		let hex = self.hex;
+		self.grid.draw(
+			&mut self.ctx,
+			&self.accessibility,
+			hex,
+			center,
+			radius,
+			|| {
+				let (center_x, center_y) = hex.to_linear(center);
+				tile_map
+					.iter_neighbors_around(center, radius)
+					.map(|(co, _tile)| {
+						let (opx, opy) = hex.offset_to_linear(co);
+						(center_x + opx, center_y + opy)
+					})
+					.collect()
+			},
+		)?;
+		Ok(())
 	}
 
-	fn mouse_button_up_event(
+	fn draw_heatmap(
 		&mut self,
-		ecs: &mut shipyard::World,
+		_ecs: &mut shipyard::World,
 		engine: &mut Engine<GameState>,
-		button: MouseButton,
-		x: f32,
-		y: f32,
 	) -> anyhow::Result<()> {
-		let screen_x = x / self.screen_size.width as f32;
-		let screen_y = y / self.screen_size.height as f32;
-		if let Some(button_pressed_data) = self.mouse_buttons_clicked.get(&button) {
-			// Test if a proper click
-			if self.is_proper_click(button_pressed_data, screen_x, screen_y) {
-				let (map_x, map_y) = self.screen_ratio_to_map(screen_x, screen_y);
-				let coord = Coord::from_linear(map_x, map_y);
-				let map_coord = MapCoord {
-					map: engine
-						.maps
-						.get_index_of(&self.visible_map)
-						.context("visible map doesn't exist")?,
-					coord,
-				};
-				self.set_selected_coord(ecs, engine, map_coord)?;
-			}
+		if self.heatmap.field().is_none() {
+			return Ok(());
 		}
-		self.mouse_buttons_clicked.remove(&button);
-		self.mouse_last_position = [screen_x, screen_y].into();
+		let (map, _name, tile_map) = engine
+			.maps
+			.get_full(&self.visible_map)
+			.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
+		let radius = (self.screen_tiles * self.aspect_ratio + 1.0)
+			.abs()
+			.min(20.0) as u8;
+		let center = self.hex.from_linear(self.view_center.x, self.view_center.y);
+		let hex = self.hex;
+		self.heatmap
+			.draw(&mut self.ctx, hex, map, center, radius, || {
+				let (center_x, center_y) = hex.to_linear(center);
+				tile_map
+					.iter_neighbors_around(center, radius)
+					.filter_map(|(co, _tile)| {
+						let idx = center
+							.offset_by(co, tile_map.width, tile_map.height, tile_map.wraps_x)?
+							.idx(tile_map.width, tile_map.height, tile_map.wraps_x)?;
+						let (opx, opy) = hex.offset_to_linear(co);
+						Some((idx, (center_x + opx, center_y + opy)))
+					})
+					.collect()
+			})?;
 		Ok(())
 	}
 
-	fn is_proper_click(
-		&self,
-		button_pressed_data: &MouseButtonPressedData,
-		screen_x: f32,
-		screen_y: f32,
-	) -> bool {
-		(button_pressed_data.screen.x - screen_x).abs()
-			< (1.0 / self.screen_size.width as f32) * self.click_leeway
-			&& (button_pressed_data.screen.y - screen_y).abs()
-				< (1.0 / self.screen_size.height as f32) * self.click_leeway
+	fn draw_heatmap_legend(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		self.heatmap.draw_legend(
+			&mut self.ctx,
+			&mut self.text,
+			(self.screen.width(), self.screen.height()),
+			self.screen.ui_scale(),
+		)?;
+		Ok(())
 	}
 
-	fn mouse_motion_event(
+	/// Says the other players are still taking their turn, with how far along they are.
+	fn draw_waiting(
 		&mut self,
 		_ecs: &mut shipyard::World,
 		_engine: &mut Engine<GameState>,
-		abs_x: f32,
-		abs_y: f32,
-		_delta_x: f32,
-		_delta_y: f32,
-	) -> anyhow::Result<()> {
-		let screen_x = abs_x / self.screen_size.width as f32;
-		let screen_y = abs_y / self.screen_size.height as f32;
-		if let Some(_button_pressed_data) = self.mouse_buttons_clicked.get(&MouseButton::Left) {
-			let (old_map_x, old_map_y) = self.screen_ratio_to_map(screen_x, screen_y);
-			let (new_map_x, new_map_y) =
-				self.screen_ratio_to_map(self.mouse_last_position.x, self.mouse_last_position.y);
-			let (delta_map_x, delta_map_y) = (new_map_x - old_map_x, new_map_y - old_map_y);
-			self.view_center.x += delta_map_x;
-			self.view_center.y += delta_map_y;
-			self.tiles_meshes.clear();
-		}
-		self.mouse_last_position = [screen_x, screen_y].into();
+	) -> anyhow::Result<()> {
+		let (done, total) = match self.waiting {
+			Some(waiting) => waiting,
+			None => return Ok(()),
+		};
+		let ui_scale = self.screen.ui_scale();
+		let margin = 8.0 * ui_scale;
+		let content = format!("Waiting for other players... {}/{}", done, total);
+		let text = self.text.layout(TextRole::Body, &content, ui_scale);
+		let (width, height) = (text.width(&self.ctx) as f32, text.height(&self.ctx) as f32);
+		let at = na::Point2::new((self.screen.width() - width) * 0.5, margin * 2.0);
+		let backing = Rect::new(
+			at.x - margin,
+			at.y - margin,
+			width + margin * 2.0,
+			height + margin * 2.0,
+		);
+		let mesh = graphics::Mesh::new_rectangle(
+			&mut self.ctx,
+			DrawMode::fill(),
+			backing,
+			Color::new(0.1, 0.1, 0.1, 0.8),
+		)?;
+		mesh.draw(&mut self.ctx, DrawParam::new())?;
+		graphics::draw(&mut self.ctx, text, DrawParam::new().dest(at))?;
 		Ok(())
 	}
 
-	fn update(
+	fn draw_session(
 		&mut self,
 		_ecs: &mut shipyard::World,
 		_engine: &mut Engine<GameState>,
 	) -> anyhow::Result<()> {
+		let size = (self.screen.width(), self.screen.height());
+		session::draw_session(
+			&mut self.ctx,
+			&mut self.text,
+			&self.session,
+			size,
+			self.screen.ui_scale(),
+		)?;
 		Ok(())
 	}
 
-	fn restrict_view_center(&mut self, engine: &Engine<GameState>) -> anyhow::Result<()> {
-		let map = engine
-			.maps
-			.get(&self.visible_map)
-			.context("visible map does not exist")?;
-
-		let (_full_max_x, max_y) = Coord::new_axial(map.width, map.height).to_linear();
-		if self.view_center.y < 0.0 {
-			self.view_center.y = 0.0;
-		} else if self.view_center.y > max_y {
-			self.view_center.y = max_y;
+	fn draw_selection(
+		&mut self,
+		ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		if None == self.selected_mesh {
+			let corners: Vec<na::Point2<f32>> = self
+				.hex
+				.corners()
+				.iter()
+				.map(|&(x, y)| na::Point2::new(x, y))
+				.collect();
+			self.selected_mesh = Some(
+				graphics::MeshBuilder::new()
+					.polygon(DrawMode::fill(), &corners, Color::new(1.0, 1.0, 1.0, 0.15))?
+					.polygon(
+						DrawMode::stroke(self.accessibility.line_width(0.05)),
+						&corners,
+						graphics::WHITE,
+					)?
+					.build(&mut self.ctx)?,
+			);
 		}
+		let seconds = ggez::timer::time_since_start(&self.ctx).as_secs_f32();
+		let pulse = 0.65 + 0.35 * (seconds * std::f32::consts::PI * 2.0 * 1.5).sin();
+		let color = Color::new(1.0, 1.0, 1.0, pulse);
+		let hex = self.hex;
+		let selected_mesh = &self.selected_mesh;
+		let ctx = &mut self.ctx;
+		if let Some(mesh) = selected_mesh {
+			ecs.run(
+				|coords: View<MapCoord>,
+				 selected: View<components::IsSelected>|
+				 -> anyhow::Result<()> {
+					for (_, c) in (&selected, &coords).iter() {
+						let (x, y) = hex.to_linear(c.coord);
+						mesh.draw(
+							ctx,
+							DrawParam::new().dest(na::Point2::new(x, y)).color(color),
+						)?;
+					}
 
-		let view_coord = Coord::from_linear(self.view_center.x, self.view_center.y);
-		let (min_x, _y) = Coord::new_axial(0, view_coord.r()).to_linear();
-		let (max_x, _y) = Coord::new_axial(map.width, view_coord.r()).to_linear();
-		if self.view_center.x < min_x - 0.5 {
-			if map.wraps_x {
-				trace!("Wrapping map on X min");
-				self.view_center.x += max_x - min_x + 1.0;
-			} else {
-				self.view_center.x = min_x;
-			}
-		} else if self.view_center.x > max_x + 0.5 {
-			if map.wraps_x {
-				trace!("Wrapping map on X max");
-				self.view_center.x -= max_x - min_x + 1.0;
-			} else {
-				self.view_center.x = max_x;
-			}
+					Ok(())
+				},
+			)?;
 		}
-
 		Ok(())
 	}
 
-	fn draw(
+	/// Draws the queued move legs of the box selected entities, the waypoint numbers are labels.
+	fn draw_orders(
 		&mut self,
 		ecs: &mut shipyard::World,
 		engine: &mut Engine<GameState>,
 	) -> anyhow::Result<()> {
-		let delta = ggez::timer::delta(&self.ctx);
-		self.zoom -= (self.zoom - self.screen_tiles) * (delta.as_secs_f32() * 5.0);
-		self.restrict_view_center(engine)?;
-		let screen_coords = Rect::new(
-			self.view_center.x - self.zoom * 0.5 * self.aspect_ratio,
-			self.view_center.y - self.zoom * 0.5,
-			self.zoom * self.aspect_ratio,
-			self.zoom,
-		);
-		graphics::set_screen_coordinates(&mut self.ctx, screen_coords)?;
-		graphics::clear(&mut self.ctx, graphics::BLACK);
-		self.draw_map(ecs, engine)?;
-		self.draw_entities(ecs, engine)?;
-		self.draw_selection(ecs, engine)?;
-		graphics::present(&mut self.ctx)?;
+		let paths = self.order_paths(ecs, engine);
+		if paths.is_empty() {
+			return Ok(());
+		}
+		let color = self
+			.accessibility
+			.overlay_color(Color::new(0.35, 0.95, 0.35, 0.75));
+		let line_width = self.accessibility.line_width(0.04);
+		let mut builder = graphics::MeshBuilder::new();
+		for path in &paths {
+			let points: Vec<na::Point2<f32>> =
+				path.iter().map(|&(x, y)| na::Point2::new(x, y)).collect();
+			builder.polyline(DrawMode::stroke(line_width), &points, color)?;
+			for &point in &points[1..] {
+				builder.circle(DrawMode::fill(), point, 0.12, 0.01, color);
+			}
+		}
+		let mesh = builder.build(&mut self.ctx)?;
+		mesh.draw(&mut self.ctx, DrawParam::new())?;
 		Ok(())
 	}
 
-	fn draw_entities(
+	fn draw_pins(
 		&mut self,
-		ecs: &mut shipyard::World,
+		_ecs: &mut shipyard::World,
 		engine: &mut Engine<GameState>,
 	) -> anyhow::Result<()> {
-		// TODO: SpriteBatch doesn't seem terribly efficient, examine if it would be better to either cache and reuse it like the map mesh, or to build a mesh for it instead...
-		if self.entity_atlas.len_atlases() != self.entity_spritebatches.len() {
-			self.entity_spritebatches.clear();
-			self.entity_spritebatches
-				.reserve(self.entity_atlas.len_atlases());
-			for i in 0..self.entity_atlas.len_atlases() {
-				self.entity_spritebatches.push(SpriteBatch::new(
-					self.entity_atlas
-						.get_image_by_index(i)
-						.context("Atlas is missing an image")?
-						.clone(),
-				));
-			}
+		if self.pin_mesh.is_none() {
+			let diamond: [na::Point2<f32>; 4] = [
+				na::Point2::new(0.0, -0.3),
+				na::Point2::new(0.1, -0.15),
+				na::Point2::new(0.0, 0.0),
+				na::Point2::new(-0.1, -0.15),
+			];
+			self.pin_mesh = Some(graphics::Mesh::new_polygon(
+				&mut self.ctx,
+				DrawMode::fill(),
+				&diamond,
+				Color::new(1.0, 0.55, 0.1, 1.0),
+			)?);
 		}
-
-		let tile_map = engine
-			.maps
-			.get(&self.visible_map)
-			.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
-		let radius = self.screen_tiles * self.aspect_ratio + 1.0;
-		let radius = if radius.abs() > 16.0 {
-			16u8
-		} else {
-			radius.abs() as u8
+		let map = match engine.maps.get_index_of(&self.visible_map) {
+			None => return Ok(()),
+			Some(map) => map,
 		};
-		let draw_sprites = ecs.try_borrow::<View<DrawSprite>>()?;
-		let center = Coord::from_linear(self.view_center.x, self.view_center.y);
-		let (center_x, center_y) = center.to_linear();
-		for (co, tile) in tile_map.iter_neighbors_around(center, radius) {
-			for &entity in &tile.entities {
-				if let Ok(draw) = draw_sprites.get(entity) {
-					if let Some(sprite) = self.entity_atlas.get_entry_by_name(&draw.sprite_name) {
-						let (opx, opy) = co.to_linear();
-						let px = center_x + opx;
-						let py = center_y + opy;
-						let idx = sprite.get_atlas_idx();
-						let image_dim = self.entity_atlas.get_image(sprite.get_id()).dimensions();
-						let batch = &mut self.entity_spritebatches[idx];
-						let src =
-							Rect::new(sprite.left(), sprite.top(), sprite.width(), sprite.height());
-						let dest = [px + draw.rect.x, py + draw.rect.y];
-						let offset = [0.5, 0.5];
-						// No clue why the size of the sprite is dependent on the size of the source image..
-						// Seems like an excessively bad mis-design...  o.O
-						// So... undo that ggez brokenness...
-						let scale = [
-							1.0 / (image_dim.w * sprite.width()),
-							1.0 / (image_dim.h * sprite.height()),
-						];
-						let params = DrawParam::new()
-							.src(src)
-							.dest(dest)
-							.offset(offset)
-							.scale(scale);
-						batch.add(params);
-					}
-				}
+		if let Some(mesh) = &self.pin_mesh {
+			for pin in self.pins.iter_on_map(map) {
+				let (x, y) = self.hex.to_linear(pin.coord.coord);
+				mesh.draw(&mut self.ctx, DrawParam::new().dest(na::Point2::new(x, y)))?;
 			}
 		}
-		let params = DrawParam::new();
-		for batch in &mut self.entity_spritebatches {
-			batch.draw(&mut self.ctx, params)?;
-			batch.clear();
-		}
-
 		Ok(())
 	}
 
-	fn draw_map(
+	fn draw_labels(
 		&mut self,
-		_ecs: &mut shipyard::World,
+		ecs: &mut shipyard::World,
 		engine: &mut Engine<GameState>,
 	) -> anyhow::Result<()> {
-		if self.tiles_meshes.is_empty() {
-			let mut mesh_builders: Vec<_> = (0..self.tiles_atlas.len_atlases())
-				.map(|_| (false, graphics::MeshBuilder::new()))
-				.collect();
-
+		let mut extra = vec![];
+		if self.labels.show_coords {
 			let tile_map = engine
 				.maps
 				.get(&self.visible_map)
 				.with_context(|| format!("Unable to load visible map: {}", self.visible_map))?;
-			let radius = self.screen_tiles * self.aspect_ratio + 1.0;
-			let radius = if radius.abs() > 20.0 {
-				20u8
-			} else {
-				radius.abs() as u8
-			};
-			let center = Coord::from_linear(self.view_center.x, self.view_center.y);
-			let (center_x, center_y) = center.to_linear();
-			for (co, tile) in tile_map.iter_neighbors_around(center, radius) {
-				let (opx, opy) = co.to_linear();
-				let px = center_x + opx;
-				let py = center_y + opy;
-				let idx: usize = tile.id.into();
-				let tile_drawable = &self.tiles_drawable[idx];
-				let uv = self.tiles_atlas.get_entry(tile_drawable.atlas_id);
-				let mut pos = tile_drawable.info.bounds;
-				pos.translate([px, py]);
-				let color = tile_drawable.info.color;
-				let color: [f32; 4] = [color.r, color.g, color.b, color.a];
-				let (active, mesh_builder) = &mut mesh_builders[uv.get_atlas_idx()];
-				*active = true;
-				mesh_builder.raw(
-					&[
-						Vertex {
-							// left-top
-							pos: [pos.left(), pos.top()],
-							uv: [uv.left(), uv.top()],
-							color,
-						},
-						Vertex {
-							// left-bottom
-							pos: [pos.left(), pos.bottom()],
-							uv: [uv.left(), uv.bottom()],
-							color,
-						},
-						Vertex {
-							// right-bottom
-							pos: [pos.right(), pos.bottom()],
-							uv: [uv.right(), uv.bottom()],
-							color,
-						},
-						Vertex {
-							// right-top
-							pos: [pos.right(), pos.top()],
-							uv: [uv.right(), uv.top()],
-							color,
-						},
-					],
-					&[0, 1, 2, 0, 2, 3],
-					None,
-				);
-			}
-			self.tiles_meshes.clear();
-			for (idx, (active, mut builder)) in mesh_builders.into_iter().enumerate() {
-				if !active {
-					self.tiles_meshes.push(None);
-				} else {
-					let texture = self
-						.tiles_atlas
-						.get_image_by_index(idx)
-						.context("failed to get image that must exist")?;
-					self.tiles_meshes
-						.push(Some(builder.texture(texture.clone()).build(&mut self.ctx)?));
+			let radius = (self.screen_tiles * self.aspect_ratio + 1.0)
+				.abs()
+				.min(20.0) as u8;
+			let center = self.hex.from_linear(self.view_center.x, self.view_center.y);
+			let (center_x, center_y) = self.hex.to_linear(center);
+			for (co, _tile) in tile_map.iter_neighbors_around(center, radius) {
+				if let Some(coord) =
+					center.offset_by(co, tile_map.width, tile_map.height, tile_map.wraps_x)
+				{
+					let (opx, opy) = self.hex.offset_to_linear(co);
+					extra.push(WorldLabel {
+						position: (center_x + opx, center_y + opy),
+						text: format!("{},{}", coord.q(), coord.r()),
+						color: Color::new(1.0, 1.0, 0.6, 0.8),
+					});
 				}
 			}
 		}
-		let param = DrawParam::new();
-		for mesh in &self.tiles_meshes {
-			match mesh {
-				Some(mesh) => mesh.draw(&mut self.ctx, param)?,
-				None => (),
+		if let Some(map) = engine.maps.get_index_of(&self.visible_map) {
+			for pin in self.pins.iter_on_map(map) {
+				let (x, y) = self.hex.to_linear(pin.coord.coord);
+				extra.push(WorldLabel {
+					position: (x, y - 0.45),
+					text: pin.name.clone(),
+					color: Color::new(1.0, 0.75, 0.4, 1.0),
+				});
+			}
+		}
+		for badge in &self.stack_badges {
+			extra.push(WorldLabel {
+				position: badge.center,
+				text: badge.units.to_string(),
+				color: graphics::WHITE,
+			});
+		}
+		for path in self.order_paths(ecs, engine) {
+			for (index, &(x, y)) in path.iter().enumerate().skip(1) {
+				extra.push(WorldLabel {
+					position: (x, y - 0.3),
+					text: index.to_string(),
+					color: Color::new(0.75, 1.0, 0.75, 1.0),
+				});
 			}
 		}
+		let screen_size = (self.screen.width(), self.screen.height());
+		self.labels.draw(
+			&mut self.ctx,
+			&mut self.text,
+			self.view_rect,
+			screen_size,
+			self.zoom,
+			self.screen.ui_scale(),
+			extra,
+		)?;
 		Ok(())
 	}
 
-	fn draw_selection(
+	fn draw_interaction(
 		&mut self,
-		ecs: &mut shipyard::World,
+		_ecs: &mut shipyard::World,
 		_engine: &mut Engine<GameState>,
 	) -> anyhow::Result<()> {
-		if None == self.selected_mesh {
-			self.selected_mesh = Some(graphics::Mesh::new_circle(
+		let (width, height) = (self.screen.width(), self.screen.height());
+		if let Some((from, to)) = self.interaction.box_selection() {
+			let area = Rect::new(
+				from.x.min(to.x) * width,
+				from.y.min(to.y) * height,
+				(from.x - to.x).abs() * width,
+				(from.y - to.y).abs() * height,
+			);
+			if area.w > 0.0 && area.h > 0.0 {
+				let mesh = graphics::MeshBuilder::new()
+					.rectangle(DrawMode::fill(), area, Color::new(1.0, 1.0, 1.0, 0.1))
+					.rectangle(DrawMode::stroke(1.0), area, graphics::WHITE)
+					.build(&mut self.ctx)?;
+				mesh.draw(&mut self.ctx, DrawParam::new())?;
+			}
+		}
+		if self.interaction.mode() == InteractionMode::EditorPaint {
+			// Brush outline the size of a tile, drawn here since the OS cursor can't be resized
+			let position = self.interaction.position();
+			let tile_pixels = width / (self.zoom * self.aspect_ratio);
+			let brush = graphics::Mesh::new_circle(
 				&mut self.ctx,
-				DrawMode::stroke(0.1),
-				na::Point2::new(0.0, 0.0),
-				1.0,
-				0.2,
-				graphics::WHITE,
-			)?);
+				DrawMode::stroke(2.0),
+				na::Point2::new(position.x * width, position.y * height),
+				tile_pixels * 0.5,
+				0.5,
+				Color::new(1.0, 1.0, 1.0, 0.8),
+			)?;
+			brush.draw(&mut self.ctx, DrawParam::new())?;
 		}
-		let selected_mesh = &self.selected_mesh;
-		let ctx = &mut self.ctx;
-		if let Some(mesh) = selected_mesh {
-			ecs.run(
-				|coords: View<MapCoord>,
-				 selected: View<components::IsSelected>|
-				 -> anyhow::Result<()> {
-					for (_, c) in (&selected, &coords).iter() {
-						let (x, y) = c.coord.to_linear();
-						mesh.draw(ctx, DrawParam::new().dest(na::Point2::new(x, y)))?;
-					}
+		Ok(())
+	}
 
-					Ok(())
-				},
+	/// Pulses a hexagon around the tile the tutorial step points at.
+	fn draw_tutorial_tile(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let highlight = self
+			.tutorial
+			.as_ref()
+			.and_then(|tutorial| tutorial.current())
+			.and_then(|step| step.highlight.as_ref());
+		// A tutorial pointing at a tile that isn't there just has nothing drawn
+		let at = match highlight {
+			Some(TutorialHighlight::Tile(address)) => match address.resolve(&engine.maps) {
+				Ok(at) => at,
+				Err(_) => return Ok(()),
+			},
+			_ => return Ok(()),
+		};
+		if engine.maps.get_index_of(&self.visible_map) != Some(at.map) {
+			return Ok(());
+		}
+		let corners: Vec<na::Point2<f32>> = self
+			.hex
+			.corners()
+			.iter()
+			.map(|&(x, y)| na::Point2::new(x * 1.15, y * 1.15))
+			.collect();
+		let seconds = ggez::timer::time_since_start(&self.ctx).as_secs_f32();
+		let pulse = 0.6 + 0.4 * (seconds * std::f32::consts::PI * 2.0).sin();
+		let color = self
+			.accessibility
+			.overlay_color(Color::new(1.0, 0.85, 0.2, pulse));
+		let mesh = graphics::Mesh::new_polygon(
+			&mut self.ctx,
+			DrawMode::stroke(self.accessibility.line_width(0.08)),
+			&corners,
+			color,
+		)?;
+		let (x, y) = self.hex.to_linear(at.coord);
+		mesh.draw(&mut self.ctx, DrawParam::new().dest(na::Point2::new(x, y)))?;
+		Ok(())
+	}
+
+	/// The tutorial's panel and an outline around the part of the screen its step points at.
+	fn draw_tutorial(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let tutorial = match &self.tutorial {
+			Some(tutorial) => tutorial,
+			None => return Ok(()),
+		};
+		let outlined = match tutorial.current().and_then(|step| step.highlight.as_ref()) {
+			Some(TutorialHighlight::Minimap) if self.show_minimap => engine
+				.maps
+				.get(&self.visible_map)
+				.map(|tile_map| self.minimap_bounds(tile_map)),
+			Some(TutorialHighlight::Chat) => Some(self.chat_bounds()),
+			_ => None,
+		};
+		let skin = self.panel_skin.map(|id| (&self.ui_atlas, id));
+		tutorial::draw_tutorial(
+			&mut self.ctx,
+			&mut self.text,
+			tutorial,
+			(self.screen.width(), self.screen.height()),
+			self.screen.ui_scale(),
+			skin,
+		)?;
+		if let Some(bounds) = outlined {
+			let grow = self.screen.ui(4.0);
+			let bounds = Rect::new(
+				bounds.x - grow,
+				bounds.y - grow,
+				bounds.w + grow * 2.0,
+				bounds.h + grow * 2.0,
+			);
+			let seconds = ggez::timer::time_since_start(&self.ctx).as_secs_f32();
+			let pulse = 0.6 + 0.4 * (seconds * std::f32::consts::PI * 2.0).sin();
+			let color = self
+				.accessibility
+				.overlay_color(Color::new(1.0, 0.85, 0.2, pulse));
+			let line_width = self.accessibility.line_width(self.screen.ui(3.0));
+			let mesh = graphics::Mesh::new_rectangle(
+				&mut self.ctx,
+				DrawMode::stroke(line_width),
+				bounds,
+				color,
+			)?;
+			mesh.draw(&mut self.ctx, DrawParam::new())?;
+		}
+		Ok(())
+	}
+
+	/// Where the chat's history and entry line go, from the bottom left corner up.
+	fn chat_bounds(&self) -> Rect {
+		let line_height = self.screen.ui(18.0);
+		let margin = self.screen.ui(8.0);
+		// The history's 10 lines above the entry line
+		let height = line_height * 11.0;
+		Rect::new(
+			margin,
+			self.screen.height() - line_height - height,
+			self.screen.width() * 0.4,
+			height,
+		)
+	}
+
+	fn draw_chat(
+		&mut self,
+		_ecs: &mut shipyard::World,
+		_engine: &mut Engine<GameState>,
+	) -> anyhow::Result<()> {
+		let height = self.screen.height();
+		let ui_scale = self.screen.ui_scale();
+		let line_height = self.screen.ui(18.0);
+		let margin = self.screen.ui(8.0);
+		let mut y = height - line_height * 2.0;
+		if self.chat.is_focused() {
+			let entry = self.text.layout(
+				TextRole::Console,
+				&format!("> {}_", self.chat.input()),
+				ui_scale,
+			);
+			graphics::draw(
+				&mut self.ctx,
+				entry,
+				DrawParam::new().dest(na::Point2::new(margin, y)),
+			)?;
+		}
+		let lines: Vec<String> = self
+			.chat
+			.visible_history(10)
+			.map(|m| format!("[{}] {}", m.sender, m.text))
+			.collect();
+		for line in lines.into_iter().rev() {
+			y -= line_height;
+			let text = self.text.layout(TextRole::Console, &line, ui_scale);
+			graphics::draw(
+				&mut self.ctx,
+				text,
+				DrawParam::new().dest(na::Point2::new(margin, y)),
 			)?;
 		}
 		Ok(())
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+use winit::VirtualKeyCode;
+
+use crate::game::atlas::{AtlasId, MultiAtlas};
+use crate::game::panel;
+use crate::game::text::{TextRole, TextStyles};
+
+const WIDTH: f32 = 420.0;
+const LINE_HEIGHT: f32 = 20.0;
+const BUTTON_HEIGHT: f32 = 28.0;
+const MARGIN: f32 = 10.0;
+/// Body lines are wrapped to about this many characters.
+const WRAP_COLUMNS: usize = 60;
+
+/// What a dialog's button does, the game acts on it once the dialog has closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModalAction {
+	/// Just closes the dialog, also what Escape picks.
+	Close,
+	Quit,
+	/// Saves what can be saved first, staying in the game if that fails.
+	SaveAndQuit,
+}
+
+/// A dialog that takes all input until one of its buttons is picked.
+pub struct Modal {
+	pub title: String,
+	pub body: String,
+	pub buttons: Vec<(String, ModalAction)>,
+	focused: usize,
+}
+
+impl Modal {
+	pub fn new(title: impl Into<String>, body: impl Into<String>) -> Modal {
+		Modal {
+			title: title.into(),
+			body: body.into(),
+			buttons: vec![],
+			focused: 0,
+		}
+	}
+
+	/// A dialog with only an OK button.
+	pub fn notice(title: impl Into<String>, body: impl Into<String>) -> Modal {
+		Modal::new(title, body).button("OK", ModalAction::Close)
+	}
+
+	/// Adds a button after the others, there is room for 3.
+	pub fn button(mut self, label: impl Into<String>, action: ModalAction) -> Modal {
+		assert!(self.buttons.len() < 3, "a dialog has at most 3 buttons");
+		self.buttons.push((label.into(), action));
+		self
+	}
+
+	fn lines(&self) -> Vec<String> {
+		self.body
+			.lines()
+			.flat_map(|line| wrap(line, WRAP_COLUMNS))
+			.collect()
+	}
+
+	/// Centered on the screen, the title and the body take a line each and the buttons a row.
+	pub fn bounds(&self, screen_size: (f32, f32), ui_scale: f32) -> Rect {
+		let width = (WIDTH * ui_scale).min(screen_size.0);
+		let height = (LINE_HEIGHT * (self.lines().len() + 1) as f32 + BUTTON_HEIGHT + MARGIN * 3.0)
+			* ui_scale;
+		Rect::new(
+			(screen_size.0 - width) * 0.5,
+			(screen_size.1 - height) * 0.5,
+			width,
+			height,
+		)
+	}
+
+	fn button_bounds(&self, bounds: Rect, ui_scale: f32) -> Vec<Rect> {
+		let count = self.buttons.len().max(1) as f32;
+		let margin = MARGIN * ui_scale;
+		let width = (bounds.w - margin * (count + 1.0)) / count;
+		let height = BUTTON_HEIGHT * ui_scale;
+		let y = bounds.bottom() - margin - height;
+		(0..self.buttons.len())
+			.map(|index| {
+				Rect::new(
+					bounds.x + margin + (width + margin) * index as f32,
+					y,
+					width,
+					height,
+				)
+			})
+			.collect()
+	}
+
+	pub fn button_at(
+		&self,
+		position: na::Point2<f32>,
+		screen_size: (f32, f32),
+		ui_scale: f32,
+	) -> Option<ModalAction> {
+		let bounds = self.bounds(screen_size, ui_scale);
+		self.button_bounds(bounds, ui_scale)
+			.iter()
+			.position(|button| button.contains([position.x, position.y]))
+			.map(|index| self.buttons[index].1)
+	}
+
+	/// Draws over a nine slice `skin` from the atlas if there is one, else a plain box, with the
+	/// focused button filled in.
+	pub fn draw<Unique: Copy>(
+		&self,
+		ctx: &mut Context,
+		text_styles: &mut TextStyles,
+		screen_size: (f32, f32),
+		ui_scale: f32,
+		skin: Option<(&MultiAtlas<graphics::Image, Unique>, AtlasId<Unique>)>,
+	) -> GameResult {
+		let bounds = self.bounds(screen_size, ui_scale);
+		// Dims the game behind to show it isn't taking input
+		let screen = Rect::new(0.0, 0.0, screen_size.0, screen_size.1);
+		let mut builder = graphics::MeshBuilder::new();
+		builder.rectangle(DrawMode::fill(), screen, Color::new(0.0, 0.0, 0.0, 0.4));
+		if skin.is_none() {
+			builder
+				.rectangle(DrawMode::fill(), bounds, Color::new(0.1, 0.1, 0.1, 0.95))
+				.rectangle(DrawMode::stroke(1.0), bounds, graphics::WHITE);
+		}
+		builder.build(ctx)?.draw(ctx, DrawParam::new())?;
+		if let Some((atlas, id)) = skin {
+			panel::draw_panel(ctx, atlas, id, bounds, ui_scale, graphics::WHITE)?;
+		}
+
+		let line_height = LINE_HEIGHT * ui_scale;
+		let margin = MARGIN * ui_scale;
+		let x = bounds.x + margin;
+		let mut y = bounds.y + margin;
+		let text = text_styles.layout(TextRole::Heading, &self.title, ui_scale);
+		graphics::draw(ctx, text, DrawParam::new().dest(na::Point2::new(x, y)))?;
+		for line in self.lines() {
+			y += line_height;
+			let text = text_styles.layout(TextRole::Body, &line, ui_scale);
+			graphics::draw(ctx, text, DrawParam::new().dest(na::Point2::new(x, y)))?;
+		}
+
+		let buttons = self.button_bounds(bounds, ui_scale);
+		let mut builder = graphics::MeshBuilder::new();
+		for (index, &button) in buttons.iter().enumerate() {
+			if index == self.focused {
+				builder.rectangle(DrawMode::fill(), button, Color::new(0.3, 0.45, 0.7, 1.0));
+			}
+			builder.rectangle(DrawMode::stroke(1.0), button, graphics::WHITE);
+		}
+		if !buttons.is_empty() {
+			builder.build(ctx)?.draw(ctx, DrawParam::new())?;
+		}
+		for (button, (label, _action)) in buttons.iter().zip(&self.buttons) {
+			let text = text_styles.layout(TextRole::Body, label, ui_scale);
+			let (width, height) = text.dimensions(ctx);
+			let at = na::Point2::new(
+				button.x + (button.w - width as f32) * 0.5,
+				button.y + (button.h - height as f32) * 0.5,
+			);
+			graphics::draw(ctx, text, DrawParam::new().dest(at))?;
+		}
+		Ok(())
+	}
+}
+
+/// Dialogs waiting to be shown, one at a time in the order they came.
+#[derive(Default)]
+pub struct ModalQueue {
+	queue: VecDeque<Modal>,
+}
+
+impl ModalQueue {
+	pub fn push(&mut self, modal: Modal) {
+		self.queue.push_back(modal);
+	}
+
+	pub fn current(&self) -> Option<&Modal> {
+		self.queue.front()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.queue.is_empty()
+	}
+
+	/// Whether a queued dialog has a button for `action`, to not ask the same thing twice.
+	pub fn has_action(&self, action: ModalAction) -> bool {
+		self.queue
+			.iter()
+			.any(|modal| modal.buttons.iter().any(|(_label, a)| *a == action))
+	}
+
+	/// Moves the focus with the arrows and Tab, picks with Return, Space or the button's number
+	/// and closes with Escape if there is a button that only closes.
+	pub fn key(&mut self, keycode: VirtualKeyCode, shift: bool) -> Option<ModalAction> {
+		use VirtualKeyCode::*;
+		let modal = self.queue.front_mut()?;
+		let count = modal.buttons.len();
+		let picked = match keycode {
+			Left | Up => {
+				modal.focused = (modal.focused + count.max(1) - 1) % count.max(1);
+				None
+			}
+			Tab if shift => {
+				modal.focused = (modal.focused + count.max(1) - 1) % count.max(1);
+				None
+			}
+			Right | Down | Tab => {
+				modal.focused = (modal.focused + 1) % count.max(1);
+				None
+			}
+			Return | NumpadEnter | Space => modal.buttons.get(modal.focused),
+			Key1 | Numpad1 => modal.buttons.get(0),
+			Key2 | Numpad2 => modal.buttons.get(1),
+			Key3 | Numpad3 => modal.buttons.get(2),
+			Escape => modal
+				.buttons
+				.iter()
+				.find(|(_label, action)| *action == ModalAction::Close),
+			_ => None,
+		};
+		let action = picked.map(|(_label, action)| *action);
+		// A dialog without buttons closes on any of the keys that would pick one
+		let closes = action.is_some()
+			|| (count == 0 && matches!(keycode, Return | NumpadEnter | Space | Escape));
+		if closes {
+			self.queue.pop_front();
+		}
+		action
+	}
+
+	/// Picks the button clicked on, clicks elsewhere do nothing.
+	pub fn click(
+		&mut self,
+		position: na::Point2<f32>,
+		screen_size: (f32, f32),
+		ui_scale: f32,
+	) -> Option<ModalAction> {
+		let action = self
+			.queue
+			.front()?
+			.button_at(position, screen_size, ui_scale)?;
+		self.queue.pop_front();
+		Some(action)
+	}
+}
+
+/// Splits `line` at spaces into lines of at most `columns` characters, words longer than that
+/// get a line of their own.
+fn wrap(line: &str, columns: usize) -> Vec<String> {
+	let mut lines = vec![];
+	let mut current = String::new();
+	for word in line.split(' ') {
+		if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > columns {
+			lines.push(std::mem::take(&mut current));
+		}
+		if !current.is_empty() {
+			current.push(' ');
+		}
+		current.push_str(word);
+	}
+	lines.push(current);
+	lines
+}
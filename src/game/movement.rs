@@ -0,0 +1,79 @@
+use over_simple_game_1::core::engine::MapCoord;
+use over_simple_game_1::core::map::coord::{Coord, HexOrientation};
+use over_simple_game_1::core::map::tile_map::TileMap;
+use serde::{Deserialize, Serialize};
+
+fn serde_movement_hop_ticks() -> u32 {
+	5
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MovementConfig {
+	/// Simulation ticks a unit takes to move one tile.
+	#[serde(default = "serde_movement_hop_ticks")]
+	pub hop_ticks: u32,
+}
+
+impl Default for MovementConfig {
+	fn default() -> Self {
+		MovementConfig {
+			hop_ticks: serde_movement_hop_ticks(),
+		}
+	}
+}
+
+/// A unit on its way to a neighboring tile, it stays on `from` until the hop completes and is
+/// drawn part of the way along meanwhile.
+#[derive(Clone, Copy, Debug)]
+pub struct MoveHop {
+	pub from: MapCoord,
+	pub to: MapCoord,
+	/// How many ticks the whole hop takes.
+	pub ticks: u32,
+	pub elapsed: u32,
+}
+
+impl MoveHop {
+	pub fn new(from: MapCoord, to: MapCoord, ticks: u32) -> MoveHop {
+		MoveHop {
+			from,
+			to,
+			ticks: ticks.max(1),
+			elapsed: 0,
+		}
+	}
+
+	/// Advances the hop a tick, true once it has arrived.
+	pub fn advance(&mut self) -> bool {
+		self.elapsed += 1;
+		self.elapsed >= self.ticks
+	}
+
+	/// `0..1` of the way to `to`, `fraction` being how far the clock is into the next tick.
+	pub fn progress(&self, fraction: f32) -> f32 {
+		((self.elapsed as f32 + fraction) / self.ticks as f32).min(1.0)
+	}
+
+	/// Where to draw the unit relative to the center of `from`.
+	pub fn offset(&self, hex: HexOrientation, tile_map: &TileMap, fraction: f32) -> (f32, f32) {
+		let (dx, dy) = linear_delta(hex, tile_map, self.from.coord, self.to.coord);
+		let progress = self.progress(fraction);
+		(dx * progress, dy * progress)
+	}
+}
+
+/// From the center of `from` to the center of `to` in linear map units, the short way around
+/// the seam of a wrapping map.
+pub fn linear_delta(hex: HexOrientation, tile_map: &TileMap, from: Coord, to: Coord) -> (f32, f32) {
+	let (from_x, from_y) = hex.to_linear(from);
+	let (to_x, to_y) = hex.to_linear(to);
+	let (mut dx, mut dy) = (to_x - from_x, to_y - from_y);
+	// Flat-top columns step down as well as right, so the seam is crossed on a slant
+	let (seam_x, seam_y) = hex.axial_to_linear(tile_map.width as f32 + 1.0, 0.0);
+	if tile_map.wraps_x && dx.abs() > seam_x / 2.0 {
+		let side = dx.signum();
+		dx -= seam_x * side;
+		dy -= seam_y * side;
+	}
+	(dx, dy)
+}
@@ -0,0 +1,193 @@
+use ggez::audio::{self, SoundSource};
+use ggez::{Context, GameResult};
+use log::*;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+
+/// What is going on, each has its own playlist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicContext {
+	Menu,
+	Peace,
+	/// There was fighting near the camera lately.
+	War,
+}
+
+/// Track paths in the resources for each context, anything ggez can decode.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct MusicPlaylists {
+	#[serde(default)]
+	pub menu: Vec<String>,
+	#[serde(default)]
+	pub peace: Vec<String>,
+	#[serde(default)]
+	pub war: Vec<String>,
+}
+
+impl MusicPlaylists {
+	fn tracks(&self, context: MusicContext) -> &[String] {
+		match context {
+			MusicContext::Menu => &self.menu,
+			MusicContext::Peace => &self.peace,
+			MusicContext::War => &self.war,
+		}
+	}
+}
+
+struct Playing {
+	source: audio::Source,
+	/// Fades in from 0 when it starts and out to 0 when it's replaced.
+	level: f32,
+}
+
+/// Plays each context's playlist shuffled, crossfading when the context changes and ducking under
+/// notification sounds.
+pub struct MusicManager {
+	playlists: MusicPlaylists,
+	context: Option<MusicContext>,
+	/// The rest of the shuffled playlist of `context`, played from the back.
+	queue: Vec<String>,
+	current: Option<Playing>,
+	/// None of the playlist's tracks would load, so it isn't tried again until the context or
+	/// playlists change.
+	exhausted: bool,
+	fading_out: Vec<Playing>,
+	/// Notification sounds still playing, the music is ducked until they finish.
+	notifications: Vec<audio::Source>,
+	duck: f32,
+	/// Seconds until the fighting counts as over.
+	war_remaining: f32,
+}
+
+impl MusicManager {
+	const CROSSFADE_SECONDS: f32 = 3.0;
+	/// How long after the last fight nearby the war music keeps going.
+	const WAR_SECONDS: f32 = 60.0;
+	/// Music volume under a notification.
+	const DUCKED: f32 = 0.3;
+	/// Seconds to duck and to come back up.
+	const DUCK_SECONDS: f32 = 0.2;
+
+	pub fn new() -> MusicManager {
+		MusicManager {
+			playlists: MusicPlaylists::default(),
+			context: None,
+			queue: vec![],
+			current: None,
+			exhausted: false,
+			fading_out: vec![],
+			notifications: vec![],
+			duck: 1.0,
+			war_remaining: 0.0,
+		}
+	}
+
+	pub fn set_playlists(&mut self, playlists: MusicPlaylists) {
+		self.playlists = playlists;
+		// Starts over on the new playlists at the next update
+		self.context = None;
+		self.exhausted = false;
+	}
+
+	/// Fighting happened near the camera, switching to the war playlist for a while.
+	pub fn combat_nearby(&mut self) {
+		self.war_remaining = Self::WAR_SECONDS;
+	}
+
+	/// Plays a one-shot sound the music ducks under, one that won't play is only logged.
+	pub fn notify(&mut self, ctx: &mut Context, path: &str, volume: f32) {
+		let played = audio::Source::new(ctx, path).and_then(|mut source| {
+			source.set_volume(volume);
+			source.play()?;
+			Ok(source)
+		});
+		match played {
+			Ok(source) => self.notifications.push(source),
+			Err(error) => debug!("No notification sound `{}`: {}", path, error),
+		}
+	}
+
+	/// `menu` is whether a menu covers the game, `volume` scales everything.
+	pub fn update(&mut self, ctx: &mut Context, menu: bool, delta: f32, volume: f32) -> GameResult {
+		self.war_remaining = (self.war_remaining - delta).max(0.0);
+		let context = if menu {
+			MusicContext::Menu
+		} else if self.war_remaining > 0.0 {
+			MusicContext::War
+		} else {
+			MusicContext::Peace
+		};
+		if self.context != Some(context) {
+			self.context = Some(context);
+			self.queue.clear();
+			self.exhausted = false;
+			self.fading_out.extend(self.current.take());
+		}
+		let finished = match &self.current {
+			Some(playing) => !playing.source.playing() && !playing.source.paused(),
+			None => true,
+		};
+		if finished && !self.exhausted {
+			self.current = self.next_track(ctx, context)?;
+			self.exhausted = self.current.is_none();
+		}
+
+		self.notifications.retain(|source| source.playing());
+		let duck_target = if self.notifications.is_empty() {
+			1.0
+		} else {
+			Self::DUCKED
+		};
+		let duck_step = delta / Self::DUCK_SECONDS;
+		self.duck = if self.duck < duck_target {
+			(self.duck + duck_step).min(duck_target)
+		} else {
+			(self.duck - duck_step).max(duck_target)
+		};
+
+		let fade_step = delta / Self::CROSSFADE_SECONDS;
+		let volume = volume * self.duck;
+		if let Some(playing) = &mut self.current {
+			playing.level = (playing.level + fade_step).min(1.0);
+			playing.source.set_volume(playing.level * volume);
+		}
+		for playing in &mut self.fading_out {
+			playing.level = (playing.level - fade_step).max(0.0);
+			playing.source.set_volume(playing.level * volume);
+			if playing.level <= 0.0 {
+				playing.source.stop();
+			}
+		}
+		self.fading_out.retain(|playing| playing.level > 0.0);
+		Ok(())
+	}
+
+	/// Starts the next track of `context`'s playlist, reshuffling it once it has all played.
+	/// Tracks that fail to load are skipped, `None` if none of them load.
+	fn next_track(
+		&mut self,
+		ctx: &mut Context,
+		context: MusicContext,
+	) -> GameResult<Option<Playing>> {
+		let tracks = self.playlists.tracks(context);
+		for _ in 0..tracks.len() {
+			if self.queue.is_empty() {
+				self.queue = tracks.to_vec();
+				self.queue.shuffle(&mut rand::thread_rng());
+			}
+			let path = match self.queue.pop() {
+				Some(path) => path,
+				None => break,
+			};
+			match audio::Source::new(ctx, &path) {
+				Ok(mut source) => {
+					source.set_volume(0.0);
+					source.play()?;
+					return Ok(Some(Playing { source, level: 0.0 }));
+				}
+				Err(error) => warn!("Failed loading music `{}`: {}", path, error),
+			}
+		}
+		Ok(None)
+	}
+}
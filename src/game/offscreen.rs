@@ -0,0 +1,92 @@
+use ggez::conf::NumSamples;
+use ggez::graphics::{self, Color, DrawParam, Drawable, Rect};
+use ggez::{Context, GameResult};
+
+/// A texture rendered into once and then drawn cheaply every frame, until it is invalidated or
+/// wanted at another size.
+pub struct RenderTarget {
+	canvas: Option<graphics::Canvas>,
+	size: (u16, u16),
+}
+
+impl RenderTarget {
+	pub fn new() -> RenderTarget {
+		RenderTarget {
+			canvas: None,
+			size: (0, 0),
+		}
+	}
+
+	/// Whether it has to be rendered before it can be drawn at `size`.
+	pub fn is_stale(&self, size: (u16, u16)) -> bool {
+		self.canvas.is_none() || self.size != size
+	}
+
+	pub fn invalidate(&mut self) {
+		self.canvas = None;
+	}
+
+	pub fn size(&self) -> (u16, u16) {
+		self.size
+	}
+
+	/// The bytes of the rendered texture, 0 when there is none.
+	pub fn memory(&self) -> usize {
+		match self.canvas {
+			Some(_) => self.size.0 as usize * self.size.1 as usize * 4,
+			None => 0,
+		}
+	}
+
+	/// Renders into a fresh `size` texture cleared to `background`, with `area` as the screen
+	/// coordinates while `render` draws. The screen coordinates are put back afterwards, so this
+	/// can be done in the middle of a frame.
+	pub fn render(
+		&mut self,
+		ctx: &mut Context,
+		size: (u16, u16),
+		area: Rect,
+		background: Color,
+		render: impl FnOnce(&mut Context) -> GameResult,
+	) -> GameResult {
+		let canvas = graphics::Canvas::new(ctx, size.0, size.1, NumSamples::One)?;
+		let previous = graphics::screen_coordinates(ctx);
+		graphics::set_canvas(ctx, Some(&canvas));
+		let rendered = graphics::set_screen_coordinates(ctx, area).and_then(|()| {
+			graphics::clear(ctx, background);
+			render(ctx)
+		});
+		// Let go of the canvas even if rendering failed, or the rest of the frame goes into it
+		graphics::set_canvas(ctx, None);
+		graphics::set_screen_coordinates(ctx, previous)?;
+		rendered?;
+		self.canvas = Some(canvas);
+		self.size = size;
+		Ok(())
+	}
+
+	/// The rendered pixels as rgba8, top row first, empty if nothing was rendered.
+	pub fn to_rgba8(&self, ctx: &mut Context) -> GameResult<Vec<u8>> {
+		let canvas = match &self.canvas {
+			Some(canvas) => canvas,
+			None => return Ok(vec![]),
+		};
+		// Canvas images are kept bottom row first, the same reason ggez flips them when drawn
+		let pixels = canvas.image().to_rgba8(ctx)?;
+		Ok(pixels
+			.chunks_exact(self.size.0 as usize * 4)
+			.rev()
+			.flatten()
+			.copied()
+			.collect())
+	}
+
+	/// Draws the texture at its own size in whatever the screen coordinates are, nothing if it
+	/// was never rendered.
+	pub fn draw(&self, ctx: &mut Context, param: DrawParam) -> GameResult {
+		match &self.canvas {
+			Some(canvas) => canvas.draw(ctx, param),
+			None => Ok(()),
+		}
+	}
+}
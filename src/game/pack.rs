@@ -0,0 +1,328 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as AnyContext;
+use log::*;
+use serde::de::DeserializeOwned;
+
+use over_simple_game_1::core::engine::io::DirectFilesystemSimpleIO;
+use over_simple_game_1::core::engine::Engine;
+use over_simple_game_1::games::civ::CivGame;
+
+use crate::game::accessibility::AccessibilityConfig;
+use crate::game::atlas::{MultiAtlasBuilder, PackedAtlas};
+use crate::game::camera::CameraConfig;
+use crate::game::effects::EffectsConfig;
+use crate::game::grid::GridStyle;
+use crate::game::interaction::InputConfig;
+use crate::game::lod::LodConfig;
+use crate::game::memory::MemoryConfig;
+use crate::game::movement::MovementConfig;
+use crate::game::music::MusicPlaylists;
+use crate::game::panel::UiImageInfo;
+use crate::game::sequence::Sequence;
+use crate::game::settings::{AudioConfig, VideoConfig};
+use crate::game::soundscape::AmbientZone;
+use crate::game::text::TextConfig;
+use crate::game::tutorial::Tutorial;
+use crate::game::user_data::PathsConfig;
+use crate::game::{
+	atlas_image_path, decoration_image, tile_images, GameSetupConfig, JournalConfig, MapAtlas,
+	TileDrawableInfo, TILES_ATLAS_SIZE,
+};
+
+/// Where the pre-built tiles atlas entries are in a content bundle.
+pub const PACKED_TILES_ATLAS: &str = "packed/tiles_atlas.ron";
+
+/// Where each page of the pre-built tiles atlas is in a content bundle.
+pub fn packed_page_path(page: usize) -> String {
+	format!("packed/tiles_atlas_{}.png", page)
+}
+
+/// Checks everything in `resources` and writes it to the zip archive `out` along with the
+/// pre-built tiles atlas. Named `resources.zip` next to the executable it is read in place of
+/// the resources folder.
+///
+/// Every problem found is logged before failing, so one run shows all of them.
+pub fn pack_assets(resources: &Path, out: &Path) -> anyhow::Result<()> {
+	let mut files = vec![];
+	list_files(resources, Path::new(""), &mut files)
+		.with_context(|| format!("failed listing `{}`", resources.display()))?;
+	let mut problems = vec![];
+	for file in &files {
+		if let Err(error) = check_file(resources, file) {
+			problems.push(format!("{}: {:#}", file.display(), error));
+		}
+	}
+
+	let mut io = DirectFilesystemSimpleIO::with_path(resources.to_owned());
+	let mut engine: Engine<DirectFilesystemSimpleIO> = Engine::builder().build();
+	let atlas = match engine.setup(&mut io) {
+		Ok(()) => {
+			check_civ_data(resources, &mut io, &engine, &mut problems);
+			build_tiles_atlas(resources, &engine, &mut problems)?
+		}
+		Err(error) => {
+			problems.push(format!("engine data: {:#}", anyhow::Error::new(error)));
+			None
+		}
+	};
+	for problem in &problems {
+		error!("{}", problem);
+	}
+	anyhow::ensure!(
+		problems.is_empty(),
+		"found {} problems in `{}`",
+		problems.len(),
+		resources.display()
+	);
+	let (packed, pages) = atlas.context("tiles atlas was not built")?;
+
+	write_bundle(resources, &files, &packed, &pages, out)
+		.with_context(|| format!("failed writing `{}`", out.display()))?;
+	info!(
+		"Packed {} files and {} tiles atlas pages into `{}`",
+		files.len(),
+		pages.len(),
+		out.display()
+	);
+	Ok(())
+}
+
+/// Every file under `dir`, relative to the resources and sorted so bundles come out the same.
+fn list_files(resources: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+	let mut entries = std::fs::read_dir(resources.join(dir))?.collect::<Result<Vec<_>, _>>()?;
+	entries.sort_by_key(|entry| entry.file_name());
+	for entry in entries {
+		let path = dir.join(entry.file_name());
+		if entry.file_type()?.is_dir() {
+			list_files(resources, &path, files)?;
+		} else {
+			files.push(path);
+		}
+	}
+	Ok(())
+}
+
+/// Checks that a file parses as what its extension says it is, and RON files as what the game
+/// reads them as.
+fn check_file(resources: &Path, file: &Path) -> anyhow::Result<()> {
+	let path = resources.join(file);
+	match path.extension().and_then(|extension| extension.to_str()) {
+		Some("ron") => check_ron(file, &std::fs::read_to_string(&path)?)?,
+		Some("json") => {
+			let text = std::fs::read_to_string(path)?;
+			serde_json::from_str::<serde_json::Value>(&text)?;
+		}
+		Some("png") => {
+			image::load_from_memory(&std::fs::read(path)?)?;
+		}
+		_ => (),
+	}
+	Ok(())
+}
+
+/// Parses `text` as the type the game reads `file` into, misspelled or missing fields included.
+/// What the engine and the civ game read is only checked to be RON here, loading it after
+/// checks the rest.
+fn check_ron(file: &Path, text: &str) -> anyhow::Result<()> {
+	let dir = file.parent().and_then(|dir| dir.to_str()).unwrap_or("");
+	let name = file
+		.file_name()
+		.and_then(|name| name.to_str())
+		.unwrap_or("");
+	match (dir, name) {
+		("config", "accessibility.ron") => parse::<AccessibilityConfig>(text),
+		("config", "audio.ron") => parse::<AudioConfig>(text),
+		("config", "camera.ron") => parse::<CameraConfig>(text),
+		("config", "effects.ron") => parse::<EffectsConfig>(text),
+		("config", "game.ron") => parse::<GameSetupConfig>(text),
+		("config", "grid.ron") => parse::<GridStyle>(text),
+		("config", "input.ron") => parse::<InputConfig>(text),
+		("config", "journal.ron") => parse::<JournalConfig>(text),
+		("config", "lod.ron") => parse::<LodConfig>(text),
+		("config", "memory.ron") => parse::<MemoryConfig>(text),
+		("config", "movement.ron") => parse::<MovementConfig>(text),
+		("config", "paths.ron") => parse::<PathsConfig>(text),
+		("config", "text.ron") => parse::<TextConfig>(text),
+		("config", "video.ron") => parse::<VideoConfig>(text),
+		("music", "playlists.ron") => parse::<MusicPlaylists>(text),
+		("sounds", "ambient.ron") => parse::<Vec<AmbientZone>>(text),
+		("sprites", "_load.ron") => parse::<Vec<String>>(text),
+		("sequences", _) => parse::<Sequence>(text),
+		("tutorials", _) => parse::<Tutorial>(text),
+		("ui", name) if name.ends_with(".png.ron") => parse::<UiImageInfo>(text),
+		(_, name) if name.ends_with(".png.ron") => parse::<TileDrawableInfo>(text),
+		_ => parse::<ron::Value>(text),
+	}
+}
+
+fn parse<T: DeserializeOwned>(text: &str) -> anyhow::Result<()> {
+	ron::de::from_str::<T>(text)?;
+	Ok(())
+}
+
+/// Loads the civ game's data and every entity template in `civ/entities` as a game would, the
+/// rules with the speed and difficulty `config/game.ron` picks.
+fn check_civ_data(
+	resources: &Path,
+	io: &mut DirectFilesystemSimpleIO,
+	engine: &Engine<DirectFilesystemSimpleIO>,
+	problems: &mut Vec<String>,
+) {
+	let mut civ = CivGame::new("civ");
+	// A broken `config/game.ron` is already a problem of its own
+	let setup = std::fs::read_to_string(resources.join("config/game.ron"))
+		.ok()
+		.and_then(|text| ron::de::from_str::<GameSetupConfig>(&text).ok())
+		.unwrap_or_default();
+	let loads = vec![
+		(
+			"civ/rules.ron",
+			civ.load_rules(io, &setup.speed, &setup.difficulty),
+		),
+		("civ/wildlife.ron", civ.load_wildlife(io, engine)),
+		("civ/goodies.ron", civ.load_goodies(io, engine)),
+		("civ/influence.ron", civ.load_influence(io, engine)),
+		("civ/unit_ai.ron", civ.load_unit_ai(io)),
+		("civ/personas.ron", civ.load_personas(io)),
+	];
+	for (file, result) in loads {
+		if let Err(error) = result {
+			problems.push(format!("{}: {:#}", file, error));
+		}
+	}
+
+	let mut templates = vec![];
+	if let Err(error) = list_files(resources, Path::new("civ/entities"), &mut templates) {
+		problems.push(format!("civ/entities: {:#}", error));
+	}
+	for file in templates {
+		if file.extension().and_then(|extension| extension.to_str()) != Some("ron") {
+			continue;
+		}
+		let template = file
+			.file_stem()
+			.and_then(|stem| stem.to_str())
+			.unwrap_or("");
+		if let Err(error) = civ.validate_template(io, template) {
+			problems.push(format!("{}: {:#}", file.display(), error));
+		}
+	}
+}
+
+fn load_rgba(path: &Path) -> anyhow::Result<(u16, u16, Vec<u8>)> {
+	let image = image::load_from_memory(&std::fs::read(path)?)?.to_rgba();
+	Ok((
+		image.width() as u16,
+		image.height() as u16,
+		image.into_raw(),
+	))
+}
+
+/// Builds the tiles atlas the same way the game does, but a missing image is a problem instead of
+/// a placeholder. The pages come back as png files.
+fn build_tiles_atlas(
+	resources: &Path,
+	engine: &Engine<DirectFilesystemSimpleIO>,
+	problems: &mut Vec<String>,
+) -> anyhow::Result<Option<(PackedAtlas, Vec<Vec<u8>>)>> {
+	let mut images = vec![];
+	for name in engine.tile_types.tile_types.values().map(|t| &t.name) {
+		let path = PathBuf::from("tiles").join(format!("{}.png.ron", name));
+		let info = match std::fs::read_to_string(resources.join(&path)) {
+			Err(_e) => TileDrawableInfo::default(),
+			Ok(text) => match ron::de::from_str::<TileDrawableInfo>(&text) {
+				Ok(info) => info,
+				Err(error) => {
+					problems.push(format!("{}: {}", path.display(), error));
+					continue;
+				}
+			},
+		};
+		let tile_images = tile_images(name, &info);
+		if tile_images.is_empty() {
+			problems.push(format!("{}: variant weights are all zero", path.display()));
+		}
+		images.extend(tile_images.into_iter().map(|(image, _weight)| image));
+	}
+	images.extend(
+		engine
+			.decoration_types
+			.decoration_types
+			.values()
+			.map(|d| decoration_image(&d.name)),
+	);
+
+	let mut builder: MultiAtlasBuilder<Vec<u8>, MapAtlas> =
+		MultiAtlasBuilder::new(TILES_ATLAS_SIZE, TILES_ATLAS_SIZE);
+	let mut missing = false;
+	for image in &images {
+		let path = atlas_image_path(image);
+		let path = path.strip_prefix("/").unwrap_or(&path);
+		match load_rgba(&resources.join(path)) {
+			Ok(rgba) => {
+				builder
+					.get_or_create_with(image, || Ok(rgba))
+					.with_context(|| format!("failed adding `{}` to the tiles atlas", image))?;
+			}
+			Err(error) => {
+				problems.push(format!("{}: {:#}", path.display(), error));
+				missing = true;
+			}
+		}
+	}
+	if missing {
+		return Ok(None);
+	}
+
+	let atlas = builder.generate(&mut |width, height, rgba| {
+		let mut png = vec![];
+		image::png::PNGEncoder::new(&mut png).encode(
+			rgba,
+			width as u32,
+			height as u32,
+			image::ColorType::RGBA(8),
+		)?;
+		Ok(png)
+	})?;
+	let pages = (0..atlas.len_atlases())
+		.filter_map(|page| atlas.get_image_by_index(page).cloned())
+		.collect();
+	Ok(Some((atlas.to_packed(), pages)))
+}
+
+fn write_bundle(
+	resources: &Path,
+	files: &[PathBuf],
+	packed: &PackedAtlas,
+	pages: &[Vec<u8>],
+	out: &Path,
+) -> anyhow::Result<()> {
+	let mut zip = zip::ZipWriter::new(File::create(out)?);
+	let deflated =
+		zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+	for file in files {
+		// Archives always use forward slashes
+		let name: Vec<_> = file
+			.components()
+			.map(|component| component.as_os_str().to_string_lossy())
+			.collect();
+		zip.start_file(name.join("/"), deflated)?;
+		std::io::copy(&mut File::open(resources.join(file))?, &mut zip)?;
+	}
+
+	zip.start_file(PACKED_TILES_ATLAS, deflated)?;
+	let text = ron::ser::to_string_pretty(packed, ron::ser::PrettyConfig::default())?;
+	zip.write_all(text.as_bytes())?;
+	// Already compressed
+	let stored =
+		zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+	for (page, png) in pages.iter().enumerate() {
+		zip.start_file(packed_page_path(page), stored)?;
+		zip.write_all(png)?;
+	}
+	zip.finish()?;
+	Ok(())
+}
@@ -0,0 +1,234 @@
+use ggez::graphics::{self, Color, DrawParam, Drawable, Rect, Vertex};
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+
+use crate::game::atlas::{AtlasEntry, AtlasId, MultiAtlas, NineSlice};
+
+/// Nine slice data for a UI image, read from the `<name>.png.ron` next to it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UiImageInfo {
+	#[serde(default)]
+	pub nine_slice: Option<NineSlice>,
+}
+
+/// A piece of one axis of a panel, in screen pixels and in texture coordinates.
+#[derive(Clone, Copy, Debug)]
+struct Span {
+	from: f32,
+	to: f32,
+	uv_from: f32,
+	uv_to: f32,
+}
+
+/// Splits one axis into the start border, the middle (one span or repeats of it) and the end
+/// border. Borders shrink evenly if the panel is smaller than both of them together.
+#[allow(clippy::too_many_arguments)]
+fn spans(
+	from: f32,
+	length: f32,
+	uv_from: f32,
+	uv_length: f32,
+	source_length: u16,
+	start: u16,
+	end: u16,
+	scale: f32,
+	tiled: bool,
+) -> Vec<Span> {
+	let uv_per_pixel = uv_length / source_length.max(1) as f32;
+	let borders = (start + end) as f32 * scale;
+	let shrink = if borders > length && borders > 0.0 {
+		length / borders
+	} else {
+		1.0
+	};
+	let start_length = start as f32 * scale * shrink;
+	let end_length = end as f32 * scale * shrink;
+	let middle_pixels = source_length.saturating_sub(start + end) as f32;
+	let uv_middle_from = uv_from + start as f32 * uv_per_pixel;
+	let uv_middle_to = uv_middle_from + middle_pixels * uv_per_pixel;
+
+	let mut spans = vec![Span {
+		from,
+		to: from + start_length,
+		uv_from,
+		uv_to: uv_middle_from,
+	}];
+	let middle_from = from + start_length;
+	let middle_to = from + length - end_length;
+	let step = middle_pixels * scale;
+	if tiled && step > 0.0 {
+		let mut at = middle_from;
+		while at < middle_to {
+			let to = (at + step).min(middle_to);
+			spans.push(Span {
+				from: at,
+				to,
+				uv_from: uv_middle_from,
+				uv_to: uv_middle_from + (uv_middle_to - uv_middle_from) * ((to - at) / step),
+			});
+			at = to;
+		}
+	} else {
+		spans.push(Span {
+			from: middle_from,
+			to: middle_to,
+			uv_from: uv_middle_from,
+			uv_to: uv_middle_to,
+		});
+	}
+	spans.push(Span {
+		from: middle_to,
+		to: from + length,
+		uv_from: uv_middle_to,
+		uv_to: uv_from + uv_length,
+	});
+	spans.retain(|span| span.to > span.from);
+	spans
+}
+
+/// Adds the quads stretching `entry` over `dest` to `vertices` and `indices`, keeping the nine
+/// slice borders at `scale` times their source size. Entries without nine slice data are
+/// stretched whole.
+pub fn nine_slice_quads<Unique: Copy>(
+	entry: &AtlasEntry<Unique>,
+	dest: Rect,
+	scale: f32,
+	color: Color,
+	vertices: &mut Vec<Vertex>,
+	indices: &mut Vec<u32>,
+) {
+	let slice = entry.nine_slice.unwrap_or_default();
+	let columns = spans(
+		dest.x,
+		dest.w,
+		entry.left(),
+		entry.width(),
+		entry.size[0],
+		slice.left,
+		slice.right,
+		scale,
+		slice.tiled,
+	);
+	let rows = spans(
+		dest.y,
+		dest.h,
+		entry.top(),
+		entry.height(),
+		entry.size[1],
+		slice.top,
+		slice.bottom,
+		scale,
+		slice.tiled,
+	);
+	let color: [f32; 4] = [color.r, color.g, color.b, color.a];
+	for row in &rows {
+		for column in &columns {
+			let base = vertices.len() as u32;
+			vertices.extend_from_slice(&[
+				Vertex {
+					pos: [column.from, row.from],
+					uv: [column.uv_from, row.uv_from],
+					color,
+				},
+				Vertex {
+					pos: [column.from, row.to],
+					uv: [column.uv_from, row.uv_to],
+					color,
+				},
+				Vertex {
+					pos: [column.to, row.to],
+					uv: [column.uv_to, row.uv_to],
+					color,
+				},
+				Vertex {
+					pos: [column.to, row.from],
+					uv: [column.uv_to, row.uv_from],
+					color,
+				},
+			]);
+			indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+		}
+	}
+}
+
+/// Draws the atlas image `id` as a panel covering `dest` in screen pixels.
+pub fn draw_panel<Unique: Copy>(
+	ctx: &mut Context,
+	atlas: &MultiAtlas<graphics::Image, Unique>,
+	id: AtlasId<Unique>,
+	dest: Rect,
+	scale: f32,
+	color: Color,
+) -> GameResult {
+	let mut vertices = Vec::new();
+	let mut indices = Vec::new();
+	nine_slice_quads(
+		atlas.get_entry(id),
+		dest,
+		scale,
+		color,
+		&mut vertices,
+		&mut indices,
+	);
+	if indices.is_empty() {
+		return Ok(());
+	}
+	let mesh = graphics::MeshBuilder::new()
+		.raw(&vertices, &indices, Some(atlas.get_image(id).clone()))
+		.build(ctx)?;
+	mesh.draw(ctx, DrawParam::new())
+}
+
+#[cfg(test)]
+mod panel_tests {
+	use super::*;
+	use assert_approx_eq::assert_approx_eq;
+
+	/// Checks each span's pixels and texture coordinates against `expected`.
+	fn assert_spans(spans: &[Span], expected: &[(f32, f32, f32, f32)]) {
+		assert_eq!(spans.len(), expected.len(), "{:?}", spans);
+		for (span, &(from, to, uv_from, uv_to)) in spans.iter().zip(expected) {
+			assert_approx_eq!(span.from, from);
+			assert_approx_eq!(span.to, to);
+			assert_approx_eq!(span.uv_from, uv_from);
+			assert_approx_eq!(span.uv_to, uv_to);
+		}
+	}
+
+	#[test]
+	fn the_middle_stretches_between_scaled_borders() {
+		// A 32 pixel source with 8 pixel borders, drawn at twice its size over 100 pixels
+		let spans = spans(10.0, 100.0, 0.0, 1.0, 32, 8, 8, 2.0, false);
+		assert_spans(
+			&spans,
+			&[
+				(10.0, 26.0, 0.0, 0.25),
+				(26.0, 94.0, 0.25, 0.75),
+				(94.0, 110.0, 0.75, 1.0),
+			],
+		);
+	}
+
+	#[test]
+	fn borders_shrink_evenly_when_they_do_not_fit() {
+		let spans = spans(0.0, 12.0, 0.5, 0.5, 32, 8, 16, 1.0, false);
+		// Half the length each way and no middle left at all
+		assert_spans(&spans, &[(0.0, 4.0, 0.5, 0.625), (4.0, 12.0, 0.75, 1.0)]);
+	}
+
+	#[test]
+	fn tiled_middles_repeat_and_crop_the_last_piece() {
+		// The middle is 16 pixels of the source and has 40 pixels to cover
+		let spans = spans(0.0, 56.0, 0.0, 1.0, 32, 8, 8, 1.0, true);
+		assert_spans(
+			&spans,
+			&[
+				(0.0, 8.0, 0.0, 0.25),
+				(8.0, 24.0, 0.25, 0.75),
+				(24.0, 40.0, 0.25, 0.75),
+				(40.0, 48.0, 0.25, 0.5),
+				(48.0, 56.0, 0.75, 1.0),
+			],
+		);
+	}
+}
@@ -0,0 +1,121 @@
+/// Size of the art generated for tiles and decorations whose images are missing.
+pub const PLACEHOLDER_WIDTH: u16 = 64;
+pub const PLACEHOLDER_HEIGHT: u16 = 76;
+
+const CHECKER_SIZE: usize = 8;
+const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+const BAND: [u8; 4] = [40, 0, 40, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// Each glyph is 3x5 pixels, rows top to bottom with the leftmost pixel the highest bit.
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+/// How many pixels each glyph pixel covers.
+const GLYPH_SCALE: usize = 2;
+
+fn glyph(c: char) -> u16 {
+	match c.to_ascii_uppercase() {
+		'A' => 0b010_101_111_101_101,
+		'B' => 0b110_101_110_101_110,
+		'C' => 0b011_100_100_100_011,
+		'D' => 0b110_101_101_101_110,
+		'E' => 0b111_100_110_100_111,
+		'F' => 0b111_100_110_100_100,
+		'G' => 0b011_100_101_101_011,
+		'H' => 0b101_101_111_101_101,
+		'I' => 0b111_010_010_010_111,
+		'J' => 0b001_001_001_101_010,
+		'K' => 0b101_101_110_101_101,
+		'L' => 0b100_100_100_100_111,
+		'M' => 0b101_111_111_101_101,
+		'N' => 0b110_101_101_101_101,
+		'O' => 0b010_101_101_101_010,
+		'P' => 0b110_101_110_100_100,
+		'Q' => 0b010_101_101_110_011,
+		'R' => 0b110_101_110_101_101,
+		'S' => 0b011_100_010_001_110,
+		'T' => 0b111_010_010_010_010,
+		'U' => 0b101_101_101_101_111,
+		'V' => 0b101_101_101_101_010,
+		'W' => 0b101_101_111_111_101,
+		'X' => 0b101_101_010_101_101,
+		'Y' => 0b101_101_010_010_010,
+		'Z' => 0b111_001_010_100_111,
+		'0' => 0b111_101_101_101_111,
+		'1' => 0b010_110_010_010_111,
+		'2' => 0b110_001_010_100_111,
+		'3' => 0b110_001_010_001_110,
+		'4' => 0b101_101_111_001_001,
+		'5' => 0b111_100_110_001_110,
+		'6' => 0b011_100_111_101_111,
+		'7' => 0b111_001_010_010_010,
+		'8' => 0b111_101_111_101_111,
+		'9' => 0b111_101_111_001_110,
+		'_' => 0b000_000_000_000_111,
+		'-' => 0b000_000_111_000_000,
+		'/' => 0b001_001_010_100_100,
+		' ' => 0,
+		_ => 0b110_001_010_000_010,
+	}
+}
+
+/// A magenta and black checker with `name` written across it, so missing art stands out in game
+/// and says what is missing. Returns the width, height and rgba8 pixels.
+pub fn placeholder_rgba(name: &str) -> (u16, u16, Vec<u8>) {
+	let (width, height) = (PLACEHOLDER_WIDTH as usize, PLACEHOLDER_HEIGHT as usize);
+	let mut rgba = Vec::with_capacity(width * height * 4);
+	for y in 0..height {
+		for x in 0..width {
+			let checker = (x / CHECKER_SIZE + y / CHECKER_SIZE) % 2 == 0;
+			rgba.extend_from_slice(if checker { &MAGENTA } else { &BLACK });
+		}
+	}
+
+	let advance = (GLYPH_WIDTH + 1) * GLYPH_SCALE;
+	let line_height = (GLYPH_HEIGHT + 1) * GLYPH_SCALE;
+	let per_line = ((width - GLYPH_SCALE) / advance).max(1);
+	let max_lines = (height - GLYPH_SCALE) / line_height;
+	let chars: Vec<char> = name.chars().collect();
+	let lines: Vec<&[char]> = chars.chunks(per_line).take(max_lines).collect();
+	let mut set = |x: usize, y: usize, color: &[u8; 4]| {
+		if x < width && y < height {
+			let i = (y * width + x) * 4;
+			rgba[i..i + 4].copy_from_slice(color);
+		}
+	};
+
+	// A dark band behind the text so it reads over the checker
+	let band_height = lines.len() * line_height + GLYPH_SCALE;
+	let band_top = (height - band_height) / 2;
+	for y in band_top..band_top + band_height {
+		for x in 0..width {
+			set(x, y, &BAND);
+		}
+	}
+	for (row, line) in lines.iter().enumerate() {
+		let left = (width - line.len() * advance + GLYPH_SCALE) / 2;
+		let top = band_top + GLYPH_SCALE + row * line_height;
+		for (column, &c) in line.iter().enumerate() {
+			let bits = glyph(c);
+			for gy in 0..GLYPH_HEIGHT {
+				for gx in 0..GLYPH_WIDTH {
+					let bit = (GLYPH_HEIGHT - 1 - gy) * GLYPH_WIDTH + (GLYPH_WIDTH - 1 - gx);
+					if bits & (1 << bit) == 0 {
+						continue;
+					}
+					for sy in 0..GLYPH_SCALE {
+						for sx in 0..GLYPH_SCALE {
+							set(
+								left + column * advance + gx * GLYPH_SCALE + sx,
+								top + gy * GLYPH_SCALE + sy,
+								&WHITE,
+							);
+						}
+					}
+				}
+			}
+		}
+	}
+	(PLACEHOLDER_WIDTH, PLACEHOLDER_HEIGHT, rgba)
+}
@@ -0,0 +1,62 @@
+use ggez::nalgebra as na;
+use winit::dpi;
+
+/// Tracks the window metrics needed to carry input positions through to map space.
+///
+/// Winit reports cursor positions and window sizes in logical pixels, the physical size is only
+/// the logical size times the HiDPI factor.  Input goes logical pixels -> screen ratio (`0..1`
+/// across the window) and the camera then takes the ratio to map space, so nothing downstream
+/// has to care what the display density is.
+#[derive(Clone, Copy, Debug)]
+pub struct ScreenMetrics {
+	pub logical_size: dpi::LogicalSize,
+	pub hidpi_factor: f64,
+	/// User setting multiplied into UI sizes (text, panels, click leeway), separate from the DPI.
+	ui_scale: f32,
+}
+
+impl ScreenMetrics {
+	pub fn new(logical_size: dpi::LogicalSize, hidpi_factor: f64) -> ScreenMetrics {
+		ScreenMetrics {
+			logical_size,
+			hidpi_factor,
+			ui_scale: 1.0,
+		}
+	}
+
+	pub fn width(&self) -> f32 {
+		self.logical_size.width as f32
+	}
+
+	pub fn height(&self) -> f32 {
+		self.logical_size.height as f32
+	}
+
+	pub fn aspect_ratio(&self) -> f32 {
+		(self.logical_size.width / self.logical_size.height) as f32
+	}
+
+	pub fn physical_size(&self) -> dpi::PhysicalSize {
+		self.logical_size.to_physical(self.hidpi_factor)
+	}
+
+	pub fn logical_to_ratio(&self, position: dpi::LogicalPosition) -> na::Point2<f32> {
+		na::Point2::new(
+			(position.x / self.logical_size.width) as f32,
+			(position.y / self.logical_size.height) as f32,
+		)
+	}
+
+	pub fn ui_scale(&self) -> f32 {
+		self.ui_scale
+	}
+
+	pub fn set_ui_scale(&mut self, ui_scale: f32) {
+		self.ui_scale = ui_scale.max(0.5).min(3.0);
+	}
+
+	/// Scales a UI size given in logical pixels by the UI scale setting.
+	pub fn ui(&self, size: f32) -> f32 {
+		size * self.ui_scale
+	}
+}
@@ -0,0 +1,213 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+use serde::Deserialize;
+
+use over_simple_game_1::core::engine::address::MapAddress;
+
+use crate::game::atlas::{AtlasId, MultiAtlas};
+use crate::game::panel;
+use crate::game::text::{TextRole, TextStyles};
+
+const DIALOG_WIDTH: f32 = 560.0;
+const LINE_HEIGHT: f32 = 20.0;
+
+fn serde_skippable() -> bool {
+	true
+}
+
+/// A step of a sequence, each runs for its `seconds` before the next one starts.
+#[derive(Clone, Debug, Deserialize)]
+pub enum SequenceStep {
+	/// Glides the camera to a tile, or cuts to it when it is on another map.
+	MoveCamera {
+		to: MapAddress,
+		seconds: f32,
+	},
+	/// Glides the camera to the first entity tagged `tag`.
+	FocusEntity {
+		tag: String,
+		seconds: f32,
+	},
+	/// Shows `text` in a panel at the bottom of the screen, each line of it on its own line.
+	Dialog {
+		#[serde(default)]
+		speaker: Option<String>,
+		text: String,
+		seconds: f32,
+	},
+	/// Shakes the view as if something hit `at`, `strength` from `0.0` to `1.0`.
+	Impact {
+		at: MapAddress,
+		strength: f32,
+	},
+	/// Rings spreading out from `at`, like a goody hut being opened.
+	Discovery {
+		at: MapAddress,
+	},
+	Wait {
+		seconds: f32,
+	},
+}
+
+impl SequenceStep {
+	pub fn seconds(&self) -> f32 {
+		match self {
+			SequenceStep::MoveCamera { seconds, .. }
+			| SequenceStep::FocusEntity { seconds, .. }
+			| SequenceStep::Dialog { seconds, .. }
+			| SequenceStep::Wait { seconds } => seconds.max(0.0),
+			SequenceStep::Impact { .. } | SequenceStep::Discovery { .. } => 0.0,
+		}
+	}
+}
+
+/// Timed steps played one after another for scenario intros and tutorial beats, read from
+/// `/sequences/<name>.ron` or next to a scenario's map as `<map>.intro.ron`.
+///
+/// ```ron
+/// (
+/// 	steps: [
+/// 		MoveCamera(to: "island:4,3", seconds: 2.0),
+/// 		Dialog(speaker: Some("Scout"), text: "Land ho!", seconds: 3.0),
+/// 		Wait(seconds: 0.5),
+/// 	],
+/// )
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct Sequence {
+	pub steps: Vec<SequenceStep>,
+	/// Whether Escape ends it early.
+	#[serde(default = "serde_skippable")]
+	pub skippable: bool,
+}
+
+/// Plays a sequence in real time, the game's input is ignored while one plays.
+pub struct SequencePlayer {
+	sequence: Sequence,
+	step: usize,
+	/// Seconds into the current step.
+	elapsed: f32,
+	started: bool,
+}
+
+impl SequencePlayer {
+	pub fn new(sequence: Sequence) -> SequencePlayer {
+		SequencePlayer {
+			sequence,
+			step: 0,
+			elapsed: 0.0,
+			started: false,
+		}
+	}
+
+	/// Advances by `delta` seconds, returning the steps that started meanwhile in order.
+	pub fn advance(&mut self, delta: f32) -> Vec<SequenceStep> {
+		let mut started = vec![];
+		if !self.started {
+			self.started = true;
+			started.extend(self.sequence.steps.first().cloned());
+		}
+		self.elapsed += delta;
+		while let Some(step) = self.sequence.steps.get(self.step) {
+			if self.elapsed < step.seconds() {
+				break;
+			}
+			self.elapsed -= step.seconds();
+			self.step += 1;
+			started.extend(self.sequence.steps.get(self.step).cloned());
+		}
+		started
+	}
+
+	/// The step playing and `0..1` through it.
+	pub fn current(&self) -> Option<(&SequenceStep, f32)> {
+		let step = self.sequence.steps.get(self.step)?;
+		let progress = match step.seconds() {
+			seconds if seconds > 0.0 => (self.elapsed / seconds).min(1.0),
+			_ => 1.0,
+		};
+		Some((step, progress))
+	}
+
+	/// Ends the current step, the next starts on the next `advance`.
+	pub fn next_step(&mut self) {
+		if let Some(step) = self.sequence.steps.get(self.step) {
+			self.elapsed = self.elapsed.max(step.seconds());
+		}
+	}
+
+	/// Ends the whole sequence, unless it can't be skipped.
+	pub fn skip(&mut self) {
+		if self.sequence.skippable {
+			self.step = self.sequence.steps.len();
+		}
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.started && self.step >= self.sequence.steps.len()
+	}
+
+	pub fn skippable(&self) -> bool {
+		self.sequence.skippable
+	}
+}
+
+/// The dialog step's panel along the bottom of the screen.
+pub fn draw_dialog<Unique: Copy>(
+	ctx: &mut Context,
+	text_styles: &mut TextStyles,
+	speaker: Option<&str>,
+	text: &str,
+	hint: &str,
+	screen_size: (f32, f32),
+	ui_scale: f32,
+	skin: Option<(&MultiAtlas<graphics::Image, Unique>, AtlasId<Unique>)>,
+) -> GameResult {
+	let mut lines: Vec<(TextRole, &str)> = vec![];
+	if let Some(speaker) = speaker {
+		lines.push((TextRole::Heading, speaker));
+	}
+	lines.extend(text.lines().map(|line| (TextRole::Body, line)));
+
+	let line_height = LINE_HEIGHT * ui_scale;
+	let margin = 10.0 * ui_scale;
+	let width = (DIALOG_WIDTH * ui_scale).min(screen_size.0);
+	// The lines and the hint
+	let height = line_height * (lines.len() as f32 + 1.0) + margin * 2.0;
+	let bounds = Rect::new(
+		(screen_size.0 - width) * 0.5,
+		(screen_size.1 - height - margin * 4.0).max(0.0),
+		width,
+		height,
+	);
+	match skin {
+		Some((atlas, id)) => {
+			panel::draw_panel(ctx, atlas, id, bounds, ui_scale, graphics::WHITE)?;
+		}
+		None => {
+			let mesh = graphics::MeshBuilder::new()
+				.rectangle(DrawMode::fill(), bounds, Color::new(0.1, 0.1, 0.1, 0.95))
+				.rectangle(DrawMode::stroke(1.0), bounds, graphics::WHITE)
+				.build(ctx)?;
+			mesh.draw(ctx, DrawParam::new())?;
+		}
+	}
+
+	let x = bounds.x + margin;
+	let mut y = bounds.y + margin;
+	for (role, line) in &lines {
+		let text = text_styles.layout(*role, line, ui_scale);
+		graphics::draw(ctx, text, DrawParam::new().dest(na::Point2::new(x, y)))?;
+		y += line_height;
+	}
+
+	let dim = Color::new(0.6, 0.6, 0.6, 1.0);
+	let text = text_styles.layout(TextRole::Tooltip, hint, ui_scale * 0.7);
+	graphics::draw(
+		ctx,
+		text,
+		DrawParam::new().dest(na::Point2::new(x, y)).color(dim),
+	)?;
+	Ok(())
+}
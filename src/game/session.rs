@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+
+use crate::game::text::{TextRole, TextStyles};
+
+/// The turn timer turns red with this little left.
+const WARN_REMAINING: Duration = Duration::from_secs(10);
+
+/// How long the session and each of its turns have taken.
+///
+/// The session time is real time. The turn time only counts while the game runs, so pausing
+/// also stops the turn timer. There are no other players yet, so the turn is always the local
+/// player's.
+pub struct SessionStats {
+	elapsed: Duration,
+	turn: u32,
+	turn_elapsed: Duration,
+	/// How long each of the finished turns took, the first turn first.
+	turn_durations: Vec<Duration>,
+	/// Ends the turn by itself once it has taken this long, for timed games.
+	turn_limit: Option<Duration>,
+}
+
+impl SessionStats {
+	pub fn new(turn_limit: Option<Duration>) -> SessionStats {
+		SessionStats {
+			elapsed: Duration::from_secs(0),
+			turn: 1,
+			turn_elapsed: Duration::from_secs(0),
+			turn_durations: vec![],
+			turn_limit,
+		}
+	}
+
+	/// Adds a frame's real time, returning whether the turn timer ran out during it.
+	pub fn advance(&mut self, delta: Duration, paused: bool) -> bool {
+		self.elapsed += delta;
+		if paused {
+			return false;
+		}
+		self.turn_elapsed += delta;
+		self.is_expired()
+	}
+
+	/// Finishes the current turn and starts the next, returning how long the finished one took.
+	pub fn end_turn(&mut self) -> Duration {
+		let took = std::mem::replace(&mut self.turn_elapsed, Duration::from_secs(0));
+		self.turn_durations.push(took);
+		self.turn += 1;
+		took
+	}
+
+	pub fn elapsed(&self) -> Duration {
+		self.elapsed
+	}
+
+	/// The current turn's number, from 1.
+	pub fn turn(&self) -> u32 {
+		self.turn
+	}
+
+	pub fn turn_elapsed(&self) -> Duration {
+		self.turn_elapsed
+	}
+
+	pub fn turn_durations(&self) -> &[Duration] {
+		&self.turn_durations
+	}
+
+	pub fn turn_limit(&self) -> Option<Duration> {
+		self.turn_limit
+	}
+
+	/// Changes the limit of the current turn on as well, `None` lets turns take as long as they
+	/// take.
+	pub fn set_turn_limit(&mut self, turn_limit: Option<Duration>) {
+		self.turn_limit = turn_limit;
+	}
+
+	/// What is left of the turn limit, `None` when turns aren't timed.
+	pub fn remaining(&self) -> Option<Duration> {
+		self.turn_limit
+			.map(|limit| limit.checked_sub(self.turn_elapsed).unwrap_or_default())
+	}
+
+	pub fn is_expired(&self) -> bool {
+		self.remaining() == Some(Duration::from_secs(0))
+	}
+
+	/// The mean of the finished turns, `None` before the first has finished.
+	pub fn average_turn(&self) -> Option<Duration> {
+		let total: Duration = self.turn_durations.iter().sum();
+		match self.turn_durations.len() {
+			0 => None,
+			turns => Some(total / turns as u32),
+		}
+	}
+
+	pub fn longest_turn(&self) -> Option<Duration> {
+		self.turn_durations.iter().max().copied()
+	}
+
+	/// A line for the chat about the session so far.
+	pub fn summary(&self) -> String {
+		let mut summary = format!(
+			"session {}, turn {} at {}",
+			format_duration(self.elapsed),
+			self.turn,
+			format_duration(self.turn_elapsed)
+		);
+		if let (Some(average), Some(longest)) = (self.average_turn(), self.longest_turn()) {
+			summary.push_str(&format!(
+				", {} turns done averaging {} and the longest {}",
+				self.turn_durations.len(),
+				format_duration(average),
+				format_duration(longest)
+			));
+		}
+		if let Some(limit) = self.turn_limit {
+			summary.push_str(&format!(", turns limited to {}", format_duration(limit)));
+		}
+		summary
+	}
+}
+
+/// As `m:ss`, or `h:mm:ss` once there are hours.
+pub fn format_duration(duration: Duration) -> String {
+	let seconds = duration.as_secs();
+	let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+	if hours > 0 {
+		format!("{}:{:02}:{:02}", hours, minutes, seconds)
+	} else {
+		format!("{}:{:02}", minutes, seconds)
+	}
+}
+
+/// The turn and how long it and the session have taken in the top right corner, with the time
+/// left instead when turns are timed.
+pub fn draw_session(
+	ctx: &mut Context,
+	text_styles: &mut TextStyles,
+	session: &SessionStats,
+	screen_size: (f32, f32),
+	ui_scale: f32,
+) -> GameResult {
+	let margin = 8.0 * ui_scale;
+	let (turn_time, warn) = match session.remaining() {
+		Some(remaining) => (
+			format!("{} left", format_duration(remaining)),
+			remaining <= WARN_REMAINING,
+		),
+		None => (format_duration(session.turn_elapsed()), false),
+	};
+	let content = format!(
+		"Turn {}  {}  ({})",
+		session.turn(),
+		turn_time,
+		format_duration(session.elapsed())
+	);
+	let text = text_styles.layout(TextRole::Body, &content, ui_scale);
+	let (width, height) = (text.width(ctx) as f32, text.height(ctx) as f32);
+	let at = na::Point2::new(screen_size.0 - width - margin * 2.0, margin * 2.0);
+	let backing = Rect::new(
+		at.x - margin,
+		at.y - margin,
+		width + margin * 2.0,
+		height + margin * 2.0,
+	);
+	let mesh = graphics::Mesh::new_rectangle(
+		ctx,
+		DrawMode::fill(),
+		backing,
+		Color::new(0.1, 0.1, 0.1, 0.8),
+	)?;
+	mesh.draw(ctx, DrawParam::new())?;
+	let color = if warn {
+		Color::new(1.0, 0.35, 0.3, 1.0)
+	} else {
+		graphics::WHITE
+	};
+	graphics::draw(ctx, text, DrawParam::new().dest(at).color(color))?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod session_tests {
+	use super::*;
+
+	fn secs(seconds: u64) -> Duration {
+		Duration::from_secs(seconds)
+	}
+
+	#[test]
+	fn pausing_stops_only_the_turn_timer() {
+		let mut session = SessionStats::new(None);
+		assert!(!session.advance(secs(3), false));
+		assert!(!session.advance(secs(5), true));
+		assert_eq!(session.elapsed(), secs(8));
+		assert_eq!(session.turn_elapsed(), secs(3));
+		assert_eq!(session.remaining(), None);
+		assert!(!session.is_expired());
+	}
+
+	#[test]
+	fn timed_turns_expire() {
+		let mut session = SessionStats::new(Some(secs(10)));
+		assert!(!session.advance(secs(4), false));
+		assert_eq!(session.remaining(), Some(secs(6)));
+		// Paused time doesn't count towards the limit
+		assert!(!session.advance(secs(20), true));
+		assert!(session.advance(secs(7), false));
+		assert_eq!(session.remaining(), Some(secs(0)));
+		assert!(session.is_expired());
+
+		assert_eq!(session.end_turn(), secs(11));
+		assert!(!session.is_expired());
+		assert_eq!(session.remaining(), Some(secs(10)));
+		session.set_turn_limit(None);
+		assert!(!session.advance(secs(60), false));
+	}
+
+	#[test]
+	fn ending_turns_keeps_their_durations() {
+		let mut session = SessionStats::new(None);
+		assert_eq!(session.turn(), 1);
+		assert_eq!(session.average_turn(), None);
+		assert_eq!(session.longest_turn(), None);
+		for seconds in &[30, 90, 60] {
+			session.advance(secs(*seconds), false);
+			assert_eq!(session.end_turn(), secs(*seconds));
+		}
+		assert_eq!(session.turn(), 4);
+		assert_eq!(session.turn_elapsed(), secs(0));
+		assert_eq!(session.turn_durations(), &[secs(30), secs(90), secs(60)]);
+		assert_eq!(session.average_turn(), Some(secs(60)));
+		assert_eq!(session.longest_turn(), Some(secs(90)));
+		assert_eq!(
+			session.summary(),
+			"session 3:00, turn 4 at 0:00, 3 turns done averaging 1:00 and the longest 1:30"
+		);
+	}
+
+	#[test]
+	fn formats_durations() {
+		assert_eq!(format_duration(secs(0)), "0:00");
+		assert_eq!(format_duration(Duration::from_millis(59_999)), "0:59");
+		assert_eq!(format_duration(secs(75)), "1:15");
+		assert_eq!(format_duration(secs(3600 + 2 * 60 + 5)), "1:02:05");
+	}
+}
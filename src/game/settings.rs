@@ -0,0 +1,352 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, FilterMode, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use winit::VirtualKeyCode;
+
+use crate::game::atlas::{AtlasId, MultiAtlas};
+use crate::game::keybindings::{self, KeyAction};
+use crate::game::panel;
+use crate::game::text::{TextRole, TextStyles};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureFilter {
+	/// Keeps pixel art crisp.
+	Nearest,
+	/// Smooths higher resolution art.
+	Linear,
+}
+
+impl Default for TextureFilter {
+	fn default() -> Self {
+		TextureFilter::Nearest
+	}
+}
+
+impl From<TextureFilter> for FilterMode {
+	fn from(filter: TextureFilter) -> Self {
+		match filter {
+			TextureFilter::Nearest => FilterMode::Nearest,
+			TextureFilter::Linear => FilterMode::Linear,
+		}
+	}
+}
+
+/// How an atlas's pages are sampled, changes apply when the atlases are next built.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct AtlasFilter {
+	#[serde(default)]
+	pub filter: TextureFilter,
+	/// Halved copies of each page drawn from when zoomed out, which stops distant tiles from
+	/// shimmering as the camera moves. Together they take a third more memory than the pages.
+	#[serde(default)]
+	pub mip_levels: u8,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct VideoConfig {
+	#[serde(default)]
+	pub fullscreen: bool,
+	/// `None` keeps whatever the UI scale already is.
+	#[serde(default)]
+	pub ui_scale: Option<f32>,
+	#[serde(default)]
+	pub tiles_filter: AtlasFilter,
+	#[serde(default)]
+	pub entities_filter: AtlasFilter,
+	/// The UI is drawn at its own size, so it has no use for mip levels.
+	#[serde(default)]
+	pub ui_filter: TextureFilter,
+}
+
+fn serde_volume() -> f32 {
+	1.0
+}
+
+/// Music plays at the music volume, ambient and notification sounds at the effects volume.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AudioConfig {
+	#[serde(default = "serde_volume")]
+	pub master_volume: f32,
+	#[serde(default = "serde_volume")]
+	pub music_volume: f32,
+	#[serde(default = "serde_volume")]
+	pub effects_volume: f32,
+}
+
+impl Default for AudioConfig {
+	fn default() -> Self {
+		AudioConfig {
+			master_volume: serde_volume(),
+			music_volume: serde_volume(),
+			effects_volume: serde_volume(),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsTab {
+	Video,
+	Audio,
+	Input,
+	Gameplay,
+}
+
+impl SettingsTab {
+	pub const ALL: [SettingsTab; 4] = [
+		SettingsTab::Video,
+		SettingsTab::Audio,
+		SettingsTab::Input,
+		SettingsTab::Gameplay,
+	];
+
+	pub fn label(self) -> &'static str {
+		match self {
+			SettingsTab::Video => "Video",
+			SettingsTab::Audio => "Audio",
+			SettingsTab::Input => "Input",
+			SettingsTab::Gameplay => "Gameplay",
+		}
+	}
+
+	pub fn settings(self) -> Vec<Setting> {
+		match self {
+			SettingsTab::Video => vec![
+				Setting::Fullscreen,
+				Setting::UiScale,
+				Setting::HighContrast,
+				Setting::ScreenEffects,
+			],
+			SettingsTab::Audio => vec![
+				Setting::MasterVolume,
+				Setting::MusicVolume,
+				Setting::EffectsVolume,
+			],
+			SettingsTab::Input => {
+				let mut settings = vec![
+					Setting::ClickLeeway,
+					Setting::DoubleClickMs,
+					Setting::LongPressMs,
+				];
+				settings.extend(KeyAction::ALL.iter().map(|&action| Setting::Key(action)));
+				settings
+			}
+			SettingsTab::Gameplay => {
+				vec![Setting::CameraPolicy, Setting::CameraMargin, Setting::Grid]
+			}
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+	Fullscreen,
+	UiScale,
+	HighContrast,
+	ScreenEffects,
+	MasterVolume,
+	MusicVolume,
+	EffectsVolume,
+	ClickLeeway,
+	DoubleClickMs,
+	LongPressMs,
+	Key(KeyAction),
+	CameraPolicy,
+	CameraMargin,
+	Grid,
+}
+
+impl Setting {
+	pub fn label(self) -> &'static str {
+		match self {
+			Setting::Fullscreen => "Fullscreen",
+			Setting::UiScale => "UI scale",
+			Setting::HighContrast => "High contrast overlays",
+			Setting::ScreenEffects => "Screen shake and flashes",
+			Setting::MasterVolume => "Master volume",
+			Setting::MusicVolume => "Music volume",
+			Setting::EffectsVolume => "Effects volume",
+			Setting::ClickLeeway => "Click leeway (pixels)",
+			Setting::DoubleClickMs => "Double click time (ms)",
+			Setting::LongPressMs => "Long press time (ms)",
+			Setting::Key(action) => action.label(),
+			Setting::CameraPolicy => "Camera at the map edge",
+			Setting::CameraMargin => "Camera edge margin (tiles)",
+			Setting::Grid => "Show grid",
+		}
+	}
+}
+
+/// What the game should do after a key press on the settings screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SettingsInput {
+	Adjust(Setting, i32),
+	Bind(KeyAction, VirtualKeyCode),
+	Close,
+}
+
+/// Keyboard driven settings overlay, the values themselves live in the game's configs.
+pub struct SettingsScreen {
+	pub tab: SettingsTab,
+	pub row: usize,
+	/// Waiting for the key to bind to this action.
+	pub binding: Option<KeyAction>,
+}
+
+impl SettingsScreen {
+	const WIDTH: f32 = 460.0;
+	const LINE_HEIGHT: f32 = 22.0;
+
+	pub fn new() -> SettingsScreen {
+		SettingsScreen {
+			tab: SettingsTab::Video,
+			row: 0,
+			binding: None,
+		}
+	}
+
+	pub fn selected(&self) -> Option<Setting> {
+		self.tab.settings().get(self.row).copied()
+	}
+
+	pub fn key(&mut self, key: VirtualKeyCode, shift: bool) -> Option<SettingsInput> {
+		if let Some(action) = self.binding.take() {
+			return match key {
+				VirtualKeyCode::Escape => None,
+				key if keybindings::is_bindable(key) => Some(SettingsInput::Bind(action, key)),
+				_ => {
+					// Keep waiting rather than silently dropping the bind
+					self.binding = Some(action);
+					None
+				}
+			};
+		}
+		let rows = self.tab.settings().len();
+		match key {
+			VirtualKeyCode::Escape => return Some(SettingsInput::Close),
+			VirtualKeyCode::Tab => {
+				let tabs = SettingsTab::ALL.len();
+				let index = SettingsTab::ALL
+					.iter()
+					.position(|&tab| tab == self.tab)
+					.unwrap_or(0);
+				let index = if shift {
+					(index + tabs - 1) % tabs
+				} else {
+					(index + 1) % tabs
+				};
+				self.tab = SettingsTab::ALL[index];
+				self.row = 0;
+			}
+			VirtualKeyCode::Up => self.row = (self.row + rows - 1) % rows,
+			VirtualKeyCode::Down => self.row = (self.row + 1) % rows,
+			VirtualKeyCode::Left => return self.selected().map(|s| SettingsInput::Adjust(s, -1)),
+			VirtualKeyCode::Right => return self.selected().map(|s| SettingsInput::Adjust(s, 1)),
+			VirtualKeyCode::Return => match self.selected() {
+				Some(Setting::Key(action)) => self.binding = Some(action),
+				Some(setting) => return Some(SettingsInput::Adjust(setting, 1)),
+				None => (),
+			},
+			_ => (),
+		}
+		None
+	}
+
+	/// Draws the tabs and the rows of the current tab centered on the screen, `value` gives the
+	/// text for each setting's current value.
+	#[allow(clippy::too_many_arguments)]
+	pub fn draw<Unique: Copy>(
+		&self,
+		ctx: &mut Context,
+		text_styles: &mut TextStyles,
+		screen_size: (f32, f32),
+		ui_scale: f32,
+		skin: Option<(&MultiAtlas<graphics::Image, Unique>, AtlasId<Unique>)>,
+		value: impl Fn(Setting) -> String,
+	) -> GameResult {
+		let settings = self.tab.settings();
+		let line_height = Self::LINE_HEIGHT * ui_scale;
+		let margin = 10.0 * ui_scale;
+		let width = Self::WIDTH * ui_scale;
+		// Tabs, a gap, the rows, a gap and the hint
+		let height = line_height * (settings.len() + 4) as f32 + margin * 2.0;
+		let bounds = Rect::new(
+			((screen_size.0 - width) * 0.5).max(0.0),
+			((screen_size.1 - height) * 0.5).max(0.0),
+			width,
+			height,
+		);
+		match skin {
+			Some((atlas, id)) => {
+				panel::draw_panel(ctx, atlas, id, bounds, ui_scale, graphics::WHITE)?;
+			}
+			None => {
+				let mesh = graphics::MeshBuilder::new()
+					.rectangle(DrawMode::fill(), bounds, Color::new(0.1, 0.1, 0.1, 0.95))
+					.rectangle(DrawMode::stroke(1.0), bounds, graphics::WHITE)
+					.build(ctx)?;
+				mesh.draw(ctx, DrawParam::new())?;
+			}
+		}
+
+		let dim = Color::new(0.6, 0.6, 0.6, 1.0);
+		let mut x = bounds.x + margin;
+		let mut y = bounds.y + margin;
+		for &tab in SettingsTab::ALL.iter() {
+			let (role, color) = if tab == self.tab {
+				(TextRole::Heading, graphics::WHITE)
+			} else {
+				(TextRole::Body, dim)
+			};
+			let text = text_styles.layout(role, tab.label(), ui_scale);
+			let tab_width = text.width(ctx) as f32;
+			graphics::draw(
+				ctx,
+				text,
+				DrawParam::new().dest(na::Point2::new(x, y)).color(color),
+			)?;
+			x += tab_width + margin * 2.0;
+		}
+
+		y += line_height * 1.5;
+		for (row, &setting) in settings.iter().enumerate() {
+			let selected = row == self.row;
+			let color = if selected { graphics::WHITE } else { dim };
+			let label = format!("{} {}", if selected { ">" } else { " " }, setting.label());
+			let text = text_styles.layout(TextRole::Body, &label, ui_scale);
+			graphics::draw(
+				ctx,
+				text,
+				DrawParam::new()
+					.dest(na::Point2::new(bounds.x + margin, y))
+					.color(color),
+			)?;
+			let value = match setting {
+				Setting::Key(action) if self.binding == Some(action) => "press a key...".to_owned(),
+				_ => value(setting),
+			};
+			let text = text_styles.layout(TextRole::Body, &value, ui_scale);
+			let value_width = text.width(ctx) as f32;
+			graphics::draw(
+				ctx,
+				text,
+				DrawParam::new()
+					.dest(na::Point2::new(bounds.right() - margin - value_width, y))
+					.color(color),
+			)?;
+			y += line_height;
+		}
+
+		y += line_height * 0.5;
+		let hint = "Tab: next tab, arrows: select and change, Enter: bind key, Esc: save and close";
+		let text = text_styles.layout(TextRole::Tooltip, hint, ui_scale * 0.7);
+		graphics::draw(
+			ctx,
+			text,
+			DrawParam::new()
+				.dest(na::Point2::new(bounds.x + margin, y))
+				.color(dim),
+		)?;
+		Ok(())
+	}
+}
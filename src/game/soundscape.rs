@@ -0,0 +1,161 @@
+use ggez::audio::{self, SoundSource};
+use ggez::{Context, GameResult};
+use log::*;
+use serde::Deserialize;
+
+use over_simple_game_1::core::map::coord::HexOrientation;
+use over_simple_game_1::core::map::tile_map::TileMap;
+
+/// A looping sound that plays while the tiles near the camera are of the listed types.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AmbientZone {
+	/// Path in the resources, anything ggez can decode.
+	pub sound: String,
+	/// Tile type names the zone covers.
+	pub tiles: Vec<String>,
+	/// Loudness when every tile near the camera is one of `tiles`.
+	#[serde(default = "serde_zone_volume")]
+	pub volume: f32,
+}
+
+fn serde_zone_volume() -> f32 {
+	1.0
+}
+
+struct ZoneSound {
+	zone: AmbientZone,
+	/// `None` when the sound failed to load, the zone is silent then.
+	source: Option<audio::Source>,
+	/// Faded towards `target` so the zones crossfade as the camera moves.
+	level: f32,
+	target: f32,
+}
+
+/// Ambient sounds following the tile types around the camera center, waves by the coast and
+/// birds in the forest, each as loud as the share of nearby tiles it covers.
+pub struct Soundscape {
+	zones: Vec<ZoneSound>,
+	/// The zones covering each tile type, indexed by `TileIdx`.
+	tile_zones: Vec<Vec<usize>>,
+}
+
+impl Soundscape {
+	/// Seconds a zone takes to fade all the way in or out.
+	const CROSSFADE_SECONDS: f32 = 2.0;
+
+	pub fn new() -> Soundscape {
+		Soundscape {
+			zones: vec![],
+			tile_zones: vec![],
+		}
+	}
+
+	/// Loads the zones' sounds, `tile_names` in `TileIdx` order. Sounds that fail to load are
+	/// left silent with a warning so the game still plays without them.
+	pub fn load<'a>(
+		&mut self,
+		ctx: &mut Context,
+		zones: Vec<AmbientZone>,
+		tile_names: impl Iterator<Item = &'a String>,
+	) {
+		self.stop();
+		self.tile_zones = tile_names
+			.map(|name| {
+				zones
+					.iter()
+					.enumerate()
+					.filter(|(_idx, zone)| zone.tiles.contains(name))
+					.map(|(idx, _zone)| idx)
+					.collect()
+			})
+			.collect();
+		self.zones = zones
+			.into_iter()
+			.map(|zone| {
+				let source = match audio::Source::new(ctx, &zone.sound) {
+					Ok(mut source) => {
+						source.set_repeat(true);
+						Some(source)
+					}
+					Err(error) => {
+						warn!("Failed loading ambient sound `{}`: {}", zone.sound, error);
+						None
+					}
+				};
+				ZoneSound {
+					zone,
+					source,
+					level: 0.0,
+					target: 0.0,
+				}
+			})
+			.collect();
+	}
+
+	/// Listens to the tiles within `radius` of `center`, nearer ones counting for more, and fades
+	/// each zone towards its share of them. `volume` scales every zone.
+	pub fn update(
+		&mut self,
+		hex: HexOrientation,
+		tile_map: &TileMap,
+		center: (f32, f32),
+		radius: f32,
+		delta: f32,
+		volume: f32,
+	) -> GameResult {
+		for zone in &mut self.zones {
+			zone.target = 0.0;
+		}
+		let min = (center.0 - radius, center.1 - radius);
+		let max = (center.0 + radius, center.1 + radius);
+		let mut total = 0.0;
+		for (_coord, tile, (x, y)) in tile_map.iter_linear_rect(hex, min, max) {
+			let distance = ((x - center.0).powi(2) + (y - center.1).powi(2)).sqrt();
+			let weight = 1.0 - distance / radius;
+			if weight <= 0.0 {
+				continue;
+			}
+			total += weight;
+			let idx: usize = tile.id.into();
+			for &zone in self.tile_zones.get(idx).into_iter().flatten() {
+				self.zones[zone].target += weight;
+			}
+		}
+
+		let step = delta / Self::CROSSFADE_SECONDS;
+		for zone in &mut self.zones {
+			let target = if total > 0.0 {
+				zone.target / total
+			} else {
+				0.0
+			};
+			zone.level = if zone.level < target {
+				(zone.level + step).min(target)
+			} else {
+				(zone.level - step).max(target)
+			};
+			let source = match &mut zone.source {
+				Some(source) => source,
+				None => continue,
+			};
+			if zone.level > 0.0 {
+				source.set_volume(zone.level * zone.zone.volume * volume);
+				if !source.playing() {
+					source.play()?;
+				}
+			} else if source.playing() {
+				source.stop();
+			}
+		}
+		Ok(())
+	}
+
+	pub fn stop(&mut self) {
+		for zone in &mut self.zones {
+			zone.level = 0.0;
+			if let Some(source) = &mut zone.source {
+				source.stop();
+			}
+		}
+	}
+}
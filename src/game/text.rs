@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use ggez::graphics::{self, Color, Font, Scale, TextFragment};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TextRole {
+	Heading,
+	Body,
+	Tooltip,
+	Console,
+}
+
+impl TextRole {
+	pub const ALL: [TextRole; 4] = [
+		TextRole::Heading,
+		TextRole::Body,
+		TextRole::Tooltip,
+		TextRole::Console,
+	];
+
+	fn default_style(self) -> TextStyle {
+		match self {
+			TextRole::Heading => TextStyle {
+				font: None,
+				size: 16.0,
+				color: Some(Color::new(1.0, 0.85, 0.5, 1.0)),
+			},
+			TextRole::Body => TextStyle {
+				font: None,
+				size: 16.0,
+				color: None,
+			},
+			TextRole::Tooltip => TextStyle {
+				font: None,
+				size: 18.0,
+				color: None,
+			},
+			TextRole::Console => TextStyle {
+				font: None,
+				size: 16.0,
+				color: None,
+			},
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TextStyle {
+	/// Name of a font from `TextConfig::fonts`, the built in font if `None`.
+	#[serde(default)]
+	pub font: Option<String>,
+	/// Size in logical pixels before UI scaling.
+	pub size: f32,
+	/// Overrides the color the text is drawn with when set.
+	#[serde(default)]
+	pub color: Option<Color>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TextConfig {
+	/// Font names to `.ttf` paths in the resources.
+	#[serde(default)]
+	pub fonts: HashMap<String, String>,
+	/// Roles left out keep their default style.
+	#[serde(default)]
+	pub styles: HashMap<TextRole, TextStyle>,
+}
+
+/// The style for each text role and a cache of laid out text.
+///
+/// Laying out text is the expensive part of drawing it, so the same string in the same role and
+/// size reuses its `Text` across frames, entries that go a whole frame unused are dropped.
+pub struct TextStyles {
+	fonts: HashMap<String, Font>,
+	styles: HashMap<TextRole, TextStyle>,
+	/// By role and size in whole pixels, then by content so it can be looked up by `&str`.
+	cache: HashMap<(TextRole, u32), HashMap<String, (graphics::Text, bool)>>,
+}
+
+impl TextStyles {
+	pub fn new() -> TextStyles {
+		TextStyles {
+			fonts: HashMap::new(),
+			styles: TextRole::ALL
+				.iter()
+				.map(|&role| (role, role.default_style()))
+				.collect(),
+			cache: HashMap::new(),
+		}
+	}
+
+	/// Replaces the fonts and styles, every style must name a font in `fonts` or none at all.
+	pub fn set(
+		&mut self,
+		fonts: HashMap<String, Font>,
+		styles: HashMap<TextRole, TextStyle>,
+	) -> anyhow::Result<()> {
+		for (role, style) in &styles {
+			if let Some(font) = &style.font {
+				anyhow::ensure!(
+					fonts.contains_key(font),
+					"text style {:?} uses unknown font `{}`",
+					role,
+					font
+				);
+			}
+		}
+		self.fonts = fonts;
+		for (role, style) in styles {
+			self.styles.insert(role, style);
+		}
+		self.cache.clear();
+		Ok(())
+	}
+
+	pub fn style(&self, role: TextRole) -> &TextStyle {
+		&self.styles[&role]
+	}
+
+	pub fn font(&self, role: TextRole) -> Font {
+		self.style(role)
+			.font
+			.as_ref()
+			.and_then(|name| self.fonts.get(name))
+			.copied()
+			.unwrap_or_default()
+	}
+
+	/// Lays out `content` in the style of `role` at its size times `scale`.
+	pub fn layout(&mut self, role: TextRole, content: &str, scale: f32) -> &graphics::Text {
+		let size = self.style(role).size * scale;
+		self.layout_sized(role, content, size)
+	}
+
+	/// Lays out `content` in the style of `role` but at `size` pixels, for text sized by
+	/// something else such as the map zoom. The size is rounded to whole pixels, so text that is
+	/// zoomed smoothly is only laid out again each time it grows or shrinks by a pixel.
+	pub fn layout_sized(&mut self, role: TextRole, content: &str, size: f32) -> &graphics::Text {
+		let pixels = size.round().max(1.0);
+		let font = self.font(role);
+		let color = self.style(role).color;
+		let texts = self
+			.cache
+			.entry((role, pixels as u32))
+			.or_insert_with(HashMap::new);
+		// Only text seen for the first time allocates its key
+		if !texts.contains_key(content) {
+			let mut fragment = TextFragment::new(content)
+				.font(font)
+				.scale(Scale::uniform(pixels));
+			if let Some(color) = color {
+				fragment = fragment.color(color);
+			}
+			texts.insert(content.to_owned(), (graphics::Text::new(fragment), false));
+		}
+		let entry = texts
+			.get_mut(content)
+			.expect("unable to lookup just inserted text?");
+		entry.1 = true;
+		&entry.0
+	}
+
+	/// Roughly the bytes the laid out text takes, with each content once for its key and once
+	/// for the text itself.
+	pub fn cache_memory(&self) -> usize {
+		self.cache
+			.values()
+			.flat_map(|texts| texts.keys())
+			.map(|content| {
+				std::mem::size_of::<(String, (graphics::Text, bool))>() + content.len() * 2
+			})
+			.sum()
+	}
+
+	/// Drops the text that wasn't laid out since the last call, call once per frame.
+	pub fn end_frame(&mut self) {
+		self.cache.retain(|_key, texts| {
+			texts.retain(|_content, (_text, used)| std::mem::replace(used, false));
+			!texts.is_empty()
+		});
+	}
+}
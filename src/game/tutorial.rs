@@ -0,0 +1,186 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Drawable, Rect};
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+use serde::Deserialize;
+
+use over_simple_game_1::core::engine::address::MapAddress;
+
+use crate::game::atlas::{AtlasId, MultiAtlas};
+use crate::game::keybindings::KeyAction;
+use crate::game::panel;
+use crate::game::text::{TextRole, TextStyles};
+
+const WIDTH: f32 = 480.0;
+const LINE_HEIGHT: f32 = 20.0;
+
+/// Something the player did or that happened in the game, which a tutorial step can wait for.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum TutorialEvent {
+	/// Space, for steps that only explain something.
+	Continue,
+	/// The key bound to the action was pressed.
+	Key(KeyAction),
+	/// A chat command was run, named without its slash.
+	Command(String),
+	/// The tile was clicked on to select it.
+	Select(MapAddress),
+	/// The box selected units were ordered to move.
+	MoveOrder,
+	Pan,
+	Zoom,
+	/// The named map became the visible one.
+	ShowMap(String),
+	GoodyFound,
+}
+
+/// What a step points the player at.
+#[derive(Clone, Debug, Deserialize)]
+pub enum TutorialHighlight {
+	Tile(MapAddress),
+	Minimap,
+	Chat,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TutorialStep {
+	/// Shown in the tutorial's panel, each line of it on its own line.
+	pub text: String,
+	#[serde(default)]
+	pub highlight: Option<TutorialHighlight>,
+	pub until: TutorialEvent,
+	/// Whether input other than what the step waits for is ignored.
+	#[serde(default)]
+	pub block_input: bool,
+}
+
+/// Steps that each wait for the player to do something, read from `/tutorials/<name>.ron` or
+/// next to a scenario's map as `<map>.tutorial.ron`.
+///
+/// ```ron
+/// (
+/// 	title: "Getting around",
+/// 	steps: [
+/// 		(text: "Drag with the mouse to look around.", until: Pan),
+/// 		(
+/// 			text: "Click the hut to select it.",
+/// 			highlight: Some(Tile("island:4,3")),
+/// 			until: Select("island:4,3"),
+/// 			block_input: true,
+/// 		),
+/// 	],
+/// )
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tutorial {
+	pub title: String,
+	pub steps: Vec<TutorialStep>,
+}
+
+/// Follows the player through a tutorial, the game reports its events and asks before letting
+/// input through.
+pub struct TutorialRunner {
+	tutorial: Tutorial,
+	step: usize,
+}
+
+impl TutorialRunner {
+	pub fn new(tutorial: Tutorial) -> TutorialRunner {
+		TutorialRunner { tutorial, step: 0 }
+	}
+
+	pub fn title(&self) -> &str {
+		&self.tutorial.title
+	}
+
+	pub fn current(&self) -> Option<&TutorialStep> {
+		self.tutorial.steps.get(self.step)
+	}
+
+	/// The current step's number from 1 and how many there are.
+	pub fn progress(&self) -> (usize, usize) {
+		(self.step + 1, self.tutorial.steps.len())
+	}
+
+	/// Whether input causing `event` is let through, `None` for input no step can wait for.
+	pub fn allows(&self, event: Option<&TutorialEvent>) -> bool {
+		match self.current() {
+			Some(step) if step.block_input => event == Some(&step.until),
+			_ => true,
+		}
+	}
+
+	/// Moves on to the next step if `event` is what the current one waits for, returning whether
+	/// it did.
+	pub fn event(&mut self, event: &TutorialEvent) -> bool {
+		match self.current() {
+			Some(step) if step.until == *event => {
+				self.step += 1;
+				true
+			}
+			_ => false,
+		}
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.step >= self.tutorial.steps.len()
+	}
+}
+
+/// The current step's panel along the top of the screen.
+pub fn draw_tutorial<Unique: Copy>(
+	ctx: &mut Context,
+	text_styles: &mut TextStyles,
+	runner: &TutorialRunner,
+	screen_size: (f32, f32),
+	ui_scale: f32,
+	skin: Option<(&MultiAtlas<graphics::Image, Unique>, AtlasId<Unique>)>,
+) -> GameResult {
+	let step = match runner.current() {
+		Some(step) => step,
+		None => return Ok(()),
+	};
+	let (number, steps) = runner.progress();
+	let heading = format!("{} ({}/{})", runner.title(), number, steps);
+	let mut lines: Vec<(TextRole, &str)> = vec![(TextRole::Heading, &heading)];
+	lines.extend(step.text.lines().map(|line| (TextRole::Body, line)));
+	let hint = match step.until {
+		TutorialEvent::Continue => "Space: continue, Esc: end the tutorial",
+		_ => "Esc: end the tutorial",
+	};
+
+	let line_height = LINE_HEIGHT * ui_scale;
+	let margin = 10.0 * ui_scale;
+	let width = (WIDTH * ui_scale).min(screen_size.0);
+	// The lines and the hint
+	let height = line_height * (lines.len() as f32 + 1.0) + margin * 2.0;
+	let bounds = Rect::new((screen_size.0 - width) * 0.5, margin, width, height);
+	match skin {
+		Some((atlas, id)) => {
+			panel::draw_panel(ctx, atlas, id, bounds, ui_scale, graphics::WHITE)?;
+		}
+		None => {
+			let mesh = graphics::MeshBuilder::new()
+				.rectangle(DrawMode::fill(), bounds, Color::new(0.1, 0.1, 0.1, 0.9))
+				.rectangle(DrawMode::stroke(1.0), bounds, graphics::WHITE)
+				.build(ctx)?;
+			mesh.draw(ctx, DrawParam::new())?;
+		}
+	}
+
+	let x = bounds.x + margin;
+	let mut y = bounds.y + margin;
+	for (role, line) in &lines {
+		let text = text_styles.layout(*role, line, ui_scale);
+		graphics::draw(ctx, text, DrawParam::new().dest(na::Point2::new(x, y)))?;
+		y += line_height;
+	}
+
+	let dim = Color::new(0.6, 0.6, 0.6, 1.0);
+	let text = text_styles.layout(TextRole::Tooltip, hint, ui_scale * 0.7);
+	graphics::draw(
+		ctx,
+		text,
+		DrawParam::new().dest(na::Point2::new(x, y)).color(dim),
+	)?;
+	Ok(())
+}
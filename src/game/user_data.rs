@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use anyhow::Context as AnyContext;
+use serde::Deserialize;
+
+use crate::game::branding;
+
+/// Overrides where the user data goes, after `--data-dir` but before `config/paths.ron`.
+pub const DATA_DIR_ENV: &str = "OVER_SIMPLE_GAME_1_DATA_DIR";
+
+/// Read straight from the resources folder as it is needed before the ggez filesystem is up, so
+/// it can't be overridden from the user data directory it picks.
+const PATHS_CONFIG: &str = "./resources/config/paths.ron";
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(crate) struct PathsConfig {
+	#[serde(default)]
+	user_data_dir: Option<String>,
+}
+
+/// Where saves, settings, logs, exports, statistics and crash reports are written, created if
+/// missing. The first of `arg` from `--data-dir`, the `OVER_SIMPLE_GAME_1_DATA_DIR` environment
+/// variable, `user_data_dir` in `config/paths.ron` and the platform's local data directory.
+pub fn resolve_user_data_dir(arg: Option<String>) -> anyhow::Result<PathBuf> {
+	let configured = match std::fs::read_to_string(PATHS_CONFIG) {
+		Ok(text) => {
+			ron::de::from_str::<PathsConfig>(&text)
+				.with_context(|| format!("failed parsing `{}`", PATHS_CONFIG))?
+				.user_data_dir
+		}
+		Err(_e) => None,
+	};
+	let path = match arg
+		.or_else(|| std::env::var(DATA_DIR_ENV).ok())
+		.or(configured)
+	{
+		Some(path) => PathBuf::from(path),
+		// The same place ggez would put it
+		None => directories::ProjectDirs::from("", branding::AUTHOR, branding::GAME_ID)
+			.map(|dirs| dirs.data_local_dir().to_owned())
+			.context("unable to find the platform's data directory, pass `--data-dir <path>`")?,
+	};
+	std::fs::create_dir_all(&path).with_context(|| {
+		format!(
+			"failed creating the user data directory `{}`",
+			path.display()
+		)
+	})?;
+	Ok(path)
+}
@@ -0,0 +1,286 @@
+use std::io::BufRead;
+use std::path::Path;
+
+use anyhow::Context as AnyContext;
+use log::*;
+
+use over_simple_game_1::core::engine::io::EngineIO;
+use over_simple_game_1::core::engine::journal::JournalEvent;
+use over_simple_game_1::core::engine::{Engine, EngineBuilder, MapCoord};
+use over_simple_game_1::core::fixed::Fixed;
+use over_simple_game_1::core::map::coord::{Coord, HexOrientation};
+use over_simple_game_1::core::map::generator::{
+	DecorationScatter, MapGenerator, SimpleAlternationMapGenerator,
+};
+use over_simple_game_1::core::map::text_map::TextMapGenerator;
+use over_simple_game_1::core::map::tiled::{TiledMapGenerator, TiledObject};
+use over_simple_game_1::prelude::TileIdx;
+
+use crate::game::args::{Args, GeneratorChoice};
+
+/// The map a new game starts on, unless it starts on a scenario.
+pub const START_MAP: &str = "world0";
+
+/// Seeds the decorations when there is no `--seed`.
+const DECORATION_SEED: u64 = 0x5EED;
+
+pub fn engine_builder<IO: EngineIO>(args: &Args) -> EngineBuilder<IO> {
+	let builder = Engine::builder();
+	let builder = if args.flat_top {
+		builder.hex_orientation(HexOrientation::FlatTop)
+	} else {
+		builder
+	};
+	match args.seed {
+		Some(seed) => builder.seed(seed),
+		None => builder,
+	}
+}
+
+/// Generates the maps a new game starts with, the starting map as `args` asks and a small cave.
+pub fn generate_start_maps<IO: 'static + EngineIO>(
+	engine: &mut Engine<IO>,
+	io: &mut IO,
+	args: &Args,
+) -> anyhow::Result<()> {
+	let size = args.map_size;
+	let mut generator = SimpleAlternationMapGenerator::new(engine, &["dirt", "grass", "sand"])?;
+	// let mut generator = civ::maps::NoiseMap::new(&engine.tile_types);
+	match args.generator {
+		GeneratorChoice::Alternating => {
+			let mut generator = decorated(engine, generator, args.seed.unwrap_or(DECORATION_SEED))?;
+			engine.generate_map(io, START_MAP, size.width, size.height, true, &mut generator)?;
+		}
+		GeneratorChoice::Bare => {
+			engine.generate_map(io, START_MAP, size.width, size.height, true, &mut generator)?;
+		}
+	}
+	engine.set_map_ambient(START_MAP, "planet")?;
+	let mut generator = SimpleAlternationMapGenerator::new(engine, &["dirt"])?;
+	engine.generate_map(io, "cave0", 4, 4, false, &mut generator)?;
+	engine.set_map_ambient("cave0", "dungeon")?;
+	Ok(())
+}
+
+/// Scatters the starting map's decorations over what `generator` makes.
+fn decorated<IO: 'static + EngineIO, G: MapGenerator>(
+	engine: &Engine<IO>,
+	generator: G,
+	seed: u64,
+) -> anyhow::Result<DecorationScatter<G>> {
+	Ok(DecorationScatter::new(generator, seed)
+		.with_rule(engine, "tree", &["grass"], Fixed::from_percent(40))?
+		.with_rule(engine, "rock", &["dirt"], Fixed::from_percent(25))?
+		.with_rule(engine, "flowers", &["sand"], Fixed::from_percent(15))?)
+}
+
+/// Generates the map again with the generator and seed in its metadata, which gives the same
+/// tiles unless the tile types or the file it was loaded from changed since.
+pub fn regenerate_map<IO: 'static + EngineIO>(
+	engine: &mut Engine<IO>,
+	io: &mut IO,
+	name: &str,
+) -> anyhow::Result<()> {
+	let tile_map = engine
+		.maps
+		.get(name)
+		.with_context(|| format!("no map named `{}`", name))?;
+	let info = tile_map.metadata.generator.clone();
+	let seed = tile_map.seed;
+	let param = |param: &str| {
+		info.params
+			.get(param)
+			.with_context(|| format!("map `{}` has no `{}` in its metadata", name, param))
+	};
+	match info.name.as_str() {
+		"alternating" => {
+			let tiles: Vec<&str> = param("tiles")?.split(',').collect();
+			let mut generator = SimpleAlternationMapGenerator::new(engine, &tiles)?;
+			if info.params.contains_key("decoration_rules") {
+				let mut generator = decorated(engine, generator, seed)?;
+				engine.regenerate_map(io, name, &mut generator)?;
+			} else {
+				engine.regenerate_map(io, name, &mut generator)?;
+			}
+		}
+		"text" => {
+			let path = Path::new(param("path")?);
+			let mut generator = TextMapGenerator::load(io, path, &engine.tile_types)?;
+			engine.regenerate_map(io, name, &mut generator)?;
+		}
+		"tiled" => {
+			let path = Path::new(param("path")?);
+			let fill = first_tile_type(engine)?;
+			let mut generator = TiledMapGenerator::load(io, path, &engine.tile_types, fill)?;
+			engine.regenerate_map(io, name, &mut generator)?;
+		}
+		other => anyhow::bail!(
+			"map `{}` was made by the `{}` generator which can't be run again",
+			name,
+			other
+		),
+	}
+	Ok(())
+}
+
+fn first_tile_type<IO: EngineIO>(engine: &Engine<IO>) -> anyhow::Result<TileIdx> {
+	let tile_types = &engine.tile_types.tile_types;
+	tile_types
+		.keys()
+		.next()
+		.and_then(|first| tile_types.get_index_of(first))
+		.context("no tile types are loaded")
+}
+
+fn map_name(path: &Path) -> anyhow::Result<String> {
+	Ok(path
+		.file_stem()
+		.and_then(|stem| stem.to_str())
+		.context("map path has no file name")?
+		.to_owned())
+}
+
+/// Adds the Tiled JSON map at `path` as a map named after the file, returning the name and the
+/// objects to spawn on it.
+///
+/// The corners a Tiled map doesn't cover are filled with the first tile type.
+pub fn generate_tiled_map<IO: 'static + EngineIO>(
+	engine: &mut Engine<IO>,
+	io: &mut IO,
+	path: &Path,
+) -> anyhow::Result<(String, Vec<TiledObject>)> {
+	let name = map_name(path)?;
+	let fill = first_tile_type(engine)?;
+	let mut generator = TiledMapGenerator::load(io, path, &engine.tile_types, fill)?;
+	engine.generate_map(
+		io,
+		&name,
+		generator.max_x,
+		generator.max_y,
+		false,
+		&mut generator,
+	)?;
+	Ok((name, generator.objects))
+}
+
+/// Adds the text map at `path` as a map named after the file, returning the name.
+pub fn generate_text_map<IO: 'static + EngineIO>(
+	engine: &mut Engine<IO>,
+	io: &mut IO,
+	path: &Path,
+) -> anyhow::Result<String> {
+	let name = map_name(path)?;
+	let mut generator = TextMapGenerator::load(io, path, &engine.tile_types)?;
+	engine.generate_map(
+		io,
+		&name,
+		generator.max_x,
+		generator.max_y,
+		generator.wraps_x,
+		&mut generator,
+	)?;
+	Ok(name)
+}
+
+/// Adds the map a scenario starts on, a Tiled map if `path` ends in `.json` and a text map
+/// otherwise. Returns the name and any objects to spawn on it.
+pub fn generate_scenario_map<IO: 'static + EngineIO>(
+	engine: &mut Engine<IO>,
+	io: &mut IO,
+	path: &Path,
+) -> anyhow::Result<(String, Vec<TiledObject>)> {
+	if path
+		.extension()
+		.map_or(false, |extension| extension == "json")
+	{
+		generate_tiled_map(engine, io, path)
+	} else {
+		Ok((generate_text_map(engine, io, path)?, vec![]))
+	}
+}
+
+/// Paints the tile changes recorded in the journal at `path` over the maps, which should be the
+/// ones the journal was recorded on. Nothing else in a journal can be replayed yet so it's skipped.
+pub fn replay_journal<IO: 'static + EngineIO>(
+	engine: &mut Engine<IO>,
+	io: &mut IO,
+	path: &Path,
+) -> anyhow::Result<()> {
+	let reader = std::io::BufReader::new(io.read(path)?);
+	let (mut painted, mut skipped) = (0, 0);
+	for (index, line) in reader.lines().enumerate() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+		let event: JournalEvent = serde_json::from_str(&line)
+			.with_context(|| format!("failed parsing line {} of the journal", index + 1))?;
+		match event {
+			JournalEvent::TileChanged { map, q, r, tile } => {
+				let map = engine
+					.maps
+					.get_index_of(&map)
+					.with_context(|| format!("the journal paints missing map `{}`", map))?;
+				let id = engine
+					.tile_types
+					.tile_types
+					.get_index_of(&tile)
+					.with_context(|| format!("the journal paints missing tile type `{}`", tile))?;
+				let coord = Coord::new_axial(q, r);
+				engine.paint_tile(MapCoord { map, coord }, id)?;
+				painted += 1;
+			}
+			JournalEvent::MapGenerated { map, .. }
+				if engine.maps.contains_key(&map) || engine.wide_maps.contains_key(&map) => {}
+			JournalEvent::MapGenerated { map, .. } => {
+				warn!(
+					"The replayed journal generated map `{}` which is missing",
+					map
+				);
+				skipped += 1;
+			}
+			_ => skipped += 1,
+		}
+	}
+	info!(
+		"Replayed {} tile changes from `{}`, skipped {} other events",
+		painted,
+		path.display(),
+		skipped
+	);
+	Ok(())
+}
+
+/// Every map with its tiles as rows of tile type names.
+pub fn maps_text<IO: EngineIO>(engine: &Engine<IO>) -> String {
+	let tile_names: Vec<&str> = engine
+		.tile_types
+		.tile_types
+		.keys()
+		.map(|name| name.as_str())
+		.collect();
+	let mut text = String::new();
+	for (name, map) in engine.maps.iter() {
+		text.push_str(&format!(
+			"\nmap `{}`: {}x{}, wraps_x: {}, seed: {}\n{}\n",
+			name,
+			map.width as u16 + 1,
+			map.height as u16 + 1,
+			map.wraps_x,
+			map.seed,
+			map.metadata,
+		));
+		for row in map.tiles.chunks(map.width as usize + 1) {
+			let row: Vec<&str> = row
+				.iter()
+				.map(|tile| {
+					let idx: usize = tile.id.into();
+					tile_names.get(idx).copied().unwrap_or("?")
+				})
+				.collect();
+			text.push_str(&row.join(" "));
+			text.push('\n');
+		}
+	}
+	text
+}
@@ -0,0 +1,385 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::ai::utility::{UtilityAi, UtilityInputs};
+use crate::core::fixed::Fixed;
+use crate::core::map::coord::Coord;
+use crate::core::map::tile_map::TileMap;
+use crate::games::civ::danger::DangerMap;
+use crate::games::civ::rules::GameRules;
+
+pub const UNIT_AI: &str = "unit_ai";
+pub const AI_PERSONAS: &str = "ai_personas";
+
+/// How far a unit looks around when deciding, inputs measuring distance reach 0 this far out.
+pub const UNIT_SIGHT: u16 = 8;
+
+/// Tiles a unit is counted on moving in a turn, there are no movement points yet.
+pub const UNIT_MOVES: u8 = 2;
+
+/// The danger at which the `danger` input tops out.
+const DANGER_SCALE: i16 = 4;
+
+/// What an AI unit can decide to do, the actions in `civ/unit_ai.ron` are named after these.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnitBehavior {
+	/// Head for the nearest goody hut.
+	Explore,
+	/// Fall back to the safest tile in reach.
+	Defend,
+	/// Close in on the nearest threat.
+	Attack,
+	/// Stay put on open land to found a city.
+	Settle,
+}
+
+impl UnitBehavior {
+	pub const ALL: [UnitBehavior; 4] = [
+		UnitBehavior::Explore,
+		UnitBehavior::Defend,
+		UnitBehavior::Attack,
+		UnitBehavior::Settle,
+	];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			UnitBehavior::Explore => "explore",
+			UnitBehavior::Defend => "defend",
+			UnitBehavior::Attack => "attack",
+			UnitBehavior::Settle => "settle",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<UnitBehavior> {
+		UnitBehavior::ALL
+			.iter()
+			.copied()
+			.find(|behavior| behavior.name() == name)
+	}
+}
+
+fn serde_trait() -> Fixed {
+	Fixed::ONE
+}
+
+/// An AI opponent's personality, from `civ/personas.ron`. Each trait scales the weights of the
+/// behaviors it drives, 1 leaves them as they are.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AiPersona {
+	pub name: String,
+	/// Scales attacking.
+	#[serde(default = "serde_trait")]
+	pub aggressiveness: Fixed,
+	/// Scales settling.
+	#[serde(default = "serde_trait")]
+	pub expansion: Fixed,
+	/// There is nothing to research yet, kept for when there is.
+	#[serde(default = "serde_trait")]
+	pub tech_focus: Fixed,
+	/// Scales exploring, and falling back the other way around, 2 never falls back.
+	#[serde(default = "serde_trait")]
+	pub risk_tolerance: Fixed,
+}
+
+impl AiPersona {
+	pub fn weight_for(&self, behavior: UnitBehavior) -> Fixed {
+		match behavior {
+			UnitBehavior::Explore => self.risk_tolerance,
+			UnitBehavior::Defend => Fixed::from_int(2) - self.risk_tolerance,
+			UnitBehavior::Attack => self.aggressiveness,
+			UnitBehavior::Settle => self.expansion,
+		}
+		.max(Fixed::ZERO)
+	}
+}
+
+/// How much keener the AI is on `behavior` at the difficulty in `rules`, its combat bonus goes to
+/// attacking and its yield bonus to exploring and settling.
+pub fn difficulty_weight(rules: &GameRules, behavior: UnitBehavior) -> Fixed {
+	let percent = match behavior {
+		UnitBehavior::Explore | UnitBehavior::Settle => rules.ai_yield(100),
+		UnitBehavior::Defend => 100,
+		UnitBehavior::Attack => rules.ai_strength(100),
+	};
+	// Far past any sensible bonus but still in range of a `Fixed`
+	Fixed::from_percent(percent.min(10_000) as i32)
+}
+
+/// `unit_ai` with its behaviors weighed the way `persona` would, if any, at the difficulty in
+/// `rules`.
+pub fn weighted_unit_ai(
+	unit_ai: &UtilityAi,
+	persona: Option<&AiPersona>,
+	rules: &GameRules,
+) -> UtilityAi {
+	unit_ai.reweighted(|action| {
+		UnitBehavior::from_name(&action.name).map_or(Fixed::ONE, |behavior| {
+			persona.map_or(Fixed::ONE, |persona| persona.weight_for(behavior))
+				* difficulty_weight(rules, behavior)
+		})
+	})
+}
+
+/// A unit's surroundings as far as deciding what to do goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnitSituation {
+	pub at: Coord,
+	/// The danger the unit is in, 1 at `DANGER_SCALE` and above.
+	pub danger: Fixed,
+	/// The nearest threat and goody hut within `UNIT_SIGHT`.
+	pub nearest_threat: Option<Coord>,
+	pub nearest_goody: Option<Coord>,
+	threat_near: Fixed,
+	goody_near: Fixed,
+	/// The share of the tiles around that are empty and undecorated.
+	open_land: Fixed,
+}
+
+impl UnitSituation {
+	pub fn new(
+		tile_map: &TileMap,
+		at: Coord,
+		danger: Option<&DangerMap>,
+		threats: &[Coord],
+		goodies: &[Coord],
+	) -> UnitSituation {
+		let nearest = |coords: &[Coord]| {
+			coords
+				.iter()
+				.copied()
+				.map(|coord| (tile_map.distance(at, coord), coord))
+				.filter(|&(distance, _coord)| distance <= UNIT_SIGHT)
+				.min()
+		};
+		let closeness = |nearest: Option<(u16, Coord)>| {
+			nearest.map_or(Fixed::ZERO, |(distance, _coord)| {
+				Fixed::ONE - Fixed::from_ratio(i32::from(distance), i32::from(UNIT_SIGHT))
+			})
+		};
+		let nearest_threat = nearest(threats);
+		let nearest_goody = nearest(goodies);
+		let danger = danger.map_or(Fixed::ZERO, |danger| {
+			(danger.danger_at(tile_map, at) / Fixed::from_int(DANGER_SCALE)).min(Fixed::ONE)
+		});
+		let (mut open, mut total) = (0, 0);
+		for (_co, tile) in tile_map.iter_neighbors_around(at, 2) {
+			total += 1;
+			if tile.decoration.is_none() && tile.entities.is_empty() {
+				open += 1;
+			}
+		}
+		let open_land = if total == 0 {
+			Fixed::ZERO
+		} else {
+			Fixed::from_ratio(open, total)
+		};
+		UnitSituation {
+			at,
+			danger,
+			nearest_threat: nearest_threat.map(|(_distance, coord)| coord),
+			nearest_goody: nearest_goody.map(|(_distance, coord)| coord),
+			threat_near: closeness(nearest_threat),
+			goody_near: closeness(nearest_goody),
+			open_land,
+		}
+	}
+
+	/// Where `behavior` would send the unit, `None` to stay where it is.
+	pub fn target(
+		&self,
+		behavior: UnitBehavior,
+		tile_map: &TileMap,
+		danger: Option<&DangerMap>,
+		moves: u8,
+	) -> Option<Coord> {
+		let target = match behavior {
+			UnitBehavior::Explore => self.nearest_goody,
+			UnitBehavior::Defend => danger.map(|danger| danger.retreat(tile_map, self.at, moves)),
+			UnitBehavior::Attack => self.nearest_threat,
+			UnitBehavior::Settle => None,
+		};
+		target.filter(|&target| target != self.at)
+	}
+}
+
+/// `danger`, `threat_near`, `goody_near` and `open_land`, each from 0 to 1.
+impl UtilityInputs for UnitSituation {
+	fn input(&self, name: &str) -> Option<Fixed> {
+		match name {
+			"danger" => Some(self.danger),
+			"threat_near" => Some(self.threat_near),
+			"goody_near" => Some(self.goody_near),
+			"open_land" => Some(self.open_land),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod ai_tests {
+	use super::*;
+	use crate::core::ai::utility::{UtilityAction, UtilityAi};
+	use crate::core::engine::schema::Schemas;
+	use crate::core::map::generator::test_maps::plain_map;
+	use crate::games::civ::danger::{Threat, ThreatRule};
+	use crate::games::civ::rules::{RuleChoices, GAME_RULES};
+
+	fn tile_map() -> TileMap {
+		plain_map(15, 15, false)
+	}
+
+	fn unit_ai() -> UtilityAi {
+		let source = include_str!("../../../resources/civ/unit_ai.ron");
+		let mut schemas = Schemas::default();
+		schemas.declare(UNIT_AI, 1);
+		let actions: Vec<UtilityAction> = schemas.read(UNIT_AI, source.as_bytes()).unwrap();
+		for action in &actions {
+			assert!(
+				UnitBehavior::from_name(&action.name).is_some(),
+				"{}",
+				action.name
+			);
+		}
+		UtilityAi::new(actions)
+	}
+
+	fn decide(tile_map: &TileMap, situation: &UnitSituation) -> UnitBehavior {
+		let ai = unit_ai();
+		let action = ai.choose(situation).unwrap().unwrap();
+		let behavior = UnitBehavior::from_name(&action.name).unwrap();
+		assert_eq!(
+			situation.target(behavior, tile_map, None, 2).is_some(),
+			behavior != UnitBehavior::Settle
+		);
+		behavior
+	}
+
+	#[test]
+	fn reads_its_surroundings() {
+		let tile_map = tile_map();
+		let at = Coord::new_axial(4, 4);
+		let goody = Coord::new_axial(8, 4);
+		let far = Coord::new_axial(15, 15);
+		let situation = UnitSituation::new(&tile_map, at, None, &[far], &[goody]);
+		assert_eq!(situation.nearest_goody, Some(goody));
+		assert_eq!(situation.nearest_threat, None);
+		assert_eq!(situation.input("goody_near"), Some(Fixed::from_percent(50)));
+		assert_eq!(situation.input("threat_near"), Some(Fixed::ZERO));
+		assert_eq!(situation.input("open_land"), Some(Fixed::ONE));
+		assert_eq!(situation.input("danger"), Some(Fixed::ZERO));
+		assert_eq!(situation.input("morale"), None);
+	}
+
+	#[test]
+	fn explores_when_safe_and_falls_back_when_not() {
+		let tile_map = tile_map();
+		let at = Coord::new_axial(4, 4);
+		let goody = Coord::new_axial(7, 4);
+		let situation = UnitSituation::new(&tile_map, at, None, &[], &[goody]);
+		assert_eq!(decide(&tile_map, &situation), UnitBehavior::Explore);
+
+		let wolf = Coord::new_axial(5, 4);
+		let danger = DangerMap::new(
+			&tile_map,
+			&[Threat {
+				at: wolf,
+				rule: ThreatRule {
+					strength: Fixed::from_int(4),
+					moves: 1,
+					range: 1,
+				},
+			}],
+		);
+		let situation = UnitSituation::new(&tile_map, at, Some(&danger), &[wolf], &[goody]);
+		let ai = unit_ai();
+		let behavior =
+			UnitBehavior::from_name(&ai.choose(&situation).unwrap().unwrap().name).unwrap();
+		assert_eq!(behavior, UnitBehavior::Defend);
+		let to = situation
+			.target(behavior, &tile_map, Some(&danger), 3)
+			.unwrap();
+		assert!(danger.danger_at(&tile_map, to) < danger.danger_at(&tile_map, at));
+	}
+
+	#[test]
+	fn personas_change_the_choice() {
+		let source = include_str!("../../../resources/civ/personas.ron");
+		let mut schemas = Schemas::default();
+		schemas.declare(AI_PERSONAS, 1);
+		let personas: Vec<AiPersona> = schemas.read(AI_PERSONAS, source.as_bytes()).unwrap();
+		let persona = |name: &str| personas.iter().find(|p| p.name == name).unwrap();
+		let balanced = persona("balanced");
+		assert_eq!(balanced.weight_for(UnitBehavior::Defend), Fixed::ONE);
+		assert_eq!(balanced.tech_focus, Fixed::ONE);
+
+		// A wolf close enough to be in danger, but also to go after
+		let tile_map = tile_map();
+		let at = Coord::new_axial(4, 4);
+		let wolf = Coord::new_axial(5, 4);
+		let danger = DangerMap::new(
+			&tile_map,
+			&[Threat {
+				at: wolf,
+				rule: ThreatRule {
+					strength: Fixed::from_int(4),
+					moves: 1,
+					range: 1,
+				},
+			}],
+		);
+		let situation = UnitSituation::new(&tile_map, at, Some(&danger), &[wolf], &[]);
+		let base = unit_ai();
+		let rules = GameRules::default();
+		let choice = |ai: UtilityAi| ai.choose(&situation).unwrap().unwrap().name.clone();
+		assert_eq!(
+			choice(weighted_unit_ai(&base, Some(balanced), &rules)),
+			"defend"
+		);
+		assert_eq!(
+			choice(weighted_unit_ai(&base, Some(persona("warlord")), &rules)),
+			"attack"
+		);
+	}
+
+	#[test]
+	fn harder_difficulties_make_the_ai_bolder() {
+		let source = include_str!("../../../resources/civ/rules.ron");
+		let mut schemas = Schemas::default();
+		schemas.declare(GAME_RULES, 1);
+		let choices: RuleChoices = schemas.read(GAME_RULES, source.as_bytes()).unwrap();
+		let normal = choices.select("standard", "normal").unwrap();
+		let deity = choices.select("standard", "deity").unwrap();
+		for &behavior in &UnitBehavior::ALL {
+			assert_eq!(difficulty_weight(&normal, behavior), Fixed::ONE);
+		}
+		assert_eq!(
+			difficulty_weight(&deity, UnitBehavior::Attack),
+			Fixed::from_percent(125)
+		);
+		assert_eq!(
+			difficulty_weight(&deity, UnitBehavior::Settle),
+			Fixed::from_percent(160)
+		);
+		assert_eq!(difficulty_weight(&deity, UnitBehavior::Defend), Fixed::ONE);
+
+		let base = unit_ai();
+		let weight = |ai: &UtilityAi, name: &str| {
+			ai.actions()
+				.iter()
+				.find(|action| action.name == name)
+				.unwrap()
+				.weight
+		};
+		let at_normal = weighted_unit_ai(&base, None, &normal);
+		let at_deity = weighted_unit_ai(&base, None, &deity);
+		assert_eq!(weight(&at_normal, "attack"), weight(&base, "attack"));
+		assert!(weight(&at_deity, "attack") > weight(&base, "attack"));
+		assert_eq!(weight(&at_deity, "defend"), weight(&base, "defend"));
+	}
+
+	#[test]
+	fn settles_on_open_land_with_nothing_else_to_do() {
+		let tile_map = tile_map();
+		let situation = UnitSituation::new(&tile_map, Coord::new_axial(4, 4), None, &[], &[]);
+		assert_eq!(decide(&tile_map, &situation), UnitBehavior::Settle);
+	}
+}
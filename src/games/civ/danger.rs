@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::fixed::Fixed;
+use crate::core::map::coord::Coord;
+use crate::core::map::path::find_path;
+use crate::core::map::tile_map::TileMap;
+
+/// The heatmap field the danger maps are shown as.
+pub const DANGER_FIELD: &str = "danger";
+
+fn serde_moves() -> u16 {
+	1
+}
+
+fn serde_range() -> u16 {
+	1
+}
+
+/// How dangerous something is to the units near it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThreatRule {
+	pub strength: Fixed,
+	/// Tiles it can move in a turn.
+	#[serde(default = "serde_moves")]
+	pub moves: u16,
+	/// Tiles away it can attack from.
+	#[serde(default = "serde_range")]
+	pub range: u16,
+}
+
+/// A known enemy on the map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Threat {
+	pub at: Coord,
+	pub rule: ThreatRule,
+}
+
+/// How much danger each tile of a map is in from the threats on it, for units to keep away
+/// from trouble they can't take on.
+///
+/// A tile a threat can reach and attack this turn gets its whole strength, past that it fades
+/// over the tiles it could move next turn. Where threats overlap their danger adds up.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DangerMap {
+	/// In the linear order of the map's tiles.
+	values: Vec<Fixed>,
+}
+
+impl DangerMap {
+	pub fn new(tile_map: &TileMap, threats: &[Threat]) -> DangerMap {
+		let row_length = tile_map.width as usize + 1;
+		let values = (0..tile_map.tiles.len())
+			.map(|idx| {
+				let coord = Coord::new_axial((idx % row_length) as u8, (idx / row_length) as u8);
+				threats.iter().fold(Fixed::ZERO, |danger, threat| {
+					danger.saturating_add(projected_danger(tile_map, threat, coord))
+				})
+			})
+			.collect();
+		DangerMap { values }
+	}
+
+	pub fn values(&self) -> &[Fixed] {
+		&self.values
+	}
+
+	pub fn danger_at(&self, tile_map: &TileMap, coord: Coord) -> Fixed {
+		coord
+			.idx(tile_map.width, tile_map.height, tile_map.wraps_x)
+			.and_then(|idx| self.values.get(idx).copied())
+			.unwrap_or(Fixed::ZERO)
+	}
+
+	/// The way from `from` to `to` by `find_path`, where each whole point of danger on a tile
+	/// costs as much as going a tile further around it.
+	pub fn safest_path(&self, tile_map: &TileMap, from: Coord, to: Coord) -> Option<Vec<Coord>> {
+		find_path(tile_map, from, to, |coord, _tile| {
+			Some(1 + self.danger_at(tile_map, coord).round().max(0) as u32)
+		})
+	}
+
+	/// The least dangerous tile within `moves` steps of `from` to fall back to, `from` itself
+	/// unless somewhere is safer. Ties go to the nearest.
+	pub fn retreat(&self, tile_map: &TileMap, from: Coord, moves: u8) -> Coord {
+		let mut best = (self.danger_at(tile_map, from), 0, from);
+		let around = tile_map
+			.iter_neighbors_around(from, moves)
+			.filter_map(|(co, _tile)| {
+				from.offset_by(co, tile_map.width, tile_map.height, tile_map.wraps_x)
+			});
+		for coord in around {
+			let danger = self.danger_at(tile_map, coord);
+			let distance = tile_map.distance(from, coord);
+			if (danger, distance) < (best.0, best.1) {
+				best = (danger, distance, coord);
+			}
+		}
+		best.2
+	}
+}
+
+fn projected_danger(tile_map: &TileMap, threat: &Threat, coord: Coord) -> Fixed {
+	let distance = tile_map.distance(threat.at, coord);
+	let reach = threat.rule.moves.saturating_add(threat.rule.range);
+	if distance <= reach {
+		return threat.rule.strength;
+	}
+	// Fades to nothing over the next turn's moves
+	let past = i32::from(distance - reach);
+	let fade = i32::from(threat.rule.moves) + 1;
+	if past >= fade {
+		return Fixed::ZERO;
+	}
+	threat.rule.strength * Fixed::from_ratio(fade - past, fade)
+}
+
+#[cfg(test)]
+mod danger_tests {
+	use super::*;
+	use crate::core::map::generator::test_maps::plain_map;
+
+	fn tile_map() -> TileMap {
+		plain_map(11, 11, false)
+	}
+
+	fn wolf(at: Coord) -> Threat {
+		Threat {
+			at,
+			rule: ThreatRule {
+				strength: Fixed::from_int(4),
+				moves: 1,
+				range: 1,
+			},
+		}
+	}
+
+	#[test]
+	fn fades_past_the_reach() {
+		let tile_map = tile_map();
+		let danger = DangerMap::new(&tile_map, &[wolf(Coord::new_axial(2, 5))]);
+		let at = |q| danger.danger_at(&tile_map, Coord::new_axial(q, 5));
+		assert_eq!(at(2), Fixed::from_int(4));
+		assert_eq!(at(4), Fixed::from_int(4));
+		assert_eq!(at(5), Fixed::from_int(2));
+		assert_eq!(at(6), Fixed::ZERO);
+	}
+
+	#[test]
+	fn overlapping_threats_add_up() {
+		let tile_map = tile_map();
+		let danger = DangerMap::new(
+			&tile_map,
+			&[wolf(Coord::new_axial(2, 5)), wolf(Coord::new_axial(6, 5))],
+		);
+		assert_eq!(
+			danger.danger_at(&tile_map, Coord::new_axial(4, 5)),
+			Fixed::from_int(8)
+		);
+		assert_eq!(
+			DangerMap::new(&tile_map, &[]).values().iter().max(),
+			Some(&Fixed::ZERO)
+		);
+	}
+
+	#[test]
+	fn paths_go_around_danger() {
+		let tile_map = tile_map();
+		let from = Coord::new_axial(1, 5);
+		let to = Coord::new_axial(9, 5);
+		let safe = DangerMap::new(&tile_map, &[]);
+		let straight = safe.safest_path(&tile_map, from, to).unwrap();
+		assert_eq!(straight.len(), tile_map.distance(from, to) as usize + 1);
+
+		// A wolf in the middle of the straight way makes a detour worth it
+		let danger = DangerMap::new(&tile_map, &[wolf(Coord::new_axial(5, 5))]);
+		let path = danger.safest_path(&tile_map, from, to).unwrap();
+		assert_eq!((path[0], path[path.len() - 1]), (from, to));
+		for step in path.windows(2) {
+			assert_eq!(tile_map.distance(step[0], step[1]), 1);
+		}
+		assert!(path.len() > straight.len());
+		let total = |path: &[Coord]| {
+			path.iter().fold(Fixed::ZERO, |total, &coord| {
+				total + danger.danger_at(&tile_map, coord)
+			})
+		};
+		assert!(total(&path) < total(&straight));
+		assert_eq!(danger.safest_path(&tile_map, to, to), Some(vec![to]));
+	}
+
+	#[test]
+	fn retreats_out_of_reach() {
+		let tile_map = tile_map();
+		let danger = DangerMap::new(&tile_map, &[wolf(Coord::new_axial(5, 5))]);
+		let from = Coord::new_axial(6, 5);
+		let to = danger.retreat(&tile_map, from, 3);
+		assert!(danger.danger_at(&tile_map, to) < danger.danger_at(&tile_map, from));
+		assert!(tile_map.distance(from, to) <= 3);
+		// Nowhere safer in reach, so it holds
+		let safe = Coord::new_axial(11, 0);
+		assert_eq!(danger.retreat(&tile_map, safe, 2), safe);
+	}
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::fixed::Fixed;
+use crate::core::map::coord::Coord;
+use crate::core::map::tile::TileIdx;
+use crate::core::map::tile_map::TileMap;
+
+pub const GOODIES: &str = "goodies";
+
+/// Mixed into the map seed so huts don't land where the decorations rolled low too.
+const PLACEMENT_SALT: u64 = 0x600D_1E5;
+const REWARD_SALT: u64 = 0x6001_7AB1E;
+
+/// What the first unit into a goody hut finds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GoodyReward {
+	Gold(u32),
+	/// Research points towards the next technology.
+	Research(u32),
+	/// A unit from this entity template joins on the hut's tile.
+	Unit(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WeightedReward {
+	pub weight: u32,
+	pub reward: GoodyReward,
+}
+
+/// Where goody huts go and what is in them, from `civ/goodies.ron`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GoodyTable {
+	/// The decoration that marks a goody hut, it is taken off the tile once found.
+	pub site: String,
+	/// The tile types huts are placed on.
+	pub on: Vec<String>,
+	/// The chance each of those tiles gets a hut when a map is set up, from 0 to 1.
+	pub density: Fixed,
+	pub rewards: Vec<WeightedReward>,
+}
+
+impl GoodyTable {
+	/// The reward in the hut at `coord`, the same one for a hut every time.
+	pub fn reward_at(&self, tile_map: &TileMap, coord: Coord) -> Option<&GoodyReward> {
+		let total: u64 = self.rewards.iter().map(|r| u64::from(r.weight)).sum();
+		if total == 0 {
+			return None;
+		}
+		let mut roll = coord.hash_with_seed(tile_map.seed ^ REWARD_SALT) % total;
+		for reward in &self.rewards {
+			let weight = u64::from(reward.weight);
+			if roll < weight {
+				return Some(&reward.reward);
+			}
+			roll -= weight;
+		}
+		None
+	}
+
+	/// The undecorated tiles of a type in `on` that get a hut.
+	pub fn placements(&self, tile_map: &TileMap, on: &[TileIdx]) -> Vec<Coord> {
+		let row_length = tile_map.width as usize + 1;
+		tile_map
+			.tiles
+			.iter()
+			.enumerate()
+			.filter(|(_idx, tile)| tile.decoration.is_none() && on.contains(&tile.id))
+			.map(|(idx, _tile)| {
+				Coord::new_axial((idx % row_length) as u8, (idx / row_length) as u8)
+			})
+			.filter(|coord| {
+				let hash = coord.hash_with_seed(tile_map.seed ^ PLACEMENT_SALT);
+				Fixed::from_bits((hash >> (64 - Fixed::FRACTION_BITS)) as i32) < self.density
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod goodies_tests {
+	use super::*;
+	use crate::core::map::generator::test_maps::{plain, plain_map};
+
+	fn table(density: Fixed) -> (GoodyTable, TileMap, TileIdx) {
+		let mut tile_map = plain_map(15, 15, false);
+		tile_map.seed = 11;
+		let table = GoodyTable {
+			site: "ruins".to_owned(),
+			on: vec!["plain".to_owned()],
+			density,
+			rewards: vec![
+				WeightedReward {
+					weight: 3,
+					reward: GoodyReward::Gold(25),
+				},
+				WeightedReward {
+					weight: 0,
+					reward: GoodyReward::Research(10),
+				},
+				WeightedReward {
+					weight: 1,
+					reward: GoodyReward::Unit("scout".to_owned()),
+				},
+			],
+		};
+		(table, tile_map, plain())
+	}
+
+	#[test]
+	fn placements_follow_the_density() {
+		let (table, tile_map, plain) = table(Fixed::from_percent(10));
+		let placed = table.placements(&tile_map, &[plain]);
+		// 256 tiles at 10%
+		assert!(placed.len() > 10 && placed.len() < 45, "{}", placed.len());
+		assert_eq!(table.placements(&tile_map, &[plain]), placed);
+		assert!(table.placements(&tile_map, &[]).is_empty());
+		let (table, tile_map, plain) = self::table(Fixed::ZERO);
+		assert!(table.placements(&tile_map, &[plain]).is_empty());
+	}
+
+	#[test]
+	fn rewards_follow_their_weights() {
+		let (table, tile_map, _plain) = table(Fixed::ONE);
+		let mut gold = 0;
+		for q in 0..16 {
+			for r in 0..16 {
+				let coord = Coord::new_axial(q, r);
+				let reward = table.reward_at(&tile_map, coord).unwrap();
+				assert_eq!(table.reward_at(&tile_map, coord), Some(reward));
+				match reward {
+					GoodyReward::Gold(25) => gold += 1,
+					GoodyReward::Unit(_) => (),
+					other => panic!("zero weight reward {:?} was picked", other),
+				}
+			}
+		}
+		assert!(gold > 150 && gold < 230, "{}", gold);
+	}
+
+	#[test]
+	fn no_reward_without_weights() {
+		let (mut table, tile_map, _plain) = table(Fixed::ONE);
+		table.rewards.retain(|r| r.weight == 0);
+		assert_eq!(table.reward_at(&tile_map, Coord::new_axial(1, 2)), None);
+	}
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::fixed::Fixed;
+use crate::core::map::coord::Coord;
+use crate::core::map::decoration::DecorationIdx;
+use crate::core::map::influence::InfluenceRule;
+use crate::core::map::tile_map::TileMap;
+
+pub const INFLUENCE: &str = "influence";
+
+fn serde_every_ticks() -> u32 {
+	10
+}
+
+/// What an influence spreads from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InfluenceFrom {
+	/// Every unit made from this entity template.
+	Template(String),
+	/// Every tile with this decoration.
+	Decoration(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InfluenceSourceRule {
+	pub from: InfluenceFrom,
+	/// Added to the source's tile each time the influence spreads.
+	pub strength: Fixed,
+}
+
+/// A religion, ideology or the like spreading over every map, from `civ/influence.ron`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InfluenceKind {
+	/// The influence field it spreads in.
+	pub name: String,
+	pub rule: InfluenceRule,
+	/// Simulation ticks between each time it spreads.
+	#[serde(default = "serde_every_ticks")]
+	pub every_ticks: u32,
+	pub sources: Vec<InfluenceSourceRule>,
+}
+
+/// An influence kind with its decorations looked up, deciding where it spreads from.
+#[derive(Clone, Debug)]
+pub struct InfluenceSpreader {
+	pub kind: InfluenceKind,
+	decorations: Vec<(DecorationIdx, Fixed)>,
+}
+
+impl InfluenceSpreader {
+	/// `decorations` are the decorations of the kind's `Decoration` sources, in order.
+	pub fn new(kind: InfluenceKind, decorations: Vec<DecorationIdx>) -> InfluenceSpreader {
+		let decorations = kind
+			.sources
+			.iter()
+			.filter(|source| matches!(source.from, InfluenceFrom::Decoration(_)))
+			.zip(decorations)
+			.map(|(source, decoration)| (decoration, source.strength))
+			.collect();
+		InfluenceSpreader { kind, decorations }
+	}
+
+	pub fn is_due(&self, tick: u64) -> bool {
+		tick % u64::from(self.kind.every_ticks.max(1)) == 0
+	}
+
+	/// The tiles of `tile_map` it spreads from and how strongly, given the template each of the
+	/// `units` on it was made from.
+	pub fn sources(&self, tile_map: &TileMap, units: &[(Coord, &str)]) -> Vec<(Coord, Fixed)> {
+		let row_length = tile_map.width as usize + 1;
+		let mut sources: Vec<(Coord, Fixed)> = tile_map
+			.tiles
+			.iter()
+			.enumerate()
+			.filter_map(|(idx, tile)| {
+				let decoration = tile.decoration?;
+				let strength = self
+					.decorations
+					.iter()
+					.filter(|(d, _strength)| *d == decoration)
+					.fold(Fixed::ZERO, |sum, &(_d, strength)| sum + strength);
+				let coord = Coord::new_axial((idx % row_length) as u8, (idx / row_length) as u8);
+				Some((coord, strength))
+			})
+			.filter(|&(_coord, strength)| strength != Fixed::ZERO)
+			.collect();
+		for &(coord, template) in units {
+			for source in &self.kind.sources {
+				match &source.from {
+					InfluenceFrom::Template(from) if from == template => {
+						sources.push((coord, source.strength))
+					}
+					_ => (),
+				}
+			}
+		}
+		sources
+	}
+}
+
+#[cfg(test)]
+mod influence_tests {
+	use super::*;
+	use crate::core::map::decoration::DecorationTypesMap;
+	use crate::core::map::generator::test_maps::{filled_map, plain};
+	use crate::core::map::tile::Tile;
+	use crate::core::structures::typed_index_map::TypedIndexMap;
+
+	#[test]
+	fn spreads_from_decorations_and_units() {
+		let mut decoration_types = TypedIndexMap::<DecorationTypesMap, String, (), u16>::new();
+		let (shrine, _old) = decoration_types
+			.insert_full("shrine".to_owned(), ())
+			.unwrap();
+		// Plain tiles with the shrine on every fifth
+		let plain = plain();
+		let tile_map = filled_map(4, 4, false, |idx| {
+			let mut tile = Tile::new(plain);
+			if idx % 5 == 0 {
+				tile.decoration = Some(shrine);
+			}
+			tile
+		});
+		let kind = InfluenceKind {
+			name: "religion".to_owned(),
+			rule: InfluenceRule {
+				spread: Fixed::from_percent(20),
+				decay: Fixed::from_percent(5),
+			},
+			every_ticks: 4,
+			sources: vec![
+				InfluenceSourceRule {
+					from: InfluenceFrom::Template("prophet".to_owned()),
+					strength: Fixed::from_int(3),
+				},
+				InfluenceSourceRule {
+					from: InfluenceFrom::Decoration("shrine".to_owned()),
+					strength: Fixed::ONE,
+				},
+			],
+		};
+		let spreader = InfluenceSpreader::new(kind, vec![shrine]);
+		assert!(spreader.is_due(8));
+		assert!(!spreader.is_due(6));
+
+		let prophet = Coord::new_axial(1, 1);
+		let sources = spreader.sources(
+			&tile_map,
+			&[(prophet, "prophet"), (Coord::new_axial(2, 2), "warrior")],
+		);
+		// 25 tiles with a shrine on every fifth, then the prophet
+		assert_eq!(sources.len(), 6);
+		assert_eq!(sources[0], (Coord::new_axial(0, 0), Fixed::ONE));
+		assert_eq!(sources[1], (Coord::new_axial(0, 1), Fixed::ONE));
+		assert_eq!(sources[5], (prophet, Fixed::from_int(3)));
+	}
+}
@@ -1,22 +1,761 @@
+use std::collections::HashMap;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 
 use anyhow::Context as AnyContext;
 use shipyard::*;
 
-use crate::core::component::ComponentAutoLoadable;
-use crate::core::engine::io::EngineIO;
+use crate::core::ai::utility::{UtilityAction, UtilityAi};
+use crate::core::component::{check_components, save_entity_components, ComponentAutoLoadable};
+use crate::core::engine::io::{EngineIO, WriteMode};
+use crate::core::engine::journal::JournalEvent;
+use crate::core::engine::schema::Schemas;
+use crate::core::engine::{Engine, MapCoord, MapIdx};
+use crate::core::fixed::Fixed;
+use crate::core::map::coord::Coord;
+use crate::core::map::decoration::DecorationIdx;
+use crate::core::map::influence::InfluenceFields;
+use crate::core::map::path::find_path;
+use crate::core::map::tile::TileIdx;
+use crate::core::reflect::TypeIssue;
 
+use self::ai::{
+	weighted_unit_ai, AiPersona, UnitBehavior, UnitSituation, AI_PERSONAS, UNIT_AI, UNIT_MOVES,
+};
+use self::danger::{DangerMap, Threat, DANGER_FIELD};
+use self::goodies::{GoodyReward, GoodyTable, GOODIES};
+use self::influence::{InfluenceFrom, InfluenceKind, InfluenceSpreader, INFLUENCE};
+use self::rules::{GameRules, RuleChoices, GAME_RULES};
+use self::wildlife::{Wildlife, WildlifeRule, WildlifeSpawner, WILDLIFE};
+
+pub mod ai;
+pub mod danger;
+pub mod goodies;
+pub mod influence;
 pub mod maps;
+pub mod rules;
+pub mod wildlife;
+
+pub const ENTITY_TEMPLATES: &str = "entity_templates";
+
+/// Simulation ticks between refreshing the danger maps, a turn's worth until there are turns.
+pub const DANGER_EVERY_TICKS: u64 = 10;
+
+/// The entity template an entity was made from, so it can be patched when the template changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FromTemplate(pub String);
+
+/// The components of a template that changed in a reload, to re-apply to its entities.
+pub struct TemplatePatch {
+	pub template: String,
+	/// Only the components that are new or have new values, removed ones are not included.
+	components: Vec<Box<dyn ComponentAutoLoadable>>,
+}
+
+impl TemplatePatch {
+	pub fn is_empty(&self) -> bool {
+		self.components.is_empty()
+	}
+
+	/// Re-applies the changed components to every entity made from the template, replacing the
+	/// values they have now, and returns how many entities were patched. Components taken out of
+	/// the template stay on the entities.
+	pub fn apply(&self, all_storages: &mut AllStoragesViewMut) -> anyhow::Result<usize> {
+		let entities: Vec<EntityId> = {
+			let from_templates = all_storages.try_borrow::<View<FromTemplate>>()?;
+			(&from_templates)
+				.iter()
+				.with_id()
+				.filter(|(_entity, from)| from.0 == self.template)
+				.map(|(entity, _from)| entity)
+				.collect()
+		};
+		for &entity in &entities {
+			for c in &self.components {
+				c.add_to_entity(entity, all_storages)?;
+			}
+		}
+		Ok(entities.len())
+	}
+}
+
+#[derive(Default)]
+pub struct TemplateReload {
+	/// The templates whose files changed, even if none of their component values did.
+	pub changed: Vec<TemplatePatch>,
+	/// Templates that failed to reload keep what they had before.
+	pub failed: Vec<(String, anyhow::Error)>,
+}
+
+/// What a wildlife step did, the removed entities are gone from the world.
+#[derive(Default)]
+pub struct WildlifeStep {
+	pub spawned: Vec<EntityId>,
+	pub despawned: Vec<EntityId>,
+}
+
+/// What the player has gathered, there is only the one player so far.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stockpile {
+	pub gold: u32,
+	/// Towards the next technology, once there are technologies.
+	pub research: u32,
+}
+
+/// A goody hut someone found, it is already gone from its tile.
+#[derive(Clone, Debug)]
+pub struct GoodyFound {
+	pub at: MapCoord,
+	pub finder: EntityId,
+	pub reward: GoodyReward,
+	/// The unit that joined for a `GoodyReward::Unit`.
+	pub spawned: Option<EntityId>,
+}
+
+/// What an AI unit would do and where it would go, with how every behavior scored.
+#[derive(Clone, Debug)]
+pub struct UnitPlan {
+	pub behavior: UnitBehavior,
+	/// `None` to stay where it is.
+	pub to: Option<MapCoord>,
+	/// The way to `to` around the danger, both ends included, empty when staying put or when
+	/// there is no way there.
+	pub path: Vec<Coord>,
+	pub scores: Vec<(UnitBehavior, Fixed)>,
+}
+
+/// The goody table with its decoration and tile types looked up.
+struct Goodies {
+	table: GoodyTable,
+	site: DecorationIdx,
+	on: Vec<TileIdx>,
+}
 
 pub struct CivGame {
 	base_resource_path: PathBuf,
+	pub schemas: Schemas,
+	/// The source of every template loaded so far, by name.
+	templates: HashMap<String, Vec<u8>>,
+	/// The source of templates that failed to reload, not tried again until they change.
+	broken_templates: HashMap<String, Vec<u8>>,
+	/// The game speed and difficulty, the standard ones until `load_rules` picks others.
+	pub rules: GameRules,
+	/// Nothing spawns until `load_wildlife`.
+	wildlife: Vec<WildlifeSpawner>,
+	/// No goody huts are placed or found until `load_goodies`.
+	goodies: Option<Goodies>,
+	pub stockpile: Stockpile,
+	/// Nothing spreads until `load_influence`.
+	influence: Vec<InfluenceSpreader>,
+	/// The influence fields of each map they have spread on so far.
+	influence_fields: HashMap<MapIdx, InfluenceFields>,
+	/// The danger each map's units are in, as of the last refresh.
+	danger: HashMap<MapIdx, DangerMap>,
+	/// Units decide nothing until `load_unit_ai`.
+	unit_ai: UtilityAi,
+	/// The personalities AI opponents can have, none until `load_personas`.
+	personas: Vec<AiPersona>,
 }
 
 impl CivGame {
 	pub fn new<P: AsRef<Path>>(base_resource_path: P) -> CivGame {
+		let mut schemas = Schemas::default();
+		schemas.declare(ENTITY_TEMPLATES, 1);
+		schemas.declare(GAME_RULES, 1);
+		schemas.declare(WILDLIFE, 1);
+		schemas.declare(GOODIES, 1);
+		schemas.declare(INFLUENCE, 1);
+		schemas.declare(UNIT_AI, 1);
+		schemas.declare(AI_PERSONAS, 1);
 		CivGame {
 			base_resource_path: base_resource_path.as_ref().into(),
+			schemas,
+			templates: HashMap::new(),
+			broken_templates: HashMap::new(),
+			rules: GameRules::default(),
+			wildlife: vec![],
+			goodies: None,
+			stockpile: Stockpile::default(),
+			influence: vec![],
+			influence_fields: HashMap::new(),
+			danger: HashMap::new(),
+			unit_ai: UtilityAi::default(),
+			personas: vec![],
+		}
+	}
+
+	/// Picks the game speed and difficulty by name from the ones in `rules.ron`.
+	pub fn load_rules<IO: 'static + EngineIO>(
+		&mut self,
+		io: &mut IO,
+		speed: &str,
+		difficulty: &str,
+	) -> anyhow::Result<()> {
+		let mut path = self.base_resource_path.clone();
+		path.push("rules.ron");
+		let reader = io.read(path.as_path())?;
+		let choices: RuleChoices = self
+			.schemas
+			.read(GAME_RULES, reader)
+			.context("Failed loading the game rules")?;
+		self.rules = choices.select(speed, difficulty)?;
+		Ok(())
+	}
+
+	/// Loads what wildlife lives where from `wildlife.ron`, its tile types have to be loaded.
+	pub fn load_wildlife<IO: 'static + EngineIO>(
+		&mut self,
+		io: &mut IO,
+		engine: &Engine<IO>,
+	) -> anyhow::Result<()> {
+		let mut path = self.base_resource_path.clone();
+		path.push("wildlife.ron");
+		let reader = io.read(path.as_path())?;
+		let rules: Vec<WildlifeRule> = self
+			.schemas
+			.read(WILDLIFE, reader)
+			.context("Failed loading the wildlife rules")?;
+		let mut spawners = Vec::with_capacity(rules.len());
+		for rule in rules {
+			let on = rule
+				.on
+				.iter()
+				.map(|name| {
+					engine
+						.tile_types
+						.tile_types
+						.get_index_of(name)
+						.with_context(|| {
+							format!(
+								"wildlife `{}` spawns on missing tile type `{}`",
+								rule.template, name
+							)
+						})
+				})
+				.collect::<anyhow::Result<Vec<_>>>()?;
+			spawners.push(WildlifeSpawner::new(rule, on));
+		}
+		self.wildlife = spawners;
+		Ok(())
+	}
+
+	/// Runs the wildlife rules due on simulation `tick`. Wildlife that strayed too far from every
+	/// other unit leaves, then each map with room for more of it gets another.
+	///
+	/// There is no fog of war or unit ownership yet, so every unit that isn't wildlife counts as a
+	/// player's unit watching its surroundings.
+	pub fn step_wildlife<IO: 'static + EngineIO>(
+		&mut self,
+		io: &mut IO,
+		engine: &mut Engine<IO>,
+		tick: u64,
+		all_storages: &mut AllStoragesViewMut,
+	) -> anyhow::Result<WildlifeStep> {
+		let mut step = WildlifeStep::default();
+		for index in 0..self.wildlife.len() {
+			let spawner = &self.wildlife[index];
+			if !spawner.is_due(tick) {
+				continue;
+			}
+			let template = spawner.rule.template.clone();
+			let (mut animals, watchers) = {
+				let coords = all_storages.try_borrow::<View<MapCoord>>()?;
+				let wildlife = all_storages.try_borrow::<View<Wildlife>>()?;
+				let mut animals: Vec<(EntityId, MapCoord)> = vec![];
+				let mut watchers: Vec<MapCoord> = vec![];
+				for (entity, &coord) in (&coords).iter().with_id() {
+					match wildlife.get(entity) {
+						Ok(Wildlife(from)) if *from == template => animals.push((entity, coord)),
+						Ok(_other) => (),
+						Err(_) => watchers.push(coord),
+					}
+				}
+				(animals, watchers)
+			};
+			let watchers_on = |map| -> Vec<Coord> {
+				watchers
+					.iter()
+					.filter(|at| at.map == map)
+					.map(|at| at.coord)
+					.collect()
+			};
+
+			let mut leaving = vec![];
+			animals.retain(|&(entity, at)| {
+				let unwatched = engine
+					.maps
+					.get_index(at.map)
+					.map_or(true, |(_name, tile_map)| {
+						spawner.is_unwatched(tile_map, at.coord, &watchers_on(at.map))
+					});
+				if unwatched {
+					leaving.push((entity, at));
+				}
+				!unwatched
+			});
+			for (entity, at) in leaving {
+				if let Some(tile) = engine
+					.maps
+					.get_index_mut(at.map)
+					.and_then(|(_name, tile_map)| tile_map.get_tile_mut(at.coord))
+				{
+					tile.entities.remove(&entity);
+				}
+				all_storages.delete(entity);
+				engine.persistent_ids.forget(entity);
+				step.despawned.push(entity);
+			}
+
+			let mut arrivals = vec![];
+			let names: Vec<String> = engine.maps.keys().cloned().collect();
+			for name in &names {
+				let (map, _name, tile_map) =
+					engine.maps.get_full(name).context("map is missing")?;
+				if !spawner.lives_on(engine.map_ambient(map)) {
+					continue;
+				}
+				let existing = animals.iter().filter(|(_entity, at)| at.map == map).count();
+				if let Some(coord) =
+					spawner.spawn_coord(tile_map, existing, &watchers_on(map), tick)
+				{
+					arrivals.push(MapCoord { map, coord });
+				}
+			}
+			for at in arrivals {
+				let entity =
+					self.create_entity_from_template(io, engine, &template, all_storages)?;
+				{
+					let entities = all_storages.try_borrow::<EntitiesView>()?;
+					let mut wildlife = all_storages.try_borrow::<ViewMut<Wildlife>>()?;
+					(&entities).try_add_component(
+						&mut wildlife,
+						Wildlife(template.clone()),
+						entity,
+					)?;
+				}
+				engine.move_entity_to_coord(
+					entity,
+					at,
+					all_storages.try_borrow()?,
+					all_storages.try_borrow()?,
+				)?;
+				step.spawned.push(entity);
+			}
+		}
+		Ok(step)
+	}
+
+	/// Loads where goody huts go and what is in them from `goodies.ron`, its decoration and tile
+	/// types have to be loaded.
+	pub fn load_goodies<IO: 'static + EngineIO>(
+		&mut self,
+		io: &mut IO,
+		engine: &Engine<IO>,
+	) -> anyhow::Result<()> {
+		let mut path = self.base_resource_path.clone();
+		path.push("goodies.ron");
+		let reader = io.read(path.as_path())?;
+		let table: GoodyTable = self
+			.schemas
+			.read(GOODIES, reader)
+			.context("Failed loading the goody huts")?;
+		let site = engine
+			.decoration_types
+			.decoration_types
+			.get_index_of(&table.site)
+			.with_context(|| format!("goody huts are missing decoration `{}`", table.site))?;
+		let on = table
+			.on
+			.iter()
+			.map(|name| {
+				engine
+					.tile_types
+					.tile_types
+					.get_index_of(name)
+					.with_context(|| format!("goody huts go on missing tile type `{}`", name))
+			})
+			.collect::<anyhow::Result<Vec<_>>>()?;
+		self.goodies = Some(Goodies { table, site, on });
+		Ok(())
+	}
+
+	/// Scatters goody huts over the map `name`, returning how many were placed.
+	pub fn place_goody_huts<IO: 'static + EngineIO>(
+		&self,
+		engine: &mut Engine<IO>,
+		name: &str,
+	) -> anyhow::Result<usize> {
+		let goodies = match &self.goodies {
+			Some(goodies) => goodies,
+			None => return Ok(0),
+		};
+		let (map, _name, tile_map) = engine
+			.maps
+			.get_full(name)
+			.with_context(|| format!("no map `{}` to place goody huts on", name))?;
+		let placements = goodies.table.placements(tile_map, &goodies.on);
+		for &coord in &placements {
+			engine.set_decoration(MapCoord { map, coord }, Some(goodies.site))?;
+		}
+		Ok(placements.len())
+	}
+
+	/// Opens the goody hut at `at` if there is one, for `entity` that just moved onto it. The hut
+	/// is taken off the tile and its reward given, wildlife doesn't open them.
+	pub fn discover_goody<IO: 'static + EngineIO>(
+		&mut self,
+		io: &mut IO,
+		engine: &mut Engine<IO>,
+		entity: EntityId,
+		at: MapCoord,
+		all_storages: &mut AllStoragesViewMut,
+	) -> anyhow::Result<Option<GoodyFound>> {
+		let goodies = match &self.goodies {
+			Some(goodies) => goodies,
+			None => return Ok(None),
+		};
+		if all_storages
+			.try_borrow::<View<Wildlife>>()?
+			.contains(entity)
+		{
+			return Ok(None);
+		}
+		let (map_name, tile_map) = match engine.maps.get_index(at.map) {
+			Some(map) => map,
+			None => return Ok(None),
+		};
+		match tile_map.get_tile(at.coord) {
+			Some(tile) if tile.decoration == Some(goodies.site) => (),
+			_ => return Ok(None),
+		}
+		let reward = match goodies.table.reward_at(tile_map, at.coord) {
+			Some(reward) => reward.clone(),
+			None => return Ok(None),
+		};
+		let map_name = map_name.clone();
+		engine.set_decoration(at, None)?;
+
+		let mut spawned = None;
+		match &reward {
+			GoodyReward::Gold(gold) => {
+				self.stockpile.gold = self.stockpile.gold.saturating_add(*gold);
+			}
+			GoodyReward::Research(research) => {
+				self.stockpile.research = self.stockpile.research.saturating_add(*research);
+			}
+			GoodyReward::Unit(template) => {
+				let unit = self.create_entity_from_template(io, engine, template, all_storages)?;
+				engine.move_entity_to_coord(
+					unit,
+					at,
+					all_storages.try_borrow()?,
+					all_storages.try_borrow()?,
+				)?;
+				spawned = Some(unit);
+			}
+		}
+		engine.journal.record(JournalEvent::Game {
+			kind: "goody_hut".to_owned(),
+			data: serde_json::json!({
+				"map": map_name,
+				"q": at.coord.q(),
+				"r": at.coord.r(),
+				"reward": reward,
+			}),
+		});
+		Ok(Some(GoodyFound {
+			at,
+			finder: entity,
+			reward,
+			spawned,
+		}))
+	}
+
+	/// Loads the religions and ideologies that spread over the maps from `influence.ron`, the
+	/// decorations they spread from have to be loaded.
+	pub fn load_influence<IO: 'static + EngineIO>(
+		&mut self,
+		io: &mut IO,
+		engine: &Engine<IO>,
+	) -> anyhow::Result<()> {
+		let mut path = self.base_resource_path.clone();
+		path.push("influence.ron");
+		let reader = io.read(path.as_path())?;
+		let kinds: Vec<InfluenceKind> = self
+			.schemas
+			.read(INFLUENCE, reader)
+			.context("Failed loading the influence kinds")?;
+		let mut spreaders = Vec::with_capacity(kinds.len());
+		for kind in kinds {
+			let decorations = kind
+				.sources
+				.iter()
+				.filter_map(|source| match &source.from {
+					InfluenceFrom::Decoration(name) => Some(name),
+					InfluenceFrom::Template(_) => None,
+				})
+				.map(|name| {
+					engine
+						.decoration_types
+						.decoration_types
+						.get_index_of(name)
+						.with_context(|| {
+							format!(
+								"influence `{}` spreads from missing decoration `{}`",
+								kind.name, name
+							)
+						})
+				})
+				.collect::<anyhow::Result<Vec<_>>>()?;
+			spreaders.push(InfluenceSpreader::new(kind, decorations));
+		}
+		self.influence = spreaders;
+		Ok(())
+	}
+
+	/// Spreads the influences due on simulation `tick` a step over every map.
+	///
+	/// There are no cities yet, so they only spread from the decorations and units their sources
+	/// name.
+	pub fn step_influence<IO: 'static + EngineIO>(
+		&mut self,
+		engine: &Engine<IO>,
+		tick: u64,
+		all_storages: &mut AllStoragesViewMut,
+	) -> anyhow::Result<()> {
+		if !self.influence.iter().any(|spreader| spreader.is_due(tick)) {
+			return Ok(());
+		}
+		let units: Vec<(MapCoord, String)> = {
+			let coords = all_storages.try_borrow::<View<MapCoord>>()?;
+			let from_templates = all_storages.try_borrow::<View<FromTemplate>>()?;
+			(&coords, &from_templates)
+				.iter()
+				.map(|(&at, from)| (at, from.0.clone()))
+				.collect()
+		};
+		for (name, tile_map) in engine.maps.iter() {
+			let map = engine.maps.get_index_of(name).context("map is missing")?;
+			let units_on: Vec<(Coord, &str)> = units
+				.iter()
+				.filter(|(at, _template)| at.map == map)
+				.map(|(at, template)| (at.coord, template.as_str()))
+				.collect();
+			let fields = self.influence_fields.entry(map).or_default();
+			for spreader in self.influence.iter().filter(|s| s.is_due(tick)) {
+				let sources = spreader.sources(tile_map, &units_on);
+				fields
+					.get_or_insert(&spreader.kind.name, spreader.kind.rule, tile_map)
+					.diffuse(tile_map, &sources)?;
+			}
+		}
+		Ok(())
+	}
+
+	/// The influence fields of `map`, `None` until something has spread on it.
+	pub fn influence_fields(&self, map: MapIdx) -> Option<&InfluenceFields> {
+		self.influence_fields.get(&map)
+	}
+
+	/// Works out the danger on every map again if simulation `tick` is due for it.
+	///
+	/// Units have no owners yet, so the wildlife with a `threat` are the only enemies.
+	pub fn refresh_danger<IO: 'static + EngineIO>(
+		&mut self,
+		engine: &Engine<IO>,
+		tick: u64,
+		all_storages: &mut AllStoragesViewMut,
+	) -> anyhow::Result<()> {
+		if tick % DANGER_EVERY_TICKS != 0 {
+			return Ok(());
+		}
+		let threats: Vec<(MapIdx, Threat)> = {
+			let coords = all_storages.try_borrow::<View<MapCoord>>()?;
+			let wildlife = all_storages.try_borrow::<View<Wildlife>>()?;
+			(&coords, &wildlife)
+				.iter()
+				.filter_map(|(at, Wildlife(template))| {
+					let rule = self
+						.wildlife
+						.iter()
+						.find(|spawner| spawner.rule.template == *template)?
+						.rule
+						.threat?;
+					Some((at.map, Threat { at: at.coord, rule }))
+				})
+				.collect()
+		};
+		self.danger.clear();
+		for (name, tile_map) in engine.maps.iter() {
+			let map = engine.maps.get_index_of(name).context("map is missing")?;
+			let on_map: Vec<Threat> = threats
+				.iter()
+				.filter(|(on, _threat)| *on == map)
+				.map(|&(_on, threat)| threat)
+				.collect();
+			self.danger.insert(map, DangerMap::new(tile_map, &on_map));
 		}
+		Ok(())
+	}
+
+	/// The danger on `map`, `None` until the first refresh.
+	pub fn danger_map(&self, map: MapIdx) -> Option<&DangerMap> {
+		self.danger.get(&map)
+	}
+
+	/// The names of the per-tile fields `map` has to look at, such as in a heatmap.
+	pub fn field_names(&self, map: MapIdx) -> Vec<String> {
+		let mut names: Vec<String> = self
+			.influence_fields(map)
+			.map(|fields| fields.names().map(|name| name.to_owned()).collect())
+			.unwrap_or_default();
+		if self.danger.contains_key(&map) {
+			names.push(DANGER_FIELD.to_owned());
+		}
+		names
+	}
+
+	/// The value of the field `name` on every tile of `map`, in the linear order of its tiles.
+	pub fn field_values(&self, map: MapIdx, name: &str) -> Option<&[Fixed]> {
+		if name == DANGER_FIELD {
+			return self.danger_map(map).map(|danger| danger.values());
+		}
+		self.influence_fields(map)?
+			.get(name)
+			.ok()
+			.map(|field| field.values())
+	}
+
+	/// Loads how AI units weigh up exploring, defending, attacking and settling from
+	/// `unit_ai.ron`.
+	pub fn load_unit_ai<IO: 'static + EngineIO>(&mut self, io: &mut IO) -> anyhow::Result<()> {
+		let mut path = self.base_resource_path.clone();
+		path.push("unit_ai.ron");
+		let reader = io.read(path.as_path())?;
+		let actions: Vec<UtilityAction> = self
+			.schemas
+			.read(UNIT_AI, reader)
+			.context("Failed loading the unit AI")?;
+		if let Some(action) = actions
+			.iter()
+			.find(|action| UnitBehavior::from_name(&action.name).is_none())
+		{
+			anyhow::bail!("unit AI action `{}` is not a unit behavior", action.name);
+		}
+		self.unit_ai = UtilityAi::new(actions);
+		Ok(())
+	}
+
+	/// Loads the AI opponents' personalities from `personas.ron`.
+	pub fn load_personas<IO: 'static + EngineIO>(&mut self, io: &mut IO) -> anyhow::Result<()> {
+		let mut path = self.base_resource_path.clone();
+		path.push("personas.ron");
+		let reader = io.read(path.as_path())?;
+		self.personas = self
+			.schemas
+			.read(AI_PERSONAS, reader)
+			.context("Failed loading the AI personas")?;
+		Ok(())
+	}
+
+	pub fn personas(&self) -> &[AiPersona] {
+		&self.personas
+	}
+
+	/// The units an AI turn goes through. Units have no owners yet, so that is every unit that
+	/// isn't wildlife.
+	pub fn ai_units(&self, all_storages: &AllStoragesViewMut) -> anyhow::Result<Vec<EntityId>> {
+		let coords = all_storages.try_borrow::<View<MapCoord>>()?;
+		let from_templates = all_storages.try_borrow::<View<FromTemplate>>()?;
+		let wildlife = all_storages.try_borrow::<View<Wildlife>>()?;
+		Ok((&coords, &from_templates)
+			.iter()
+			.with_id()
+			.filter(|&(entity, _)| !wildlife.contains(entity))
+			.map(|(entity, _)| entity)
+			.collect())
+	}
+
+	/// What `entity` would do if the AI had it, weighed by the `persona` named if any and the
+	/// difficulty. `None` if it is not on a map or no behavior scored above nothing.
+	///
+	/// There are no AI players yet so nothing calls this on its own, and with units having no
+	/// owners the threats are the dangerous wildlife.
+	pub fn plan_unit<IO: 'static + EngineIO>(
+		&self,
+		engine: &Engine<IO>,
+		entity: EntityId,
+		persona: Option<&str>,
+		all_storages: &AllStoragesViewMut,
+	) -> anyhow::Result<Option<UnitPlan>> {
+		let persona = match persona {
+			Some(name) => Some(
+				self.personas
+					.iter()
+					.find(|persona| persona.name == name)
+					.with_context(|| format!("no AI persona named `{}`", name))?,
+			),
+			None => None,
+		};
+		let unit_ai = weighted_unit_ai(&self.unit_ai, persona, &self.rules);
+		let coords = all_storages.try_borrow::<View<MapCoord>>()?;
+		let at = match (&coords).get(entity) {
+			Ok(&at) => at,
+			Err(_) => return Ok(None),
+		};
+		let (_name, tile_map) = engine.maps.get_index(at.map).context("map is missing")?;
+		let wildlife = all_storages.try_borrow::<View<Wildlife>>()?;
+		let threats: Vec<Coord> = (&coords, &wildlife)
+			.iter()
+			.with_id()
+			.filter(|&(id, (on, Wildlife(template)))| {
+				id != entity
+					&& on.map == at.map
+					&& self.wildlife.iter().any(|spawner| {
+						spawner.rule.template == *template && spawner.rule.threat.is_some()
+					})
+			})
+			.map(|(_id, (on, _wildlife))| on.coord)
+			.collect();
+		let goodies: Vec<Coord> = match &self.goodies {
+			Some(goodies) => {
+				let row_length = tile_map.width as usize + 1;
+				tile_map
+					.tiles
+					.iter()
+					.enumerate()
+					.filter(|(_idx, tile)| tile.decoration == Some(goodies.site))
+					.map(|(idx, _tile)| {
+						Coord::new_axial((idx % row_length) as u8, (idx / row_length) as u8)
+					})
+					.collect()
+			}
+			None => vec![],
+		};
+		let danger = self.danger_map(at.map);
+		let situation = UnitSituation::new(tile_map, at.coord, danger, &threats, &goodies);
+		let behavior = match unit_ai.choose(&situation)? {
+			Some(action) => UnitBehavior::from_name(&action.name)
+				.context("unit AI action is not a unit behavior")?,
+			None => return Ok(None),
+		};
+		let scores = unit_ai
+			.scores(&situation)?
+			.into_iter()
+			.filter_map(|(action, score)| Some((UnitBehavior::from_name(&action.name)?, score)))
+			.collect();
+		let to = situation.target(behavior, tile_map, danger, UNIT_MOVES);
+		let path = match (to, danger) {
+			(Some(to), Some(danger)) => danger.safest_path(tile_map, at.coord, to),
+			(Some(to), None) => find_path(tile_map, at.coord, to, |_coord, _tile| Some(1)),
+			(None, _) => None,
+		};
+		Ok(Some(UnitPlan {
+			behavior,
+			to: to.map(|coord| MapCoord { map: at.map, coord }),
+			path: path.unwrap_or_default(),
+			scores,
+		}))
 	}
 
 	// pub fn setup<IO: EngineIO>(&mut self) {}
@@ -25,22 +764,173 @@ impl CivGame {
 	pub fn create_entity_from_template<IO: 'static + EngineIO>(
 		&mut self,
 		io: &mut IO,
+		engine: &mut Engine<IO>,
 		template: &str,
 		all_storages: &mut AllStoragesViewMut,
 	) -> anyhow::Result<EntityId> {
-		// TODO: Build a cache for this
-		let mut path = self.base_resource_path.clone();
-		path.push("entities");
-		path.push(format!("{}.ron", template));
-		let reader = io.read(path.as_path())?;
-		let components: Vec<Box<dyn ComponentAutoLoadable>> = ron::de::from_reader(reader)
-			.with_context(|| format!("Failed loading component template for: {}", template))?;
+		if !self.templates.contains_key(template) {
+			let source = self.read_template_file(io, template)?;
+			self.templates.insert(template.to_owned(), source);
+		}
+		let components = self.parse_template(template, &self.templates[template])?;
 		let entity = all_storages
 			.try_borrow::<EntitiesViewMut>()?
 			.add_entity((), ());
 		for c in components {
 			c.add_to_entity(entity, all_storages)?;
 		}
+		{
+			let entities = all_storages.try_borrow::<EntitiesView>()?;
+			let mut from_templates = all_storages.try_borrow::<ViewMut<FromTemplate>>()?;
+			(&entities).try_add_component(
+				&mut from_templates,
+				FromTemplate(template.to_owned()),
+				entity,
+			)?;
+		}
+		engine.assign_persistent_id(
+			entity,
+			all_storages.try_borrow()?,
+			all_storages.try_borrow()?,
+		);
 		Ok(entity)
 	}
+
+	/// Writes the auto loadable components `entity` has now to `path` as an entity template.
+	pub fn write_entity_template<IO: 'static + EngineIO>(
+		&self,
+		io: &mut IO,
+		path: &Path,
+		entity: EntityId,
+		all_storages: &AllStoragesViewMut,
+	) -> anyhow::Result<()> {
+		let components = save_entity_components(entity, all_storages)?;
+		let writer = io.write(path, WriteMode::Truncate)?;
+		self.schemas.write(ENTITY_TEMPLATES, writer, &components)?;
+		Ok(())
+	}
+
+	/// Reads every template loaded so far again, returning what changed for the ones whose files
+	/// did. Templates not used yet are read when first used anyway, and ones that failed to
+	/// reload are only reported again once their files change.
+	pub fn reload_templates<IO: 'static + EngineIO>(&mut self, io: &mut IO) -> TemplateReload {
+		let mut reload = TemplateReload::default();
+		let mut names: Vec<String> = self.templates.keys().cloned().collect();
+		names.sort();
+		for name in names {
+			match self.reload_template(io, &name) {
+				Ok(Some(patch)) => reload.changed.push(patch),
+				Ok(None) => {}
+				Err(error) => reload.failed.push((name, error)),
+			}
+		}
+		reload
+	}
+
+	fn reload_template<IO: 'static + EngineIO>(
+		&mut self,
+		io: &mut IO,
+		template: &str,
+	) -> anyhow::Result<Option<TemplatePatch>> {
+		let source = self.read_template_file(io, template)?;
+		if self.templates[template] == source
+			|| self.broken_templates.get(template) == Some(&source)
+		{
+			return Ok(None);
+		}
+		match self.changed_components(template, &source) {
+			Ok(components) => {
+				self.broken_templates.remove(template);
+				self.templates.insert(template.to_owned(), source);
+				Ok(Some(TemplatePatch {
+					template: template.to_owned(),
+					components,
+				}))
+			}
+			Err(error) => {
+				self.broken_templates.insert(template.to_owned(), source);
+				Err(error)
+			}
+		}
+	}
+
+	/// The components in `source` that the loaded `template` doesn't have with the same values.
+	fn changed_components(
+		&self,
+		template: &str,
+		source: &[u8],
+	) -> anyhow::Result<Vec<Box<dyn ComponentAutoLoadable>>> {
+		// Components are compared by their serialized values since they can't be compared directly
+		let old_values = self
+			.parse_template(template, &self.templates[template])?
+			.iter()
+			.map(|c| component_value(c.as_ref()))
+			.collect::<anyhow::Result<Vec<String>>>()?;
+		let mut components = vec![];
+		for c in self.parse_template(template, source)? {
+			if !old_values.contains(&component_value(c.as_ref())?) {
+				components.push(c);
+			}
+		}
+		Ok(components)
+	}
+
+	fn read_template_file<IO: 'static + EngineIO>(
+		&self,
+		io: &mut IO,
+		template: &str,
+	) -> anyhow::Result<Vec<u8>> {
+		let mut path = self.base_resource_path.clone();
+		path.push("entities");
+		path.push(format!("{}.ron", template));
+		let mut reader = io.read(path.as_path())?;
+		let mut source = vec![];
+		reader
+			.read_to_end(&mut source)
+			.with_context(|| format!("Failed reading component template for: {}", template))?;
+		Ok(source)
+	}
+
+	fn parse_template(
+		&self,
+		template: &str,
+		source: &[u8],
+	) -> anyhow::Result<Vec<Box<dyn ComponentAutoLoadable>>> {
+		self.schemas
+			.read(ENTITY_TEMPLATES, source)
+			.map_err(|error| {
+				// Loading stops at the first problem, checking lists them all
+				let error = anyhow::Error::from(error);
+				match self.check_template(source) {
+					Ok(issues) if !issues.is_empty() => {
+						let issues: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+						error.context(issues.join(", "))
+					}
+					_ => error,
+				}
+			})
+			.with_context(|| format!("Failed loading component template for: {}", template))
+	}
+
+	/// Reads and loads the components of `template` without making an entity of them, so broken
+	/// templates can be found before a game needs them.
+	pub fn validate_template<IO: 'static + EngineIO>(
+		&self,
+		io: &mut IO,
+		template: &str,
+	) -> anyhow::Result<()> {
+		let source = self.read_template_file(io, template)?;
+		self.parse_template(template, &source)?;
+		Ok(())
+	}
+
+	/// Type checks a template's components against the registered components.
+	pub fn check_template(&self, source: &[u8]) -> anyhow::Result<Vec<TypeIssue>> {
+		let data: ron::Value = self.schemas.read(ENTITY_TEMPLATES, source)?;
+		Ok(check_components(&data))
+	}
+}
+
+fn component_value(component: &(dyn ComponentAutoLoadable + 'static)) -> anyhow::Result<String> {
+	Ok(ron::ser::to_string(&component)?)
 }
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+pub const GAME_RULES: &str = "game_rules";
+
+fn serde_percent() -> u32 {
+	100
+}
+
+/// How long things take, as percentages of the base turn costs so they scale the same on every
+/// machine.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSpeed {
+	pub name: String,
+	#[serde(default = "serde_percent")]
+	pub research: u32,
+	#[serde(default = "serde_percent")]
+	pub production: u32,
+	#[serde(default = "serde_percent")]
+	pub growth: u32,
+}
+
+impl Default for GameSpeed {
+	fn default() -> Self {
+		GameSpeed {
+			name: "standard".to_owned(),
+			research: 100,
+			production: 100,
+			growth: 100,
+		}
+	}
+}
+
+/// How hard the other players push, the bonuses are percentages on top of the AI's yields.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Difficulty {
+	pub name: String,
+	#[serde(default)]
+	pub ai_yield_bonus: u32,
+	#[serde(default)]
+	pub ai_combat_bonus: u32,
+	/// From 0 where barbarians only defend their camps to 100 where they raid anything in reach.
+	/// There are no barbarians yet, kept for when there are.
+	#[serde(default)]
+	pub barbarian_aggression: u32,
+}
+
+impl Default for Difficulty {
+	fn default() -> Self {
+		Difficulty {
+			name: "normal".to_owned(),
+			ai_yield_bonus: 0,
+			ai_combat_bonus: 0,
+			barbarian_aggression: 50,
+		}
+	}
+}
+
+/// Every speed and difficulty a game can be set up with, from `civ/rules.ron`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RuleChoices {
+	pub speeds: Vec<GameSpeed>,
+	pub difficulties: Vec<Difficulty>,
+}
+
+impl RuleChoices {
+	pub fn select(&self, speed: &str, difficulty: &str) -> anyhow::Result<GameRules> {
+		let speed = self
+			.speeds
+			.iter()
+			.find(|s| s.name == speed)
+			.ok_or_else(|| {
+				let names: Vec<&str> = self.speeds.iter().map(|s| s.name.as_str()).collect();
+				anyhow::anyhow!("no game speed `{}`, there is: {}", speed, names.join(", "))
+			})?;
+		let difficulty = self
+			.difficulties
+			.iter()
+			.find(|d| d.name == difficulty)
+			.ok_or_else(|| {
+				let names: Vec<&str> = self.difficulties.iter().map(|d| d.name.as_str()).collect();
+				anyhow::anyhow!(
+					"no difficulty `{}`, there is: {}",
+					difficulty,
+					names.join(", ")
+				)
+			})?;
+		Ok(GameRules {
+			speed: speed.clone(),
+			difficulty: difficulty.clone(),
+		})
+	}
+}
+
+/// The speed and difficulty picked when the game was set up, what the civ systems scale their
+/// costs and bonuses by instead of using constants of their own.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRules {
+	pub speed: GameSpeed,
+	pub difficulty: Difficulty,
+}
+
+impl GameRules {
+	pub fn research_cost(&self, base: u32) -> u32 {
+		scale_cost(base, self.speed.research)
+	}
+
+	pub fn production_cost(&self, base: u32) -> u32 {
+		scale_cost(base, self.speed.production)
+	}
+
+	pub fn growth_cost(&self, base: u32) -> u32 {
+		scale_cost(base, self.speed.growth)
+	}
+
+	/// What an AI player gets out of a tile or building that yields `base`.
+	pub fn ai_yield(&self, base: u32) -> u32 {
+		percent_of(base, 100 + self.difficulty.ai_yield_bonus)
+	}
+
+	/// An AI unit's `strength` in combat.
+	pub fn ai_strength(&self, strength: u32) -> u32 {
+		percent_of(strength, 100 + self.difficulty.ai_combat_bonus)
+	}
+}
+
+/// Rounded to the nearest, anything that cost something still costs at least a turn's worth.
+fn scale_cost(base: u32, percent: u32) -> u32 {
+	if base == 0 {
+		0
+	} else {
+		percent_of(base, percent).max(1)
+	}
+}
+
+fn percent_of(value: u32, percent: u32) -> u32 {
+	let scaled = (u64::from(value) * u64::from(percent) + 50) / 100;
+	scaled.min(u64::from(u32::MAX)) as u32
+}
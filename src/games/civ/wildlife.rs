@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::fixed::Fixed;
+use crate::core::map::ambient::MapAmbient;
+use crate::core::map::coord::Coord;
+use crate::core::map::tile::TileIdx;
+use crate::core::map::tile_map::TileMap;
+use crate::games::civ::danger::ThreatRule;
+
+pub const WILDLIFE: &str = "wildlife";
+
+fn serde_every_ticks() -> u32 {
+	50
+}
+
+fn serde_despawn_distance() -> u16 {
+	8
+}
+
+/// Neutral animals or resources that come and go on their own, from `civ/wildlife.ron`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WildlifeRule {
+	/// The entity template spawned.
+	pub template: String,
+	/// The map ambients it lives on, such as `"planet"`, so caves and space get their own.
+	pub ambients: Vec<String>,
+	/// The tile types it spawns on.
+	pub on: Vec<String>,
+	/// How many there can be on a map per tile it spawns on, `0.05` being one per twenty tiles.
+	pub density: Fixed,
+	/// Simulation ticks between spawning another on each map.
+	#[serde(default = "serde_every_ticks")]
+	pub every_ticks: u32,
+	/// Once this many tiles from every unit that isn't wildlife it leaves the map, and it only
+	/// spawns within this of one.
+	#[serde(default = "serde_despawn_distance")]
+	pub despawn_distance: u16,
+	/// Makes it a danger to the units around it, it is harmless without.
+	#[serde(default)]
+	pub threat: Option<ThreatRule>,
+}
+
+/// The template a wildlife entity was spawned from, which also tells it apart from the units
+/// watching for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Wildlife(pub String);
+
+/// A wildlife rule with its tile types looked up, deciding where and when it spawns.
+///
+/// The decisions only depend on the map, the tick and where the units are, so every machine in a
+/// game or replay makes the same ones.
+#[derive(Clone, Debug)]
+pub struct WildlifeSpawner {
+	pub rule: WildlifeRule,
+	on: Vec<TileIdx>,
+}
+
+impl WildlifeSpawner {
+	pub fn new(rule: WildlifeRule, on: Vec<TileIdx>) -> WildlifeSpawner {
+		WildlifeSpawner { rule, on }
+	}
+
+	pub fn is_due(&self, tick: u64) -> bool {
+		tick % u64::from(self.rule.every_ticks.max(1)) == 0
+	}
+
+	pub fn lives_on(&self, ambient: Option<&MapAmbient>) -> bool {
+		ambient.map_or(false, |ambient| self.rule.ambients.contains(&ambient.name))
+	}
+
+	/// How many of them `tile_map` has room for.
+	pub fn cap(&self, tile_map: &TileMap) -> usize {
+		let matching = tile_map
+			.tiles
+			.iter()
+			.filter(|tile| self.on.contains(&tile.id))
+			.count();
+		self.rule.density.mul_floor(matching as i64).max(0) as usize
+	}
+
+	/// Whether one at `coord` is too far from all of `watchers` to stay.
+	pub fn is_unwatched(&self, tile_map: &TileMap, coord: Coord, watchers: &[Coord]) -> bool {
+		watchers
+			.iter()
+			.all(|&watcher| tile_map.distance(coord, watcher) > self.rule.despawn_distance)
+	}
+
+	/// Where another spawns at `tick` when `tile_map` has `existing` of them, an empty tile of
+	/// the right type near one of `watchers`, or `None` when there is no room.
+	pub fn spawn_coord(
+		&self,
+		tile_map: &TileMap,
+		existing: usize,
+		watchers: &[Coord],
+		tick: u64,
+	) -> Option<Coord> {
+		if existing >= self.cap(tile_map) {
+			return None;
+		}
+		let seed = tile_map.seed ^ tick.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+		let row_length = tile_map.width as usize + 1;
+		tile_map
+			.tiles
+			.iter()
+			.enumerate()
+			.filter(|(_idx, tile)| self.on.contains(&tile.id) && tile.entities.is_empty())
+			.map(|(idx, _tile)| {
+				Coord::new_axial((idx % row_length) as u8, (idx / row_length) as u8)
+			})
+			.filter(|&coord| !self.is_unwatched(tile_map, coord, watchers))
+			// The lowest hash is as good as a random pick, and the same one every time
+			.min_by_key(|coord| coord.hash_with_seed(seed))
+	}
+}
+
+#[cfg(test)]
+mod wildlife_tests {
+	use super::*;
+	use crate::core::map::generator::test_maps::{filled_map, tile_ids};
+	use crate::core::map::tile::Tile;
+
+	fn spawner(density: Fixed) -> (WildlifeSpawner, TileMap) {
+		let tiles = tile_ids(2);
+		let (grass, sand) = (tiles[0], tiles[1]);
+		// Columns of grass with a column of sand every third, 9 to a row
+		let mut tile_map = filled_map(8, 8, false, |idx| match idx % 9 % 3 {
+			2 => Tile::new(sand),
+			_ => Tile::new(grass),
+		});
+		tile_map.seed = 7;
+		let rule = WildlifeRule {
+			template: "deer".to_owned(),
+			ambients: vec!["planet".to_owned()],
+			on: vec!["grass".to_owned()],
+			density,
+			every_ticks: 10,
+			despawn_distance: 3,
+			threat: None,
+		};
+		(WildlifeSpawner::new(rule, vec![grass]), tile_map)
+	}
+
+	#[test]
+	fn only_due_every_so_many_ticks() {
+		let (spawner, _tile_map) = spawner(Fixed::ONE);
+		assert!(spawner.is_due(0));
+		assert!(!spawner.is_due(5));
+		assert!(spawner.is_due(30));
+	}
+
+	#[test]
+	fn cap_scales_with_matching_tiles() {
+		// Two of every three columns of a 9x9 map are grass
+		let (spawner, tile_map) = spawner(Fixed::from_percent(10));
+		assert_eq!(spawner.cap(&tile_map), 5);
+		assert_eq!(
+			spawner.spawn_coord(&tile_map, 5, &[Coord::new_axial(4, 4)], 0),
+			None
+		);
+	}
+
+	#[test]
+	fn spawns_on_empty_matching_tiles_near_watchers() {
+		let (spawner, tile_map) = spawner(Fixed::ONE);
+		let watcher = Coord::new_axial(4, 4);
+		for tick in 0..50 {
+			let coord = spawner.spawn_coord(&tile_map, 0, &[watcher], tick).unwrap();
+			assert!(spawner.on.contains(&tile_map.get_tile(coord).unwrap().id));
+			assert!(tile_map.distance(coord, watcher) <= 3);
+			assert_eq!(
+				spawner.spawn_coord(&tile_map, 0, &[watcher], tick),
+				Some(coord)
+			);
+		}
+		assert_eq!(spawner.spawn_coord(&tile_map, 0, &[], 0), None);
+	}
+
+	#[test]
+	fn unwatched_once_past_the_distance() {
+		let (spawner, tile_map) = spawner(Fixed::ONE);
+		let watchers = [Coord::new_axial(0, 0), Coord::new_axial(8, 8)];
+		assert!(!spawner.is_unwatched(&tile_map, Coord::new_axial(3, 0), &watchers));
+		assert!(!spawner.is_unwatched(&tile_map, Coord::new_axial(8, 5), &watchers));
+		assert!(spawner.is_unwatched(&tile_map, Coord::new_axial(4, 4), &watchers));
+		assert!(spawner.is_unwatched(&tile_map, Coord::new_axial(4, 4), &[]));
+	}
+}
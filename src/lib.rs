@@ -1,11 +1,18 @@
+// So `component_auto_loadable!` can be used on the components defined in here too
+extern crate self as over_simple_game_1;
+
 #[macro_use]
 pub mod core;
+#[cfg_attr(feature = "deterministic", deny(clippy::float_arithmetic))]
 pub mod games;
 
 pub mod prelude {
 	pub use crate::core::component::ComponentAutoLoadable;
 	pub use crate::core::engine::io::EngineIO;
 	pub use crate::core::engine::Engine;
-	pub use crate::core::map::coord::Coord;
-	pub use crate::core::map::tile::{Tile, TileIdx, TileType};
+	pub use crate::core::map::ambient::{MapAmbient, MapAmbientIdx, ParallaxLayer, Starfield};
+	pub use crate::core::map::coord::{Coord, Coord32, HexOrientation};
+	pub use crate::core::map::decoration::{DecorationIdx, DecorationType};
+	pub use crate::core::map::tile::{Tile, TileEntities, TileIdx, TileType};
+	pub use crate::core::map::tile_map::TileMap;
 }
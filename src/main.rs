@@ -1,24 +1,99 @@
 mod game;
 
 use anyhow::Context as AnyContext;
+use game::args::Args;
 use log::*;
 use over_simple_game_1::core::map::coord::*;
 use std::collections::HashSet;
 use std::path::Path;
+use structopt::StructOpt;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::prelude::*;
 
 fn main() -> anyhow::Result<()> {
-	setup_logging("./log4rs.yaml")?;
+	// First so a panic anywhere after this still gets a crash report
+	game::crash::install_panic_hook();
 
-	let mut game = game::Game::new().context("Game init failed")?;
+	let args = Args::from_args();
+	let user_data = game::user_data::resolve_user_data_dir(args.data_dir.clone())?;
+	setup_logging(user_data.join("log4rs.yaml"), &user_data.join("logs"))?;
+	info!("User data directory: {}", user_data.display());
+	let _trace_guard = setup_tracing(args.trace_chrome.as_deref())?;
 
-	game.setup().context("Game setup failed")?;
+	if let Some(out) = &args.pack_assets {
+		return game::pack::pack_assets(Path::new("./resources"), Path::new(out))
+			.context("Packing the assets failed");
+	}
+	if let Some(address) = &args.connect {
+		anyhow::bail!(
+			"Unable to join the game at `{}`, there is no multiplayer yet",
+			address
+		);
+	}
+	if args.headless {
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			game::headless::run_headless(&args, user_data.clone())
+		}));
+		return match result {
+			Ok(result) => result.context("Headless run failed"),
+			Err(payload) => report_crash(None, &user_data, payload),
+		};
+	}
 
-	game.run().context("Game run failed")?;
+	let mut game = None;
+	let result =
+		std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| -> anyhow::Result<()> {
+			let game =
+				game.insert(game::Game::new(user_data.clone(), &args).context("Game init failed")?);
 
-	Ok(())
+			game.setup(&args).context("Game setup failed")?;
+
+			game.run().context("Game run failed")?;
+
+			Ok(())
+		}));
+
+	match result {
+		Ok(result) => result,
+		Err(payload) => report_crash(game.as_mut(), &user_data, payload),
+	}
+}
+
+/// Writes a crash report for a caught panic and then carries on unwinding. The report has the
+/// game's state when it got far enough to have a game, else only the panic.
+fn report_crash(
+	game: Option<&mut game::Game>,
+	user_data: &Path,
+	payload: Box<dyn std::any::Any + Send>,
+) -> ! {
+	let report = game::crash::take_panic_report()
+		.unwrap_or_else(|| "panicked without a report from the panic hook".to_owned());
+	let written = match game {
+		Some(game) => game.write_crash_report(&report),
+		None => game::crash::write_panic_report(user_data, &report),
+	};
+	match written {
+		Ok(path) => {
+			error!(
+				"The game crashed, a crash report was written to: {}",
+				path.display()
+			);
+			eprintln!(
+				"\nThe game crashed, please include this folder when reporting the bug:\n{}\n",
+				path.display()
+			);
+		}
+		Err(e) => error!(
+			"The game crashed and writing the crash report failed: {:?}",
+			e
+		),
+	}
+	std::panic::resume_unwind(payload)
 }
 
-fn setup_logging<P: AsRef<Path>>(config_path: P) -> anyhow::Result<()> {
+/// Uses the logger configuration at `config_path`, writing a default one logging to the console
+/// and to `log_dir` if it is missing.
+fn setup_logging<P: AsRef<Path>>(config_path: P, log_dir: &Path) -> anyhow::Result<()> {
 	let config_path = config_path.as_ref();
 	let init = log4rs::init_file(config_path, Default::default());
 	match init {
@@ -39,12 +114,13 @@ fn setup_logging<P: AsRef<Path>>(config_path: P) -> anyhow::Result<()> {
 					)
 				})?;
 			}
-			std::fs::write(config_path, DEFAULT_LOGGING_YAML).with_context(|| {
-				format!(
-					"failed writing default logger configuration file at: {}",
-					config_path.display()
-				)
-			})?;
+			std::fs::write(config_path, default_logging_yaml(&log_dir.join("game.log")))
+				.with_context(|| {
+					format!(
+						"failed writing default logger configuration file at: {}",
+						config_path.display()
+					)
+				})?;
 			log4rs::init_file(config_path, Default::default()).with_context(|| {
 				format!(
 					"failed parsing or loading logger configuration file at: {}",
@@ -62,16 +138,50 @@ fn setup_logging<P: AsRef<Path>>(config_path: P) -> anyhow::Result<()> {
 	Ok(())
 }
 
-const DEFAULT_LOGGING_YAML: &str = r#"
+/// Writes a chrome trace (viewable at `chrome://tracing`) to `path` when given one.
+fn setup_tracing(path: Option<&str>) -> anyhow::Result<Option<FlushGuard>> {
+	let path = match path {
+		None => return Ok(None),
+		Some(path) => path.to_owned(),
+	};
+	let (chrome_layer, guard) = ChromeLayerBuilder::new().file(path.clone()).build();
+	// Not using `init` as that would also try to take over the `log` facade from log4rs
+	tracing::subscriber::set_global_default(tracing_subscriber::registry().with(chrome_layer))
+		.context("failed to install the chrome trace subscriber")?;
+	info!("Writing chrome trace to: {}", path);
+	Ok(Some(guard))
+}
+
+fn default_logging_yaml(log_path: &Path) -> String {
+	// Single quoted so Windows path separators aren't read as escapes
+	let log_path = log_path.display().to_string().replace('\'', "''");
+	format!(
+		r#"
 refresh_rate: 30 seconds
 appenders:
   stdout:
     kind: console
     encoder:
-      pattern: "{d} [{t}:{I}:{T}] {h({l})} {M}: {m}{n}"
+      pattern: "{{d}} [{{t}}:{{I}}:{{T}}] {{h({{l}})}} {{M}}: {{m}}{{n}}"
+  file:
+    kind: rolling_file
+    path: '{}'
+    append: false
+    encoder:
+      pattern: "{{d}} [{{t}}:{{I}}:{{T}}] {{l}} {{M}}: {{m}}{{n}}"
+    policy:
+      trigger:
+        kind: size
+        limit: 10 mb
+      roller:
+        kind: delete
 
 root:
   level: trace
   appenders:
     - stdout
-"#;
+    - file
+"#,
+		log_path
+	)
+}